@@ -0,0 +1,69 @@
+use crate::{Error, Result, config::ApprovalConfig};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+/// A tool call waiting for a human decision
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    pub session_id: String,
+    pub tool_name: String,
+    pub arguments: HashMap<String, Value>,
+}
+
+/// Decides whether tool calls that require approval may run
+#[async_trait]
+pub trait ApprovalHandler: Send + Sync {
+    /// Returns `Ok(true)` to run the tool and `Ok(false)` to deny it
+    async fn approve(&self, request: &ApprovalRequest) -> Result<bool>;
+}
+
+pub fn create_approval_handler(config: &ApprovalConfig) -> Option<Arc<dyn ApprovalHandler>> {
+    config.webhook_url.as_ref().map(|url| {
+        Arc::new(WebhookApprovalHandler::new(
+            url.clone(),
+            Duration::from_secs(config.timeout_secs),
+        )) as Arc<dyn ApprovalHandler>
+    })
+}
+
+/// Posts each `ApprovalRequest` as JSON to a webhook and waits for `{"approved": bool}`
+pub struct WebhookApprovalHandler {
+    client: reqwest::Client,
+    url: String,
+    timeout: Duration,
+}
+
+#[derive(Deserialize)]
+struct ApprovalResponse {
+    approved: bool,
+}
+
+impl WebhookApprovalHandler {
+    pub fn new(url: String, timeout: Duration) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+            timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for WebhookApprovalHandler {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<bool> {
+        let response: ApprovalResponse = self
+            .client
+            .post(&self.url)
+            .timeout(self.timeout)
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| Error::tool(format!("Approval request failed: {e}")))?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.approved)
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{
+    llm::ContextFallback,
+    mcp::{McpContent, McpToolCallRequest, McpToolCallResponse},
+};
+use serde::{Deserialize, Serialize};
+
+/// Attribution of content the agent drew on while answering
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Citation {
+    /// Document path, resource URI, or `tool:<name>` for plain tool output
+    pub source_id: String,
+    /// Part of the source that was used, e.g. `chunk=3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span: Option<String>,
+    /// Tool that returned the content
+    pub tool: String,
+}
+
+/// Final output of a run together with the sources it was based on
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentReply {
+    /// Run that produced the reply, for its timeline
+    pub run_id: String,
+    pub output: String,
+    pub citations: Vec<Citation>,
+    /// How likely the output is to be right, from 0 to 1, when replies are scored
+    pub confidence: Option<f64>,
+    /// Moderation categories the input was blocked for; empty unless it was
+    pub blocked: Vec<String>,
+    /// How the last LLM call of the run was answered after exceeding the model's context
+    pub context_fallback: Option<ContextFallback>,
+}
+
+/// Derives citations from a tool result.
+///
+/// Resource items cite their URI, with any `#fragment` as the span; other successful
+/// results cite the tool itself. Failed calls contributed nothing and cite nothing.
+pub fn citations_from_tool_result(
+    call: &McpToolCallRequest,
+    response: &McpToolCallResponse,
+) -> Vec<Citation> {
+    if response.is_error {
+        return Vec::new();
+    }
+
+    let citations: Vec<Citation> = response
+        .content
+        .iter()
+        .filter_map(|item| match item {
+            McpContent::Resource { resource } => Some(&resource.uri),
+            _ => None,
+        })
+        .map(|uri| {
+            let (source_id, span) = match uri.rsplit_once('#') {
+                Some((source, fragment)) => (source.to_string(), Some(fragment.to_string())),
+                None => (uri.clone(), None),
+            };
+            Citation {
+                source_id,
+                span,
+                tool: call.name.clone(),
+            }
+        })
+        .collect();
+    if !citations.is_empty() {
+        return citations;
+    }
+
+    vec![Citation {
+        source_id: format!("tool:{}", call.name),
+        span: None,
+        tool: call.name.clone(),
+    }]
+}
@@ -0,0 +1,73 @@
+use crate::{Error, Result, config::ConfidenceConfig};
+use serde::Serialize;
+use std::time::Duration;
+
+/// How long the escalation webhook gets to accept an escalation
+const ESCALATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Asks the LLM to judge the reply at the end of the conversation
+pub const JUDGE_PROMPT: &str = "Rate how likely the last reply is to be correct and \
+    complete, given the conversation and the tool results it had. Reply with only a \
+    number between 0 and 1.";
+
+/// Reads a confidence score from the judge's reply: the first number in it, taken as a
+/// percentage when followed by `%` or above 1, and clamped to 0..=1
+pub fn parse_confidence(reply: &str) -> Option<f64> {
+    let start = reply.find(|c: char| c.is_ascii_digit())?;
+    let number: String = reply[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.')
+        .collect();
+    let mut score: f64 = number.trim_end_matches('.').parse().ok()?;
+    let percent = reply[start + number.len()..].trim_start().starts_with('%');
+    if percent || score > 1.0 {
+        score /= 100.0;
+    }
+    Some(score.clamp(0.0, 1.0))
+}
+
+/// A low-confidence answer handed to a human
+#[derive(Debug, Clone, Serialize)]
+pub struct Escalation {
+    pub session_id: String,
+    pub question: String,
+    /// The answer as the LLM gave it, before any fallback reply replaced it
+    pub answer: String,
+    pub confidence: f64,
+}
+
+/// Scores replies and decides what happens to the ones below the threshold
+pub(crate) struct ConfidenceScoring {
+    pub threshold: f64,
+    pub fallback_reply: Option<String>,
+    escalation: Option<(reqwest::Client, String)>,
+}
+
+impl ConfidenceScoring {
+    pub fn new(config: &ConfidenceConfig) -> Self {
+        Self {
+            threshold: config.threshold,
+            fallback_reply: config.fallback_reply.clone(),
+            escalation: config
+                .escalation_webhook
+                .clone()
+                .map(|url| (reqwest::Client::new(), url)),
+        }
+    }
+
+    /// Posts the escalation as JSON to the escalation webhook, if there is one
+    pub async fn escalate(&self, escalation: &Escalation) -> Result<()> {
+        let Some((client, url)) = &self.escalation else {
+            return Ok(());
+        };
+        client
+            .post(url)
+            .timeout(ESCALATION_TIMEOUT)
+            .json(escalation)
+            .send()
+            .await
+            .map_err(|e| Error::internal(format!("Escalation request failed: {e}")))?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::llm::{ChatMessage, Tokenizer, Tool, count_tokens};
+
+/// Drops the oldest conversation messages until `messages` and `tools` fit in `budget`
+/// tokens, returning how many were dropped. System messages and the run's input at
+/// `kept_from` with everything after it are kept, so the result may still exceed the
+/// budget. Tool results are dropped together with the reply that called them.
+pub fn trim_to_budget(
+    tokenizer: &dyn Tokenizer,
+    messages: &mut Vec<ChatMessage>,
+    tools: &[Tool],
+    budget: usize,
+    kept_from: usize,
+) -> usize {
+    let mut total = count_tokens(tokenizer, messages, tools);
+    if total <= budget {
+        return 0;
+    }
+
+    let kept_from = kept_from.min(messages.len());
+    let mut dropped = vec![false; messages.len()];
+    let mut index = 0;
+    while index < kept_from && total > budget {
+        if messages[index].role == "system" {
+            index += 1;
+            continue;
+        }
+        // A reply with tool calls goes with the results that follow it
+        let mut end = index + 1;
+        if messages[index].tool_calls.is_some() {
+            while end < kept_from && messages[end].role == "tool" {
+                end += 1;
+            }
+        }
+        for (message, dropped) in messages[index..end].iter().zip(&mut dropped[index..end]) {
+            total -= count_tokens(tokenizer, std::slice::from_ref(message), &[]);
+            *dropped = true;
+        }
+        index = end;
+    }
+
+    let count = dropped.iter().filter(|dropped| **dropped).count();
+    let mut dropped = dropped.into_iter();
+    messages.retain(|_| !dropped.next().unwrap_or(false));
+    count
+}
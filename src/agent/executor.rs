@@ -1,38 +1,163 @@
-use super::fsm::{AgentEvent, AgentState, AgentStateMachine};
+use super::{
+    approval::{ApprovalHandler, ApprovalRequest, create_approval_handler},
+    citations::{AgentReply, citations_from_tool_result},
+    confidence::{ConfidenceScoring, Escalation, JUDGE_PROMPT, parse_confidence},
+    context_window::trim_to_budget,
+    fanout::{decomposition_prompt, findings_prompt, parse_subquestions},
+    fsm::{AgentContext, AgentEvent, AgentState, AgentStateMachine},
+    hooks::{AgentHook, HookContext},
+    language::ResponseLanguage,
+    runs::{PausedRun, RunOutcome, RunSettings, ToolMode},
+    structured_output::{self, check_reply},
+    tool_arguments::{
+        apply_secret_arguments, apply_static_arguments, hide_injected_arguments, redact_secrets,
+        transform_arguments,
+    },
+    tool_context::{CONTEXT_ARGUMENT, build_tool_context},
+    tool_groups::merge_group_results,
+    tool_hints::{ToolLatencies, describe_with_hints},
+    tool_output::{render_tool_result, tool_images},
+};
 use crate::{
     Error, Result,
-    config::{LlmConfig, McpServerConfig},
-    history::{HistoryStorage, Message},
-    llm::{ChatMessage, Function, LlmClient, OpenAiClient, Tool},
+    cache::{Cache, cache_key, create_cache, get_json, set_json},
+    chaos::{Chaos, ChaosLlmClient, ChaosMcpClient},
+    config::{
+        AgentMode, ConfidenceConfig, Config, EmptyResponseConfig, FanoutConfig, LlmConfig,
+        McpServerConfig, ModelRoutingConfig, SamplingConfig, StructuredOutputConfig,
+        ToolGroupConfig, ToolOutputFormat, ToolsConfig,
+    },
+    history::{HistoryStorage, Message, ToolCallRecord},
+    llm::{
+        CachedLlmClient, ChatMessage, ContentPart, Function, LlmClient, MessageContent,
+        PacingPolicy, ResponseFormat, Tool, ToolChoice, create_llm_client, tokenizer_for,
+    },
     mcp::{
         McpClient, McpClientCapabilities, McpInitializeRequest, McpRootsCapability,
-        create_mcp_client,
+        McpToolCallResponse, create_mcp_client,
     },
+    moderation::{ModerationVerdict, Moderator},
+    tools::{NativeTool, NativeToolRegistry, ToolContext, builtin_tools, error_result},
 };
-use std::collections::HashMap;
+use futures::StreamExt;
+use std::{collections::HashMap, sync::Arc};
 use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
 pub struct Agent {
     llm_client: Box<dyn LlmClient>,
     mcp_clients: HashMap<String, Box<dyn McpClient>>,
     available_tools: Vec<Tool>,
     tool_to_client_map: HashMap<String, String>, // Maps tool_name -> client_name
+    tool_output_schemas: HashMap<String, serde_json::Value>, // Declared JSON output schemas
     discovered_prompts: Vec<String>,
     default_system_prompt: String,
     base_system_prompt: Option<String>,
+    hooks: Vec<Arc<dyn AgentHook>>,
+    tools_config: ToolsConfig,
+    native_tools: NativeToolRegistry,
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    /// LLM calls a run may make before it fails with `MaxTurnsExceeded`
+    max_turns: usize,
+    max_continuations: usize,
+    /// Prompt tokens a request may use before the oldest messages are dropped
+    max_context_tokens: Option<usize>,
+    /// Sampling parameters of replies, unless the request sets its own
+    sampling: SamplingConfig,
+    empty_response: EmptyResponseConfig,
+    structured_output: StructuredOutputConfig,
+    /// Models of tool-choosing and answering turns
+    routing: ModelRoutingConfig,
+    /// Pause between the LLM calls of a run
+    pacing: Option<PacingPolicy>,
+    retroactive_system_prompt: bool,
+    tool_cache: Option<ToolCache>,
+    /// Configured model name, used to count prompt tokens
+    model: String,
+    response_language: Option<ResponseLanguage>,
+    tool_latencies: ToolLatencies,
+    fanout: Option<FanoutConfig>,
+    confidence: Option<ConfidenceScoring>,
+    moderator: Option<Moderator>,
+    partial_reply_interval: Option<std::time::Duration>,
+}
+
+/// Per-request settings overriding the agent's configuration
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptions {
+    /// Language to reply in, instead of `agent.response_language`
+    pub response_language: Option<String>,
+    /// Whether the agent runs tools itself or hands calls back to the caller
+    pub tool_mode: ToolMode,
+    /// Tools the caller runs itself, offered next to the agent's own. Calls to them
+    /// pause the run in either mode.
+    pub tools: Vec<Tool>,
+    /// Model to answer with, instead of `llm.model`
+    pub model: Option<String>,
+    /// Sampling parameters of the reply, over those of `llm`
+    pub sampling: SamplingConfig,
+    /// Offer none of the agent's own tools; the caller's are still offered
+    pub without_tools: bool,
+    /// JSON the final reply must be, checked before it is returned
+    pub response_format: Option<ResponseFormat>,
+    /// Whether and which tools the first LLM call must call
+    pub tool_choice: Option<ToolChoice>,
+    /// Images sent along with the input. Only this run sees them; the history keeps the
+    /// text.
+    pub images: Vec<ContentPart>,
+}
+
+/// How `run_fsm_loop` stopped
+enum LoopEnd {
+    Reply(String),
+    /// Waiting for the caller's tool results; the others are in place
+    Paused(Vec<Option<McpToolCallResponse>>),
 }
 
+/// What the sub-runs of a fanned-out request found
+#[derive(Default)]
+struct Findings {
+    /// Hands their answers to the LLM to combine
+    prompt: String,
+    tool_calls: Vec<ToolCallRecord>,
+    citations: Vec<super::Citation>,
+}
+
+/// The LLM call a reply came from, for the helpers that ask again about the reply
+struct ReplyCall<'a> {
+    ctx: &'a HookContext,
+    model: &'a str,
+    sampling: &'a SamplingConfig,
+    /// Where the run's input is in the call's messages
+    input_index: usize,
+    /// Tools the run offers, which a retry of the call offers again
+    tools: &'a [Tool],
+    response_format: Option<&'a ResponseFormat>,
+    tool_choice: Option<&'a ToolChoice>,
+}
+
+/// Cached results of the tools named in `cache.tools`
+struct ToolCache {
+    cache: Arc<dyn Cache>,
+    tools: Vec<String>,
+    ttl: Option<std::time::Duration>,
+}
+
+/// Sent after a reply that was cut off by the token limit
+const CONTINUATION_PROMPT: &str = "Your previous reply was cut off. Continue exactly where it stopped, without repeating anything.";
+
 impl Agent {
     pub async fn new(llm_config: LlmConfig, mcp_configs: Vec<McpServerConfig>) -> Result<Self> {
         info!("Initializing agent with {} MCP servers", mcp_configs.len());
 
         // Initialize LLM client
-        let llm_client = Box::new(OpenAiClient::new(llm_config.clone()));
+        let llm_client = create_llm_client(llm_config.clone())?;
 
         // Initialize MCP clients
         let mut mcp_clients = HashMap::new();
         let mut available_tools = Vec::new();
         let mut tool_to_client_map = HashMap::new();
+        let mut tool_output_schemas = HashMap::new();
         let mut discovered_prompts = Vec::new();
 
         for config in mcp_configs {
@@ -53,6 +178,10 @@ impl Agent {
                         // Map tool name to client name
                         tool_to_client_map.insert(tool_name.clone(), name.clone());
 
+                        if let Some(schema) = tool.output_schema {
+                            tool_output_schemas.insert(tool_name.clone(), schema);
+                        }
+
                         let llm_tool = Tool {
                             tool_type: "function".to_string(),
                             function: Function {
@@ -93,12 +222,269 @@ impl Agent {
             mcp_clients,
             available_tools,
             tool_to_client_map,
+            tool_output_schemas,
             discovered_prompts,
             default_system_prompt,
             base_system_prompt: llm_config.system_prompt,
+            max_turns: crate::config::default_max_turns(),
+            max_continuations: llm_config.max_continuations,
+            max_context_tokens: llm_config.max_context_tokens,
+            sampling: llm_config.sampling,
+            empty_response: llm_config.empty_response,
+            structured_output: llm_config.structured_output,
+            routing: llm_config.routing,
+            pacing: PacingPolicy::new(&llm_config.pacing),
+            retroactive_system_prompt: llm_config.retroactive_system_prompt,
+            tool_cache: None,
+            model: llm_config.model,
+            response_language: None,
+            tool_latencies: ToolLatencies::new(),
+            fanout: None,
+            confidence: None,
+            moderator: None,
+            partial_reply_interval: None,
+            hooks: Vec::new(),
+            tools_config: ToolsConfig::default(),
+            native_tools: NativeToolRegistry::new(),
+            approval_handler: None,
         })
     }
 
+    /// Creates an agent from the full application configuration
+    pub async fn from_config(config: &Config) -> Result<Self> {
+        let mut agent = Self::new(config.llm.clone(), config.mcp_servers.clone())
+            .await?
+            .with_tools_config(config.tools.clone());
+        if let Some(chaos) = Chaos::from_config(&config.chaos) {
+            agent = agent.with_chaos(chaos);
+        }
+        if config.features.approvals {
+            agent.approval_handler = create_approval_handler(&config.tools.approval);
+        }
+        if let Some(language) = &config.agent.response_language {
+            agent = agent.with_response_language(language);
+        }
+        if config.agent.mode == AgentMode::Fanout {
+            agent = agent.with_fanout(config.agent.fanout.clone());
+        }
+        if config.agent.confidence.enabled {
+            agent = agent.with_confidence(&config.agent.confidence);
+        }
+        if let Some(moderator) = Moderator::from_config(&config.moderation)? {
+            agent = agent.with_moderator(moderator);
+        }
+        agent = agent.with_max_turns(config.agent.max_turns);
+        if config.agent.partial_replies.enabled && config.features.streaming {
+            agent = agent.with_partial_replies(std::time::Duration::from_millis(
+                config.agent.partial_replies.interval_ms,
+            ));
+        }
+        if let Some(cache) = create_cache(
+            &config.cache,
+            &config.cache_database_path(),
+            &config.server.database,
+        )
+        .await?
+        {
+            if config.cache.llm {
+                agent.llm_client = Box::new(CachedLlmClient::new(
+                    agent.llm_client,
+                    cache.clone(),
+                    config.llm.model.clone(),
+                    config.cache.ttl(),
+                ));
+            }
+            agent = agent.with_tool_cache(cache, config.cache.tools.clone(), config.cache.ttl());
+        }
+        for tool in builtin_tools(&config.tools) {
+            agent.register_native_tool(tool);
+        }
+        info!("Registered {} native tools", agent.native_tools.len());
+        Ok(agent)
+    }
+
+    /// Routes LLM and MCP tool calls through `chaos`, which delays and fails some of them
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.llm_client = Box::new(ChaosLlmClient::new(self.llm_client, chaos.clone()));
+        self.mcp_clients = std::mem::take(&mut self.mcp_clients)
+            .into_iter()
+            .map(|(name, client)| {
+                let client: Box<dyn McpClient> =
+                    Box::new(ChaosMcpClient::new(client, chaos.clone()));
+                (name, client)
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_tools_config(mut self, tools_config: ToolsConfig) -> Self {
+        self.tools_config = tools_config;
+        self
+    }
+
+    /// Sets the handler consulted before running tools that require approval. Without one,
+    /// such calls are denied.
+    pub fn with_approval_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval_handler = Some(handler);
+        self
+    }
+
+    /// Model the LLM client is configured with
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Sets the model name reported in previews and used to pick a tokenizer
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Makes replies use `language`, re-asking once when one comes back in another
+    pub fn with_response_language(mut self, language: &str) -> Self {
+        self.response_language = Some(ResponseLanguage::parse(language));
+        self
+    }
+
+    /// Splits requests into independent sub-questions answered by parallel sub-runs,
+    /// within `fanout`'s limits, and combines their answers into one reply
+    pub fn with_fanout(mut self, fanout: FanoutConfig) -> Self {
+        self.fanout = Some(fanout);
+        self
+    }
+
+    /// Scores each reply with an LLM judge, replacing or escalating the ones below
+    /// `confidence.threshold` as configured
+    pub fn with_confidence(mut self, confidence: &ConfidenceConfig) -> Self {
+        self.confidence = Some(ConfidenceScoring::new(confidence));
+        self
+    }
+
+    /// Checks user input before the run and final replies before they are returned,
+    /// answering with the moderator's `blocked_reply` instead of blocked text
+    pub fn with_moderator(mut self, moderator: Moderator) -> Self {
+        self.moderator = Some(moderator);
+        self
+    }
+
+    /// Streams replies from the LLM, saving the text received so far to the history
+    /// every `interval` until the reply is complete
+    pub fn with_partial_replies(mut self, interval: std::time::Duration) -> Self {
+        self.partial_reply_interval = Some(interval);
+        self
+    }
+
+    /// Sets how many LLM calls a run may make
+    pub fn with_max_turns(mut self, max_turns: usize) -> Self {
+        self.max_turns = max_turns;
+        self
+    }
+
+    /// Pauses between the LLM calls of each run
+    pub fn with_pacing(mut self, pacing: PacingPolicy) -> Self {
+        self.pacing = Some(pacing);
+        self
+    }
+
+    /// Sets how many continuations to request for replies cut off by the token limit
+    pub fn with_max_continuations(mut self, max_continuations: usize) -> Self {
+        self.max_continuations = max_continuations;
+        self
+    }
+
+    /// Sets how empty LLM replies are retried
+    pub fn with_empty_response(mut self, empty_response: EmptyResponseConfig) -> Self {
+        self.empty_response = empty_response;
+        self
+    }
+
+    /// Sets how replies requested as JSON are checked
+    pub fn with_structured_output(mut self, structured_output: StructuredOutputConfig) -> Self {
+        self.structured_output = structured_output;
+        self
+    }
+
+    /// Sets the models of tool-choosing and answering turns
+    pub fn with_model_routing(mut self, routing: ModelRoutingConfig) -> Self {
+        self.routing = routing;
+        self
+    }
+
+    /// Reuses results of `tools` for calls with the same arguments. Only successful
+    /// results are cached.
+    pub fn with_tool_cache(
+        mut self,
+        cache: Arc<dyn Cache>,
+        tools: Vec<String>,
+        ttl: Option<std::time::Duration>,
+    ) -> Self {
+        self.tool_cache = (!tools.is_empty()).then_some(ToolCache { cache, tools, ttl });
+        self
+    }
+
+    /// Drops the oldest messages of requests whose prompt would exceed `max_tokens`
+    pub fn with_max_context_tokens(mut self, max_tokens: Option<usize>) -> Self {
+        self.max_context_tokens = max_tokens;
+        self
+    }
+
+    /// Sets the sampling parameters of replies
+    pub fn with_sampling(mut self, sampling: SamplingConfig) -> Self {
+        self.sampling = sampling;
+        self
+    }
+
+    /// Sets whether system prompt changes apply to sessions that already started
+    pub fn with_retroactive_system_prompt(mut self, retroactive: bool) -> Self {
+        self.retroactive_system_prompt = retroactive;
+        self
+    }
+
+    /// The base system prompt of new runs, before discovered MCP prompts
+    pub fn system_prompt(&self) -> &str {
+        self.base_system_prompt
+            .as_deref()
+            .unwrap_or(&self.default_system_prompt)
+    }
+
+    /// Replaces the base system prompt of runs from now on
+    pub fn set_system_prompt(&mut self, prompt: String) {
+        self.base_system_prompt = Some(prompt);
+    }
+
+    /// Registers a native tool, advertising it to the LLM. Native tools take precedence
+    /// over MCP tools with the same name.
+    pub fn register_native_tool(&mut self, tool: Arc<dyn NativeTool>) {
+        let definition = tool.definition();
+        if let Some(client_name) = self.tool_to_client_map.get(&definition.name) {
+            warn!(
+                "Native tool '{}' shadows the tool of the same name from MCP client '{}'",
+                definition.name, client_name
+            );
+        }
+
+        self.available_tools
+            .retain(|t| t.function.name != definition.name);
+        self.available_tools.push(Tool {
+            tool_type: "function".to_string(),
+            function: Function {
+                name: definition.name.clone(),
+                description: definition.description,
+                parameters: definition.input_schema,
+            },
+        });
+        if let Some(schema) = definition.output_schema {
+            self.tool_output_schemas
+                .insert(definition.name.clone(), schema);
+        }
+        self.native_tools.register(tool);
+    }
+
+    /// Registers a lifecycle hook. Hooks are invoked in registration order.
+    pub fn add_hook(&mut self, hook: Arc<dyn AgentHook>) {
+        self.hooks.push(hook);
+    }
+
     async fn initialize_mcp_client(
         config: McpServerConfig,
     ) -> Result<(
@@ -161,17 +547,16 @@ impl Agent {
                                 Ok(prompt_response) => {
                                     // Look for assistant messages in the prompt
                                     for message in prompt_response.messages {
-                                        if message.role == "assistant" {
-                                            if let crate::mcp::McpContent::Text { text } =
+                                        if message.role == "assistant"
+                                            && let crate::mcp::McpContent::Text { text } =
                                                 message.content
-                                            {
-                                                prompts.push(text);
-                                                info!(
-                                                    "Discovered system prompt from MCP client '{}'",
-                                                    config.name
-                                                );
-                                                break;
-                                            }
+                                        {
+                                            prompts.push(text);
+                                            info!(
+                                                "Discovered system prompt from MCP client '{}'",
+                                                config.name
+                                            );
+                                            break;
                                         }
                                     }
                                 }
@@ -203,148 +588,926 @@ impl Agent {
         input: &str,
         history: &HistoryStorage,
     ) -> Result<String> {
+        Ok(self
+            .process_with_citations(session_id, input, history)
+            .await?
+            .output)
+    }
+
+    /// Like `process`, also returning the sources the answer drew on. They are saved
+    /// with the assistant message under the `citations` metadata key, next to the
+    /// turn's `tool_calls` and the raw `turn` messages used to replay it.
+    pub async fn process_with_citations(
+        &mut self,
+        session_id: &str,
+        input: &str,
+        history: &HistoryStorage,
+    ) -> Result<AgentReply> {
+        match self
+            .process_with_options(session_id, input, history, ProcessOptions::default())
+            .await?
+        {
+            RunOutcome::Reply(reply) => Ok(reply),
+            RunOutcome::Paused(_) => Err(Error::internal(
+                "Run paused for tool calls without a caller to run them",
+            )),
+        }
+    }
+
+    /// Like `process_with_citations`, with per-request `options`. Runs pause instead of
+    /// replying when the LLM calls tools the caller runs itself.
+    pub async fn process_with_options(
+        &mut self,
+        session_id: &str,
+        input: &str,
+        history: &HistoryStorage,
+        options: ProcessOptions,
+    ) -> Result<RunOutcome> {
         info!("Processing request for session: {}", session_id);
 
-        // Generate final system prompt
-        let final_system_prompt = self.build_system_prompt();
+        // The caller's tools replace the agent's of the same name
+        let client_tools: Vec<String> = options
+            .tools
+            .iter()
+            .map(|tool| tool.function.name.clone())
+            .collect();
+        let mut tools = if options.without_tools {
+            Vec::new()
+        } else {
+            self.advertised_tools()
+        };
+        tools.retain(|tool| !client_tools.contains(&tool.function.name));
+        tools.extend(options.tools);
+        // Checked before the run starts, so a rejected request leaves no trace
+        if let Some(name) = options.tool_choice.as_ref().and_then(|c| c.function_name())
+            && !tools.iter().any(|tool| tool.function.name == name)
+        {
+            return Err(Error::ToolNotFound {
+                tool_name: name.to_string(),
+            });
+        }
+
+        let run_id = Uuid::new_v4().to_string();
+        let hook_ctx = HookContext::for_run(session_id, &run_id, 0);
+        for hook in &self.hooks {
+            // An aborted request still ends its run for the hooks following it
+            if let Err(e) = hook.on_request(&hook_ctx, input).await {
+                return self.abort_run(&hook_ctx, e).await;
+            }
+        }
+
+        // Blocked input never reaches the LLM or the history
+        if let Some(moderator) = self.moderator.as_ref().filter(|m| m.check_input)
+            && let ModerationVerdict::Block { categories } = moderator.check(input).await
+        {
+            warn!("🛑 Input blocked by moderation: {}", categories.join(", "));
+            let result = Ok(moderator.blocked_reply.clone());
+            for hook in &self.hooks {
+                hook.on_complete(&hook_ctx, &result).await;
+            }
+            return Ok(RunOutcome::Reply(AgentReply {
+                run_id,
+                output: moderator.blocked_reply.clone(),
+                citations: Vec::new(),
+                confidence: None,
+                blocked: categories,
+                context_fallback: None,
+            }));
+        }
 
         // Retrieve message history
-        let previous_messages = history.list(session_id).await?;
+        let previous_messages = match history.list(session_id).await {
+            Ok(messages) => messages,
+            Err(e) => return self.abort_run(&hook_ctx, e).await,
+        };
         debug!(
             "Retrieved {} previous messages for session",
             previous_messages.len()
         );
 
-        // Build initial messages
-        let mut messages = Vec::new();
+        let (mut messages, pin_prompt) = self.initial_messages(input, previous_messages);
+        if let Some(message) = messages.iter_mut().rev().find(|m| m.role == "user") {
+            message.content = std::mem::take(&mut message.content).with_images(options.images);
+        }
+        let settings = RunSettings {
+            language: options
+                .response_language
+                .as_deref()
+                .map(ResponseLanguage::parse)
+                .or_else(|| self.response_language.clone()),
+            tool_mode: options.tool_mode,
+            client_tools,
+            // Replies are only moderated once complete, so they can't be saved before
+            partial_replies: self.partial_reply_interval.is_some()
+                && !self.moderator.as_ref().is_some_and(|m| m.check_output),
+            model: options.model,
+            sampling: options.sampling.or(&self.sampling),
+            response_format: options.response_format,
+            tool_choice: options.tool_choice,
+        };
+        if let Some(language) = &settings.language {
+            add_language_directive(&mut messages, language);
+        }
 
-        // Add system prompt if available
-        if !final_system_prompt.is_empty() {
-            messages.push(ChatMessage {
-                role: "system".to_string(),
-                content: final_system_prompt,
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            });
+        if let Some(prompt) = pin_prompt {
+            let prompt = Message::system(session_id.to_string(), prompt)
+                .with_metadata(serde_json::json!({ SYSTEM_PROMPT_FLAG: true }));
+            if let Err(e) = history.save(prompt).await {
+                return self.abort_run(&hook_ctx, e).await;
+            }
         }
 
-        // Add previous messages
-        for msg in previous_messages {
-            messages.push(ChatMessage {
-                role: msg.role,
-                content: msg.content,
-                tool_calls: None,
-                tool_call_id: None,
-                name: None,
-            });
+        // Save user message to history
+        let user_message = Message::user(session_id.to_string(), input.to_string());
+        if let Err(e) = history.save(user_message).await {
+            return self.abort_run(&hook_ctx, e).await;
         }
 
-        // Add current user input
+        let turn_start = messages.len();
+
+        // Sub-runs can't pause, so requests with caller-run tools are answered in one run,
+        // and they answer on their own, so neither are requests choosing the tools
+        let findings = match &self.fanout {
+            Some(fanout)
+                if settings.tool_mode == ToolMode::Auto
+                    && settings.client_tools.is_empty()
+                    && settings.tool_choice.is_none() =>
+            {
+                let model = settings.model.as_deref();
+                self.fan_out(&hook_ctx, history, &messages, &tools, fanout, model)
+                    .await
+            }
+            _ => None,
+        };
+
+        // Create FSM with initial state
+        let mut fsm = match findings {
+            // The sub-runs did the research, so the LLM only combines their answers
+            Some(findings) => {
+                messages.push(ChatMessage {
+                    role: "system".to_string(),
+                    content: findings.prompt.into(),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                });
+                let mut fsm = AgentStateMachine::new(messages, Vec::new(), HashMap::new());
+                fsm.context.tool_calls = findings.tool_calls;
+                fsm.context.add_citations(findings.citations);
+                fsm
+            }
+            None => AgentStateMachine::new(
+                messages,
+                tools,
+                // Note: We can't move mcp_clients here due to borrowing rules
+                // In a real implementation, you'd use Arc<Mutex<>> or similar
+                HashMap::new(), // Placeholder for now
+            ),
+        };
+        fsm.context.run_id = run_id;
+        fsm.context.max_turns = self.max_turns;
+
+        self.drive_run(session_id, history, fsm, turn_start, settings)
+            .await
+    }
+
+    /// Ends a run that failed before it got going, so the hooks that saw it start see it end
+    async fn abort_run<T>(&self, ctx: &HookContext, error: Error) -> Result<T> {
+        let result = Err(error);
+        for hook in &self.hooks {
+            hook.on_complete(ctx, &result).await;
+        }
+        result.map(|_: String| unreachable!("the run was aborted"))
+    }
+
+    /// Splits the request in the last of `messages` into independent sub-questions and
+    /// answers each in its own run, at most `max_concurrency` at a time. Returns `None`
+    /// when the request doesn't split into several sub-questions.
+    async fn fan_out(
+        &self,
+        ctx: &HookContext,
+        history: &HistoryStorage,
+        messages: &[ChatMessage],
+        tools: &[Tool],
+        fanout: &FanoutConfig,
+        model: Option<&str>,
+    ) -> Option<Findings> {
+        let mut decomposition = messages.to_vec();
+        decomposition.push(ChatMessage {
+            role: "system".to_string(),
+            content: decomposition_prompt(fanout.max_subquestions).into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+        let mut request = crate::llm::ChatCompletionRequest {
+            model: model.unwrap_or_default().to_string(),
+            messages: decomposition,
+            ..Default::default()
+        };
+        let input_index = messages.len().saturating_sub(1);
+        if let Err(e) = self
+            .run_before_llm_hooks(ctx, &mut request, input_index)
+            .await
+        {
+            warn!("Hook rejected request decomposition: {}", e);
+            return None;
+        }
+        let response = match self.llm_client.create_chat_completion(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Request decomposition failed, answering in one run: {}", e);
+                return None;
+            }
+        };
+        for hook in &self.hooks {
+            if let Err(e) = hook.after_llm_call(ctx, &response).await {
+                warn!("after_llm_call hook failed: {}", e);
+            }
+        }
+        let reply = response.choices.first()?.message.content.clone();
+        let questions = parse_subquestions(&reply.text(), fanout.max_subquestions);
+        if questions.len() < 2 {
+            debug!("Request has no independent sub-questions, answering in one run");
+            return None;
+        }
+        info!(
+            "🔀 Answering {} sub-questions, {} at a time",
+            questions.len(),
+            fanout.max_concurrency
+        );
+
+        // Each sub-run sees the conversation so far with its question as the last message
+        let context = &messages[..messages.len() - 1];
+        let runs = questions
+            .clone()
+            .into_iter()
+            .map(|question| self.answer_subquestion(ctx, history, context, tools, model, question));
+        let answers: Vec<_> = futures::stream::iter(runs)
+            .buffered(fanout.max_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut findings = Findings::default();
+        let mut answered = Vec::new();
+        for (question, (answer, mut fsm)) in questions.into_iter().zip(answers) {
+            findings.tool_calls.append(&mut fsm.context.tool_calls);
+            findings.citations.append(&mut fsm.context.citations);
+            answered.push((question, answer));
+        }
+        findings.prompt = findings_prompt(&answered);
+        Some(findings)
+    }
+
+    /// Answers one sub-question of a fanned-out request in a run of its own
+    async fn answer_subquestion(
+        &self,
+        ctx: &HookContext,
+        history: &HistoryStorage,
+        context: &[ChatMessage],
+        tools: &[Tool],
+        model: Option<&str>,
+        question: String,
+    ) -> (String, AgentStateMachine) {
+        let mut messages = context.to_vec();
         messages.push(ChatMessage {
             role: "user".to_string(),
-            content: input.to_string(),
+            content: question.clone().into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
         });
+        let mut fsm = AgentStateMachine::new(messages, tools.to_vec(), HashMap::new());
+        fsm.context.max_turns = self.max_turns;
+        // Sub-runs are part of the request's run
+        if let Some(run_id) = &ctx.run_id {
+            fsm.context.run_id = run_id.clone();
+        }
+        let settings = RunSettings {
+            language: None,
+            tool_mode: ToolMode::Auto,
+            client_tools: Vec::new(),
+            // The answer is only part of the reply, which is saved once combined
+            partial_replies: false,
+            model: model.map(str::to_string),
+            sampling: self.sampling.clone(),
+            response_format: None,
+            tool_choice: None,
+        };
+        let answer = match self
+            .run_fsm_loop(&ctx.session_id, history, &mut fsm, &settings)
+            .await
+        {
+            Ok(LoopEnd::Reply(answer)) => answer,
+            Ok(LoopEnd::Paused(_)) => "Error: the question needed the caller's tools".to_string(),
+            Err(e) => {
+                warn!("Sub-question '{}' failed: {}", question, e);
+                format!("Error: could not answer: {e}")
+            }
+        };
+        (answer, fsm)
+    }
 
-        // Save user message to history
-        let user_message = Message::user(session_id.to_string(), input.to_string());
-        history.save(user_message).await?;
+    /// Continues a paused run with the caller's tool `results`, keyed by tool call id.
+    /// Calls left without a result are answered with an error for the LLM.
+    pub async fn resume_run(
+        &mut self,
+        run: PausedRun,
+        mut results: HashMap<String, McpToolCallResponse>,
+        history: &HistoryStorage,
+    ) -> Result<RunOutcome> {
+        let PausedRun {
+            session_id,
+            mut fsm,
+            results: partial,
+            turn_start,
+            settings,
+            ..
+        } = run;
+        info!("Resuming paused run for session: {}", session_id);
+
+        let calls = fsm.context.pending_tool_calls.clone();
+        let ids = fsm.context.tool_call_id_mapping.clone();
+        let mut turn_results = Vec::with_capacity(partial.len());
+        for (index, result) in partial.into_iter().enumerate() {
+            let result = match result {
+                Some(result) => result,
+                None => {
+                    let result = ids
+                        .get(index)
+                        .and_then(|id| results.remove(id))
+                        .unwrap_or_else(|| error_result("No result was provided for this call"));
+                    if let Some(call) = calls.get(index) {
+                        fsm.context
+                            .add_citations(citations_from_tool_result(call, &result));
+                        fsm.context.tool_calls.push(ToolCallRecord {
+                            name: call.name.clone(),
+                            arguments: serde_json::to_value(&call.arguments).unwrap_or_default(),
+                            result: render_tool_result(&result, ToolOutputFormat::Text, None),
+                            is_error: result.is_error,
+                            duration_ms: 0,
+                        });
+                    }
+                    result
+                }
+            };
+            turn_results.push(result);
+        }
+        fsm.add_tool_execution_results(turn_results);
+        self.transition(&session_id, &mut fsm, AgentEvent::ToolsExecutionCompleted)
+            .await?;
 
-        // Create FSM with initial state
-        let mut fsm = AgentStateMachine::new(
-            messages,
-            self.available_tools.clone(),
-            // Note: We can't move mcp_clients here due to borrowing rules
-            // In a real implementation, you'd use Arc<Mutex<>> or similar
-            HashMap::new(), // Placeholder for now
-        );
+        self.drive_run(&session_id, history, fsm, turn_start, settings)
+            .await
+    }
 
+    /// Runs the FSM until it replies, fails or pauses for the caller's tools
+    async fn drive_run(
+        &self,
+        session_id: &str,
+        history: &HistoryStorage,
+        mut fsm: AgentStateMachine,
+        turn_start: usize,
+        settings: RunSettings,
+    ) -> Result<RunOutcome> {
         // Process through FSM until terminal state
-        let result = self.run_fsm_loop(&mut fsm).await?;
-
-        // Save assistant response to history
-        let assistant_message = Message::assistant(session_id.to_string(), result.clone());
-        history.save(assistant_message).await?;
-
-        Ok(result)
+        let result = match self
+            .run_fsm_loop(session_id, history, &mut fsm, &settings)
+            .await
+        {
+            Ok(LoopEnd::Paused(results)) => {
+                return Ok(RunOutcome::Paused(Box::new(paused_run(
+                    session_id, fsm, results, turn_start, settings,
+                ))));
+            }
+            Ok(LoopEnd::Reply(output)) => Ok(output),
+            Err(e) => Err(e),
+        };
+        self.finish_run(session_id, history, fsm, turn_start, result)
+            .await
+            .map(RunOutcome::Reply)
     }
 
-    async fn run_fsm_loop(&mut self, fsm: &mut AgentStateMachine) -> Result<String> {
-        let start_time = std::time::Instant::now();
-        info!("🚀 Starting FSM loop");
-        let mut loop_iteration = 0;
-
-        // Initial event to start processing
-        debug!("🎬 Sending initial ProcessInput event");
-        let event_start = std::time::Instant::now();
-        fsm.process_event(AgentEvent::ProcessInput, Some(self.llm_client.as_ref()))
+    /// Runs the completion hooks and saves the reply with what the turn drew on
+    async fn finish_run(
+        &self,
+        session_id: &str,
+        history: &HistoryStorage,
+        mut fsm: AgentStateMachine,
+        turn_start: usize,
+        result: Result<String>,
+    ) -> Result<AgentReply> {
+        let hook_ctx =
+            HookContext::for_run(session_id, &fsm.context.run_id, fsm.context.current_turn);
+        let mut confidence = None;
+        let result = match (result, &self.confidence) {
+            (Ok(output), Some(scoring)) => {
+                confidence = self
+                    .score_reply(&hook_ctx, &fsm.context.messages, fsm.context.input_index)
+                    .await;
+                match confidence {
+                    Some(score) if score < scoring.threshold => {
+                        let question = turn_start
+                            .checked_sub(1)
+                            .and_then(|i| fsm.context.messages.get(i))
+                            .map(|m| m.content.text().into_owned())
+                            .unwrap_or_default();
+                        Ok(self
+                            .handle_low_confidence(scoring, session_id, question, output, score)
+                            .await)
+                    }
+                    _ => Ok(output),
+                }
+            }
+            (result, _) => result,
+        };
+        let result = match result {
+            Ok(output) => Ok(self.moderate_reply(output).await),
+            Err(e) => Err(e),
+        };
+        for hook in &self.hooks {
+            hook.on_complete(&hook_ctx, &result).await;
+        }
+        let partial_reply_id = fsm.context.partial_reply_id.take();
+        if result.is_err()
+            && let Some(id) = partial_reply_id
+            && let Err(e) = history.delete(id).await
+        {
+            warn!("Failed to remove partial reply of failed run: {}", e);
+        }
+        let result = result?;
+        let citations = std::mem::take(&mut fsm.context.citations);
+        let tool_calls = std::mem::take(&mut fsm.context.tool_calls);
+        // Tool calls and results exchanged before the final reply, replayed as-is later
+        let mut turn = fsm.context.messages.split_off(turn_start);
+        turn.pop();
+
+        // Save assistant response to history, with what the turn drew on
+        let mut metadata = serde_json::Map::new();
+        if !citations.is_empty() {
+            metadata.insert("citations".to_string(), serde_json::to_value(&citations)?);
+        }
+        if !tool_calls.is_empty() {
+            metadata.insert("tool_calls".to_string(), serde_json::to_value(&tool_calls)?);
+        }
+        if !turn.is_empty() {
+            metadata.insert("turn".to_string(), serde_json::to_value(&turn)?);
+        }
+        if let Some(confidence) = confidence {
+            metadata.insert("confidence".to_string(), confidence.into());
+        }
+        let context_fallback = fsm.context.context_fallback.take();
+        if let Some(fallback) = &context_fallback {
+            metadata.insert(
+                "context_fallback".to_string(),
+                serde_json::to_value(fallback)?,
+            );
+        }
+        let mut assistant_message = Message::assistant(session_id.to_string(), result.clone());
+        if !metadata.is_empty() {
+            assistant_message = assistant_message.with_metadata(metadata.into());
+        }
+        self.save_reply(history, partial_reply_id, assistant_message)
             .await?;
-        debug!(
-            "⏱️ Initial ProcessInput event took {:?}",
-            event_start.elapsed()
-        );
 
-        // Main FSM loop
-        info!("🔄 Entering main FSM loop");
-        while !fsm.is_terminal() {
-            loop_iteration += 1;
-            debug!(
-                "🔄 FSM loop iteration {} - current state: {:?}",
-                loop_iteration,
-                fsm.current_state()
-            );
+        Ok(AgentReply {
+            run_id: fsm.context.run_id.clone(),
+            output: result,
+            citations,
+            confidence,
+            blocked: Vec::new(),
+            context_fallback,
+        })
+    }
 
-            // Check if max turns exceeded (matches Go implementation maxTurns: 5)
-            if fsm.context.current_turn >= fsm.context.max_turns {
-                error!(
-                    "🚨 Maximum interaction turns exceeded ({}/{}), terminating to prevent infinite loop",
-                    fsm.context.current_turn, fsm.context.max_turns
-                );
-                fsm.context.last_error = Some("exceeded maximum interaction turns".to_string());
-                fsm.process_event(AgentEvent::ErrorOccurred, Some(self.llm_client.as_ref()))
-                    .await?;
-                break;
+    /// Replaces a final reply the moderator blocks with its `blocked_reply`
+    async fn moderate_reply(&self, output: String) -> String {
+        let Some(moderator) = self.moderator.as_ref().filter(|m| m.check_output) else {
+            return output;
+        };
+        match moderator.check(&output).await {
+            ModerationVerdict::Allow => output,
+            ModerationVerdict::Block { categories } => {
+                warn!("🛑 Reply blocked by moderation: {}", categories.join(", "));
+                moderator.blocked_reply.clone()
             }
+        }
+    }
 
-            if loop_iteration > 15 {
-                error!(
-                    "🚨 FSM loop iteration limit exceeded ({}), breaking to prevent infinite loop",
-                    loop_iteration
-                );
-                break;
+    /// Calls the LLM with streaming, saving the reply received so far every `interval`
+    /// as a `partial` assistant message in the row `partial_reply_id`
+    async fn stream_reply(
+        &self,
+        session_id: &str,
+        history: &HistoryStorage,
+        request: crate::llm::ChatCompletionRequest,
+        interval: std::time::Duration,
+        partial_reply_id: &mut Option<i64>,
+    ) -> Result<crate::llm::ChatCompletionResponse> {
+        let (deltas, mut received) = tokio::sync::mpsc::unbounded_channel();
+        let call = self
+            .llm_client
+            .create_chat_completion_streaming(request, deltas);
+        let persist = async {
+            let mut text = String::new();
+            let mut saved_at = std::time::Instant::now();
+            // The row is created once; in-memory storage gets no partial replies
+            let mut unsaved = false;
+            while let Some(delta) = received.recv().await {
+                text.push_str(&delta);
+                if unsaved || saved_at.elapsed() < interval || text.trim().is_empty() {
+                    continue;
+                }
+                saved_at = std::time::Instant::now();
+                let message = Message::assistant(session_id.to_string(), text.clone())
+                    .with_metadata(serde_json::json!({ PARTIAL_REPLY_FLAG: true }));
+                let saved = match *partial_reply_id {
+                    Some(id) => history.update(id, &message).await,
+                    None if history.status().await.is_ok() => {
+                        history.insert(message).await.map(|id| {
+                            unsaved = id.is_none();
+                            *partial_reply_id = id;
+                        })
+                    }
+                    None => {
+                        unsaved = true;
+                        Ok(())
+                    }
+                };
+                if let Err(e) = saved {
+                    warn!("Failed to save partial reply: {}", e);
+                }
             }
-            match fsm.current_state() {
-                AgentState::AwaitingLlmResponse => {
-                    // Check if we need to make an LLM call or process existing response
-                    if fsm.context.llm_response.is_none() {
-                        // Make LLM call
-                        debug!(
-                            "🤖 Making LLM call with {} messages",
-                            fsm.context.messages.len()
-                        );
+        };
+        let (response, ()) = tokio::join!(call, persist);
+        response
+    }
 
-                        let chat_request = crate::llm::ChatCompletionRequest {
-                            model: "".to_string(), // Model will be set by the LLM client
-                            messages: fsm.context.messages.clone(),
-                            tools: self.available_tools.clone(),
-                            temperature: None,
-                            max_tokens: None,
-                        };
+    /// Saves a reply, in place of its partial version if it was streamed
+    async fn save_reply(
+        &self,
+        history: &HistoryStorage,
+        partial_reply_id: Option<i64>,
+        message: Message,
+    ) -> Result<()> {
+        if let Some(id) = partial_reply_id {
+            match history.update(id, &message).await {
+                Ok(()) => return Ok(()),
+                Err(e) => warn!("Failed to finalize partial reply, saving anew: {}", e),
+            }
+        }
+        history.save(message).await
+    }
 
-                        let llm_start = std::time::Instant::now();
-                        match self.llm_client.create_chat_completion(chat_request).await {
-                            Ok(response) => {
-                                let llm_duration = llm_start.elapsed();
-                                info!(
-                                    "✅ LLM responded with {} choices in {:?}",
+    /// Asks the LLM how likely the reply ending `messages` is to be right
+    async fn score_reply(
+        &self,
+        ctx: &HookContext,
+        messages: &[ChatMessage],
+        input_index: usize,
+    ) -> Option<f64> {
+        let mut messages = messages.to_vec();
+        messages.push(ChatMessage {
+            role: "system".to_string(),
+            content: JUDGE_PROMPT.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+        let mut request = crate::llm::ChatCompletionRequest {
+            model: "".to_string(),
+            messages,
+            ..Default::default()
+        };
+        if let Err(e) = self
+            .run_before_llm_hooks(ctx, &mut request, input_index)
+            .await
+        {
+            warn!("Hook rejected confidence scoring: {}", e);
+            return None;
+        }
+        let response = match self.llm_client.create_chat_completion(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Confidence scoring failed: {}", e);
+                return None;
+            }
+        };
+        for hook in &self.hooks {
+            if let Err(e) = hook.after_llm_call(ctx, &response).await {
+                warn!("after_llm_call hook failed: {}", e);
+            }
+        }
+        let reply = &response.choices.first()?.message.content;
+        let score = parse_confidence(&reply.text());
+        if score.is_none() {
+            warn!("Confidence judge replied without a score: {}", reply);
+        }
+        score
+    }
+
+    /// Escalates a low-confidence `output` and returns what to reply instead
+    async fn handle_low_confidence(
+        &self,
+        scoring: &ConfidenceScoring,
+        session_id: &str,
+        question: String,
+        output: String,
+        confidence: f64,
+    ) -> String {
+        info!(
+            "🤔 Reply confidence {:.2} is below {:.2}",
+            confidence, scoring.threshold
+        );
+        let escalation = Escalation {
+            session_id: session_id.to_string(),
+            question,
+            answer: output,
+            confidence,
+        };
+        if let Err(e) = scoring.escalate(&escalation).await {
+            warn!("Failed to escalate low-confidence reply: {}", e);
+        }
+        scoring.fallback_reply.clone().unwrap_or(escalation.answer)
+    }
+
+    /// Builds the conversation for a new request: the system prompt, the replayed
+    /// history and the user input. Also returns the system prompt to pin when the
+    /// session should keep it from now on.
+    fn initial_messages(
+        &self,
+        input: &str,
+        previous_messages: Vec<Message>,
+    ) -> (Vec<ChatMessage>, Option<String>) {
+        // Generate final system prompt, or reuse the one the session started with
+        let pinned_prompt = if self.retroactive_system_prompt {
+            None
+        } else {
+            previous_messages
+                .iter()
+                .find(|m| m.role == "system" && is_flagged(m, SYSTEM_PROMPT_FLAG))
+                .map(|m| m.content.clone())
+        };
+        let pin_prompt = !self.retroactive_system_prompt && pinned_prompt.is_none();
+        let final_system_prompt = pinned_prompt.unwrap_or_else(|| self.build_system_prompt());
+
+        // Build initial messages
+        let mut messages = Vec::new();
+
+        // Add system prompt if available
+        if !final_system_prompt.is_empty() {
+            messages.push(ChatMessage {
+                role: "system".to_string(),
+                content: final_system_prompt.clone().into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+        }
+
+        // Add previous messages, expanding the tool calls behind each reply
+        messages.extend(replay_history(previous_messages));
+
+        // Add current user input
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: input.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+
+        let pin_prompt =
+            (pin_prompt && !final_system_prompt.is_empty()).then_some(final_system_prompt);
+        (messages, pin_prompt)
+    }
+
+    /// Assembles the first LLM request `process` would send for `input`, without
+    /// calling the LLM or saving anything. `before_llm_call` hooks are applied with a
+    /// preview context.
+    pub async fn preview_request(
+        &self,
+        session_id: &str,
+        input: &str,
+        history: &HistoryStorage,
+    ) -> Result<crate::llm::ChatCompletionRequest> {
+        let previous_messages = history.list(session_id).await?;
+        let (mut messages, _) = self.initial_messages(input, previous_messages);
+        if let Some(language) = &self.response_language {
+            add_language_directive(&mut messages, language);
+        }
+        let input_index = messages.len() - 1;
+        let mut request = crate::llm::ChatCompletionRequest {
+            model: self.model.clone(),
+            messages,
+            tools: self.advertised_tools(),
+            ..Default::default()
+        }
+        .with_sampling(&self.sampling);
+        self.run_before_llm_hooks(&HookContext::preview(session_id), &mut request, input_index)
+            .await?;
+        Ok(request)
+    }
+
+    async fn run_fsm_loop(
+        &self,
+        session_id: &str,
+        history: &HistoryStorage,
+        fsm: &mut AgentStateMachine,
+        settings: &RunSettings,
+    ) -> Result<LoopEnd> {
+        let start_time = std::time::Instant::now();
+        info!("🚀 Starting FSM loop");
+        let mut loop_iteration = 0;
+
+        // Initial event to start processing; a resumed run first sends its tool results
+        if fsm.context.tool_call_results.is_empty() {
+            debug!("🎬 Sending initial ProcessInput event");
+            let event_start = std::time::Instant::now();
+            self.transition(session_id, fsm, AgentEvent::ProcessInput)
+                .await?;
+            debug!(
+                "⏱️ Initial ProcessInput event took {:?}",
+                event_start.elapsed()
+            );
+        }
+
+        // Main FSM loop
+        info!("🔄 Entering main FSM loop");
+        while !fsm.is_terminal() {
+            loop_iteration += 1;
+            debug!(
+                "🔄 FSM loop iteration {} - current state: {:?}",
+                loop_iteration,
+                fsm.current_state()
+            );
+
+            // Each turn takes at most four iterations: the LLM call, a re-ask of the
+            // answer model, the tool calls and the return to the LLM
+            let iteration_limit = (fsm.context.max_turns + 1) * 4;
+            if loop_iteration > iteration_limit {
+                let diagnostics = fsm.diagnostics(
+                    format!("exceeded {iteration_limit} loop iterations"),
+                    loop_iteration,
+                );
+                error!("🚨 FSM watchdog stopped the run: {:?}", diagnostics);
+                return Err(Error::fsm_stuck(diagnostics));
+            }
+            match fsm.current_state() {
+                AgentState::AwaitingLlmResponse => {
+                    // Check if we need to make an LLM call or process existing response
+                    if fsm.context.llm_response.is_none() {
+                        // Each LLM call is a turn; the answer model's re-ask belongs to
+                        // the turn of the reply it replaces
+                        if fsm.context.is_max_turns_reached() {
+                            let max_turns = fsm.context.max_turns;
+                            error!(
+                                "🚨 Maximum interaction turns exceeded ({}/{}), terminating to prevent infinite loop",
+                                fsm.context.current_turn, max_turns
+                            );
+                            fsm.context.set_error(format!(
+                                "exceeded maximum interaction turns ({max_turns})"
+                            ));
+                            self.transition(session_id, fsm, AgentEvent::ErrorOccurred)
+                                .await?;
+                            return Err(Error::MaxTurnsExceeded { max_turns });
+                        }
+
+                        // Make LLM call
+                        debug!(
+                            "🤖 Making LLM call with {} messages",
+                            fsm.context.messages.len()
+                        );
+
+                        let (model, tool_turn) = self.turn_model(settings, &fsm.context);
+                        let model = model.as_str();
+                        let mut chat_request = crate::llm::ChatCompletionRequest {
+                            model: model.to_string(),
+                            messages: fsm.context.messages.clone(),
+                            tools: fsm.context.available_tools.clone(),
+                            response_format: settings.response_format.clone(),
+                            // Forcing a tool on later calls would call it forever
+                            tool_choice: settings
+                                .tool_choice
+                                .clone()
+                                .filter(|_| fsm.context.current_turn == 0),
+                            ..Default::default()
+                        }
+                        .with_sampling(&settings.sampling);
+
+                        let hook_ctx = HookContext::for_run(
+                            session_id,
+                            &fsm.context.run_id,
+                            fsm.context.current_turn,
+                        );
+                        let input_index = match self
+                            .run_before_llm_hooks(
+                                &hook_ctx,
+                                &mut chat_request,
+                                fsm.context.input_index,
+                            )
+                            .await
+                        {
+                            Ok(input_index) => input_index,
+                            Err(e) => {
+                                error!("❌ Hook rejected LLM call: {}", e);
+                                fsm.context.set_error(e.to_string());
+                                self.transition(session_id, fsm, AgentEvent::ErrorOccurred)
+                                    .await?;
+                                continue;
+                            }
+                        };
+
+                        let llm_start = std::time::Instant::now();
+                        let request_messages = chat_request.messages.clone();
+                        let response = match self.partial_reply_interval {
+                            // Replies of the tool model may be dropped, so they aren't shown
+                            Some(interval) if settings.partial_replies && !tool_turn => {
+                                self.stream_reply(
+                                    session_id,
+                                    history,
+                                    chat_request,
+                                    interval,
+                                    &mut fsm.context.partial_reply_id,
+                                )
+                                .await
+                            }
+                            _ => self.llm_client.create_chat_completion(chat_request).await,
+                        };
+                        match response {
+                            Ok(mut response) => {
+                                if response.context_fallback.is_some() {
+                                    fsm.context.context_fallback =
+                                        response.context_fallback.clone();
+                                }
+                                for hook in &self.hooks {
+                                    if let Err(e) = hook.after_llm_call(&hook_ctx, &response).await
+                                    {
+                                        warn!("after_llm_call hook failed: {}", e);
+                                    }
+                                }
+                                fsm.context.answering = false;
+                                if tool_turn && !requests_tools(&response) {
+                                    info!("🔀 Tool model answered; asking the answer model");
+                                    fsm.context.answering = true;
+                                    continue;
+                                }
+                                let call = ReplyCall {
+                                    ctx: &hook_ctx,
+                                    model,
+                                    sampling: &settings.sampling,
+                                    input_index,
+                                    tools: &fsm.context.available_tools,
+                                    response_format: settings.response_format.as_ref(),
+                                    tool_choice: settings
+                                        .tool_choice
+                                        .as_ref()
+                                        .filter(|_| fsm.context.current_turn == 0),
+                                };
+                                self.retry_empty_reply(&call, &request_messages, &mut response)
+                                    .await;
+                                self.continue_truncated_reply(
+                                    &call,
+                                    request_messages.clone(),
+                                    &mut response,
+                                )
+                                .await;
+                                if let Some(language) = &settings.language {
+                                    self.enforce_language(
+                                        &call,
+                                        request_messages.clone(),
+                                        &mut response,
+                                        language,
+                                    )
+                                    .await;
+                                }
+                                if let Some(format) =
+                                    settings.response_format.as_ref().filter(|f| f.is_json())
+                                    && let Err(problems) = self
+                                        .enforce_response_format(
+                                            &call,
+                                            request_messages,
+                                            &mut response,
+                                            format,
+                                        )
+                                        .await
+                                {
+                                    error!("❌ Reply doesn't match the response format");
+                                    fsm.context.set_error(format!(
+                                        "Reply does not match the requested response_format: {}",
+                                        problems.join("; ")
+                                    ));
+                                    self.transition(session_id, fsm, AgentEvent::ErrorOccurred)
+                                        .await?;
+                                    continue;
+                                }
+                                let llm_duration = llm_start.elapsed();
+                                info!(
+                                    "✅ LLM responded with {} choices in {:?}",
                                     response.choices.len(),
                                     llm_duration
                                 );
                                 fsm.context.llm_response = Some(response);
-                                // Increment turn counter (matches Go implementation)
                                 fsm.context.increment_turn();
                                 debug!(
                                     "🔄 Turn incremented to {}/{}",
@@ -353,11 +1516,8 @@ impl Agent {
                             }
                             Err(e) => {
                                 error!("❌ LLM call failed: {}", e);
-                                fsm.process_event(
-                                    AgentEvent::ErrorOccurred,
-                                    Some(self.llm_client.as_ref()),
-                                )
-                                .await?;
+                                self.transition(session_id, fsm, AgentEvent::ErrorOccurred)
+                                    .await?;
                                 continue;
                             }
                         }
@@ -365,12 +1525,22 @@ impl Agent {
 
                     // Process the LLM response
                     if let Some(response) = &fsm.context.llm_response {
-                        if !response.choices.is_empty() {
+                        if is_empty_reply(response) {
+                            warn!("⚠️ LLM returned an empty response");
+                            fsm.context.set_error(format!(
+                                "LLM returned an empty response after {} attempts",
+                                self.empty_response.retries + 1
+                            ));
+                            self.transition(session_id, fsm, AgentEvent::ErrorOccurred)
+                                .await?;
+                        } else {
                             let choice = &response.choices[0];
-                            if choice.message.tool_calls.is_some()
-                                && !choice.message.tool_calls.as_ref().unwrap().is_empty()
+                            if let Some(tool_calls) = choice
+                                .message
+                                .tool_calls
+                                .as_ref()
+                                .filter(|calls| !calls.is_empty())
                             {
-                                let tool_calls = choice.message.tool_calls.as_ref().unwrap();
                                 debug!("🔧 LLM requested {} tool calls", tool_calls.len());
 
                                 // Add the LLM's assistant message with tool_calls to conversation
@@ -385,6 +1555,38 @@ impl Agent {
                                     name: None,
                                 });
 
+                                // Interim text next to tool calls is part of the conversation
+                                // too, so keep it in history and let listeners see it
+                                let commentary = choice.message.content.text();
+                                let commentary = commentary.trim();
+                                if commentary.is_empty()
+                                    && let Some(id) = fsm.context.partial_reply_id.take()
+                                    && let Err(e) = history.delete(id).await
+                                {
+                                    warn!("Failed to remove empty partial reply: {}", e);
+                                }
+                                if !commentary.is_empty() {
+                                    let message = Message::assistant(
+                                        session_id.to_string(),
+                                        commentary.to_string(),
+                                    )
+                                    .with_metadata(serde_json::json!({ "commentary": true }));
+                                    let partial_reply_id = fsm.context.partial_reply_id.take();
+                                    if let Err(e) =
+                                        self.save_reply(history, partial_reply_id, message).await
+                                    {
+                                        warn!("Failed to save assistant commentary: {}", e);
+                                    }
+                                    let hook_ctx = HookContext::for_run(
+                                        session_id,
+                                        &fsm.context.run_id,
+                                        fsm.context.current_turn,
+                                    );
+                                    for hook in &self.hooks {
+                                        hook.on_commentary(&hook_ctx, commentary).await;
+                                    }
+                                }
+
                                 // Convert LLM tool calls to MCP tool call requests and store ID mapping
                                 let mut mcp_tool_calls = Vec::new();
                                 let mut tool_call_ids = Vec::new();
@@ -412,11 +1614,8 @@ impl Agent {
                                     fsm.context.pending_tool_calls.len()
                                 );
 
-                                fsm.process_event(
-                                    AgentEvent::LlmRequestedTools,
-                                    Some(self.llm_client.as_ref()),
-                                )
-                                .await?;
+                                self.transition(session_id, fsm, AgentEvent::LlmRequestedTools)
+                                    .await?;
                             } else {
                                 debug!("💬 LLM provided content response");
 
@@ -435,19 +1634,13 @@ impl Agent {
                                     });
                                 }
 
-                                fsm.process_event(
+                                self.transition(
+                                    session_id,
+                                    fsm,
                                     AgentEvent::LlmRespondedWithContent,
-                                    Some(self.llm_client.as_ref()),
                                 )
                                 .await?;
                             }
-                        } else {
-                            warn!("⚠️ LLM response has no choices");
-                            fsm.process_event(
-                                AgentEvent::ErrorOccurred,
-                                Some(self.llm_client.as_ref()),
-                            )
-                            .await?;
                         }
                     }
                 }
@@ -458,10 +1651,41 @@ impl Agent {
                     let tool_calls = fsm.prepare_tool_execution();
                     info!("🛠️ Executing {} tool calls", tool_calls.len());
 
-                    // Execute tools
+                    // Execute tools, leaving the caller's to the caller
                     let mut results = Vec::new();
                     let tools_start = std::time::Instant::now();
                     for (i, tool_call) in tool_calls.iter().enumerate() {
+                        // The LLM may name any tool, but only those the run offers run
+                        if !fsm
+                            .context
+                            .available_tools
+                            .iter()
+                            .any(|tool| tool.function.name == tool_call.name)
+                        {
+                            warn!(
+                                "🚫 LLM called tool {} the run doesn't offer",
+                                tool_call.name
+                            );
+                            let result = error_result(format!(
+                                "Error: Tool '{}' is not available",
+                                tool_call.name
+                            ));
+                            fsm.context.tool_calls.push(ToolCallRecord {
+                                name: tool_call.name.clone(),
+                                arguments: serde_json::to_value(&tool_call.arguments)
+                                    .unwrap_or_default(),
+                                result: render_tool_result(&result, ToolOutputFormat::Text, None),
+                                is_error: true,
+                                duration_ms: 0,
+                            });
+                            results.push(Some(result));
+                            continue;
+                        }
+                        if settings.runs_on_caller(&tool_call.name) {
+                            debug!("📤 Leaving tool {} to the caller", tool_call.name);
+                            results.push(None);
+                            continue;
+                        }
                         debug!(
                             "🔨 Executing tool {}/{}: {}",
                             i + 1,
@@ -469,7 +1693,22 @@ impl Agent {
                             tool_call.name
                         );
                         let tool_start = std::time::Instant::now();
-                        let result = self.execute_mcp_tool(tool_call).await;
+                        let mut tool_call = tool_call.clone();
+                        // Recorded as the LLM sent them, without the injected context
+                        let arguments =
+                            serde_json::to_value(&tool_call.arguments).unwrap_or_default();
+                        self.prepare_arguments(&mut tool_call);
+                        self.inject_tool_context(&mut tool_call, &fsm.context.messages);
+                        let hook_ctx = HookContext::for_run(
+                            session_id,
+                            &fsm.context.run_id,
+                            fsm.context.current_turn,
+                        );
+                        let result = self
+                            .execute_tool_with_hooks(&hook_ctx, &tool_call, Some(history))
+                            .await;
+                        fsm.context
+                            .add_citations(citations_from_tool_result(&tool_call, &result));
                         let tool_duration = tool_start.elapsed();
                         debug!(
                             "✅ Tool {} completed with {} content items in {:?}",
@@ -477,10 +1716,26 @@ impl Agent {
                             result.content.len(),
                             tool_duration
                         );
-                        results.push(result);
+                        fsm.context.tool_calls.push(ToolCallRecord {
+                            name: tool_call.name.clone(),
+                            arguments,
+                            result: render_tool_result(&result, ToolOutputFormat::Text, None),
+                            is_error: result.is_error,
+                            duration_ms: tool_duration.as_millis() as u64,
+                        });
+                        results.push(Some(result));
                     }
                     let total_tools_duration = tools_start.elapsed();
 
+                    if results.iter().any(Option::is_none) {
+                        info!(
+                            "⏸️ Pausing run for {} tool calls the caller runs",
+                            results.iter().filter(|r| r.is_none()).count()
+                        );
+                        return Ok(LoopEnd::Paused(results));
+                    }
+                    let results = results.into_iter().flatten().collect::<Vec<_>>();
+
                     info!(
                         "🎯 All {} tools completed in {:?}, adding results to FSM",
                         results.len(),
@@ -491,11 +1746,8 @@ impl Agent {
 
                     // Continue with tools execution completed
                     debug!("📤 Sending ToolsExecutionCompleted event");
-                    fsm.process_event(
-                        AgentEvent::ToolsExecutionCompleted,
-                        Some(self.llm_client.as_ref()),
-                    )
-                    .await?;
+                    self.transition(session_id, fsm, AgentEvent::ToolsExecutionCompleted)
+                        .await?;
                 }
                 AgentState::ReadyToCallLlm => {
                     // Clear previous LLM response and prepare for new call
@@ -510,40 +1762,56 @@ impl Agent {
                         );
                         for (index, tool_result) in fsm.context.tool_call_results.iter().enumerate()
                         {
-                            if let Some(crate::mcp::McpContent::Text { text }) =
-                                tool_result.content.first()
-                            {
-                                // Get the corresponding tool call ID from the mapping
-                                let tool_call_id = fsm
-                                    .context
-                                    .tool_call_id_mapping
-                                    .get(index)
-                                    .cloned()
-                                    .unwrap_or_else(|| {
-                                        warn!("Missing tool call ID mapping for index {}", index);
-                                        format!("tool_call_{index}")
-                                    });
-
-                                debug!("📝 Adding tool result for tool_call_id: {}", tool_call_id);
-                                fsm.context.messages.push(ChatMessage {
-                                    role: "tool".to_string(),
-                                    content: text.clone(),
-                                    tool_calls: None,
-                                    tool_call_id: Some(tool_call_id),
-                                    name: None,
+                            // Get the corresponding tool call ID from the mapping
+                            let tool_call_id = fsm
+                                .context
+                                .tool_call_id_mapping
+                                .get(index)
+                                .cloned()
+                                .unwrap_or_else(|| {
+                                    warn!("Missing tool call ID mapping for index {}", index);
+                                    format!("tool_call_{index}")
                                 });
-                            }
+                            let (format, schema) = fsm
+                                .context
+                                .pending_tool_calls
+                                .get(index)
+                                .map(|call| self.output_format_for(&call.name))
+                                .unwrap_or((ToolOutputFormat::Text, None));
+
+                            debug!("📝 Adding tool result for tool_call_id: {}", tool_call_id);
+                            fsm.context.messages.push(ChatMessage {
+                                role: "tool".to_string(),
+                                content: MessageContent::from(render_tool_result(
+                                    tool_result,
+                                    format,
+                                    schema,
+                                ))
+                                .with_images(tool_images(tool_result)),
+                                tool_calls: None,
+                                tool_call_id: Some(tool_call_id),
+                                name: None,
+                            });
                         }
                         fsm.context.tool_call_results.clear();
                         fsm.context.tool_call_id_mapping.clear();
                     }
 
+                    if let Some(pacing) = &self.pacing {
+                        let delay = pacing.delay(fsm.context.current_turn);
+                        debug!("⏳ Pacing the next LLM call by {:?}", delay);
+                        tokio::time::sleep(delay).await;
+                    }
+
                     // Make another LLM call
-                    fsm.process_event(AgentEvent::ProcessInput, Some(self.llm_client.as_ref()))
+                    self.transition(session_id, fsm, AgentEvent::ProcessInput)
                         .await?;
                 }
-                _ => {
-                    break;
+                state => {
+                    let diagnostics =
+                        fsm.diagnostics(format!("no step runs in {state:?}"), loop_iteration);
+                    error!("🚨 FSM watchdog stopped the run: {:?}", diagnostics);
+                    return Err(Error::fsm_stuck(diagnostics));
                 }
             }
         }
@@ -558,7 +1826,7 @@ impl Agent {
         match fsm.current_state() {
             AgentState::Done => {
                 info!("✅ FSM completed successfully in state: Done");
-                Ok(fsm.get_final_content().to_string())
+                Ok(LoopEnd::Reply(fsm.get_final_content().to_string()))
             }
             AgentState::Error => {
                 error!("❌ FSM ended in error state after {:?}", total_duration);
@@ -571,15 +1839,9 @@ impl Agent {
                 }
             }
             _ => {
-                warn!(
-                    "⚠️ FSM ended in unexpected state: {:?} after {:?}",
-                    fsm.current_state(),
-                    total_duration
-                );
-                Err(Error::internal(format!(
-                    "FSM ended in unexpected state: {:?}",
-                    fsm.current_state()
-                )))
+                let diagnostics = fsm.diagnostics("loop ended before the run did", loop_iteration);
+                error!("🚨 FSM watchdog stopped the run: {:?}", diagnostics);
+                Err(Error::fsm_stuck(diagnostics))
             }
         }
     }
@@ -602,16 +1864,808 @@ impl Agent {
         prompt_parts.join("\n\n")
     }
 
+    /// Resolves how a tool's output should be rendered: configuration takes precedence,
+    /// otherwise tools that declared an output schema at discovery are treated as JSON.
+    fn output_format_for(&self, tool_name: &str) -> (ToolOutputFormat, Option<&serde_json::Value>) {
+        let discovered = self.tool_output_schemas.get(tool_name);
+        let settings = self.tools_config.settings_for(tool_name);
+        let configured_schema = settings.and_then(|s| s.output_schema.as_ref());
+        match settings.and_then(|s| s.output_format) {
+            Some(ToolOutputFormat::Json) => {
+                (ToolOutputFormat::Json, configured_schema.or(discovered))
+            }
+            Some(ToolOutputFormat::Text) => (ToolOutputFormat::Text, None),
+            None if discovered.is_some() => (ToolOutputFormat::Json, discovered),
+            None => (ToolOutputFormat::Text, None),
+        }
+    }
+
+    /// Tools as offered to the LLM, without the arguments the agent fills in itself,
+    /// followed by the tool groups that have any of their tools available. Descriptions
+    /// carry the configured cost and, with `latency_hints`, the measured latency.
+    fn advertised_tools(&self) -> Vec<Tool> {
+        let mut tools = self.available_tools.clone();
+        for tool in &mut tools {
+            if let Some(settings) = self.tools_config.settings_for(&tool.function.name) {
+                hide_injected_arguments(tool, settings);
+            }
+        }
+        for group in &self.tools_config.groups {
+            let Some(first) = tools
+                .iter()
+                .find(|tool| group.tools.contains(&tool.function.name))
+            else {
+                debug!("No tool of group '{}' is available", group.name);
+                continue;
+            };
+            let parameters = group
+                .input_schema
+                .clone()
+                .unwrap_or_else(|| first.function.parameters.clone());
+            tools.retain(|tool| tool.function.name != group.name);
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: Function {
+                    name: group.name.clone(),
+                    description: group.description.clone(),
+                    parameters,
+                },
+            });
+        }
+        for tool in &mut tools {
+            let name = &tool.function.name;
+            let cost = self
+                .tools_config
+                .settings_for(name)
+                .and_then(|s| s.cost.as_deref());
+            let latency = self
+                .tools_config
+                .latency_hints
+                .then(|| self.tool_latencies.average(name))
+                .flatten();
+            tool.function.description =
+                describe_with_hints(&tool.function.description, cost, latency);
+        }
+        tools
+    }
+
+    fn tool_group(&self, name: &str) -> Option<&ToolGroupConfig> {
+        self.tools_config
+            .groups
+            .iter()
+            .find(|group| group.name == name)
+    }
+
+    /// Calls the group's available tools concurrently with the group's arguments
+    async fn execute_group(
+        &self,
+        group: &ToolGroupConfig,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        ctx: &ToolContext<'_>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let calls: Vec<crate::mcp::McpToolCallRequest> = group
+            .tools
+            .iter()
+            .filter(|name| {
+                self.available_tools
+                    .iter()
+                    .any(|tool| &tool.function.name == *name)
+            })
+            .map(|name| {
+                let mut call = crate::mcp::McpToolCallRequest {
+                    name: name.clone(),
+                    arguments: tool_call.arguments.clone(),
+                };
+                self.prepare_arguments(&mut call);
+                call
+            })
+            .collect();
+        info!("🧩 Calling {} tools of group '{}'", calls.len(), group.name);
+        let results =
+            futures::future::join_all(calls.iter().map(|call| self.run_tool(call, ctx))).await;
+        merge_group_results(
+            calls
+                .into_iter()
+                .map(|call| call.name)
+                .zip(results)
+                .collect(),
+        )
+    }
+
+    /// Adapts the LLM's arguments with the tool's transform, then sets its static arguments
+    fn prepare_arguments(&self, tool_call: &mut crate::mcp::McpToolCallRequest) {
+        if let Some(settings) = self.tools_config.settings_for(&tool_call.name) {
+            transform_arguments(tool_call, &settings.transform);
+            apply_static_arguments(tool_call, settings);
+        }
+    }
+
+    /// Adds the reserved `_context` argument for tools that opted in to receiving it
+    fn inject_tool_context(
+        &self,
+        tool_call: &mut crate::mcp::McpToolCallRequest,
+        messages: &[ChatMessage],
+    ) {
+        let Some(settings) = self.tools_config.settings_for(&tool_call.name) else {
+            return;
+        };
+        if let Some(context) = build_tool_context(settings, messages) {
+            debug!(
+                "Injecting conversation context into tool '{}'",
+                tool_call.name
+            );
+            tool_call
+                .arguments
+                .insert(CONTEXT_ARGUMENT.to_string(), context);
+        }
+    }
+
+    /// Moves the run on with `event`, letting hooks know of the state change
+    async fn transition(
+        &self,
+        session_id: &str,
+        fsm: &mut AgentStateMachine,
+        event: AgentEvent,
+    ) -> Result<()> {
+        let from = fsm.current_state().clone();
+        fsm.process_event(event.clone(), Some(self.llm_client.as_ref()))
+            .await?;
+        let ctx = HookContext::for_run(session_id, &fsm.context.run_id, fsm.context.current_turn);
+        for hook in &self.hooks {
+            hook.on_transition(&ctx, &from, fsm.current_state(), &event)
+                .await;
+        }
+        Ok(())
+    }
+
+    /// Runs the `before_llm_call` hooks on `request`, trimming it to the context window
+    /// around them. `input_index` is where the run's input is in the request's messages;
+    /// returns where it ended up, after the messages trimmed before it and those the
+    /// hooks added ahead of the conversation.
+    async fn run_before_llm_hooks(
+        &self,
+        ctx: &HookContext,
+        request: &mut crate::llm::ChatCompletionRequest,
+        input_index: usize,
+    ) -> Result<usize> {
+        // The input and everything after it stay at the end of the request
+        let kept = request.messages.len().saturating_sub(input_index);
+        // Trimmed before, so hooks see the conversation as it is sent, and again after,
+        // in case what they added pushed it back over the budget
+        self.trim_request(request, kept);
+        for hook in &self.hooks {
+            hook.before_llm_call(ctx, request).await?;
+        }
+        self.trim_request(request, kept);
+        Ok(request.messages.len().saturating_sub(kept))
+    }
+
+    /// Drops the oldest messages of `request` past `max_context_tokens`, keeping the last
+    /// `kept`
+    fn trim_request(&self, request: &mut crate::llm::ChatCompletionRequest, kept: usize) {
+        let Some(max_tokens) = self.max_context_tokens else {
+            return;
+        };
+        let model = if request.model.is_empty() {
+            &self.model
+        } else {
+            &request.model
+        };
+        let budget = max_tokens.saturating_sub(request.max_tokens.unwrap_or(0) as usize);
+        let kept_from = request.messages.len().saturating_sub(kept);
+        let dropped = trim_to_budget(
+            tokenizer_for(model).as_ref(),
+            &mut request.messages,
+            &request.tools,
+            budget,
+            kept_from,
+        );
+        if dropped > 0 {
+            info!(
+                "✂️ Dropped the {} oldest messages to fit {} context tokens",
+                dropped, max_tokens
+            );
+        }
+    }
+
+    /// Re-asks the LLM with a system nudge while it replies with nothing, up to the
+    /// configured number of retries. `response` is replaced by the first usable reply.
+    async fn retry_empty_reply(
+        &self,
+        call: &ReplyCall<'_>,
+        messages: &[ChatMessage],
+        response: &mut crate::llm::ChatCompletionResponse,
+    ) {
+        for attempt in 1..=self.empty_response.retries {
+            if !is_empty_reply(response) {
+                return;
+            }
+            info!(
+                "🔁 LLM returned an empty response, retrying {}/{}",
+                attempt, self.empty_response.retries
+            );
+
+            let mut messages = messages.to_vec();
+            messages.push(ChatMessage {
+                role: "system".to_string(),
+                content: self.empty_response.nudge.clone().into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+            let mut request = crate::llm::ChatCompletionRequest {
+                model: call.model.to_string(),
+                messages,
+                tools: call.tools.to_vec(),
+                response_format: call.response_format.cloned(),
+                tool_choice: call.tool_choice.cloned(),
+                ..Default::default()
+            }
+            .with_sampling(call.sampling);
+            if let Err(e) = self
+                .run_before_llm_hooks(call.ctx, &mut request, call.input_index)
+                .await
+            {
+                warn!("Hook rejected empty response retry: {}", e);
+                return;
+            }
+
+            match self.llm_client.create_chat_completion(request).await {
+                Ok(retried) => {
+                    for hook in &self.hooks {
+                        if let Err(e) = hook.after_llm_call(call.ctx, &retried).await {
+                            warn!("after_llm_call hook failed: {}", e);
+                        }
+                    }
+                    *response = retried;
+                }
+                Err(e) => {
+                    warn!("Empty response retry failed: {}", e);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Asks the LLM to carry on while its reply stops at the token limit, appending each
+    /// continuation to `response` so the rest of the run sees a single message.
+    async fn continue_truncated_reply(
+        &self,
+        call: &ReplyCall<'_>,
+        messages: Vec<ChatMessage>,
+        response: &mut crate::llm::ChatCompletionResponse,
+    ) {
+        for attempt in 1..=self.max_continuations {
+            let Some(choice) = response.choices.first_mut() else {
+                return;
+            };
+            if !is_truncated(choice) {
+                return;
+            }
+            info!(
+                "✂️ LLM reply hit the token limit, requesting continuation {}/{}",
+                attempt, self.max_continuations
+            );
+
+            let mut messages = messages.clone();
+            messages.push(ChatMessage {
+                role: "assistant".to_string(),
+                content: choice.message.content.clone(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+            messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: CONTINUATION_PROMPT.into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+            // Without tools, so the model can only go on writing
+            let mut request = crate::llm::ChatCompletionRequest {
+                model: call.model.to_string(),
+                messages,
+                ..Default::default()
+            }
+            .with_sampling(call.sampling);
+            if let Err(e) = self
+                .run_before_llm_hooks(call.ctx, &mut request, call.input_index)
+                .await
+            {
+                warn!("Hook rejected continuation request: {}", e);
+                return;
+            }
+
+            let next = match self.llm_client.create_chat_completion(request).await {
+                Ok(next) => next,
+                Err(e) => {
+                    warn!(
+                        "Continuation request failed, keeping truncated reply: {}",
+                        e
+                    );
+                    return;
+                }
+            };
+            for hook in &self.hooks {
+                if let Err(e) = hook.after_llm_call(call.ctx, &next).await {
+                    warn!("after_llm_call hook failed: {}", e);
+                }
+            }
+            let Some(next_choice) = next.choices.into_iter().next() else {
+                warn!("Continuation response has no choices, keeping truncated reply");
+                return;
+            };
+            choice
+                .message
+                .content
+                .push_str(&next_choice.message.content.text());
+            choice.finish_reason = next_choice.finish_reason;
+        }
+
+        if response.choices.first().is_some_and(is_truncated) {
+            warn!(
+                "LLM reply still truncated after {} continuations",
+                self.max_continuations
+            );
+        }
+    }
+
+    /// Asks the LLM once to rewrite a final reply that came back in a language other
+    /// than `language`. The original reply is kept if the rewrite fails.
+    async fn enforce_language(
+        &self,
+        call: &ReplyCall<'_>,
+        mut messages: Vec<ChatMessage>,
+        response: &mut crate::llm::ChatCompletionResponse,
+        language: &ResponseLanguage,
+    ) {
+        let Some(choice) = response.choices.first_mut() else {
+            return;
+        };
+        let is_final = choice
+            .message
+            .tool_calls
+            .as_ref()
+            .is_none_or(|calls| calls.is_empty());
+        if !is_final || !language.drifted(&choice.message.content.text()) {
+            return;
+        }
+        info!(
+            "🌐 LLM replied in the wrong language, asking for {}",
+            language.name()
+        );
+
+        messages.push(ChatMessage {
+            role: "assistant".to_string(),
+            content: choice.message.content.clone(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+        messages.push(ChatMessage {
+            role: "user".to_string(),
+            content: language.correction().into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+        let mut request = crate::llm::ChatCompletionRequest {
+            model: call.model.to_string(),
+            messages,
+            ..Default::default()
+        }
+        .with_sampling(call.sampling);
+        if let Err(e) = self
+            .run_before_llm_hooks(call.ctx, &mut request, call.input_index)
+            .await
+        {
+            warn!("Hook rejected language correction: {}", e);
+            return;
+        }
+
+        let corrected = match self.llm_client.create_chat_completion(request).await {
+            Ok(corrected) => corrected,
+            Err(e) => {
+                warn!("Language correction failed, keeping reply: {}", e);
+                return;
+            }
+        };
+        for hook in &self.hooks {
+            if let Err(e) = hook.after_llm_call(call.ctx, &corrected).await {
+                warn!("after_llm_call hook failed: {}", e);
+            }
+        }
+        match corrected.choices.into_iter().next() {
+            Some(corrected) if !corrected.message.content.text().trim().is_empty() => {
+                if language.drifted(&corrected.message.content.text()) {
+                    warn!("Corrected reply is still not in {}", language.name());
+                }
+                choice.message.content = corrected.message.content;
+            }
+            _ => warn!("Language correction came back empty, keeping reply"),
+        }
+    }
+
+    /// Re-asks the LLM, saying what was wrong, while a final reply isn't the JSON
+    /// `format` asks for, up to the configured number of retries. A valid reply is left
+    /// as its bare JSON; otherwise the problems with the last one are returned.
+    async fn enforce_response_format(
+        &self,
+        call: &ReplyCall<'_>,
+        mut messages: Vec<ChatMessage>,
+        response: &mut crate::llm::ChatCompletionResponse,
+        format: &ResponseFormat,
+    ) -> std::result::Result<(), Vec<String>> {
+        let retries = self.structured_output.retries;
+        for attempt in 0..=retries {
+            let Some(choice) = response.choices.first_mut() else {
+                return Ok(());
+            };
+            let is_final = choice
+                .message
+                .tool_calls
+                .as_ref()
+                .is_none_or(|calls| calls.is_empty());
+            if !is_final {
+                return Ok(());
+            }
+            let problems = match check_reply(&choice.message.content.text(), format) {
+                Ok(json) => {
+                    choice.message.content = json.to_string().into();
+                    return Ok(());
+                }
+                Err(problems) => problems,
+            };
+            if attempt == retries {
+                return Err(problems);
+            }
+            info!(
+                "🧩 LLM reply doesn't match the response format, retrying {}/{}",
+                attempt + 1,
+                retries
+            );
+
+            messages.push(ChatMessage {
+                role: "assistant".to_string(),
+                content: choice.message.content.clone(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+            messages.push(ChatMessage {
+                role: "user".to_string(),
+                content: structured_output::correction(&problems).into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            });
+            let mut request = crate::llm::ChatCompletionRequest {
+                model: call.model.to_string(),
+                messages: messages.clone(),
+                response_format: Some(format.clone()),
+                ..Default::default()
+            }
+            .with_sampling(call.sampling);
+            if let Err(e) = self
+                .run_before_llm_hooks(call.ctx, &mut request, call.input_index)
+                .await
+            {
+                warn!("Hook rejected response format retry: {}", e);
+                return Err(problems);
+            }
+
+            match self.llm_client.create_chat_completion(request).await {
+                Ok(retried) => {
+                    for hook in &self.hooks {
+                        if let Err(e) = hook.after_llm_call(call.ctx, &retried).await {
+                            warn!("after_llm_call hook failed: {}", e);
+                        }
+                    }
+                    *response = retried;
+                }
+                Err(e) => {
+                    warn!("Response format retry failed: {}", e);
+                    return Err(problems);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn execute_tool_with_hooks(
+        &self,
+        ctx: &HookContext,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        history: Option<&HistoryStorage>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let mut tool_call = tool_call.clone();
+        for hook in &self.hooks {
+            if let Err(e) = hook.before_tool(ctx, &mut tool_call).await {
+                warn!("Hook rejected tool '{}': {}", tool_call.name, e);
+                return crate::mcp::McpToolCallResponse {
+                    content: vec![crate::mcp::McpContent::Text {
+                        text: format!("Error: Tool call rejected: {e}"),
+                    }],
+                    is_error: true,
+                };
+            }
+        }
+
+        if let Err(reason) = self.check_approval(ctx, &tool_call).await {
+            warn!("Tool '{}' was not approved: {}", tool_call.name, reason);
+            return crate::mcp::McpToolCallResponse {
+                content: vec![crate::mcp::McpContent::Text {
+                    text: format!("Error: Tool call not approved: {reason}"),
+                }],
+                is_error: true,
+            };
+        }
+
+        let mut response = if self.tools_config.heartbeat.after_ms == 0 {
+            let tool_ctx = ToolContext::new(&ctx.session_id, history);
+            self.execute_tool_cached(&tool_call, &tool_ctx).await
+        } else {
+            let (progress, updates) = tokio::sync::mpsc::unbounded_channel();
+            let tool_ctx = ToolContext::new(&ctx.session_id, history).with_progress(progress);
+            self.watch_tool(
+                ctx,
+                &tool_call,
+                self.execute_tool_cached(&tool_call, &tool_ctx),
+                updates,
+            )
+            .await
+        };
+
+        for hook in &self.hooks {
+            if let Err(e) = hook.after_tool(ctx, &tool_call, &mut response).await {
+                warn!("after_tool hook failed for '{}': {}", tool_call.name, e);
+            }
+        }
+        response
+    }
+
+    /// Model of the run's next LLM call, and whether it is the routed tool model. The
+    /// run's own model overrides routing; without either the LLM client's is used.
+    fn turn_model(&self, settings: &RunSettings, context: &AgentContext) -> (String, bool) {
+        if let Some(model) = &settings.model {
+            return (model.clone(), false);
+        }
+        let answer_model = self.routing.answer_model.clone().unwrap_or_default();
+        match &self.routing.tool_model {
+            Some(tool_model) if !context.available_tools.is_empty() && !context.answering => {
+                (tool_model.clone(), true)
+            }
+            _ => (answer_model, false),
+        }
+    }
+
+    /// Runs `call` while sending heartbeats once it exceeds `tools.heartbeat.after_ms`.
+    /// Calls that stop reporting progress for `stall_timeout_ms`, or outlive
+    /// `timeout_ms`, are dropped, cancelling them, and fail with a stall error.
+    async fn watch_tool(
+        &self,
+        ctx: &HookContext,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        call: impl std::future::Future<Output = crate::mcp::McpToolCallResponse>,
+        mut updates: tokio::sync::mpsc::UnboundedReceiver<crate::mcp::McpProgress>,
+    ) -> crate::mcp::McpToolCallResponse {
+        use std::time::Duration;
+        use tokio::time::{Instant, sleep_until};
+
+        let config = &self.tools_config.heartbeat;
+        let start = Instant::now();
+        let heartbeats_from = start + Duration::from_millis(config.after_ms);
+        let interval = Duration::from_millis(config.interval_ms.max(1));
+        let deadline = config
+            .timeout_ms
+            .map(|ms| start + Duration::from_millis(ms));
+        let mut next_heartbeat = heartbeats_from;
+        let mut last_progress_at = start;
+        let mut progress = None;
+        tokio::pin!(call);
+
+        loop {
+            // Progress before heartbeats start doesn't shorten the stall timeout
+            let stalls_at = config
+                .stall_timeout_ms
+                .map(|ms| last_progress_at.max(heartbeats_from) + Duration::from_millis(ms));
+            tokio::select! {
+                response = &mut call => return response,
+                Some(update) = updates.recv() => {
+                    last_progress_at = Instant::now();
+                    progress = Some(update);
+                }
+                _ = sleep_until(next_heartbeat) => {
+                    let elapsed = start.elapsed();
+                    debug!("Tool '{}' still running after {:?}", tool_call.name, elapsed);
+                    for hook in &self.hooks {
+                        hook.on_tool_heartbeat(ctx, tool_call, elapsed, progress.as_ref())
+                            .await;
+                    }
+                    next_heartbeat += interval;
+                }
+                _ = sleep_until(stalls_at.unwrap_or(start)), if stalls_at.is_some() => {
+                    let quiet = last_progress_at.elapsed();
+                    warn!(
+                        "Tool '{}' reported no progress for {:?}, cancelling it",
+                        tool_call.name, quiet
+                    );
+                    return error_result(format!(
+                        "Error: Tool stalled: no progress for {}s, the call was cancelled",
+                        quiet.as_secs()
+                    ));
+                }
+                _ = sleep_until(deadline.unwrap_or(start)), if deadline.is_some() => {
+                    warn!("Tool '{}' timed out, cancelling it", tool_call.name);
+                    return error_result(format!(
+                        "Error: Tool timed out after {}s, the call was cancelled",
+                        start.elapsed().as_secs()
+                    ));
+                }
+            }
+        }
+    }
+
+    async fn execute_tool_cached(
+        &self,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        tool_ctx: &ToolContext<'_>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let key = match &self.tool_cache {
+            Some(tool_cache) if tool_cache.tools.contains(&tool_call.name) => {
+                // Sorted, and without the injected context that differs on every call
+                let arguments: std::collections::BTreeMap<_, _> = tool_call
+                    .arguments
+                    .iter()
+                    .filter(|(name, _)| name.as_str() != CONTEXT_ARGUMENT)
+                    .collect();
+                cache_key("tool", &(&tool_call.name, arguments)).ok()
+            }
+            _ => None,
+        };
+        let (Some(key), Some(tool_cache)) = (key, &self.tool_cache) else {
+            return self.execute_tool(tool_call, tool_ctx).await;
+        };
+
+        if let Some(response) = get_json(tool_cache.cache.as_ref(), &key).await {
+            debug!("Tool cache hit for '{}'", tool_call.name);
+            return response;
+        }
+        let (cache, ttl) = (tool_cache.cache.clone(), tool_cache.ttl);
+        let response = self.execute_tool(tool_call, tool_ctx).await;
+        if !response.is_error {
+            set_json(cache.as_ref(), &key, &response, ttl).await;
+        }
+        response
+    }
+
+    /// Configuration takes precedence over the native tool's own default. Groups need
+    /// approval when any of their tools does.
+    fn requires_approval(&self, tool_name: &str) -> bool {
+        if let Some(group) = self.tool_group(tool_name) {
+            return group.tools.iter().any(|tool| self.requires_approval(tool));
+        }
+        self.tools_config
+            .settings_for(tool_name)
+            .and_then(|s| s.require_approval)
+            .unwrap_or_else(|| {
+                self.native_tools
+                    .get(tool_name)
+                    .is_some_and(|tool| tool.requires_approval())
+            })
+    }
+
+    /// Asks the approval handler about tools that require approval, returning the denial reason
+    async fn check_approval(
+        &self,
+        ctx: &HookContext,
+        tool_call: &crate::mcp::McpToolCallRequest,
+    ) -> std::result::Result<(), String> {
+        if !self.requires_approval(&tool_call.name) {
+            return Ok(());
+        }
+        let Some(handler) = &self.approval_handler else {
+            return Err("approval is required but no approval handler is configured".to_string());
+        };
+
+        let request = ApprovalRequest {
+            session_id: ctx.session_id.clone(),
+            tool_name: tool_call.name.clone(),
+            arguments: tool_call.arguments.clone(),
+        };
+        match handler.approve(&request).await {
+            Ok(true) => {
+                info!("Tool '{}' approved", tool_call.name);
+                Ok(())
+            }
+            Ok(false) => Err("denied by the user".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
     pub async fn execute_mcp_tool_for_testing(
         &mut self,
         tool_call: &crate::mcp::McpToolCallRequest,
     ) -> crate::mcp::McpToolCallResponse {
-        self.execute_mcp_tool(tool_call).await
+        self.execute_tool(tool_call, &ToolContext::new("", None))
+            .await
+    }
+
+    /// Runs a tool call, fanning out calls to tool groups, and records how long it took
+    async fn execute_tool(
+        &self,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        ctx: &ToolContext<'_>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let start = std::time::Instant::now();
+        let response = match self.tool_group(&tool_call.name) {
+            Some(group) => self.execute_group(group, tool_call, ctx).await,
+            None => self.run_tool(tool_call, ctx).await,
+        };
+        self.tool_latencies.record(&tool_call.name, start.elapsed());
+        response
+    }
+
+    /// Runs a tool call with the tool's secret arguments, masking them in the result
+    async fn run_tool(
+        &self,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        ctx: &ToolContext<'_>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let Some(settings) = self
+            .tools_config
+            .settings_for(&tool_call.name)
+            .filter(|settings| !settings.secrets.is_empty())
+        else {
+            return self.dispatch_tool(tool_call, ctx).await;
+        };
+
+        let mut tool_call = tool_call.clone();
+        let secrets = match apply_secret_arguments(&mut tool_call, settings) {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                error!(
+                    "Secrets for tool '{}' are unavailable: {}",
+                    tool_call.name, e
+                );
+                return error_result(format!("Error: Tool is not configured correctly: {e}"));
+            }
+        };
+        let mut response = self.dispatch_tool(&tool_call, ctx).await;
+        redact_secrets(&mut response, &secrets);
+        response
+    }
+
+    /// Dispatches a tool call to the native tool registry or the owning MCP client
+    async fn dispatch_tool(
+        &self,
+        tool_call: &crate::mcp::McpToolCallRequest,
+        ctx: &ToolContext<'_>,
+    ) -> crate::mcp::McpToolCallResponse {
+        let Some(tool) = self.native_tools.get(&tool_call.name) else {
+            return self.execute_mcp_tool(tool_call, ctx).await;
+        };
+
+        debug!("Executing native tool: {}", tool_call.name);
+        match tool.call(tool_call.arguments.clone(), ctx).await {
+            Ok(response) => response,
+            Err(e) => {
+                error!("Native tool '{}' failed: {}", tool_call.name, e);
+                crate::mcp::McpToolCallResponse {
+                    content: vec![crate::mcp::McpContent::Text {
+                        text: format!("Error: Tool execution failed: {e}"),
+                    }],
+                    is_error: true,
+                }
+            }
+        }
     }
 
     async fn execute_mcp_tool(
-        &mut self,
+        &self,
         tool_call: &crate::mcp::McpToolCallRequest,
+        ctx: &ToolContext<'_>,
     ) -> crate::mcp::McpToolCallResponse {
         debug!("Executing MCP tool: {}", tool_call.name);
 
@@ -623,13 +2677,21 @@ impl Agent {
                     tool_call.name, client_name
                 );
 
-                match self.mcp_clients.get_mut(client_name) {
+                match self.mcp_clients.get(client_name) {
                     Some(client) => {
                         debug!(
                             "Executing tool '{}' on client '{}'",
                             tool_call.name, client_name
                         );
-                        match client.call_tool(tool_call.clone()).await {
+                        let result = match &ctx.progress {
+                            Some(progress) => {
+                                client
+                                    .call_tool_with_progress(tool_call.clone(), progress.clone())
+                                    .await
+                            }
+                            None => client.call_tool(tool_call.clone()).await,
+                        };
+                        match result {
                             Ok(response) => {
                                 debug!(
                                     "Tool '{}' executed successfully on client '{}' with {} content items",
@@ -706,9 +2768,31 @@ impl Agent {
             mcp_clients,
             available_tools,
             tool_to_client_map,
+            tool_output_schemas: HashMap::new(),
             discovered_prompts: Vec::new(),
             default_system_prompt: "You are a helpful assistant.".to_string(),
             base_system_prompt: None,
+            hooks: Vec::new(),
+            tools_config: ToolsConfig::default(),
+            native_tools: NativeToolRegistry::new(),
+            approval_handler: None,
+            max_turns: crate::config::default_max_turns(),
+            max_continuations: crate::config::default_max_continuations(),
+            max_context_tokens: None,
+            sampling: SamplingConfig::default(),
+            empty_response: EmptyResponseConfig::default(),
+            structured_output: Default::default(),
+            routing: Default::default(),
+            pacing: None,
+            retroactive_system_prompt: true,
+            tool_cache: None,
+            model: String::new(),
+            response_language: None,
+            tool_latencies: ToolLatencies::new(),
+            fanout: None,
+            confidence: None,
+            moderator: None,
+            partial_reply_interval: None,
         }
     }
 
@@ -728,3 +2812,138 @@ impl Agent {
         self.mcp_clients.remove(client_name)
     }
 }
+
+/// Metadata flag of the system prompt a session was pinned to
+const SYSTEM_PROMPT_FLAG: &str = "system_prompt";
+
+/// Marks a reply saved while it was still being streamed
+const PARTIAL_REPLY_FLAG: &str = "partial";
+
+fn is_flagged(message: &Message, flag: &str) -> bool {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get(flag))
+        .and_then(|value| value.as_bool())
+        .unwrap_or(false)
+}
+
+/// Rebuilds the conversation sent to the LLM from stored messages. Replies carrying a
+/// `turn` are preceded by the tool call and result messages that led to them, exactly
+/// as exchanged. Commentary is skipped as it is already part of those messages, and
+/// stored system messages since the system prompt is always sent up front.
+fn replay_history(messages: Vec<Message>) -> Vec<ChatMessage> {
+    let mut replayed = Vec::new();
+    for message in messages {
+        if message.role == "system"
+            || is_flagged(&message, "commentary")
+            || is_flagged(&message, PARTIAL_REPLY_FLAG)
+        {
+            continue;
+        }
+        if let Some(turn) = message.metadata.as_ref().and_then(|m| m.get("turn")) {
+            match serde_json::from_value::<Vec<ChatMessage>>(turn.clone()) {
+                Ok(turn) => replayed.extend(turn),
+                Err(e) => warn!(
+                    "Ignoring unreadable turn of message {:?}: {}",
+                    message.id, e
+                ),
+            }
+        }
+        replayed.push(ChatMessage {
+            role: message.role,
+            content: message.content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        });
+    }
+    replayed
+}
+
+/// Whether a response has neither tool calls nor any text to show the user
+fn is_empty_reply(response: &crate::llm::ChatCompletionResponse) -> bool {
+    response.choices.first().is_none_or(|choice| {
+        choice.message.content.text().trim().is_empty()
+            && choice
+                .message
+                .tool_calls
+                .as_ref()
+                .is_none_or(|calls| calls.is_empty())
+    })
+}
+
+/// Whether a response calls any tool
+fn requests_tools(response: &crate::llm::ChatCompletionResponse) -> bool {
+    response.choices.first().is_some_and(|choice| {
+        choice
+            .message
+            .tool_calls
+            .as_ref()
+            .is_some_and(|calls| !calls.is_empty())
+    })
+}
+
+/// Whether a text reply stopped because it ran out of tokens
+fn is_truncated(choice: &crate::llm::Choice) -> bool {
+    choice
+        .finish_reason
+        .as_deref()
+        .is_some_and(|reason| reason.eq_ignore_ascii_case("length"))
+        && choice
+            .message
+            .tool_calls
+            .as_ref()
+            .is_none_or(|calls| calls.is_empty())
+}
+
+/// Appends the language directive to the system prompt, adding one if there is none
+fn add_language_directive(messages: &mut Vec<ChatMessage>, language: &ResponseLanguage) {
+    match messages.first_mut() {
+        Some(system) if system.role == "system" => {
+            system.content.push_str("\n\n");
+            system.content.push_str(&language.directive());
+        }
+        _ => messages.insert(
+            0,
+            ChatMessage {
+                role: "system".to_string(),
+                content: language.directive().into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            },
+        ),
+    }
+}
+
+/// Captures a run stopped at the caller's tool calls, listing those calls
+fn paused_run(
+    session_id: &str,
+    fsm: AgentStateMachine,
+    results: Vec<Option<McpToolCallResponse>>,
+    turn_start: usize,
+    settings: RunSettings,
+) -> PausedRun {
+    let requested = fsm
+        .context
+        .messages
+        .last()
+        .and_then(|message| message.tool_calls.clone())
+        .unwrap_or_default();
+    let tool_calls = requested
+        .into_iter()
+        .zip(&results)
+        .filter(|(_, result)| result.is_none())
+        .map(|(call, _)| call)
+        .collect();
+    PausedRun {
+        session_id: session_id.to_string(),
+        paused_at: std::time::Instant::now(),
+        tool_calls,
+        fsm,
+        results,
+        turn_start,
+        settings,
+    }
+}
@@ -0,0 +1,38 @@
+/// Asks the LLM to split the user's last message into independent sub-questions
+pub fn decomposition_prompt(max_subquestions: usize) -> String {
+    format!(
+        "Split the user's last message into independent sub-questions that can each be \
+         answered on its own, at most {max_subquestions}. Reply with only a JSON array of \
+         strings. If the message is a single question, reply with []."
+    )
+}
+
+/// Reads the sub-questions from the LLM's reply to `decomposition_prompt`, keeping at
+/// most `max_subquestions`. Replies that aren't a JSON array of strings yield none.
+pub fn parse_subquestions(reply: &str, max_subquestions: usize) -> Vec<String> {
+    let (Some(start), Some(end)) = (reply.find('['), reply.rfind(']')) else {
+        return Vec::new();
+    };
+    if end < start {
+        return Vec::new();
+    }
+    let questions: Vec<String> = serde_json::from_str(&reply[start..=end]).unwrap_or_default();
+    questions
+        .into_iter()
+        .map(|question| question.trim().to_string())
+        .filter(|question| !question.is_empty())
+        .take(max_subquestions)
+        .collect()
+}
+
+/// Hands the sub-runs' answers to the LLM to combine into one reply
+pub fn findings_prompt(findings: &[(String, String)]) -> String {
+    let mut prompt = String::from(
+        "The parts of the user's last message were answered separately below. Combine them \
+         into a single reply to the user, without mentioning that it was split.",
+    );
+    for (i, (question, answer)) in findings.iter().enumerate() {
+        prompt.push_str(&format!("\n\n{}. {question}\n{answer}", i + 1));
+    }
+    prompt
+}
@@ -1,10 +1,15 @@
+use super::citations::Citation;
 use crate::{
     Error, Result,
-    llm::{ChatCompletionResponse, ChatMessage, Tool},
+    history::ToolCallRecord,
+    llm::{ChatCompletionResponse, ChatMessage, ContextFallback, Tool},
     mcp::{McpToolCallRequest, McpToolCallResponse},
 };
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use tracing::{debug, info, warn};
+use uuid::Uuid;
 
 // Agent states
 #[derive(Debug, Clone, PartialEq)]
@@ -30,6 +35,8 @@ pub enum AgentEvent {
 // Agent context (shared state)
 #[derive(Debug, Clone)]
 pub struct AgentContext {
+    /// Identifies the run in its timeline and, once paused, in its tool results URL
+    pub run_id: String,
     pub messages: Vec<ChatMessage>,
     pub available_tools: Vec<Tool>,
     pub mcp_clients: HashMap<String, String>,
@@ -40,6 +47,19 @@ pub struct AgentContext {
     pub tool_call_id_mapping: Vec<String>, // Maps MCP tool call index to original LLM tool call ID
     pub last_error: Option<String>,
     pub llm_response: Option<ChatCompletionResponse>,
+    /// Sources of the tool results seen during the run, in first-seen order
+    pub citations: Vec<Citation>,
+    /// Every tool call made during the run, for the transcript
+    pub tool_calls: Vec<ToolCallRecord>,
+    /// History row holding the reply being streamed, until it is finalized
+    pub partial_reply_id: Option<i64>,
+    /// How the latest LLM call too long for the model was answered
+    pub context_fallback: Option<ContextFallback>,
+    /// Set when the routed tool model answered instead of calling tools, so the next
+    /// call goes to the answer model
+    pub answering: bool,
+    /// Position of the run's input in `messages`; it and what follows are never trimmed
+    pub input_index: usize,
 }
 
 impl AgentContext {
@@ -48,17 +68,29 @@ impl AgentContext {
         available_tools: Vec<Tool>,
         mcp_clients: HashMap<String, String>,
     ) -> Self {
+        // Runs start with their input as the last user message
+        let input_index = initial_messages
+            .iter()
+            .rposition(|message| message.role == "user")
+            .unwrap_or(initial_messages.len());
         Self {
+            run_id: Uuid::new_v4().to_string(),
             messages: initial_messages,
             available_tools,
             mcp_clients,
             current_turn: 0,
-            max_turns: 5, // Match Go implementation
+            max_turns: crate::config::default_max_turns(),
             pending_tool_calls: Vec::new(),
             tool_call_results: Vec::new(),
             tool_call_id_mapping: Vec::new(),
             last_error: None,
             llm_response: None,
+            citations: Vec::new(),
+            tool_calls: Vec::new(),
+            partial_reply_id: None,
+            context_fallback: None,
+            answering: false,
+            input_index,
         }
     }
 
@@ -70,6 +102,14 @@ impl AgentContext {
         self.current_turn += 1;
     }
 
+    pub fn add_citations(&mut self, citations: Vec<Citation>) {
+        for citation in citations {
+            if !self.citations.contains(&citation) {
+                self.citations.push(citation);
+            }
+        }
+    }
+
     pub fn set_error(&mut self, error: String) {
         self.last_error = Some(error);
     }
@@ -101,10 +141,43 @@ impl AgentContext {
     }
 }
 
+/// Transitions kept for diagnosing a stuck run
+const TRANSITION_TRAIL: usize = 8;
+
+/// Snapshot of a run's state machine when the watchdog stopped it, carried by the
+/// error and the run's `run_failed` event
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FsmDiagnostics {
+    /// Why the watchdog stopped the run
+    pub reason: String,
+    /// State the run was in
+    pub state: String,
+    /// Event that led to `state`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_event: Option<String>,
+    /// Iterations of the run loop so far
+    pub iterations: usize,
+    pub turn: usize,
+    pub max_turns: usize,
+    /// Latest transitions as `From --Event--> To`, oldest first
+    pub transitions: Vec<String>,
+    /// Messages in the conversation sent to the LLM
+    pub messages: usize,
+    /// Tools the run was about to call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pending_tool_calls: Vec<String>,
+    /// Whether an LLM response was waiting to be handled
+    pub has_llm_response: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
 // Simple FSM implementation
 pub struct AgentStateMachine {
     state: AgentState,
     pub context: AgentContext,
+    /// Latest transitions, oldest first
+    trail: VecDeque<(AgentState, AgentEvent, AgentState)>,
 }
 
 impl AgentStateMachine {
@@ -122,6 +195,7 @@ impl AgentStateMachine {
         Self {
             state: AgentState::ReadyToCallLlm,
             context: AgentContext::new(initial_messages, available_tools, mcp_clients),
+            trail: VecDeque::new(),
         }
     }
 
@@ -177,10 +251,41 @@ impl AgentStateMachine {
             );
         }
 
+        if self.trail.len() == TRANSITION_TRAIL {
+            self.trail.pop_front();
+        }
+        self.trail.push_back((old_state, event, new_state.clone()));
         self.state = new_state;
         Ok(())
     }
 
+    /// What the machine looks like, for an error stopping it after `iterations` of the
+    /// run loop
+    pub fn diagnostics(&self, reason: impl Into<String>, iterations: usize) -> FsmDiagnostics {
+        FsmDiagnostics {
+            reason: reason.into(),
+            state: format!("{:?}", self.state),
+            last_event: self.trail.back().map(|(_, event, _)| format!("{event:?}")),
+            iterations,
+            turn: self.context.current_turn,
+            max_turns: self.context.max_turns,
+            transitions: self
+                .trail
+                .iter()
+                .map(|(from, event, to)| format!("{from:?} --{event:?}--> {to:?}"))
+                .collect(),
+            messages: self.context.messages.len(),
+            pending_tool_calls: self
+                .context
+                .pending_tool_calls
+                .iter()
+                .map(|call| call.name.clone())
+                .collect(),
+            has_llm_response: self.context.llm_response.is_some(),
+            last_error: self.context.last_error.clone(),
+        }
+    }
+
     pub fn is_terminal(&self) -> bool {
         matches!(self.state, AgentState::Done | AgentState::Error)
     }
@@ -205,11 +310,11 @@ impl AgentStateMachine {
         self.context.tool_call_results.extend(results);
     }
 
-    pub fn get_final_content(&self) -> &str {
+    pub fn get_final_content(&self) -> Cow<'_, str> {
         if let Some(last_message) = self.context.messages.last() {
-            &last_message.content
+            last_message.content.text()
         } else {
-            ""
+            Cow::Borrowed("")
         }
     }
 
@@ -0,0 +1,122 @@
+use super::fsm::{AgentEvent, AgentState};
+use crate::{
+    Result,
+    llm::{ChatCompletionRequest, ChatCompletionResponse},
+    mcp::{McpProgress, McpToolCallRequest, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use std::time::Duration;
+
+/// Information about the run a hook is being invoked for
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub session_id: String,
+    pub turn: usize,
+    /// Set when a request is only being assembled for inspection and won't be sent
+    pub preview: bool,
+    /// Run the hook is invoked for; unset outside of runs, such as in previews
+    pub run_id: Option<String>,
+}
+
+impl HookContext {
+    pub fn new(session_id: impl Into<String>, turn: usize) -> Self {
+        Self {
+            session_id: session_id.into(),
+            turn,
+            preview: false,
+            run_id: None,
+        }
+    }
+
+    /// Context for a hook invoked during the run `run_id`
+    pub fn for_run(session_id: impl Into<String>, run_id: impl Into<String>, turn: usize) -> Self {
+        Self {
+            run_id: Some(run_id.into()),
+            ..Self::new(session_id, turn)
+        }
+    }
+
+    /// Context for previewing the first request of a run
+    pub fn preview(session_id: impl Into<String>) -> Self {
+        Self {
+            preview: true,
+            ..Self::new(session_id, 0)
+        }
+    }
+}
+
+/// Callbacks invoked at each stage of an agent run.
+///
+/// Every method has a no-op default so implementors only override the stages they
+/// care about. Hooks run in registration order.
+#[async_trait]
+pub trait AgentHook: Send + Sync {
+    /// Called once per request, before anything is persisted. Returning an error aborts the
+    /// run, which then completes with that error.
+    async fn on_request(&self, _ctx: &HookContext, _input: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called before every LLM call. The request may be modified in place; returning an
+    /// error fails the run.
+    async fn before_llm_call(
+        &self,
+        _ctx: &HookContext,
+        _request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after every successful LLM call.
+    async fn after_llm_call(
+        &self,
+        _ctx: &HookContext,
+        _response: &ChatCompletionResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the LLM returns text alongside tool calls (e.g. "Let me check that"),
+    /// after it has been saved to history and before the tools run.
+    async fn on_commentary(&self, _ctx: &HookContext, _content: &str) {}
+
+    /// Called before every tool call. The arguments may be modified in place; returning an
+    /// error skips the tool and reports the error to the LLM as the tool result.
+    async fn before_tool(&self, _ctx: &HookContext, _call: &mut McpToolCallRequest) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after every tool call, including failed ones. The response may be modified in place.
+    async fn after_tool(
+        &self,
+        _ctx: &HookContext,
+        _call: &McpToolCallRequest,
+        _response: &mut McpToolCallResponse,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called periodically while a tool call runs longer than `tools.heartbeat.after_ms`,
+    /// with how long it has run and the latest progress it reported.
+    async fn on_tool_heartbeat(
+        &self,
+        _ctx: &HookContext,
+        _call: &McpToolCallRequest,
+        _elapsed: Duration,
+        _progress: Option<&McpProgress>,
+    ) {
+    }
+
+    /// Called after every state change of the run, with the event that caused it.
+    async fn on_transition(
+        &self,
+        _ctx: &HookContext,
+        _from: &AgentState,
+        _to: &AgentState,
+        _event: &AgentEvent,
+    ) {
+    }
+
+    /// Called once the run has finished, with its final output or error.
+    async fn on_complete(&self, _ctx: &HookContext, _result: &Result<String>) {}
+}
@@ -0,0 +1,105 @@
+use whatlang::Lang;
+
+/// Replies shorter than this aren't checked, since detection on a few words is a guess
+const MIN_DETECTION_CHARS: usize = 24;
+
+/// ISO 639-1 codes of the detectable languages people are most likely to configure
+const TWO_LETTER_CODES: &[(&str, Lang)] = &[
+    ("ar", Lang::Ara),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("es", Lang::Spa),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hu", Lang::Hun),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("ko", Lang::Kor),
+    ("nb", Lang::Nob),
+    ("nl", Lang::Nld),
+    ("no", Lang::Nob),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("sv", Lang::Swe),
+    ("th", Lang::Tha),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("vi", Lang::Vie),
+    ("zh", Lang::Cmn),
+];
+
+/// A language replies should be written in, as configured
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseLanguage {
+    /// Name used in instructions to the model
+    name: String,
+    /// Detectable language, when the configured one is known
+    lang: Option<Lang>,
+}
+
+impl ResponseLanguage {
+    /// Accepts an English name (`Portuguese`), an ISO 639-3 code (`por`) or an ISO 639-1
+    /// code with an optional region (`pt-BR`). Other values are passed to the model as
+    /// given, without checking replies.
+    pub fn parse(language: &str) -> Self {
+        let language = language.trim();
+        let lower = language.to_lowercase();
+        let base = lower.split(['-', '_']).next().unwrap_or(&lower);
+        let lang = Lang::all()
+            .iter()
+            .copied()
+            .find(|lang| lang.eng_name().to_lowercase() == lower)
+            .or_else(|| Lang::from_code(base))
+            .or_else(|| {
+                TWO_LETTER_CODES
+                    .iter()
+                    .find(|(code, _)| *code == base)
+                    .map(|(_, lang)| *lang)
+            });
+        Self {
+            name: lang.map_or_else(|| language.to_string(), |lang| lang.eng_name().to_string()),
+            lang,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// System prompt addition asking for replies in this language
+    pub fn directive(&self) -> String {
+        format!(
+            "Always reply in {}, whatever language the user or tool results use.",
+            self.name
+        )
+    }
+
+    /// Sent when a reply came back in another language
+    pub fn correction(&self) -> String {
+        format!(
+            "Your last reply was not in {}. Rewrite it in {}, keeping its content and formatting, and reply with the rewritten text only.",
+            self.name, self.name
+        )
+    }
+
+    /// Whether `text` is confidently in a different language. Short or ambiguous text,
+    /// and languages that can't be detected, never count as drifted.
+    pub fn drifted(&self, text: &str) -> bool {
+        let Some(expected) = self.lang else {
+            return false;
+        };
+        if text.trim().chars().count() < MIN_DETECTION_CHARS {
+            return false;
+        }
+        whatlang::detect(text).is_some_and(|info| info.is_reliable() && info.lang() != expected)
+    }
+}
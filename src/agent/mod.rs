@@ -1,5 +1,38 @@
+pub mod approval;
+mod citations;
+mod confidence;
+mod context_window;
 mod executor;
+mod fanout;
 pub mod fsm;
+pub mod hooks;
+mod language;
+mod replay;
+mod runs;
+mod structured_output;
+mod tool_arguments;
+mod tool_context;
+mod tool_groups;
+mod tool_hints;
+mod tool_output;
 
-pub use executor::Agent;
-pub use fsm::{AgentContext, AgentEvent, AgentState, AgentStateMachine};
+pub use approval::{ApprovalHandler, ApprovalRequest};
+pub use citations::{AgentReply, Citation, citations_from_tool_result};
+pub use confidence::{Escalation, JUDGE_PROMPT, parse_confidence};
+pub use context_window::trim_to_budget;
+pub use executor::{Agent, ProcessOptions};
+pub use fanout::parse_subquestions;
+pub use fsm::{AgentContext, AgentEvent, AgentState, AgentStateMachine, FsmDiagnostics};
+pub use hooks::{AgentHook, HookContext};
+pub use language::ResponseLanguage;
+pub use replay::{ReplayReport, ReplayTurn, replay_session};
+pub use runs::{PAUSED_RUN_TTL, PausedRun, PausedRuns, RunOutcome, ToolMode};
+pub use structured_output::{check_reply, schema_violations};
+pub use tool_arguments::{
+    apply_secret_arguments, apply_static_arguments, hide_injected_arguments, redact_secrets,
+    transform_arguments,
+};
+pub use tool_context::{CONTEXT_ARGUMENT, build_tool_context};
+pub use tool_groups::merge_group_results;
+pub use tool_hints::{ToolLatencies, describe_with_hints};
+pub use tool_output::{render_tool_result, tool_images};
@@ -0,0 +1,228 @@
+use super::{executor::Agent, tool_context::CONTEXT_ARGUMENT};
+use crate::{
+    Result,
+    history::{HistoryStorage, Message, ToolCallRecord},
+    mcp::{McpContent, McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, error_result},
+};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+
+/// Outcome of re-running one stored user turn
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayTurn {
+    /// 1-based position among the session's user messages
+    pub turn: usize,
+    pub input: String,
+    /// Stored reply, if the original run completed
+    pub recorded_output: Option<String>,
+    pub replayed_output: Option<String>,
+    /// Why the replay failed, if it did
+    pub error: Option<String>,
+    pub recorded_tools: Vec<String>,
+    pub replayed_tools: Vec<String>,
+    pub output_matches: bool,
+    pub tools_match: bool,
+}
+
+impl ReplayTurn {
+    pub fn diverged(&self) -> bool {
+        !(self.output_matches && self.tools_match)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayReport {
+    pub session_id: String,
+    pub turns: Vec<ReplayTurn>,
+}
+
+impl ReplayReport {
+    /// The first turn whose output or tool calls differ from the recording
+    pub fn first_divergence(&self) -> Option<&ReplayTurn> {
+        self.turns.iter().find(|turn| turn.diverged())
+    }
+
+    pub fn diverged_count(&self) -> usize {
+        self.turns.iter().filter(|turn| turn.diverged()).count()
+    }
+}
+
+/// Stands in for a real tool during replay, answering with the results recorded for
+/// the turn being replayed so nothing runs for real
+struct RecordedTool {
+    definition: McpTool,
+    records: Arc<Mutex<Vec<ToolCallRecord>>>,
+}
+
+#[async_trait]
+impl NativeTool for RecordedTool {
+    fn definition(&self) -> McpTool {
+        self.definition.clone()
+    }
+
+    async fn call(
+        &self,
+        mut arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        arguments.remove(CONTEXT_ARGUMENT);
+        let arguments = serde_json::to_value(arguments)?;
+        let name = &self.definition.name;
+
+        let mut records = self.records.lock().unwrap();
+        // Prefer the call made with the same arguments, then any call of the same tool
+        let position = records
+            .iter()
+            .position(|r| &r.name == name && r.arguments == arguments)
+            .or_else(|| records.iter().position(|r| &r.name == name));
+        Ok(match position {
+            Some(position) => {
+                let record = records.remove(position);
+                McpToolCallResponse {
+                    content: vec![McpContent::Text {
+                        text: record.result,
+                    }],
+                    is_error: record.is_error,
+                }
+            }
+            None => error_result(format!("No recorded result for '{name}' in this turn")),
+        })
+    }
+}
+
+fn tool_calls_of(message: &Message) -> Vec<ToolCallRecord> {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("tool_calls"))
+        .and_then(|calls| serde_json::from_value(calls.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn is_commentary(message: &Message) -> bool {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get("commentary"))
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn call_signature(calls: &[ToolCallRecord]) -> Vec<(String, Value)> {
+    calls
+        .iter()
+        .map(|call| (call.name.clone(), call.arguments.clone()))
+        .collect()
+}
+
+/// Re-runs every user turn of a stored session with `agent`, comparing replies and tool
+/// calls with the recording. Each turn starts from the recorded conversation before it,
+/// so one divergence doesn't spill into later turns. Every tool the agent knows, and
+/// every recorded one, is replaced by a stand-in serving the recorded results; calls
+/// without a recording fail. The agent's tools stay replaced afterwards.
+pub async fn replay_session(
+    agent: &mut Agent,
+    history: &HistoryStorage,
+    session_id: &str,
+) -> Result<ReplayReport> {
+    let messages = history.list(session_id).await?;
+    let records = Arc::new(Mutex::new(Vec::new()));
+
+    let mut tool_names: BTreeSet<String> = messages
+        .iter()
+        .flat_map(tool_calls_of)
+        .map(|call| call.name)
+        .collect();
+    let known: HashMap<String, McpTool> = agent
+        .get_available_tools()
+        .iter()
+        .map(|tool| {
+            (
+                tool.function.name.clone(),
+                McpTool {
+                    name: tool.function.name.clone(),
+                    description: tool.function.description.clone(),
+                    input_schema: tool.function.parameters.clone(),
+                    output_schema: None,
+                },
+            )
+        })
+        .collect();
+    tool_names.extend(known.keys().cloned());
+    for name in tool_names {
+        let definition = known.get(&name).cloned().unwrap_or_else(|| McpTool {
+            name: name.clone(),
+            description: "Replays recorded results".to_string(),
+            input_schema: serde_json::json!({"type": "object"}),
+            output_schema: None,
+        });
+        agent.register_native_tool(Arc::new(RecordedTool {
+            definition,
+            records: records.clone(),
+        }));
+    }
+
+    let mut turns = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        if message.role != "user" {
+            continue;
+        }
+        let recorded = messages[index + 1..]
+            .iter()
+            .take_while(|m| m.role != "user")
+            .find(|m| m.role == "assistant" && !is_commentary(m));
+        let recorded_calls = recorded.map(tool_calls_of).unwrap_or_default();
+        *records.lock().unwrap() = recorded_calls.clone();
+
+        // Start from the conversation as it was recorded up to this turn
+        let scratch = HistoryStorage::new(":memory:").await?;
+        for earlier in &messages[..index] {
+            scratch.save(earlier.clone()).await?;
+        }
+        info!(
+            "Replaying turn {} of session {}",
+            turns.len() + 1,
+            session_id
+        );
+        let result = agent
+            .process_with_citations(session_id, &message.content, &scratch)
+            .await;
+
+        let replayed_calls = scratch
+            .list(session_id)
+            .await?
+            .last()
+            .filter(|m| m.role == "assistant")
+            .map(tool_calls_of)
+            .unwrap_or_default();
+        let (replayed_output, error) = match result {
+            Ok(reply) => (Some(reply.output), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let recorded_output = recorded.map(|m| m.content.clone());
+        turns.push(ReplayTurn {
+            turn: turns.len() + 1,
+            input: message.content.clone(),
+            output_matches: recorded_output.as_deref().map(str::trim)
+                == replayed_output.as_deref().map(str::trim),
+            tools_match: call_signature(&recorded_calls) == call_signature(&replayed_calls),
+            recorded_tools: recorded_calls.into_iter().map(|c| c.name).collect(),
+            replayed_tools: replayed_calls.into_iter().map(|c| c.name).collect(),
+            recorded_output,
+            replayed_output,
+            error,
+        });
+    }
+
+    Ok(ReplayReport {
+        session_id: session_id.to_string(),
+        turns,
+    })
+}
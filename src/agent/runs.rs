@@ -0,0 +1,158 @@
+use super::{fsm::AgentStateMachine, language::ResponseLanguage};
+use crate::{
+    Error, Result,
+    config::SamplingConfig,
+    llm::{ResponseFormat, ToolCall, ToolChoice},
+    mcp::McpToolCallResponse,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+use tokio::sync::Mutex;
+
+/// Paused runs nobody resumed within this long are dropped
+pub const PAUSED_RUN_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Who runs the tool calls the LLM makes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolMode {
+    /// The agent runs its own tools; only calls to caller-supplied tools are handed back
+    #[default]
+    Auto,
+    /// Every tool call is handed back to the caller
+    Manual,
+}
+
+/// A run stopped at tool calls the caller executes itself, resumed once their results
+/// are in
+pub struct PausedRun {
+    pub session_id: String,
+    /// When the run stopped; it is dropped `PAUSED_RUN_TTL` later unless resumed
+    pub paused_at: Instant,
+    /// Calls awaiting results from the caller, in the order the LLM made them
+    pub tool_calls: Vec<ToolCall>,
+    pub(crate) fsm: AgentStateMachine,
+    /// Results of the turn's calls by position; `None` for the caller's calls
+    pub(crate) results: Vec<Option<McpToolCallResponse>>,
+    /// Index of the first message of the turn in the FSM's conversation
+    pub(crate) turn_start: usize,
+    pub(crate) settings: RunSettings,
+}
+
+impl PausedRun {
+    pub fn run_id(&self) -> &str {
+        &self.fsm.context.run_id
+    }
+
+    /// Model the run answers with, when its request picked one
+    pub fn model(&self) -> Option<&str> {
+        self.settings.model.as_deref()
+    }
+
+    /// Checks that `ids` answer every pending call exactly once, and nothing else
+    pub fn check_results<'a>(&self, ids: impl IntoIterator<Item = &'a str>) -> Result<()> {
+        let mut answered: Vec<&str> = Vec::new();
+        let mut problems = Vec::new();
+        for id in ids {
+            if answered.contains(&id) {
+                problems.push(format!("duplicate result for '{id}'"));
+            } else if !self.tool_calls.iter().any(|call| call.id == id) {
+                problems.push(format!("no pending tool call '{id}'"));
+            }
+            answered.push(id);
+        }
+        let missing: Vec<&str> = self
+            .tool_calls
+            .iter()
+            .map(|call| call.id.as_str())
+            .filter(|id| !answered.contains(id))
+            .collect();
+        if !missing.is_empty() {
+            problems.push(format!("missing results for {}", missing.join(", ")));
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::tool(format!(
+                "Invalid tool results: {}",
+                problems.join("; ")
+            )))
+        }
+    }
+}
+
+/// Per-run settings resolved from the request options
+pub(crate) struct RunSettings {
+    pub language: Option<ResponseLanguage>,
+    pub tool_mode: ToolMode,
+    /// Names of the caller-supplied tools
+    pub client_tools: Vec<String>,
+    /// Whether replies are saved to the history while they are streamed
+    pub partial_replies: bool,
+    /// Model the run's LLM calls use, instead of the LLM client's
+    pub model: Option<String>,
+    /// Sampling parameters of the run's replies
+    pub sampling: SamplingConfig,
+    /// JSON the final reply must be
+    pub response_format: Option<ResponseFormat>,
+    /// Whether and which tools the run's first LLM call must call
+    pub tool_choice: Option<ToolChoice>,
+}
+
+impl RunSettings {
+    /// Whether calls to `tool` are handed back to the caller
+    pub fn runs_on_caller(&self, tool: &str) -> bool {
+        self.tool_mode == ToolMode::Manual || self.client_tools.iter().any(|name| name == tool)
+    }
+}
+
+/// What a run ended with
+pub enum RunOutcome {
+    Reply(super::AgentReply),
+    /// Waiting for the caller to run tools
+    Paused(Box<PausedRun>),
+}
+
+/// Runs waiting for tool results, by run id
+#[derive(Default)]
+pub struct PausedRuns {
+    runs: Mutex<HashMap<String, PausedRun>>,
+}
+
+impl PausedRuns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps `run` until it is resumed, returning its id. Expired runs are dropped.
+    pub async fn insert(&self, run: PausedRun) -> String {
+        let id = run.run_id().to_string();
+        let mut runs = self.runs.lock().await;
+        runs.retain(|_, run| run.paused_at.elapsed() < PAUSED_RUN_TTL);
+        runs.insert(id.clone(), run);
+        id
+    }
+
+    /// Removes and returns the run with `id`, if it is still waiting
+    pub async fn take(&self, id: &str) -> Option<PausedRun> {
+        let run = self.runs.lock().await.remove(id)?;
+        (run.paused_at.elapsed() < PAUSED_RUN_TTL).then_some(run)
+    }
+
+    /// Puts back a run taken with `take`, under the same id. It still expires
+    /// `PAUSED_RUN_TTL` after it paused.
+    pub async fn restore(&self, id: &str, run: PausedRun) {
+        self.runs.lock().await.insert(id.to_string(), run);
+    }
+
+    pub async fn len(&self) -> usize {
+        self.runs.lock().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
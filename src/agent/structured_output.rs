@@ -0,0 +1,287 @@
+//! Checks of replies against the JSON `response_format` a request asked for.
+
+use crate::llm::ResponseFormat;
+use regex::Regex;
+use serde_json::Value;
+
+/// How deep `$ref`s are followed before giving up on a schema that refers to itself
+const MAX_REF_DEPTH: usize = 32;
+
+/// The JSON text of `reply` when it has the requested format, or what is wrong with
+/// it. Replies wrapped in a Markdown code block are unwrapped.
+pub fn check_reply<'a>(reply: &'a str, format: &ResponseFormat) -> Result<&'a str, Vec<String>> {
+    let text = unfence(reply);
+    let json: Value = serde_json::from_str(text)
+        .map_err(|e| vec![format!("the reply is not valid JSON: {e}")])?;
+    let problems = match format.schema() {
+        Some(schema) => schema_violations(schema, &json),
+        None if json.is_object() => Vec::new(),
+        None => vec!["the reply is not a JSON object".to_string()],
+    };
+    if problems.is_empty() {
+        Ok(text)
+    } else {
+        Err(problems)
+    }
+}
+
+/// Sent when a reply didn't have the requested format
+pub fn correction(problems: &[String]) -> String {
+    format!(
+        "Your last reply does not have the required JSON format:\n- {}\nReply again with the corrected JSON only.",
+        problems.join("\n- ")
+    )
+}
+
+/// The code inside a reply that is a single Markdown code block
+fn unfence(reply: &str) -> &str {
+    let trimmed = reply.trim();
+    let Some(body) = trimmed
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+    else {
+        return trimmed;
+    };
+    // The opening fence may name a language
+    body.split_once('\n').map_or(body, |(_, code)| code).trim()
+}
+
+/// Where `value` breaks `schema`, each prefixed with its path such as `$.items[2].name`.
+/// Covers the keywords structured outputs use: `type`, `enum`, `const`, `properties`,
+/// `required`, `additionalProperties`, `items`, `allOf`, `anyOf`, `oneOf`, local `$ref`s,
+/// lengths, sizes, `pattern` and numeric bounds. Other keywords are ignored.
+pub fn schema_violations(schema: &Value, value: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    Validator { root: schema }.check(schema, value, "$", &mut problems);
+    problems
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl<'a> Validator<'a> {
+    /// Follows `$ref`s such as `#/$defs/Item` within the root schema
+    fn resolve(&self, mut schema: &'a Value) -> &'a Value {
+        for _ in 0..MAX_REF_DEPTH {
+            let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+                break;
+            };
+            let target = match reference.strip_prefix('#') {
+                Some("") => Some(self.root),
+                Some(pointer) => self.root.pointer(pointer),
+                None => None,
+            };
+            match target {
+                Some(target) => schema = target,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn check(&self, schema: &'a Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+        let schema = self.resolve(schema);
+        if schema == &Value::Bool(false) {
+            problems.push(format!("{path}: is not allowed"));
+            return;
+        }
+
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                other => other.as_str().into_iter().collect(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| has_type(value, t)) {
+                problems.push(format!(
+                    "{path}: expected {}, got {}",
+                    allowed.join(" or "),
+                    type_name(value)
+                ));
+                return;
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array)
+            && !options.contains(value)
+        {
+            problems.push(format!(
+                "{path}: must be one of {}",
+                Value::Array(options.clone())
+            ));
+        }
+        if let Some(expected) = schema.get("const")
+            && expected != value
+        {
+            problems.push(format!("{path}: must be {expected}"));
+        }
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for part in all {
+                self.check(part, value, path, problems);
+            }
+        }
+        for key in ["anyOf", "oneOf"] {
+            let Some(variants) = schema.get(key).and_then(Value::as_array) else {
+                continue;
+            };
+            let matching = variants
+                .iter()
+                .filter(|variant| {
+                    let mut found = Vec::new();
+                    self.check(variant, value, path, &mut found);
+                    found.is_empty()
+                })
+                .count();
+            if matching == 0 {
+                problems.push(format!("{path}: matches none of the allowed shapes"));
+            } else if key == "oneOf" && matching > 1 {
+                problems.push(format!(
+                    "{path}: matches more than one of the allowed shapes"
+                ));
+            }
+        }
+
+        match value {
+            Value::Object(fields) => self.check_object(schema, fields, path, problems),
+            Value::Array(items) => {
+                check_size(
+                    schema,
+                    items.len(),
+                    "minItems",
+                    "maxItems",
+                    "items",
+                    path,
+                    problems,
+                );
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{path}[{index}]"), problems);
+                    }
+                }
+            }
+            Value::String(text) => {
+                let length = text.chars().count();
+                check_size(
+                    schema,
+                    length,
+                    "minLength",
+                    "maxLength",
+                    "characters",
+                    path,
+                    problems,
+                );
+                if let Some(pattern) = schema.get("pattern").and_then(Value::as_str)
+                    && let Ok(regex) = Regex::new(pattern)
+                    && !regex.is_match(text)
+                {
+                    problems.push(format!("{path}: does not match the pattern {pattern}"));
+                }
+            }
+            Value::Number(number) => {
+                if let Some(n) = number.as_f64() {
+                    check_bounds(schema, n, path, problems);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &'a Value,
+        fields: &serde_json::Map<String, Value>,
+        path: &str,
+        problems: &mut Vec<String>,
+    ) {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    problems.push(format!("{path}: missing required property {name}"));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, field) in fields {
+            let field_path = format!("{path}.{name}");
+            match (
+                properties.and_then(|p| p.get(name)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(property), _) => self.check(property, field, &field_path, problems),
+                (None, Some(Value::Bool(false))) => {
+                    problems.push(format!("{field_path}: is not an allowed property"))
+                }
+                (None, Some(additional)) => self.check(additional, field, &field_path, problems),
+                (None, None) => {}
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match (expected, value) {
+        ("integer", Value::Number(n)) => {
+            n.is_i64() || n.is_u64() || n.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        (expected, value) => {
+            expected == type_name(value) || (expected == "number" && value.is_number())
+        }
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Checks a count against the `min` and `max` keywords of `schema`
+fn check_size(
+    schema: &Value,
+    count: usize,
+    min: &str,
+    max: &str,
+    unit: &str,
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    if let Some(min) = schema.get(min).and_then(Value::as_u64)
+        && (count as u64) < min
+    {
+        problems.push(format!("{path}: has {count} {unit}, fewer than {min}"));
+    }
+    if let Some(max) = schema.get(max).and_then(Value::as_u64)
+        && (count as u64) > max
+    {
+        problems.push(format!("{path}: has {count} {unit}, more than {max}"));
+    }
+}
+
+fn check_bounds(schema: &Value, n: f64, path: &str, problems: &mut Vec<String>) {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    if let Some(min) = bound("minimum")
+        && n < min
+    {
+        problems.push(format!("{path}: must be at least {min}"));
+    }
+    if let Some(max) = bound("maximum")
+        && n > max
+    {
+        problems.push(format!("{path}: must be at most {max}"));
+    }
+    if let Some(min) = bound("exclusiveMinimum")
+        && n <= min
+    {
+        problems.push(format!("{path}: must be greater than {min}"));
+    }
+    if let Some(max) = bound("exclusiveMaximum")
+        && n >= max
+    {
+        problems.push(format!("{path}: must be less than {max}"));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    Result,
+    config::{ArgumentTransform, ArgumentType, ToolSettings},
+    llm::Tool,
+    mcp::{McpContent, McpToolCallRequest, McpToolCallResponse},
+};
+use serde_json::Value;
+
+/// Replaces secret values found in tool output
+const REDACTED: &str = "[redacted]";
+
+/// Removes the arguments the agent fills in itself (static and secret ones) from the
+/// schema advertised to the LLM, so it neither sees nor fills them
+pub fn hide_injected_arguments(tool: &mut Tool, settings: &ToolSettings) {
+    let hidden: Vec<&String> = settings.injected_arguments().collect();
+    if hidden.is_empty() {
+        return;
+    }
+    let parameters = &mut tool.function.parameters;
+    if let Some(properties) = parameters
+        .get_mut("properties")
+        .and_then(Value::as_object_mut)
+    {
+        properties.retain(|name, _| !hidden.contains(&name));
+    }
+    if let Some(required) = parameters.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|name| {
+            name.as_str()
+                .is_none_or(|name| !hidden.iter().any(|hidden| *hidden == name))
+        });
+    }
+}
+
+/// Applies a tool's argument fixes to a call, in `ArgumentTransform`'s order. Values that
+/// can't be converted to the configured type are left for the tool to reject.
+pub fn transform_arguments(call: &mut McpToolCallRequest, transform: &ArgumentTransform) {
+    for (from, to) in &transform.rename {
+        if let Some(value) = call.arguments.remove(from) {
+            call.arguments.insert(to.clone(), value);
+        }
+    }
+    for name in &transform.drop {
+        call.arguments.remove(name);
+    }
+    for (name, argument_type) in &transform.coerce {
+        if let Some(value) = call.arguments.get_mut(name)
+            && let Some(coerced) = coerce(value, *argument_type)
+        {
+            *value = coerced;
+        }
+    }
+    for (name, value) in &transform.set {
+        call.arguments.insert(name.clone(), value.clone());
+    }
+}
+
+/// Converts `value` to `argument_type`, or `None` if it already has it or can't be converted
+fn coerce(value: &Value, argument_type: ArgumentType) -> Option<Value> {
+    match (argument_type, value) {
+        (ArgumentType::String, Value::String(_)) => None,
+        (ArgumentType::String, value) => Some(Value::String(value.to_string())),
+        (ArgumentType::Integer, Value::String(text)) => {
+            text.trim().parse::<i64>().ok().map(Value::from)
+        }
+        (ArgumentType::Integer, Value::Number(number)) if !number.is_i64() => number
+            .as_f64()
+            .filter(|n| n.fract() == 0.0 && n.abs() < i64::MAX as f64)
+            .map(|n| Value::from(n as i64)),
+        (ArgumentType::Number, Value::String(text)) => {
+            text.trim().parse::<f64>().ok().map(Value::from)
+        }
+        (ArgumentType::Boolean, Value::String(text)) => match text.trim().to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+            "false" | "no" | "off" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        (ArgumentType::Boolean, Value::Number(number)) => match number.as_i64() {
+            Some(0) => Some(Value::Bool(false)),
+            Some(1) => Some(Value::Bool(true)),
+            _ => None,
+        },
+        (ArgumentType::Array, Value::Array(_)) => None,
+        (ArgumentType::Array, Value::String(text)) => Some(
+            serde_json::from_str::<Value>(text)
+                .ok()
+                .filter(Value::is_array)
+                .unwrap_or_else(|| Value::Array(vec![value.clone()])),
+        ),
+        (ArgumentType::Array, value) => Some(Value::Array(vec![value.clone()])),
+        (ArgumentType::Object, Value::String(text)) => serde_json::from_str::<Value>(text)
+            .ok()
+            .filter(Value::is_object),
+        _ => None,
+    }
+}
+
+/// Sets the tool's static arguments on a call, replacing any value the LLM sent
+pub fn apply_static_arguments(call: &mut McpToolCallRequest, settings: &ToolSettings) {
+    for (name, value) in &settings.arguments {
+        call.arguments.insert(name.clone(), value.clone());
+    }
+}
+
+/// Sets the tool's secret arguments on a call, returning the values to mask in its
+/// output. Fails without changing the call if a secret can't be resolved.
+pub fn apply_secret_arguments(
+    call: &mut McpToolCallRequest,
+    settings: &ToolSettings,
+) -> Result<Vec<String>> {
+    let secrets = settings
+        .secrets
+        .iter()
+        .map(|(name, source)| Ok((name, source.resolve()?)))
+        .collect::<Result<Vec<_>>>()?;
+    let mut values = Vec::with_capacity(secrets.len());
+    for (name, value) in secrets {
+        call.arguments
+            .insert(name.clone(), Value::String(value.clone()));
+        values.push(value);
+    }
+    Ok(values)
+}
+
+/// Masks `secrets` in a tool's text output, in case the tool echoes them back
+pub fn redact_secrets(response: &mut McpToolCallResponse, secrets: &[String]) {
+    let redact = |text: &mut String| {
+        for secret in secrets.iter().filter(|secret| !secret.is_empty()) {
+            if text.contains(secret.as_str()) {
+                *text = text.replace(secret.as_str(), REDACTED);
+            }
+        }
+    };
+    for content in &mut response.content {
+        match content {
+            McpContent::Text { text } => redact(text),
+            McpContent::Resource { resource } => {
+                if let Some(text) = &mut resource.text {
+                    redact(text);
+                }
+            }
+            McpContent::Image { .. } => {}
+        }
+    }
+}
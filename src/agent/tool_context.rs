@@ -0,0 +1,62 @@
+use crate::{
+    config::{ToolContextMode, ToolSettings},
+    llm::ChatMessage,
+};
+use serde_json::{Value, json};
+
+/// Name of the reserved argument carrying conversation context to opted-in tools
+pub const CONTEXT_ARGUMENT: &str = "_context";
+
+/// Maximum characters kept from each transcript message
+const MAX_MESSAGE_CHARS: usize = 2000;
+
+/// Builds the `_context` argument for a tool call from the current conversation.
+///
+/// Returns `None` when the tool has not opted in to receiving context.
+pub fn build_tool_context(settings: &ToolSettings, messages: &[ChatMessage]) -> Option<Value> {
+    if settings.context == ToolContextMode::None {
+        return None;
+    }
+
+    let question = messages
+        .iter()
+        .rev()
+        .find(|m| m.role == "user")
+        .map(|m| m.content.clone())
+        .unwrap_or_default();
+
+    match settings.context {
+        ToolContextMode::None => None,
+        ToolContextMode::Question => Some(json!({ "question": question })),
+        ToolContextMode::Transcript => {
+            let conversational: Vec<&ChatMessage> = messages
+                .iter()
+                .filter(|m| (m.role == "user" || m.role == "assistant") && !m.content.is_empty())
+                .collect();
+            let skip = conversational
+                .len()
+                .saturating_sub(settings.context_max_messages);
+            let transcript: Vec<Value> = conversational
+                .into_iter()
+                .skip(skip)
+                .map(|m| {
+                    json!({
+                        "role": m.role,
+                        "content": truncate(&m.content.text(), MAX_MESSAGE_CHARS),
+                    })
+                })
+                .collect();
+
+            Some(json!({ "question": question, "transcript": transcript }))
+        }
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_chars).collect();
+        format!("{truncated}…")
+    }
+}
@@ -0,0 +1,20 @@
+use crate::mcp::{McpContent, McpToolCallResponse};
+
+/// Combines the results of a group's calls, in the group's tool order, each introduced
+/// by the tool's name. The merged result only fails if every call failed.
+pub fn merge_group_results(results: Vec<(String, McpToolCallResponse)>) -> McpToolCallResponse {
+    let is_error = results.iter().all(|(_, result)| result.is_error);
+    let mut content = Vec::new();
+    for (tool, result) in results {
+        let outcome = if result.is_error {
+            "failed"
+        } else {
+            "returned"
+        };
+        content.push(McpContent::Text {
+            text: format!("[{tool}] {outcome}:"),
+        });
+        content.extend(result.content);
+    }
+    McpToolCallResponse { content, is_error }
+}
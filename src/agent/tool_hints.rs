@@ -0,0 +1,55 @@
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+/// Running average of how long each tool takes to run
+#[derive(Debug, Default)]
+pub struct ToolLatencies {
+    samples: Mutex<HashMap<String, (u32, Duration)>>,
+}
+
+impl ToolLatencies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, tool: &str, duration: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let (count, total) = samples.entry(tool.to_string()).or_default();
+        *count += 1;
+        *total += duration;
+    }
+
+    pub fn average(&self, tool: &str) -> Option<Duration> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples
+            .get(tool)
+            .map(|(count, total)| *total / (*count).max(1))
+    }
+}
+
+/// Appends the cost and average latency hints, when known, to a tool description
+pub fn describe_with_hints(
+    description: &str,
+    cost: Option<&str>,
+    latency: Option<Duration>,
+) -> String {
+    let mut hints = Vec::new();
+    if let Some(cost) = cost {
+        hints.push(format!("cost: {cost}"));
+    }
+    if let Some(latency) = latency {
+        hints.push(format!("average latency: {}", format_latency(latency)));
+    }
+    if hints.is_empty() {
+        description.to_string()
+    } else {
+        format!("{description} ({})", hints.join("; "))
+    }
+}
+
+fn format_latency(latency: Duration) -> String {
+    if latency < Duration::from_secs(1) {
+        format!("{}ms", latency.as_millis())
+    } else {
+        format!("{:.1}s", latency.as_secs_f64())
+    }
+}
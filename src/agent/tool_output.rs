@@ -0,0 +1,64 @@
+use crate::{
+    config::ToolOutputFormat,
+    llm::ContentPart,
+    mcp::{McpContent, McpToolCallResponse},
+};
+use serde_json::Value;
+
+/// Renders a tool call response into the text sent back to the LLM.
+///
+/// Text output concatenates every content item. JSON output is validated and passed
+/// through verbatim in a fenced block, followed by the schema when one is known; if the
+/// tool did not actually return valid JSON the text rendering is used instead.
+pub fn render_tool_result(
+    response: &McpToolCallResponse,
+    format: ToolOutputFormat,
+    schema: Option<&Value>,
+) -> String {
+    let text = render_text(&response.content);
+
+    if format == ToolOutputFormat::Json
+        && !response.is_error
+        && let Ok(json) = serde_json::from_str::<Value>(&text)
+    {
+        let pretty = serde_json::to_string_pretty(&json).unwrap_or(text);
+        let mut rendered = format!("```json\n{pretty}\n```");
+        if let Some(schema) = schema {
+            let schema = serde_json::to_string_pretty(schema).unwrap_or_default();
+            rendered.push_str(&format!("\n\nOutput schema:\n```json\n{schema}\n```"));
+        }
+        return rendered;
+    }
+
+    text
+}
+
+/// Images in a tool call response, sent to the LLM along with the tool's result
+pub fn tool_images(response: &McpToolCallResponse) -> Vec<ContentPart> {
+    response
+        .content
+        .iter()
+        .filter_map(|item| match item {
+            McpContent::Image { data, mime_type } => Some(ContentPart::ImageBase64 {
+                mime_type: mime_type.clone(),
+                data: data.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+fn render_text(content: &[McpContent]) -> String {
+    content
+        .iter()
+        .map(|item| match item {
+            McpContent::Text { text } => text.clone(),
+            McpContent::Image { mime_type, .. } => format!("[image: {mime_type}]"),
+            McpContent::Resource { resource } => match &resource.text {
+                Some(text) => text.clone(),
+                None => format!("[resource: {}]", resource.uri),
+            },
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,91 @@
+use super::Cache;
+use crate::{Result, config::DatabaseConfig, db};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use std::time::Duration;
+use tracing::info;
+
+/// Cache kept in a SQLite table, surviving restarts
+pub struct DiskCache {
+    conn: Connection,
+}
+
+impl DiskCache {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS cache (
+                key TEXT PRIMARY KEY,
+                value BLOB NOT NULL,
+                expires_at DATETIME
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Disk cache initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Deletes expired entries, returning how many there were
+    pub async fn purge_expired(&self) -> Result<u64> {
+        Ok(self
+            .conn
+            .execute(
+                "DELETE FROM cache WHERE expires_at IS NOT NULL AND expires_at <= ?",
+                [Utc::now().to_rfc3339()],
+            )
+            .await?)
+    }
+}
+
+#[async_trait]
+impl Cache for DiskCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut rows = self
+            .conn
+            .query("SELECT value, expires_at FROM cache WHERE key = ?", [key])
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let expires_at: Option<String> = row.get(1)?;
+        let expired = expires_at
+            .and_then(|at| DateTime::parse_from_rfc3339(&at).ok())
+            .is_some_and(|at| at <= Utc::now());
+        if expired {
+            self.remove(key).await?;
+            return Ok(None);
+        }
+        Ok(Some(row.get(0)?))
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+        let expires_at = ttl
+            .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+            .map(|ttl| (Utc::now() + ttl).to_rfc3339());
+        self.conn
+            .execute(
+                "INSERT INTO cache (key, value, expires_at) VALUES (?, ?, ?) \
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, expires_at = excluded.expires_at",
+                libsql::params![key, value, expires_at],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM cache WHERE key = ?", [key])
+            .await?;
+        Ok(())
+    }
+}
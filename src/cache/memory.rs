@@ -0,0 +1,94 @@
+use super::Cache;
+use crate::Result;
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+struct Entry {
+    value: Vec<u8>,
+    expires_at: Option<Instant>,
+    /// Tick of the last read or write, for LRU eviction
+    used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    tick: u64,
+}
+
+/// In-process cache evicting the least recently used entry beyond `capacity`
+pub struct MemoryCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let tick = entries.tick;
+        let expired = match entries.map.get_mut(key) {
+            None => return Ok(None),
+            Some(entry) if entry.expires_at.is_some_and(|at| at <= Instant::now()) => true,
+            Some(entry) => {
+                entry.used = tick;
+                return Ok(Some(entry.value.clone()));
+            }
+        };
+        if expired {
+            entries.map.remove(key);
+        }
+        Ok(None)
+    }
+
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.tick += 1;
+        let entry = Entry {
+            value,
+            expires_at: ttl.map(|ttl| Instant::now() + ttl),
+            used: entries.tick,
+        };
+        entries.map.insert(key.to_string(), entry);
+
+        while entries.map.len() > self.capacity {
+            let oldest = entries
+                .map
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(oldest) => entries.map.remove(&oldest),
+                None => break,
+            };
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.entries.lock().unwrap().map.remove(key);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Key-value cache shared by LLM reply, tool result and embedding caching, backed by
+//! an in-memory LRU or a SQLite table.
+
+mod disk;
+mod memory;
+
+pub use disk::DiskCache;
+pub use memory::MemoryCache;
+
+use crate::{
+    Result,
+    config::{CacheBackend, CacheConfig, DatabaseConfig},
+};
+use async_trait::async_trait;
+use ring::digest::{SHA256, digest};
+use serde::{Serialize, de::DeserializeOwned};
+use std::{fmt::Write, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+#[async_trait]
+pub trait Cache: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+
+    /// Stores `value`, replacing any entry under `key`. Entries with a `ttl` expire after it.
+    async fn set(&self, key: &str, value: Vec<u8>, ttl: Option<Duration>) -> Result<()>;
+
+    async fn remove(&self, key: &str) -> Result<()>;
+}
+
+/// Builds a key under `namespace` from a hash of `parts`' JSON form. SHA-256 keeps
+/// different requests from sharing an entry, and is stable across builds so disk entries
+/// stay reachable.
+pub fn cache_key(namespace: &str, parts: &impl Serialize) -> Result<String> {
+    let hash = digest(&SHA256, &serde_json::to_vec(parts)?);
+    let mut key = String::with_capacity(namespace.len() + 65);
+    key.push_str(namespace);
+    key.push(':');
+    for byte in hash.as_ref() {
+        let _ = write!(key, "{byte:02x}");
+    }
+    Ok(key)
+}
+
+/// Reads a JSON value. Failures are logged and treated as misses, since a cache must
+/// never break the feature using it.
+pub async fn get_json<T: DeserializeOwned>(cache: &dyn Cache, key: &str) -> Option<T> {
+    match cache.get(key).await {
+        Ok(Some(bytes)) => match serde_json::from_slice(&bytes) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                warn!("Ignoring unreadable cache entry {}: {}", key, e);
+                None
+            }
+        },
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Cache read failed for {}: {}", key, e);
+            None
+        }
+    }
+}
+
+/// Stores a JSON value, logging failures
+pub async fn set_json<T: Serialize>(
+    cache: &dyn Cache,
+    key: &str,
+    value: &T,
+    ttl: Option<Duration>,
+) {
+    let result = match serde_json::to_vec(value) {
+        Ok(bytes) => cache.set(key, bytes, ttl).await,
+        Err(e) => Err(e.into()),
+    };
+    if let Err(e) = result {
+        warn!("Cache write failed for {}: {}", key, e);
+    }
+}
+
+/// Creates the configured cache, or `None` when caching is disabled. The disk backend
+/// keeps its table in the database at `db_path`, tuned with `database`.
+pub async fn create_cache(
+    config: &CacheConfig,
+    db_path: &str,
+    database: &DatabaseConfig,
+) -> Result<Option<Arc<dyn Cache>>> {
+    let cache: Arc<dyn Cache> = match config.backend {
+        CacheBackend::None => return Ok(None),
+        CacheBackend::Memory => Arc::new(MemoryCache::new(config.capacity)),
+        CacheBackend::Disk => Arc::new(DiskCache::open(db_path, database).await?),
+    };
+    info!("Cache initialized: {:?}", config.backend);
+    Ok(Some(cache))
+}
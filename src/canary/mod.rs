@@ -0,0 +1,342 @@
+//! Canary probes: fixed prompts sent to the configured model on a schedule to catch
+//! silent provider-side model changes. The first answer of each probe becomes its
+//! baseline, and later answers are compared with it by similarity and latency.
+
+#[cfg(feature = "knowledge")]
+use crate::knowledge::{Embedder, cosine_similarity};
+use crate::{
+    Result,
+    config::{CanaryConfig, CanaryProbeConfig, DatabaseConfig},
+    db,
+    llm::{ChatCompletionRequest, ChatMessage, LlmClient},
+    notifications::{Notification, NotificationSink},
+};
+use chrono::Utc;
+use libsql::Connection;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Latency has only drifted when slower than the baseline by at least this much, so
+/// fast baselines don't flag ordinary jitter
+const MIN_LATENCY_DRIFT_MS: u64 = 1000;
+
+/// First answer of a probe with a model
+#[derive(Debug, Clone, PartialEq)]
+pub struct Baseline {
+    pub answer: String,
+    pub latency_ms: u64,
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// Baselines of the probes, by probe and model
+pub struct CanaryStore {
+    conn: Connection,
+}
+
+impl CanaryStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS canary_baselines (
+                probe TEXT NOT NULL,
+                model TEXT NOT NULL,
+                answer TEXT NOT NULL,
+                latency_ms INTEGER NOT NULL,
+                embedding TEXT,
+                created_at DATETIME NOT NULL,
+                PRIMARY KEY (probe, model)
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Canary store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    pub async fn baseline(&self, probe: &str, model: &str) -> Result<Option<Baseline>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT answer, latency_ms, embedding FROM canary_baselines WHERE probe = ? AND model = ?",
+                [probe, model],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let embedding = row
+            .get::<Option<String>>(2)?
+            .map(|embedding| serde_json::from_str(&embedding))
+            .transpose()?;
+        Ok(Some(Baseline {
+            answer: row.get(0)?,
+            latency_ms: row.get::<i64>(1)? as u64,
+            embedding,
+        }))
+    }
+
+    pub async fn set_baseline(&self, probe: &str, model: &str, baseline: &Baseline) -> Result<()> {
+        let embedding = baseline
+            .embedding
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO canary_baselines (probe, model, answer, latency_ms, \
+                 embedding, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    probe,
+                    model,
+                    baseline.answer.as_str(),
+                    baseline.latency_ms as i64,
+                    embedding,
+                    Utc::now().to_rfc3339(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// How a probe's answer compared with its baseline
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProbeResult {
+    pub name: String,
+    pub answer: String,
+    pub latency_ms: u64,
+    /// Similarity of the answer to the baseline's, from 0 to 1; `None` for a new baseline
+    pub similarity: Option<f64>,
+    pub baseline_latency_ms: Option<u64>,
+    /// Why the answer counts as drifted; empty when it doesn't
+    pub drift: Vec<String>,
+}
+
+/// Outcome of a canary run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CanaryReport {
+    pub model: String,
+    pub probes: Vec<ProbeResult>,
+}
+
+impl CanaryReport {
+    pub fn drifted(&self) -> impl Iterator<Item = &ProbeResult> {
+        self.probes.iter().filter(|probe| !probe.drift.is_empty())
+    }
+}
+
+/// Share of the words of `a` and `b` they have in common
+pub fn word_similarity(a: &str, b: &str) -> f64 {
+    let words = |text: &str| -> HashSet<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+pub struct Canary {
+    llm: Arc<dyn LlmClient>,
+    model: String,
+    store: Arc<CanaryStore>,
+    probes: Vec<CanaryProbeConfig>,
+    min_similarity: f64,
+    max_latency_factor: f64,
+    #[cfg(feature = "knowledge")]
+    embedder: Option<Arc<dyn Embedder>>,
+    notifier: Option<Arc<dyn NotificationSink>>,
+}
+
+impl Canary {
+    pub fn new(
+        llm: Arc<dyn LlmClient>,
+        model: String,
+        store: Arc<CanaryStore>,
+        config: &CanaryConfig,
+    ) -> Self {
+        Self {
+            llm,
+            model,
+            store,
+            probes: config.probes.clone(),
+            min_similarity: config.min_similarity,
+            max_latency_factor: config.max_latency_factor,
+            #[cfg(feature = "knowledge")]
+            embedder: None,
+            notifier: None,
+        }
+    }
+
+    /// Compares answers by embedding similarity instead of shared words
+    #[cfg(feature = "knowledge")]
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    /// Reports drifted runs through a notification sink
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationSink>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Sends every probe, comparing answers with their baselines or recording them as
+    /// baselines, and notifies when any drifted
+    pub async fn run(&self) -> Result<CanaryReport> {
+        let mut report = CanaryReport {
+            model: self.model.clone(),
+            probes: Vec::with_capacity(self.probes.len()),
+        };
+        for probe in &self.probes {
+            report.probes.push(self.probe(probe).await?);
+        }
+
+        let drifted: Vec<String> = report
+            .drifted()
+            .map(|probe| format!("{}: {}", probe.name, probe.drift.join("; ")))
+            .collect();
+        if !drifted.is_empty() {
+            warn!("Canary probes drifted: {}", drifted.join(", "));
+            if let Some(notifier) = &self.notifier {
+                let notification = Notification::new(
+                    format!("Model drift detected on {}", self.model),
+                    drifted.join("\n"),
+                );
+                if let Err(e) = notifier.send(&notification).await {
+                    warn!("Failed to notify canary drift: {}", e);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    async fn probe(&self, probe: &CanaryProbeConfig) -> Result<ProbeResult> {
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: probe.prompt.clone().into(),
+                tool_calls: None,
+                tool_call_id: None,
+                name: None,
+            }],
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let started = Instant::now();
+        let response = match self.llm.create_chat_completion(request).await {
+            Ok(response) => response,
+            Err(e) => {
+                return Ok(ProbeResult {
+                    name: probe.name.clone(),
+                    answer: String::new(),
+                    latency_ms: started.elapsed().as_millis() as u64,
+                    similarity: None,
+                    baseline_latency_ms: None,
+                    drift: vec![format!("request failed: {e}")],
+                });
+            }
+        };
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let answer = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.text().into_owned())
+            .unwrap_or_default();
+        #[cfg(feature = "knowledge")]
+        let embedding = match &self.embedder {
+            Some(embedder) => embedder.embed(std::slice::from_ref(&answer)).await?.pop(),
+            None => None,
+        };
+        #[cfg(not(feature = "knowledge"))]
+        let embedding = None;
+
+        let mut result = ProbeResult {
+            name: probe.name.clone(),
+            answer,
+            latency_ms,
+            similarity: None,
+            baseline_latency_ms: None,
+            drift: Vec::new(),
+        };
+        let Some(baseline) = self.store.baseline(&probe.name, &self.model).await? else {
+            info!("Recording canary baseline of probe '{}'", probe.name);
+            let baseline = Baseline {
+                answer: result.answer.clone(),
+                latency_ms,
+                embedding,
+            };
+            self.store
+                .set_baseline(&probe.name, &self.model, &baseline)
+                .await?;
+            return Ok(result);
+        };
+
+        let similarity = match (&embedding, &baseline.embedding) {
+            #[cfg(feature = "knowledge")]
+            (Some(embedding), Some(baseline)) => cosine_similarity(embedding, baseline) as f64,
+            _ => word_similarity(&result.answer, &baseline.answer),
+        };
+        if similarity < self.min_similarity {
+            result.drift.push(format!(
+                "answer similarity {similarity:.2} is below {:.2}",
+                self.min_similarity
+            ));
+        }
+        let allowed = (baseline.latency_ms as f64 * self.max_latency_factor)
+            .max((baseline.latency_ms + MIN_LATENCY_DRIFT_MS) as f64);
+        if latency_ms as f64 > allowed {
+            result.drift.push(format!(
+                "latency {latency_ms} ms exceeds {:.1}x the baseline's {} ms",
+                self.max_latency_factor, baseline.latency_ms
+            ));
+        }
+        result.similarity = Some(similarity);
+        result.baseline_latency_ms = Some(baseline.latency_ms);
+        Ok(result)
+    }
+
+    /// Runs the probes every `interval`, starting with a run right away
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        info!(
+            "Running {} canary probes every {:?}",
+            self.probes.len(),
+            interval
+        );
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                match self.run().await {
+                    Ok(report) => info!(
+                        "Canary run finished, {} of {} probes drifted",
+                        report.drifted().count(),
+                        report.probes.len()
+                    ),
+                    Err(e) => error!("Canary run failed: {}", e),
+                }
+            }
+        })
+    }
+}
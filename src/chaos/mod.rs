@@ -0,0 +1,196 @@
+//! Fault injection for resilience testing: LLM calls failing with server errors, MCP tool
+//! calls timing out and history writes failing, at the rates set in `chaos`.
+
+use crate::{
+    Error, Result,
+    config::ChaosConfig,
+    llm::{ChatCompletionRequest, ChatCompletionResponse, LlmClient, ModelInfo},
+    mcp::{
+        McpClient, McpGetPromptRequest, McpGetPromptResponse, McpInitializeRequest,
+        McpInitializeResponse, McpProgress, McpPrompt, McpTool, McpToolCallRequest,
+        McpToolCallResponse,
+    },
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Decides which calls fail, from the configured rates
+pub struct Chaos {
+    config: ChaosConfig,
+    /// xorshift state, so seeded runs fail the same calls every time
+    state: Mutex<u64>,
+}
+
+impl Chaos {
+    pub fn new(config: ChaosConfig) -> Self {
+        let seed = config
+            .seed
+            .unwrap_or_else(|| Uuid::new_v4().as_u64_pair().0);
+        Self {
+            config,
+            // xorshift never leaves zero
+            state: Mutex::new(seed.max(1)),
+        }
+    }
+
+    /// `None` unless chaos is enabled
+    pub fn from_config(config: &ChaosConfig) -> Option<Arc<Self>> {
+        if !config.enabled {
+            return None;
+        }
+        warn!(
+            "Chaos mode enabled: injecting failures (LLM {}, MCP {}, database {})",
+            config.llm_error_rate, config.mcp_timeout_rate, config.db_error_rate
+        );
+        Some(Arc::new(Self::new(config.clone())))
+    }
+
+    /// True with probability `rate`
+    fn roll(&self, rate: f64) -> bool {
+        if rate <= 0.0 {
+            return false;
+        }
+        let mut state = self.state.lock().unwrap();
+        *state ^= *state << 13;
+        *state ^= *state >> 7;
+        *state ^= *state << 17;
+        let sample = (*state >> 11) as f64 / (1u64 << 53) as f64;
+        sample < rate
+    }
+
+    /// Delays an LLM call and fails it at `llm_error_rate`
+    pub async fn llm_call(&self) -> Result<()> {
+        if self.config.llm_latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(self.config.llm_latency_ms)).await;
+        }
+        if self.roll(self.config.llm_error_rate) {
+            debug!("Injecting LLM failure");
+            return Err(Error::llm_status(
+                500,
+                "Injected fault: LLM API returned 500 Internal Server Error",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Delays a tool call and times it out at `mcp_timeout_rate`
+    pub async fn tool_call(&self, name: &str) -> Result<()> {
+        if self.config.mcp_latency_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(self.config.mcp_latency_ms)).await;
+        }
+        if self.roll(self.config.mcp_timeout_rate) {
+            debug!("Injecting timeout of tool '{}'", name);
+            return Err(Error::mcp(format!(
+                "Injected fault: call to tool '{name}' timed out"
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fails a database write at `db_error_rate`
+    pub fn db_write(&self) -> Result<()> {
+        if self.roll(self.config.db_error_rate) {
+            debug!("Injecting database write failure");
+            return Err(Error::internal("Injected fault: database write failed"));
+        }
+        Ok(())
+    }
+}
+
+/// Injects chaos into the calls of the wrapped LLM client
+pub struct ChaosLlmClient {
+    inner: Box<dyn LlmClient>,
+    chaos: Arc<Chaos>,
+}
+
+impl ChaosLlmClient {
+    pub fn new(inner: Box<dyn LlmClient>, chaos: Arc<Chaos>) -> Self {
+        Self { inner, chaos }
+    }
+}
+
+#[async_trait]
+impl LlmClient for ChaosLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        self.chaos.llm_call().await?;
+        self.inner.create_chat_completion(request).await
+    }
+
+    async fn create_chat_completion_streaming(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        self.chaos.llm_call().await?;
+        self.inner
+            .create_chat_completion_streaming(request, deltas)
+            .await
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        self.inner.request_payload(request)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
+
+/// Injects chaos into the tool calls of the wrapped MCP client
+pub struct ChaosMcpClient {
+    inner: Box<dyn McpClient>,
+    chaos: Arc<Chaos>,
+}
+
+impl ChaosMcpClient {
+    pub fn new(inner: Box<dyn McpClient>, chaos: Arc<Chaos>) -> Self {
+        Self { inner, chaos }
+    }
+}
+
+#[async_trait]
+impl McpClient for ChaosMcpClient {
+    async fn initialize(&mut self, request: McpInitializeRequest) -> Result<McpInitializeResponse> {
+        self.inner.initialize(request).await
+    }
+
+    async fn list_tools(&self) -> Result<Vec<McpTool>> {
+        self.inner.list_tools().await
+    }
+
+    async fn call_tool(&self, request: McpToolCallRequest) -> Result<McpToolCallResponse> {
+        self.chaos.tool_call(&request.name).await?;
+        self.inner.call_tool(request).await
+    }
+
+    async fn call_tool_with_progress(
+        &self,
+        request: McpToolCallRequest,
+        progress: UnboundedSender<McpProgress>,
+    ) -> Result<McpToolCallResponse> {
+        self.chaos.tool_call(&request.name).await?;
+        self.inner.call_tool_with_progress(request, progress).await
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<McpPrompt>> {
+        self.inner.list_prompts().await
+    }
+
+    async fn get_prompt(&self, request: McpGetPromptRequest) -> Result<McpGetPromptResponse> {
+        self.inner.get_prompt(request).await
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,70 @@
+//! Slash-commands at the start of user input, such as `/model gpt-4o`, which control the
+//! session from chat platforms instead of being sent to the LLM.
+
+/// What `/help` replies
+pub const HELP: &str = "Commands:
+/reset - forget the conversation so far
+/model <name> - answer with another model; /model default goes back
+/tools on|off - let the assistant use its tools or not
+/help - show this list";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Forget the conversation so far
+    Reset,
+    /// Answer with this model, or with the default one for `None`
+    Model(Option<String>),
+    /// Offer the agent's tools or not
+    Tools(bool),
+    Help,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedInput {
+    pub commands: Vec<Command>,
+    /// What follows the commands, for the LLM; empty when the input was only commands
+    pub input: String,
+}
+
+/// Splits the commands off the start of `input`, as in `/reset /model gpt-4o Hi`. The
+/// input from the first word that isn't a known command on is left as it is, so text
+/// like `/shrug` still reaches the LLM. Fails with a message for the user when a known
+/// command is used wrongly.
+pub fn parse(input: &str) -> Result<ParsedInput, String> {
+    let mut commands = Vec::new();
+    let mut rest = input.trim_start();
+    while let Some(name) = rest.strip_prefix('/') {
+        let (name, after) = name.split_at(name.find(char::is_whitespace).unwrap_or(name.len()));
+        let (command, after) = match name {
+            "reset" => (Command::Reset, after),
+            "help" => (Command::Help, after),
+            "model" => match next_word(after) {
+                ("", _) => return Err("Usage: /model <name>, or /model default".to_string()),
+                ("default", after) => (Command::Model(None), after),
+                (model, after) => (Command::Model(Some(model.to_string())), after),
+            },
+            "tools" => match next_word(after) {
+                ("on", after) => (Command::Tools(true), after),
+                ("off", after) => (Command::Tools(false), after),
+                _ => return Err("Usage: /tools on|off".to_string()),
+            },
+            _ => break,
+        };
+        commands.push(command);
+        rest = after.trim_start();
+    }
+    Ok(ParsedInput {
+        commands,
+        input: rest.to_string(),
+    })
+}
+
+/// The first word of `text` and what follows it. A word starting another command is no
+/// argument, so it is left in place.
+fn next_word(text: &str) -> (&str, &str) {
+    let text = text.trim_start();
+    if text.starts_with('/') {
+        return ("", text);
+    }
+    text.split_at(text.find(char::is_whitespace).unwrap_or(text.len()))
+}
@@ -0,0 +1,114 @@
+//! The `mcpServers` map that MCP server READMEs and desktop clients use, accepted in
+//! place of `mcp_servers` so such snippets can be pasted verbatim:
+//!
+//! ```yaml
+//! mcpServers:
+//!   filesystem:
+//!     command: npx
+//!     args: ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"]
+//!   search:
+//!     url: https://example.com/mcp
+//! ```
+//!
+//! Servers with a `command` run over stdio, others over streamable HTTP unless their
+//! `type` says otherwise. Servers marked `disabled` are left out, and settings only
+//! other clients understand, such as `autoApprove`, are ignored.
+
+use crate::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::path::Path;
+
+const MAP_KEY: &str = "mcpServers";
+const LIST_KEY: &str = "mcp_servers";
+/// Keys of a map entry with the same meaning in `mcp_servers`
+const COPIED_KEYS: [&str; 5] = ["command", "args", "env", "url", "headers"];
+
+/// Moves the servers of an `mcpServers` map in `document` onto its `mcp_servers` list
+pub(super) fn convert_mcp_servers(document: &mut Value, source: &Path) -> Result<()> {
+    let Some(mapping) = document.as_mapping_mut() else {
+        return Ok(());
+    };
+    let servers = match mapping.remove(MAP_KEY) {
+        None | Some(Value::Null) => return Ok(()),
+        Some(Value::Mapping(servers)) => servers,
+        Some(_) => return Err(invalid(source, "expected a map of server names to servers")),
+    };
+
+    let mut converted = Vec::new();
+    for (name, server) in servers {
+        let name = name
+            .as_str()
+            .ok_or_else(|| invalid(source, "server names must be strings"))?;
+        let server = server
+            .as_mapping()
+            .ok_or_else(|| invalid(source, format!("server '{name}' must be a map of settings")))?;
+        if server.get("disabled").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+        converted.push(Value::Mapping(convert_server(name, server, source)?));
+    }
+
+    match mapping.get_mut(LIST_KEY) {
+        Some(Value::Sequence(list)) => list.extend(converted),
+        None | Some(Value::Null) => {
+            mapping.insert(LIST_KEY.into(), Value::Sequence(converted));
+        }
+        Some(_) => {} // left for deserialization to reject
+    }
+    Ok(())
+}
+
+fn convert_server(name: &str, server: &Mapping, source: &Path) -> Result<Mapping> {
+    let mut converted = Mapping::new();
+    converted.insert("name".into(), name.into());
+    for key in COPIED_KEYS {
+        if let Some(value) = server.get(key) {
+            converted.insert(key.into(), value.clone());
+        }
+    }
+
+    let declared = server
+        .get("type")
+        .or_else(|| server.get("transport"))
+        .and_then(Value::as_str);
+    let client_type = match declared {
+        Some(declared) => client_type(declared).ok_or_else(|| {
+            invalid(
+                source,
+                format!("server '{name}' has unsupported type '{declared}'"),
+            )
+        })?,
+        None if server.contains_key("command") => "stdio",
+        None if server.contains_key("url") => "streamable_http",
+        None => {
+            return Err(invalid(
+                source,
+                format!("server '{name}' needs a 'command' or a 'url'"),
+            ));
+        }
+    };
+    converted.insert("type".into(), client_type.into());
+    Ok(converted)
+}
+
+/// `type` of `mcp_servers` for the transport names used in `mcpServers` maps
+fn client_type(declared: &str) -> Option<&'static str> {
+    match declared
+        .to_ascii_lowercase()
+        .replace(['-', '_'], "")
+        .as_str()
+    {
+        "stdio" => Some("stdio"),
+        "sse" => Some("sse"),
+        "http" => Some("http"),
+        "streamablehttp" => Some("streamable_http"),
+        _ => None,
+    }
+}
+
+fn invalid(source: &Path, error: impl std::fmt::Display) -> Error {
+    Error::config(format!(
+        "In {}: invalid {MAP_KEY}: {error}",
+        source.display()
+    ))
+}
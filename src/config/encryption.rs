@@ -0,0 +1,320 @@
+//! `!encrypted` config values, decrypted when the config is loaded, so configs holding API
+//! keys can be committed:
+//!
+//! ```yaml
+//! llm:
+//!   api_key: !encrypted "v1:3q2+7w..."
+//! ```
+//!
+//! Values are encrypted with AES-256-GCM under a key taken from, in order, the
+//! `JARVIS_CONFIG_KEY` environment variable, the OS keyring (macOS Keychain or the
+//! Secret Service on Linux) or the key file at `JARVIS_CONFIG_KEY_FILE`, by default
+//! `~/.config/jarvis/config.key`. `jarvis config encrypt` creates the key on first use.
+
+use crate::{Error, Result};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use ring::{
+    aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    rand::{SecureRandom, SystemRandom},
+};
+use serde_yaml::Value;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use tracing::debug;
+
+/// YAML tag of encrypted values
+pub const ENCRYPTED_TAG: &str = "encrypted";
+const KEY_ENV: &str = "JARVIS_CONFIG_KEY";
+const KEY_FILE_ENV: &str = "JARVIS_CONFIG_KEY_FILE";
+const VERSION_PREFIX: &str = "v1:";
+const KEYRING_SERVICE: &str = "jarvis";
+const KEYRING_ACCOUNT: &str = "config-key";
+
+/// Where a [`ConfigKey`] was found or stored
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeySource {
+    Env,
+    Keyring,
+    File(PathBuf),
+}
+
+impl std::fmt::Display for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env => write!(f, "the {KEY_ENV} environment variable"),
+            Self::Keyring => f.write_str("the OS keyring"),
+            Self::File(path) => write!(f, "key file {}", path.display()),
+        }
+    }
+}
+
+/// 256-bit key encrypting config values
+#[derive(Clone, PartialEq)]
+pub struct ConfigKey([u8; 32]);
+
+// Keeps the key out of logs
+impl std::fmt::Debug for ConfigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ConfigKey(***)")
+    }
+}
+
+impl ConfigKey {
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| Error::internal("Failed to generate a config key"))?;
+        Ok(Self(bytes))
+    }
+
+    pub fn from_base64(text: &str) -> Result<Self> {
+        let bytes = BASE64
+            .decode(text.trim())
+            .map_err(|e| Error::config(format!("Invalid config key: {e}")))?;
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| Error::config("Invalid config key: expected 32 bytes"))?;
+        Ok(Self(bytes))
+    }
+
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.0)
+    }
+
+    /// Reads the key from a key file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::config(format!("Failed to read key file {}: {e}", path.display()))
+        })?;
+        Self::from_base64(&text)
+    }
+
+    /// Finds the key in the environment, the OS keyring or the key file
+    pub fn find() -> Result<Option<(Self, KeySource)>> {
+        if let Ok(text) = std::env::var(KEY_ENV) {
+            return Ok(Some((Self::from_base64(&text)?, KeySource::Env)));
+        }
+        if let Some(text) = keyring_get() {
+            return Ok(Some((Self::from_base64(&text)?, KeySource::Keyring)));
+        }
+        let path = key_file_path();
+        if path.is_file() {
+            return Ok(Some((Self::from_file(&path)?, KeySource::File(path))));
+        }
+        Ok(None)
+    }
+
+    /// The key from [`ConfigKey::find`], failing if there is none
+    pub fn load() -> Result<Self> {
+        match Self::find()? {
+            Some((key, source)) => {
+                debug!("Using config key from {}", source);
+                Ok(key)
+            }
+            None => Err(Error::config(format!(
+                "Config has encrypted values but no key was found in {KEY_ENV}, the OS keyring or {}",
+                key_file_path().display()
+            ))),
+        }
+    }
+
+    /// The existing key, or a new one stored in the OS keyring if available and the key
+    /// file otherwise
+    pub fn load_or_create() -> Result<(Self, KeySource)> {
+        if let Some(found) = Self::find()? {
+            return Ok(found);
+        }
+        let key = Self::generate()?;
+        if keyring_set(&key.to_base64()) {
+            return Ok((key, KeySource::Keyring));
+        }
+        let path = key_file_path();
+        key.write_file(&path)?;
+        Ok((key, KeySource::File(path)))
+    }
+
+    /// Writes the key to a file readable only by the current user
+    pub fn write_file(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(path).map_err(|e| {
+            Error::config(format!("Failed to create key file {}: {e}", path.display()))
+        })?;
+        file.write_all(self.to_base64().as_bytes())?;
+        Ok(())
+    }
+
+    fn cipher(&self) -> LessSafeKey {
+        // 32 bytes is always a valid AES-256 key
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.0).expect("AES-256 key"))
+    }
+
+    /// Encrypts `plaintext` into the text of an `!encrypted` value
+    pub fn encrypt(&self, plaintext: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| Error::internal("Failed to generate a nonce"))?;
+        let mut data = plaintext.as_bytes().to_vec();
+        self.cipher()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| Error::internal("Failed to encrypt value"))?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(data);
+        Ok(format!("{VERSION_PREFIX}{}", BASE64.encode(sealed)))
+    }
+
+    /// Decrypts the text of an `!encrypted` value
+    pub fn decrypt(&self, text: &str) -> Result<String> {
+        let sealed = text
+            .trim()
+            .strip_prefix(VERSION_PREFIX)
+            .and_then(|data| BASE64.decode(data).ok())
+            .filter(|sealed| sealed.len() > NONCE_LEN)
+            .ok_or_else(|| Error::config("Malformed encrypted value"))?;
+        let (nonce, data) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| Error::config("Malformed encrypted value"))?;
+        let mut data = data.to_vec();
+        let plaintext = self
+            .cipher()
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| {
+                Error::config("Failed to decrypt value: wrong key or corrupted ciphertext")
+            })?;
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|_| Error::config("Decrypted value is not UTF-8"))
+    }
+}
+
+/// Replaces the `!encrypted` values of `document`, read from `source`, with their
+/// plaintext, asking `key` for the key only if there are any. Errors name the value.
+pub(super) fn decrypt_values(
+    document: &mut Value,
+    key: &mut dyn FnMut() -> Result<ConfigKey>,
+    source: &Path,
+    path: &str,
+) -> Result<()> {
+    let invalid =
+        |message: &str| Error::config(format!("In {}: '{path}': {message}", source.display()));
+    match document {
+        Value::Tagged(tagged) if tagged.tag == ENCRYPTED_TAG => {
+            let Value::String(text) = &tagged.value else {
+                return Err(invalid("encrypted values must be strings"));
+            };
+            let plaintext = key()?.decrypt(text).map_err(|e| match e {
+                Error::Config(message) => invalid(&message),
+                e => invalid(&e.to_string()),
+            })?;
+            *document = Value::String(plaintext);
+        }
+        Value::Tagged(tagged) => decrypt_values(&mut tagged.value, key, source, path)?,
+        Value::Mapping(mapping) => {
+            for (name, value) in mapping.iter_mut() {
+                let name = name.as_str().unwrap_or("?");
+                let child = if path.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{path}.{name}")
+                };
+                decrypt_values(value, key, source, &child)?;
+            }
+        }
+        Value::Sequence(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                decrypt_values(item, key, source, &format!("{path}[{index}]"))?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// `JARVIS_CONFIG_KEY_FILE`, or `config.key` in the user's config directory
+pub fn key_file_path() -> PathBuf {
+    if let Ok(path) = std::env::var(KEY_FILE_ENV) {
+        return PathBuf::from(path);
+    }
+    let config_dir = if cfg!(windows) {
+        std::env::var("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|_| std::env::var("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    config_dir
+        .unwrap_or_else(|_| PathBuf::from("."))
+        .join("jarvis")
+        .join("config.key")
+}
+
+/// Reads the key from the OS keyring through its command line tool, if there is one
+fn keyring_get() -> Option<String> {
+    let output = if cfg!(target_os = "macos") {
+        Command::new("security")
+            .args(["find-generic-password", "-s", KEYRING_SERVICE])
+            .args(["-a", KEYRING_ACCOUNT, "-w"])
+            .stderr(Stdio::null())
+            .output()
+    } else if cfg!(target_os = "linux") {
+        Command::new("secret-tool")
+            .args(["lookup", "service", KEYRING_SERVICE])
+            .args(["account", KEYRING_ACCOUNT])
+            .stderr(Stdio::null())
+            .output()
+    } else {
+        return None;
+    };
+    let output = output.ok().filter(|output| output.status.success())?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    (!text.trim().is_empty()).then(|| text.trim().to_string())
+}
+
+/// Stores the key in the OS keyring, returning whether that worked
+fn keyring_set(key: &str) -> bool {
+    // The key goes through stdin, since other users can read command lines
+    let (mut command, input) = if cfg!(target_os = "macos") {
+        let mut command = Command::new("security");
+        // With `-w` last, security prompts for the password, and then to retype it
+        command
+            .args(["add-generic-password", "-U", "-s", KEYRING_SERVICE])
+            .args(["-a", KEYRING_ACCOUNT, "-w"]);
+        (command, format!("{key}\n{key}\n"))
+    } else if cfg!(target_os = "linux") {
+        let mut command = Command::new("secret-tool");
+        command
+            .args([
+                "store",
+                "--label=Jarvis config key",
+                "service",
+                KEYRING_SERVICE,
+            ])
+            .args(["account", KEYRING_ACCOUNT]);
+        (command, key.to_string())
+    } else {
+        return false;
+    };
+    command
+        .stdin(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(input.as_bytes())?;
+            }
+            child.wait()
+        })
+        .is_ok_and(|status| status.success())
+}
@@ -0,0 +1,217 @@
+//! `include:` lists of files merged into the config at load time, so large fleets of MCP
+//! servers can live in their own files:
+//!
+//! ```yaml
+//! include: ["mcp/*.yaml", "agent.yaml"]
+//! ```
+//!
+//! Paths and globs are relative to the file that includes them, and included files may
+//! include others. Mappings merge, lists such as `mcp_servers` append, and a value set
+//! twice to different things is an error naming the file that set it again.
+
+use super::{
+    McpServerConfig,
+    compat::convert_mcp_servers,
+    encryption::{ConfigKey, decrypt_values},
+};
+use crate::{Error, Result};
+use serde_yaml::{Mapping, Value};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+const INCLUDE_KEY: &str = "include";
+
+/// Reads a config file and merges in everything it includes. Encrypted values are
+/// decrypted with `key`, or the key found by [`ConfigKey::load`] if unset.
+pub(super) fn read_root(path: &Path, key: Option<ConfigKey>) -> Result<Value> {
+    let text = std::fs::read_to_string(path)?;
+    let document: Value = serde_yaml::from_str(&text)?;
+    let mut includer = Includer::new(key);
+    includer.stack.push(canonical(path));
+    includer.expand(document, path, parent_dir(path))
+}
+
+/// Merges in the includes of a config not read from a file, relative to `base_dir`
+pub(super) fn expand_root(document: Value, base_dir: &Path) -> Result<Value> {
+    Includer::new(None).expand(document, Path::new("config"), base_dir)
+}
+
+struct Includer {
+    /// Files being expanded, to catch include cycles
+    stack: Vec<PathBuf>,
+    /// File each MCP server was defined in
+    servers: HashMap<String, PathBuf>,
+    /// Key of encrypted values, loaded when the first one is found
+    key: Option<ConfigKey>,
+}
+
+impl Includer {
+    fn new(key: Option<ConfigKey>) -> Self {
+        Self {
+            stack: Vec::new(),
+            servers: HashMap::new(),
+            key,
+        }
+    }
+
+    fn key(&mut self) -> Result<ConfigKey> {
+        if self.key.is_none() {
+            self.key = Some(ConfigKey::load()?);
+        }
+        Ok(self.key.clone().expect("key was just loaded"))
+    }
+
+    fn expand(&mut self, document: Value, source: &Path, base_dir: &Path) -> Result<Value> {
+        let mut document = match document {
+            Value::Null => Value::Mapping(Mapping::new()),
+            Value::Mapping(_) => document,
+            _ => return Err(in_file(source, "expected a mapping of config sections")),
+        };
+        decrypt_values(&mut document, &mut || self.key(), source, "")?;
+        convert_mcp_servers(&mut document, source)?;
+        self.register_servers(&document, source)?;
+
+        for pattern in include_patterns(&document, source)? {
+            for path in matching_files(base_dir, &pattern, source)? {
+                debug!("Including config file {}", path.display());
+                let included = self.read(&path)?;
+                merge(&mut document, included, &path, "")?;
+            }
+        }
+        Ok(document)
+    }
+
+    /// Reads and expands an included file, without its own `include` list
+    fn read(&mut self, path: &Path) -> Result<Value> {
+        let canonical = canonical(path);
+        if self.stack.contains(&canonical) {
+            return Err(in_file(path, "includes itself"));
+        }
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::config(format!(
+                "Failed to read included config {}: {e}",
+                path.display()
+            ))
+        })?;
+        let document: Value = serde_yaml::from_str(&text).map_err(|e| in_file(path, e))?;
+
+        self.stack.push(canonical);
+        let expanded = self.expand(document, path, parent_dir(path));
+        self.stack.pop();
+
+        let mut expanded = expanded?;
+        if let Value::Mapping(mapping) = &mut expanded {
+            mapping.remove(INCLUDE_KEY);
+        }
+        Ok(expanded)
+    }
+
+    /// Validates the file's MCP servers, so their errors name the file they are in
+    fn register_servers(&mut self, document: &Value, source: &Path) -> Result<()> {
+        let Some(servers) = document.get("mcp_servers") else {
+            return Ok(());
+        };
+        let servers: Vec<McpServerConfig> = serde_yaml::from_value(servers.clone())
+            .map_err(|e| in_file(source, format!("invalid mcp_servers: {e}")))?;
+        for server in servers {
+            if let Some(previous) = self.servers.get(&server.name) {
+                return Err(in_file(
+                    source,
+                    format!(
+                        "MCP server '{}' is already defined in {}",
+                        server.name,
+                        previous.display()
+                    ),
+                ));
+            }
+            self.servers.insert(server.name, source.to_path_buf());
+        }
+        Ok(())
+    }
+}
+
+fn include_patterns(document: &Value, source: &Path) -> Result<Vec<String>> {
+    match document.get(INCLUDE_KEY) {
+        None | Some(Value::Null) => Ok(Vec::new()),
+        Some(Value::String(pattern)) => Ok(vec![pattern.clone()]),
+        Some(patterns) => serde_yaml::from_value(patterns.clone())
+            .map_err(|_| in_file(source, "include must be a list of file paths or globs")),
+    }
+}
+
+/// Files matching `pattern`, sorted so merges happen in a stable order. A plain path
+/// must exist; a glob may match nothing.
+fn matching_files(base_dir: &Path, pattern: &str, source: &Path) -> Result<Vec<PathBuf>> {
+    let full = base_dir.join(pattern);
+    let full = full.to_string_lossy();
+    let mut files = glob::glob(&full)
+        .map_err(|e| in_file(source, format!("invalid include pattern '{pattern}': {e}")))?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+    files.sort();
+
+    if files.is_empty() && !pattern.contains(['*', '?', '[']) {
+        return Err(in_file(
+            source,
+            format!("included file {full} does not exist"),
+        ));
+    }
+    Ok(files)
+}
+
+/// Merges `source`, read from `file`, into `target`
+fn merge(target: &mut Value, source: Value, file: &Path, path: &str) -> Result<()> {
+    match (target, source) {
+        (Value::Mapping(target), Value::Mapping(source)) => {
+            for (key, value) in source {
+                let name = key.as_str().map_or_else(
+                    || serde_yaml::to_string(&key).unwrap_or_default(),
+                    str::to_string,
+                );
+                let child = if path.is_empty() {
+                    name.trim().to_string()
+                } else {
+                    format!("{path}.{}", name.trim())
+                };
+                match target.get_mut(&key) {
+                    Some(existing) => merge(existing, value, file, &child)?,
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+            Ok(())
+        }
+        (Value::Sequence(target), Value::Sequence(source)) => {
+            target.extend(source);
+            Ok(())
+        }
+        (target @ Value::Null, source) => {
+            *target = source;
+            Ok(())
+        }
+        (target, source) if *target == source => Ok(()),
+        _ => Err(in_file(
+            file,
+            format!("'{path}' is already set to a different value"),
+        )),
+    }
+}
+
+fn in_file(path: &Path, error: impl std::fmt::Display) -> Error {
+    Error::config(format!("In {}: {error}", path.display()))
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    path.parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
@@ -1,18 +1,85 @@
+mod compat;
+mod encryption;
+mod include;
+mod schema;
 mod types;
 
+pub use encryption::{ConfigKey, ENCRYPTED_TAG, KeySource, key_file_path};
+pub use schema::{schema, unknown_fields};
 pub use types::*;
 
-use crate::Result;
-use std::env;
-use tracing::debug;
+use crate::{Error, Result};
+use std::{env, path::Path};
+use tracing::{debug, info, warn};
 
 pub async fn load() -> Result<Config> {
     let config_path = env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
 
     debug!("Loading configuration from: {}", config_path);
 
-    let config_str = tokio::fs::read_to_string(&config_path).await?;
-    let config: Config = serde_yaml::from_str(&config_str)?;
+    load_file(Path::new(&config_path))
+}
+
+/// Loads a config file along with the files it includes
+pub fn load_file(path: &Path) -> Result<Config> {
+    from_document(include::read_root(path, None)?)
+}
+
+/// [`load_file`] decrypting `!encrypted` values with `key` instead of the configured key
+pub fn load_file_with_key(path: &Path, key: ConfigKey) -> Result<Config> {
+    from_document(include::read_root(path, Some(key))?)
+}
+
+/// Parses a config, resolving its includes relative to the current directory. With
+/// `strict: true`, fields the config does not know fail the parse instead of being
+/// ignored.
+pub fn parse(text: &str) -> Result<Config> {
+    let document = serde_yaml::from_str(text)?;
+    from_document(include::expand_root(document, Path::new("."))?)
+}
 
+fn from_document(document: serde_yaml::Value) -> Result<Config> {
+    let mut config: Config = serde_yaml::from_value(document.clone())?;
+    if config.strict {
+        let raw = serde_json::to_value(&document)?;
+        let unknown = unknown_fields(&raw);
+        if !unknown.is_empty() {
+            return Err(Error::config(format!(
+                "Unknown config fields: {}",
+                unknown.join(", ")
+            )));
+        }
+    }
+    config.apply_auth_presets()?;
     Ok(config)
 }
+
+/// Warns about files still used from the working directory, where they were kept before
+/// `server.data_dir` existed, so they can be moved under it
+pub fn warn_legacy_paths(config: &Config) {
+    let paths = std::iter::once(&config.server.database_path).chain(&config.cache.path);
+    for path in paths.filter(|path| config.server.is_legacy_path(path)) {
+        warn!(
+            "Using {} from the working directory; move it into {} to keep it with the other data",
+            path, config.server.data_dir
+        );
+    }
+}
+
+/// Creates `server.data_dir` if missing, readable only by the current user since it
+/// holds conversation history
+pub fn create_data_dir(server: &ServerConfig) -> Result<()> {
+    let path = Path::new(&server.data_dir);
+    if path.is_dir() {
+        return Ok(());
+    }
+
+    std::fs::create_dir_all(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))?;
+    }
+    info!("Created data directory: {}", path.display());
+    Ok(())
+}
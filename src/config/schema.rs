@@ -0,0 +1,127 @@
+//! JSON Schema of `config.yaml`, and the check behind `strict: true` that rejects fields
+//! the schema does not know.
+
+use super::Config;
+use serde_json::{Map, Value};
+
+/// JSON Schema describing every field of [`Config`]
+pub fn schema() -> Value {
+    serde_json::to_value(schemars::schema_for!(Config)).unwrap_or_default()
+}
+
+/// Dotted paths of the fields in a raw config that [`Config`] has no place for, such as
+/// `llm.systme_prompt` or `mcp_servers[1].comand`
+pub fn unknown_fields(config: &Value) -> Vec<String> {
+    let schema = schema();
+    let definitions = schema
+        .get("definitions")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+    let mut unknown = Vec::new();
+    Walker {
+        definitions: &definitions,
+    }
+    .walk(&schema, config, "", &mut unknown);
+    unknown
+}
+
+struct Walker<'a> {
+    definitions: &'a Map<String, Value>,
+}
+
+impl Walker<'_> {
+    /// Follows `$ref`s into `definitions`
+    fn resolve<'s>(&'s self, mut schema: &'s Value) -> &'s Value {
+        while let Some(name) = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.strip_prefix("#/definitions/"))
+        {
+            match self.definitions.get(name) {
+                Some(definition) => schema = definition,
+                None => break,
+            }
+        }
+        schema
+    }
+
+    fn walk(&self, schema: &Value, value: &Value, path: &str, unknown: &mut Vec<String>) {
+        let schema = self.resolve(schema);
+
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for part in all {
+                self.walk(part, value, path, unknown);
+            }
+        }
+        // Of several alternatives, the value is taken to be the one it fits best
+        for key in ["anyOf", "oneOf"] {
+            if let Some(variants) = schema.get(key).and_then(Value::as_array) {
+                let best = variants
+                    .iter()
+                    .filter(|variant| admits(self.resolve(variant), value))
+                    .map(|variant| {
+                        let mut found = Vec::new();
+                        self.walk(variant, value, path, &mut found);
+                        found
+                    })
+                    .min_by_key(Vec::len);
+                unknown.extend(best.unwrap_or_default());
+            }
+        }
+
+        match value {
+            Value::Object(fields) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let additional = schema.get("additionalProperties");
+                if properties.is_none() && additional.is_none() {
+                    // Free-form, or described only by the alternatives above
+                    return;
+                }
+                for (name, field) in fields {
+                    let field_path = if path.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{path}.{name}")
+                    };
+                    match (properties.and_then(|p| p.get(name)), additional) {
+                        (Some(property), _) => self.walk(property, field, &field_path, unknown),
+                        (None, Some(Value::Bool(false))) | (None, None) => unknown.push(field_path),
+                        (None, Some(additional)) => {
+                            self.walk(additional, field, &field_path, unknown)
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (index, item) in items.iter().enumerate() {
+                        self.walk(item_schema, item, &format!("{path}[{index}]"), unknown);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether the `type` of a schema allows the kind of `value`
+fn admits(schema: &Value, value: &Value) -> bool {
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let allows = |t: &Value| {
+        t.as_str()
+            .is_some_and(|t| t == kind || (t == "integer" && kind == "number"))
+    };
+    match schema.get("type") {
+        None => true,
+        Some(Value::Array(types)) => types.iter().any(allows),
+        Some(t) => allows(t),
+    }
+}
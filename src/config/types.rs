@@ -1,15 +1,147 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Config {
+    /// Reject fields the config schema does not know, such as a misspelled
+    /// `systme_prompt`, instead of ignoring them
+    #[serde(default)]
+    pub strict: bool,
+    /// Files merged into this config when it is loaded, as paths or globs relative to it
+    /// such as `mcp/*.yaml`
+    #[serde(default)]
+    pub include: Vec<String>,
     pub llm: LlmConfig,
     pub server: ServerConfig,
+    /// Also read from an `mcpServers` map of names to servers, the shape of MCP server
+    /// READMEs, which is converted when the config is loaded
     #[serde(default)]
     pub mcp_servers: Vec<McpServerConfig>,
+    /// Named authentication that MCP servers refer to with `auth`, so secrets shared by
+    /// several servers are written once
+    #[serde(default)]
+    pub auth_presets: HashMap<String, AuthPreset>,
+    #[serde(default)]
+    pub tools: ToolsConfig,
+    /// RSS/Atom feeds polled in the background
+    #[serde(default)]
+    pub feeds: Vec<FeedConfig>,
+    /// Agent prompts run on a schedule
+    #[serde(default)]
+    pub schedules: Vec<ScheduleConfig>,
+    /// Example prompts showing off tools, listed by `GET /examples` for clients to offer
+    /// as quick replies
+    #[serde(default)]
+    pub examples: Vec<ExampleConfig>,
+    /// Slash-commands such as `/reset` at the start of the input, run by the server
+    /// instead of being sent to the LLM
+    #[serde(default)]
+    pub commands: CommandsConfig,
+    /// How replies are formatted for each integration
+    #[serde(default)]
+    pub formatting: FormattingConfig,
+    /// Typing indicators and placeholders chat integrations show while a run is in progress
+    #[serde(default)]
+    pub progress: ProgressConfig,
+    /// Push notifications for scheduled and webhook-triggered runs
+    #[serde(default)]
+    pub notifications: NotificationsConfig,
+    /// Document knowledge base searched by the `knowledge_search` tool
+    #[serde(default)]
+    pub knowledge: KnowledgeConfig,
+    /// Shared cache for LLM replies, tool results and embeddings
+    #[serde(default)]
+    pub cache: CacheConfig,
+    #[serde(default)]
+    pub agent: AgentConfig,
+    /// Safety moderation of user input and final replies
+    #[serde(default)]
+    pub moderation: ModerationConfig,
+    /// Accounting of the tokens and cost of each request
+    #[serde(default)]
+    pub usage: UsageConfig,
+    /// Retention of deleted conversation messages
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Probe prompts checking the model for silent provider-side changes
+    #[serde(default)]
+    pub canary: CanaryConfig,
+    /// Artificial failures and latency for resilience testing. Never enable in production.
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+    /// Subsystems the server runs, to turn off without removing their configuration
+    #[serde(default)]
+    pub features: FeaturesConfig,
+    /// Checking GitHub for a newer release at startup
+    #[serde(default)]
+    pub updates: UpdatesConfig,
+}
+
+impl Config {
+    /// Which subsystems this configuration runs: those whose feature is on and that are
+    /// configured
+    pub fn features(&self) -> Features {
+        let features = &self.features;
+        Features {
+            streaming: features.streaming,
+            approvals: features.approvals && self.tools.approval.webhook_url.is_some(),
+            rag: cfg!(feature = "knowledge") && features.rag && self.knowledge.enabled,
+            scheduler: features.scheduler,
+            feeds: cfg!(feature = "feeds") && features.feeds && !self.feeds.is_empty(),
+            notifications: cfg!(feature = "notifications")
+                && features.notifications
+                && self.notifications.provider != NotificationProvider::None,
+            commands: features.commands
+                && (self.commands.enabled || self.commands.integrations.values().any(|on| *on)),
+        }
+    }
+
+    /// Adds the headers of each MCP server's `auth` preset to the server's own, which
+    /// take precedence
+    pub fn apply_auth_presets(&mut self) -> crate::Result<()> {
+        for server in &mut self.mcp_servers {
+            let Some(name) = &server.auth else {
+                continue;
+            };
+            let preset = self.auth_presets.get(name).ok_or_else(|| {
+                crate::Error::config(format!(
+                    "MCP server '{}' uses unknown auth preset '{name}'",
+                    server.name
+                ))
+            })?;
+            if matches!(
+                server.client_type,
+                McpClientType::Stdio | McpClientType::Fixture
+            ) {
+                return Err(crate::Error::config(format!(
+                    "MCP server '{}' sends no headers, so it cannot use auth preset '{name}'",
+                    server.name
+                )));
+            }
+            for (header, value) in preset.headers(name)? {
+                if !server
+                    .headers
+                    .keys()
+                    .any(|own| own.eq_ignore_ascii_case(&header))
+                {
+                    server.headers.insert(header, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Database of the disk cache: `cache.path` if set, else the history database
+    pub fn cache_database_path(&self) -> String {
+        match &self.cache.path {
+            Some(path) => self.server.data_path(path),
+            None => self.server.resolved_database_path(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LlmConfig {
     #[serde(default = "default_provider")]
     pub provider: String,
@@ -18,9 +150,613 @@ pub struct LlmConfig {
     pub model: String,
     #[serde(default)]
     pub system_prompt: Option<String>,
+    /// How many times to ask the model to continue a reply cut off by the token limit
+    #[serde(default = "default_max_continuations")]
+    pub max_continuations: usize,
+    /// Prompt tokens a request may use, counting messages, tool definitions and the
+    /// reply's `max_tokens`. Beyond it the oldest conversation messages are dropped.
+    /// Unlimited when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_context_tokens: Option<usize>,
+    /// Retries of calls failing with a network error, rate limit or server error, before
+    /// `fallbacks` take over
+    #[serde(default)]
+    pub retry: LlmRetryConfig,
+    /// A pause between the LLM calls of a run, for providers rate limiting bursts of turns
+    #[serde(default)]
+    pub pacing: LlmPacingConfig,
+    /// Seconds an LLM request may take, reply included, before it fails as a network
+    /// error; 0 waits indefinitely
+    #[serde(default = "default_llm_request_timeout")]
+    pub request_timeout: u64,
+    /// Seconds to wait for a connection to the provider; 0 waits indefinitely
+    #[serde(default = "default_llm_connect_timeout")]
+    pub connect_timeout: u64,
+    /// Proxy for requests to the provider, such as `http://proxy.local:3128`. Unset uses
+    /// `HTTPS_PROXY`/`HTTP_PROXY` from the environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// What to do when the provider rejects a request for exceeding the model's context
+    #[serde(default)]
+    pub context_fallback: ContextFallbackConfig,
+    /// Sampling parameters of replies, which requests may override
+    #[serde(flatten)]
+    pub sampling: SamplingConfig,
+    #[serde(default)]
+    pub empty_response: EmptyResponseConfig,
+    /// How replies requested with a JSON `response_format` are checked
+    #[serde(default)]
+    pub structured_output: StructuredOutputConfig,
+    /// A cheaper model for the turns that pick tools
+    #[serde(default)]
+    pub routing: ModelRoutingConfig,
+    /// Whether edits to the system prompt also apply to existing sessions. When false,
+    /// each session keeps the prompt it started with.
+    #[serde(default = "default_true")]
+    pub retroactive_system_prompt: bool,
+    /// Options of the `ollama` provider
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    /// Options of the `azure_openai` provider, whose `base_url` is the resource endpoint
+    /// such as `https://my-resource.openai.azure.com`
+    #[serde(default)]
+    pub azure: AzureOpenAiConfig,
+    /// Options of the `openrouter` provider
+    #[serde(default)]
+    pub openrouter: OpenRouterConfig,
+    /// Models that reason before answering, such as OpenAI's o-series
+    #[serde(default)]
+    pub reasoning: ReasoningConfig,
+    /// Options of the `replay` provider
+    #[serde(default)]
+    pub replay: ReplayConfig,
+    /// Providers tried in order when the one before fails with a network error, rate
+    /// limit or server error
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<LlmFallbackConfig>,
+}
+
+impl LlmConfig {
+    /// This config answering through `fallback` instead, without further fallbacks
+    pub fn with_fallback(&self, fallback: &LlmFallbackConfig) -> LlmConfig {
+        LlmConfig {
+            provider: fallback.provider.clone(),
+            base_url: fallback.base_url.clone(),
+            api_key: fallback.api_key.clone(),
+            model: fallback.model.clone(),
+            ollama: fallback.ollama.clone(),
+            azure: fallback.azure.clone(),
+            openrouter: fallback.openrouter.clone(),
+            fallbacks: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Sampling parameters of LLM calls; unset ones are left to the client's defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct SamplingConfig {
+    /// Randomness of replies, from 0 to 2
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// Longest reply, in tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u16>,
+    /// Only sample from the most likely tokens making up this share of the probability,
+    /// from 0 to 1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Sequences ending the reply where they would be generated
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    /// Penalty on tokens by how often they already appear, from -2 to 2, against
+    /// repetitive replies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    /// Penalty on tokens that already appear at all, from -2 to 2, for new topics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    /// Makes sampling repeatable: requests with the same seed and parameters mostly get
+    /// the same reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Added to the likelihood of tokens, by the model's token ID, from -100 (never) to
+    /// 100 (only). OpenAI-compatible providers only.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub logit_bias: BTreeMap<String, i32>,
+}
+
+impl SamplingConfig {
+    /// These parameters, with those unset taken from `defaults`
+    pub fn or(&self, defaults: &SamplingConfig) -> SamplingConfig {
+        SamplingConfig {
+            temperature: self.temperature.or(defaults.temperature),
+            max_tokens: self.max_tokens.or(defaults.max_tokens),
+            top_p: self.top_p.or(defaults.top_p),
+            stop: if self.stop.is_empty() {
+                defaults.stop.clone()
+            } else {
+                self.stop.clone()
+            },
+            frequency_penalty: self.frequency_penalty.or(defaults.frequency_penalty),
+            presence_penalty: self.presence_penalty.or(defaults.presence_penalty),
+            seed: self.seed.or(defaults.seed),
+            logit_bias: if self.logit_bias.is_empty() {
+                defaults.logit_bias.clone()
+            } else {
+                self.logit_bias.clone()
+            },
+        }
+    }
+}
+
+/// Exponential backoff between attempts of an LLM call
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LlmRetryConfig {
+    /// Attempts per call, the first included; 1 disables retries
+    #[serde(default = "default_llm_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubling with each further one
+    #[serde(default = "default_llm_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Longest delay between attempts. A `Retry-After` asking for longer ends the retries.
+    #[serde(default = "default_llm_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    /// Wait a random part of each delay, between half and all of it, so clients failing
+    /// together don't retry together
+    #[serde(default = "default_true")]
+    pub jitter: bool,
+}
+
+impl Default for LlmRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_llm_retry_max_attempts(),
+            initial_backoff_ms: default_llm_retry_initial_backoff_ms(),
+            max_backoff_ms: default_llm_retry_max_backoff_ms(),
+            jitter: true,
+        }
+    }
+}
+
+/// Pauses between consecutive LLM calls of a run, such as the one answering tool results
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LlmPacingConfig {
+    /// Pause before the second call of a run, doubling with each further one; 0 disables
+    /// pacing
+    #[serde(default)]
+    pub initial_delay_ms: u64,
+    /// Longest pause between calls
+    #[serde(default = "default_llm_pacing_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// Pause a random part of each delay, between half and all of it, so concurrent runs
+    /// don't call together
+    #[serde(default = "default_true")]
+    pub jitter: bool,
+}
+
+impl Default for LlmPacingConfig {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 0,
+            max_delay_ms: default_llm_pacing_max_delay_ms(),
+            jitter: true,
+        }
+    }
+}
+
+/// Second chances for requests too long for the model. The larger model is tried first,
+/// then the request is truncated.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ContextFallbackConfig {
+    /// Model with a larger context, served by the same provider
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Send the request again without the oldest conversation messages, halving it
+    #[serde(default = "default_true")]
+    pub truncate: bool,
+}
+
+impl Default for ContextFallbackConfig {
+    fn default() -> Self {
+        Self {
+            model: None,
+            truncate: true,
+        }
+    }
+}
+
+/// A provider of `llm.fallbacks`; the prompt and reply settings are those of `llm`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LlmFallbackConfig {
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    /// Empty for the provider's default, where it has one
+    #[serde(default)]
+    pub base_url: String,
+    #[serde(default)]
+    pub api_key: String,
+    /// Model answering in place of any the request asked for
+    pub model: String,
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    #[serde(default)]
+    pub azure: AzureOpenAiConfig,
+    #[serde(default)]
+    pub openrouter: OpenRouterConfig,
+}
+
+/// How the app is named to OpenRouter, which shows it in its rankings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OpenRouterConfig {
+    /// URL of the app, sent as `HTTP-Referer`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub referer: Option<String>,
+    /// Name of the app, sent as `X-Title`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Recorded completions answered by the `replay` provider, for testing agent flows
+/// without a live provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReplayConfig {
+    /// Directory of the recordings, one JSON file per request
+    #[serde(default = "default_replay_dir")]
+    pub dir: String,
+    /// Provider answering requests without a recording, using the other `llm` settings;
+    /// its completions are recorded. Unset fails such requests.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<String>,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            dir: default_replay_dir(),
+            record: None,
+        }
+    }
+}
+
+fn default_replay_dir() -> String {
+    "replays".to_string()
+}
+
+/// Looking up the latest release at startup, logging when it is newer than this build
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct UpdatesConfig {
+    /// Off by default, as it calls the GitHub API
+    #[serde(default)]
+    pub check: bool,
+    /// GitHub repository whose releases are checked, as `owner/name`
+    #[serde(default = "default_updates_repository")]
+    pub repository: String,
+}
+
+impl Default for UpdatesConfig {
+    fn default() -> Self {
+        Self {
+            check: false,
+            repository: default_updates_repository(),
+        }
+    }
+}
+
+fn default_updates_repository() -> String {
+    "comigor/jarvis-rs".to_string()
+}
+
+/// Subsystems the server runs, each on unless turned off here
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FeaturesConfig {
+    /// The `GET /sessions/{id}/events` and `GET /sessions/{id}/progress` streams, and
+    /// replies saved while they are streamed (`agent.partial_replies`)
+    #[serde(default = "default_true")]
+    pub streaming: bool,
+    /// Approval of tool calls through `tools.approval`. When off, calls needing approval
+    /// are denied.
+    #[serde(default = "default_true")]
+    pub approvals: bool,
+    /// The knowledge base (`knowledge`)
+    #[serde(default = "default_true")]
+    pub rag: bool,
+    /// Scheduled jobs (`schedules`), follow-ups and callbacks
+    #[serde(default = "default_true")]
+    pub scheduler: bool,
+    /// Feed polling (`feeds`)
+    #[serde(default = "default_true")]
+    pub feeds: bool,
+    /// Push notifications (`notifications`)
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+    /// Slash-commands (`commands`)
+    #[serde(default = "default_true")]
+    pub commands: bool,
+}
+
+impl Default for FeaturesConfig {
+    fn default() -> Self {
+        Self {
+            streaming: true,
+            approvals: true,
+            rag: true,
+            scheduler: true,
+            feeds: true,
+            notifications: true,
+            commands: true,
+        }
+    }
+}
+
+/// Whether each subsystem runs, as `GET /admin/features` reports it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Features {
+    pub streaming: bool,
+    pub approvals: bool,
+    pub rag: bool,
+    pub scheduler: bool,
+    pub feeds: bool,
+    pub notifications: bool,
+    pub commands: bool,
+}
+
+/// Models that reason before answering. OpenAI-compatible requests to them carry no
+/// `temperature`, `top_p` or `stop`, and limit the reply with `max_completion_tokens`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ReasoningConfig {
+    /// Reasoning models besides the o1, o3, o4 and gpt-5 families, by name or by prefix
+    /// ending in `*`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<String>,
+    /// How much the models reason; the provider's default when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub effort: Option<ReasoningEffort>,
+}
+
+/// Families of reasoning models, by name prefix
+const REASONING_MODELS: &[&str] = &["o1", "o3", "o4", "gpt-5"];
+
+impl ReasoningConfig {
+    /// Whether `model` is a reasoning model. A provider prefix such as OpenRouter's
+    /// `openai/` is ignored.
+    pub fn applies_to(&self, model: &str) -> bool {
+        let name = model.rsplit('/').next().unwrap_or(model);
+        let family = |prefix: &str| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+        };
+        REASONING_MODELS.iter().any(|prefix| family(prefix))
+            || self
+                .models
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => model.starts_with(prefix),
+                    None => model == pattern,
+                })
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// Azure OpenAI deployments models are served by
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AzureOpenAiConfig {
+    /// `api-version` query parameter of every request
+    #[serde(default = "default_azure_api_version")]
+    pub api_version: String,
+    /// Deployment serving each model; models not listed are served by the deployment of
+    /// the same name
+    #[serde(default)]
+    pub deployments: HashMap<String, String>,
+}
+
+impl Default for AzureOpenAiConfig {
+    fn default() -> Self {
+        Self {
+            api_version: default_azure_api_version(),
+            deployments: HashMap::new(),
+        }
+    }
+}
+
+fn default_azure_api_version() -> String {
+    "2024-10-21".to_string()
+}
+
+impl AzureOpenAiConfig {
+    /// Deployment serving `model`
+    pub fn deployment_for<'a>(&'a self, model: &'a str) -> &'a str {
+        self.deployments
+            .get(model)
+            .map(String::as_str)
+            .unwrap_or(model)
+    }
+}
+
+/// Model options sent to a local Ollama server
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct OllamaConfig {
+    /// How long the model stays loaded after a request, such as `5m`, or `-1` for ever;
+    /// Ollama's default when unset
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Context window in tokens; Ollama's default, often much smaller than the model's,
+    /// when unset
+    #[serde(default)]
+    pub num_ctx: Option<u32>,
+}
+
+/// What to do when the LLM answers with no choices or blank content
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct EmptyResponseConfig {
+    /// Times to re-ask with `nudge` before failing the request; 0 fails right away
+    #[serde(default = "default_empty_response_retries")]
+    pub retries: usize,
+    /// System note appended to the conversation when re-asking
+    #[serde(default = "default_empty_response_nudge")]
+    pub nudge: String,
+}
+
+impl Default for EmptyResponseConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_empty_response_retries(),
+            nudge: default_empty_response_nudge(),
+        }
+    }
+}
+
+/// Checks of replies requested with `response_format: json_object` or `json_schema`
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StructuredOutputConfig {
+    /// Times to re-ask, telling the model what was wrong, when a reply isn't valid JSON
+    /// or doesn't match the schema; the request fails once they run out
+    #[serde(default = "default_structured_output_retries")]
+    pub retries: usize,
+}
+
+impl Default for StructuredOutputConfig {
+    fn default() -> Self {
+        Self {
+            retries: default_structured_output_retries(),
+        }
+    }
+}
+
+/// Models a run switches between by turn. Turns offering tools use `tool_model`; when
+/// it answers instead of calling a tool, its reply is dropped and `answer_model` writes
+/// the final answer.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ModelRoutingConfig {
+    /// Model choosing tools, such as `gpt-4o-mini`. Routing is off when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_model: Option<String>,
+    /// Model writing the final answer, such as `gpt-4o`; `model` when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub answer_model: Option<String>,
+}
+
+/// How the agent answers, independent of the model
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgentConfig {
+    /// Language replies are written in, e.g. `Portuguese`, `por` or `pt-BR`. Replies
+    /// detected in another language are rewritten once. Requests can override it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_language: Option<String>,
+    /// `single` (default) answers each request in one run; `fanout` splits requests
+    /// into independent sub-questions answered in parallel, then combines the answers
+    #[serde(default)]
+    pub mode: AgentMode,
+    /// Limits of the `fanout` mode
+    #[serde(default)]
+    pub fanout: FanoutConfig,
+    /// Scoring of replies, and what happens to the low-confidence ones
+    #[serde(default)]
+    pub confidence: ConfidenceConfig,
+    /// Saving replies to the history while they are streamed
+    #[serde(default)]
+    pub partial_replies: PartialRepliesConfig,
+    /// LLM calls a run may make before it fails, counting the one that answers
+    #[serde(default = "default_max_turns")]
+    pub max_turns: usize,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            response_language: None,
+            mode: AgentMode::default(),
+            fanout: FanoutConfig::default(),
+            confidence: ConfidenceConfig::default(),
+            partial_replies: PartialRepliesConfig::default(),
+            max_turns: default_max_turns(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PartialRepliesConfig {
+    /// Whether replies are streamed from the LLM and saved as they grow, so a crash
+    /// keeps what was written and the history shows progress. Ignored while output
+    /// moderation is on, as the text isn't checked until it is complete.
+    #[serde(default)]
+    pub enabled: bool,
+    /// How often the growing reply is saved
+    #[serde(default = "default_partial_reply_interval_ms")]
+    pub interval_ms: u64,
+}
+
+impl Default for PartialRepliesConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: default_partial_reply_interval_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ConfidenceConfig {
+    /// Whether replies are scored, by asking the LLM to judge them after each run
+    #[serde(default)]
+    pub enabled: bool,
+    /// Replies scored below this, between 0 and 1, are low-confidence
+    #[serde(default = "default_confidence_threshold")]
+    pub threshold: f64,
+    /// Sent instead of low-confidence replies, such as "I don't know". Unset keeps them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fallback_reply: Option<String>,
+    /// Low-confidence replies are POSTed here with the question for a human to follow up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation_webhook: Option<String>,
+}
+
+impl Default for ConfidenceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: default_confidence_threshold(),
+            fallback_reply: None,
+            escalation_webhook: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentMode {
+    #[default]
+    Single,
+    Fanout,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FanoutConfig {
+    /// Most sub-questions a request is split into; extra ones are dropped
+    #[serde(default = "default_max_subquestions")]
+    pub max_subquestions: usize,
+    /// Most sub-questions answered at the same time
+    #[serde(default = "default_max_concurrency")]
+    pub max_concurrency: usize,
+}
+
+impl Default for FanoutConfig {
+    fn default() -> Self {
+        Self {
+            max_subquestions: default_max_subquestions(),
+            max_concurrency: default_max_concurrency(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServerConfig {
     #[serde(default = "default_host")]
     pub host: String,
@@ -28,65 +764,1439 @@ pub struct ServerConfig {
     pub port: u16,
     #[serde(default)]
     pub logs: LogsConfig,
+    /// Relative to `data_dir` unless absolute
     #[serde(default = "default_database_path")]
     pub database_path: String,
+    /// Directory holding the database, caches and other files the server writes,
+    /// created at startup
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
+    /// Keys callers authenticate inference requests with, as `Authorization: Bearer
+    /// <key>`. Requests are accepted without one when none are configured.
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// SQLite tuning applied to every connection to the database
+    #[serde(default)]
+    pub database: DatabaseConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct LogsConfig {
-    #[serde(default = "default_log_level")]
-    pub level: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct McpServerConfig {
-    pub name: String,
-    pub url: Option<String>,
-    #[serde(rename = "type")]
-    pub client_type: McpClientType,
-    #[serde(default)]
-    pub headers: HashMap<String, String>,
-    // Stdio specific fields
-    pub command: Option<String>,
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct CommandsConfig {
+    /// Whether commands are run for requests from integrations not listed below
     #[serde(default)]
-    pub args: Vec<String>,
+    pub enabled: bool,
+    /// Per-integration overrides, keyed by the `integration` requests name, such as
+    /// `telegram: true`
     #[serde(default)]
-    pub env: HashMap<String, String>,
+    pub integrations: HashMap<String, bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "snake_case")]
-pub enum McpClientType {
-    Sse,
-    StreamableHttp,
-    Http,
-    Stdio,
+impl CommandsConfig {
+    /// Whether commands are run for requests from `integration`
+    pub fn enabled_for(&self, integration: Option<&str>) -> bool {
+        integration
+            .and_then(|integration| self.integrations.get(integration))
+            .copied()
+            .unwrap_or(self.enabled)
+    }
 }
 
-impl Default for LogsConfig {
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FormattingConfig {
+    /// Format of replies to requests from each integration, keyed by the `integration`
+    /// requests name; others get Markdown unless they ask for a `format`
+    #[serde(default)]
+    pub integrations: HashMap<String, OutputFormat>,
+    /// Width plain text replies are wrapped at; 0 leaves lines whole
+    #[serde(default = "default_plain_width")]
+    pub plain_width: usize,
+    /// Longest message each integration can send, in characters. Replies to its requests
+    /// are also returned split into `chunks` that fit.
+    #[serde(default = "default_max_chunk_sizes")]
+    pub max_chunk_sizes: HashMap<String, usize>,
+}
+
+impl Default for FormattingConfig {
     fn default() -> Self {
         Self {
-            level: default_log_level(),
+            integrations: HashMap::new(),
+            plain_width: default_plain_width(),
+            max_chunk_sizes: default_max_chunk_sizes(),
         }
     }
 }
 
-pub fn default_provider() -> String {
-    "openai".to_string()
+fn default_plain_width() -> usize {
+    80
 }
 
-pub fn default_host() -> String {
-    "0.0.0.0".to_string()
+fn default_max_chunk_sizes() -> HashMap<String, usize> {
+    HashMap::from([
+        ("telegram".to_string(), 4096),
+        ("discord".to_string(), 2000),
+    ])
 }
 
-pub fn default_port() -> u16 {
-    8080
+impl FormattingConfig {
+    /// Format of replies to requests from `integration`
+    pub fn format_for(&self, integration: Option<&str>) -> OutputFormat {
+        integration
+            .and_then(|integration| self.integrations.get(integration))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Size of the chunks replies to requests from `integration` are split into
+    pub fn max_chunk_size_for(&self, integration: Option<&str>) -> Option<usize> {
+        integration.and_then(|integration| self.max_chunk_sizes.get(integration).copied())
+    }
 }
 
-pub fn default_log_level() -> String {
-    "info".to_string()
+/// Progress streamed by `GET /sessions/{id}/progress` for chat integrations to show
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProgressConfig {
+    /// Seconds between renewals of the typing indicator, for integrations not listed in
+    /// `typing_intervals`
+    #[serde(default = "default_typing_interval_secs")]
+    pub typing_interval_secs: u64,
+    /// Seconds between renewals of the typing indicator, keyed by integration; chat
+    /// platforms clear it after a few seconds
+    #[serde(default = "default_typing_intervals")]
+    pub typing_intervals: HashMap<String, u64>,
+    /// Whether to also stream text for a placeholder message showing the run's step
+    #[serde(default = "default_true")]
+    pub placeholders: bool,
 }
 
-pub fn default_database_path() -> String {
-    "history.db".to_string()
+impl Default for ProgressConfig {
+    fn default() -> Self {
+        Self {
+            typing_interval_secs: default_typing_interval_secs(),
+            typing_intervals: default_typing_intervals(),
+            placeholders: true,
+        }
+    }
+}
+
+fn default_typing_interval_secs() -> u64 {
+    5
+}
+
+fn default_typing_intervals() -> HashMap<String, u64> {
+    // Telegram shows "typing" for 5 seconds and Discord for 10
+    HashMap::from([("telegram".to_string(), 4), ("discord".to_string(), 8)])
+}
+
+impl ProgressConfig {
+    /// How often requests from `integration` renew the typing indicator
+    pub fn typing_interval_for(&self, integration: Option<&str>) -> std::time::Duration {
+        let secs = integration
+            .and_then(|integration| self.typing_intervals.get(integration))
+            .copied()
+            .unwrap_or(self.typing_interval_secs);
+        std::time::Duration::from_secs(secs.max(1))
+    }
+}
+
+/// Formats replies are converted to from the Markdown the LLM writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Left as the LLM wrote it
+    #[default]
+    Markdown,
+    /// The HTML subset of Telegram's `parse_mode: HTML`
+    TelegramHtml,
+    /// Slack's mrkdwn
+    SlackMrkdwn,
+    /// Without markup, wrapped at `formatting.plain_width`
+    Plain,
+}
+
+/// Deleted messages stay in the database, recoverable, until purged
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HistoryConfig {
+    /// Days a deleted message is kept before being removed for good
+    #[serde(default = "default_purge_after_days")]
+    pub purge_after_days: u64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            purge_after_days: default_purge_after_days(),
+        }
+    }
+}
+
+/// Fixed prompts sent to the configured model on a schedule. The first answer of each
+/// probe becomes its baseline; later answers drifting from it in content or latency are
+/// reported through `notifications`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CanaryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Interval between runs, e.g. `30m`, `6h`, `1d`
+    #[serde(default = "default_canary_every")]
+    pub every: String,
+    #[serde(default)]
+    pub probes: Vec<CanaryProbeConfig>,
+    /// Answers less similar than this to their baseline, from 0 to 1, have drifted
+    #[serde(default = "default_canary_min_similarity")]
+    pub min_similarity: f64,
+    /// Answers taking more than this many times their baseline's latency have drifted
+    #[serde(default = "default_canary_max_latency_factor")]
+    pub max_latency_factor: f64,
+    /// Compare answers by the similarity of their `knowledge.embedding_model` embeddings
+    /// instead of the words they share
+    #[serde(default = "default_true")]
+    pub embeddings: bool,
+}
+
+impl Default for CanaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            every: default_canary_every(),
+            probes: Vec::new(),
+            min_similarity: default_canary_min_similarity(),
+            max_latency_factor: default_canary_max_latency_factor(),
+            embeddings: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CanaryProbeConfig {
+    /// Identifies the probe's baseline
+    pub name: String,
+    /// Sent on its own, without the system prompt, history or tools
+    pub prompt: String,
+}
+
+/// SQLite settings trading durability for fewer "database is locked" errors when
+/// sessions write concurrently
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DatabaseConfig {
+    /// `wal` lets readers and a writer work at the same time
+    #[serde(default)]
+    pub journal_mode: JournalMode,
+    /// How long a connection waits for a lock before failing; 0 fails at once
+    #[serde(default = "default_busy_timeout_ms")]
+    pub busy_timeout_ms: u64,
+    /// How often SQLite flushes to disk; `normal` is safe with `wal`
+    #[serde(default)]
+    pub synchronous: Synchronous,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            journal_mode: JournalMode::default(),
+            busy_timeout_ms: default_busy_timeout_ms(),
+            synchronous: Synchronous::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalMode {
+    #[default]
+    Wal,
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+    Off,
+}
+
+impl JournalMode {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Wal => "WAL",
+            Self::Delete => "DELETE",
+            Self::Truncate => "TRUNCATE",
+            Self::Persist => "PERSIST",
+            Self::Memory => "MEMORY",
+            Self::Off => "OFF",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Synchronous {
+    Off,
+    #[default]
+    Normal,
+    Full,
+    Extra,
+}
+
+impl Synchronous {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Self::Off => "OFF",
+            Self::Normal => "NORMAL",
+            Self::Full => "FULL",
+            Self::Extra => "EXTRA",
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Database path, overridable with the `HISTORY_DB_PATH` environment variable
+    pub fn resolved_database_path(&self) -> String {
+        std::env::var("HISTORY_DB_PATH").unwrap_or_else(|_| self.data_path(&self.database_path))
+    }
+
+    /// Places a relative `path` under `data_dir`. Absolute paths and `:memory:` are kept,
+    /// and so is a relative path to a file in the working directory while `data_dir` has
+    /// none, as files were kept there before `data_dir` existed.
+    pub fn data_path(&self, path: &str) -> String {
+        if path == ":memory:"
+            || std::path::Path::new(path).is_absolute()
+            || self.is_legacy_path(path)
+        {
+            return path.to_string();
+        }
+        std::path::Path::new(&self.data_dir)
+            .join(path)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// Whether the relative `path` names a file in the working directory that
+    /// `data_dir` doesn't have
+    pub fn is_legacy_path(&self, path: &str) -> bool {
+        let path = std::path::Path::new(path);
+        path.is_relative()
+            && path.is_file()
+            && !std::path::Path::new(&self.data_dir).join(path).exists()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApiKeyConfig {
+    /// Identifies the key in usage reports
+    pub name: String,
+    pub key: String,
+    /// Models requests with this key may use; any model when empty
+    #[serde(default)]
+    pub models: Vec<String>,
+    /// Model used when a request doesn't pick one, instead of `llm.model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_model: Option<String>,
+    /// Requests allowed in any minute; unlimited when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Whether the key may use sessions other keys started
+    #[serde(default)]
+    pub admin: bool,
+}
+
+impl ApiKeyConfig {
+    pub fn allows(&self, model: &str) -> bool {
+        self.models.is_empty() || self.models.iter().any(|allowed| allowed == model)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LogsConfig {
+    #[serde(default = "default_log_level")]
+    pub level: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct McpServerConfig {
+    pub name: String,
+    pub url: Option<String>,
+    #[serde(rename = "type")]
+    pub client_type: McpClientType,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // Stdio specific fields
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// YAML or JSON file of tools and canned results (`fixture` type only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixture: Option<String>,
+    /// Name of the `auth_presets` entry whose headers this server sends
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
+}
+
+/// Authentication shared by HTTP MCP servers: `bearer` or `basic` set the
+/// `Authorization` header, and `headers` adds any others
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AuthPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bearer: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basic: Option<BasicAuth>,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl AuthPreset {
+    /// Headers sent by servers using this preset, named `name`
+    pub fn headers(&self, name: &str) -> crate::Result<HashMap<String, String>> {
+        use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+        let mut headers = self.headers.clone();
+        let authorization = match (&self.bearer, &self.basic) {
+            (Some(_), Some(_)) => {
+                return Err(crate::Error::config(format!(
+                    "Auth preset '{name}' sets both 'bearer' and 'basic'"
+                )));
+            }
+            (Some(token), None) => Some(format!("Bearer {token}")),
+            (None, Some(basic)) => Some(format!(
+                "Basic {}",
+                BASE64.encode(format!("{}:{}", basic.username, basic.password))
+            )),
+            (None, None) => None,
+        };
+        if let Some(authorization) = authorization {
+            headers.retain(|header, _| !header.eq_ignore_ascii_case("authorization"));
+            headers.insert("Authorization".to_string(), authorization);
+        }
+        Ok(headers)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum McpClientType {
+    Sse,
+    StreamableHttp,
+    Http,
+    Stdio,
+    /// Serves tools from a fixture file instead of a real server, for tests and CI
+    Fixture,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FeedConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_feed_interval_minutes")]
+    pub interval_minutes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ExampleConfig {
+    /// Prompt sent to the agent when the example is picked
+    pub prompt: String,
+    /// Short text for the button; defaults to the shortened prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Tool the example demonstrates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Profile the example is meant for, such as `demo` or `family`; examples without
+    /// one are offered to every profile
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScheduleConfig {
+    pub name: String,
+    /// Prompt sent to the agent on every run
+    pub prompt: String,
+    /// Run repeatedly at this interval, e.g. `30m`, `6h`, `1d`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<String>,
+    /// Run daily at this time (`HH:MM`) in `tools.datetime.timezone`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
+    /// Session the runs are recorded in; defaults to `schedule:<name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Push the result through `notifications` after each run
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct KnowledgeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// OpenAI-compatible embeddings model
+    #[serde(default = "default_embedding_model")]
+    pub embedding_model: String,
+    /// Embeddings endpoint; defaults to `llm.base_url`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Embeddings API key; defaults to `llm.api_key`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Target chunk length in characters
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+    /// Characters repeated between consecutive chunks
+    #[serde(default = "default_chunk_overlap")]
+    pub chunk_overlap: usize,
+    /// Chunks returned by `knowledge_search` unless the model asks for another number
+    #[serde(default = "default_knowledge_top_k")]
+    pub top_k: usize,
+    /// Optional second pass reordering vector-search hits by relevance
+    #[serde(default)]
+    pub rerank: RerankConfig,
+}
+
+impl KnowledgeConfig {
+    /// Default number of results for `knowledge_search`
+    pub fn result_count(&self) -> usize {
+        match self.rerank.provider {
+            RerankProvider::None => self.top_k,
+            _ => self.rerank.top_k_out,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RerankConfig {
+    #[serde(default)]
+    pub provider: RerankProvider,
+    /// Rerank API base URL (`cross_encoder`), serving Cohere/Jina-style `POST /rerank`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Rerank model; for `llm` defaults to `llm.model`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Vector-search candidates handed to the reranker
+    #[serde(default = "default_rerank_top_k_in")]
+    pub top_k_in: usize,
+    /// Hits kept after reranking
+    #[serde(default = "default_knowledge_top_k")]
+    pub top_k_out: usize,
+}
+
+impl Default for RerankConfig {
+    fn default() -> Self {
+        Self {
+            provider: RerankProvider::None,
+            url: None,
+            api_key: None,
+            model: None,
+            top_k_in: default_rerank_top_k_in(),
+            top_k_out: default_knowledge_top_k(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CacheConfig {
+    #[serde(default)]
+    pub backend: CacheBackend,
+    /// Entries the memory backend keeps before evicting the least recently used
+    #[serde(default = "default_cache_capacity")]
+    pub capacity: usize,
+    /// Database file of the disk backend, relative to `server.data_dir`; defaults to the
+    /// history database
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Lifetime of entries; unset keeps them until evicted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ttl_seconds: Option<u64>,
+    /// Reuse replies to identical LLM requests. Off by default since a cached reply
+    /// repeats word for word.
+    #[serde(default)]
+    pub llm: bool,
+    /// Reuse embeddings of identical texts
+    #[serde(default = "default_true")]
+    pub embeddings: bool,
+    /// Tools whose results are reused for identical arguments
+    #[serde(default)]
+    pub tools: Vec<String>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            backend: CacheBackend::default(),
+            capacity: default_cache_capacity(),
+            path: None,
+            ttl_seconds: None,
+            llm: false,
+            embeddings: true,
+            tools: Vec::new(),
+        }
+    }
+}
+
+impl CacheConfig {
+    pub fn ttl(&self) -> Option<std::time::Duration> {
+        self.ttl_seconds.map(std::time::Duration::from_secs)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheBackend {
+    /// Caching disabled
+    None,
+    /// In-process LRU, lost on restart
+    #[default]
+    Memory,
+    /// SQLite table, kept across restarts
+    Disk,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankProvider {
+    #[default]
+    None,
+    /// Cross-encoder served over HTTP
+    CrossEncoder,
+    /// The chat model scores each passage
+    Llm,
+}
+
+impl Default for KnowledgeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            embedding_model: default_embedding_model(),
+            base_url: None,
+            api_key: None,
+            chunk_size: default_chunk_size(),
+            chunk_overlap: default_chunk_overlap(),
+            top_k: default_knowledge_top_k(),
+            rerank: RerankConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct NotificationsConfig {
+    /// Push service; notifications are disabled when `none`
+    #[serde(default)]
+    pub provider: NotificationProvider,
+    /// Server URL (required for Gotify; ntfy defaults to https://ntfy.sh)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// ntfy topic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topic: Option<String>,
+    /// ntfy access token, Gotify application token or Pushover API token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Pushover user key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_key: Option<String>,
+    /// Provider-specific priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+    /// Also notify when an agent run fails, with the session and error
+    #[serde(default)]
+    pub run_failures: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationProvider {
+    #[default]
+    None,
+    Ntfy,
+    Pushover,
+    Gotify,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct UsageConfig {
+    /// Token prices by model name, for the cost of each request. Requests with models
+    /// not listed cost nothing.
+    #[serde(default)]
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ModelPrice {
+    /// Price of a million prompt tokens
+    pub input_per_million: f64,
+    /// Price of a million completion tokens
+    pub output_per_million: f64,
+}
+
+/// Faults injected to check how retries and fallbacks cope. Rates are probabilities
+/// between 0 and 1 applied to each call.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ChaosConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Seed of the fault dice, for reproducible runs; random when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    /// Rate of LLM calls failing as if the API answered 500
+    #[serde(default)]
+    pub llm_error_rate: f64,
+    /// Delay added to every LLM call
+    #[serde(default)]
+    pub llm_latency_ms: u64,
+    /// Rate of MCP tool calls timing out
+    #[serde(default)]
+    pub mcp_timeout_rate: f64,
+    /// Delay added to every MCP tool call, and how long a timing-out call hangs
+    #[serde(default)]
+    pub mcp_latency_ms: u64,
+    /// Rate of history database writes failing
+    #[serde(default)]
+    pub db_error_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ModerationConfig {
+    /// Classifier that flags text; moderation is disabled when `none`
+    #[serde(default)]
+    pub provider: ModerationProviderKind,
+    /// OpenAI API key (required for `openai`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Base URL of the OpenAI-compatible API; defaults to https://api.openai.com/v1
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    #[serde(default = "default_moderation_model")]
+    pub model: String,
+    /// Words and phrases flagging each category, for the `keywords` classifier
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub keywords: HashMap<String, Vec<String>>,
+    /// What flagging each category does; categories not listed use `default_action`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub actions: HashMap<String, ModerationAction>,
+    #[serde(default)]
+    pub default_action: ModerationAction,
+    /// Whether user input is checked before it reaches the LLM
+    #[serde(default = "default_true")]
+    pub check_input: bool,
+    /// Whether final replies are checked before they are returned
+    #[serde(default = "default_true")]
+    pub check_output: bool,
+    /// Reply given instead of blocked input or output
+    #[serde(default = "default_blocked_reply")]
+    pub blocked_reply: String,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> Self {
+        Self {
+            provider: ModerationProviderKind::default(),
+            api_key: None,
+            base_url: None,
+            model: default_moderation_model(),
+            keywords: HashMap::new(),
+            actions: HashMap::new(),
+            default_action: ModerationAction::default(),
+            check_input: true,
+            check_output: true,
+            blocked_reply: default_blocked_reply(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationProviderKind {
+    #[default]
+    None,
+    /// OpenAI's moderation endpoint
+    Openai,
+    /// Local classifier matching the configured `keywords`
+    Keywords,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ModerationAction {
+    /// Replace the text with `blocked_reply`
+    #[default]
+    Block,
+    /// Log the flag and let the text through
+    Warn,
+    /// Ignore the category
+    Allow,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ToolsConfig {
+    /// Per-tool settings keyed by tool name
+    #[serde(default)]
+    pub settings: HashMap<String, ToolSettings>,
+    /// Native `convert_units` and `format_number` tools
+    #[serde(default)]
+    pub units: UnitsToolConfig,
+    /// Native `calculate` tool
+    #[serde(default)]
+    pub calculator: CalculatorToolConfig,
+    /// Native `current_datetime`, `add_duration` and `next_occurrence` tools
+    #[serde(default)]
+    pub datetime: DateTimeToolConfig,
+    /// Native `web_search` tool
+    #[serde(default)]
+    pub web_search: WebSearchToolConfig,
+    /// Native `send_email` and `search_inbox` tools
+    #[serde(default)]
+    pub email: EmailToolConfig,
+    /// Native `list_events` and `create_event` tools
+    #[serde(default)]
+    pub calendar: CalendarToolConfig,
+    /// How calls to tools that require approval are confirmed
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    /// Whether tool descriptions offered to the LLM mention how long the tool takes on
+    /// average, measured from this agent's calls
+    #[serde(default)]
+    pub latency_hints: bool,
+    /// Virtual tools that call several tools at once and merge their results
+    #[serde(default)]
+    pub groups: Vec<ToolGroupConfig>,
+    /// Heartbeats of long-running tool calls and cancelling those that stall
+    #[serde(default)]
+    pub heartbeat: ToolHeartbeatConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ToolHeartbeatConfig {
+    /// How long a tool call runs before heartbeat events are sent for it; 0 turns
+    /// heartbeats and stall detection off
+    #[serde(default = "default_heartbeat_after_ms")]
+    pub after_ms: u64,
+    /// Time between heartbeat events
+    #[serde(default = "default_heartbeat_interval_ms")]
+    pub interval_ms: u64,
+    /// Calls that report no progress for this long once heartbeats started are
+    /// cancelled as stalled. Tools that never report progress are cancelled after
+    /// `after_ms` plus this. Unset waits for `timeout_ms`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stall_timeout_ms: Option<u64>,
+    /// Calls still running after this long are cancelled, progress or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+impl Default for ToolHeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            after_ms: default_heartbeat_after_ms(),
+            interval_ms: default_heartbeat_interval_ms(),
+            stall_timeout_ms: None,
+            timeout_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolGroupConfig {
+    /// Name the group is offered to the LLM under
+    pub name: String,
+    pub description: String,
+    /// Tools called with the group's arguments, concurrently. Each tool's own
+    /// `settings` still apply.
+    pub tools: Vec<String>,
+    /// JSON Schema of the group's arguments; defaults to the first tool's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<serde_json::Value>,
+}
+
+impl ToolsConfig {
+    pub fn settings_for(&self, tool_name: &str) -> Option<&ToolSettings> {
+        self.settings.get(tool_name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ToolSettings {
+    /// Conversation context passed to the tool as the reserved `_context` argument
+    #[serde(default)]
+    pub context: ToolContextMode,
+    /// Maximum number of recent messages included when `context` is `transcript`
+    #[serde(default = "default_context_max_messages")]
+    pub context_max_messages: usize,
+    /// Format of the tool's output; `json` results are passed to the model verbatim.
+    /// When unset, tools that declare an output schema are treated as JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_format: Option<ToolOutputFormat>,
+    /// Optional JSON Schema describing the tool's JSON output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<serde_json::Value>,
+    /// Whether calls must be approved before running. Overrides the tool's own default
+    /// (only side-effecting native tools such as `send_email` require approval by default).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_approval: Option<bool>,
+    /// Values always passed for these arguments, such as an account id or API region.
+    /// They are removed from the schema the LLM sees and set on every call, replacing
+    /// anything the LLM sent.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub arguments: HashMap<String, serde_json::Value>,
+    /// Arguments set from secrets, such as API keys. Like `arguments` they are hidden
+    /// from the LLM, but they are only added right before the tool runs, so they never
+    /// reach hooks, approval requests or the history, and are masked in the tool's output.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, SecretSource>,
+    /// Fixes applied to the arguments the LLM sent before the tool runs
+    #[serde(default, skip_serializing_if = "ArgumentTransform::is_empty")]
+    pub transform: ArgumentTransform,
+    /// Cost hint added to the description the LLM sees, such as "free" or "$0.01 per
+    /// call", so it can prefer cheaper tools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<String>,
+}
+
+/// Adapts LLM arguments to a tool's schema. Steps run in field order: renames first,
+/// so the other steps use the tool's argument names.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ArgumentTransform {
+    /// New names for arguments, keyed by the name the LLM used
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rename: HashMap<String, String>,
+    /// Arguments removed before the call
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub drop: Vec<String>,
+    /// Types arguments are converted to, when the value allows it
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub coerce: HashMap<String, ArgumentType>,
+    /// Values set on every call, replacing the LLM's. Unlike `arguments`, they stay in
+    /// the schema the LLM sees.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub set: HashMap<String, serde_json::Value>,
+}
+
+impl ArgumentTransform {
+    pub fn is_empty(&self) -> bool {
+        self.rename.is_empty()
+            && self.drop.is_empty()
+            && self.coerce.is_empty()
+            && self.set.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArgumentType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    /// Single values are wrapped in an array
+    Array,
+    /// JSON text is parsed into an object
+    Object,
+}
+
+impl ToolSettings {
+    /// Names of the arguments the agent fills in instead of the LLM
+    pub fn injected_arguments(&self) -> impl Iterator<Item = &String> {
+        self.arguments.keys().chain(self.secrets.keys())
+    }
+}
+
+/// Where a secret's value comes from: `{env: NAME}` or `{value: ...}`
+#[derive(Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum SecretSource {
+    /// Environment variable holding the value
+    Env { env: String },
+    /// The value itself
+    Value { value: String },
+}
+
+impl SecretSource {
+    pub fn resolve(&self) -> crate::Result<String> {
+        match self {
+            Self::Env { env } => std::env::var(env).map_err(|_| {
+                crate::Error::config(format!("Environment variable {env} is not set"))
+            }),
+            Self::Value { value } => Ok(value.clone()),
+        }
+    }
+}
+
+// Keeps secret values out of logs
+impl std::fmt::Debug for SecretSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+            Self::Value { .. } => f.write_str("Value { value: *** }"),
+        }
+    }
+}
+
+impl Default for ToolSettings {
+    fn default() -> Self {
+        Self {
+            context: ToolContextMode::default(),
+            context_max_messages: default_context_max_messages(),
+            output_format: None,
+            output_schema: None,
+            require_approval: None,
+            arguments: HashMap::new(),
+            secrets: HashMap::new(),
+            transform: ArgumentTransform::default(),
+            cost: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolContextMode {
+    #[default]
+    None,
+    Question,
+    Transcript,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolOutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UnitsToolConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub currency: CurrencyConfig,
+}
+
+impl Default for UnitsToolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            currency: CurrencyConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalculatorToolConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl Default for CalculatorToolConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DateTimeToolConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// IANA timezone used when the model does not pass one; UTC when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl Default for DateTimeToolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timezone: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CurrencyConfig {
+    /// Where exchange rates come from; currency conversion is disabled when `none`
+    #[serde(default)]
+    pub provider: CurrencyProvider,
+    /// Currency the static `rates` are expressed against
+    #[serde(default = "default_base_currency")]
+    pub base: String,
+    /// Static rates: units of each currency per one unit of `base`
+    #[serde(default)]
+    pub rates: HashMap<String, f64>,
+    /// Override for the Frankfurter API endpoint
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+impl Default for CurrencyConfig {
+    fn default() -> Self {
+        Self {
+            provider: CurrencyProvider::default(),
+            base: default_base_currency(),
+            rates: HashMap::new(),
+            base_url: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CurrencyProvider {
+    #[default]
+    None,
+    Static,
+    Frankfurter,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebSearchToolConfig {
+    /// Search backend; the tool is disabled when `none`
+    #[serde(default)]
+    pub provider: WebSearchProvider,
+    /// Instance URL (required for SearxNG) or endpoint override for hosted providers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// API key for Brave and Tavily
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Results returned when the model does not ask for a specific number
+    #[serde(default = "default_search_results")]
+    pub max_results: usize,
+}
+
+impl Default for WebSearchToolConfig {
+    fn default() -> Self {
+        Self {
+            provider: WebSearchProvider::default(),
+            base_url: None,
+            api_key: None,
+            max_results: default_search_results(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebSearchProvider {
+    #[default]
+    None,
+    Searxng,
+    Brave,
+    Tavily,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct EmailToolConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Sender address used by `send_email`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    /// Recipients `send_email` may write to: full addresses or `@domain` entries.
+    /// An empty list allows any recipient.
+    #[serde(default)]
+    pub allowed_recipients: Vec<String>,
+    /// Enables `send_email`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smtp: Option<SmtpConfig>,
+    /// Enables `search_inbox`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imap: Option<ImapConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    #[default]
+    Starttls,
+    Tls,
+    None,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ImapConfig {
+    pub host: String,
+    #[serde(default = "default_imap_port")]
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    #[serde(default = "default_mailbox")]
+    pub mailbox: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CalendarToolConfig {
+    /// Calendar service; the tools are disabled when `none`. Times are shown in
+    /// `tools.datetime.timezone`.
+    #[serde(default)]
+    pub provider: CalendarProvider,
+    /// CalDAV calendar collection URL, or an endpoint override for Google
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Google calendar to use
+    #[serde(default = "default_calendar_id")]
+    pub calendar_id: String,
+    /// Google OAuth client credentials and a refresh token with calendar scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl Default for CalendarToolConfig {
+    fn default() -> Self {
+        Self {
+            provider: CalendarProvider::default(),
+            url: None,
+            username: None,
+            password: None,
+            calendar_id: default_calendar_id(),
+            client_id: None,
+            client_secret: None,
+            refresh_token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarProvider {
+    #[default]
+    None,
+    Caldav,
+    Google,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ApprovalConfig {
+    /// Endpoint receiving approval requests; calls needing approval are denied when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// How long to wait for a decision before denying the call
+    #[serde(default = "default_approval_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for ApprovalConfig {
+    fn default() -> Self {
+        Self {
+            webhook_url: None,
+            timeout_secs: default_approval_timeout_secs(),
+        }
+    }
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            level: default_log_level(),
+        }
+    }
+}
+
+pub fn default_provider() -> String {
+    "openai".to_string()
+}
+
+pub fn default_max_continuations() -> usize {
+    2
+}
+
+pub fn default_max_turns() -> usize {
+    5
+}
+
+pub fn default_empty_response_retries() -> usize {
+    1
+}
+
+pub fn default_structured_output_retries() -> usize {
+    2
+}
+
+pub fn default_empty_response_nudge() -> String {
+    "Your previous reply was empty. Answer the user's last message.".to_string()
+}
+
+pub fn default_max_subquestions() -> usize {
+    4
+}
+
+pub fn default_max_concurrency() -> usize {
+    2
+}
+
+pub fn default_confidence_threshold() -> f64 {
+    0.5
+}
+
+pub fn default_moderation_model() -> String {
+    "omni-moderation-latest".to_string()
+}
+
+pub fn default_blocked_reply() -> String {
+    "Sorry, I can't help with that.".to_string()
+}
+
+pub fn default_partial_reply_interval_ms() -> u64 {
+    1000
+}
+
+pub fn default_host() -> String {
+    "0.0.0.0".to_string()
+}
+
+pub fn default_port() -> u16 {
+    8080
+}
+
+pub fn default_log_level() -> String {
+    "info".to_string()
+}
+
+pub fn default_database_path() -> String {
+    "history.db".to_string()
+}
+
+pub fn default_purge_after_days() -> u64 {
+    30
+}
+
+pub fn default_busy_timeout_ms() -> u64 {
+    5000
+}
+
+pub fn default_data_dir() -> String {
+    "data".to_string()
+}
+
+pub fn default_feed_interval_minutes() -> u64 {
+    30
+}
+
+pub fn default_embedding_model() -> String {
+    "text-embedding-3-small".to_string()
+}
+
+pub fn default_chunk_size() -> usize {
+    1000
+}
+
+pub fn default_chunk_overlap() -> usize {
+    200
+}
+
+pub fn default_knowledge_top_k() -> usize {
+    4
+}
+
+pub fn default_rerank_top_k_in() -> usize {
+    20
+}
+
+pub fn default_cache_capacity() -> usize {
+    1000
+}
+
+pub fn default_context_max_messages() -> usize {
+    10
+}
+
+pub fn default_search_results() -> usize {
+    5
+}
+
+pub fn default_smtp_port() -> u16 {
+    587
+}
+
+pub fn default_imap_port() -> u16 {
+    993
+}
+
+pub fn default_mailbox() -> String {
+    "INBOX".to_string()
+}
+
+pub fn default_calendar_id() -> String {
+    "primary".to_string()
+}
+
+pub fn default_approval_timeout_secs() -> u64 {
+    60
+}
+
+pub fn default_heartbeat_after_ms() -> u64 {
+    10_000
+}
+
+pub fn default_heartbeat_interval_ms() -> u64 {
+    10_000
+}
+
+pub fn default_llm_retry_max_attempts() -> u32 {
+    3
+}
+
+pub fn default_llm_retry_initial_backoff_ms() -> u64 {
+    500
+}
+
+pub fn default_llm_retry_max_backoff_ms() -> u64 {
+    20_000
+}
+
+pub fn default_llm_pacing_max_delay_ms() -> u64 {
+    10_000
+}
+
+pub fn default_llm_request_timeout() -> u64 {
+    300
+}
+
+pub fn default_llm_connect_timeout() -> u64 {
+    10
+}
+
+pub fn default_canary_every() -> String {
+    "6h".to_string()
+}
+
+pub fn default_canary_min_similarity() -> f64 {
+    0.8
+}
+
+pub fn default_canary_max_latency_factor() -> f64 {
+    3.0
+}
+
+pub fn default_true() -> bool {
+    true
+}
+
+pub fn default_base_currency() -> String {
+    "USD".to_string()
 }
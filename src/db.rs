@@ -0,0 +1,57 @@
+//! Connections to the SQLite databases behind the history and the other stores, tuned by
+//! `server.database`.
+
+use crate::{Result, config::DatabaseConfig};
+use libsql::{Builder, Connection, Rows, Value};
+use std::hash::Hasher;
+
+/// Opens a connection to the database at `db_path`, or an in-memory one for
+/// `:memory:`, and tunes it with `config`. Stores keep the one connection this returns
+/// for as long as they live, because an in-memory database, schema included, goes away
+/// with its connection.
+pub async fn connect(db_path: &str, config: &DatabaseConfig) -> Result<Connection> {
+    let db = Builder::new_local(db_path).build().await?;
+    let conn = db.connect()?;
+    tune(&conn, config, db_path == ":memory:").await?;
+    Ok(conn)
+}
+
+/// Applies `config` to a connection. In-memory databases keep their journal mode.
+pub async fn tune(conn: &Connection, config: &DatabaseConfig, in_memory: bool) -> Result<()> {
+    // Pragmas that set a value also return it, so they run as queries
+    let mut pragmas = vec![
+        format!("PRAGMA busy_timeout = {}", config.busy_timeout_ms),
+        format!("PRAGMA synchronous = {}", config.synchronous.as_sql()),
+    ];
+    if !in_memory {
+        pragmas.push(format!(
+            "PRAGMA journal_mode = {}",
+            config.journal_mode.as_sql()
+        ));
+    }
+    for pragma in pragmas {
+        let mut rows = conn.query(&pragma, ()).await?;
+        while rows.next().await?.is_some() {}
+    }
+    Ok(())
+}
+
+/// Feeds every value of `rows` to `hasher`, so that stores can tell clients whether what
+/// a query returns changed without sending it
+pub async fn hash_rows(mut rows: Rows, hasher: &mut impl Hasher) -> Result<()> {
+    let columns = rows.column_count();
+    while let Some(row) = rows.next().await? {
+        for i in 0..columns {
+            match row.get_value(i)? {
+                Value::Null => hasher.write_u8(0),
+                Value::Integer(n) => hasher.write_i64(n),
+                Value::Real(x) => hasher.write_u64(x.to_bits()),
+                Value::Text(text) => hasher.write(text.as_bytes()),
+                Value::Blob(bytes) => hasher.write(&bytes),
+            }
+            // Separates values, so that moving bytes from one to the next changes the hash
+            hasher.write_u8(0xff);
+        }
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use std::time::Duration;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -13,11 +14,27 @@ pub enum Error {
     #[error("LLM error: {0}")]
     Llm(String),
 
+    /// The LLM API answered with an error status
+    #[error("LLM error: {message}")]
+    LlmStatus {
+        status: u16,
+        message: String,
+        /// How long the API asked to wait before trying again
+        retry_after: Option<Duration>,
+    },
+
     #[error("MCP error: {0}")]
     Mcp(String),
 
-    #[error("FSM error: {0}")]
-    Fsm(String),
+    /// The agent's state machine failed; `diagnostics` describe where a stuck run stopped
+    #[error("FSM error: {message}")]
+    Fsm {
+        message: String,
+        diagnostics: Option<Box<crate::agent::FsmDiagnostics>>,
+    },
+
+    #[error("Tool error: {0}")]
+    Tool(String),
 
     #[error("HTTP error: {0}")]
     Http(#[from] axum::Error),
@@ -64,8 +81,24 @@ impl Clone for Error {
         match self {
             Self::Config(s) => Self::Config(s.clone()),
             Self::Llm(s) => Self::Llm(s.clone()),
+            Self::LlmStatus {
+                status,
+                message,
+                retry_after,
+            } => Self::LlmStatus {
+                status: *status,
+                message: message.clone(),
+                retry_after: *retry_after,
+            },
             Self::Mcp(s) => Self::Mcp(s.clone()),
-            Self::Fsm(s) => Self::Fsm(s.clone()),
+            Self::Fsm {
+                message,
+                diagnostics,
+            } => Self::Fsm {
+                message: message.clone(),
+                diagnostics: diagnostics.clone(),
+            },
+            Self::Tool(s) => Self::Tool(s.clone()),
             Self::InvalidTransition { current, requested } => Self::InvalidTransition {
                 current: current.clone(),
                 requested: requested.clone(),
@@ -103,17 +136,112 @@ impl Error {
         Self::Llm(msg.into())
     }
 
+    pub fn llm_status(status: u16, msg: impl Into<String>) -> Self {
+        Self::LlmStatus {
+            status,
+            message: msg.into(),
+            retry_after: None,
+        }
+    }
+
     pub fn mcp(msg: impl Into<String>) -> Self {
         Self::Mcp(msg.into())
     }
 
     pub fn fsm(msg: impl Into<String>) -> Self {
-        Self::Fsm(msg.into())
+        Self::Fsm {
+            message: msg.into(),
+            diagnostics: None,
+        }
+    }
+
+    /// A run the watchdog stopped, described by `diagnostics`
+    pub fn fsm_stuck(diagnostics: crate::agent::FsmDiagnostics) -> Self {
+        Self::Fsm {
+            message: format!(
+                "run stuck in {} after {} iterations: {}",
+                diagnostics.state, diagnostics.iterations, diagnostics.reason
+            ),
+            diagnostics: Some(Box::new(diagnostics)),
+        }
+    }
+
+    /// Where the state machine was stopped, for FSM errors of stuck runs
+    pub fn fsm_diagnostics(&self) -> Option<&crate::agent::FsmDiagnostics> {
+        match self {
+            Self::Fsm { diagnostics, .. } => diagnostics.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub fn tool(msg: impl Into<String>) -> Self {
+        Self::Tool(msg.into())
     }
 
     pub fn internal(msg: impl Into<String>) -> Self {
         Self::Internal(msg.into())
     }
+
+    /// Whether a failed LLM call may succeed when tried again or with another provider:
+    /// network failures, rate limits and server errors, but not rejected requests
+    pub fn is_retryable(&self) -> bool {
+        use async_openai::error::OpenAIError;
+        match self {
+            Self::LlmStatus { status, .. } => *status == 429 || *status >= 500,
+            Self::Network(e) | Self::OpenAi(OpenAIError::Reqwest(e)) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.is_request()
+                    || e.status()
+                        .is_some_and(|status| status.is_server_error() || status.as_u16() == 429)
+            }
+            // Server errors come without a type; rejected requests are invalid ones
+            Self::OpenAi(OpenAIError::ApiError(e)) => {
+                e.r#type.as_deref() != Some("invalid_request_error")
+            }
+            // Streams cut off and garbled bodies from gateways in front of the API
+            Self::OpenAi(OpenAIError::StreamError(_) | OpenAIError::JSONDeserialize(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the provider rejected the request for exceeding the model's context
+    pub fn is_context_length_exceeded(&self) -> bool {
+        use async_openai::error::OpenAIError;
+        const MESSAGES: [&str; 6] = [
+            "maximum context length",
+            "context length",
+            "context window",
+            "too many tokens",
+            "exceeds the maximum number of tokens",
+            "prompt is too long",
+        ];
+        let message = match self {
+            Self::OpenAi(OpenAIError::ApiError(e)) => {
+                if e.code.as_deref() == Some("context_length_exceeded") {
+                    return true;
+                }
+                &e.message
+            }
+            Self::LlmStatus {
+                status: 400 | 413,
+                message,
+                ..
+            } => message,
+            Self::Llm(message) => message,
+            _ => return false,
+        };
+        let message = message.to_lowercase();
+        MESSAGES.iter().any(|m| message.contains(m))
+    }
+
+    /// How long the API asked to wait before trying again, from its `Retry-After`
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::LlmStatus { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -138,6 +266,9 @@ mod tests {
         let fsm_err = Error::fsm("Invalid state transition");
         assert_eq!(fsm_err.to_string(), "FSM error: Invalid state transition");
 
+        let tool_err = Error::tool("Missing argument");
+        assert_eq!(tool_err.to_string(), "Tool error: Missing argument");
+
         let internal_err = Error::internal("Internal server error");
         assert_eq!(
             internal_err.to_string(),
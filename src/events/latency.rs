@@ -0,0 +1,179 @@
+//! Flamegraph-style latency breakdown of a run, built from its recorded events: the run
+//! splits into turns, and each turn into its LLM calls and tool calls.
+
+use super::{RunEvent, RunEventKind};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Latency source of LLM calls in [`LatencyBreakdown::by_source`]
+pub const LLM_SOURCE: &str = "llm";
+/// Latency source of native tool calls in [`LatencyBreakdown::by_source`]
+pub const NATIVE_SOURCE: &str = "native";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpanKind {
+    Run,
+    Turn,
+    LlmCall,
+    ToolCall,
+}
+
+/// A timed part of a run, with the parts it is made of
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Span {
+    /// `run`, `turn M`, `llm call N` or `tool X`
+    pub name: String,
+    pub kind: SpanKind,
+    /// MCP server of a tool call; unset for native tools and other spans
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// Milliseconds from the start of the run
+    pub start_ms: i64,
+    pub duration_ms: i64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Span>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LatencyBreakdown {
+    pub root: Span,
+    /// Total milliseconds spent in LLM calls (`llm`), native tools (`native`) and the
+    /// tools of each MCP server (`mcp:<server>`)
+    pub by_source: BTreeMap<String, i64>,
+}
+
+impl LatencyBreakdown {
+    /// Breaks down the run the events belong to. Calls left open, as when the run
+    /// failed midway, end with the last event.
+    pub fn from_events(events: &[RunEvent]) -> Option<Self> {
+        let run_start = events.first()?.at;
+        let run_end = events.last()?.at;
+        let mut builder = Builder {
+            run_start,
+            turns: Vec::new(),
+            by_source: BTreeMap::new(),
+        };
+        let mut open_llm_calls: Vec<(usize, DateTime<Utc>)> = Vec::new();
+        let mut open_tool_calls: Vec<(&str, Option<&str>, DateTime<Utc>)> = Vec::new();
+        let mut llm_calls = 0;
+
+        for event in events {
+            match &event.kind {
+                RunEventKind::LlmCall { turn } => {
+                    builder.enter_turn(*turn, event.at);
+                    llm_calls += 1;
+                    open_llm_calls.push((llm_calls, event.at));
+                }
+                RunEventKind::LlmResponse { .. } if !open_llm_calls.is_empty() => {
+                    let (number, start) = open_llm_calls.remove(0);
+                    builder.llm_call(number, start, event.at);
+                }
+                RunEventKind::ToolCall { name, server, .. } => {
+                    open_tool_calls.push((name, server.as_deref(), event.at));
+                }
+                RunEventKind::ToolResult { name, .. } => {
+                    if let Some(index) = open_tool_calls.iter().position(|(n, ..)| n == name) {
+                        let (name, server, start) = open_tool_calls.remove(index);
+                        builder.tool_call(name, server, start, event.at);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Calls that never finished
+        for (number, start) in open_llm_calls {
+            builder.llm_call(number, start, run_end);
+        }
+        for (name, server, start) in open_tool_calls {
+            builder.tool_call(name, server, start, run_end);
+        }
+
+        let mut turns = builder.turns;
+        if let Some(turn) = turns.last_mut() {
+            turn.duration_ms = offset(run_start, run_end) - turn.start_ms;
+        }
+        for turn in &mut turns {
+            turn.children.sort_by_key(|span| span.start_ms);
+        }
+        Some(Self {
+            root: Span {
+                name: "run".to_string(),
+                kind: SpanKind::Run,
+                server: None,
+                start_ms: 0,
+                duration_ms: offset(run_start, run_end),
+                children: turns,
+            },
+            by_source: builder.by_source,
+        })
+    }
+}
+
+/// Milliseconds from `run_start` to `at`
+fn offset(run_start: DateTime<Utc>, at: DateTime<Utc>) -> i64 {
+    (at - run_start).num_milliseconds().max(0)
+}
+
+struct Builder {
+    run_start: DateTime<Utc>,
+    turns: Vec<Span>,
+    by_source: BTreeMap<String, i64>,
+}
+
+impl Builder {
+    fn span(&self, name: String, kind: SpanKind, start: DateTime<Utc>, end: DateTime<Utc>) -> Span {
+        Span {
+            name,
+            kind,
+            server: None,
+            start_ms: offset(self.run_start, start),
+            duration_ms: (end - start).num_milliseconds().max(0),
+            children: Vec::new(),
+        }
+    }
+
+    /// Starts a span for `turn` unless it is the current one, ending the previous turn
+    fn enter_turn(&mut self, turn: usize, at: DateTime<Utc>) {
+        let name = format!("turn {turn}");
+        if self.turns.last().is_some_and(|t| t.name == name) {
+            return;
+        }
+        let start_ms = offset(self.run_start, at);
+        if let Some(previous) = self.turns.last_mut() {
+            previous.duration_ms = start_ms - previous.start_ms;
+        }
+        let span = self.span(name, SpanKind::Turn, at, at);
+        self.turns.push(span);
+    }
+
+    fn llm_call(&mut self, number: usize, start: DateTime<Utc>, end: DateTime<Utc>) {
+        let span = self.span(format!("llm call {number}"), SpanKind::LlmCall, start, end);
+        self.add(LLM_SOURCE.to_string(), span);
+    }
+
+    fn tool_call(
+        &mut self,
+        name: &str,
+        server: Option<&str>,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) {
+        let mut span = self.span(format!("tool {name}"), SpanKind::ToolCall, start, end);
+        span.server = server.map(str::to_string);
+        let source = match server {
+            Some(server) => format!("mcp:{server}"),
+            None => NATIVE_SOURCE.to_string(),
+        };
+        self.add(source, span);
+    }
+
+    fn add(&mut self, source: String, span: Span) {
+        *self.by_source.entry(source).or_default() += span.duration_ms;
+        if let Some(turn) = self.turns.last_mut() {
+            turn.children.push(span);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{EventSubscriber, SessionEvent, SessionEventKind};
+use crate::security::escape_label;
+use async_trait::async_trait;
+use std::{collections::BTreeMap, fmt::Write, sync::Mutex};
+
+/// Counts of runs, LLM calls and tool calls, kept from the session events for `/metrics`
+#[derive(Default)]
+pub struct RunMetrics {
+    counts: Mutex<Counts>,
+}
+
+#[derive(Default)]
+struct Counts {
+    runs_completed: u64,
+    runs_failed: u64,
+    llm_calls: u64,
+    /// Successful and failed calls by tool name
+    tool_calls: BTreeMap<String, (u64, u64)>,
+}
+
+impl RunMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The counts in the Prometheus text format
+    pub fn render(&self) -> String {
+        let counts = self.counts.lock().unwrap();
+        let mut metrics = String::from(
+            "# HELP jarvis_runs_total Agent runs, by outcome.\n\
+             # TYPE jarvis_runs_total counter\n",
+        );
+        let _ = writeln!(
+            metrics,
+            "jarvis_runs_total{{outcome=\"completed\"}} {}",
+            counts.runs_completed
+        );
+        let _ = writeln!(
+            metrics,
+            "jarvis_runs_total{{outcome=\"failed\"}} {}",
+            counts.runs_failed
+        );
+        metrics.push_str(
+            "# HELP jarvis_llm_calls_total LLM calls of agent runs.\n\
+             # TYPE jarvis_llm_calls_total counter\n",
+        );
+        let _ = writeln!(metrics, "jarvis_llm_calls_total {}", counts.llm_calls);
+        metrics.push_str(
+            "# HELP jarvis_tool_calls_total Tool calls, by tool and outcome.\n\
+             # TYPE jarvis_tool_calls_total counter\n",
+        );
+        for (tool, (ok, failed)) in &counts.tool_calls {
+            let tool = escape_label(tool);
+            let _ = writeln!(
+                metrics,
+                "jarvis_tool_calls_total{{tool=\"{tool}\",outcome=\"ok\"}} {ok}"
+            );
+            let _ = writeln!(
+                metrics,
+                "jarvis_tool_calls_total{{tool=\"{tool}\",outcome=\"error\"}} {failed}"
+            );
+        }
+        metrics
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for RunMetrics {
+    async fn on_event(&self, event: &SessionEvent) {
+        let mut counts = self.counts.lock().unwrap();
+        match &event.kind {
+            SessionEventKind::RunCompleted => counts.runs_completed += 1,
+            SessionEventKind::RunFailed { .. } => counts.runs_failed += 1,
+            SessionEventKind::LlmCall { .. } => counts.llm_calls += 1,
+            SessionEventKind::ToolResult { name, is_error } => {
+                let (ok, failed) = counts.tool_calls.entry(name.clone()).or_default();
+                if *is_error {
+                    *failed += 1;
+                } else {
+                    *ok += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Live session events (new messages, LLM and tool activity) broadcast to subscribers
+//! such as the `GET /sessions/{id}/events` stream, the run metrics and the notifications
+//! of failed runs. The agent publishes them once through [`SessionEventHook`]; subsystems
+//! reacting to runs subscribe with an [`EventSubscriber`] rather than another hook.
+
+mod latency;
+mod metrics;
+mod progress;
+mod timeline;
+
+pub use latency::{LLM_SOURCE, LatencyBreakdown, NATIVE_SOURCE, Span, SpanKind};
+pub use metrics::RunMetrics;
+pub use progress::{ProgressUpdate, placeholder, progress_stream};
+pub use timeline::{
+    LlmCallPayload, RunEvent, RunEventHook, RunEventKind, RunEventStore, redact_keys,
+};
+
+use crate::{
+    Result,
+    agent::{AgentHook, CONTEXT_ARGUMENT, HookContext},
+    llm::ChatCompletionRequest,
+    mcp::{McpProgress, McpToolCallRequest, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionEvent {
+    pub session_id: String,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: SessionEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEventKind {
+    /// The next request came from a conversation of an integration, which gets the
+    /// reply in its response
+    Channel {
+        integration: String,
+        conversation_id: String,
+    },
+    /// A message was added to the conversation
+    Message {
+        role: String,
+        content: String,
+    },
+    /// Text the assistant said alongside tool calls, before running them
+    Commentary {
+        content: String,
+    },
+    /// The LLM is being called for the given turn
+    LlmCall {
+        turn: usize,
+    },
+    ToolCall {
+        name: String,
+        arguments: Value,
+    },
+    /// A tool call is still running
+    ToolHeartbeat {
+        name: String,
+        elapsed_ms: u64,
+        /// Latest progress the tool reported
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        progress: Option<McpProgress>,
+    },
+    ToolResult {
+        name: String,
+        is_error: bool,
+    },
+    RunCompleted,
+    RunFailed {
+        error: String,
+    },
+}
+
+impl SessionEventKind {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            SessionEventKind::Channel { .. } => "channel",
+            SessionEventKind::Message { .. } => "message",
+            SessionEventKind::Commentary { .. } => "commentary",
+            SessionEventKind::LlmCall { .. } => "llm_call",
+            SessionEventKind::ToolCall { .. } => "tool_call",
+            SessionEventKind::ToolHeartbeat { .. } => "tool_heartbeat",
+            SessionEventKind::ToolResult { .. } => "tool_result",
+            SessionEventKind::RunCompleted => "run_completed",
+            SessionEventKind::RunFailed { .. } => "run_failed",
+        }
+    }
+}
+
+/// Fan-out of session events to any number of subscribers. Events published while
+/// nobody listens are dropped.
+pub struct SessionEvents {
+    sender: broadcast::Sender<SessionEvent>,
+}
+
+impl SessionEvents {
+    /// `capacity` bounds how far a slow subscriber may fall behind before missing events
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn publish(&self, session_id: &str, kind: SessionEventKind) {
+        let _ = self.sender.send(SessionEvent {
+            session_id: session_id.to_string(),
+            at: Utc::now(),
+            kind,
+        });
+    }
+
+    /// Receives events of every session from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SessionEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Hands every event published from now on to `subscriber`, in order, until the
+    /// events are dropped
+    pub fn spawn_subscriber(
+        &self,
+        name: &'static str,
+        subscriber: Arc<dyn EventSubscriber>,
+    ) -> JoinHandle<()> {
+        let mut receiver = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => subscriber.on_event(&event).await,
+                    Err(RecvError::Lagged(missed)) => {
+                        warn!("Event subscriber {} missed {} events", name, missed)
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+/// A subsystem reacting to session events, see [`SessionEvents::spawn_subscriber`]
+#[async_trait]
+pub trait EventSubscriber: Send + Sync {
+    async fn on_event(&self, event: &SessionEvent);
+}
+
+/// Publishes the agent's run activity for each session
+pub struct SessionEventHook {
+    events: Arc<SessionEvents>,
+}
+
+impl SessionEventHook {
+    pub fn new(events: Arc<SessionEvents>) -> Self {
+        Self { events }
+    }
+}
+
+#[async_trait]
+impl AgentHook for SessionEventHook {
+    async fn on_request(&self, ctx: &HookContext, input: &str) -> Result<()> {
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::Message {
+                role: "user".to_string(),
+                content: input.to_string(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        _request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        if ctx.preview {
+            return Ok(());
+        }
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::LlmCall { turn: ctx.turn },
+        );
+        Ok(())
+    }
+
+    async fn on_commentary(&self, ctx: &HookContext, content: &str) {
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::Commentary {
+                content: content.to_string(),
+            },
+        );
+    }
+
+    async fn before_tool(&self, ctx: &HookContext, call: &mut McpToolCallRequest) -> Result<()> {
+        // Injected conversation context is internal and can be large
+        let mut arguments = call.arguments.clone();
+        arguments.remove(CONTEXT_ARGUMENT);
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::ToolCall {
+                name: call.name.clone(),
+                arguments: serde_json::to_value(arguments).unwrap_or_default(),
+            },
+        );
+        Ok(())
+    }
+
+    async fn after_tool(
+        &self,
+        ctx: &HookContext,
+        call: &McpToolCallRequest,
+        response: &mut McpToolCallResponse,
+    ) -> Result<()> {
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::ToolResult {
+                name: call.name.clone(),
+                is_error: response.is_error,
+            },
+        );
+        Ok(())
+    }
+
+    async fn on_tool_heartbeat(
+        &self,
+        ctx: &HookContext,
+        call: &McpToolCallRequest,
+        elapsed: Duration,
+        progress: Option<&McpProgress>,
+    ) {
+        self.events.publish(
+            &ctx.session_id,
+            SessionEventKind::ToolHeartbeat {
+                name: call.name.clone(),
+                elapsed_ms: elapsed.as_millis() as u64,
+                progress: progress.cloned(),
+            },
+        );
+    }
+
+    async fn on_complete(&self, ctx: &HookContext, result: &Result<String>) {
+        match result {
+            Ok(output) => {
+                self.events.publish(
+                    &ctx.session_id,
+                    SessionEventKind::Message {
+                        role: "assistant".to_string(),
+                        content: output.clone(),
+                    },
+                );
+                self.events
+                    .publish(&ctx.session_id, SessionEventKind::RunCompleted);
+            }
+            Err(e) => self.events.publish(
+                &ctx.session_id,
+                SessionEventKind::RunFailed {
+                    error: e.to_string(),
+                },
+            ),
+        }
+    }
+}
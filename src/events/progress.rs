@@ -0,0 +1,126 @@
+use super::{SessionEvent, SessionEventKind, SessionEvents};
+use futures::Stream;
+use serde::Serialize;
+use std::{collections::VecDeque, time::Duration};
+use tokio::{
+    sync::broadcast::{Receiver, error::RecvError},
+    time::{Interval, MissedTickBehavior},
+};
+
+/// What a chat integration shows while a run is in progress
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProgressUpdate {
+    /// Send (or renew) the typing indicator
+    Typing,
+    /// Set the placeholder message to `text`
+    Placeholder { text: String },
+    /// The run ended; remove the placeholder in favor of the reply
+    Done { failed: bool },
+}
+
+impl ProgressUpdate {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProgressUpdate::Typing => "typing",
+            ProgressUpdate::Placeholder { .. } => "placeholder",
+            ProgressUpdate::Done { .. } => "done",
+        }
+    }
+}
+
+/// Text of the placeholder while `event` is the run's latest step
+pub fn placeholder(event: &SessionEventKind) -> Option<String> {
+    match event {
+        SessionEventKind::LlmCall { turn: 0 } => Some("Thinking…".to_string()),
+        SessionEventKind::LlmCall { .. } => Some("Reading the results…".to_string()),
+        SessionEventKind::Commentary { content } => Some(content.clone()),
+        SessionEventKind::ToolCall { name, .. } => Some(format!("Running {name}…")),
+        SessionEventKind::ToolHeartbeat {
+            name,
+            progress: Some(progress),
+            ..
+        } => Some(match (&progress.message, progress.total) {
+            (Some(message), _) => format!("Running {name}: {message}"),
+            (None, Some(total)) => format!("Running {name} ({}/{total})…", progress.progress),
+            (None, None) => format!("Still running {name}…"),
+        }),
+        SessionEventKind::ToolHeartbeat { name, .. } => Some(format!("Still running {name}…")),
+        _ => None,
+    }
+}
+
+struct ProgressState {
+    events: Receiver<SessionEvent>,
+    session_id: String,
+    typing: Interval,
+    placeholders: bool,
+    running: bool,
+    pending: VecDeque<ProgressUpdate>,
+}
+
+/// Progress of the runs of `session_id`: a typing indicator renewed every
+/// `typing_interval` while one runs and, if `placeholders`, the step it is on
+pub fn progress_stream(
+    events: &SessionEvents,
+    session_id: String,
+    typing_interval: Duration,
+    placeholders: bool,
+) -> impl Stream<Item = ProgressUpdate> + use<> {
+    let mut typing = tokio::time::interval(typing_interval);
+    typing.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let state = ProgressState {
+        events: events.subscribe(),
+        session_id,
+        typing,
+        placeholders,
+        running: false,
+        pending: VecDeque::new(),
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(update) = state.pending.pop_front() {
+                return Some((update, state));
+            }
+            tokio::select! {
+                _ = state.typing.tick(), if state.running => {
+                    return Some((ProgressUpdate::Typing, state));
+                }
+                received = state.events.recv() => match received {
+                    Ok(event) if event.session_id == state.session_id => state.apply(&event.kind),
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    })
+}
+
+impl ProgressState {
+    fn apply(&mut self, event: &SessionEventKind) {
+        match event {
+            SessionEventKind::RunCompleted | SessionEventKind::RunFailed { .. } => {
+                if self.running {
+                    self.running = false;
+                    self.pending.push_back(ProgressUpdate::Done {
+                        failed: matches!(event, SessionEventKind::RunFailed { .. }),
+                    });
+                }
+                return;
+            }
+            SessionEventKind::Message { role, .. } if role == "assistant" => return,
+            _ => {}
+        }
+        if !self.running {
+            self.running = true;
+            self.pending.push_back(ProgressUpdate::Typing);
+            self.typing.reset();
+        }
+        if self.placeholders
+            && let Some(text) = placeholder(event)
+        {
+            self.pending.push_back(ProgressUpdate::Placeholder { text });
+        }
+    }
+}
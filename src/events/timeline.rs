@@ -0,0 +1,507 @@
+//! Persisted run events: every state change, LLM call and tool call of a run, kept so
+//! its timeline can be looked up after the fact with `GET /runs/{id}/timeline`. The
+//! payload sent to the provider for each LLM call can be kept too, for
+//! `GET /runs/{id}/llm_calls/{n}`.
+
+use crate::{
+    Result,
+    agent::{AgentEvent, AgentHook, AgentState, CONTEXT_ARGUMENT, FsmDiagnostics, HookContext},
+    config::DatabaseConfig,
+    db,
+    llm::{ChatCompletionRequest, ChatCompletionResponse, ContextFallback, LlmClient},
+    mcp::{McpProgress, McpToolCallRequest, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunEvent {
+    pub run_id: String,
+    pub session_id: String,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: RunEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEventKind {
+    RunStarted {
+        input: String,
+    },
+    /// The run's state machine moved from one state to another
+    Transition {
+        from: String,
+        to: String,
+        event: String,
+    },
+    /// The LLM is being called for the given turn
+    LlmCall {
+        turn: usize,
+    },
+    LlmResponse {
+        turn: usize,
+        finish_reason: Option<String>,
+        tool_calls: usize,
+        prompt_tokens: Option<u32>,
+        completion_tokens: Option<u32>,
+        /// Fallback provider that answered, when the configured one failed
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        provider: Option<String>,
+        /// How the request was changed after exceeding the model's context
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        context_fallback: Option<ContextFallback>,
+    },
+    ToolCall {
+        name: String,
+        arguments: Value,
+        /// MCP server the tool belongs to; unset for native tools
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        server: Option<String>,
+    },
+    /// A tool call is still running
+    ToolHeartbeat {
+        name: String,
+        elapsed_ms: u64,
+        /// Latest progress the tool reported
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        progress: Option<McpProgress>,
+    },
+    ToolResult {
+        name: String,
+        is_error: bool,
+    },
+    RunCompleted,
+    RunFailed {
+        error: String,
+        /// Where the state machine was when the watchdog stopped the run
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fsm: Option<FsmDiagnostics>,
+    },
+}
+
+impl RunEventKind {
+    /// Name stored alongside the event, matching its serialized `type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            RunEventKind::RunStarted { .. } => "run_started",
+            RunEventKind::Transition { .. } => "transition",
+            RunEventKind::LlmCall { .. } => "llm_call",
+            RunEventKind::LlmResponse { .. } => "llm_response",
+            RunEventKind::ToolCall { .. } => "tool_call",
+            RunEventKind::ToolHeartbeat { .. } => "tool_heartbeat",
+            RunEventKind::ToolResult { .. } => "tool_result",
+            RunEventKind::RunCompleted => "run_completed",
+            RunEventKind::RunFailed { .. } => "run_failed",
+        }
+    }
+}
+
+/// Placeholder of keys removed from stored payloads
+const REDACTED: &str = "[redacted]";
+
+/// Payload fields holding credentials, matched case-insensitively
+const KEY_FIELDS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "api-key",
+    "x-api-key",
+    "x-goog-api-key",
+    "authorization",
+    "access_token",
+    "password",
+    "secret",
+];
+
+/// The payload sent to the provider for an LLM call of a run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LlmCallPayload {
+    pub run_id: String,
+    pub session_id: String,
+    /// Position of the call in the run, from 1
+    pub call: usize,
+    pub turn: usize,
+    pub at: DateTime<Utc>,
+    pub payload: Value,
+}
+
+/// Replaces credential fields and any occurrence of `keys` in `payload`
+pub fn redact_keys(payload: &mut Value, keys: &[String]) {
+    match payload {
+        Value::Object(fields) => {
+            for (name, value) in fields.iter_mut() {
+                if KEY_FIELDS.contains(&name.to_lowercase().as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_keys(value, keys);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(|value| redact_keys(value, keys)),
+        Value::String(text) => {
+            for key in keys.iter().filter(|key| !key.is_empty()) {
+                if text.contains(key.as_str()) {
+                    *text = text.replace(key.as_str(), REDACTED);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+pub struct RunEventStore {
+    conn: Connection,
+}
+
+impl RunEventStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id TEXT NOT NULL,
+                session_id TEXT NOT NULL,
+                at DATETIME NOT NULL,
+                kind TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_run_events_run_id ON run_events(run_id, id)",
+            (),
+        )
+        .await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_call_payloads (
+                run_id TEXT NOT NULL,
+                call INTEGER NOT NULL,
+                session_id TEXT NOT NULL,
+                turn INTEGER NOT NULL,
+                at DATETIME NOT NULL,
+                payload TEXT NOT NULL,
+                PRIMARY KEY (run_id, call)
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Run event store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    pub async fn record(&self, run_id: &str, session_id: &str, kind: RunEventKind) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO run_events (run_id, session_id, at, kind, data) VALUES (?, ?, ?, ?, ?)",
+                libsql::params![
+                    run_id,
+                    session_id,
+                    Utc::now().to_rfc3339(),
+                    kind.name(),
+                    serde_json::to_string(&kind)?,
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the payload of the next LLM call of `run_id`, returning its position
+    pub async fn record_llm_call(
+        &self,
+        run_id: &str,
+        session_id: &str,
+        turn: usize,
+        payload: &Value,
+    ) -> Result<usize> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT COALESCE(MAX(call), 0) + 1 FROM llm_call_payloads WHERE run_id = ?",
+                libsql::params![run_id],
+            )
+            .await?;
+        let call = match rows.next().await? {
+            Some(row) => row.get::<i64>(0)?,
+            None => 1,
+        };
+        self.conn
+            .execute(
+                "INSERT INTO llm_call_payloads (run_id, call, session_id, turn, at, payload) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    run_id,
+                    call,
+                    session_id,
+                    turn as i64,
+                    Utc::now().to_rfc3339(),
+                    serde_json::to_string(payload)?,
+                ],
+            )
+            .await?;
+        Ok(call as usize)
+    }
+
+    /// Payload of the `call`th LLM call of `run_id`, counting from 1
+    pub async fn llm_call(&self, run_id: &str, call: usize) -> Result<Option<LlmCallPayload>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT session_id, turn, at, payload FROM llm_call_payloads \
+                 WHERE run_id = ? AND call = ?",
+                libsql::params![run_id, call as i64],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let at: String = row.get(2)?;
+        let payload: String = row.get(3)?;
+        Ok(Some(LlmCallPayload {
+            run_id: run_id.to_string(),
+            session_id: row.get(0)?,
+            call,
+            turn: row.get::<i64>(1)? as usize,
+            at: DateTime::parse_from_rfc3339(&at)
+                .map(|at| at.with_timezone(&Utc))
+                .unwrap_or_default(),
+            payload: serde_json::from_str(&payload)?,
+        }))
+    }
+
+    /// Events of `run_id` in the order they happened; empty for unknown runs
+    pub async fn timeline(&self, run_id: &str) -> Result<Vec<RunEvent>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT session_id, at, data FROM run_events WHERE run_id = ? ORDER BY id",
+                libsql::params![run_id],
+            )
+            .await?;
+        let mut events = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let at: String = row.get(1)?;
+            let data: String = row.get(2)?;
+            events.push(RunEvent {
+                run_id: run_id.to_string(),
+                session_id: row.get(0)?,
+                at: DateTime::parse_from_rfc3339(&at)
+                    .map(|at| at.with_timezone(&Utc))
+                    .unwrap_or_default(),
+                kind: serde_json::from_str(&data)?,
+            });
+        }
+        Ok(events)
+    }
+}
+
+/// Records the activity of every run. Failing to record never fails the run.
+pub struct RunEventHook {
+    store: Arc<RunEventStore>,
+    /// MCP server of each tool, by tool name
+    tool_servers: HashMap<String, String>,
+    /// Builds the provider payload of each LLM call, and the keys to redact from it
+    payloads: Option<(Arc<dyn LlmClient>, Vec<String>)>,
+}
+
+impl RunEventHook {
+    pub fn new(store: Arc<RunEventStore>) -> Self {
+        Self {
+            store,
+            tool_servers: HashMap::new(),
+            payloads: None,
+        }
+    }
+
+    /// Stores the payload `llm` sends for each LLM call, with `keys` redacted from it
+    pub fn with_payloads(mut self, llm: Arc<dyn LlmClient>, keys: Vec<String>) -> Self {
+        self.payloads = Some((llm, keys));
+        self
+    }
+
+    /// Names the MCP server of each tool call, so timelines can tell servers apart
+    pub fn with_tool_servers(mut self, tool_servers: HashMap<String, String>) -> Self {
+        self.tool_servers = tool_servers;
+        self
+    }
+
+    async fn record(&self, ctx: &HookContext, kind: RunEventKind) {
+        let Some(run_id) = &ctx.run_id else {
+            return;
+        };
+        if ctx.preview {
+            return;
+        }
+        if let Err(e) = self.store.record(run_id, &ctx.session_id, kind).await {
+            warn!("Failed to record event of run {}: {}", run_id, e);
+        }
+    }
+
+    async fn record_payload(&self, ctx: &HookContext, request: &ChatCompletionRequest) {
+        let (Some(run_id), Some((llm, keys))) = (&ctx.run_id, &self.payloads) else {
+            return;
+        };
+        if ctx.preview {
+            return;
+        }
+        let mut payload = match llm.request_payload(request) {
+            Ok(payload) => payload,
+            Err(e) => {
+                warn!("Failed to build LLM payload of run {}: {}", run_id, e);
+                return;
+            }
+        };
+        redact_keys(&mut payload, keys);
+        if let Err(e) = self
+            .store
+            .record_llm_call(run_id, &ctx.session_id, ctx.turn, &payload)
+            .await
+        {
+            warn!("Failed to record LLM payload of run {}: {}", run_id, e);
+        }
+    }
+}
+
+#[async_trait]
+impl AgentHook for RunEventHook {
+    async fn on_request(&self, ctx: &HookContext, input: &str) -> Result<()> {
+        self.record(
+            ctx,
+            RunEventKind::RunStarted {
+                input: input.to_string(),
+            },
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        self.record(ctx, RunEventKind::LlmCall { turn: ctx.turn })
+            .await;
+        self.record_payload(ctx, request).await;
+        Ok(())
+    }
+
+    async fn after_llm_call(
+        &self,
+        ctx: &HookContext,
+        response: &ChatCompletionResponse,
+    ) -> Result<()> {
+        let choice = response.choices.first();
+        self.record(
+            ctx,
+            RunEventKind::LlmResponse {
+                turn: ctx.turn,
+                finish_reason: choice.and_then(|c| c.finish_reason.clone()),
+                tool_calls: choice
+                    .and_then(|c| c.message.tool_calls.as_ref())
+                    .map_or(0, Vec::len),
+                prompt_tokens: response.usage.as_ref().map(|u| u.prompt_tokens),
+                completion_tokens: response.usage.as_ref().map(|u| u.completion_tokens),
+                provider: response.provider.clone(),
+                context_fallback: response.context_fallback.clone(),
+            },
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn before_tool(&self, ctx: &HookContext, call: &mut McpToolCallRequest) -> Result<()> {
+        // Injected conversation context is internal and can be large
+        let mut arguments = call.arguments.clone();
+        arguments.remove(CONTEXT_ARGUMENT);
+        self.record(
+            ctx,
+            RunEventKind::ToolCall {
+                name: call.name.clone(),
+                arguments: serde_json::to_value(arguments).unwrap_or_default(),
+                server: self.tool_servers.get(&call.name).cloned(),
+            },
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn after_tool(
+        &self,
+        ctx: &HookContext,
+        call: &McpToolCallRequest,
+        response: &mut McpToolCallResponse,
+    ) -> Result<()> {
+        self.record(
+            ctx,
+            RunEventKind::ToolResult {
+                name: call.name.clone(),
+                is_error: response.is_error,
+            },
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn on_tool_heartbeat(
+        &self,
+        ctx: &HookContext,
+        call: &McpToolCallRequest,
+        elapsed: Duration,
+        progress: Option<&McpProgress>,
+    ) {
+        self.record(
+            ctx,
+            RunEventKind::ToolHeartbeat {
+                name: call.name.clone(),
+                elapsed_ms: elapsed.as_millis() as u64,
+                progress: progress.cloned(),
+            },
+        )
+        .await;
+    }
+
+    async fn on_transition(
+        &self,
+        ctx: &HookContext,
+        from: &AgentState,
+        to: &AgentState,
+        event: &AgentEvent,
+    ) {
+        self.record(
+            ctx,
+            RunEventKind::Transition {
+                from: format!("{from:?}"),
+                to: format!("{to:?}"),
+                event: format!("{event:?}"),
+            },
+        )
+        .await;
+    }
+
+    async fn on_complete(&self, ctx: &HookContext, result: &Result<String>) {
+        let kind = match result {
+            Ok(_) => RunEventKind::RunCompleted,
+            Err(e) => RunEventKind::RunFailed {
+                error: e.to_string(),
+                fsm: e.fsm_diagnostics().cloned(),
+            },
+        };
+        self.record(ctx, kind).await;
+    }
+}
@@ -0,0 +1,74 @@
+//! Configured example prompts showing off tools, shaped as quick replies: a short
+//! button label and the prompt sent when it is picked.
+
+use crate::config::ExampleConfig;
+use serde::Serialize;
+
+/// Longest label made from a prompt, so buttons stay readable in chat clients
+pub const MAX_LABEL_CHARS: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Example {
+    /// Button text
+    pub label: String,
+    /// Message sent as the user when the button is picked
+    pub prompt: String,
+    pub tool: Option<String>,
+    pub profile: Option<String>,
+}
+
+impl From<&ExampleConfig> for Example {
+    fn from(config: &ExampleConfig) -> Self {
+        Self {
+            label: config
+                .label
+                .clone()
+                .unwrap_or_else(|| shorten(&config.prompt)),
+            prompt: config.prompt.clone(),
+            tool: config.tool.clone(),
+            profile: config.profile.clone(),
+        }
+    }
+}
+
+/// Which examples to list
+#[derive(Debug, Clone, Default)]
+pub struct ExampleFilter {
+    pub tool: Option<String>,
+    /// Only examples for this profile and those for every profile; all when unset
+    pub profile: Option<String>,
+    pub limit: Option<usize>,
+}
+
+/// Examples matching `filter`, in config order
+pub fn list(examples: &[ExampleConfig], filter: &ExampleFilter) -> Vec<Example> {
+    examples
+        .iter()
+        .filter(|example| {
+            filter
+                .tool
+                .as_ref()
+                .is_none_or(|tool| example.tool.as_ref() == Some(tool))
+        })
+        .filter(|example| match (&filter.profile, &example.profile) {
+            (Some(wanted), Some(profile)) => wanted == profile,
+            _ => true,
+        })
+        .take(filter.limit.unwrap_or(usize::MAX))
+        .map(Example::from)
+        .collect()
+}
+
+/// The prompt cut to [`MAX_LABEL_CHARS`] at a word boundary, with an ellipsis when cut
+fn shorten(prompt: &str) -> String {
+    let prompt = prompt.trim();
+    if prompt.chars().count() <= MAX_LABEL_CHARS {
+        return prompt.to_string();
+    }
+    let cut: String = prompt.chars().take(MAX_LABEL_CHARS - 1).collect();
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(space) if space > 0 => &cut[..space],
+        _ => &cut,
+    };
+    format!("{}…", cut.trim_end())
+}
@@ -0,0 +1,145 @@
+//! RSS/Atom monitoring: a background task polls the configured feeds and stores new items.
+
+mod store;
+
+pub use store::FeedStore;
+
+use crate::{Error, Result, config::FeedConfig};
+use chrono::{DateTime, Utc};
+use std::{sync::Arc, time::Duration};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Longest summary kept per item
+const MAX_SUMMARY_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedItem {
+    /// Name of the configured feed the item came from
+    pub feed: String,
+    pub guid: String,
+    pub title: String,
+    pub link: Option<String>,
+    pub summary: Option<String>,
+    pub published: Option<DateTime<Utc>>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// Reduces HTML summaries to plain text
+fn strip_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn truncate(text: String, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text,
+    }
+}
+
+/// Parses an RSS, Atom or JSON Feed document into items
+pub fn parse_feed(
+    feed_name: &str,
+    body: &[u8],
+    fetched_at: DateTime<Utc>,
+) -> Result<Vec<FeedItem>> {
+    let feed = feed_rs::parser::parse(body)
+        .map_err(|e| Error::internal(format!("Failed to parse feed '{feed_name}': {e}")))?;
+
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| {
+            let summary = entry
+                .summary
+                .map(|s| s.content)
+                .or_else(|| entry.content.and_then(|c| c.body))
+                .map(|s| truncate(strip_html(&s), MAX_SUMMARY_CHARS))
+                .filter(|s| !s.is_empty());
+            FeedItem {
+                feed: feed_name.to_string(),
+                guid: entry.id,
+                title: entry
+                    .title
+                    .map(|t| strip_html(&t.content))
+                    .unwrap_or_else(|| "(untitled)".to_string()),
+                link: entry.links.into_iter().next().map(|l| l.href),
+                summary,
+                published: entry.published.or(entry.updated),
+                fetched_at,
+            }
+        })
+        .collect())
+}
+
+pub async fn fetch_feed(client: &reqwest::Client, feed: &FeedConfig) -> Result<Vec<FeedItem>> {
+    let body = client
+        .get(&feed.url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    parse_feed(&feed.name, &body, Utc::now())
+}
+
+/// Fetches a feed once and stores its new items, returning how many were new
+pub async fn poll_feed(
+    client: &reqwest::Client,
+    store: &FeedStore,
+    feed: &FeedConfig,
+) -> Result<usize> {
+    let items = fetch_feed(client, feed).await?;
+    let new_items = store.insert_new(&items).await?;
+    debug!(
+        "Feed '{}': {} items, {} new",
+        feed.name,
+        items.len(),
+        new_items
+    );
+    Ok(new_items)
+}
+
+/// Starts one polling task per feed
+pub fn spawn_monitor(feeds: Vec<FeedConfig>, store: Arc<FeedStore>) -> Vec<JoinHandle<()>> {
+    info!("Monitoring {} feeds", feeds.len());
+    let client = reqwest::Client::new();
+    feeds
+        .into_iter()
+        .map(|feed| {
+            let client = client.clone();
+            let store = store.clone();
+            tokio::spawn(async move {
+                let mut interval =
+                    tokio::time::interval(Duration::from_secs(feed.interval_minutes.max(1) * 60));
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = poll_feed(&client, &store, &feed).await {
+                        warn!("Failed to poll feed '{}': {}", feed.name, e);
+                    }
+                }
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,103 @@
+use super::FeedItem;
+use crate::{Error, Result, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use tracing::info;
+
+/// Persists fetched feed items, ignoring items that were already seen
+pub struct FeedStore {
+    conn: Connection,
+}
+
+impl FeedStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS feed_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                feed TEXT NOT NULL,
+                guid TEXT NOT NULL,
+                title TEXT NOT NULL,
+                link TEXT,
+                summary TEXT,
+                published_at DATETIME,
+                fetched_at DATETIME NOT NULL,
+                UNIQUE (feed, guid)
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Feed store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Stores items, returning how many were new
+    pub async fn insert_new(&self, items: &[FeedItem]) -> Result<usize> {
+        let mut inserted = 0;
+        for item in items {
+            inserted += self
+                .conn
+                .execute(
+                    "INSERT OR IGNORE INTO feed_items (feed, guid, title, link, summary, published_at, fetched_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                    libsql::params![
+                        item.feed.as_str(),
+                        item.guid.as_str(),
+                        item.title.as_str(),
+                        item.link.clone(),
+                        item.summary.clone(),
+                        item.published.map(|p| p.to_rfc3339()),
+                        item.fetched_at.to_rfc3339(),
+                    ],
+                )
+                .await? as usize;
+        }
+        Ok(inserted)
+    }
+
+    /// Items first seen after `since`, newest first
+    pub async fn recent(
+        &self,
+        since: DateTime<Utc>,
+        feed: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<FeedItem>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT feed, guid, title, link, summary, published_at, fetched_at FROM feed_items \
+                 WHERE fetched_at >= ?1 AND (?2 IS NULL OR feed = ?2) \
+                 ORDER BY COALESCE(published_at, fetched_at) DESC, id DESC LIMIT ?3",
+                libsql::params![since.to_rfc3339(), feed, limit as i64],
+            )
+            .await?;
+
+        let parse_time = |value: String| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))
+        };
+
+        let mut items = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let published: Option<String> = row.get(5)?;
+            items.push(FeedItem {
+                feed: row.get(0)?,
+                guid: row.get(1)?,
+                title: row.get(2)?,
+                link: row.get(3)?,
+                summary: row.get(4)?,
+                published: published.map(parse_time).transpose()?,
+                fetched_at: parse_time(row.get(6)?)?,
+            });
+        }
+        Ok(items)
+    }
+}
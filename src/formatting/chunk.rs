@@ -0,0 +1,194 @@
+//! Splitting of long replies into messages that fit a platform's size limit.
+
+/// Splits `text` into chunks of at most `max` characters, breaking between paragraphs
+/// where it can, else between lines, then words. Code blocks are kept whole when they fit;
+/// ones that don't are split between lines, closing the fence at the end of each chunk
+/// and reopening it at the start of the next. A `max` of 0 leaves the text whole.
+pub fn chunk(text: &str, max: usize) -> Vec<String> {
+    if max == 0 || len(text) <= max {
+        return vec![text.to_string()];
+    }
+    let mut pieces = Vec::new();
+    for (index, block) in blocks(text).into_iter().enumerate() {
+        let separator = if index == 0 { "" } else { "\n\n" };
+        match block {
+            Block::Code { opener, lines } => {
+                split_code(opener, &lines, max, separator, &mut pieces)
+            }
+            Block::Prose(lines) => split(&lines.join("\n"), max, separator, &mut pieces),
+        }
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for piece in pieces {
+        if current.is_empty() {
+            current = piece.text;
+        } else if len(&current) + len(piece.separator) + len(&piece.text) <= max {
+            current.push_str(piece.separator);
+            current.push_str(&piece.text);
+        } else {
+            chunks.push(std::mem::replace(&mut current, piece.text));
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Text no chunk boundary falls inside, and what joins it to the text before it
+struct Piece {
+    text: String,
+    separator: &'static str,
+}
+
+enum Block<'a> {
+    /// Lines of a paragraph, list or other text between blank lines
+    Prose(Vec<&'a str>),
+    /// A fenced code block: its opening fence line and its contents
+    Code {
+        opener: &'a str,
+        lines: Vec<&'a str>,
+    },
+}
+
+/// `text` as paragraphs and code blocks, which may contain blank lines
+fn blocks(text: &str) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    let mut prose = Vec::new();
+    let mut code: Option<(&str, Vec<&str>)> = None;
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if let Some((opener, lines)) = &mut code {
+            if trimmed.starts_with(fence(opener)) && trimmed.trim_end() == fence(trimmed) {
+                blocks.push(Block::Code {
+                    opener,
+                    lines: std::mem::take(lines),
+                });
+                code = None;
+            } else {
+                lines.push(line);
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            if !prose.is_empty() {
+                blocks.push(Block::Prose(std::mem::take(&mut prose)));
+            }
+            code = Some((line, Vec::new()));
+        } else if trimmed.is_empty() {
+            if !prose.is_empty() {
+                blocks.push(Block::Prose(std::mem::take(&mut prose)));
+            }
+        } else {
+            prose.push(line);
+        }
+    }
+    // An unclosed block runs to the end, and is closed in its chunks
+    if let Some((opener, lines)) = code {
+        blocks.push(Block::Code { opener, lines });
+    }
+    if !prose.is_empty() {
+        blocks.push(Block::Prose(prose));
+    }
+    blocks
+}
+
+/// The run of backticks or tildes a fence line starts with
+fn fence(line: &str) -> &str {
+    let line = line.trim_start();
+    let Some(marker) = line.chars().next() else {
+        return "";
+    };
+    let end = line.find(|c| c != marker).unwrap_or(line.len());
+    &line[..end]
+}
+
+fn split_code(
+    opener: &str,
+    lines: &[&str],
+    max: usize,
+    separator: &'static str,
+    pieces: &mut Vec<Piece>,
+) {
+    let closer = fence(opener);
+    let wrap = |code: &str| format!("{opener}\n{code}\n{closer}");
+    let whole = wrap(&lines.join("\n"));
+    if len(&whole) <= max {
+        pieces.push(Piece {
+            text: whole,
+            separator,
+        });
+        return;
+    }
+    let overhead = len(opener) + len(closer) + 2;
+    if overhead >= max {
+        // No room for the fences, so the code goes out as text
+        return split(&lines.join("\n"), max, separator, pieces);
+    }
+    let mut parts = Vec::new();
+    split(&lines.join("\n"), max - overhead, "", &mut parts);
+    // Put back together as many lines as fit in each fenced piece
+    let mut code: Option<String> = None;
+    let mut separator = separator;
+    for part in parts {
+        code = match code {
+            Some(mut code)
+                if len(&code) + len(part.separator) + len(&part.text) <= max - overhead =>
+            {
+                code.push_str(part.separator);
+                code.push_str(&part.text);
+                Some(code)
+            }
+            Some(code) => {
+                pieces.push(Piece {
+                    text: wrap(&code),
+                    separator,
+                });
+                separator = "\n";
+                Some(part.text)
+            }
+            None => Some(part.text),
+        };
+    }
+    if let Some(code) = code {
+        pieces.push(Piece {
+            text: wrap(&code),
+            separator,
+        });
+    }
+}
+
+/// Splits `text` between lines, then words, then characters, until each piece fits
+fn split(text: &str, max: usize, separator: &'static str, pieces: &mut Vec<Piece>) {
+    if len(text) <= max {
+        pieces.push(Piece {
+            text: text.to_string(),
+            separator,
+        });
+        return;
+    }
+    for finer in ["\n", " "] {
+        if text.contains(finer) {
+            for (index, part) in text.split(finer).enumerate() {
+                split(
+                    part,
+                    max,
+                    if index == 0 { separator } else { finer },
+                    pieces,
+                );
+            }
+            return;
+        }
+    }
+    let chars: Vec<char> = text.chars().collect();
+    for (index, part) in chars.chunks(max).enumerate() {
+        pieces.push(Piece {
+            text: part.iter().collect(),
+            separator: if index == 0 { separator } else { "" },
+        });
+    }
+}
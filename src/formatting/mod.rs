@@ -0,0 +1,277 @@
+//! Conversion of the Markdown replies the LLM writes into what chat platforms render:
+//! Telegram's HTML, Slack's mrkdwn, or plain text wrapped to a width, split into messages
+//! within the platform's size limit.
+
+mod chunk;
+
+pub use chunk::chunk;
+
+use crate::config::OutputFormat;
+
+/// `markdown` in `format`, as chunks of at most `max` characters
+pub fn render_chunks(
+    markdown: &str,
+    format: OutputFormat,
+    width: usize,
+    max: usize,
+) -> Vec<String> {
+    match format {
+        // Telegram counts the text left once the HTML is parsed, which the Markdown is no
+        // shorter than, and splitting the HTML could break its tags
+        OutputFormat::TelegramHtml => chunk(markdown, max)
+            .iter()
+            .map(|chunk| render(chunk, format, width))
+            .collect(),
+        _ => chunk(&render(markdown, format, width), max),
+    }
+}
+
+/// `markdown` in `format`; plain text lines are wrapped at `width` characters, unless 0
+pub fn render(markdown: &str, format: OutputFormat, width: usize) -> String {
+    if format == OutputFormat::Markdown {
+        return markdown.to_string();
+    }
+    let mut lines = Vec::new();
+    let mut code: Option<Vec<&str>> = None;
+    let mut language = "";
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            match code.take() {
+                Some(block) => lines.push(code_block(&block, language, format)),
+                None => {
+                    language = trimmed[3..].trim();
+                    code = Some(Vec::new());
+                }
+            }
+            continue;
+        }
+        match &mut code {
+            Some(block) => block.push(line),
+            None => lines.extend(block_line(line, format, width)),
+        }
+    }
+    // An unclosed block runs to the end
+    if let Some(block) = code {
+        lines.push(code_block(&block, language, format));
+    }
+    lines.join("\n")
+}
+
+fn code_block(lines: &[&str], language: &str, format: OutputFormat) -> String {
+    let code = lines.join("\n");
+    match format {
+        OutputFormat::TelegramHtml if language.is_empty() => {
+            format!("<pre>{}</pre>", escape(&code, format))
+        }
+        OutputFormat::TelegramHtml => format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>",
+            escape(language, format),
+            escape(&code, format)
+        ),
+        OutputFormat::SlackMrkdwn => format!("```\n{}\n```", escape(&code, format)),
+        OutputFormat::Plain | OutputFormat::Markdown => code,
+    }
+}
+
+/// A line outside code blocks, as one or more lines
+fn block_line(line: &str, format: OutputFormat, width: usize) -> Vec<String> {
+    let indent = &line[..line.len() - line.trim_start().len()];
+    let trimmed = line.trim();
+
+    if ["---", "***", "___"].contains(&trimmed) {
+        return Vec::new();
+    }
+    if let Some(heading) = heading(trimmed) {
+        let heading = inline(heading, format);
+        return vec![match format {
+            OutputFormat::TelegramHtml => format!("<b>{heading}</b>"),
+            OutputFormat::SlackMrkdwn => format!("*{heading}*"),
+            _ => heading,
+        }];
+    }
+    if let Some(quote) = trimmed.strip_prefix('>') {
+        let quote = inline(quote.trim_start(), format);
+        return match format {
+            OutputFormat::TelegramHtml => vec![format!("<blockquote>{quote}</blockquote>")],
+            OutputFormat::Plain => wrap(&quote, "> ", "> ", width),
+            _ => vec![format!("> {quote}")],
+        };
+    }
+    if let Some(item) = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+    {
+        let item = inline(item, format);
+        return match format {
+            OutputFormat::Plain => {
+                let marker = format!("{indent}- ");
+                wrap(&item, &marker, &" ".repeat(marker.len()), width)
+            }
+            _ => vec![format!("{indent}• {item}")],
+        };
+    }
+    let text = inline(trimmed, format);
+    match format {
+        OutputFormat::Plain => wrap(&text, indent, indent, width),
+        _ => vec![format!("{indent}{text}")],
+    }
+}
+
+/// Text of an ATX heading such as `## Title`
+fn heading(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let text = &line[level..];
+    (text.is_empty() || text.starts_with(' ')).then(|| text.trim())
+}
+
+/// Greedily fills lines of at most `width` characters, the first starting with `first`
+/// and the others with `rest`. Words longer than a line are kept whole.
+fn wrap(text: &str, first: &str, rest: &str, width: usize) -> Vec<String> {
+    if width == 0 || first.chars().count() + text.chars().count() <= width {
+        return vec![format!("{first}{text}")];
+    }
+    let mut lines = Vec::new();
+    let mut line = first.to_string();
+    let mut empty = true;
+    for word in text.split_whitespace() {
+        if !empty && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = rest.to_string();
+            empty = true;
+        }
+        if !empty {
+            line.push(' ');
+        }
+        line.push_str(word);
+        empty = false;
+    }
+    lines.push(line);
+    lines
+}
+
+/// Inline Markdown of the kinds every format has a counterpart for
+enum Span<'a> {
+    Code(&'a str),
+    Bold(&'a str),
+    Italic(&'a str),
+    Strike(&'a str),
+    Link { text: &'a str, url: &'a str },
+}
+
+fn inline(text: &str, format: OutputFormat) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut previous = None;
+    while let Some(c) = rest.chars().next() {
+        if let Some((span, after)) = span(rest, previous) {
+            out.push_str(&render_span(span, format));
+            previous = rest[..rest.len() - after.len()].chars().last();
+            rest = after;
+            continue;
+        }
+        out.push_str(&escape(&rest[..c.len_utf8()], format));
+        previous = Some(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// The span `text` starts with, and the text after it
+fn span(text: &str, previous: Option<char>) -> Option<(Span<'_>, &str)> {
+    if let Some(rest) = text.strip_prefix('`') {
+        let end = rest.find('`')?;
+        return Some((Span::Code(&rest[..end]), &rest[end + 1..]));
+    }
+    for (delimiter, bold) in [("**", true), ("__", true), ("~~", false)] {
+        if let Some(rest) = text.strip_prefix(delimiter) {
+            let end = rest.find(delimiter)?;
+            if end == 0 {
+                return None;
+            }
+            let inner = &rest[..end];
+            let span = if bold {
+                Span::Bold(inner)
+            } else {
+                Span::Strike(inner)
+            };
+            return Some((span, &rest[end + delimiter.len()..]));
+        }
+    }
+    if let Some(rest) = text.strip_prefix('[') {
+        let close = rest.find("](")?;
+        let end = rest[close..].find(')')? + close;
+        return Some((
+            Span::Link {
+                text: &rest[..close],
+                url: &rest[close + 2..end],
+            },
+            &rest[end + 1..],
+        ));
+    }
+    // Single `*` or `_` only emphasize at word boundaries, so `snake_case` and `2 * 3 * 4`
+    // stay as they are
+    let delimiter = text.chars().next().filter(|c| *c == '*' || *c == '_')?;
+    if previous.is_some_and(char::is_alphanumeric) {
+        return None;
+    }
+    let rest = &text[1..];
+    if rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let end = rest.char_indices().find_map(|(index, c)| {
+        let closes = c == delimiter
+            && index > 0
+            && !rest[..index].ends_with(char::is_whitespace)
+            && !rest[index + 1..].starts_with(char::is_alphanumeric);
+        closes.then_some(index)
+    })?;
+    Some((Span::Italic(&rest[..end]), &rest[end + 1..]))
+}
+
+fn render_span(span: Span, format: OutputFormat) -> String {
+    match (span, format) {
+        (Span::Code(code), OutputFormat::TelegramHtml) => {
+            format!("<code>{}</code>", escape(code, format))
+        }
+        (Span::Code(code), OutputFormat::SlackMrkdwn) => format!("`{}`", escape(code, format)),
+        (Span::Code(code), _) => code.to_string(),
+        (Span::Bold(inner), OutputFormat::TelegramHtml) => {
+            format!("<b>{}</b>", inline(inner, format))
+        }
+        (Span::Bold(inner), OutputFormat::SlackMrkdwn) => format!("*{}*", inline(inner, format)),
+        (Span::Italic(inner), OutputFormat::TelegramHtml) => {
+            format!("<i>{}</i>", inline(inner, format))
+        }
+        (Span::Italic(inner), OutputFormat::SlackMrkdwn) => format!("_{}_", inline(inner, format)),
+        (Span::Strike(inner), OutputFormat::TelegramHtml) => {
+            format!("<s>{}</s>", inline(inner, format))
+        }
+        (Span::Strike(inner), OutputFormat::SlackMrkdwn) => format!("~{}~", inline(inner, format)),
+        (Span::Bold(inner) | Span::Italic(inner) | Span::Strike(inner), _) => inline(inner, format),
+        (Span::Link { text, url }, OutputFormat::TelegramHtml) => format!(
+            "<a href=\"{}\">{}</a>",
+            escape(url, format).replace('"', "&quot;"),
+            inline(text, format)
+        ),
+        (Span::Link { text, url }, OutputFormat::SlackMrkdwn) => {
+            format!("<{}|{}>", escape(url, format), inline(text, format))
+        }
+        (Span::Link { text, url }, _) if text == url || text.is_empty() => url.to_string(),
+        (Span::Link { text, url }, _) => format!("{} ({url})", inline(text, format)),
+    }
+}
+
+/// Escapes the characters both Telegram's HTML and Slack's mrkdwn reserve
+fn escape(text: &str, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::TelegramHtml | OutputFormat::SlackMrkdwn => text
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+        OutputFormat::Plain | OutputFormat::Markdown => text.to_string(),
+    }
+}
@@ -1,5 +1,13 @@
+mod pii;
 mod storage;
+mod transcript;
 mod types;
 
-pub use storage::HistoryStorage;
-pub use types::Message;
+pub use pii::{
+    PiiFinding, PiiKind, PiiMatch, PiiReport, SessionPiiReport, detect_pii, scan_history,
+};
+pub use storage::{
+    DEFAULT_COMPRESSION_THRESHOLD, DEFAULT_FALLBACK_CAPACITY, HistoryStorage, StorageStatus,
+};
+pub use transcript::{TranscriptFormat, render_transcript};
+pub use types::{Message, ToolCallRecord};
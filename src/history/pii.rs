@@ -0,0 +1,186 @@
+//! Detection of personal data (email addresses, phone numbers and card numbers) in the
+//! stored history, for data-hygiene reviews. Findings are reported redacted.
+
+use super::{HistoryStorage, Message};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::{collections::BTreeMap, ops::Range, sync::LazyLock};
+
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap()
+});
+static CARD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap());
+static PHONE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{1,4}\)[ .-]?)?\b\d{2,5}(?:[ .-]?\d{2,5}){1,4}\b")
+        .unwrap()
+});
+
+/// Kind of personal data found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Email,
+    Phone,
+    /// 13 to 19 digits passing the Luhn check
+    Card,
+}
+
+impl PiiKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PiiKind::Email => "email",
+            PiiKind::Phone => "phone",
+            PiiKind::Card => "card",
+        }
+    }
+}
+
+/// Personal data found in a text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    /// The match with most of it masked, e.g. `j***@example.com` or `**** 4242`
+    pub redacted: String,
+}
+
+/// Personal data in `text`, in order of appearance
+pub fn detect_pii(text: &str) -> Vec<PiiMatch> {
+    let mut found: Vec<(Range<usize>, PiiMatch)> = Vec::new();
+    for email in EMAIL.find_iter(text) {
+        found.push((email.range(), redact(PiiKind::Email, email.as_str())));
+    }
+    // Cards before phones, whose pattern also matches many card numbers
+    for card in CARD.find_iter(text) {
+        if luhn_valid(&digits(card.as_str())) && !overlaps(&found, &card.range()) {
+            found.push((card.range(), redact(PiiKind::Card, card.as_str())));
+        }
+    }
+    for phone in PHONE.find_iter(text) {
+        let count = digits(phone.as_str()).len();
+        if (10..=15).contains(&count) && !overlaps(&found, &phone.range()) {
+            found.push((phone.range(), redact(PiiKind::Phone, phone.as_str())));
+        }
+    }
+    found.sort_by_key(|(range, _)| range.start);
+    found.into_iter().map(|(_, found)| found).collect()
+}
+
+fn overlaps(found: &[(Range<usize>, PiiMatch)], range: &Range<usize>) -> bool {
+    found
+        .iter()
+        .any(|(other, _)| other.start < range.end && range.start < other.end)
+}
+
+fn digits(text: &str) -> Vec<u32> {
+    text.chars().filter_map(|c| c.to_digit(10)).collect()
+}
+
+fn luhn_valid(digits: &[u32]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| match (i % 2, digit * 2) {
+            (0, _) => digit,
+            (_, doubled) if doubled > 9 => doubled - 9,
+            (_, doubled) => doubled,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn redact(kind: PiiKind, text: &str) -> PiiMatch {
+    let last = |n: usize| {
+        let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+        digits[digits.len().saturating_sub(n)..].to_string()
+    };
+    let redacted = match kind {
+        PiiKind::Email => {
+            let (local, domain) = text.split_once('@').unwrap_or((text, ""));
+            let first: String = local.chars().take(1).collect();
+            format!("{first}***@{domain}")
+        }
+        PiiKind::Phone => format!("***{}", last(2)),
+        PiiKind::Card => format!("**** {}", last(4)),
+    };
+    PiiMatch { kind, redacted }
+}
+
+/// Personal data found in a stored message
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PiiFinding {
+    pub message_id: Option<i64>,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+    /// Whether the message was deleted and awaits purging
+    pub deleted: bool,
+    #[serde(flatten)]
+    pub found: PiiMatch,
+}
+
+/// A session containing personal data
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionPiiReport {
+    pub session_id: String,
+    pub counts: BTreeMap<PiiKind, usize>,
+    pub findings: Vec<PiiFinding>,
+}
+
+/// What a scan of the history found
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PiiReport {
+    pub sessions_scanned: usize,
+    pub messages_scanned: usize,
+    /// Only the sessions containing personal data
+    pub sessions: Vec<SessionPiiReport>,
+}
+
+/// Scans the messages of `session_ids`, or of every session, deleted ones included
+pub async fn scan_history(
+    history: &HistoryStorage,
+    session_ids: Option<Vec<String>>,
+) -> Result<PiiReport> {
+    let session_ids = match session_ids {
+        Some(session_ids) => session_ids,
+        None => history.sessions().await?,
+    };
+    let mut report = PiiReport::default();
+    for session_id in session_ids {
+        let stored = history.list(&session_id).await?;
+        let deleted = history.list_deleted(&session_id).await?;
+        report.sessions_scanned += 1;
+        report.messages_scanned += stored.len() + deleted.len();
+
+        let mut session = SessionPiiReport {
+            session_id,
+            counts: BTreeMap::new(),
+            findings: Vec::new(),
+        };
+        let messages = stored
+            .iter()
+            .map(|message| (message, false))
+            .chain(deleted.iter().map(|message| (message, true)));
+        for (message, deleted) in messages {
+            scan_message(&mut session, message, deleted);
+        }
+        if !session.findings.is_empty() {
+            report.sessions.push(session);
+        }
+    }
+    Ok(report)
+}
+
+fn scan_message(session: &mut SessionPiiReport, message: &Message, deleted: bool) {
+    for found in detect_pii(&message.content) {
+        *session.counts.entry(found.kind).or_default() += 1;
+        session.findings.push(PiiFinding {
+            message_id: message.id,
+            role: message.role.clone(),
+            created_at: message.created_at,
+            deleted,
+            found,
+        });
+    }
+}
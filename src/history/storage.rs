@@ -1,73 +1,181 @@
 use super::Message;
-use crate::{Error, Result};
-use libsql::{Builder, Database};
-use std::sync::{Arc, Mutex};
-use tracing::{debug, info, warn};
+use crate::{Error, Result, chaos::Chaos, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::RwLock, task::JoinHandle};
+use tracing::{debug, error, info, warn};
+
+/// Rows whose content and metadata together exceed this many bytes are stored compressed
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 4096;
+
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Messages buffered in memory while the database is unavailable, before the oldest are dropped
+pub const DEFAULT_FALLBACK_CAPACITY: usize = 1000;
+
+/// Whether messages currently reach the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageStatus {
+    Ok,
+    /// Messages are buffered in memory and lost on restart until the database recovers
+    Degraded,
+}
+
+impl StorageStatus {
+    pub fn is_ok(&self) -> bool {
+        *self == Self::Ok
+    }
+}
 
 pub struct HistoryStorage {
-    db: Option<Database>,
+    db_path: String,
+    database: DatabaseConfig,
+    db: RwLock<Option<Connection>>,
     // In-memory fallback storage
-    fallback: Arc<Mutex<Vec<Message>>>,
+    fallback: Arc<Mutex<VecDeque<Message>>>,
+    fallback_capacity: usize,
+    compression_threshold: usize,
+    /// Fails some writes on purpose, for resilience testing
+    chaos: Option<Arc<Chaos>>,
 }
 
 impl HistoryStorage {
+    /// Opens the history at `db_path` with the default database tuning
     pub async fn new(db_path: &str) -> Result<Self> {
-        let mut storage = Self {
-            db: None,
-            fallback: Arc::new(Mutex::new(Vec::new())),
-        };
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
 
+    /// Opens the history at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
         // Try to initialize database
-        match storage.init_database(db_path).await {
-            Ok(()) => {
+        let db = match init_database(db_path, database).await {
+            Ok(db) => {
                 info!("Database initialized successfully: {}", db_path);
+                Some(db)
             }
             Err(e) => {
                 warn!(
                     "Database initialization failed, using in-memory fallback: {}",
                     e
                 );
+                None
             }
-        }
+        };
+
+        Ok(Self {
+            db_path: db_path.to_string(),
+            database: database.clone(),
+            db: RwLock::new(db),
+            fallback: Arc::new(Mutex::new(VecDeque::new())),
+            fallback_capacity: DEFAULT_FALLBACK_CAPACITY,
+            compression_threshold: DEFAULT_COMPRESSION_THRESHOLD,
+            chaos: None,
+        })
+    }
+
+    /// Keeps at most `messages` in the in-memory fallback, dropping the oldest beyond it
+    pub fn with_fallback_capacity(mut self, messages: usize) -> Self {
+        self.fallback_capacity = messages.max(1);
+        self
+    }
+
+    /// Compresses rows larger than `bytes`; `usize::MAX` turns compression off.
+    /// Rows already stored are read back either way.
+    pub fn with_compression_threshold(mut self, bytes: usize) -> Self {
+        self.compression_threshold = bytes;
+        self
+    }
 
-        Ok(storage)
+    /// Fails database writes at the chaos `db_error_rate`, as if the database were failing
+    pub fn with_chaos(mut self, chaos: Arc<Chaos>) -> Self {
+        self.chaos = Some(chaos);
+        self
     }
 
-    async fn init_database(&mut self, db_path: &str) -> Result<()> {
-        // Handle in-memory database
-        let db = if db_path == ":memory:" {
-            Builder::new_local(":memory:").build().await?
+    /// Degraded while the database is unavailable or messages wait in the fallback buffer
+    pub async fn status(&self) -> StorageStatus {
+        if self.db.read().await.is_some() && self.buffered() == 0 {
+            StorageStatus::Ok
         } else {
-            Builder::new_local(db_path).build().await?
-        };
+            StorageStatus::Degraded
+        }
+    }
 
-        // Create table if it doesn't exist
-        let conn = db.connect()?;
-        conn.execute(
-            r#"
-            CREATE TABLE IF NOT EXISTS messages (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id TEXT NOT NULL,
-                role TEXT NOT NULL,
-                content TEXT NOT NULL,
-                created_at DATETIME NOT NULL
-            )
-            "#,
-            (),
-        )
-        .await?;
+    /// Number of messages held in the in-memory fallback
+    pub fn buffered(&self) -> usize {
+        self.fallback.lock().map(|f| f.len()).unwrap_or_default()
+    }
 
-        self.db = Some(db);
-        Ok(())
+    /// Re-opens the database if needed and moves buffered messages into it, in order.
+    /// Returns how many were flushed; those that couldn't be stay buffered.
+    pub async fn recover(&self) -> Result<usize> {
+        if self.db.read().await.is_none() {
+            let db = init_database(&self.db_path, &self.database).await?;
+            info!("Database recovered: {}", self.db_path);
+            *self.db.write().await = Some(db);
+        }
+
+        let buffered: Vec<Message> = self.lock_fallback()?.drain(..).collect();
+        let db = self.db.read().await;
+        let Some(db) = db.as_ref() else {
+            return Ok(0);
+        };
+        for (index, message) in buffered.iter().enumerate() {
+            if let Err(e) = self.save_to_db(db, message).await {
+                // Requeue ahead of anything buffered meanwhile to keep the order
+                let mut fallback = self.lock_fallback()?;
+                for message in buffered[index..].iter().rev() {
+                    fallback.push_front(message.clone());
+                }
+                return Err(e);
+            }
+        }
+        if !buffered.is_empty() {
+            info!(
+                "Flushed {} buffered messages to the database",
+                buffered.len()
+            );
+        }
+        Ok(buffered.len())
+    }
+
+    /// Periodically retries [`recover`](Self::recover) while storage is degraded
+    pub fn spawn_recovery(self: Arc<Self>, retry_interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(retry_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if self.status().await.is_ok() {
+                    continue;
+                }
+                if let Err(e) = self.recover().await {
+                    error!("History storage still degraded: {}", e);
+                }
+            }
+        })
     }
 
     pub async fn save(&self, message: Message) -> Result<()> {
+        self.insert(message).await.map(|_| ())
+    }
+
+    /// Like `save`, returning the id of the new row when the message reached the database
+    pub async fn insert(&self, message: Message) -> Result<Option<i64>> {
         // Try database first
-        if let Some(ref db) = self.db {
+        if let Some(ref db) = *self.db.read().await {
             match self.save_to_db(db, &message).await {
-                Ok(()) => {
+                Ok(id) => {
                     debug!("Message saved to database: {}", message.session_id);
-                    return Ok(());
+                    return Ok(Some(id));
                 }
                 Err(e) => {
                     warn!("Failed to save to database, using fallback: {}", e);
@@ -77,38 +185,221 @@ impl HistoryStorage {
 
         // Fallback to in-memory storage
         self.save_to_fallback(message)?;
+        Ok(None)
+    }
+
+    /// Replaces the content, metadata and timestamp of the row `id` with `message`'s
+    pub async fn update(&self, id: i64, message: &Message) -> Result<()> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
+        let (content, metadata, compressed) = self.encode(message)?;
+        let updated = conn
+            .execute(
+                "UPDATE messages SET content = ?, metadata = ?, compressed = ?, created_at = ? WHERE id = ? AND deleted_at IS NULL",
+                libsql::params![
+                    content,
+                    metadata,
+                    compressed,
+                    message.created_at.to_rfc3339(),
+                    id
+                ],
+            )
+            .await?;
+        if updated == 0 {
+            return Err(Error::internal(format!("No message with id {id}")));
+        }
         Ok(())
     }
 
-    async fn save_to_db(&self, db: &Database, message: &Message) -> Result<()> {
-        let conn = db.connect()?;
+    /// Soft-deletes the row `id`: it stays in the database, hidden from `list`, until
+    /// restored or purged
+    pub async fn delete(&self, id: i64) -> Result<()> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
         conn.execute(
-            "INSERT INTO messages (session_id, role, content, created_at) VALUES (?, ?, ?, ?)",
-            (
+            "UPDATE messages SET deleted_at = ? WHERE id = ? AND deleted_at IS NULL",
+            libsql::params![Utc::now().to_rfc3339(), id],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Soft-deletes every message of a session, including those waiting in the fallback
+    /// buffer, which are dropped. Returns how many were deleted.
+    pub async fn clear(&self, session_id: &str) -> Result<u64> {
+        let mut cleared = {
+            let mut fallback = self.lock_fallback()?;
+            let buffered = fallback.len();
+            fallback.retain(|message| message.session_id != session_id);
+            (buffered - fallback.len()) as u64
+        };
+        if let Some(conn) = self.db.read().await.as_ref() {
+            cleared += conn
+                .execute(
+                    "UPDATE messages SET deleted_at = ? WHERE session_id = ? AND deleted_at IS NULL",
+                    libsql::params![Utc::now().to_rfc3339(), session_id],
+                )
+                .await?;
+        }
+        Ok(cleared)
+    }
+
+    /// Undoes the deletion of the row `id`, returning whether it was deleted
+    pub async fn restore(&self, id: i64) -> Result<bool> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
+        let restored = conn
+            .execute(
+                "UPDATE messages SET deleted_at = NULL WHERE id = ? AND deleted_at IS NOT NULL",
+                [id],
+            )
+            .await?;
+        Ok(restored > 0)
+    }
+
+    /// Deleted messages of a session not purged yet, oldest first
+    pub async fn list_deleted(&self, session_id: &str) -> Result<Vec<Message>> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
+        self.query_messages(conn, session_id, "deleted_at IS NOT NULL")
+            .await
+    }
+
+    /// Ids of every session with stored messages, deleted ones included
+    pub async fn sessions(&self) -> Result<Vec<String>> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
+        let mut rows = conn
+            .query(
+                "SELECT DISTINCT session_id FROM messages ORDER BY session_id",
+                (),
+            )
+            .await?;
+        let mut sessions = Vec::new();
+        while let Some(row) = rows.next().await? {
+            sessions.push(row.get::<String>(0)?);
+        }
+        Ok(sessions)
+    }
+
+    /// Permanently removes messages deleted before `before`, returning how many
+    pub async fn purge(&self, before: DateTime<Utc>) -> Result<u64> {
+        let db = self.db.read().await;
+        let conn = db
+            .as_ref()
+            .ok_or_else(|| Error::internal("History database is unavailable"))?;
+        let purged = conn
+            .execute(
+                "DELETE FROM messages WHERE deleted_at IS NOT NULL AND deleted_at < ?",
+                [before.to_rfc3339()],
+            )
+            .await?;
+        if purged > 0 {
+            info!("Purged {} deleted messages", purged);
+        }
+        Ok(purged)
+    }
+
+    /// Periodically purges messages deleted more than `retention` ago
+    pub fn spawn_purge(self: Arc<Self>, retention: Duration, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                let Ok(retention) = chrono::Duration::from_std(retention) else {
+                    continue;
+                };
+                if let Err(e) = self.purge(Utc::now() - retention).await {
+                    error!("Failed to purge deleted messages: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn save_to_db(&self, conn: &Connection, message: &Message) -> Result<i64> {
+        if let Some(chaos) = &self.chaos {
+            chaos.db_write()?;
+        }
+        let (content, metadata, compressed) = self.encode(message)?;
+        conn.execute(
+            "INSERT INTO messages (session_id, role, content, created_at, metadata, compressed) VALUES (?, ?, ?, ?, ?, ?)",
+            libsql::params![
                 message.session_id.as_str(),
                 message.role.as_str(),
-                message.content.as_str(),
+                content,
                 message.created_at.to_rfc3339(),
-            ),
+                metadata,
+                compressed,
+            ],
         )
         .await?;
-        Ok(())
+        Ok(conn.last_insert_rowid())
     }
 
-    fn save_to_fallback(&self, message: Message) -> Result<()> {
-        let mut fallback = self
-            .fallback
+    /// Content and metadata column values of `message`, and whether they are compressed
+    fn encode(&self, message: &Message) -> Result<(libsql::Value, libsql::Value, bool)> {
+        let metadata = message
+            .metadata
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?;
+        let size = message.content.len() + metadata.as_ref().map_or(0, String::len);
+
+        // Large rows, typically big tool results, are stored as zstd blobs in the same columns
+        Ok(if size > self.compression_threshold {
+            (
+                libsql::Value::Blob(compress(&message.content)?),
+                match metadata {
+                    Some(metadata) => libsql::Value::Blob(compress(&metadata)?),
+                    None => libsql::Value::Null,
+                },
+                true,
+            )
+        } else {
+            (
+                libsql::Value::Text(message.content.clone()),
+                metadata.map_or(libsql::Value::Null, libsql::Value::Text),
+                false,
+            )
+        })
+    }
+
+    fn lock_fallback(&self) -> Result<std::sync::MutexGuard<'_, VecDeque<Message>>> {
+        self.fallback
             .lock()
-            .map_err(|e| Error::internal(format!("Mutex lock failed: {e}")))?;
-        fallback.push(message);
+            .map_err(|e| Error::internal(format!("Mutex lock failed: {e}")))
+    }
+
+    fn save_to_fallback(&self, message: Message) -> Result<()> {
+        let mut fallback = self.lock_fallback()?;
+        fallback.push_back(message);
+        if fallback.len() > self.fallback_capacity {
+            fallback.pop_front();
+            warn!(
+                "Fallback buffer full ({} messages), dropped the oldest",
+                self.fallback_capacity
+            );
+        }
         Ok(())
     }
 
     pub async fn list(&self, session_id: &str) -> Result<Vec<Message>> {
-        // Try database first
-        if let Some(ref db) = self.db {
+        // Try database first, followed by anything still waiting to be flushed
+        if let Some(ref db) = *self.db.read().await {
             match self.list_from_db(db, session_id).await {
-                Ok(messages) => {
+                Ok(mut messages) => {
+                    messages.extend(self.list_from_fallback(session_id)?);
                     debug!(
                         "Retrieved {} messages from database for session: {}",
                         messages.len(),
@@ -126,26 +417,76 @@ impl HistoryStorage {
         self.list_from_fallback(session_id)
     }
 
-    async fn list_from_db(&self, db: &Database, session_id: &str) -> Result<Vec<Message>> {
-        let conn = db.connect()?;
+    /// A hash of the messages `list` returns for a session, changing whenever one is
+    /// added, edited or deleted
+    pub async fn fingerprint(&self, session_id: &str) -> Result<String> {
+        let mut hasher = DefaultHasher::new();
+        if let Some(ref conn) = *self.db.read().await {
+            let rows = conn
+                .query(
+                    "SELECT id, role, content, created_at, metadata FROM messages WHERE session_id = ? AND deleted_at IS NULL ORDER BY id ASC",
+                    [session_id],
+                )
+                .await?;
+            db::hash_rows(rows, &mut hasher).await?;
+        }
+        for message in self.list_from_fallback(session_id)? {
+            message.role.hash(&mut hasher);
+            message.content.hash(&mut hasher);
+            message.created_at.hash(&mut hasher);
+            message
+                .metadata
+                .map(|metadata| metadata.to_string())
+                .hash(&mut hasher);
+        }
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    async fn list_from_db(&self, conn: &Connection, session_id: &str) -> Result<Vec<Message>> {
+        self.query_messages(conn, session_id, "deleted_at IS NULL")
+            .await
+    }
+
+    /// Messages of a session matching `filter`, in order
+    async fn query_messages(
+        &self,
+        conn: &Connection,
+        session_id: &str,
+        filter: &str,
+    ) -> Result<Vec<Message>> {
         let mut rows = conn.query(
-            "SELECT id, session_id, role, content, created_at FROM messages WHERE session_id = ? ORDER BY id ASC",
+            &format!("SELECT id, session_id, role, content, created_at, metadata, compressed FROM messages WHERE session_id = ? AND {filter} ORDER BY id ASC"),
             [session_id]
         ).await?;
 
         let mut messages = Vec::new();
         while let Some(row) = rows.next().await? {
             let created_at_str: String = row.get(4)?;
-            let created_at = chrono::DateTime::parse_from_rfc3339(&created_at_str)
+            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
                 .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))?
-                .with_timezone(&chrono::Utc);
+                .with_timezone(&Utc);
+            let compressed = row.get::<bool>(6)?;
+            let (content, metadata) = if compressed {
+                (
+                    decompress(&row.get::<Vec<u8>>(3)?)?,
+                    row.get::<Option<Vec<u8>>>(5)?
+                        .map(|bytes| decompress(&bytes))
+                        .transpose()?,
+                )
+            } else {
+                (row.get(3)?, row.get::<Option<String>>(5)?)
+            };
+            let metadata = metadata
+                .map(|json| serde_json::from_str(&json))
+                .transpose()?;
 
             let message = Message {
                 id: Some(row.get(0)?),
                 session_id: row.get(1)?,
                 role: row.get(2)?,
-                content: row.get(3)?,
+                content,
                 created_at,
+                metadata,
             };
             messages.push(message);
         }
@@ -154,10 +495,7 @@ impl HistoryStorage {
     }
 
     fn list_from_fallback(&self, session_id: &str) -> Result<Vec<Message>> {
-        let fallback = self
-            .fallback
-            .lock()
-            .map_err(|e| Error::internal(format!("Mutex lock failed: {e}")))?;
+        let fallback = self.lock_fallback()?;
 
         let messages: Vec<Message> = fallback
             .iter()
@@ -173,3 +511,66 @@ impl HistoryStorage {
         Ok(messages)
     }
 }
+
+async fn init_database(db_path: &str, database: &DatabaseConfig) -> Result<Connection> {
+    let conn = db::connect(db_path, database).await?;
+
+    // Create table if it doesn't exist
+    conn.execute(
+        r#"
+        CREATE TABLE IF NOT EXISTS messages (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            session_id TEXT NOT NULL,
+            role TEXT NOT NULL,
+            content TEXT NOT NULL,
+            created_at DATETIME NOT NULL,
+            metadata TEXT,
+            compressed INTEGER NOT NULL DEFAULT 0,
+            deleted_at TEXT
+        )
+        "#,
+        (),
+    )
+    .await?;
+
+    // Databases created before message metadata, compression or soft deletion existed
+    // lack the columns
+    let mut columns = conn.query("PRAGMA table_info(messages)", ()).await?;
+    let mut has_metadata = false;
+    let mut has_compressed = false;
+    let mut has_deleted_at = false;
+    while let Some(row) = columns.next().await? {
+        match row.get::<String>(1)?.as_str() {
+            "metadata" => has_metadata = true,
+            "compressed" => has_compressed = true,
+            "deleted_at" => has_deleted_at = true,
+            _ => {}
+        }
+    }
+    if !has_metadata {
+        conn.execute("ALTER TABLE messages ADD COLUMN metadata TEXT", ())
+            .await?;
+    }
+    if !has_compressed {
+        conn.execute(
+            "ALTER TABLE messages ADD COLUMN compressed INTEGER NOT NULL DEFAULT 0",
+            (),
+        )
+        .await?;
+    }
+    if !has_deleted_at {
+        conn.execute("ALTER TABLE messages ADD COLUMN deleted_at TEXT", ())
+            .await?;
+    }
+
+    Ok(conn)
+}
+
+fn compress(text: &str) -> Result<Vec<u8>> {
+    Ok(zstd::encode_all(text.as_bytes(), COMPRESSION_LEVEL)?)
+}
+
+fn decompress(bytes: &[u8]) -> Result<String> {
+    String::from_utf8(zstd::decode_all(bytes)?)
+        .map_err(|e| Error::internal(format!("Compressed message is not valid UTF-8: {e}")))
+}
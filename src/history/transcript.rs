@@ -0,0 +1,242 @@
+use super::{Message, ToolCallRecord};
+use crate::agent::Citation;
+use serde::de::DeserializeOwned;
+
+/// Output formats for session transcripts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TranscriptFormat {
+    Markdown,
+    Html,
+}
+
+impl TranscriptFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(TranscriptFormat::Markdown),
+            "html" => Some(TranscriptFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "markdown",
+            TranscriptFormat::Html => "html",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            TranscriptFormat::Markdown => "text/markdown; charset=utf-8",
+            TranscriptFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// A message together with what its metadata records about how it was produced
+struct Entry<'a> {
+    message: &'a Message,
+    title: String,
+    timestamp: String,
+    /// Time since the preceding user message, for assistant replies
+    response_ms: Option<i64>,
+    tool_calls: Vec<ToolCallRecord>,
+    citations: Vec<Citation>,
+}
+
+fn metadata_field<T: DeserializeOwned + Default>(message: &Message, key: &str) -> T {
+    message
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.get(key))
+        .and_then(|value| serde_json::from_value(value.clone()).ok())
+        .unwrap_or_default()
+}
+
+fn entries(messages: &[Message]) -> Vec<Entry<'_>> {
+    let mut last_user = None;
+    messages
+        .iter()
+        .map(|message| {
+            let commentary: bool = metadata_field(message, "commentary");
+            let response_ms = match message.role.as_str() {
+                "user" => {
+                    last_user = Some(message.created_at);
+                    None
+                }
+                // Timed against the final reply, not interim commentary
+                "assistant" if !commentary => last_user
+                    .take()
+                    .map(|asked| (message.created_at - asked).num_milliseconds()),
+                _ => None,
+            };
+            let mut title = message.role.clone();
+            if let Some(first) = title.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            if commentary {
+                title.push_str(" (commentary)");
+            }
+            Entry {
+                message,
+                title,
+                timestamp: message
+                    .created_at
+                    .format("%Y-%m-%d %H:%M:%S UTC")
+                    .to_string(),
+                response_ms,
+                tool_calls: metadata_field(message, "tool_calls"),
+                citations: metadata_field(message, "citations"),
+            }
+        })
+        .collect()
+}
+
+fn format_duration(ms: i64) -> String {
+    if ms < 1000 {
+        format!("{ms} ms")
+    } else {
+        format!("{:.1} s", ms as f64 / 1000.0)
+    }
+}
+
+fn format_citation(citation: &Citation) -> String {
+    match &citation.span {
+        Some(span) => format!("{} ({})", citation.source_id, span),
+        None => citation.source_id.clone(),
+    }
+}
+
+fn pretty_arguments(call: &ToolCallRecord) -> String {
+    serde_json::to_string_pretty(&call.arguments).unwrap_or_else(|_| call.arguments.to_string())
+}
+
+/// Renders a session's messages, including the tool calls behind each reply
+pub fn render_transcript(
+    session_id: &str,
+    messages: &[Message],
+    format: TranscriptFormat,
+) -> String {
+    let entries = entries(messages);
+    match format {
+        TranscriptFormat::Markdown => render_markdown(session_id, &entries),
+        TranscriptFormat::Html => render_html(session_id, &entries),
+    }
+}
+
+/// Fences `content` in a code block that its own backticks cannot close
+fn code_block(content: &str, language: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{content}\n{fence}")
+}
+
+fn render_markdown(session_id: &str, entries: &[Entry]) -> String {
+    let mut out = format!("# Session `{session_id}`\n");
+    for entry in entries {
+        out.push_str(&format!("\n## {} — {}", entry.title, entry.timestamp));
+        if let Some(ms) = entry.response_ms {
+            out.push_str(&format!(" ({})", format_duration(ms)));
+        }
+        out.push_str("\n\n");
+
+        for call in &entry.tool_calls {
+            out.push_str(&format!(
+                "**Tool call:** `{}` · {}{}\n\nArguments:\n{}\n\nResult:\n{}\n\n",
+                call.name,
+                format_duration(call.duration_ms as i64),
+                if call.is_error { " · failed" } else { "" },
+                code_block(&pretty_arguments(call), "json"),
+                code_block(&call.result, ""),
+            ));
+        }
+
+        out.push_str(entry.message.content.trim_end());
+        out.push('\n');
+
+        if !entry.citations.is_empty() {
+            let sources: Vec<String> = entry.citations.iter().map(format_citation).collect();
+            out.push_str(&format!("\n**Sources:** {}\n", sources.join("; ")));
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+const HTML_STYLE: &str = "body{font-family:sans-serif;max-width:50rem;margin:2rem auto;padding:0 1rem}\
+    .message{border-left:4px solid #ccc;padding:0 1rem;margin:1.5rem 0}\
+    .user{border-color:#4a90d9}.assistant{border-color:#7bb661}\
+    h2{font-size:1rem}time,.timing{color:#666;font-weight:normal}\
+    .content{white-space:pre-wrap}\
+    pre{background:#f4f4f4;padding:.5rem;overflow-x:auto;white-space:pre-wrap}\
+    .failed summary{color:#b00}.sources{color:#555;font-size:.9rem}";
+
+fn render_html(session_id: &str, entries: &[Entry]) -> String {
+    let session_id = escape_html(session_id);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Session {session_id}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n\
+         <h1>Session <code>{session_id}</code></h1>\n"
+    );
+    for entry in entries {
+        out.push_str(&format!(
+            "<section class=\"message {}\">\n<h2>{} <time datetime=\"{}\">{}</time>",
+            escape_html(&entry.message.role),
+            escape_html(&entry.title),
+            entry.message.created_at.to_rfc3339(),
+            entry.timestamp
+        ));
+        if let Some(ms) = entry.response_ms {
+            out.push_str(&format!(
+                " <span class=\"timing\">({})</span>",
+                format_duration(ms)
+            ));
+        }
+        out.push_str("</h2>\n");
+
+        for call in &entry.tool_calls {
+            out.push_str(&format!(
+                "<details class=\"tool-call{}\">\n<summary>Tool call <code>{}</code> · {}{}</summary>\n\
+                 <p>Arguments</p>\n<pre>{}</pre>\n<p>Result</p>\n<pre>{}</pre>\n</details>\n",
+                if call.is_error { " failed" } else { "" },
+                escape_html(&call.name),
+                format_duration(call.duration_ms as i64),
+                if call.is_error { " · failed" } else { "" },
+                escape_html(&pretty_arguments(call)),
+                escape_html(&call.result),
+            ));
+        }
+
+        out.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape_html(entry.message.content.trim_end())
+        ));
+
+        if !entry.citations.is_empty() {
+            let sources: Vec<String> = entry
+                .citations
+                .iter()
+                .map(|citation| escape_html(&format_citation(citation)))
+                .collect();
+            out.push_str(&format!(
+                "<p class=\"sources\">Sources: {}</p>\n",
+                sources.join("; ")
+            ));
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
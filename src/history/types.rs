@@ -1,5 +1,6 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
@@ -8,6 +9,9 @@ pub struct Message {
     pub role: String,
     pub content: String,
     pub created_at: DateTime<Utc>,
+    /// Structured data attached to the message, such as `citations`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
 }
 
 impl Message {
@@ -18,9 +22,15 @@ impl Message {
             role,
             content,
             created_at: Utc::now(),
+            metadata: None,
         }
     }
 
+    pub fn with_metadata(mut self, metadata: Value) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
     pub fn user(session_id: String, content: String) -> Self {
         Self::new(session_id, "user".to_string(), content)
     }
@@ -37,3 +47,16 @@ impl Message {
         Self::new(session_id, "tool".to_string(), content)
     }
 }
+
+/// A tool call made while producing an assistant message, kept under the message's
+/// `tool_calls` metadata key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    pub arguments: Value,
+    /// Result as rendered for the LLM
+    pub result: String,
+    #[serde(default)]
+    pub is_error: bool,
+    pub duration_ms: u64,
+}
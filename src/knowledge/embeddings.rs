@@ -0,0 +1,119 @@
+use crate::{
+    Result,
+    cache::{Cache, cache_key, get_json, set_json},
+    config::Config,
+};
+use async_openai::{Client, config::OpenAIConfig, types::CreateEmbeddingRequestArgs};
+use async_trait::async_trait;
+use std::{sync::Arc, time::Duration};
+
+/// Inputs sent per embeddings request
+const BATCH_SIZE: usize = 64;
+
+/// Turns text into vectors for similarity search
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embeds each text, returning vectors in input order
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+}
+
+/// OpenAI-compatible `/embeddings` endpoint
+pub struct OpenAiEmbedder {
+    client: Client<OpenAIConfig>,
+    model: String,
+}
+
+impl OpenAiEmbedder {
+    /// `knowledge.embedding_model`, at the LLM endpoint unless `knowledge` names another
+    pub fn from_config(config: &Config) -> Self {
+        let knowledge = &config.knowledge;
+        Self::new(
+            knowledge
+                .base_url
+                .as_deref()
+                .unwrap_or(&config.llm.base_url),
+            knowledge.api_key.as_deref().unwrap_or(&config.llm.api_key),
+            knowledge.embedding_model.clone(),
+        )
+    }
+
+    pub fn new(base_url: &str, api_key: &str, model: String) -> Self {
+        let mut config = OpenAIConfig::new().with_api_key(api_key);
+        if !base_url.is_empty() {
+            config = config.with_api_base(base_url);
+        }
+        Self {
+            client: Client::with_config(config),
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_SIZE) {
+            let request = CreateEmbeddingRequestArgs::default()
+                .model(&self.model)
+                .input(batch.to_vec())
+                .build()?;
+            let mut response = self.client.embeddings().create(request).await?;
+            response.data.sort_by_key(|e| e.index);
+            vectors.extend(response.data.into_iter().map(|e| e.embedding));
+        }
+        Ok(vectors)
+    }
+}
+
+/// Reuses cached vectors for texts embedded before, embedding only the rest
+pub struct CachedEmbedder {
+    inner: Arc<dyn Embedder>,
+    cache: Arc<dyn Cache>,
+    /// Vectors of different models are not interchangeable
+    model: String,
+    ttl: Option<Duration>,
+}
+
+impl CachedEmbedder {
+    pub fn new(
+        inner: Arc<dyn Embedder>,
+        cache: Arc<dyn Cache>,
+        model: String,
+        ttl: Option<Duration>,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            model,
+            ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for CachedEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut keys = Vec::with_capacity(texts.len());
+        let mut vectors = Vec::with_capacity(texts.len());
+        let mut missing = Vec::new();
+        for (i, text) in texts.iter().enumerate() {
+            let key = cache_key("embedding", &(&self.model, text))?;
+            let cached: Option<Vec<f32>> = get_json(self.cache.as_ref(), &key).await;
+            if cached.is_none() {
+                missing.push(i);
+            }
+            vectors.push(cached);
+            keys.push(key);
+        }
+
+        if !missing.is_empty() {
+            let inputs: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            for (i, vector) in missing.into_iter().zip(self.inner.embed(&inputs).await?) {
+                set_json(self.cache.as_ref(), &keys[i], &vector, self.ttl).await;
+                vectors[i] = Some(vector);
+            }
+        }
+        Ok(vectors.into_iter().flatten().collect())
+    }
+}
@@ -0,0 +1,252 @@
+use crate::{Error, Result};
+use std::path::Path;
+
+/// Document formats the ingestion pipeline can extract text from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Pdf,
+    Html,
+    Markdown,
+    Text,
+}
+
+impl DocumentFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "pdf" => Some(DocumentFormat::Pdf),
+            "html" | "htm" => Some(DocumentFormat::Html),
+            "md" | "markdown" => Some(DocumentFormat::Markdown),
+            "txt" | "text" => Some(DocumentFormat::Text),
+            _ => None,
+        }
+    }
+
+    /// Infers the format from a file name's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_name)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DocumentFormat::Pdf => "pdf",
+            DocumentFormat::Html => "html",
+            DocumentFormat::Markdown => "markdown",
+            DocumentFormat::Text => "text",
+        }
+    }
+}
+
+/// Extracts plain text from a document
+pub fn extract_text(format: DocumentFormat, bytes: &[u8]) -> Result<String> {
+    match format {
+        #[cfg(feature = "pdf")]
+        DocumentFormat::Pdf => pdf_extract::extract_text_from_mem(bytes)
+            .map_err(|e| Error::internal(format!("Failed to read PDF: {e}"))),
+        #[cfg(not(feature = "pdf"))]
+        DocumentFormat::Pdf => Err(Error::internal(
+            "PDF documents need jarvis built with the `pdf` feature",
+        )),
+        DocumentFormat::Html => Ok(html_to_text(&utf8(bytes)?)),
+        DocumentFormat::Markdown => Ok(markdown_to_text(&utf8(bytes)?)),
+        DocumentFormat::Text => utf8(bytes),
+    }
+}
+
+fn utf8(bytes: &[u8]) -> Result<String> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| Error::internal("Document is not valid UTF-8"))
+}
+
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "br",
+    "li",
+    "tr",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "blockquote",
+    "pre",
+    "table",
+    "ul",
+    "ol",
+];
+
+/// Reduces an HTML page to its visible text, keeping block boundaries as line breaks
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    // Source line breaks are insignificant in HTML; only block tags start new lines
+    let push_inline = |text: &mut String, segment: &str| text.push_str(&segment.replace('\n', " "));
+    while let Some(start) = rest.find('<') {
+        push_inline(&mut text, &rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        rest = &rest[start + end + 1..];
+
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        // Skip the contents of non-visible elements entirely
+        if !tag.starts_with('/') && matches!(name.as_str(), "script" | "style" | "noscript") {
+            let closing = format!("</{name}");
+            match rest.to_ascii_lowercase().find(&closing) {
+                Some(index) => {
+                    rest = &rest[index..];
+                    rest = rest.find('>').map_or("", |gt| &rest[gt + 1..]);
+                }
+                None => rest = "",
+            }
+            continue;
+        }
+        if BLOCK_TAGS.contains(&name.as_str()) {
+            text.push('\n');
+        } else {
+            text.push(' ');
+        }
+    }
+    push_inline(&mut text, rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    // Adjacent block tags leave empty lines that carry no paragraph meaning
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Strips Markdown syntax that carries no meaning for retrieval
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut body = markdown;
+    // YAML front matter
+    if let Some(rest) = body.strip_prefix("---\n")
+        && let Some(end) = rest.find("\n---")
+    {
+        body = rest[end + 4..].trim_start_matches('\n');
+    }
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            continue;
+        }
+        let line = trimmed
+            .trim_start_matches('#')
+            .trim_start_matches('>')
+            .trim();
+        lines.push(strip_links(line).replace("**", "").replace("__", ""));
+    }
+    normalize_lines(&lines.join("\n"))
+}
+
+/// `[text](url)` becomes `text`; images are dropped
+fn strip_links(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(open) = rest.find('[') {
+        let Some(close) = rest[open..].find("](").map(|i| open + i) else {
+            break;
+        };
+        let Some(end) = rest[close..].find(')').map(|i| close + i) else {
+            break;
+        };
+        let is_image = rest[..open].ends_with('!');
+        out.push_str(rest[..open].trim_end_matches('!'));
+        if !is_image {
+            out.push_str(&rest[open + 1..close]);
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Collapses runs of spaces and blank lines
+fn normalize_lines(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// Chunk sizes are measured in characters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkingOptions {
+    pub chunk_size: usize,
+    pub overlap: usize,
+}
+
+impl ChunkingOptions {
+    pub fn new(chunk_size: usize, overlap: usize) -> Result<Self> {
+        if chunk_size == 0 || overlap >= chunk_size {
+            return Err(Error::config(
+                "knowledge.chunk_size must be positive and larger than knowledge.chunk_overlap",
+            ));
+        }
+        Ok(Self {
+            chunk_size,
+            overlap,
+        })
+    }
+}
+
+/// Splits text into word-aligned chunks of at most `chunk_size` characters, each
+/// starting with roughly the last `overlap` characters of the previous one.
+/// Words longer than a whole chunk are kept intact.
+pub fn chunk_text(text: &str, options: ChunkingOptions) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let len = |word: &str| word.chars().count();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < words.len() {
+        let mut end = start + 1;
+        let mut size = len(words[start]);
+        while end < words.len() && size + 1 + len(words[end]) <= options.chunk_size {
+            size += 1 + len(words[end]);
+            end += 1;
+        }
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+
+        let mut next = end;
+        let mut overlap = 0;
+        while next > start + 1 && overlap + len(words[next - 1]) < options.overlap {
+            overlap += len(words[next - 1]) + 1;
+            next -= 1;
+        }
+        start = next;
+    }
+    chunks
+}
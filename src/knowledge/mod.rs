@@ -0,0 +1,235 @@
+//! Document knowledge base: ingestion (parsing, chunking, embedding) and similarity search
+//! backing the `knowledge_search` tool.
+
+mod embeddings;
+mod ingest;
+mod rerank;
+mod store;
+
+pub use embeddings::{CachedEmbedder, Embedder, OpenAiEmbedder};
+pub use ingest::{
+    ChunkingOptions, DocumentFormat, chunk_text, extract_text, html_to_text, markdown_to_text,
+};
+pub use rerank::{CrossEncoderReranker, LlmReranker, Reranker};
+pub use store::{ChunkRecord, SearchHit, VectorStore, cosine_similarity};
+
+use crate::{
+    Error, Result,
+    cache::create_cache,
+    config::{Config, RerankProvider},
+    llm::create_llm_client,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tracing::{info, warn};
+
+/// Outcome of ingesting a file or directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IngestReport {
+    pub documents: usize,
+    pub chunks: usize,
+    /// Files that were not ingested because their format is unsupported
+    pub skipped: Vec<PathBuf>,
+}
+
+pub struct KnowledgeBase {
+    store: VectorStore,
+    embedder: Arc<dyn Embedder>,
+    chunking: ChunkingOptions,
+    reranker: Option<Arc<dyn Reranker>>,
+    /// Vector-search candidates fetched for the reranker
+    rerank_candidates: usize,
+}
+
+impl KnowledgeBase {
+    pub fn new(store: VectorStore, embedder: Arc<dyn Embedder>, chunking: ChunkingOptions) -> Self {
+        Self {
+            store,
+            embedder,
+            chunking,
+            reranker: None,
+            rerank_candidates: 0,
+        }
+    }
+
+    /// Reorders the best `candidates` vector-search hits with `reranker` before truncating
+    pub fn with_reranker(mut self, reranker: Arc<dyn Reranker>, candidates: usize) -> Self {
+        self.reranker = Some(reranker);
+        self.rerank_candidates = candidates;
+        self
+    }
+
+    /// Builds the knowledge base from `knowledge`, falling back to the LLM endpoint for embeddings
+    pub async fn from_config(config: &Config, db_path: &str) -> Result<Self> {
+        let knowledge = &config.knowledge;
+        let mut embedder: Arc<dyn Embedder> = Arc::new(OpenAiEmbedder::from_config(config));
+        if config.cache.embeddings
+            && let Some(cache) = create_cache(
+                &config.cache,
+                &config.cache_database_path(),
+                &config.server.database,
+            )
+            .await?
+        {
+            embedder = Arc::new(CachedEmbedder::new(
+                embedder,
+                cache,
+                knowledge.embedding_model.clone(),
+                config.cache.ttl(),
+            ));
+        }
+        let knowledge_base = Self::new(
+            VectorStore::open(db_path, &config.server.database).await?,
+            embedder,
+            ChunkingOptions::new(knowledge.chunk_size, knowledge.chunk_overlap)?,
+        );
+
+        let rerank = &knowledge.rerank;
+        let reranker: Arc<dyn Reranker> = match rerank.provider {
+            RerankProvider::None => return Ok(knowledge_base),
+            RerankProvider::CrossEncoder => Arc::new(CrossEncoderReranker::new(
+                rerank.url.as_deref().ok_or_else(|| {
+                    Error::config("knowledge.rerank.url is required for the cross_encoder reranker")
+                })?,
+                rerank.api_key.clone(),
+                rerank.model.clone(),
+            )),
+            RerankProvider::Llm => {
+                let model = rerank.model.clone().unwrap_or(config.llm.model.clone());
+                let mut llm_config = config.llm.clone();
+                llm_config.model = model.clone();
+                Arc::new(LlmReranker::new(
+                    Arc::from(create_llm_client(llm_config)?),
+                    model,
+                ))
+            }
+        };
+        Ok(knowledge_base.with_reranker(reranker, rerank.top_k_in))
+    }
+
+    /// Chunks and embeds a document's text, replacing any earlier version of `source`.
+    /// Returns the number of chunks stored.
+    pub async fn ingest_text(
+        &self,
+        source: &str,
+        format: DocumentFormat,
+        text: &str,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<usize> {
+        let chunks = chunk_text(text, self.chunking);
+        let embeddings = self.embedder.embed(&chunks).await?;
+        if embeddings.len() != chunks.len() {
+            return Err(Error::internal(format!(
+                "Expected {} embeddings, got {}",
+                chunks.len(),
+                embeddings.len()
+            )));
+        }
+
+        let mut metadata = metadata.clone();
+        metadata.insert("format".to_string(), format.as_str().to_string());
+        let records: Vec<ChunkRecord> = chunks
+            .into_iter()
+            .zip(embeddings)
+            .enumerate()
+            .map(|(chunk_index, (content, embedding))| ChunkRecord {
+                source: source.to_string(),
+                chunk_index,
+                content,
+                metadata: metadata.clone(),
+                embedding,
+            })
+            .collect();
+        self.store.replace_source(source, &records).await?;
+        info!("Ingested '{}' as {} chunks", source, records.len());
+        Ok(records.len())
+    }
+
+    pub async fn ingest_bytes(
+        &self,
+        source: &str,
+        format: DocumentFormat,
+        bytes: &[u8],
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<usize> {
+        let text = extract_text(format, bytes)?;
+        self.ingest_text(source, format, &text, metadata).await
+    }
+
+    /// Ingests a file, or every supported file below a directory
+    pub async fn ingest_path(
+        &self,
+        path: &Path,
+        metadata: &BTreeMap<String, String>,
+    ) -> Result<IngestReport> {
+        let mut report = IngestReport::default();
+        let mut pending = vec![path.to_path_buf()];
+        while let Some(path) = pending.pop() {
+            if tokio::fs::metadata(&path).await?.is_dir() {
+                let mut entries = tokio::fs::read_dir(&path).await?;
+                let mut children = Vec::new();
+                while let Some(entry) = entries.next_entry().await? {
+                    children.push(entry.path());
+                }
+                // Reversed so files are processed in name order off the stack
+                children.sort_by(|a, b| b.cmp(a));
+                pending.extend(children);
+                continue;
+            }
+
+            let Some(format) = DocumentFormat::from_path(&path) else {
+                warn!("Skipping unsupported file: {}", path.display());
+                report.skipped.push(path);
+                continue;
+            };
+            let bytes = tokio::fs::read(&path).await?;
+            report.chunks += self
+                .ingest_bytes(&path.to_string_lossy(), format, &bytes, metadata)
+                .await?;
+            report.documents += 1;
+        }
+        Ok(report)
+    }
+
+    pub async fn search(&self, query: &str, top_k: usize) -> Result<Vec<SearchHit>> {
+        let embedding = self
+            .embedder
+            .embed(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| Error::internal("No embedding returned for the query"))?;
+        let Some(reranker) = &self.reranker else {
+            return self.store.search(&embedding, top_k).await;
+        };
+
+        let mut hits = self
+            .store
+            .search(&embedding, self.rerank_candidates.max(top_k))
+            .await?;
+        let documents: Vec<String> = hits.iter().map(|hit| hit.content.clone()).collect();
+        match reranker.rerank(query, &documents).await {
+            Ok(scores) if scores.len() == hits.len() => {
+                for (hit, score) in hits.iter_mut().zip(scores) {
+                    hit.score = score;
+                }
+                hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+            }
+            Ok(scores) => warn!(
+                "Reranker returned {} scores for {} hits, keeping vector order",
+                scores.len(),
+                hits.len()
+            ),
+            // Vector order is still a usable answer
+            Err(e) => warn!("Reranking failed, keeping vector order: {}", e),
+        }
+        hits.truncate(top_k);
+        Ok(hits)
+    }
+
+    pub async fn chunk_count(&self) -> Result<usize> {
+        self.store.count().await
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    Error, Result,
+    llm::{ChatCompletionRequest, ChatMessage, LlmClient},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+/// Scores passages against a query; higher is more relevant
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Returns one score per document, in input order
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>>;
+}
+
+/// Cross-encoder behind a Cohere/Jina-compatible `POST /rerank` endpoint
+pub struct CrossEncoderReranker {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    #[serde(alias = "score")]
+    relevance_score: f32,
+}
+
+impl CrossEncoderReranker {
+    pub fn new(base_url: &str, api_key: Option<String>, model: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl Reranker for CrossEncoderReranker {
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let mut body = json!({
+            "query": query,
+            "documents": documents,
+            "top_n": documents.len(),
+        });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+        let mut request = self
+            .client
+            .post(format!("{}/rerank", self.base_url))
+            .json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+        let response: RerankResponse = request.send().await?.error_for_status()?.json().await?;
+
+        // Documents the service left out rank below everything it scored
+        let mut scores = vec![f32::MIN; documents.len()];
+        for result in response.results {
+            if let Some(score) = scores.get_mut(result.index) {
+                *score = result.relevance_score;
+            }
+        }
+        Ok(scores)
+    }
+}
+
+/// Asks the chat model to grade each passage from 0 to 10
+pub struct LlmReranker {
+    llm: Arc<dyn LlmClient>,
+    model: String,
+}
+
+impl LlmReranker {
+    pub fn new(llm: Arc<dyn LlmClient>, model: String) -> Self {
+        Self { llm, model }
+    }
+}
+
+fn message(role: &str, content: String) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+#[async_trait]
+impl Reranker for LlmReranker {
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        let passages = documents
+            .iter()
+            .enumerate()
+            .map(|(i, doc)| format!("[{i}] {doc}"))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let request = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: vec![
+                message(
+                    "system",
+                    "You grade how well passages answer a query. Reply only with a JSON array \
+                     of numbers from 0 (irrelevant) to 10 (answers it fully), one per passage, \
+                     in passage order."
+                        .to_string(),
+                ),
+                message("user", format!("Query: {query}\n\nPassages:\n{passages}")),
+            ],
+            temperature: Some(0.0),
+            ..Default::default()
+        };
+        let response = self.llm.create_chat_completion(request).await?;
+        let content = response
+            .choices
+            .first()
+            .map(|choice| choice.message.content.text())
+            .unwrap_or_default();
+        parse_scores(&content, documents.len())
+    }
+}
+
+/// Reads the first JSON array of numbers in `content`, tolerating surrounding prose
+fn parse_scores(content: &str, expected: usize) -> Result<Vec<f32>> {
+    let array = content
+        .find('[')
+        .zip(content.rfind(']'))
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| &content[start..=end])
+        .ok_or_else(|| Error::llm(format!("Reranker reply has no score list: {content}")))?;
+    let scores: Vec<f32> = serde_json::from_str(array)
+        .map_err(|e| Error::llm(format!("Invalid reranker scores '{array}': {e}")))?;
+    if scores.len() != expected {
+        return Err(Error::llm(format!(
+            "Reranker returned {} scores for {} passages",
+            scores.len(),
+            expected
+        )));
+    }
+    Ok(scores)
+}
@@ -0,0 +1,151 @@
+use crate::{Result, config::DatabaseConfig, db};
+use chrono::Utc;
+use libsql::Connection;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// A chunk ready to be stored
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkRecord {
+    pub source: String,
+    pub chunk_index: usize,
+    pub content: String,
+    pub metadata: BTreeMap<String, String>,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    pub source: String,
+    pub chunk_index: usize,
+    pub content: String,
+    pub metadata: BTreeMap<String, String>,
+    /// Cosine similarity to the query, or the reranker's score when one is configured
+    pub score: f32,
+}
+
+fn encode_embedding(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let denominator = norm(a) * norm(b);
+    if denominator == 0.0 {
+        0.0
+    } else {
+        dot / denominator
+    }
+}
+
+/// Chunk embeddings kept in libSQL and searched by brute-force cosine similarity,
+/// which is plenty for a personal document collection
+pub struct VectorStore {
+    conn: Connection,
+}
+
+impl VectorStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS knowledge_chunks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                source TEXT NOT NULL,
+                chunk_index INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                metadata TEXT NOT NULL,
+                embedding BLOB NOT NULL,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Vector store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Replaces all chunks of a source
+    pub async fn replace_source(&self, source: &str, chunks: &[ChunkRecord]) -> Result<()> {
+        let tx = self.conn.transaction().await?;
+        tx.execute(
+            "DELETE FROM knowledge_chunks WHERE source = ?",
+            libsql::params![source],
+        )
+        .await?;
+        let created_at = Utc::now().to_rfc3339();
+        for chunk in chunks {
+            tx.execute(
+                "INSERT INTO knowledge_chunks (source, chunk_index, content, metadata, embedding, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    chunk.source.as_str(),
+                    chunk.chunk_index as i64,
+                    chunk.content.as_str(),
+                    serde_json::to_string(&chunk.metadata)?,
+                    encode_embedding(&chunk.embedding),
+                    created_at.as_str(),
+                ],
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// The `top_k` chunks most similar to `query`, best first
+    pub async fn search(&self, query: &[f32], top_k: usize) -> Result<Vec<SearchHit>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT source, chunk_index, content, metadata, embedding FROM knowledge_chunks",
+                (),
+            )
+            .await?;
+
+        let mut hits = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let metadata: String = row.get(3)?;
+            let embedding: Vec<u8> = row.get(4)?;
+            hits.push(SearchHit {
+                source: row.get(0)?,
+                chunk_index: row.get::<i64>(1)? as usize,
+                content: row.get(2)?,
+                metadata: serde_json::from_str(&metadata)?,
+                score: cosine_similarity(query, &decode_embedding(&embedding)),
+            });
+        }
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(top_k);
+        Ok(hits)
+    }
+
+    pub async fn count(&self) -> Result<usize> {
+        let mut rows = self
+            .conn
+            .query("SELECT COUNT(*) FROM knowledge_chunks", ())
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<i64>(0)? as usize),
+            None => Ok(0),
+        }
+    }
+}
@@ -1,10 +1,35 @@
 pub mod agent;
+pub mod cache;
+pub mod canary;
+pub mod chaos;
+pub mod commands;
 pub mod config;
+pub mod db;
 pub mod error;
+pub mod events;
+pub mod examples;
+#[cfg(feature = "feeds")]
+pub mod feeds;
+pub mod formatting;
 pub mod history;
+#[cfg(feature = "knowledge")]
+pub mod knowledge;
 pub mod llm;
 pub mod mcp;
 pub mod mcp_client;
+pub mod moderation;
+pub mod notifications;
+pub mod profiles;
+pub mod prompts;
+pub mod scheduler;
+pub mod security;
 pub mod server;
+#[cfg(windows)]
+pub mod service;
+pub mod sessions;
+pub mod tasks;
+pub mod tools;
+pub mod usage;
+pub mod version;
 
 pub use error::{Error, Result};
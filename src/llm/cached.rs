@@ -0,0 +1,60 @@
+use super::{ChatCompletionRequest, ChatCompletionResponse, LlmClient, ModelInfo};
+use crate::{
+    Result,
+    cache::{Cache, cache_key, get_json, set_json},
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{sync::Arc, time::Duration};
+use tracing::debug;
+
+/// Answers repeated identical requests from the cache instead of the wrapped client
+pub struct CachedLlmClient {
+    inner: Box<dyn LlmClient>,
+    cache: Arc<dyn Cache>,
+    /// Part of the key, as requests without a model of their own use the client's
+    model: String,
+    ttl: Option<Duration>,
+}
+
+impl CachedLlmClient {
+    pub fn new(
+        inner: Box<dyn LlmClient>,
+        cache: Arc<dyn Cache>,
+        model: String,
+        ttl: Option<Duration>,
+    ) -> Self {
+        Self {
+            inner,
+            cache,
+            model,
+            ttl,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for CachedLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        let key = cache_key("llm", &(&self.model, &request))?;
+        if let Some(response) = get_json(self.cache.as_ref(), &key).await {
+            debug!("LLM cache hit");
+            return Ok(response);
+        }
+
+        let response = self.inner.create_chat_completion(request).await?;
+        set_json(self.cache.as_ref(), &key, &response, self.ttl).await;
+        Ok(response)
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        self.inner.request_payload(request)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
@@ -0,0 +1,182 @@
+use super::{client::LlmClient, create_provider_client};
+use crate::{
+    Result,
+    config::{LlmConfig, ModelPrice},
+};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// How long a listing is reused before the providers are asked again
+const CATALOG_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// A model a provider serves
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelInfo {
+    pub id: String,
+    pub provider: String,
+    pub capabilities: ModelCapabilities,
+    /// Input tokens the model takes, where the provider tells
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u32>,
+    /// Token prices, where the provider tells
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<ModelPrice>,
+}
+
+/// What a model can do, as reported by its provider or guessed from its name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ModelCapabilities {
+    pub chat: bool,
+    pub tools: bool,
+    pub vision: bool,
+    pub embeddings: bool,
+}
+
+impl ModelCapabilities {
+    /// Capabilities of an OpenAI model by its name, also behind a router prefix such as
+    /// `openai/gpt-4o`
+    pub fn of_openai_model(id: &str) -> Self {
+        let name = id.rsplit('/').next().unwrap_or(id);
+        let embeddings = name.contains("embedding");
+        let special = [
+            "instruct",
+            "audio",
+            "realtime",
+            "tts",
+            "transcribe",
+            "image",
+            "search",
+        ]
+        .iter()
+        .any(|kind| name.contains(kind));
+        let chat = !embeddings
+            && !special
+            && ["gpt-", "chatgpt-", "o1", "o3", "o4"]
+                .iter()
+                .any(|prefix| name.starts_with(prefix));
+        let vision = chat
+            && ([
+                "gpt-4o",
+                "gpt-4.1",
+                "gpt-4-turbo",
+                "gpt-5",
+                "o3",
+                "o4",
+                "o1-20",
+            ]
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+                || name == "o1");
+        Self {
+            chat,
+            tools: chat && !name.starts_with("o1-mini") && !name.starts_with("chatgpt-"),
+            vision,
+            embeddings,
+        }
+    }
+}
+
+/// A provider that couldn't be listed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderError {
+    pub provider: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModelListing {
+    pub models: Vec<ModelInfo>,
+    /// Providers whose models are missing from `models`
+    pub errors: Vec<ProviderError>,
+}
+
+/// Models of the configured provider and its fallbacks, listed through their APIs
+pub struct ModelCatalog {
+    /// `llm.model`, answering requests that don't pick one
+    default_model: String,
+    providers: Vec<(String, Box<dyn LlmClient>)>,
+    cached: Mutex<Option<(Instant, ModelListing)>>,
+}
+
+impl ModelCatalog {
+    /// Lists the models of each `(provider, client)` pair, in order
+    pub fn new(default_model: String, providers: Vec<(String, Box<dyn LlmClient>)>) -> Self {
+        Self {
+            default_model,
+            providers,
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// A catalog of `llm.provider` and each of `llm.fallbacks`
+    pub fn from_config(config: &LlmConfig) -> Result<Self> {
+        let mut providers = vec![(
+            config.provider.clone(),
+            create_provider_client(config.clone())?,
+        )];
+        for fallback in &config.fallbacks {
+            providers.push((
+                fallback.provider.clone(),
+                create_provider_client(config.with_fallback(fallback))?,
+            ));
+        }
+        Ok(Self::new(config.model.clone(), providers))
+    }
+
+    pub fn default_model(&self) -> &str {
+        &self.default_model
+    }
+
+    /// Price of `model` in the latest listing, without listing the models again
+    pub async fn listed_price(&self, model: &str) -> Option<ModelPrice> {
+        let cached = self.cached.lock().await;
+        let (_, listing) = cached.as_ref()?;
+        listing
+            .models
+            .iter()
+            .find(|m| m.id == model && m.pricing.is_some())?
+            .pricing
+    }
+
+    /// Every model the providers serve, listed once per provider and model. Listings are
+    /// reused for a few minutes, unless a provider failed.
+    pub async fn list(&self) -> ModelListing {
+        let mut cached = self.cached.lock().await;
+        if let Some((at, listing)) = cached.as_ref()
+            && at.elapsed() < CATALOG_TTL
+        {
+            return listing.clone();
+        }
+
+        let mut listing = ModelListing::default();
+        for (provider, client) in &self.providers {
+            match client.list_models().await {
+                Ok(models) => {
+                    for model in models {
+                        let listed = listing
+                            .models
+                            .iter()
+                            .any(|m| m.provider == model.provider && m.id == model.id);
+                        if !listed {
+                            listing.models.push(model);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to list the models of {}: {}", provider, e);
+                    listing.errors.push(ProviderError {
+                        provider: provider.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        *cached = listing
+            .errors
+            .is_empty()
+            .then(|| (Instant::now(), listing.clone()));
+        listing
+    }
+}
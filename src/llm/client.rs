@@ -1,7 +1,32 @@
-use super::types::*;
-use crate::{Result, config::LlmConfig};
-use async_openai::{Client, config::OpenAIConfig, types as openai_types};
+use super::{
+    catalog::{ModelCapabilities, ModelInfo},
+    retry::{RetryPolicy, retry_after},
+    types::*,
+};
+use crate::{
+    Error, Result,
+    config::{
+        AzureOpenAiConfig, LlmConfig, ModelPrice, OpenRouterConfig, ReasoningConfig,
+        ReasoningEffort,
+    },
+};
+use async_openai::{
+    Client,
+    config::{AzureConfig, Config, OpenAIConfig},
+    error::{OpenAIError, WrappedError},
+    types as openai_types,
+};
 use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::header::{HeaderMap, HeaderValue};
+use secrecy::SecretString;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::debug;
 
 #[async_trait]
@@ -10,46 +35,363 @@ pub trait LlmClient: Send + Sync {
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse>;
+
+    /// Like `create_chat_completion`, sending the reply text to `deltas` piece by piece
+    /// as it is generated. Clients that can't stream send the whole text at once.
+    async fn create_chat_completion_streaming(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        let response = self.create_chat_completion(request).await?;
+        if let Some(choice) = response.choices.first()
+            && !choice.message.content.is_empty()
+        {
+            let _ = deltas.send(choice.message.content.text().into_owned());
+        }
+        Ok(response)
+    }
+
+    /// The body `create_chat_completion` would send the provider for `request`, for
+    /// looking into what a model was actually asked. Clients without a wire format of
+    /// their own give the request itself.
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        Ok(serde_json::to_value(request)?)
+    }
+
+    /// Models the provider serves. Clients that can't tell list none.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        Ok(Vec::new())
+    }
 }
 
+/// API of the `openrouter` provider when `base_url` is empty
+pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
+/// OpenAI's API or a compatible one; with the `azure_openai` provider, Azure OpenAI, and
+/// with `openrouter`, the models of many vendors named as `vendor/model`
 pub struct OpenAiClient {
-    client: Client<OpenAIConfig>,
+    client: Client<Arc<dyn Config>>,
     model: String,
+    azure: Option<AzureDeployments>,
+    openrouter: bool,
+    reasoning: ReasoningConfig,
+    /// Sends chat completions, whose error responses async-openai doesn't expose
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
+
+/// Azure names the deployment in the URL instead of the model in the body, so each
+/// deployment gets a client of its own
+struct AzureDeployments {
+    base_url: String,
+    api_key: String,
+    config: AzureOpenAiConfig,
+    http: reqwest::Client,
+    clients: Mutex<HashMap<String, Client<Arc<dyn Config>>>>,
+}
+
+impl AzureDeployments {
+    fn client(&self, model: &str) -> Client<Arc<dyn Config>> {
+        let deployment = self.config.deployment_for(model);
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients
+            .entry(deployment.to_string())
+            .or_insert_with(|| {
+                Client::with_config(Arc::new(
+                    AzureConfig::new()
+                        .with_api_base(&self.base_url)
+                        .with_api_key(&self.api_key)
+                        .with_api_version(&self.config.api_version)
+                        .with_deployment_id(deployment),
+                ) as Arc<dyn Config>)
+                .with_http_client(self.http.clone())
+            })
+            .clone()
+    }
+}
+
+/// An OpenAI config sending extra headers with every request
+struct HeadersConfig {
+    inner: OpenAIConfig,
+    headers: HeaderMap,
+}
+
+impl Config for HeadersConfig {
+    fn headers(&self) -> HeaderMap {
+        let mut headers = self.inner.headers();
+        headers.extend(self.headers.clone());
+        headers
+    }
+
+    fn url(&self, path: &str) -> String {
+        self.inner.url(path)
+    }
+
+    fn query(&self) -> Vec<(&str, &str)> {
+        self.inner.query()
+    }
+
+    fn api_base(&self) -> &str {
+        self.inner.api_base()
+    }
+
+    fn api_key(&self) -> &SecretString {
+        self.inner.api_key()
+    }
+}
+
+/// `HTTP-Referer` and `X-Title` naming the app to OpenRouter; values that aren't valid
+/// header values are left out
+fn openrouter_headers(config: &OpenRouterConfig) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    for (name, value) in [
+        ("HTTP-Referer", &config.referer),
+        ("X-Title", &config.title),
+    ] {
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers
+}
+
+/// A user message with the images of the tool results before it
+fn tool_images_message(
+    images: Vec<ContentPart>,
+) -> Result<openai_types::ChatCompletionRequestMessage> {
+    ChatMessage {
+        role: "user".to_string(),
+        content: MessageContent::from("Images returned by the tools above:").with_images(images),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+    .to_openai_message()
+}
+
+/// A model of OpenRouter's `/models` listing
+#[derive(Debug, Deserialize)]
+struct OpenRouterModel {
+    id: String,
+    #[serde(default)]
+    context_length: Option<u32>,
+    #[serde(default)]
+    pricing: Option<OpenRouterPricing>,
+    #[serde(default)]
+    architecture: Option<OpenRouterArchitecture>,
+    #[serde(default)]
+    supported_parameters: Vec<String>,
+}
+
+/// Prices per token, as decimal strings
+#[derive(Debug, Deserialize)]
+struct OpenRouterPricing {
+    prompt: String,
+    completion: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterArchitecture {
+    #[serde(default)]
+    input_modalities: Vec<String>,
+    #[serde(default)]
+    output_modalities: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenRouterModels {
+    data: Vec<OpenRouterModel>,
+}
+
+impl OpenRouterModel {
+    fn into_info(self) -> ModelInfo {
+        let (inputs, outputs) = self
+            .architecture
+            .map(|a| (a.input_modalities, a.output_modalities))
+            .unwrap_or_default();
+        let pricing = self.pricing.and_then(|pricing| {
+            let per_million = |price: &str| price.parse::<f64>().ok().map(|p| p * 1_000_000.0);
+            Some(ModelPrice {
+                input_per_million: per_million(&pricing.prompt)?,
+                output_per_million: per_million(&pricing.completion)?,
+            })
+        });
+        let chat = outputs.is_empty() || outputs.iter().any(|m| m == "text");
+        ModelInfo {
+            capabilities: ModelCapabilities {
+                chat,
+                tools: chat && self.supported_parameters.iter().any(|p| p == "tools"),
+                vision: inputs.iter().any(|m| m == "image"),
+                embeddings: false,
+            },
+            id: self.id,
+            provider: "openrouter".to_string(),
+            context_window: self.context_length,
+            pricing,
+        }
+    }
 }
 
 impl OpenAiClient {
     pub fn new(config: LlmConfig) -> Self {
-        let mut openai_config = OpenAIConfig::new().with_api_key(config.api_key);
+        let http = super::http_client_or_default(&config);
+        Self::with_http_client(config, http)
+    }
+
+    /// A client sending its requests through `http`, such as one from
+    /// [`super::http_client`]
+    pub fn with_http_client(config: LlmConfig, http: reqwest::Client) -> Self {
+        let openrouter = config.provider == "openrouter";
+        let mut openai_config = OpenAIConfig::new().with_api_key(&config.api_key);
 
         if !config.base_url.is_empty() {
-            openai_config = openai_config.with_api_base(config.base_url);
+            openai_config = openai_config.with_api_base(&config.base_url);
+        } else if openrouter {
+            openai_config = openai_config.with_api_base(OPENROUTER_BASE_URL);
         }
 
-        let client = Client::with_config(openai_config);
+        let client = if openrouter {
+            Client::with_config(Arc::new(HeadersConfig {
+                inner: openai_config,
+                headers: openrouter_headers(&config.openrouter),
+            }) as Arc<dyn Config>)
+        } else {
+            Client::with_config(Arc::new(openai_config) as Arc<dyn Config>)
+        }
+        .with_http_client(http.clone());
 
+        let azure = (config.provider == "azure_openai").then(|| AzureDeployments {
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key,
+            config: config.azure,
+            http: http.clone(),
+            clients: Mutex::new(HashMap::new()),
+        });
         Self {
             client,
             model: config.model,
+            azure,
+            openrouter,
+            reasoning: config.reasoning,
+            http,
+            retry: RetryPolicy::new(&config.retry),
         }
     }
-}
 
-#[async_trait]
-impl LlmClient for OpenAiClient {
-    async fn create_chat_completion(
+    /// Client for requests answered by `model`
+    fn client(&self, model: &str) -> Client<Arc<dyn Config>> {
+        match &self.azure {
+            Some(azure) => azure.client(model),
+            None => self.client.clone(),
+        }
+    }
+
+    /// The model a request picked, else the configured one
+    fn model<'a>(&'a self, request: &'a ChatCompletionRequest) -> &'a str {
+        if request.model.is_empty() {
+            &self.model
+        } else {
+            &request.model
+        }
+    }
+
+    /// Models of OpenRouter's listing, whose entries async-openai would drop the
+    /// pricing of
+    async fn list_openrouter_models(&self) -> Result<Vec<ModelInfo>> {
+        let config = self.client.config();
+        let response = self
+            .http
+            .get(config.url("/models"))
+            .headers(config.headers())
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::llm_status(
+                status.as_u16(),
+                format!("OpenRouter returned {status}: {text}"),
+            ));
+        }
+        let listing: OpenRouterModels = response.json().await?;
+        let mut models: Vec<ModelInfo> = listing
+            .data
+            .into_iter()
+            .map(OpenRouterModel::into_info)
+            .collect();
+        models.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(models)
+    }
+
+    /// Sends a chat completion once. Rate limits and server errors keep their status and
+    /// `Retry-After`; other rejections are OpenAI API errors.
+    async fn send(
         &self,
-        request: ChatCompletionRequest,
-    ) -> Result<ChatCompletionResponse> {
-        debug!(
-            "Creating chat completion with {} messages",
-            request.messages.len()
-        );
+        client: &Client<Arc<dyn Config>>,
+        request: &openai_types::CreateChatCompletionRequest,
+    ) -> Result<openai_types::CreateChatCompletionResponse> {
+        let config = client.config();
+        let response = self
+            .http
+            .post(config.url("/chat/completions"))
+            .query(&config.query())
+            .headers(config.headers())
+            .json(request)
+            .send()
+            .await?;
+        let status = response.status();
+        let retry_after = retry_after(response.headers());
+        let body = response.bytes().await?;
+        if status.is_success() {
+            return serde_json::from_slice(&body)
+                .map_err(|e| OpenAIError::JSONDeserialize(e).into());
+        }
+
+        let api_error = serde_json::from_slice::<WrappedError>(&body).map(|e| e.error);
+        if status.as_u16() == 429 || status.is_server_error() {
+            let message = match api_error {
+                Ok(e) => e.message,
+                Err(_) => format!("{status}: {}", String::from_utf8_lossy(&body)),
+            };
+            return Err(Error::LlmStatus {
+                status: status.as_u16(),
+                message,
+                retry_after,
+            });
+        }
+        Err(match api_error {
+            Ok(e) => OpenAIError::ApiError(e).into(),
+            Err(_) => Error::llm_status(
+                status.as_u16(),
+                format!("{status}: {}", String::from_utf8_lossy(&body)),
+            ),
+        })
+    }
 
+    /// Converts a request to OpenAI's types
+    fn build_request(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<openai_types::CreateChatCompletionRequest> {
+        // Requests may pick another model than the configured one
+        let model = self.model(&request).to_string();
         // Convert our types to OpenAI types
         let mut messages = Vec::new();
+        // Tool messages only carry text, so images tools return follow them in a user message
+        let mut tool_images = Vec::new();
         for msg in request.messages {
+            if msg.role != "tool" && !tool_images.is_empty() {
+                messages.push(tool_images_message(std::mem::take(&mut tool_images))?);
+            }
+            if msg.role == "tool" {
+                tool_images.extend(msg.content.images().cloned());
+            }
             messages.push(msg.to_openai_message()?);
         }
+        if !tool_images.is_empty() {
+            messages.push(tool_images_message(tool_images)?);
+        }
 
         let tools: Option<Vec<openai_types::ChatCompletionTool>> = if request.tools.is_empty() {
             None
@@ -63,23 +405,93 @@ impl LlmClient for OpenAiClient {
             )
         };
 
+        let reasoning = self.reasoning.applies_to(&model);
         let mut request_builder = openai_types::CreateChatCompletionRequestArgs::default();
-        request_builder
-            .model(&self.model)
-            .messages(messages)
-            .temperature(request.temperature.unwrap_or(0.7));
+        request_builder.model(model).messages(messages);
 
         if let Some(tools) = tools {
             request_builder.tools(tools);
+            if let Some(choice) = &request.tool_choice {
+                request_builder.tool_choice(choice.to_openai());
+            }
         }
 
-        if let Some(max_tokens) = request.max_tokens {
-            request_builder.max_tokens(max_tokens as u32);
+        if reasoning {
+            // Reasoning models reject sampling parameters but the seed, and count the
+            // reasoning in the reply's tokens
+            if let Some(max_tokens) = request.max_tokens {
+                request_builder.max_completion_tokens(max_tokens as u32);
+            }
+            if let Some(effort) = self.reasoning.effort {
+                request_builder.reasoning_effort(match effort {
+                    ReasoningEffort::Low => openai_types::ReasoningEffort::Low,
+                    ReasoningEffort::Medium => openai_types::ReasoningEffort::Medium,
+                    ReasoningEffort::High => openai_types::ReasoningEffort::High,
+                });
+            }
+        } else {
+            request_builder.temperature(request.temperature.unwrap_or(0.7));
+
+            if let Some(max_tokens) = request.max_tokens {
+                request_builder.max_tokens(max_tokens as u32);
+            }
+
+            if let Some(top_p) = request.top_p {
+                request_builder.top_p(top_p);
+            }
+
+            if !request.stop.is_empty() {
+                request_builder.stop(openai_types::Stop::StringArray(request.stop));
+            }
+
+            if let Some(penalty) = request.frequency_penalty {
+                request_builder.frequency_penalty(penalty);
+            }
+
+            if let Some(penalty) = request.presence_penalty {
+                request_builder.presence_penalty(penalty);
+            }
+
+            if !request.logit_bias.is_empty() {
+                request_builder.logit_bias(
+                    request
+                        .logit_bias
+                        .into_iter()
+                        .map(|(token, bias)| (token, Value::from(bias)))
+                        .collect::<HashMap<_, _>>(),
+                );
+            }
         }
 
-        let openai_request = request_builder.build()?;
+        if let Some(seed) = request.seed {
+            request_builder.seed(seed);
+        }
+
+        if let Some(format) = &request.response_format {
+            request_builder.response_format(format.to_openai());
+        }
+
+        Ok(request_builder.build()?)
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        debug!(
+            "Creating chat completion with {} messages",
+            request.messages.len()
+        );
 
-        let response = self.client.chat().create(openai_request).await?;
+        let client = self.client(self.model(&request));
+        let openai_request = self.build_request(request)?;
+        let response = self
+            .retry
+            .run(|| self.send(&client, &openai_request))
+            .await?;
 
         debug!(
             "Received chat completion response with {} choices",
@@ -106,7 +518,7 @@ impl LlmClient for OpenAiClient {
 
                 let message = ChatMessage {
                     role: choice.message.role.to_string(),
-                    content: choice.message.content.unwrap_or_default(),
+                    content: choice.message.content.unwrap_or_default().into(),
                     tool_calls,
                     tool_call_id: None,
                     name: None,
@@ -120,11 +532,7 @@ impl LlmClient for OpenAiClient {
             })
             .collect();
 
-        let usage = response.usage.map(|u| Usage {
-            prompt_tokens: u.prompt_tokens,
-            completion_tokens: u.completion_tokens,
-            total_tokens: u.total_tokens,
-        });
+        let usage = response.usage.map(Usage::from_openai);
 
         Ok(ChatCompletionResponse {
             id: response.id,
@@ -133,6 +541,128 @@ impl LlmClient for OpenAiClient {
             model: response.model,
             choices,
             usage,
+            provider: None,
+            context_fallback: None,
         })
     }
+
+    async fn create_chat_completion_streaming(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        debug!(
+            "Streaming chat completion with {} messages",
+            request.messages.len()
+        );
+        let client = self.client(self.model(&request));
+        let openai_request = self.build_request(request)?;
+        let mut stream = client.chat().create_stream(openai_request).await?;
+
+        let mut response = ChatCompletionResponse {
+            id: String::new(),
+            object: "chat.completion".to_string(),
+            created: 0,
+            model: String::new(),
+            choices: Vec::new(),
+            usage: None,
+            provider: None,
+            context_fallback: None,
+        };
+        let mut content = String::new();
+        let mut finish_reason = None;
+        // Tool calls arrive in pieces, keyed by their position in the reply
+        let mut tool_calls: BTreeMap<u32, ToolCall> = BTreeMap::new();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            response.id = chunk.id;
+            response.created = chunk.created as u64;
+            response.model = chunk.model;
+            if let Some(usage) = chunk.usage {
+                response.usage = Some(Usage::from_openai(usage));
+            }
+            // Only the first choice is used, as with non-streamed replies
+            let Some(choice) = chunk.choices.into_iter().find(|c| c.index == 0) else {
+                continue;
+            };
+            if let Some(delta) = choice.delta.content.filter(|d| !d.is_empty()) {
+                content.push_str(&delta);
+                let _ = deltas.send(delta);
+            }
+            for piece in choice.delta.tool_calls.unwrap_or_default() {
+                let call = tool_calls.entry(piece.index).or_insert_with(|| ToolCall {
+                    id: String::new(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: String::new(),
+                        arguments: String::new(),
+                    },
+                });
+                if let Some(id) = piece.id {
+                    call.id = id;
+                }
+                if let Some(function) = piece.function {
+                    call.function
+                        .name
+                        .push_str(&function.name.unwrap_or_default());
+                    call.function
+                        .arguments
+                        .push_str(&function.arguments.unwrap_or_default());
+                }
+            }
+            if let Some(reason) = choice.finish_reason {
+                finish_reason = Some(format!("{reason:?}"));
+            }
+        }
+
+        response.choices.push(Choice {
+            index: 0,
+            message: ChatMessage {
+                role: "assistant".to_string(),
+                content: content.into(),
+                tool_calls: (!tool_calls.is_empty()).then(|| tool_calls.into_values().collect()),
+                tool_call_id: None,
+                name: None,
+            },
+            finish_reason,
+        });
+        Ok(response)
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        Ok(serde_json::to_value(self.build_request(request.clone())?)?)
+    }
+
+    /// Azure can't list deployments with an API key, so its are the configured ones.
+    /// OpenRouter's listing also tells what each model can do and costs.
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        if self.openrouter {
+            return self.list_openrouter_models().await;
+        }
+        let (provider, ids) = match &self.azure {
+            Some(azure) => {
+                let mut ids = vec![self.model.clone()];
+                ids.extend(azure.config.deployments.keys().cloned());
+                ids.sort();
+                ids.dedup();
+                ("azure_openai", ids)
+            }
+            None => {
+                let response = self.client.models().list().await?;
+                let mut ids: Vec<String> = response.data.into_iter().map(|m| m.id).collect();
+                ids.sort();
+                ("openai", ids)
+            }
+        };
+        Ok(ids
+            .into_iter()
+            .map(|id| ModelInfo {
+                capabilities: ModelCapabilities::of_openai_model(&id),
+                id,
+                provider: provider.to_string(),
+                context_window: None,
+                pricing: None,
+            })
+            .collect())
+    }
 }
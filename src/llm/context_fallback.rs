@@ -0,0 +1,137 @@
+use super::{
+    catalog::ModelInfo,
+    client::LlmClient,
+    fallback::complete,
+    tokens::{count_tokens, tokenizer_for},
+    types::*,
+};
+use crate::{Result, agent::trim_to_budget, config::ContextFallbackConfig};
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+/// Answers requests the model's context can't hold: on the `llm.context_fallback` model
+/// when there is one, then without the oldest half of the conversation. Responses
+/// answered so carry the `context_fallback` taken.
+pub struct ContextFallbackLlmClient {
+    inner: Box<dyn LlmClient>,
+    /// `llm.model`, for requests that don't pick one
+    default_model: String,
+    config: ContextFallbackConfig,
+}
+
+impl ContextFallbackLlmClient {
+    pub fn new(
+        inner: Box<dyn LlmClient>,
+        default_model: String,
+        config: ContextFallbackConfig,
+    ) -> Self {
+        Self {
+            inner,
+            default_model,
+            config,
+        }
+    }
+
+    async fn call(
+        &self,
+        mut request: ChatCompletionRequest,
+        deltas: Option<&UnboundedSender<String>>,
+    ) -> Result<ChatCompletionResponse> {
+        let mut error = match complete(self.inner.as_ref(), request.clone(), deltas).await {
+            Err(e) if e.is_context_length_exceeded() => e,
+            result => return result,
+        };
+        if request.model.is_empty() {
+            request.model = self.default_model.clone();
+        }
+        let mut fallback = ContextFallback {
+            from_model: request.model.clone(),
+            to_model: None,
+            dropped_messages: 0,
+        };
+
+        if let Some(model) = &self.config.model
+            && *model != request.model
+        {
+            warn!(
+                "{} can't hold the request ({}), retrying on {}",
+                request.model, error, model
+            );
+            request.model = model.clone();
+            fallback.to_model = Some(model.clone());
+            error = match complete(self.inner.as_ref(), request.clone(), deltas).await {
+                Err(e) if e.is_context_length_exceeded() => e,
+                result => return with_fallback(result, fallback),
+            };
+        }
+        if !self.config.truncate {
+            return Err(error);
+        }
+
+        let tokenizer = tokenizer_for(&request.model);
+        let budget = count_tokens(tokenizer.as_ref(), &request.messages, &request.tools) / 2;
+        // The request's input is its last user message, not counting those carrying
+        // images of the tool results before them
+        let input_index = (0..request.messages.len())
+            .rev()
+            .find(|&i| {
+                request.messages[i].role == "user"
+                    && (i == 0 || request.messages[i - 1].role != "tool")
+            })
+            .unwrap_or(request.messages.len());
+        fallback.dropped_messages = trim_to_budget(
+            tokenizer.as_ref(),
+            &mut request.messages,
+            &request.tools,
+            budget,
+            input_index,
+        );
+        if fallback.dropped_messages == 0 {
+            return Err(error);
+        }
+        warn!(
+            "{} can't hold the request ({}), retrying without the {} oldest messages",
+            request.model, error, fallback.dropped_messages
+        );
+        let result = complete(self.inner.as_ref(), request, deltas).await;
+        with_fallback(result, fallback)
+    }
+}
+
+fn with_fallback(
+    result: Result<ChatCompletionResponse>,
+    fallback: ContextFallback,
+) -> Result<ChatCompletionResponse> {
+    result.map(|response| ChatCompletionResponse {
+        context_fallback: Some(fallback),
+        ..response
+    })
+}
+
+#[async_trait]
+impl LlmClient for ContextFallbackLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        self.call(request, None).await
+    }
+
+    async fn create_chat_completion_streaming(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        self.call(request, Some(&deltas)).await
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        self.inner.request_payload(request)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+}
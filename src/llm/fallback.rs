@@ -0,0 +1,101 @@
+use super::{catalog::ModelInfo, client::LlmClient, types::*};
+use crate::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+/// A client of `llm.fallbacks`
+pub struct FallbackProvider {
+    /// `provider:model`, reported as the response's `provider`
+    pub name: String,
+    /// Model the provider answers with, whatever the request asked for
+    pub model: String,
+    pub client: Box<dyn LlmClient>,
+}
+
+/// Tries the configured client, then each fallback in turn while calls fail with
+/// retryable errors. Responses of fallbacks carry the `provider` that answered.
+pub struct FallbackLlmClient {
+    primary: Box<dyn LlmClient>,
+    fallbacks: Vec<FallbackProvider>,
+}
+
+impl FallbackLlmClient {
+    pub fn new(primary: Box<dyn LlmClient>, fallbacks: Vec<FallbackProvider>) -> Self {
+        Self { primary, fallbacks }
+    }
+
+    async fn call(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: Option<&UnboundedSender<String>>,
+    ) -> Result<ChatCompletionResponse> {
+        let mut result = complete(self.primary.as_ref(), request.clone(), deltas).await;
+        for fallback in &self.fallbacks {
+            match &result {
+                Err(e) if e.is_retryable() => {
+                    warn!("LLM call failed, falling back to {}: {}", fallback.name, e)
+                }
+                _ => break,
+            }
+            let request = ChatCompletionRequest {
+                model: fallback.model.clone(),
+                ..request.clone()
+            };
+            result = complete(fallback.client.as_ref(), request, deltas)
+                .await
+                .map(|response| ChatCompletionResponse {
+                    provider: Some(fallback.name.clone()),
+                    ..response
+                });
+        }
+        result
+    }
+}
+
+pub(super) async fn complete(
+    client: &dyn LlmClient,
+    request: ChatCompletionRequest,
+    deltas: Option<&UnboundedSender<String>>,
+) -> Result<ChatCompletionResponse> {
+    match deltas {
+        Some(deltas) => {
+            client
+                .create_chat_completion_streaming(request, deltas.clone())
+                .await
+        }
+        None => client.create_chat_completion(request).await,
+    }
+}
+
+#[async_trait]
+impl LlmClient for FallbackLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        self.call(request, None).await
+    }
+
+    async fn create_chat_completion_streaming(
+        &self,
+        request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        self.call(request, Some(&deltas)).await
+    }
+
+    /// The configured provider's payload; fallbacks only get the request when it fails
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        self.primary.request_payload(request)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let mut models = self.primary.list_models().await?;
+        for fallback in &self.fallbacks {
+            models.extend(fallback.client.list_models().await?);
+        }
+        Ok(models)
+    }
+}
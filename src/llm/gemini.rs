@@ -0,0 +1,529 @@
+use super::{
+    catalog::{ModelCapabilities, ModelInfo},
+    client::LlmClient,
+    types::*,
+};
+use crate::{Error, Result, config::LlmConfig};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use tracing::debug;
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// Google Gemini through the Generative Language API's `generateContent`
+pub struct GeminiClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentRequest {
+    contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<GeminiTool>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Content {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_call: Option<GeminiFunctionCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    function_response: Option<FunctionResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    inline_data: Option<Blob>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file_data: Option<FileData>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Blob {
+    mime_type: String,
+    /// Base64-encoded
+    data: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileData {
+    file_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiFunctionCall {
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FunctionResponse {
+    name: String,
+    response: Value,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiTool {
+    function_declarations: Vec<FunctionDeclaration>,
+}
+
+#[derive(Debug, Serialize)]
+struct FunctionDeclaration {
+    name: String,
+    description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parameters: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    stop_sequences: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_schema: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    #[serde(default)]
+    usage_metadata: Option<UsageMetadata>,
+    #[serde(default)]
+    model_version: Option<String>,
+    #[serde(default)]
+    response_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Option<Content>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: u32,
+    #[serde(default)]
+    candidates_token_count: u32,
+    /// Tokens of thinking models' thoughts, which `candidates_token_count` leaves out
+    #[serde(default)]
+    thoughts_token_count: Option<u32>,
+    #[serde(default)]
+    total_token_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListModelsResponse {
+    #[serde(default)]
+    models: Vec<GeminiModel>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiModel {
+    /// `models/` followed by the id
+    name: String,
+    #[serde(default)]
+    input_token_limit: Option<u32>,
+    #[serde(default)]
+    supported_generation_methods: Vec<String>,
+}
+
+impl GeminiClient {
+    pub fn new(config: LlmConfig) -> Self {
+        let http = super::http_client_or_default(&config);
+        Self::with_http_client(config, http)
+    }
+
+    /// A client sending its requests through `http`, such as one from
+    /// [`super::http_client`]
+    pub fn with_http_client(config: LlmConfig, http: reqwest::Client) -> Self {
+        let base_url = if config.base_url.is_empty() {
+            DEFAULT_BASE_URL.to_string()
+        } else {
+            config.base_url.trim_end_matches('/').to_string()
+        };
+        Self {
+            client: http,
+            base_url,
+            api_key: config.api_key,
+            model: config.model,
+        }
+    }
+
+    /// Converts a request to Gemini's types. System messages become the system
+    /// instruction, assistant messages the `model` role, and tool results
+    /// `functionResponse` parts of a user turn.
+    fn build_request(&self, request: ChatCompletionRequest) -> Result<GenerateContentRequest> {
+        let mut system = Vec::new();
+        let mut contents: Vec<Content> = Vec::new();
+        // Gemini matches results to calls by function name rather than id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+        for message in request.messages {
+            let (role, parts) = match message.role.as_str() {
+                "system" => {
+                    system.push(message.content.text().into_owned());
+                    continue;
+                }
+                "user" => match message.content {
+                    MessageContent::Text(text) => ("user", vec![text_part(text)]),
+                    MessageContent::Parts(parts) => {
+                        ("user", parts.into_iter().map(content_part).collect())
+                    }
+                },
+                "assistant" => {
+                    let mut parts = Vec::new();
+                    if !message.content.is_empty() {
+                        parts.push(text_part(message.content.text().into_owned()));
+                    }
+                    for call in message.tool_calls.unwrap_or_default() {
+                        call_names.insert(call.id.clone(), call.function.name.clone());
+                        let args = serde_json::from_str(&call.function.arguments)
+                            .unwrap_or_else(|_| json!({}));
+                        parts.push(Part {
+                            function_call: Some(GeminiFunctionCall {
+                                name: call.function.name,
+                                args,
+                            }),
+                            ..Default::default()
+                        });
+                    }
+                    ("model", parts)
+                }
+                "tool" => {
+                    let name = message
+                        .tool_call_id
+                        .as_ref()
+                        .and_then(|id| call_names.get(id).cloned())
+                        .or(message.name)
+                        .unwrap_or_default();
+                    // The response must be an object
+                    let text = message.content.text();
+                    let response = match serde_json::from_str(&text) {
+                        Ok(Value::Object(object)) => Value::Object(object),
+                        _ => json!({ "result": text }),
+                    };
+                    let mut parts = vec![Part {
+                        function_response: Some(FunctionResponse { name, response }),
+                        ..Default::default()
+                    }];
+                    // Images the tool returned go along with its response
+                    parts.extend(message.content.images().cloned().map(content_part));
+                    ("user", parts)
+                }
+                role => return Err(Error::llm(format!("Unknown message role: {role}"))),
+            };
+            if parts.is_empty() {
+                continue;
+            }
+            // Turns alternate, so results of parallel calls go out together
+            match contents.last_mut() {
+                Some(last) if last.role.as_deref() == Some(role) => last.parts.extend(parts),
+                _ => contents.push(Content {
+                    role: Some(role.to_string()),
+                    parts,
+                }),
+            }
+        }
+
+        let tools = if request.tools.is_empty() {
+            Vec::new()
+        } else {
+            vec![GeminiTool {
+                function_declarations: request
+                    .tools
+                    .into_iter()
+                    .map(|tool| FunctionDeclaration {
+                        name: tool.function.name,
+                        description: tool.function.description,
+                        parameters: parameters(tool.function.parameters),
+                    })
+                    .collect(),
+            }]
+        };
+
+        Ok(GenerateContentRequest {
+            contents,
+            system_instruction: (!system.is_empty()).then(|| Content {
+                role: None,
+                parts: vec![text_part(system.join("\n\n"))],
+            }),
+            tools,
+            generation_config: GenerationConfig {
+                temperature: request.temperature.unwrap_or(0.7),
+                max_output_tokens: request.max_tokens,
+                top_p: request.top_p,
+                stop_sequences: request.stop,
+                frequency_penalty: request.frequency_penalty,
+                presence_penalty: request.presence_penalty,
+                seed: request.seed,
+                response_mime_type: request
+                    .response_format
+                    .as_ref()
+                    .filter(|format| format.is_json())
+                    .map(|_| "application/json"),
+                response_schema: request
+                    .response_format
+                    .as_ref()
+                    .and_then(|format| format.schema())
+                    .map(|schema| strip_unsupported(schema.clone())),
+            },
+        })
+    }
+}
+
+fn text_part(text: String) -> Part {
+    Part {
+        text: Some(text),
+        ..Default::default()
+    }
+}
+
+/// Images are sent inline, or by URI when they are given as one
+fn content_part(part: ContentPart) -> Part {
+    match part {
+        ContentPart::Text { text } => text_part(text),
+        image => match image.inline_image() {
+            Some((mime_type, data)) => Part {
+                inline_data: Some(Blob { mime_type, data }),
+                ..Default::default()
+            },
+            None => Part {
+                file_data: Some(FileData {
+                    file_uri: image.image_url().unwrap_or_default(),
+                }),
+                ..Default::default()
+            },
+        },
+    }
+}
+
+/// Gemini takes an OpenAPI subset of JSON Schema: it rejects keywords such as
+/// `additionalProperties` and objects without properties
+fn parameters(schema: Value) -> Option<Value> {
+    let empty = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .is_none_or(Map::is_empty);
+    if empty {
+        return None;
+    }
+    Some(strip_unsupported(schema))
+}
+
+fn strip_unsupported(schema: Value) -> Value {
+    match schema {
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .filter(|(key, _)| !matches!(key.as_str(), "$schema" | "additionalProperties"))
+                .map(|(key, value)| (key, strip_unsupported(value)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(strip_unsupported).collect()),
+        other => other,
+    }
+}
+
+/// Gemini's finish reasons under the names OpenAI's client reports
+fn finish_reason(reason: &str, has_tool_calls: bool) -> String {
+    match reason {
+        "STOP" if has_tool_calls => "ToolCalls".to_string(),
+        "STOP" => "Stop".to_string(),
+        "MAX_TOKENS" => "Length".to_string(),
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => {
+            "ContentFilter".to_string()
+        }
+        other => other.to_string(),
+    }
+}
+
+#[async_trait]
+impl LlmClient for GeminiClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        debug!(
+            "Creating Gemini completion with {} messages",
+            request.messages.len()
+        );
+        // Requests may pick another model than the configured one
+        let model = if request.model.is_empty() {
+            self.model.clone()
+        } else {
+            request.model.clone()
+        };
+        let body = self.build_request(request)?;
+        let response = self
+            .client
+            .post(format!(
+                "{}/v1beta/models/{model}:generateContent",
+                self.base_url
+            ))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::llm_status(
+                status.as_u16(),
+                format!("Gemini returned {status}: {text}"),
+            ));
+        }
+        let response: GenerateContentResponse = response.json().await?;
+
+        let choices = response
+            .candidates
+            .into_iter()
+            .enumerate()
+            .map(|(index, candidate)| {
+                let mut content = String::new();
+                let mut tool_calls = Vec::new();
+                for part in candidate.content.map(|c| c.parts).unwrap_or_default() {
+                    if let Some(text) = part.text {
+                        content.push_str(&text);
+                    }
+                    if let Some(call) = part.function_call {
+                        tool_calls.push(ToolCall {
+                            id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                            call_type: "function".to_string(),
+                            function: FunctionCall {
+                                name: call.name,
+                                arguments: call.args.to_string(),
+                            },
+                        });
+                    }
+                }
+                let finish_reason = candidate
+                    .finish_reason
+                    .map(|reason| finish_reason(&reason, !tool_calls.is_empty()));
+                Choice {
+                    index: index as u32,
+                    message: ChatMessage {
+                        role: "assistant".to_string(),
+                        content: content.into(),
+                        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                        tool_call_id: None,
+                        name: None,
+                    },
+                    finish_reason,
+                }
+            })
+            .collect();
+
+        let usage = response.usage_metadata.map(|u| Usage {
+            prompt_tokens: u.prompt_token_count,
+            completion_tokens: u.candidates_token_count + u.thoughts_token_count.unwrap_or(0),
+            total_tokens: u.total_token_count,
+            reasoning_tokens: u.thoughts_token_count,
+        });
+
+        Ok(ChatCompletionResponse {
+            id: response.response_id.unwrap_or_default(),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: response.model_version.unwrap_or(model),
+            choices,
+            usage,
+            provider: None,
+            context_fallback: None,
+        })
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        Ok(serde_json::to_value(self.build_request(request.clone())?)?)
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .client
+            .get(format!("{}/v1beta/models", self.base_url))
+            .query(&[("pageSize", "1000")])
+            .header("x-goog-api-key", &self.api_key)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::llm_status(
+                status.as_u16(),
+                format!("Gemini returned {status}: {text}"),
+            ));
+        }
+        let response: ListModelsResponse = response.json().await?;
+        Ok(response
+            .models
+            .into_iter()
+            .map(|model| {
+                let supports = |method: &str| {
+                    model
+                        .supported_generation_methods
+                        .iter()
+                        .any(|m| m == method)
+                };
+                let chat = supports("generateContent");
+                ModelInfo {
+                    id: model
+                        .name
+                        .strip_prefix("models/")
+                        .unwrap_or(&model.name)
+                        .to_string(),
+                    provider: "gemini".to_string(),
+                    // Gemini's chat models all call functions and read images
+                    capabilities: ModelCapabilities {
+                        chat,
+                        tools: chat,
+                        vision: chat,
+                        embeddings: supports("embedContent"),
+                    },
+                    context_window: model.input_token_limit,
+                    pricing: None,
+                }
+            })
+            .collect())
+    }
+}
@@ -1,5 +1,128 @@
+mod cached;
+mod catalog;
 mod client;
+mod context_fallback;
+mod fallback;
+mod gemini;
+mod ollama;
+mod replay;
+mod retry;
+mod tokens;
 mod types;
 
+pub use cached::CachedLlmClient;
+pub use catalog::{ModelCapabilities, ModelCatalog, ModelInfo, ModelListing, ProviderError};
 pub use client::{LlmClient, OpenAiClient};
+pub use context_fallback::ContextFallbackLlmClient;
+pub use fallback::{FallbackLlmClient, FallbackProvider};
+pub use gemini::GeminiClient;
+pub use ollama::OllamaClient;
+pub use replay::ReplayLlmClient;
+pub use retry::{PacingPolicy, RetryPolicy, retry_after};
+pub use tokens::{
+    HeuristicTokenizer, TiktokenTokenizer, Tokenizer, count_tokens, estimate_tokens, tokenizer_for,
+};
 pub use types::*;
+
+use crate::{Error, Result, config::LlmConfig};
+use std::time::Duration;
+use tracing::warn;
+
+/// The client for `llm.provider`, falling back to `llm.fallbacks` when any are configured
+/// and following `llm.context_fallback` on requests too long for the model
+pub fn create_llm_client(config: LlmConfig) -> Result<Box<dyn LlmClient>> {
+    let context_fallback = config.context_fallback.clone();
+    let model = config.model.clone();
+    let client = create_fallback_client(config)?;
+    if context_fallback.model.is_none() && !context_fallback.truncate {
+        return Ok(client);
+    }
+    Ok(Box::new(ContextFallbackLlmClient::new(
+        client,
+        model,
+        context_fallback,
+    )))
+}
+
+fn create_fallback_client(config: LlmConfig) -> Result<Box<dyn LlmClient>> {
+    if config.fallbacks.is_empty() {
+        return create_provider_client(config);
+    }
+    let fallbacks = config
+        .fallbacks
+        .iter()
+        .map(|fallback| {
+            Ok(FallbackProvider {
+                name: format!("{}:{}", fallback.provider, fallback.model),
+                model: fallback.model.clone(),
+                client: create_provider_client(config.with_fallback(fallback))?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let primary = create_provider_client(LlmConfig {
+        fallbacks: Vec::new(),
+        ..config
+    })?;
+    Ok(Box::new(FallbackLlmClient::new(primary, fallbacks)))
+}
+
+fn create_provider_client(config: LlmConfig) -> Result<Box<dyn LlmClient>> {
+    let http = http_client(&config)?;
+    match config.provider.as_str() {
+        "openai" | "azure_openai" | "openrouter" => {
+            Ok(Box::new(OpenAiClient::with_http_client(config, http)))
+        }
+        "gemini" => Ok(Box::new(GeminiClient::with_http_client(config, http))),
+        "ollama" => Ok(Box::new(OllamaClient::with_http_client(config, http))),
+        "replay" => {
+            let recorder = match &config.replay.record {
+                Some(provider) if provider == "replay" => {
+                    return Err(Error::config(
+                        "llm.replay.record must name another provider",
+                    ));
+                }
+                Some(provider) => Some(create_provider_client(LlmConfig {
+                    provider: provider.clone(),
+                    ..config.clone()
+                })?),
+                None => None,
+            };
+            Ok(Box::new(ReplayLlmClient::new(
+                &config.replay.dir,
+                config.model,
+                recorder,
+            )))
+        }
+        other => Err(Error::config(format!(
+            "Unknown llm.provider '{other}', expected openai, azure_openai, openrouter, gemini, ollama or replay"
+        ))),
+    }
+}
+
+/// HTTP client for requests to the provider of `config`, with its timeouts and proxy
+pub fn http_client(config: &LlmConfig) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if config.request_timeout > 0 {
+        builder = builder.timeout(Duration::from_secs(config.request_timeout));
+    }
+    if config.connect_timeout > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(config.connect_timeout));
+    }
+    if let Some(proxy) = &config.proxy {
+        // The error leaves out the URL, which may hold credentials
+        let proxy = reqwest::Proxy::all(proxy)
+            .map_err(|e| Error::config(format!("Invalid llm.proxy: {e}")))?;
+        builder = builder.proxy(proxy);
+    }
+    builder
+        .build()
+        .map_err(|e| Error::config(format!("Failed to create the LLM HTTP client: {e}")))
+}
+
+/// [`http_client`], or a default client when `config` has an invalid proxy
+fn http_client_or_default(config: &LlmConfig) -> reqwest::Client {
+    http_client(config).unwrap_or_else(|e| {
+        warn!("{e}, sending LLM requests without timeouts or proxy");
+        reqwest::Client::new()
+    })
+}
@@ -0,0 +1,385 @@
+use super::{
+    catalog::{ModelCapabilities, ModelInfo},
+    client::LlmClient,
+    types::*,
+};
+use crate::{
+    Error, Result,
+    config::{LlmConfig, OllamaConfig},
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// A local Ollama server through its native `/api/chat` endpoint. Tool calling works with
+/// the models Ollama supports it for; others answer in text.
+pub struct OllamaClient {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    options: OllamaConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<Tool>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<Value>,
+    /// `json`, or the schema of the reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<Value>,
+    options: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaMessage {
+    role: String,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<OllamaToolCall>,
+    /// Function a tool message is the result of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+    /// Base64-encoded images
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    /// An object, unlike OpenAI's JSON-encoded string
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    model: String,
+    message: OllamaMessage,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u32,
+    #[serde(default)]
+    eval_count: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    #[serde(default)]
+    models: Vec<OllamaTag>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTag {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OllamaShow {
+    /// Such as `completion`, `tools`, `vision` or `embedding`; missing before Ollama 0.6
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    model_info: HashMap<String, Value>,
+}
+
+impl OllamaClient {
+    pub fn new(config: LlmConfig) -> Self {
+        let http = super::http_client_or_default(&config);
+        Self::with_http_client(config, http)
+    }
+
+    /// A client sending its requests through `http`, such as one from
+    /// [`super::http_client`]
+    pub fn with_http_client(config: LlmConfig, http: reqwest::Client) -> Self {
+        let base_url = if config.base_url.is_empty() {
+            DEFAULT_BASE_URL.to_string()
+        } else {
+            config.base_url.trim_end_matches('/').to_string()
+        };
+        Self {
+            client: http,
+            base_url,
+            model: config.model,
+            options: config.ollama,
+        }
+    }
+
+    /// Details of an installed model; empty when Ollama can't tell
+    async fn show(&self, model: &str) -> OllamaShow {
+        let response = self
+            .client
+            .post(format!("{}/api/show", self.base_url))
+            .json(&json!({ "model": model }))
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => {
+                response.json().await.unwrap_or_default()
+            }
+            _ => OllamaShow::default(),
+        }
+    }
+
+    /// Converts a request to Ollama's types
+    fn build_request(&self, request: ChatCompletionRequest) -> OllamaChatRequest {
+        // Ollama matches results to calls by function name rather than id
+        let mut call_names: HashMap<String, String> = HashMap::new();
+        let messages = request
+            .messages
+            .into_iter()
+            .map(|message| {
+                let tool_calls = message
+                    .tool_calls
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|call| {
+                        call_names.insert(call.id, call.function.name.clone());
+                        OllamaToolCall {
+                            function: OllamaFunctionCall {
+                                name: call.function.name,
+                                arguments: serde_json::from_str(&call.function.arguments)
+                                    .unwrap_or_else(|_| json!({})),
+                            },
+                        }
+                    })
+                    .collect();
+                let tool_name = message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| call_names.get(id).cloned())
+                    .or(message.name.filter(|_| message.role == "tool"));
+                // Ollama only takes inline images
+                let mut content = String::new();
+                let mut images = Vec::new();
+                for part in message.content.into_parts() {
+                    match part {
+                        ContentPart::Text { text } => {
+                            if !content.is_empty() {
+                                content.push_str("\n\n");
+                            }
+                            content.push_str(&text);
+                        }
+                        image => match image.inline_image() {
+                            Some((_, data)) => images.push(data),
+                            None => warn!("Ollama can't fetch images by URL, skipping one"),
+                        },
+                    }
+                }
+                OllamaMessage {
+                    role: message.role,
+                    content,
+                    tool_calls,
+                    tool_name,
+                    images,
+                }
+            })
+            .collect();
+
+        let mut options = json!({ "temperature": request.temperature.unwrap_or(0.7) });
+        if let Some(max_tokens) = request.max_tokens {
+            options["num_predict"] = json!(max_tokens);
+        }
+        if let Some(top_p) = request.top_p {
+            options["top_p"] = json!(top_p);
+        }
+        if !request.stop.is_empty() {
+            options["stop"] = json!(request.stop);
+        }
+        if let Some(penalty) = request.frequency_penalty {
+            options["frequency_penalty"] = json!(penalty);
+        }
+        if let Some(penalty) = request.presence_penalty {
+            options["presence_penalty"] = json!(penalty);
+        }
+        if let Some(seed) = request.seed {
+            options["seed"] = json!(seed);
+        }
+        if let Some(num_ctx) = self.options.num_ctx {
+            options["num_ctx"] = json!(num_ctx);
+        }
+        // Requests may pick another model than the configured one
+        let model = if request.model.is_empty() {
+            self.model.clone()
+        } else {
+            request.model
+        };
+        OllamaChatRequest {
+            model,
+            messages,
+            tools: request.tools,
+            stream: false,
+            keep_alive: self.options.keep_alive.as_deref().map(keep_alive),
+            format: match request.response_format {
+                Some(ResponseFormat::JsonObject) => Some(json!("json")),
+                Some(ResponseFormat::JsonSchema { json_schema }) => Some(json_schema.schema),
+                _ => None,
+            },
+            options,
+        }
+    }
+}
+
+/// Ollama reads a bare number as seconds and anything else as a duration such as `5m`
+fn keep_alive(value: &str) -> Value {
+    match value.parse::<i64>() {
+        Ok(seconds) => json!(seconds),
+        Err(_) => json!(value),
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        debug!(
+            "Creating Ollama completion with {} messages",
+            request.messages.len()
+        );
+        let body = self.build_request(request);
+        let response = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::llm_status(
+                status.as_u16(),
+                format!("Ollama returned {status}: {text}"),
+            ));
+        }
+        let response: OllamaChatResponse = response.json().await?;
+
+        let tool_calls: Vec<ToolCall> = response
+            .message
+            .tool_calls
+            .into_iter()
+            .map(|call| ToolCall {
+                id: format!("call_{}", uuid::Uuid::new_v4().simple()),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: call.function.name,
+                    arguments: call.function.arguments.to_string(),
+                },
+            })
+            .collect();
+        // Named as the OpenAI client reports them
+        let finish_reason = match response.done_reason.as_deref() {
+            _ if !tool_calls.is_empty() => Some("ToolCalls".to_string()),
+            Some("stop") => Some("Stop".to_string()),
+            Some("length") => Some("Length".to_string()),
+            other => other.map(str::to_string),
+        };
+
+        Ok(ChatCompletionResponse {
+            id: format!("ollama-{}", uuid::Uuid::new_v4().simple()),
+            object: "chat.completion".to_string(),
+            created: chrono::Utc::now().timestamp() as u64,
+            model: if response.model.is_empty() {
+                body.model
+            } else {
+                response.model
+            },
+            choices: vec![Choice {
+                index: 0,
+                message: ChatMessage {
+                    role: "assistant".to_string(),
+                    content: response.message.content.into(),
+                    tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                    tool_call_id: None,
+                    name: None,
+                },
+                finish_reason,
+            }],
+            usage: Some(Usage {
+                prompt_tokens: response.prompt_eval_count,
+                completion_tokens: response.eval_count,
+                total_tokens: response.prompt_eval_count + response.eval_count,
+                reasoning_tokens: None,
+            }),
+            provider: None,
+            context_fallback: None,
+        })
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        Ok(serde_json::to_value(self.build_request(request.clone()))?)
+    }
+
+    /// Installed models, with capabilities from `/api/show`
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        let response = self
+            .client
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::llm_status(
+                status.as_u16(),
+                format!("Ollama returned {status}: {text}"),
+            ));
+        }
+        let tags: OllamaTags = response.json().await?;
+        let details =
+            futures::future::join_all(tags.models.iter().map(|tag| self.show(&tag.name))).await;
+        Ok(tags
+            .models
+            .into_iter()
+            .zip(details)
+            .map(|(tag, show)| {
+                let capabilities = match show.capabilities {
+                    Some(capabilities) => {
+                        let has = |name: &str| capabilities.iter().any(|c| c == name);
+                        ModelCapabilities {
+                            chat: has("completion"),
+                            tools: has("tools"),
+                            vision: has("vision"),
+                            embeddings: has("embedding"),
+                        }
+                    }
+                    None => ModelCapabilities {
+                        chat: true,
+                        ..Default::default()
+                    },
+                };
+                let context_window = show
+                    .model_info
+                    .iter()
+                    .find(|(key, _)| key.ends_with(".context_length"))
+                    .and_then(|(_, value)| value.as_u64())
+                    .map(|length| length as u32);
+                ModelInfo {
+                    id: tag.name,
+                    provider: "ollama".to_string(),
+                    capabilities,
+                    context_window,
+                    pricing: None,
+                }
+            })
+            .collect())
+    }
+}
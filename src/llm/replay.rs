@@ -0,0 +1,123 @@
+use super::{ChatCompletionRequest, ChatCompletionResponse, LlmClient, MessageContent, ModelInfo};
+use crate::{Error, Result};
+use async_trait::async_trait;
+use ring::digest::{SHA256, digest};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Write,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+/// Answers requests with completions recorded on disk, one JSON file per request, so
+/// agent flows can run without a provider. Requests without a recording go to the
+/// recording client when there is one and are recorded; otherwise they fail.
+pub struct ReplayLlmClient {
+    dir: PathBuf,
+    /// Part of the hash, as requests without a model of their own use the client's
+    model: String,
+    recorder: Option<Box<dyn LlmClient>>,
+}
+
+/// A recording, keeping the request for telling what a file answers
+#[derive(Serialize, Deserialize)]
+struct Recording {
+    request: Value,
+    response: ChatCompletionResponse,
+}
+
+impl ReplayLlmClient {
+    pub fn new(
+        dir: impl Into<PathBuf>,
+        model: String,
+        recorder: Option<Box<dyn LlmClient>>,
+    ) -> Self {
+        Self {
+            dir: dir.into(),
+            model,
+            recorder,
+        }
+    }
+
+    /// The request as it is hashed: with the client's model when it names none and
+    /// message text trimmed, so whitespace-only differences share a recording
+    fn normalize(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        let mut request = request.clone();
+        if request.model.is_empty() {
+            request.model = self.model.clone();
+        }
+        for message in &mut request.messages {
+            if let MessageContent::Text(text) = &mut message.content {
+                *text = text.trim().to_string();
+            }
+        }
+        Ok(serde_json::to_value(&request)?)
+    }
+
+    fn path(&self, normalized: &Value) -> Result<PathBuf> {
+        let hash = digest(&SHA256, &serde_json::to_vec(normalized)?);
+        let mut name = String::with_capacity(69);
+        for byte in &hash.as_ref()[..16] {
+            let _ = write!(name, "{byte:02x}");
+        }
+        name.push_str(".json");
+        Ok(self.dir.join(name))
+    }
+
+    async fn read(path: &Path) -> Result<Option<Recording>> {
+        match tokio::fs::read(path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes).map_err(|e| {
+                Error::llm(format!("Invalid recording {}: {e}", path.display()))
+            })?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for ReplayLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        let normalized = self.normalize(&request)?;
+        let path = self.path(&normalized)?;
+        if let Some(recording) = Self::read(&path).await? {
+            debug!("Replaying {}", path.display());
+            return Ok(recording.response);
+        }
+
+        let Some(recorder) = &self.recorder else {
+            return Err(Error::llm(format!(
+                "No recording {} for this request, and llm.replay.record is unset",
+                path.display()
+            )));
+        };
+        let response = recorder.create_chat_completion(request).await?;
+        let recording = Recording {
+            request: normalized,
+            response,
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&recording)?).await?;
+        info!("Recorded {}", path.display());
+        Ok(recording.response)
+    }
+
+    fn request_payload(&self, request: &ChatCompletionRequest) -> Result<Value> {
+        match &self.recorder {
+            Some(recorder) => recorder.request_payload(request),
+            None => Ok(serde_json::to_value(request)?),
+        }
+    }
+
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        match &self.recorder {
+            Some(recorder) => recorder.list_models().await,
+            None => Ok(Vec::new()),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::{
+    Result,
+    config::{LlmPacingConfig, LlmRetryConfig},
+};
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use std::{future::Future, time::Duration};
+use tracing::warn;
+use uuid::Uuid;
+
+/// When and how often a failing LLM call is tried again
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &LlmRetryConfig) -> Self {
+        Self {
+            max_attempts: config.max_attempts.max(1),
+            initial_backoff: Duration::from_millis(config.initial_backoff_ms),
+            max_backoff: Duration::from_millis(config.max_backoff_ms),
+            jitter: config.jitter,
+        }
+    }
+
+    /// The delay before attempt `attempt + 1` after attempt `attempt` failed, counting from
+    /// 1, or `None` when the call should fail as it did. A `retry_after` from the API
+    /// replaces the backoff, unless it asks for longer than the longest backoff.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= self.max_backoff).then_some(retry_after);
+        }
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_backoff);
+        Some(if self.jitter {
+            with_jitter(backoff)
+        } else {
+            backoff
+        })
+    }
+
+    /// Runs `call` until it succeeds, fails with an error that isn't retryable, or runs
+    /// out of attempts
+    pub async fn run<T, F, Fut>(&self, mut call: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let error = match call().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            let delay = error
+                .is_retryable()
+                .then(|| self.delay(attempt, error.retry_after()))
+                .flatten();
+            let Some(delay) = delay else {
+                return Err(error);
+            };
+            warn!(
+                "LLM call failed ({}), retrying {}/{} in {:?}",
+                error,
+                attempt,
+                self.max_attempts - 1,
+                delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// How long a run pauses between its LLM calls
+#[derive(Debug, Clone)]
+pub struct PacingPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl PacingPolicy {
+    /// The policy of `config`, or `None` when pacing is disabled
+    pub fn new(config: &LlmPacingConfig) -> Option<Self> {
+        (config.initial_delay_ms > 0).then(|| Self {
+            initial_delay: Duration::from_millis(config.initial_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+            jitter: config.jitter,
+        })
+    }
+
+    /// The pause before the next call of a run that made `calls` of them so far, none
+    /// before the first
+    pub fn delay(&self, calls: usize) -> Duration {
+        if calls == 0 {
+            return Duration::ZERO;
+        }
+        let delay = self
+            .initial_delay
+            .saturating_mul(1 << (calls - 1).min(16))
+            .min(self.max_delay);
+        if self.jitter {
+            with_jitter(delay)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Between half and all of `delay`, picked at random
+fn with_jitter(delay: Duration) -> Duration {
+    let fraction = (Uuid::new_v4().as_u64_pair().0 >> 11) as f64 / (1u64 << 53) as f64;
+    delay.mul_f64(0.5 + fraction / 2.0)
+}
+
+/// The wait a response asks for in `retry-after-ms` or `Retry-After`, which holds either
+/// seconds or an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(ms) = header("retry-after-ms").and_then(|ms| ms.trim().parse::<f64>().ok()) {
+        return Duration::try_from_secs_f64(ms / 1000.0).ok();
+    }
+    let value = header("retry-after")?.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (at.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
@@ -0,0 +1,99 @@
+use super::{ChatMessage, Tool};
+use std::sync::Arc;
+use tiktoken_rs::{CoreBPE, bpe_for_tokenizer, tokenizer::get_tokenizer};
+
+/// Rough characters-per-token ratio of English text with common tokenizers
+const CHARS_PER_TOKEN: usize = 4;
+/// Tokens each message costs for its role and separators
+const MESSAGE_OVERHEAD: usize = 4;
+
+/// Counts how many tokens a model would see for a piece of text
+pub trait Tokenizer: Send + Sync {
+    /// Encoding name, or `heuristic` when counts are approximate
+    fn name(&self) -> &str;
+
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Approximates counts from text length, for models whose tokenizer isn't known
+pub struct HeuristicTokenizer;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(CHARS_PER_TOKEN)
+    }
+}
+
+/// Exact counts with an OpenAI tiktoken encoding
+pub struct TiktokenTokenizer {
+    name: String,
+    bpe: &'static CoreBPE,
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn count(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+/// Picks the tokenizer of `model`. OpenAI models, also behind a router prefix such as
+/// `openai/gpt-4o`, get their tiktoken encoding; any other model gets the heuristic.
+pub fn tokenizer_for(model: &str) -> Arc<dyn Tokenizer> {
+    let name = model.rsplit('/').next().unwrap_or(model);
+    let Some(tokenizer) = get_tokenizer(name) else {
+        return Arc::new(HeuristicTokenizer);
+    };
+    match bpe_for_tokenizer(tokenizer) {
+        Ok(bpe) => Arc::new(TiktokenTokenizer {
+            name: format!("{tokenizer:?}"),
+            bpe,
+        }),
+        Err(_) => Arc::new(HeuristicTokenizer),
+    }
+}
+
+/// Counts the prompt tokens of a request's messages and tool definitions. Message framing
+/// is approximated the same way for every tokenizer.
+pub fn count_tokens(tokenizer: &dyn Tokenizer, messages: &[ChatMessage], tools: &[Tool]) -> usize {
+    let message_tokens: usize = messages
+        .iter()
+        .map(|message| {
+            let mut tokens = MESSAGE_OVERHEAD
+                + tokenizer.count(&message.role)
+                + tokenizer.count(&message.content.text());
+            if let Some(tool_calls) = &message.tool_calls {
+                tokens += tool_calls
+                    .iter()
+                    .map(|call| {
+                        tokenizer.count(&call.function.name)
+                            + tokenizer.count(&call.function.arguments)
+                    })
+                    .sum::<usize>();
+            }
+            tokens
+        })
+        .sum();
+    let tool_tokens: usize = tools
+        .iter()
+        .map(|tool| {
+            serde_json::to_string(tool)
+                .map(|json| tokenizer.count(&json))
+                .unwrap_or_default()
+        })
+        .sum();
+    message_tokens + tool_tokens
+}
+
+/// Approximates how many prompt tokens a request uses without knowing the model. Not
+/// exact for any model, but close enough to spot oversized prompts.
+pub fn estimate_tokens(messages: &[ChatMessage], tools: &[Tool]) -> usize {
+    count_tokens(&HeuristicTokenizer, messages, tools)
+}
@@ -1,21 +1,185 @@
+use crate::config::SamplingConfig;
 use async_openai::types::{
     ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestAssistantMessageContent,
-    ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
+    ChatCompletionRequestMessage, ChatCompletionRequestMessageContentPartImage,
+    ChatCompletionRequestMessageContentPartText, ChatCompletionRequestSystemMessageArgs,
     ChatCompletionRequestSystemMessageContent, ChatCompletionRequestToolMessageArgs,
     ChatCompletionRequestToolMessageContent, ChatCompletionRequestUserMessageArgs,
-    ChatCompletionRequestUserMessageContent, ChatCompletionTool, FunctionObject,
+    ChatCompletionRequestUserMessageContent, ChatCompletionRequestUserMessageContentPart,
+    ChatCompletionTool, FunctionObject, ImageDetail, ImageUrl, ResponseFormatJsonSchema,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
     pub role: String,
-    pub content: String,
+    pub content: MessageContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
 }
 
+/// What a message says: text alone, or text along with images
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// Text of the content, with that of several text parts joined by blank lines
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(text) => Cow::Borrowed(text),
+            Self::Parts(parts) => Cow::Owned(
+                parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Parts(parts) => parts.is_empty(),
+        }
+    }
+
+    /// Adds `text` to the end of the content's text
+    pub fn push_str(&mut self, text: &str) {
+        match self {
+            Self::Text(content) => content.push_str(text),
+            Self::Parts(parts) => match parts.last_mut() {
+                Some(ContentPart::Text { text: last }) => last.push_str(text),
+                _ => parts.push(ContentPart::Text {
+                    text: text.to_string(),
+                }),
+            },
+        }
+    }
+
+    /// Parts of the content that aren't text
+    pub fn images(&self) -> impl Iterator<Item = &ContentPart> {
+        let parts = match self {
+            Self::Text(_) => &[][..],
+            Self::Parts(parts) => parts.as_slice(),
+        };
+        parts
+            .iter()
+            .filter(|part| !matches!(part, ContentPart::Text { .. }))
+    }
+
+    /// The content with `images` after what it already holds
+    pub fn with_images(self, images: Vec<ContentPart>) -> Self {
+        if images.is_empty() {
+            return self;
+        }
+        let mut parts = self.into_parts();
+        parts.extend(images);
+        Self::Parts(parts)
+    }
+
+    pub fn into_parts(self) -> Vec<ContentPart> {
+        match self {
+            Self::Text(text) if text.is_empty() => Vec::new(),
+            Self::Text(text) => vec![ContentPart::Text { text }],
+            Self::Parts(parts) => parts,
+        }
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        matches!(self, Self::Text(text) if text == other)
+    }
+}
+
+impl PartialEq<String> for MessageContent {
+    fn eq(&self, other: &String) -> bool {
+        matches!(self, Self::Text(text) if text == other)
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+/// A piece of multimodal message content
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text {
+        text: String,
+    },
+    ImageUrl {
+        url: String,
+    },
+    /// Inline image data, base64-encoded
+    ImageBase64 {
+        mime_type: String,
+        data: String,
+    },
+}
+
+impl ContentPart {
+    /// URL of an image part, with inline data as a `data:` URL
+    pub fn image_url(&self) -> Option<String> {
+        match self {
+            Self::Text { .. } => None,
+            Self::ImageUrl { url } => Some(url.clone()),
+            Self::ImageBase64 { mime_type, data } => {
+                Some(format!("data:{mime_type};base64,{data}"))
+            }
+        }
+    }
+
+    /// Mime type and base64 data of an inline image, including one given as a `data:` URL
+    pub fn inline_image(&self) -> Option<(String, String)> {
+        match self {
+            Self::ImageBase64 { mime_type, data } => Some((mime_type.clone(), data.clone())),
+            Self::ImageUrl { url } => {
+                let (mime_type, data) = url.strip_prefix("data:")?.split_once(";base64,")?;
+                Some((mime_type.to_string(), data.to_string()))
+            }
+            Self::Text { .. } => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
     pub id: String,
@@ -31,16 +195,178 @@ pub struct FunctionCall {
 
 // Function is defined locally
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct ChatCompletionRequest {
     pub model: String,
     pub messages: Vec<ChatMessage>,
     pub tools: Vec<Tool>,
     pub max_tokens: Option<u16>,
     pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// Sequences ending the reply where they would be generated
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Bias of tokens by their ID, from -100 to 100
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub logit_bias: BTreeMap<String, i32>,
+    /// Shape the reply must take, when not free text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Whether and which tools the reply must call, when not left to the model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
 }
 
-#[derive(Debug, Clone)]
+impl ChatCompletionRequest {
+    /// Applies the parameters `sampling` sets, leaving the others as they are
+    pub fn with_sampling(mut self, sampling: &SamplingConfig) -> Self {
+        self.temperature = sampling.temperature.or(self.temperature);
+        self.max_tokens = sampling.max_tokens.or(self.max_tokens);
+        self.top_p = sampling.top_p.or(self.top_p);
+        if !sampling.stop.is_empty() {
+            self.stop = sampling.stop.clone();
+        }
+        self.frequency_penalty = sampling.frequency_penalty.or(self.frequency_penalty);
+        self.presence_penalty = sampling.presence_penalty.or(self.presence_penalty);
+        self.seed = sampling.seed.or(self.seed);
+        if !sampling.logit_bias.is_empty() {
+            self.logit_bias = sampling.logit_bias.clone();
+        }
+        self
+    }
+}
+
+/// Shape of a reply, in OpenAI's `response_format` format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    /// Any JSON object
+    JsonObject,
+    /// JSON matching `json_schema.schema`
+    JsonSchema {
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: Value,
+    /// Asks OpenAI to constrain generation to the schema, which then has to stay
+    /// within the subset it supports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// Whether replies must be JSON
+    pub fn is_json(&self) -> bool {
+        !matches!(self, ResponseFormat::Text)
+    }
+
+    /// The schema replies must match, if any
+    pub fn schema(&self) -> Option<&Value> {
+        match self {
+            ResponseFormat::JsonSchema { json_schema } => Some(&json_schema.schema),
+            _ => None,
+        }
+    }
+
+    pub fn to_openai(&self) -> async_openai::types::ResponseFormat {
+        use async_openai::types::ResponseFormat as OpenAiFormat;
+        match self {
+            ResponseFormat::Text => OpenAiFormat::Text,
+            ResponseFormat::JsonObject => OpenAiFormat::JsonObject,
+            ResponseFormat::JsonSchema { json_schema } => OpenAiFormat::JsonSchema {
+                json_schema: ResponseFormatJsonSchema {
+                    description: json_schema.description.clone(),
+                    name: json_schema.name.clone(),
+                    schema: Some(json_schema.schema.clone()),
+                    strict: json_schema.strict,
+                },
+            },
+        }
+    }
+}
+
+/// Whether and which tools a reply calls, in OpenAI's `tool_choice` format: `"auto"`,
+/// `"none"`, `"required"` or `{"type": "function", "function": {"name": ...}}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolChoice {
+    Mode(ToolChoiceMode),
+    /// Calls the named tool
+    Function(NamedToolChoice),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolChoiceMode {
+    /// The model decides
+    Auto,
+    /// No tool is called
+    None,
+    /// At least one tool is called
+    Required,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "function")]
+pub struct NamedToolChoice {
+    pub function: FunctionName,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionName {
+    pub name: String,
+}
+
+impl ToolChoice {
+    /// Forces a call to `name`
+    pub fn function(name: impl Into<String>) -> Self {
+        ToolChoice::Function(NamedToolChoice {
+            function: FunctionName { name: name.into() },
+        })
+    }
+
+    /// The tool that must be called, if one is named
+    pub fn function_name(&self) -> Option<&str> {
+        match self {
+            ToolChoice::Function(named) => Some(&named.function.name),
+            ToolChoice::Mode(_) => None,
+        }
+    }
+
+    pub fn to_openai(&self) -> async_openai::types::ChatCompletionToolChoiceOption {
+        use async_openai::types::{
+            ChatCompletionNamedToolChoice, ChatCompletionToolChoiceOption as OpenAiChoice,
+            ChatCompletionToolType, FunctionName as OpenAiFunctionName,
+        };
+        match self {
+            ToolChoice::Mode(ToolChoiceMode::Auto) => OpenAiChoice::Auto,
+            ToolChoice::Mode(ToolChoiceMode::None) => OpenAiChoice::None,
+            ToolChoice::Mode(ToolChoiceMode::Required) => OpenAiChoice::Required,
+            ToolChoice::Function(named) => OpenAiChoice::Named(ChatCompletionNamedToolChoice {
+                r#type: ChatCompletionToolType::Function,
+                function: OpenAiFunctionName {
+                    name: named.function.name.clone(),
+                },
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub id: String,
     pub object: String,
@@ -48,9 +374,27 @@ pub struct ChatCompletionResponse {
     pub model: String,
     pub choices: Vec<Choice>,
     pub usage: Option<Usage>,
+    /// Provider of `llm.fallbacks` that answered, when the configured one failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// How the request was answered after exceeding the model's context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_fallback: Option<ContextFallback>,
 }
 
-#[derive(Debug, Clone)]
+/// What a request the model's context couldn't hold was changed into
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextFallback {
+    /// Model the request was for
+    pub from_model: String,
+    /// Model of `llm.context_fallback` that answered instead, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_model: Option<String>,
+    /// Oldest messages left out of the request
+    pub dropped_messages: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Choice {
     pub index: u32,
     pub message: ChatMessage,
@@ -64,6 +408,22 @@ pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Tokens the model reasoned with before answering, counted in `completion_tokens`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl Usage {
+    pub fn from_openai(usage: async_openai::types::CompletionUsage) -> Self {
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+            reasoning_tokens: usage
+                .completion_tokens_details
+                .and_then(|details| details.reasoning_tokens),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,7 +446,7 @@ impl ChatMessage {
             "system" => {
                 let msg = ChatCompletionRequestSystemMessageArgs::default()
                     .content(ChatCompletionRequestSystemMessageContent::Text(
-                        self.content.clone(),
+                        self.content.text().into_owned(),
                     ))
                     .build()
                     .map_err(|e| {
@@ -96,9 +456,7 @@ impl ChatMessage {
             }
             "user" => {
                 let mut builder = ChatCompletionRequestUserMessageArgs::default();
-                builder.content(ChatCompletionRequestUserMessageContent::Text(
-                    self.content.clone(),
-                ));
+                builder.content(self.user_content());
                 if let Some(ref name) = self.name {
                     builder.name(name);
                 }
@@ -111,7 +469,7 @@ impl ChatMessage {
                 let mut builder = ChatCompletionRequestAssistantMessageArgs::default();
                 if !self.content.is_empty() {
                     builder.content(ChatCompletionRequestAssistantMessageContent::Text(
-                        self.content.clone(),
+                        self.content.text().into_owned(),
                     ));
                 }
                 if let Some(ref tool_calls) = self.tool_calls {
@@ -137,7 +495,7 @@ impl ChatMessage {
             "tool" => {
                 let msg = ChatCompletionRequestToolMessageArgs::default()
                     .content(ChatCompletionRequestToolMessageContent::Text(
-                        self.content.clone(),
+                        self.content.text().into_owned(),
                     ))
                     .tool_call_id(self.tool_call_id.as_ref().unwrap_or(&String::new()))
                     .build()
@@ -152,6 +510,35 @@ impl ChatMessage {
     }
 }
 
+impl ChatMessage {
+    /// Content of a user message, as multimodal parts when it carries any
+    fn user_content(&self) -> ChatCompletionRequestUserMessageContent {
+        let parts = match &self.content {
+            MessageContent::Text(text) => {
+                return ChatCompletionRequestUserMessageContent::Text(text.clone());
+            }
+            MessageContent::Parts(parts) => parts,
+        };
+        let parts = parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => ChatCompletionRequestUserMessageContentPart::Text(
+                    ChatCompletionRequestMessageContentPartText { text: text.clone() },
+                ),
+                image => ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                    ChatCompletionRequestMessageContentPartImage {
+                        image_url: ImageUrl {
+                            url: image.image_url().unwrap_or_default(),
+                            detail: Some(ImageDetail::Auto),
+                        },
+                    },
+                ),
+            })
+            .collect();
+        ChatCompletionRequestUserMessageContent::Array(parts)
+    }
+}
+
 impl Tool {
     pub fn to_openai_tool(&self) -> ChatCompletionTool {
         ChatCompletionTool {
@@ -1,7 +1,112 @@
 use anyhow::Result;
-use jarvis_rust::{config, server};
+use clap::{Parser, Subcommand};
+use jarvis_rust::{
+    agent::{Agent, replay_session},
+    config,
+    history::{HistoryStorage, scan_history},
+    server,
+};
+use std::io::Read;
+#[cfg(feature = "knowledge")]
+use std::{collections::BTreeMap, path::PathBuf};
 use tracing::info;
 
+#[derive(Parser)]
+#[command(
+    name = "jarvis",
+    version,
+    long_version = concat!(
+        env!("CARGO_PKG_VERSION"),
+        " (",
+        env!("JARVIS_GIT_SHA"),
+        ", built ",
+        env!("JARVIS_BUILD_DATE"),
+        ")"
+    ),
+    about = "J.A.R.V.I.S. agent server"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP server (default)
+    Serve,
+    /// Parse, chunk and embed documents into the knowledge base
+    #[cfg(feature = "knowledge")]
+    Ingest {
+        /// Files or directories (searched recursively) of PDF, HTML, Markdown or text files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Metadata stored with every chunk, e.g. --tag topic=appliances
+        #[arg(long = "tag", value_name = "KEY=VALUE", value_parser = parse_tag)]
+        tags: Vec<(String, String)>,
+    },
+    /// Re-run a stored session against the current config, serving recorded tool
+    /// results, and report where replies diverge
+    Replay {
+        session_id: String,
+        /// Print the full report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report the sessions whose stored history contains email addresses, phone numbers
+    /// or card numbers
+    ScanPii {
+        /// Only these sessions instead of every one
+        #[arg(long = "session", value_name = "SESSION_ID")]
+        sessions: Vec<String>,
+        /// Print the full report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Manage the Windows service
+    #[cfg(windows)]
+    Service {
+        #[command(subcommand)]
+        action: ServiceAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the JSON Schema of config.yaml
+    Schema,
+    /// Encrypt a value into an `!encrypted` config value, creating the key on first use
+    Encrypt {
+        /// Value to encrypt; read from stdin if omitted
+        value: Option<String>,
+    },
+}
+
+#[cfg(windows)]
+#[derive(Subcommand)]
+enum ServiceAction {
+    /// Register `jarvis service run` as an automatically started service
+    Install,
+    /// Stop and remove the service
+    Uninstall,
+    /// Entry point used by the service control manager
+    Run,
+}
+
+#[cfg(feature = "knowledge")]
+fn parse_tag(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{value}'")),
+    }
+}
+
 /// Validates that a log level string is valid
 fn validate_log_level(level: &str) -> Result<()> {
     level
@@ -17,6 +122,39 @@ fn validate_log_level(level: &str) -> Result<()> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    #[cfg(windows)]
+    if let Some(Command::Service {
+        action: ServiceAction::Run,
+    }) = &cli.command
+    {
+        jarvis_rust::service::use_executable_dir()?;
+    }
+
+    // Needs no config file, so it works before one exists
+    if let Some(Command::Config { action }) = &cli.command {
+        match action {
+            ConfigAction::Schema => {
+                println!("{}", serde_json::to_string_pretty(&config::schema())?)
+            }
+            ConfigAction::Encrypt { value } => {
+                let value = match value {
+                    Some(value) => value.clone(),
+                    None => {
+                        let mut value = String::new();
+                        std::io::stdin().read_to_string(&mut value)?;
+                        value.trim_end_matches(['\r', '\n']).to_string()
+                    }
+                };
+                let (key, source) = config::ConfigKey::load_or_create()?;
+                eprintln!("Encrypting with the key in {source}");
+                println!("!{} \"{}\"", config::ENCRYPTED_TAG, key.encrypt(&value)?);
+            }
+        }
+        return Ok(());
+    }
+
     // Load configuration first (before logging setup)
     let config = match config::load().await {
         Ok(config) => config,
@@ -44,14 +182,146 @@ async fn main() -> Result<()> {
         .json()
         .init();
 
-    info!(
-        "Starting J.A.R.V.I.S. Rust server with log level: {}",
-        log_level
-    );
     info!("Configuration loaded successfully");
 
-    // Start the server
-    server::run(config).await?;
+    config::create_data_dir(&config.server)?;
+    config::warn_legacy_paths(&config);
+
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => {
+            info!(
+                "Starting J.A.R.V.I.S. Rust server with log level: {}",
+                log_level
+            );
+            server::run(config).await?;
+        }
+        #[cfg(feature = "knowledge")]
+        Command::Ingest { paths, tags } => {
+            let knowledge = jarvis_rust::knowledge::KnowledgeBase::from_config(
+                &config,
+                &config.server.resolved_database_path(),
+            )
+            .await?;
+            let metadata: BTreeMap<String, String> = tags.into_iter().collect();
+            for path in paths {
+                let report = knowledge.ingest_path(&path, &metadata).await?;
+                println!(
+                    "{}: {} documents, {} chunks",
+                    path.display(),
+                    report.documents,
+                    report.chunks
+                );
+                for skipped in report.skipped {
+                    println!("  skipped unsupported file {}", skipped.display());
+                }
+            }
+            if !config.knowledge.enabled {
+                println!("Note: set knowledge.enabled to let the agent search these documents");
+            }
+        }
+        Command::Replay { session_id, json } => {
+            let history = HistoryStorage::open(
+                &config.server.resolved_database_path(),
+                &config.server.database,
+            )
+            .await?;
+            let mut agent = Agent::from_config(&config).await?;
+            let report = replay_session(&mut agent, &history, &session_id).await?;
+            if report.turns.is_empty() {
+                anyhow::bail!("Session '{session_id}' has no stored turns");
+            }
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for turn in &report.turns {
+                    if !turn.diverged() {
+                        println!("Turn {}: matches", turn.turn);
+                        continue;
+                    }
+                    println!("Turn {}: diverged", turn.turn);
+                    println!("  input: {}", turn.input);
+                    if !turn.tools_match {
+                        println!(
+                            "  tools: recorded [{}], replayed [{}]",
+                            turn.recorded_tools.join(", "),
+                            turn.replayed_tools.join(", ")
+                        );
+                    }
+                    if let Some(error) = &turn.error {
+                        println!("  error: {error}");
+                    }
+                    if !turn.output_matches {
+                        print_replay_output("recorded", turn.recorded_output.as_deref());
+                        print_replay_output("replayed", turn.replayed_output.as_deref());
+                    }
+                }
+                println!(
+                    "{} of {} turns diverged",
+                    report.diverged_count(),
+                    report.turns.len()
+                );
+            }
+            if report.first_divergence().is_some() {
+                std::process::exit(1);
+            }
+        }
+        Command::ScanPii { sessions, json } => {
+            let history = HistoryStorage::open(
+                &config.server.resolved_database_path(),
+                &config.server.database,
+            )
+            .await?;
+            let sessions = (!sessions.is_empty()).then_some(sessions);
+            let report = scan_history(&history, sessions).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for session in &report.sessions {
+                    let counts: Vec<String> = session
+                        .counts
+                        .iter()
+                        .map(|(kind, count)| format!("{count} {}", kind.as_str()))
+                        .collect();
+                    println!("{}: {}", session.session_id, counts.join(", "));
+                    for finding in &session.findings {
+                        println!(
+                            "  #{} {} {}{}: {} {}",
+                            finding.message_id.unwrap_or_default(),
+                            finding.role,
+                            finding.created_at.to_rfc3339(),
+                            if finding.deleted { " (deleted)" } else { "" },
+                            finding.found.kind.as_str(),
+                            finding.found.redacted
+                        );
+                    }
+                }
+                println!(
+                    "{} of {} sessions ({} messages) contain personal data",
+                    report.sessions.len(),
+                    report.sessions_scanned,
+                    report.messages_scanned
+                );
+            }
+        }
+        Command::Config { .. } => unreachable!("handled before loading the config"),
+        #[cfg(windows)]
+        Command::Service { action } => match action {
+            ServiceAction::Install => jarvis_rust::service::install()?,
+            ServiceAction::Uninstall => jarvis_rust::service::uninstall()?,
+            // The dispatcher blocks until the service stops
+            ServiceAction::Run => {
+                tokio::task::spawn_blocking(move || jarvis_rust::service::run(config)).await??
+            }
+        },
+    }
 
     Ok(())
 }
+
+fn print_replay_output(label: &str, output: Option<&str>) {
+    println!("  {label}:");
+    for line in output.unwrap_or("(none)").lines() {
+        println!("    {line}");
+    }
+}
@@ -3,6 +3,7 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use tokio::sync::mpsc::UnboundedSender;
 
 pub use crate::config::McpClientType;
 
@@ -76,6 +77,9 @@ pub struct McpTool {
     pub description: String,
     #[serde(default)]
     pub input_schema: Value,
+    /// JSON Schema of the structured output, for tools that return JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -113,6 +117,17 @@ pub struct McpResourceContent {
     pub blob: Option<String>,
 }
 
+/// Progress a server reported for a running tool call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpProgress {
+    /// Increases with every update, even when `total` is unknown
+    pub progress: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpPrompt {
     pub name: String,
@@ -153,12 +168,23 @@ pub trait McpClient: Send + Sync {
     async fn initialize(&mut self, request: McpInitializeRequest) -> Result<McpInitializeResponse>;
     async fn list_tools(&self) -> Result<Vec<McpTool>>;
     async fn call_tool(&self, request: McpToolCallRequest) -> Result<McpToolCallResponse>;
+    /// Calls a tool, forwarding the progress notifications the server sends for it.
+    /// Dropping the returned future cancels the call where the transport allows it.
+    async fn call_tool_with_progress(
+        &self,
+        request: McpToolCallRequest,
+        _progress: UnboundedSender<McpProgress>,
+    ) -> Result<McpToolCallResponse> {
+        self.call_tool(request).await
+    }
     async fn list_prompts(&self) -> Result<Vec<McpPrompt>>;
     async fn get_prompt(&self, request: McpGetPromptRequest) -> Result<McpGetPromptResponse>;
     async fn close(&mut self) -> Result<()>;
 }
 
 pub async fn create_mcp_client(config: McpServerConfig) -> Result<Box<dyn McpClient>> {
-    // Use rmcp client exclusively
-    crate::mcp_client::create_rmcp_client(config).await
+    match config.client_type {
+        McpClientType::Fixture => Ok(Box::new(super::FixtureMcpClient::from_config(&config)?)),
+        _ => crate::mcp_client::create_rmcp_client(config).await,
+    }
 }
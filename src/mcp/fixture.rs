@@ -0,0 +1,224 @@
+//! MCP client serving tools and canned results from a fixture file, so whole runs can be
+//! tested without spawning real tool servers.
+//!
+//! ```yaml
+//! tools:
+//!   - name: get_weather
+//!     description: Gets the weather
+//!     input_schema: {type: object, properties: {location: {type: string}}}
+//!     calls:
+//!       - arguments: {location: London}  # matches calls with these arguments
+//!         text: Rainy
+//!       - text: Sunny                    # no arguments: matches any call
+//!       - text: Service unavailable
+//!         is_error: true
+//! ```
+
+use super::{
+    McpClient, McpContent, McpGetPromptRequest, McpGetPromptResponse, McpInitializeRequest,
+    McpInitializeResponse, McpPrompt, McpPromptMessage, McpPromptsCapability,
+    McpServerCapabilities, McpServerInfo, McpTool, McpToolCallRequest, McpToolCallResponse,
+    McpToolsCapability,
+};
+use crate::{Error, Result, config::McpServerConfig};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::Path;
+use tracing::debug;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct McpFixture {
+    #[serde(default)]
+    pub tools: Vec<FixtureTool>,
+    #[serde(default)]
+    pub prompts: Vec<FixturePrompt>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureTool {
+    #[serde(flatten)]
+    pub tool: McpTool,
+    /// Canned results, the first whose arguments match a call answering it
+    #[serde(default)]
+    pub calls: Vec<FixtureCall>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FixtureCall {
+    /// Arguments a call must have for this result; other arguments are ignored
+    #[serde(default)]
+    pub arguments: Map<String, Value>,
+    /// Text result, the usual shorthand for `content`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Full result content, returned after `text`
+    #[serde(default)]
+    pub content: Vec<McpContent>,
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+impl FixtureCall {
+    fn matches(&self, request: &McpToolCallRequest) -> bool {
+        self.arguments
+            .iter()
+            .all(|(name, value)| request.arguments.get(name) == Some(value))
+    }
+
+    fn response(&self) -> McpToolCallResponse {
+        let mut content: Vec<McpContent> = self
+            .text
+            .iter()
+            .map(|text| McpContent::Text { text: text.clone() })
+            .collect();
+        content.extend(self.content.iter().cloned());
+        McpToolCallResponse {
+            content,
+            is_error: self.is_error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixturePrompt {
+    #[serde(flatten)]
+    pub prompt: McpPrompt,
+    #[serde(default)]
+    pub messages: Vec<McpPromptMessage>,
+}
+
+impl McpFixture {
+    /// Reads a fixture file: JSON for `.json` files, YAML otherwise
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::config(format!("Failed to read fixture {}: {e}", path.display()))
+        })?;
+        let fixture = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text)
+                .map_err(|e| Error::config(format!("Invalid fixture {}: {e}", path.display())))?
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| Error::config(format!("Invalid fixture {}: {e}", path.display())))?
+        };
+        Ok(fixture)
+    }
+}
+
+pub struct FixtureMcpClient {
+    name: String,
+    fixture: McpFixture,
+}
+
+impl FixtureMcpClient {
+    pub fn new(name: impl Into<String>, fixture: McpFixture) -> Self {
+        Self {
+            name: name.into(),
+            fixture,
+        }
+    }
+
+    /// Client for a `fixture` server, loading its `fixture` file
+    pub fn from_config(config: &McpServerConfig) -> Result<Self> {
+        let path = config.fixture.as_ref().ok_or_else(|| {
+            Error::config(format!(
+                "Fixture MCP server '{}' requires 'fixture' field",
+                config.name
+            ))
+        })?;
+        Ok(Self::new(&config.name, McpFixture::from_file(path)?))
+    }
+}
+
+#[async_trait]
+impl McpClient for FixtureMcpClient {
+    async fn initialize(
+        &mut self,
+        _request: McpInitializeRequest,
+    ) -> Result<McpInitializeResponse> {
+        Ok(McpInitializeResponse {
+            capabilities: McpServerCapabilities {
+                tools: (!self.fixture.tools.is_empty()).then_some(McpToolsCapability {
+                    list_changed: false,
+                }),
+                prompts: (!self.fixture.prompts.is_empty()).then_some(McpPromptsCapability {
+                    list_changed: false,
+                }),
+                resources: None,
+            },
+            protocol_version: "2024-11-05".to_string(),
+            server_info: Some(McpServerInfo {
+                name: self.name.clone(),
+                version: "fixture".to_string(),
+            }),
+        })
+    }
+
+    async fn list_tools(&self) -> Result<Vec<McpTool>> {
+        Ok(self
+            .fixture
+            .tools
+            .iter()
+            .map(|tool| tool.tool.clone())
+            .collect())
+    }
+
+    async fn call_tool(&self, request: McpToolCallRequest) -> Result<McpToolCallResponse> {
+        debug!("Fixture '{}' answering tool '{}'", self.name, request.name);
+        let tool = self
+            .fixture
+            .tools
+            .iter()
+            .find(|tool| tool.tool.name == request.name)
+            .ok_or_else(|| {
+                Error::mcp(format!(
+                    "Fixture '{}' has no tool '{}'",
+                    self.name, request.name
+                ))
+            })?;
+        tool.calls
+            .iter()
+            .find(|call| call.matches(&request))
+            .map(FixtureCall::response)
+            .ok_or_else(|| {
+                Error::mcp(format!(
+                    "Fixture '{}' has no result for tool '{}' with arguments {}",
+                    self.name,
+                    request.name,
+                    serde_json::to_string(&request.arguments).unwrap_or_default()
+                ))
+            })
+    }
+
+    async fn list_prompts(&self) -> Result<Vec<McpPrompt>> {
+        Ok(self
+            .fixture
+            .prompts
+            .iter()
+            .map(|prompt| prompt.prompt.clone())
+            .collect())
+    }
+
+    async fn get_prompt(&self, request: McpGetPromptRequest) -> Result<McpGetPromptResponse> {
+        let prompt = self
+            .fixture
+            .prompts
+            .iter()
+            .find(|prompt| prompt.prompt.name == request.name)
+            .ok_or_else(|| {
+                Error::mcp(format!(
+                    "Fixture '{}' has no prompt '{}'",
+                    self.name, request.name
+                ))
+            })?;
+        Ok(McpGetPromptResponse {
+            description: prompt.prompt.description.clone(),
+            messages: prompt.messages.clone(),
+        })
+    }
+
+    async fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 mod client;
+mod fixture;
 
 pub use client::{
     McpClient, McpClientCapabilities, McpClientType, McpContent, McpGetPromptRequest,
-    McpGetPromptResponse, McpInitializeRequest, McpInitializeResponse, McpPrompt,
+    McpGetPromptResponse, McpInitializeRequest, McpInitializeResponse, McpProgress, McpPrompt,
     McpPromptArgument, McpPromptMessage, McpPromptsCapability, McpResourceContent,
     McpRootsCapability, McpServerCapabilities, McpServerInfo, McpTool, McpToolCallRequest,
     McpToolCallResponse, McpToolsCapability, create_mcp_client,
 };
+pub use fixture::{FixtureCall, FixtureMcpClient, FixturePrompt, FixtureTool, McpFixture};
@@ -1,27 +1,115 @@
 use crate::{
     Error, Result,
     config::McpServerConfig,
-    mcp::{McpContent, McpToolCallRequest, McpToolCallResponse},
+    mcp::{McpContent, McpProgress, McpToolCallRequest, McpToolCallResponse},
 };
 use async_trait::async_trait;
-use reqwest::header::HeaderMap;
 use rmcp::{
-    RoleClient,
-    model::{CallToolRequestParam, ClientCapabilities, ClientInfo, Implementation},
-    service::{RunningService, ServiceExt},
-    transport::{
-        ConfigureCommandExt, SseClientTransport, StreamableHttpClientTransport, TokioChildProcess,
-        sse_client::SseClientConfig, streamable_http_client::StreamableHttpClientTransportConfig,
+    ClientHandler, RoleClient,
+    model::{
+        CallToolRequest, CallToolRequestParam, CancelledNotificationParam, ClientCapabilities,
+        ClientInfo, ClientRequest, Implementation, ProgressNotificationParam, ProgressToken,
+        RawContent, RequestId, ResourceContents, ServerResult,
     },
+    service::{NotificationContext, Peer, PeerRequestOptions, RunningService},
 };
-use tokio::process::Command;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, info, warn};
 
+/// Windows only finds `.exe` files for a bare command name, so shims like `npx` or
+/// `uvx` (`.cmd` files) are started through `cmd /C`, without a console window
+#[cfg(all(windows, feature = "mcp-stdio"))]
+fn stdio_command(command: &str) -> tokio::process::Command {
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut cmd = tokio::process::Command::new("cmd");
+    cmd.arg("/C").arg(command).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+#[cfg(all(not(windows), feature = "mcp-stdio"))]
+fn stdio_command(command: &str) -> tokio::process::Command {
+    tokio::process::Command::new(command)
+}
+
+/// Error for MCP servers using a transport this build was compiled without
+#[cfg(not(all(feature = "mcp-stdio", feature = "mcp-sse", feature = "mcp-http")))]
+fn transport_disabled(transport: &str, feature: &str) -> Error {
+    Error::config(format!(
+        "MCP {transport} transport needs jarvis built with the `{feature}` feature"
+    ))
+}
+
+/// Where the progress of running tool calls goes, by the progress token of their request
+type ProgressSenders = Arc<Mutex<HashMap<ProgressToken, UnboundedSender<McpProgress>>>>;
+
+/// Client side of the rmcp connection, forwarding progress notifications to the tool
+/// call they belong to
+struct McpClientHandler {
+    info: ClientInfo,
+    progress: ProgressSenders,
+}
+
+impl ClientHandler for McpClientHandler {
+    fn get_info(&self) -> ClientInfo {
+        self.info.clone()
+    }
+
+    async fn on_progress(
+        &self,
+        params: ProgressNotificationParam,
+        _context: NotificationContext<RoleClient>,
+    ) {
+        let progress = self.progress.lock().unwrap();
+        if let Some(sender) = progress.get(&params.progress_token) {
+            let _ = sender.send(McpProgress {
+                progress: params.progress,
+                total: params.total,
+                message: params.message,
+            });
+        }
+    }
+}
+
+/// A tool call awaiting its response. Dropping it stops forwarding the call's progress
+/// and, unless the response arrived, tells the server to cancel the call.
+struct PendingCall {
+    peer: Peer<RoleClient>,
+    id: RequestId,
+    token: ProgressToken,
+    progress: ProgressSenders,
+    answered: bool,
+}
+
+impl Drop for PendingCall {
+    fn drop(&mut self) {
+        self.progress.lock().unwrap().remove(&self.token);
+        if self.answered {
+            return;
+        }
+        let peer = self.peer.clone();
+        let request_id = self.id.clone();
+        tokio::spawn(async move {
+            let cancelled = CancelledNotificationParam {
+                request_id,
+                reason: Some("cancelled by the client".to_string()),
+            };
+            if let Err(e) = peer.notify_cancelled(cancelled).await {
+                debug!("Failed to cancel tool call: {}", e);
+            }
+        });
+    }
+}
+
 /// Wrapper around rmcp client to provide compatibility with our existing MCP interface
 pub struct RmcpClient {
     name: String,
     config: McpServerConfig,
-    peer: Option<RunningService<RoleClient, rmcp::model::InitializeRequestParam>>,
+    peer: Option<RunningService<RoleClient, McpClientHandler>>,
+    progress: ProgressSenders,
 }
 
 impl RmcpClient {
@@ -32,6 +120,7 @@ impl RmcpClient {
             name: config.name.clone(),
             config,
             peer: None,
+            progress: Arc::default(),
         };
 
         // Initialize the rmcp service
@@ -40,6 +129,25 @@ impl RmcpClient {
         Ok(client)
     }
 
+    #[cfg_attr(
+        not(any(feature = "mcp-stdio", feature = "mcp-sse", feature = "mcp-http")),
+        allow(dead_code)
+    )]
+    fn handler(&self) -> McpClientHandler {
+        // Create client info for MCP protocol compliance
+        McpClientHandler {
+            info: ClientInfo {
+                protocol_version: Default::default(),
+                capabilities: ClientCapabilities::default(),
+                client_info: Implementation {
+                    name: "jarvis-rust".to_string(),
+                    version: "0.1.0".to_string(),
+                },
+            },
+            progress: self.progress.clone(),
+        }
+    }
+
     async fn initialize_service(&mut self) -> Result<()> {
         debug!("Initializing rmcp service for: {}", self.name);
 
@@ -48,17 +156,31 @@ impl RmcpClient {
             crate::config::McpClientType::Sse => self.initialize_sse_service().await,
             crate::config::McpClientType::StreamableHttp => self.initialize_http_service().await,
             crate::config::McpClientType::Http => self.initialize_http_service().await,
+            crate::config::McpClientType::Fixture => Err(Error::config(
+                "Fixture MCP servers are served by FixtureMcpClient".to_string(),
+            )),
         }
     }
 
+    #[cfg(not(feature = "mcp-stdio"))]
     async fn initialize_stdio_service(&mut self) -> Result<()> {
+        Err(transport_disabled("stdio", "mcp-stdio"))
+    }
+
+    #[cfg(feature = "mcp-stdio")]
+    async fn initialize_stdio_service(&mut self) -> Result<()> {
+        use rmcp::{
+            ServiceExt,
+            transport::{ConfigureCommandExt, TokioChildProcess},
+        };
+
         let command = self.config.command.as_ref().ok_or_else(|| {
             Error::config("Stdio MCP client requires 'command' field".to_string())
         })?;
 
         debug!("Creating stdio process for command: {}", command);
 
-        let mut cmd = Command::new(command);
+        let mut cmd = stdio_command(command);
 
         // Add arguments if provided
         for arg in &self.config.args {
@@ -73,17 +195,8 @@ impl RmcpClient {
         // Create the rmcp service using the pattern from the example
         let transport = TokioChildProcess::new(cmd.configure(|_| {}))?;
 
-        // Create client info for MCP protocol compliance
-        let client_info = ClientInfo {
-            protocol_version: Default::default(),
-            capabilities: ClientCapabilities::default(),
-            client_info: Implementation {
-                name: "jarvis-rust".to_string(),
-                version: "0.1.0".to_string(),
-            },
-        };
-
-        let peer = client_info
+        let peer = self
+            .handler()
             .serve(transport)
             .await
             .map_err(|e| Error::mcp(format!("Failed to create rmcp service: {e}")))?;
@@ -96,7 +209,18 @@ impl RmcpClient {
         Ok(())
     }
 
+    #[cfg(not(feature = "mcp-sse"))]
+    async fn initialize_sse_service(&mut self) -> Result<()> {
+        Err(transport_disabled("SSE", "mcp-sse"))
+    }
+
+    #[cfg(feature = "mcp-sse")]
     async fn initialize_sse_service(&mut self) -> Result<()> {
+        use rmcp::{
+            ServiceExt,
+            transport::{SseClientTransport, sse_client::SseClientConfig},
+        };
+
         let url = self
             .config
             .url
@@ -112,7 +236,7 @@ impl RmcpClient {
                 .map_err(|e| Error::mcp(format!("Failed to create SSE transport: {e}")))?
         } else {
             // Custom client with headers
-            let mut headers = HeaderMap::new();
+            let mut headers = reqwest::header::HeaderMap::new();
             for (key, value) in &self.config.headers {
                 let header_name: reqwest::header::HeaderName = key
                     .parse()
@@ -139,17 +263,8 @@ impl RmcpClient {
             .map_err(|e| Error::mcp(format!("Failed to create SSE transport: {e}")))?
         };
 
-        // Create client info for MCP protocol compliance
-        let client_info = ClientInfo {
-            protocol_version: Default::default(),
-            capabilities: ClientCapabilities::default(),
-            client_info: Implementation {
-                name: "jarvis-rust".to_string(),
-                version: "0.1.0".to_string(),
-            },
-        };
-
-        let peer = client_info
+        let peer = self
+            .handler()
             .serve(transport)
             .await
             .map_err(|e| Error::mcp(format!("Failed to serve SSE rmcp service: {e}")))?;
@@ -162,7 +277,21 @@ impl RmcpClient {
         Ok(())
     }
 
+    #[cfg(not(feature = "mcp-http"))]
     async fn initialize_http_service(&mut self) -> Result<()> {
+        Err(transport_disabled("HTTP", "mcp-http"))
+    }
+
+    #[cfg(feature = "mcp-http")]
+    async fn initialize_http_service(&mut self) -> Result<()> {
+        use rmcp::{
+            ServiceExt,
+            transport::{
+                StreamableHttpClientTransport,
+                streamable_http_client::StreamableHttpClientTransportConfig,
+            },
+        };
+
         let url = self
             .config
             .url
@@ -172,7 +301,7 @@ impl RmcpClient {
         debug!("Creating HTTP connection to: {}", url);
 
         // Create HTTP client with headers (same pattern as SSE transport)
-        let mut headers = HeaderMap::new();
+        let mut headers = reqwest::header::HeaderMap::new();
         for (key, value) in &self.config.headers {
             let header_name: reqwest::header::HeaderName = key
                 .parse()
@@ -194,17 +323,8 @@ impl RmcpClient {
             StreamableHttpClientTransportConfig::with_uri(url.clone()),
         );
 
-        // Create client info for MCP protocol compliance
-        let client_info = ClientInfo {
-            protocol_version: Default::default(),
-            capabilities: ClientCapabilities::default(),
-            client_info: Implementation {
-                name: "jarvis-rust".to_string(),
-                version: "0.1.0".to_string(),
-            },
-        };
-
-        let peer = client_info
+        let peer = self
+            .handler()
             .serve(transport)
             .await
             .map_err(|e| Error::mcp(format!("Failed to serve HTTP rmcp service: {e}")))?;
@@ -266,6 +386,7 @@ impl crate::mcp::McpClient for RmcpClient {
                                 .map(|d| d.to_string())
                                 .unwrap_or_default(),
                             input_schema: serde_json::Value::Object((*tool.input_schema).clone()),
+                            output_schema: None,
                         })
                         .collect();
 
@@ -283,107 +404,86 @@ impl crate::mcp::McpClient for RmcpClient {
     }
 
     async fn call_tool(&self, request: McpToolCallRequest) -> Result<McpToolCallResponse> {
-        if let Some(ref peer) = self.peer {
-            debug!(
-                "Calling tool: {} with rmcp peer: {}",
-                request.name, self.name
-            );
+        let (progress, _) = tokio::sync::mpsc::unbounded_channel();
+        self.call_tool_with_progress(request, progress).await
+    }
 
-            // Convert arguments to the format expected by rmcp
-            let arguments = if request.arguments.is_empty() {
-                None
-            } else {
-                Some(serde_json::Map::from_iter(
-                    request
-                        .arguments
-                        .iter()
-                        .map(|(k, v)| (k.clone(), v.clone())),
-                ))
-            };
+    async fn call_tool_with_progress(
+        &self,
+        request: McpToolCallRequest,
+        progress: UnboundedSender<McpProgress>,
+    ) -> Result<McpToolCallResponse> {
+        let Some(ref peer) = self.peer else {
+            warn!("rmcp peer not initialized for: {}", self.name);
+            return Err(Error::mcp("rmcp peer not initialized".to_string()));
+        };
+        debug!(
+            "Calling tool: {} with rmcp peer: {}",
+            request.name, self.name
+        );
+
+        // Convert arguments to the format expected by rmcp
+        let arguments = if request.arguments.is_empty() {
+            None
+        } else {
+            Some(serde_json::Map::from_iter(
+                request
+                    .arguments
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.clone())),
+            ))
+        };
 
-            let rmcp_request = CallToolRequestParam {
+        let rmcp_request = ClientRequest::CallToolRequest(CallToolRequest {
+            method: Default::default(),
+            params: CallToolRequestParam {
                 name: request.name.clone().into(),
                 arguments,
-            };
+            },
+            extensions: Default::default(),
+        });
 
-            match peer.call_tool(rmcp_request).await {
-                Ok(result) => {
-                    debug!("Tool {} called successfully via rmcp", request.name);
+        // Every request carries a progress token the server reports progress under
+        let handle = peer
+            .send_cancellable_request(rmcp_request, PeerRequestOptions::no_options())
+            .await
+            .map_err(|e| Error::mcp(format!("Tool call failed: {e}")))?;
+        self.progress
+            .lock()
+            .unwrap()
+            .insert(handle.progress_token.clone(), progress);
+        let mut pending = PendingCall {
+            peer: handle.peer.clone(),
+            id: handle.id.clone(),
+            token: handle.progress_token.clone(),
+            progress: self.progress.clone(),
+            answered: false,
+        };
+        let result = handle.await_response().await;
+        pending.answered = true;
 
-                    // Convert rmcp result to our format
-                    let content = result
-                        .content
-                        .into_iter()
-                        .map(|content_item| {
-                            // Handle different content types properly based on rmcp types
-                            // Note: The rmcp crate uses a generic Content type that we need to match on
-                            // For now, we extract the text representation until we can determine the exact type structure
-                            match serde_json::to_value(&content_item) {
-                                Ok(value) => {
-                                    // Try to extract as text content first
-                                    if let Some(text) = value.get("text").and_then(|v| v.as_str()) {
-                                        McpContent::Text {
-                                            text: text.to_string(),
-                                        }
-                                    } else if value.get("data").is_some()
-                                        && value.get("mimeType").is_some()
-                                    {
-                                        // Handle image content
-                                        let data = value["data"].as_str().unwrap_or("").to_string();
-                                        let mime_type =
-                                            value["mimeType"].as_str().unwrap_or("").to_string();
-                                        McpContent::Image { data, mime_type }
-                                    } else if value.get("uri").is_some() {
-                                        // Handle resource content
-                                        let uri = value["uri"].as_str().unwrap_or("").to_string();
-                                        let text = value
-                                            .get("text")
-                                            .and_then(|v| v.as_str())
-                                            .map(|s| s.to_string());
-                                        let blob = value
-                                            .get("blob")
-                                            .and_then(|v| v.as_str())
-                                            .map(|s| s.to_string());
-                                        McpContent::Resource {
-                                            resource: crate::mcp::McpResourceContent {
-                                                uri,
-                                                text,
-                                                blob,
-                                            },
-                                        }
-                                    } else {
-                                        // Fallback to text representation
-                                        McpContent::Text {
-                                            text: value.to_string(),
-                                        }
-                                    }
-                                }
-                                Err(_) => {
-                                    // Final fallback to debug representation
-                                    McpContent::Text {
-                                        text: format!("{content_item:?}"),
-                                    }
-                                }
-                            }
-                        })
-                        .collect();
+        match result {
+            Ok(ServerResult::CallToolResult(result)) => {
+                debug!("Tool {} called successfully via rmcp", request.name);
 
-                    Ok(McpToolCallResponse {
-                        content,
-                        is_error: result.is_error.unwrap_or(false),
-                    })
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to call tool {} via rmcp peer {}: {}",
-                        request.name, self.name, e
-                    );
-                    Err(Error::mcp(format!("Tool call failed: {e}")))
-                }
+                // Convert rmcp result to our format
+                let content = result.content.into_iter().map(convert_content).collect();
+
+                Ok(McpToolCallResponse {
+                    content,
+                    is_error: result.is_error.unwrap_or(false),
+                })
+            }
+            Ok(_) => Err(Error::mcp(
+                "Tool call failed: unexpected response".to_string(),
+            )),
+            Err(e) => {
+                warn!(
+                    "Failed to call tool {} via rmcp peer {}: {}",
+                    request.name, self.name, e
+                );
+                Err(Error::mcp(format!("Tool call failed: {e}")))
             }
-        } else {
-            warn!("rmcp peer not initialized for: {}", self.name);
-            Err(Error::mcp("rmcp peer not initialized".to_string()))
         }
     }
 
@@ -521,6 +621,36 @@ impl crate::mcp::McpClient for RmcpClient {
     }
 }
 
+/// Converts rmcp content into our content type, preserving text (including JSON) verbatim
+fn convert_content(content: rmcp::model::Content) -> McpContent {
+    match content.raw {
+        RawContent::Text(text) => McpContent::Text { text: text.text },
+        RawContent::Image(image) => McpContent::Image {
+            data: image.data,
+            mime_type: image.mime_type,
+        },
+        RawContent::Resource(embedded) => match embedded.resource {
+            ResourceContents::TextResourceContents { uri, text, .. } => McpContent::Resource {
+                resource: crate::mcp::McpResourceContent {
+                    uri,
+                    text: Some(text),
+                    blob: None,
+                },
+            },
+            ResourceContents::BlobResourceContents { uri, blob, .. } => McpContent::Resource {
+                resource: crate::mcp::McpResourceContent {
+                    uri,
+                    text: None,
+                    blob: Some(blob),
+                },
+            },
+        },
+        RawContent::Audio(audio) => McpContent::Text {
+            text: format!("[audio content: {}]", audio.raw.mime_type),
+        },
+    }
+}
+
 /// Factory function to create rmcp-based MCP client
 pub async fn create_rmcp_client(config: McpServerConfig) -> Result<Box<dyn crate::mcp::McpClient>> {
     let client = RmcpClient::new(config).await?;
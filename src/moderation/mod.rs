@@ -0,0 +1,212 @@
+//! Safety moderation of user input and final replies (OpenAI moderation endpoint or a
+//! local keyword classifier).
+
+use crate::{
+    Error, Result,
+    config::{ModerationAction, ModerationConfig, ModerationProviderKind},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+/// Classifies text into the safety categories it falls under
+#[async_trait]
+pub trait ModerationProvider: Send + Sync {
+    /// Returns the categories `text` is flagged for, empty when it is fine
+    async fn classify(&self, text: &str) -> Result<Vec<String>>;
+}
+
+pub fn create_moderation_provider(
+    config: &ModerationConfig,
+) -> Result<Option<Arc<dyn ModerationProvider>>> {
+    let provider: Arc<dyn ModerationProvider> = match config.provider {
+        ModerationProviderKind::None => return Ok(None),
+        ModerationProviderKind::Openai => Arc::new(OpenAiModeration::new(
+            config.api_key.clone().ok_or_else(|| {
+                Error::config("moderation.api_key is required for the openai provider")
+            })?,
+            config.base_url.clone(),
+            config.model.clone(),
+        )),
+        ModerationProviderKind::Keywords => {
+            if config.keywords.is_empty() {
+                return Err(Error::config(
+                    "moderation.keywords is required for the keywords provider",
+                ));
+            }
+            Arc::new(KeywordModeration::new(config.keywords.clone()))
+        }
+    };
+    Ok(Some(provider))
+}
+
+/// OpenAI's moderation endpoint (https://platform.openai.com/docs/api-reference/moderations)
+pub struct OpenAiModeration {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Deserialize)]
+struct OpenAiModerationResponse {
+    results: Vec<OpenAiModerationResult>,
+}
+
+#[derive(Deserialize)]
+struct OpenAiModerationResult {
+    categories: HashMap<String, bool>,
+}
+
+impl OpenAiModeration {
+    pub fn new(api_key: String, base_url: Option<String>, model: String) -> Self {
+        let base_url = base_url.unwrap_or_else(|| "https://api.openai.com/v1".to_string());
+        Self {
+            client: reqwest::Client::new(),
+            url: format!("{}/moderations", base_url.trim_end_matches('/')),
+            api_key,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl ModerationProvider for OpenAiModeration {
+    async fn classify(&self, text: &str) -> Result<Vec<String>> {
+        let response: OpenAiModerationResponse = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.api_key)
+            .json(&json!({"model": self.model, "input": text}))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let mut categories: Vec<String> = response
+            .results
+            .into_iter()
+            .flat_map(|result| result.categories)
+            .filter_map(|(category, flagged)| flagged.then_some(category))
+            .collect();
+        categories.sort();
+        categories.dedup();
+        Ok(categories)
+    }
+}
+
+/// Flags a category when the text contains one of its words or phrases, ignoring case
+pub struct KeywordModeration {
+    keywords: HashMap<String, Vec<String>>,
+}
+
+impl KeywordModeration {
+    pub fn new(keywords: HashMap<String, Vec<String>>) -> Self {
+        let keywords = keywords
+            .into_iter()
+            .map(|(category, words)| {
+                let words = words.into_iter().map(|word| word.to_lowercase()).collect();
+                (category, words)
+            })
+            .collect();
+        Self { keywords }
+    }
+}
+
+#[async_trait]
+impl ModerationProvider for KeywordModeration {
+    async fn classify(&self, text: &str) -> Result<Vec<String>> {
+        let text = text.to_lowercase();
+        let mut categories: Vec<String> = self
+            .keywords
+            .iter()
+            .filter(|(_, words)| words.iter().any(|word| contains_word(&text, word)))
+            .map(|(category, _)| category.clone())
+            .collect();
+        categories.sort();
+        Ok(categories)
+    }
+}
+
+/// Whether `phrase` occurs in `text` on word boundaries
+fn contains_word(text: &str, phrase: &str) -> bool {
+    if phrase.is_empty() {
+        return false;
+    }
+    text.match_indices(phrase).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + phrase.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Outcome of checking a text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModerationVerdict {
+    Allow,
+    /// Flagged for categories whose action is `block`
+    Block {
+        categories: Vec<String>,
+    },
+}
+
+/// Applies the configured actions to what the provider flags
+pub struct Moderator {
+    provider: Arc<dyn ModerationProvider>,
+    actions: HashMap<String, ModerationAction>,
+    default_action: ModerationAction,
+    pub check_input: bool,
+    pub check_output: bool,
+    pub blocked_reply: String,
+}
+
+impl Moderator {
+    pub fn new(provider: Arc<dyn ModerationProvider>, config: &ModerationConfig) -> Self {
+        Self {
+            provider,
+            actions: config.actions.clone(),
+            default_action: config.default_action,
+            check_input: config.check_input,
+            check_output: config.check_output,
+            blocked_reply: config.blocked_reply.clone(),
+        }
+    }
+
+    pub fn from_config(config: &ModerationConfig) -> Result<Option<Self>> {
+        Ok(create_moderation_provider(config)?.map(|provider| Self::new(provider, config)))
+    }
+
+    /// Classifies `text` and applies each flagged category's action. Text the provider
+    /// fails to classify is let through.
+    pub async fn check(&self, text: &str) -> ModerationVerdict {
+        let flagged = match self.provider.classify(text).await {
+            Ok(flagged) => flagged,
+            Err(e) => {
+                warn!("Moderation failed, letting the text through: {}", e);
+                return ModerationVerdict::Allow;
+            }
+        };
+        let mut blocked = Vec::new();
+        for category in flagged {
+            match self
+                .actions
+                .get(&category)
+                .copied()
+                .unwrap_or(self.default_action)
+            {
+                ModerationAction::Block => blocked.push(category),
+                ModerationAction::Warn => warn!("Text flagged for '{}'", category),
+                ModerationAction::Allow => {}
+            }
+        }
+        if blocked.is_empty() {
+            ModerationVerdict::Allow
+        } else {
+            ModerationVerdict::Block {
+                categories: blocked,
+            }
+        }
+    }
+}
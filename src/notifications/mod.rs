@@ -0,0 +1,63 @@
+//! Push notifications delivering agent output to a phone (ntfy, Pushover, Gotify).
+
+#[cfg(feature = "notifications")]
+mod providers;
+
+#[cfg(feature = "notifications")]
+pub use providers::{GotifySink, NtfySink, PushoverSink, create_notification_sink};
+
+use crate::{
+    Result,
+    events::{EventSubscriber, SessionEvent, SessionEventKind},
+};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::warn;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub title: String,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Destination for push notifications
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<()>;
+}
+
+/// Notifies of each failed run, subscribed to the session events
+pub struct RunFailureNotifier {
+    sink: Arc<dyn NotificationSink>,
+}
+
+impl RunFailureNotifier {
+    pub fn new(sink: Arc<dyn NotificationSink>) -> Self {
+        Self { sink }
+    }
+}
+
+#[async_trait]
+impl EventSubscriber for RunFailureNotifier {
+    async fn on_event(&self, event: &SessionEvent) {
+        let SessionEventKind::RunFailed { error } = &event.kind else {
+            return;
+        };
+        let notification = Notification::new(
+            format!("Run failed in session {}", event.session_id),
+            error.clone(),
+        );
+        if let Err(e) = self.sink.send(&notification).await {
+            warn!("Failed to send run failure notification: {}", e);
+        }
+    }
+}
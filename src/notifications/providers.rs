@@ -0,0 +1,194 @@
+//! The ntfy, Pushover and Gotify notification sinks
+
+use super::{Notification, NotificationSink};
+use crate::{
+    Error, Result,
+    config::{NotificationProvider, NotificationsConfig},
+    formatting,
+};
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::Arc;
+
+pub fn create_notification_sink(
+    config: &NotificationsConfig,
+) -> Result<Option<Arc<dyn NotificationSink>>> {
+    let required = |value: &Option<String>, field: &str| {
+        value.clone().ok_or_else(|| {
+            Error::config(format!(
+                "notifications.{field} is required for this provider"
+            ))
+        })
+    };
+    let sink: Arc<dyn NotificationSink> = match config.provider {
+        NotificationProvider::None => return Ok(None),
+        NotificationProvider::Ntfy => Arc::new(NtfySink::new(
+            config.url.clone(),
+            required(&config.topic, "topic")?,
+            config.token.clone(),
+            config.priority,
+        )),
+        NotificationProvider::Pushover => Arc::new(PushoverSink::new(
+            required(&config.token, "token")?,
+            required(&config.user_key, "user_key")?,
+            config.url.clone(),
+            config.priority,
+        )),
+        NotificationProvider::Gotify => Arc::new(GotifySink::new(
+            required(&config.url, "url")?,
+            required(&config.token, "token")?,
+            config.priority,
+        )),
+    };
+    Ok(Some(sink))
+}
+
+fn trim_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// ntfy topic, either on ntfy.sh or a self-hosted server
+pub struct NtfySink {
+    client: reqwest::Client,
+    base_url: String,
+    topic: String,
+    token: Option<String>,
+    priority: Option<i32>,
+}
+
+impl NtfySink {
+    pub fn new(
+        base_url: Option<String>,
+        topic: String,
+        token: Option<String>,
+        priority: Option<i32>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: trim_url(base_url.unwrap_or_else(|| "https://ntfy.sh".to_string())),
+            topic,
+            token,
+            priority,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for NtfySink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        // ntfy's JSON publishing keeps non-ASCII titles intact, unlike its headers
+        let mut body = json!({
+            "topic": self.topic,
+            "title": notification.title,
+            "message": notification.message,
+        });
+        if let Some(priority) = self.priority {
+            body["priority"] = json!(priority);
+        }
+        let mut request = self.client.post(&self.base_url).json(&body);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Pushover (https://pushover.net/api)
+pub struct PushoverSink {
+    client: reqwest::Client,
+    api_token: String,
+    user_key: String,
+    base_url: String,
+    priority: Option<i32>,
+}
+
+impl PushoverSink {
+    pub fn new(
+        api_token: String,
+        user_key: String,
+        base_url: Option<String>,
+        priority: Option<i32>,
+    ) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_token,
+            user_key,
+            base_url: trim_url(base_url.unwrap_or_else(|| "https://api.pushover.net".to_string())),
+            priority,
+        }
+    }
+}
+
+/// Longest message Pushover accepts, in characters
+const PUSHOVER_MAX_MESSAGE: usize = 1024;
+
+#[async_trait]
+impl NotificationSink for PushoverSink {
+    /// Longer messages are sent as several notifications, numbered in their titles
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let chunks = formatting::chunk(&notification.message, PUSHOVER_MAX_MESSAGE);
+        for (index, message) in chunks.iter().enumerate() {
+            let title = match chunks.len() {
+                1 => notification.title.clone(),
+                count => format!("{} ({}/{count})", notification.title, index + 1),
+            };
+            let mut body = json!({
+                "token": self.api_token,
+                "user": self.user_key,
+                "title": title,
+                "message": message,
+            });
+            if let Some(priority) = self.priority {
+                body["priority"] = json!(priority);
+            }
+            self.client
+                .post(format!("{}/1/messages.json", self.base_url))
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?;
+        }
+        Ok(())
+    }
+}
+
+/// Self-hosted Gotify server
+pub struct GotifySink {
+    client: reqwest::Client,
+    base_url: String,
+    app_token: String,
+    priority: Option<i32>,
+}
+
+impl GotifySink {
+    pub fn new(base_url: String, app_token: String, priority: Option<i32>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: trim_url(base_url),
+            app_token,
+            priority,
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for GotifySink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut body = json!({
+            "title": notification.title,
+            "message": notification.message,
+        });
+        if let Some(priority) = self.priority {
+            body["priority"] = json!(priority);
+        }
+        self.client
+            .post(format!("{}/message", self.base_url))
+            .header("X-Gotify-Key", &self.app_token)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,188 @@
+//! Durable per-user preferences, injected into the system prompt of the user's sessions.
+
+use crate::{
+    Result,
+    agent::{AgentHook, HookContext},
+    config::DatabaseConfig,
+    db,
+    llm::{ChatCompletionRequest, ChatMessage},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use libsql::Connection;
+use std::{collections::BTreeMap, sync::Arc};
+use tracing::info;
+
+/// User that sessions belong to unless a request names one
+pub const DEFAULT_USER: &str = "default";
+
+/// Normalizes preference keys so `Preferred Units` and `preferred_units` are the same
+pub fn normalize_key(key: &str) -> String {
+    key.split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_")
+        .to_lowercase()
+}
+
+pub struct ProfileStore {
+    conn: Connection,
+}
+
+impl ProfileStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS profiles (
+                user_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at DATETIME NOT NULL,
+                PRIMARY KEY (user_id, key)
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_users (
+                session_id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Profile store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    pub async fn set(&self, user_id: &str, key: &str, value: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO profiles (user_id, key, value, updated_at) VALUES (?, ?, ?, ?) \
+                 ON CONFLICT(user_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                libsql::params![user_id, normalize_key(key), value, Utc::now().to_rfc3339()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Removes a preference, returning whether it existed
+    pub async fn remove(&self, user_id: &str, key: &str) -> Result<bool> {
+        let removed = self
+            .conn
+            .execute(
+                "DELETE FROM profiles WHERE user_id = ? AND key = ?",
+                libsql::params![user_id, normalize_key(key)],
+            )
+            .await?;
+        Ok(removed > 0)
+    }
+
+    pub async fn preferences(&self, user_id: &str) -> Result<BTreeMap<String, String>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT key, value FROM profiles WHERE user_id = ?",
+                libsql::params![user_id],
+            )
+            .await?;
+        let mut preferences = BTreeMap::new();
+        while let Some(row) = rows.next().await? {
+            preferences.insert(row.get(0)?, row.get(1)?);
+        }
+        Ok(preferences)
+    }
+
+    /// Records which user a session belongs to
+    pub async fn link_session(&self, session_id: &str, user_id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO session_users (session_id, user_id) VALUES (?, ?) \
+                 ON CONFLICT(session_id) DO UPDATE SET user_id = excluded.user_id",
+                libsql::params![session_id, user_id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// The session's user, or [`DEFAULT_USER`] when it was never linked
+    pub async fn user_for_session(&self, session_id: &str) -> Result<String> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT user_id FROM session_users WHERE session_id = ?",
+                libsql::params![session_id],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get(0)?),
+            None => Ok(DEFAULT_USER.to_string()),
+        }
+    }
+}
+
+/// Renders preferences as a system prompt section
+pub fn render_preferences(preferences: &BTreeMap<String, String>) -> Option<String> {
+    if preferences.is_empty() {
+        return None;
+    }
+    let lines = preferences
+        .iter()
+        .map(|(key, value)| format!("- {key}: {value}"))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!(
+        "Known preferences of the user you are talking to (respect them):\n{lines}"
+    ))
+}
+
+/// Appends the session user's preferences to the system prompt of every LLM call
+pub struct ProfileHook {
+    store: Arc<ProfileStore>,
+}
+
+impl ProfileHook {
+    pub fn new(store: Arc<ProfileStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AgentHook for ProfileHook {
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        let user_id = self.store.user_for_session(&ctx.session_id).await?;
+        let Some(section) = render_preferences(&self.store.preferences(&user_id).await?) else {
+            return Ok(());
+        };
+
+        match request.messages.first_mut() {
+            Some(system) if system.role == "system" => {
+                system.content.push_str(&format!("\n\n{section}"));
+            }
+            _ => request.messages.insert(
+                0,
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: section.into(),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                },
+            ),
+        }
+        Ok(())
+    }
+}
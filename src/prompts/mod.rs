@@ -0,0 +1,167 @@
+//! Revisions of the base system prompt set through `PUT /admin/system_prompt`, kept so
+//! the latest survives restarts and earlier ones can be reverted to. The latest revision
+//! replaces `llm.system_prompt`.
+
+use crate::{Result, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::{Connection, Row};
+use serde::Serialize;
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptRevision {
+    /// 1 for the first prompt set, bumped by every change
+    pub revision: i64,
+    pub prompt: String,
+    /// Name of the API key that set it; `None` while no keys were configured
+    pub set_by: Option<String>,
+    /// Revision this one restored, when it was a revert
+    pub reverted_from: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct SystemPromptStore {
+    conn: Connection,
+}
+
+impl SystemPromptStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS system_prompts (
+                revision INTEGER PRIMARY KEY AUTOINCREMENT,
+                prompt TEXT NOT NULL,
+                set_by TEXT,
+                reverted_from INTEGER,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("System prompt store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// The prompt in use; `None` until one is set, meaning the configured one
+    pub async fn current(&self) -> Result<Option<PromptRevision>> {
+        self.query_one(
+            "SELECT revision, prompt, set_by, reverted_from, created_at FROM system_prompts \
+             ORDER BY revision DESC LIMIT 1",
+            libsql::params![],
+        )
+        .await
+    }
+
+    pub async fn revision(&self, revision: i64) -> Result<Option<PromptRevision>> {
+        self.query_one(
+            "SELECT revision, prompt, set_by, reverted_from, created_at FROM system_prompts \
+             WHERE revision = ?",
+            libsql::params![revision],
+        )
+        .await
+    }
+
+    /// Every revision, newest first
+    pub async fn history(&self) -> Result<Vec<PromptRevision>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT revision, prompt, set_by, reverted_from, created_at FROM system_prompts \
+                 ORDER BY revision DESC",
+                (),
+            )
+            .await?;
+        let mut revisions = Vec::new();
+        while let Some(row) = rows.next().await? {
+            revisions.push(revision_from_row(&row)?);
+        }
+        Ok(revisions)
+    }
+
+    /// Makes `prompt` the current one as a new revision
+    pub async fn set(&self, prompt: &str, set_by: Option<&str>) -> Result<PromptRevision> {
+        self.insert(prompt, set_by, None).await
+    }
+
+    /// Restores the prompt of `revision`, or of the one before the current when unset, as
+    /// a new revision. `None` when there is no such revision.
+    pub async fn revert(
+        &self,
+        revision: Option<i64>,
+        set_by: Option<&str>,
+    ) -> Result<Option<PromptRevision>> {
+        let target = match revision {
+            Some(revision) => self.revision(revision).await?,
+            None => {
+                self.query_one(
+                    "SELECT revision, prompt, set_by, reverted_from, created_at \
+                     FROM system_prompts ORDER BY revision DESC LIMIT 1 OFFSET 1",
+                    libsql::params![],
+                )
+                .await?
+            }
+        };
+        let Some(target) = target else {
+            return Ok(None);
+        };
+        self.insert(&target.prompt, set_by, Some(target.revision))
+            .await
+            .map(Some)
+    }
+
+    async fn insert(
+        &self,
+        prompt: &str,
+        set_by: Option<&str>,
+        reverted_from: Option<i64>,
+    ) -> Result<PromptRevision> {
+        let created_at = Utc::now();
+        self.conn
+            .execute(
+                "INSERT INTO system_prompts (prompt, set_by, reverted_from, created_at) \
+                 VALUES (?, ?, ?, ?)",
+                libsql::params![prompt, set_by, reverted_from, created_at.to_rfc3339()],
+            )
+            .await?;
+        Ok(PromptRevision {
+            revision: self.conn.last_insert_rowid(),
+            prompt: prompt.to_string(),
+            set_by: set_by.map(str::to_string),
+            reverted_from,
+            created_at,
+        })
+    }
+
+    async fn query_one(
+        &self,
+        sql: &str,
+        params: impl libsql::params::IntoParams,
+    ) -> Result<Option<PromptRevision>> {
+        let mut rows = self.conn.query(sql, params).await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(revision_from_row(&row)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn revision_from_row(row: &Row) -> Result<PromptRevision> {
+    let created_at: String = row.get(4)?;
+    Ok(PromptRevision {
+        revision: row.get(0)?,
+        prompt: row.get(1)?,
+        set_by: row.get(2)?,
+        reverted_from: row.get(3)?,
+        created_at: DateTime::parse_from_rfc3339(&created_at)
+            .map(|at| at.with_timezone(&Utc))
+            .unwrap_or_default(),
+    })
+}
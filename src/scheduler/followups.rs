@@ -0,0 +1,134 @@
+use crate::{Error, Result, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use tracing::info;
+
+/// A continuation the agent scheduled for a session
+#[derive(Debug, Clone, PartialEq)]
+pub struct FollowUp {
+    pub id: i64,
+    pub session_id: String,
+    /// Instruction the agent left for itself, run as the session's next turn
+    pub prompt: String,
+    pub due_at: DateTime<Utc>,
+}
+
+/// Persists pending follow-ups and the webhook each session's replies go to
+pub struct FollowUpStore {
+    conn: Connection,
+}
+
+impl FollowUpStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS followups (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                prompt TEXT NOT NULL,
+                due_at DATETIME NOT NULL,
+                completed_at DATETIME
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_callbacks (
+                session_id TEXT PRIMARY KEY,
+                url TEXT NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Follow-up store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Schedules a follow-up, returning its id
+    pub async fn create(
+        &self,
+        session_id: &str,
+        prompt: &str,
+        due_at: DateTime<Utc>,
+    ) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO followups (session_id, prompt, due_at) VALUES (?, ?, ?)",
+                libsql::params![session_id, prompt, due_at.to_rfc3339()],
+            )
+            .await?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Pending follow-ups due at or before `now`, oldest first
+    pub async fn due(&self, now: DateTime<Utc>) -> Result<Vec<FollowUp>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT id, session_id, prompt, due_at FROM followups \
+                 WHERE completed_at IS NULL AND due_at <= ? ORDER BY due_at, id",
+                libsql::params![now.to_rfc3339()],
+            )
+            .await?;
+
+        let mut followups = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let due_at: String = row.get(3)?;
+            followups.push(FollowUp {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                prompt: row.get(2)?,
+                due_at: DateTime::parse_from_rfc3339(&due_at)
+                    .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))?
+                    .with_timezone(&Utc),
+            });
+        }
+        Ok(followups)
+    }
+
+    pub async fn complete(&self, id: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE followups SET completed_at = ? WHERE id = ?",
+                libsql::params![Utc::now().to_rfc3339(), id],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Remembers where replies for a session should be delivered
+    pub async fn set_callback(&self, session_id: &str, url: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO session_callbacks (session_id, url) VALUES (?, ?) \
+                 ON CONFLICT(session_id) DO UPDATE SET url = excluded.url",
+                libsql::params![session_id, url],
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn callback(&self, session_id: &str) -> Result<Option<String>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT url FROM session_callbacks WHERE session_id = ?",
+                libsql::params![session_id],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Runs configured agent prompts on a schedule, e.g. a morning news summary, and
+//! follow-ups the agent scheduled for itself.
+
+mod followups;
+
+pub use followups::{FollowUp, FollowUpStore};
+
+use crate::{
+    Error, Result,
+    agent::Agent,
+    config::ScheduleConfig,
+    history::HistoryStorage,
+    notifications::{Notification, NotificationSink},
+    tools::datetime::{Recurrence, next_occurrence},
+};
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Mutex, task::JoinHandle};
+use tracing::{error, info, warn};
+
+/// When a job runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Schedule {
+    /// Repeatedly, this long after the previous run
+    Every(Duration),
+    /// Once a day at a wall-clock time in the scheduler's timezone
+    DailyAt(NaiveTime),
+}
+
+impl Schedule {
+    /// Next run strictly after `after`
+    pub fn next_run(&self, after: DateTime<Utc>, tz: Tz) -> Result<DateTime<Utc>> {
+        match self {
+            Schedule::Every(interval) => chrono::Duration::from_std(*interval)
+                .ok()
+                .and_then(|interval| after.checked_add_signed(interval))
+                .ok_or_else(|| Error::config("Schedule interval is out of range")),
+            Schedule::DailyAt(time) => {
+                Ok(
+                    next_occurrence(after.with_timezone(&tz), Recurrence::Daily, *time)?
+                        .with_timezone(&Utc),
+                )
+            }
+        }
+    }
+}
+
+/// Parses intervals such as `90s`, `15m`, `2h` or `1d`
+pub fn parse_interval(value: &str) -> Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| Error::config(format!("Invalid interval '{value}'")))?;
+    let seconds = match unit.trim() {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => {
+            return Err(Error::config(format!(
+                "Invalid interval '{value}', expected a number followed by s, m, h or d"
+            )));
+        }
+    };
+    if amount == 0 {
+        return Err(Error::config(format!(
+            "Interval '{value}' must be positive"
+        )));
+    }
+    Ok(Duration::from_secs(amount * seconds))
+}
+
+/// A prompt run by the scheduler
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledJob {
+    pub name: String,
+    pub prompt: String,
+    pub session_id: String,
+    pub schedule: Schedule,
+    /// Whether results are pushed to the notification sink
+    pub notify: bool,
+}
+
+impl ScheduledJob {
+    pub fn from_config(config: &ScheduleConfig) -> Result<Self> {
+        let schedule = match (&config.every, &config.at) {
+            (Some(every), None) => Schedule::Every(parse_interval(every)?),
+            (None, Some(at)) => {
+                Schedule::DailyAt(NaiveTime::parse_from_str(at.trim(), "%H:%M").map_err(|_| {
+                    Error::config(format!(
+                        "Invalid time '{at}' for schedule '{}', expected HH:MM",
+                        config.name
+                    ))
+                })?)
+            }
+            _ => {
+                return Err(Error::config(format!(
+                    "Schedule '{}' needs exactly one of 'every' or 'at'",
+                    config.name
+                )));
+            }
+        };
+        Ok(Self {
+            name: config.name.clone(),
+            prompt: config.prompt.clone(),
+            session_id: config
+                .session_id
+                .clone()
+                .unwrap_or_else(|| format!("schedule:{}", config.name)),
+            schedule,
+            notify: config.notify,
+        })
+    }
+}
+
+pub struct Scheduler {
+    agent: Arc<Mutex<Agent>>,
+    history: Arc<HistoryStorage>,
+    timezone: Tz,
+    notifier: Option<Arc<dyn NotificationSink>>,
+    client: reqwest::Client,
+}
+
+impl Scheduler {
+    pub fn new(agent: Arc<Mutex<Agent>>, history: Arc<HistoryStorage>, timezone: Tz) -> Self {
+        Self {
+            agent,
+            history,
+            timezone,
+            notifier: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Delivers job results through a notification sink
+    pub fn with_notifier(mut self, notifier: Arc<dyn NotificationSink>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Runs a job's prompt through the agent in the job's session
+    pub async fn run_job(&self, job: &ScheduledJob) -> Result<String> {
+        info!("Running scheduled job '{}'", job.name);
+        let mut agent = self.agent.lock().await;
+        agent
+            .process(&job.session_id, &job.prompt, &self.history)
+            .await
+    }
+
+    /// Runs a job and, if it asks for it, pushes the outcome to the notifier
+    pub async fn run_and_notify(&self, job: &ScheduledJob) -> Result<String> {
+        let result = self.run_job(job).await;
+        if job.notify
+            && let Some(notifier) = &self.notifier
+        {
+            let notification = match &result {
+                Ok(output) => Notification::new(job.name.clone(), output.clone()),
+                Err(e) => Notification::new(format!("{} failed", job.name), e.to_string()),
+            };
+            if let Err(e) = notifier.send(&notification).await {
+                warn!("Failed to notify result of job '{}': {}", job.name, e);
+            }
+        }
+        result
+    }
+
+    /// Starts one task per job, each sleeping until its next run
+    pub fn spawn(self: Arc<Self>, jobs: Vec<ScheduledJob>) -> Vec<JoinHandle<()>> {
+        info!("Scheduling {} jobs", jobs.len());
+        jobs.into_iter()
+            .map(|job| {
+                let scheduler = self.clone();
+                tokio::spawn(async move {
+                    loop {
+                        let now = Utc::now();
+                        let next = match job.schedule.next_run(now, scheduler.timezone) {
+                            Ok(next) => next,
+                            Err(e) => {
+                                error!("Stopping scheduled job '{}': {}", job.name, e);
+                                return;
+                            }
+                        };
+                        info!("Scheduled job '{}' next runs at {}", job.name, next);
+                        tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+                        match scheduler.run_and_notify(&job).await {
+                            Ok(output) => {
+                                info!("Scheduled job '{}' finished: {}", job.name, output)
+                            }
+                            Err(e) => error!("Scheduled job '{}' failed: {}", job.name, e),
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a follow-up as the next turn of its session
+    pub async fn run_followup(&self, followup: &FollowUp) -> Result<String> {
+        info!(
+            "Running follow-up {} for session {}",
+            followup.id, followup.session_id
+        );
+        let prompt = format!(
+            "[Scheduled follow-up] Earlier you scheduled this follow-up: {}\n\
+             Write the message to send to the user now.",
+            followup.prompt
+        );
+        let mut agent = self.agent.lock().await;
+        agent
+            .process(&followup.session_id, &prompt, &self.history)
+            .await
+    }
+
+    /// Sends a follow-up's output to the session's callback webhook, falling back to
+    /// the notification sink
+    pub async fn deliver_followup(
+        &self,
+        followup: &FollowUp,
+        output: &str,
+        callback: Option<&str>,
+    ) -> Result<()> {
+        if let Some(url) = callback {
+            self.client
+                .post(url)
+                .json(&json!({
+                    "session_id": followup.session_id,
+                    "output": output,
+                    "followup_id": followup.id,
+                }))
+                .send()
+                .await?
+                .error_for_status()?;
+            return Ok(());
+        }
+        match &self.notifier {
+            Some(notifier) => notifier.send(&Notification::new("Follow-up", output)).await,
+            None => {
+                warn!(
+                    "No webhook or notifications configured, follow-up {} is only in the session history",
+                    followup.id
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Runs and delivers every follow-up that is due, returning how many ran
+    pub async fn run_due_followups(&self, store: &FollowUpStore) -> Result<usize> {
+        let due = store.due(Utc::now()).await?;
+        for followup in &due {
+            // Completed before running so a failing follow-up is not retried forever
+            store.complete(followup.id).await?;
+            match self.run_followup(followup).await {
+                Ok(output) => {
+                    let callback = store.callback(&followup.session_id).await?;
+                    if let Err(e) = self
+                        .deliver_followup(followup, &output, callback.as_deref())
+                        .await
+                    {
+                        warn!("Failed to deliver follow-up {}: {}", followup.id, e);
+                    }
+                }
+                Err(e) => error!("Follow-up {} failed: {}", followup.id, e),
+            }
+        }
+        Ok(due.len())
+    }
+
+    /// Polls the store for due follow-ups
+    pub fn spawn_followups(
+        self: Arc<Self>,
+        store: Arc<FollowUpStore>,
+        poll_interval: Duration,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(poll_interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.run_due_followups(&store).await {
+                    error!("Failed to run follow-ups: {}", e);
+                }
+            }
+        })
+    }
+}
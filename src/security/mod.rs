@@ -0,0 +1,217 @@
+//! Security events: requests refused by authentication, session access checks, model
+//! restrictions, rate limits or moderation, kept so operators can spot abuse patterns.
+//! Inputs are only stored as a hash.
+
+use crate::{Result, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use ring::digest::{SHA256, digest};
+use serde::Serialize;
+use std::fmt::Write;
+use tracing::{info, warn};
+
+/// Why a request was refused
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityRule {
+    /// No API key was presented while keys are configured
+    MissingApiKey,
+    /// The presented key isn't a configured one
+    UnknownApiKey,
+    /// The key or user doesn't own the session
+    SessionAccess,
+    /// The key may not use the requested model
+    ModelNotAllowed,
+    /// The key exceeded its requests per minute
+    RateLimit,
+    /// Moderation blocked the input
+    Moderation,
+    /// A key without `admin` called an admin endpoint
+    AdminOnly,
+}
+
+impl SecurityRule {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityRule::MissingApiKey => "missing_api_key",
+            SecurityRule::UnknownApiKey => "unknown_api_key",
+            SecurityRule::SessionAccess => "session_access",
+            SecurityRule::ModelNotAllowed => "model_not_allowed",
+            SecurityRule::RateLimit => "rate_limit",
+            SecurityRule::Moderation => "moderation",
+            SecurityRule::AdminOnly => "admin_only",
+        }
+    }
+}
+
+/// A refused request
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SecurityEvent {
+    /// Name of the API key the request carried; `None` without a known key
+    pub principal: Option<String>,
+    pub rule: SecurityRule,
+    pub session_id: Option<String>,
+    /// What the rule matched, such as the model or the moderation categories
+    pub detail: Option<String>,
+    /// Hash of the refused input, to tell repeated attempts apart without keeping them
+    pub input_hash: Option<String>,
+}
+
+impl SecurityEvent {
+    pub fn new(rule: SecurityRule, principal: Option<&str>) -> Self {
+        Self {
+            principal: principal.map(str::to_string),
+            rule,
+            session_id: None,
+            detail: None,
+            input_hash: None,
+        }
+    }
+
+    pub fn with_session(mut self, session_id: &str) -> Self {
+        self.session_id = Some(session_id.to_string());
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn with_input(mut self, input: &str) -> Self {
+        self.input_hash = Some(input_hash(input));
+        self
+    }
+}
+
+/// First 16 hex digits of the SHA-256 of `input`
+pub fn input_hash(input: &str) -> String {
+    digest(&SHA256, input.as_bytes()).as_ref()[..8]
+        .iter()
+        .fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        })
+}
+
+/// How many requests a rule refused for a principal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockedCount {
+    pub rule: String,
+    pub principal: Option<String>,
+    pub count: u64,
+}
+
+pub struct SecurityEventStore {
+    conn: Connection,
+}
+
+impl SecurityEventStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS security_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                principal TEXT,
+                rule TEXT NOT NULL,
+                session_id TEXT,
+                detail TEXT,
+                input_hash TEXT,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_security_events_rule ON security_events(rule, principal)",
+            (),
+        )
+        .await?;
+        info!("Security event store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Logs `event` and stores it
+    pub async fn record(&self, event: &SecurityEvent) -> Result<()> {
+        warn!(
+            target: "security",
+            rule = event.rule.as_str(),
+            principal = event.principal.as_deref(),
+            session_id = event.session_id.as_deref(),
+            detail = event.detail.as_deref(),
+            input_hash = event.input_hash.as_deref(),
+            "Request blocked"
+        );
+        self.conn
+            .execute(
+                "INSERT INTO security_events (principal, rule, session_id, detail, input_hash, \
+                 created_at) VALUES (?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    event.principal.as_deref(),
+                    event.rule.as_str(),
+                    event.session_id.as_deref(),
+                    event.detail.as_deref(),
+                    event.input_hash.as_deref(),
+                    Utc::now().to_rfc3339(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Refused requests since `since`, or ever, by rule and principal
+    pub async fn counts(&self, since: Option<DateTime<Utc>>) -> Result<Vec<BlockedCount>> {
+        let since = since.map_or_else(String::new, |since| since.to_rfc3339());
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT rule, principal, COUNT(*) FROM security_events WHERE created_at >= ? \
+                 GROUP BY rule, principal ORDER BY rule, principal",
+                [since],
+            )
+            .await?;
+        let mut counts = Vec::new();
+        while let Some(row) = rows.next().await? {
+            counts.push(BlockedCount {
+                rule: row.get(0)?,
+                principal: row.get(1)?,
+                count: row.get::<i64>(2)? as u64,
+            });
+        }
+        Ok(counts)
+    }
+}
+
+/// `counts` in the Prometheus text format
+pub fn render_metrics(counts: &[BlockedCount]) -> String {
+    let mut metrics = String::from(
+        "# HELP jarvis_blocked_requests_total Requests refused, by rule and API key.\n\
+         # TYPE jarvis_blocked_requests_total counter\n",
+    );
+    for count in counts {
+        let principal = count.principal.as_deref().unwrap_or("");
+        let _ = writeln!(
+            metrics,
+            "jarvis_blocked_requests_total{{rule=\"{}\",principal=\"{}\"}} {}",
+            count.rule,
+            escape_label(principal),
+            count.count
+        );
+    }
+    metrics
+}
+
+pub(crate) fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
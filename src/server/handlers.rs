@@ -1,38 +1,580 @@
-use super::types::{ErrorResponse, InferenceRequest, InferenceResponse};
-use crate::{agent::Agent, history::HistoryStorage};
-use axum::{extract::State, http::StatusCode, response::Json};
-use std::sync::Arc;
+use super::types::{
+    ErrorResponse, ExamplesQuery, HandOffRequest, HealthResponse, InferenceRequest,
+    InferenceResponse, KeyUsageResponse, ModelsResponse, ProgressQuery, PromptPreviewRequest,
+    PromptPreviewResponse, RevertSystemPromptRequest, RunTimelineResponse, SessionUsageResponse,
+    SystemPromptRequest, SystemPromptResponse, TasksQuery, ToolResultsRequest, TranscriptQuery,
+    UsageQuery, UsageReportQuery, UsageReportResponse,
+};
+#[cfg(feature = "knowledge")]
+use super::types::{IngestDocumentRequest, IngestDocumentResponse};
+use crate::{
+    Error,
+    agent::{Agent, AgentReply, PausedRuns, ProcessOptions, RunOutcome},
+    commands::{self, Command},
+    config::{
+        ApiKeyConfig, CommandsConfig, ExampleConfig, Features, FormattingConfig, OutputFormat,
+        ProgressConfig,
+    },
+    events::{
+        LatencyBreakdown, LlmCallPayload, RunEventStore, RunMetrics, SessionEventKind,
+        SessionEvents, progress_stream,
+    },
+    examples::{self, Example, ExampleFilter},
+    formatting,
+    history::{HistoryStorage, TranscriptFormat, render_transcript},
+    llm::{ModelCatalog, count_tokens, tokenizer_for},
+    notifications::{Notification, NotificationSink},
+    profiles::ProfileStore,
+    prompts::{PromptRevision, SystemPromptStore},
+    scheduler::FollowUpStore,
+    security::{SecurityEvent, SecurityEventStore, SecurityRule, render_metrics},
+    sessions::{
+        IntegrationSession, SessionMetadata, SessionMetadataUpdate, SessionStore, UpdateOutcome,
+        integration_session_id,
+    },
+    tasks::{TaskStatus, TaskStore},
+    tools::{error_result, text_result},
+    usage::{Tally, UsageStore},
+    version::BuildInfo,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{
+        IntoResponse, Json, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+};
+#[cfg(feature = "knowledge")]
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::Utc;
+use std::{convert::Infallible, sync::Arc};
 use tokio::sync::Mutex;
-use tracing::{error, info};
+use tokio_stream::{
+    Stream, StreamExt,
+    wrappers::{BroadcastStream, errors::BroadcastStreamRecvError},
+};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 #[derive(Clone)]
 pub struct AppState {
     pub history: Arc<HistoryStorage>,
     pub agent: Arc<Mutex<Agent>>,
+    pub notifier: Option<Arc<dyn NotificationSink>>,
+    pub followups: Option<Arc<FollowUpStore>>,
+    pub tasks: Option<Arc<TaskStore>>,
+    pub profiles: Option<Arc<ProfileStore>>,
+    #[cfg(feature = "knowledge")]
+    pub knowledge: Option<Arc<crate::knowledge::KnowledgeBase>>,
+    pub events: Option<Arc<SessionEvents>>,
+    /// Runs waiting for the caller's tool results
+    pub runs: Arc<PausedRuns>,
+    /// Keys inference requests must carry, when any are configured
+    pub api_keys: Arc<Vec<ApiKeyConfig>>,
+    /// Tokens and cost of each request
+    pub usage: Option<Arc<UsageStore>>,
+    /// Recorded events of each run
+    pub timeline: Option<Arc<RunEventStore>>,
+    /// Titles, tags and system prompts of sessions
+    pub sessions: Option<Arc<SessionStore>>,
+    /// Configured example prompts
+    pub examples: Arc<Vec<ExampleConfig>>,
+    /// Which requests have slash-commands in their input run
+    pub commands: Arc<CommandsConfig>,
+    /// How replies are formatted for each integration
+    pub formatting: Arc<FormattingConfig>,
+    /// Typing indicators and placeholders streamed to chat integrations
+    pub progress: Arc<ProgressConfig>,
+    /// Refused requests, for spotting abuse
+    pub security: Option<Arc<SecurityEventStore>>,
+    /// Revisions of the base system prompt set through the admin endpoints
+    pub system_prompts: Option<Arc<SystemPromptStore>>,
+    /// Models of the configured providers
+    pub models: Option<Arc<ModelCatalog>>,
+    /// Counts of runs, LLM calls and tool calls for `/metrics`
+    pub run_metrics: Option<Arc<RunMetrics>>,
+    /// Subsystems the server runs
+    pub features: Arc<Features>,
+}
+
+type ErrorReply = (StatusCode, Json<ErrorResponse>);
+
+/// Stores a refused request as a security event, when they are recorded
+async fn record_blocked(state: &AppState, event: SecurityEvent) {
+    if let Some(security) = &state.security
+        && let Err(e) = security.record(&event).await
+    {
+        warn!("Failed to record security event: {}", e);
+    }
+}
+
+/// The configured key a request carries as `Authorization: Bearer <key>`. `None` when
+/// no keys are configured; requests without a known key are rejected otherwise.
+async fn authenticate<'a>(
+    state: &'a AppState,
+    headers: &HeaderMap,
+) -> Result<Option<&'a ApiKeyConfig>, ErrorReply> {
+    let keys = &state.api_keys;
+    if keys.is_empty() {
+        return Ok(None);
+    }
+    let presented = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if let Some(key) = presented.and_then(|presented| keys.iter().find(|key| key.key == presented))
+    {
+        return Ok(Some(key));
+    }
+    let rule = match presented {
+        Some(_) => SecurityRule::UnknownApiKey,
+        None => SecurityRule::MissingApiKey,
+    };
+    record_blocked(state, SecurityEvent::new(rule, None)).await;
+    Err((
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "Missing or unknown API key".to_string(),
+        }),
+    ))
+}
+
+/// Refuses `key` a session another API key started or, when both name one, another user;
+/// the first request to run in a session id owns it. Admin keys may use every session, and
+/// without configured keys anyone may.
+async fn authorize_session(
+    state: &AppState,
+    key: Option<&ApiKeyConfig>,
+    session_id: &str,
+    user_id: Option<&str>,
+) -> Result<(), ErrorReply> {
+    let Some(sessions) = &state.sessions else {
+        return Ok(());
+    };
+    let owner = sessions
+        .claim(session_id, key.map(|key| key.name.as_str()), user_id)
+        .await
+        .map_err(|e| owner_unknown(session_id, e))?;
+    let Some(key) = key.filter(|key| !key.admin) else {
+        return Ok(());
+    };
+    let other_user = matches!(
+        (owner.user_id.as_deref(), user_id),
+        (Some(owner), Some(user)) if owner != user
+    );
+    if owner.api_key.as_deref() != Some(key.name.as_str()) || other_user {
+        return Err(refuse_session(state, key, session_id, user_id).await);
+    }
+    Ok(())
+}
+
+/// Refuses `key` a session another API key owns, without claiming it. A session nobody
+/// owns yet is only open while it has no history: messages stored before owners were
+/// recorded are left to admin keys.
+async fn authorize_owned_session(
+    state: &AppState,
+    key: Option<&ApiKeyConfig>,
+    session_id: &str,
+) -> Result<(), ErrorReply> {
+    let Some(sessions) = &state.sessions else {
+        return Ok(());
+    };
+    let Some(key) = key.filter(|key| !key.admin) else {
+        return Ok(());
+    };
+    let owned = match sessions
+        .owner(session_id)
+        .await
+        .map_err(|e| owner_unknown(session_id, e))?
+    {
+        Some(owner) => owner.api_key.as_deref() == Some(key.name.as_str()),
+        None => state
+            .history
+            .list(session_id)
+            .await
+            .map_err(|e| owner_unknown(session_id, e))?
+            .is_empty(),
+    };
+    if !owned {
+        return Err(refuse_session(state, key, session_id, None).await);
+    }
+    Ok(())
+}
+
+fn owner_unknown(session_id: &str, e: crate::Error) -> ErrorReply {
+    error!("Failed to check the owner of session {}: {}", session_id, e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: format!("Failed to check session access: {e}"),
+        }),
+    )
+}
+
+/// Records that `key` was refused a session, answering with 403
+async fn refuse_session(
+    state: &AppState,
+    key: &ApiKeyConfig,
+    session_id: &str,
+    user_id: Option<&str>,
+) -> ErrorReply {
+    warn!(
+        "API key '{}' was refused session {} it does not own",
+        key.name, session_id
+    );
+    let mut event =
+        SecurityEvent::new(SecurityRule::SessionAccess, Some(&key.name)).with_session(session_id);
+    if let Some(user_id) = user_id {
+        event = event.with_detail(format!("user {user_id}"));
+    }
+    record_blocked(state, event).await;
+    (
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: format!("This API key may not use session '{session_id}'"),
+        }),
+    )
+}
+
+/// Refuses keys without `admin`; without configured keys anyone may use admin endpoints
+async fn authorize_admin(state: &AppState, key: Option<&ApiKeyConfig>) -> Result<(), ErrorReply> {
+    let Some(key) = key.filter(|key| !key.admin) else {
+        return Ok(());
+    };
+    warn!("API key '{}' was refused an admin endpoint", key.name);
+    record_blocked(
+        state,
+        SecurityEvent::new(SecurityRule::AdminOnly, Some(&key.name)),
+    )
+    .await;
+    Err((
+        StatusCode::FORBIDDEN,
+        Json(ErrorResponse {
+            error: "This API key may not use admin endpoints".to_string(),
+        }),
+    ))
+}
+
+/// Session of a conversation of `integration`: the one it was handed off to, else its own.
+/// Nothing is recorded until the request is let through, see [`link_channel`].
+async fn conversation_session(
+    state: &AppState,
+    integration: Option<&str>,
+    conversation_id: &str,
+) -> Result<String, ErrorReply> {
+    let bad_request = |error: &str| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: error.to_string(),
+            }),
+        )
+    };
+    let integration =
+        integration.ok_or_else(|| bad_request("conversation_id needs an integration"))?;
+    let session_id = integration_session_id(integration, conversation_id).ok_or_else(|| {
+        bad_request(
+            "integration may only hold letters, digits, '-', '_' and '.', and conversation_id may not be empty",
+        )
+    })?;
+    let Some(sessions) = &state.sessions else {
+        return Ok(session_id);
+    };
+    match sessions.conversation(integration, conversation_id).await {
+        Ok(Some(linked)) => Ok(linked.session_id),
+        Ok(None) => Ok(session_id),
+        Err(e) => {
+            warn!("Failed to look up the session of {}: {}", session_id, e);
+            Ok(session_id)
+        }
+    }
+}
+
+/// Records the session of the conversation a request came from, once it is let through
+async fn link_channel(state: &AppState, channel: Option<&(String, String)>) {
+    let (Some(sessions), Some((integration, conversation_id))) = (&state.sessions, channel) else {
+        return;
+    };
+    if let Err(e) = sessions
+        .link_conversation(integration, conversation_id)
+        .await
+    {
+        warn!(
+            "Failed to record the session of {}:{}: {}",
+            integration, conversation_id, e
+        );
+    }
+}
+
+/// The model a request runs with: the one it asks for, else its key's default. Fails
+/// when the key doesn't allow that model, or `configured` when neither is set.
+fn select_model(
+    key: Option<&ApiKeyConfig>,
+    requested: Option<String>,
+    configured: &str,
+) -> Result<Option<String>, ErrorReply> {
+    let Some(key) = key else {
+        return Ok(requested);
+    };
+    let model = requested.or_else(|| key.default_model.clone());
+    let effective = model.as_deref().unwrap_or(configured);
+    if !key.allows(effective) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(ErrorResponse {
+                error: format!("This API key may not use model '{effective}'"),
+            }),
+        ));
+    }
+    Ok(model)
+}
+
+/// Refuses the request when `key` already made its allowed requests in the last minute,
+/// recording the refusal. Requests are let through if usage can't be read.
+async fn check_rate_limit(
+    state: &AppState,
+    usage: &UsageStore,
+    key: &ApiKeyConfig,
+    session_id: &str,
+    input: Option<&str>,
+) -> Result<(), ErrorReply> {
+    let Some(limit) = key.requests_per_minute else {
+        return Ok(());
+    };
+    let recent = match usage
+        .requests_since(&key.name, Utc::now() - chrono::Duration::minutes(1))
+        .await
+    {
+        Ok(recent) => recent,
+        Err(e) => {
+            warn!("Failed to read usage of API key '{}': {}", key.name, e);
+            return Ok(());
+        }
+    };
+    if recent < u64::from(limit) {
+        return Ok(());
+    }
+    warn!("API key '{}' exceeded its rate limit", key.name);
+    if let Err(e) = usage.record_rate_limited(&key.name, session_id).await {
+        warn!("Failed to record rate limit hit: {}", e);
+    }
+    let mut event = SecurityEvent::new(SecurityRule::RateLimit, Some(&key.name))
+        .with_session(session_id)
+        .with_detail(format!("{limit} requests per minute"));
+    if let Some(input) = input {
+        event = event.with_input(input);
+    }
+    record_blocked(state, event).await;
+    Err((
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(ErrorResponse {
+            error: format!("Rate limit of {limit} requests per minute exceeded"),
+        }),
+    ))
+}
+
+/// Records the tokens a request's LLM calls used, when usage is tracked
+async fn record_usage(
+    state: &AppState,
+    api_key: Option<&ApiKeyConfig>,
+    session_id: &str,
+    model: &str,
+    tally: Tally,
+) {
+    let Some(usage) = &state.usage else {
+        return;
+    };
+    // Models without a configured price cost what their provider lists
+    let listed = match &state.models {
+        Some(models) if !usage.has_price(model) => models.listed_price(model).await,
+        _ => None,
+    };
+    if let Err(e) = usage
+        .record_request_at(
+            api_key.map(|key| key.name.as_str()),
+            session_id,
+            model,
+            tally,
+            listed,
+        )
+        .await
+    {
+        warn!("Failed to record usage of session {}: {}", session_id, e);
+    }
 }
 
 pub async fn inference(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(request): Json<InferenceRequest>,
 ) -> Result<Json<InferenceResponse>, (StatusCode, Json<ErrorResponse>)> {
     info!("Received inference request for input: {}", request.input);
+    let api_key = authenticate(&state, &headers).await?;
+    // The server posts follow-ups to the callback, so only admin keys may set one
+    if request.callback_url.is_some() {
+        authorize_admin(&state, api_key).await?;
+    }
 
     // Generate session ID if not provided
-    let session_id = request
-        .session_id
-        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut channel = None;
+    let session_id = match (request.session_id, &request.conversation_id) {
+        (Some(session_id), _) => session_id,
+        (None, Some(conversation_id)) => {
+            let session_id =
+                conversation_session(&state, request.integration.as_deref(), conversation_id)
+                    .await?;
+            channel = request
+                .integration
+                .clone()
+                .map(|integration| (integration, conversation_id.clone()));
+            session_id
+        }
+        (None, None) => Uuid::new_v4().to_string(),
+    };
+    authorize_session(&state, api_key, &session_id, request.user_id.as_deref()).await?;
+
+    if let (Some(key), Some(usage)) = (api_key, &state.usage) {
+        check_rate_limit(&state, usage, key, &session_id, Some(&request.input)).await?;
+    }
+
+    if let (Some(url), Some(followups)) = (&request.callback_url, &state.followups)
+        && let Err(e) = followups.set_callback(&session_id, url).await
+    {
+        warn!("Failed to store callback for session {}: {}", session_id, e);
+    }
+
+    if let (Some(user_id), Some(profiles)) = (&request.user_id, &state.profiles)
+        && let Err(e) = profiles.link_session(&session_id, user_id).await
+    {
+        warn!("Failed to link session {} to user: {}", session_id, e);
+    }
+
+    let mut presentation = Presentation::new(
+        &state,
+        request.format,
+        request.max_chunk_size,
+        request.integration.as_deref(),
+    );
+    // JSON output is returned as the model wrote it, in one piece
+    if request
+        .response_format
+        .as_ref()
+        .is_some_and(|f| f.is_json())
+    {
+        presentation.format = OutputFormat::Markdown;
+        presentation.max_chunk_size = None;
+    }
+    let mut input = request.input;
+    if state.commands.enabled_for(request.integration.as_deref()) {
+        let parsed = match commands::parse(&input) {
+            Ok(parsed) => parsed,
+            Err(usage) => {
+                let response = command_response(&state, session_id, usage).await;
+                return Ok(formatted(&state, response, presentation));
+            }
+        };
+        let mut replies = Vec::new();
+        for command in parsed.commands {
+            replies.push(run_command(&state, api_key, &session_id, command).await?);
+        }
+        // Commands alone are answered without the LLM
+        if !replies.is_empty() && parsed.input.is_empty() {
+            link_channel(&state, channel.as_ref()).await;
+            let output = replies.join("\n");
+            let response = command_response(&state, session_id, output).await;
+            return Ok(formatted(&state, response, presentation));
+        }
+        input = parsed.input;
+    }
+
+    let settings = match &state.sessions {
+        Some(sessions) => sessions.get(&session_id).await.unwrap_or_else(|e| {
+            warn!("Failed to read settings of session {}: {}", session_id, e);
+            SessionMetadata::default()
+        }),
+        None => SessionMetadata::default(),
+    };
 
     // Process the request through the agent
-    let mut agent = state.agent.lock().await;
-    match agent
-        .process(&session_id, &request.input, &state.history)
-        .await
+    let (result, model, tally) = {
+        let mut agent = state.agent.lock().await;
+        let requested = request.model.or(settings.model);
+        let model = match select_model(api_key, requested, agent.model()) {
+            Ok(model) => model,
+            Err(refused) => {
+                let event = SecurityEvent::new(
+                    SecurityRule::ModelNotAllowed,
+                    api_key.map(|key| key.name.as_str()),
+                )
+                .with_session(&session_id)
+                .with_detail(refused.1.error.clone())
+                .with_input(&input);
+                record_blocked(&state, event).await;
+                return Err(refused);
+            }
+        };
+        link_channel(&state, channel.as_ref()).await;
+        // Usage is priced at the model the request runs with
+        let used_model = model.clone().unwrap_or_else(|| agent.model().to_string());
+        if let Some(usage) = &state.usage {
+            usage.begin(&session_id);
+        }
+        // Keeps the reply from being relayed back to the conversation asking
+        if let (Some((integration, conversation_id)), Some(events)) = (channel, &state.events) {
+            events.publish(
+                &session_id,
+                SessionEventKind::Channel {
+                    integration,
+                    conversation_id,
+                },
+            );
+        }
+        let result = agent
+            .process_with_options(
+                &session_id,
+                &input,
+                &state.history,
+                ProcessOptions {
+                    response_language: request.response_language,
+                    tool_mode: request.tool_mode,
+                    tools: request.tools,
+                    model,
+                    sampling: request.sampling,
+                    without_tools: !settings.tools_enabled,
+                    response_format: request.response_format,
+                    tool_choice: request.tool_choice,
+                    images: request.images,
+                },
+            )
+            .await;
+        let tally = state.usage.as_ref().map(|usage| usage.take(&session_id));
+        (result, used_model, tally.unwrap_or_default())
+    };
+    record_usage(&state, api_key, &session_id, &model, tally).await;
+    if let Ok(RunOutcome::Reply(reply)) = &result
+        && !reply.blocked.is_empty()
     {
-        Ok(output) => {
-            info!("Successfully processed request for session: {}", session_id);
-            Ok(Json(InferenceResponse { session_id, output }))
+        let event = SecurityEvent::new(
+            SecurityRule::Moderation,
+            api_key.map(|key| key.name.as_str()),
+        )
+        .with_session(&session_id)
+        .with_detail(reply.blocked.join(", "))
+        .with_input(&input);
+        record_blocked(&state, event).await;
+    }
+    match result {
+        Ok(outcome) => {
+            let response = run_response(&state, session_id, outcome, request.notify).await;
+            Ok(formatted(&state, response, presentation))
         }
+        Err(e @ Error::ToolNotFound { .. }) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
         Err(e) => {
             error!(
                 "Failed to process request for session {}: {}",
@@ -47,3 +589,1304 @@ pub async fn inference(
         }
     }
 }
+
+/// Resumes a run paused for tool calls the caller ran, with their results
+pub async fn submit_tool_results(
+    State(state): State<AppState>,
+    Path(run_id): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<ToolResultsRequest>,
+) -> Result<Json<InferenceResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+
+    let run = state.runs.take(&run_id).await.ok_or_else(|| {
+        error(
+            StatusCode::NOT_FOUND,
+            format!("No run '{run_id}' is waiting for tool results"),
+        )
+    })?;
+    // Only a key allowed on the session learns which calls are pending
+    let session_id = run.session_id.clone();
+    if let Err(refused) = authorize_session(&state, api_key, &session_id, None).await {
+        state.runs.restore(&run_id, run).await;
+        return Err(refused);
+    }
+    // A rejected submission can be corrected and sent again
+    if let Err(e) = run.check_results(
+        request
+            .tool_results
+            .iter()
+            .map(|result| result.tool_call_id.as_str()),
+    ) {
+        state.runs.restore(&run_id, run).await;
+        return Err(error(StatusCode::BAD_REQUEST, e.to_string()));
+    }
+    if let (Some(key), Some(usage)) = (api_key, &state.usage)
+        && let Err(refused) = check_rate_limit(&state, usage, key, &session_id, None).await
+    {
+        state.runs.restore(&run_id, run).await;
+        return Err(refused);
+    }
+    info!("Resuming run {} for session {}", run_id, session_id);
+    let results = request
+        .tool_results
+        .into_iter()
+        .map(|result| {
+            let response = if result.is_error {
+                error_result(result.content)
+            } else {
+                text_result(result.content)
+            };
+            (result.tool_call_id, response)
+        })
+        .collect();
+    let (result, model, tally) = {
+        let mut agent = state.agent.lock().await;
+        let model = run.model().unwrap_or(agent.model()).to_string();
+        if let Some(usage) = &state.usage {
+            usage.begin(&session_id);
+        }
+        let result = agent.resume_run(run, results, &state.history).await;
+        let tally = state.usage.as_ref().map(|usage| usage.take(&session_id));
+        (result, model, tally.unwrap_or_default())
+    };
+    record_usage(&state, api_key, &session_id, &model, tally).await;
+    match result {
+        Ok(outcome) => {
+            let response = run_response(&state, session_id, outcome, false).await;
+            let presentation = Presentation::new(
+                &state,
+                request.format,
+                request.max_chunk_size,
+                request.integration.as_deref(),
+            );
+            Ok(formatted(&state, response, presentation))
+        }
+        Err(e) => {
+            error!("Failed to resume run {}: {}", run_id, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Processing error: {e}"),
+            ))
+        }
+    }
+}
+
+/// Runs a slash-command for a session, returning what to tell the user
+async fn run_command(
+    state: &AppState,
+    api_key: Option<&ApiKeyConfig>,
+    session_id: &str,
+    command: Command,
+) -> Result<String, ErrorReply> {
+    let error = |e: crate::Error| {
+        error!("Failed to run command for session {}: {}", session_id, e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Command failed: {e}"),
+            }),
+        )
+    };
+    let update = match command {
+        Command::Help => return Ok(commands::HELP.to_string()),
+        Command::Reset => {
+            let cleared = state.history.clear(session_id).await.map_err(error)?;
+            info!("Reset session {} ({} messages)", session_id, cleared);
+            return Ok("Started a new conversation.".to_string());
+        }
+        Command::Model(model) => {
+            if let Some(model) = &model
+                && let Err((_, Json(refused))) = select_model(api_key, Some(model.clone()), "")
+            {
+                return Ok(refused.error);
+            }
+            SessionMetadataUpdate {
+                model: Some(model),
+                ..Default::default()
+            }
+        }
+        Command::Tools(enabled) => SessionMetadataUpdate {
+            tools_enabled: Some(enabled),
+            ..Default::default()
+        },
+    };
+    let Some(sessions) = &state.sessions else {
+        return Ok("Session settings are not available.".to_string());
+    };
+    let reply = match (&update.model, update.tools_enabled) {
+        (Some(Some(model)), _) => format!("Now answering with {model}."),
+        (Some(None), _) => "Back to the default model.".to_string(),
+        (_, Some(true)) => "Tools are on.".to_string(),
+        _ => "Tools are off.".to_string(),
+    };
+    sessions
+        .update(session_id, update, None)
+        .await
+        .map_err(error)?;
+    Ok(reply)
+}
+
+/// How a request's output is returned: what was asked for, else what is configured for
+/// its integration
+#[derive(Clone, Copy)]
+struct Presentation {
+    format: OutputFormat,
+    max_chunk_size: Option<usize>,
+}
+
+impl Presentation {
+    fn new(
+        state: &AppState,
+        format: Option<OutputFormat>,
+        max_chunk_size: Option<usize>,
+        integration: Option<&str>,
+    ) -> Self {
+        Self {
+            format: format.unwrap_or_else(|| state.formatting.format_for(integration)),
+            max_chunk_size: max_chunk_size
+                .or_else(|| state.formatting.max_chunk_size_for(integration)),
+        }
+    }
+}
+
+fn formatted(
+    state: &AppState,
+    mut response: InferenceResponse,
+    presentation: Presentation,
+) -> Json<InferenceResponse> {
+    let width = state.formatting.plain_width;
+    if let Some(max) = presentation.max_chunk_size
+        && !response.output.is_empty()
+    {
+        response.chunks =
+            formatting::render_chunks(&response.output, presentation.format, width, max);
+    }
+    response.output = formatting::render(&response.output, presentation.format, width);
+    Json(response)
+}
+
+/// Response to input that was only commands, which start no run
+async fn command_response(
+    state: &AppState,
+    session_id: String,
+    output: String,
+) -> InferenceResponse {
+    InferenceResponse {
+        session_id,
+        output,
+        chunks: Vec::new(),
+        citations: Vec::new(),
+        confidence: None,
+        run_id: Uuid::new_v4().to_string(),
+        tool_calls: Vec::new(),
+        context_fallback: None,
+        storage: state.history.status().await,
+    }
+}
+
+/// Answers with the run's reply, or keeps a paused run and hands back its tool calls
+async fn run_response(
+    state: &AppState,
+    session_id: String,
+    outcome: RunOutcome,
+    notify: bool,
+) -> InferenceResponse {
+    match outcome {
+        RunOutcome::Paused(run) => {
+            info!(
+                "Run for session {} paused for {} tool calls",
+                session_id,
+                run.tool_calls.len()
+            );
+            let tool_calls = run.tool_calls.iter().cloned().map(Into::into).collect();
+            let run_id = state.runs.insert(*run).await;
+            InferenceResponse {
+                session_id,
+                output: String::new(),
+                chunks: Vec::new(),
+                citations: Vec::new(),
+                confidence: None,
+                run_id,
+                tool_calls,
+                context_fallback: None,
+                storage: state.history.status().await,
+            }
+        }
+        RunOutcome::Reply(AgentReply {
+            run_id,
+            output,
+            citations,
+            confidence,
+            context_fallback,
+            ..
+        }) => {
+            info!("Successfully processed request for session: {}", session_id);
+            if notify {
+                match &state.notifier {
+                    Some(notifier) => {
+                        if let Err(e) = notifier
+                            .send(&Notification::new("Jarvis", output.clone()))
+                            .await
+                        {
+                            warn!("Failed to notify session {}: {}", session_id, e);
+                        }
+                    }
+                    None => warn!("Notification requested but no notifications are configured"),
+                }
+            }
+            InferenceResponse {
+                session_id,
+                output,
+                chunks: Vec::new(),
+                citations,
+                confidence,
+                run_id,
+                tool_calls: Vec::new(),
+                context_fallback,
+                storage: state.history.status().await,
+            }
+        }
+    }
+}
+
+/// Version of the running build
+pub async fn version() -> Json<BuildInfo> {
+    Json(crate::version::BUILD)
+}
+
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        storage: state.history.status().await,
+        buffered_messages: state.history.buffered(),
+    })
+}
+
+/// Models requests may pick, from every configured provider. Keys limited to some models
+/// only see those.
+pub async fn list_models(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<ModelsResponse>, ErrorReply> {
+    let api_key = authenticate(&state, &headers).await?;
+    let Some(catalog) = &state.models else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "The model catalog is not available".to_string(),
+            }),
+        ));
+    };
+    let mut listing = catalog.list().await;
+    if let Some(key) = api_key {
+        listing.models.retain(|model| key.allows(&model.id));
+    }
+    let default_model = api_key
+        .and_then(|key| key.default_model.clone())
+        .unwrap_or_else(|| catalog.default_model().to_string());
+    Ok(Json(ModelsResponse {
+        default_model,
+        models: listing.models,
+        errors: listing.errors,
+    }))
+}
+
+/// Shows the first LLM request a message would produce, without sending it
+pub async fn prompt_preview(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<PromptPreviewRequest>,
+) -> Result<Json<PromptPreviewResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let api_key = authenticate(&state, &headers).await?;
+    if let Some(session_id) = &request.session_id {
+        authorize_owned_session(&state, api_key, session_id).await?;
+    }
+    // A session that can't exist yet previews an empty history
+    let session_id = request
+        .session_id
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    let preview = {
+        let agent = state.agent.lock().await;
+        agent
+            .preview_request(&session_id, &request.input, &state.history)
+            .await
+    };
+    match preview {
+        Ok(preview) => {
+            let tokenizer = tokenizer_for(&preview.model);
+            Ok(Json(PromptPreviewResponse {
+                session_id: request.session_id,
+                estimated_tokens: count_tokens(
+                    tokenizer.as_ref(),
+                    &preview.messages,
+                    &preview.tools,
+                ),
+                tokenizer: tokenizer.name().to_string(),
+                messages: preview.messages,
+                tools: preview.tools,
+            }))
+        }
+        Err(e) => {
+            error!("Failed to preview prompt for session {}: {}", session_id, e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Preview error: {e}"),
+                }),
+            ))
+        }
+    }
+}
+
+pub async fn session_transcript(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<TranscriptQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+
+    let format = TranscriptFormat::from_name(query.format.as_deref().unwrap_or("markdown"))
+        .ok_or_else(|| {
+            error(
+                StatusCode::BAD_REQUEST,
+                "Unknown transcript format, expected markdown or html".to_string(),
+            )
+        })?;
+    let load_failed = |e: crate::Error| {
+        error!("Failed to load session {}: {}", session_id, e);
+        error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to load session: {e}"),
+        )
+    };
+    // A session that doesn't exist is a 404 whatever the client has cached
+    let messages = state.history.list(&session_id).await.map_err(load_failed)?;
+    if messages.is_empty() {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("Session '{session_id}' not found"),
+        ));
+    }
+    let fingerprint = state
+        .history
+        .fingerprint(&session_id)
+        .await
+        .map_err(load_failed)?;
+    let etag = format!("\"{fingerprint}-{}\"", format.name());
+    if not_modified(&headers, &etag) {
+        return Ok(not_modified_response(&etag));
+    }
+
+    let transcript = render_transcript(&session_id, &messages, format);
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        transcript,
+    )
+        .into_response())
+}
+
+/// Whether `If-None-Match` names `etag` (or is `*`), so the client's copy is still current
+fn not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim())
+        // Weak comparison, as RFC 9110 asks of If-None-Match
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+/// 304 telling the client to keep using what it has under `etag`
+fn not_modified_response(etag: &str) -> Response {
+    (
+        StatusCode::NOT_MODIFIED,
+        [
+            (header::ETAG, etag.to_string()),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+    )
+        .into_response()
+}
+
+pub async fn session_events(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ErrorResponse>)> {
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    let Some(events) = &state.events else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Session events are not available".to_string(),
+            }),
+        ));
+    };
+
+    let stream = BroadcastStream::new(events.subscribe()).filter_map(move |item| match item {
+        Ok(event) if event.session_id == session_id => Some(Ok(Event::default()
+            .event(event.kind.name())
+            .json_data(&event)
+            .unwrap_or_default())),
+        Ok(_) => None,
+        // Tell the client it missed events rather than silently skipping them
+        Err(BroadcastStreamRecvError::Lagged(missed)) => Some(Ok(Event::default()
+            .event("lagged")
+            .data(missed.to_string()))),
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// What a chat integration should show while the session's runs are in progress: `typing`
+/// to send its typing indicator, `placeholder` with the text of a message to edit as the
+/// run moves on, and `done` when the reply is ready
+pub async fn session_progress(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    Query(query): Query<ProgressQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, Json<ErrorResponse>)> {
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    let Some(events) = &state.events else {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Session events are not available".to_string(),
+            }),
+        ));
+    };
+
+    let interval = state
+        .progress
+        .typing_interval_for(query.integration.as_deref());
+    let stream =
+        progress_stream(events, session_id, interval, state.progress.placeholders).map(|update| {
+            Ok(Event::default()
+                .event(update.name())
+                .json_data(&update)
+                .unwrap_or_default())
+        });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Tracked tasks, with the prompts and sessions they came from. Admin only, as they span
+/// every key's sessions.
+pub async fn list_tasks(
+    State(state): State<AppState>,
+    Query(query): Query<TasksQuery>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_admin(&state, api_key).await?;
+
+    let Some(tasks) = &state.tasks else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Task tracking is not available".to_string(),
+        ));
+    };
+    let status = TaskStatus::parse_filter(query.status.as_deref().unwrap_or("open"))
+        .map_err(|e| error(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let list_failed = |e: crate::Error| {
+        error!("Failed to list tasks: {}", e);
+        error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to list tasks: {e}"),
+        )
+    };
+    let etag = format!(
+        "\"{}\"",
+        tasks.fingerprint(status).await.map_err(list_failed)?
+    );
+    if not_modified(&headers, &etag) {
+        return Ok(not_modified_response(&etag));
+    }
+    let tasks = tasks.list(status).await.map_err(list_failed)?;
+    Ok((
+        [
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        Json(tasks),
+    )
+        .into_response())
+}
+
+/// Configured example prompts, as labels and the prompts to send when they are picked
+pub async fn list_examples(
+    State(state): State<AppState>,
+    Query(query): Query<ExamplesQuery>,
+) -> Json<Vec<Example>> {
+    let filter = ExampleFilter {
+        tool: query.tool,
+        profile: query.profile,
+        limit: query.limit,
+    };
+    Json(examples::list(&state.examples, &filter))
+}
+
+/// What an API key used over a period: requests, tokens, cost and rate-limit hits. A key
+/// may read its own usage; the usage of others is for admin keys.
+pub async fn key_usage(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(query): Query<UsageQuery>,
+    headers: HeaderMap,
+) -> Result<Json<KeyUsageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    if api_key.is_some_and(|key| key.name != name) {
+        authorize_admin(&state, api_key).await?;
+    }
+
+    let Some(usage) = &state.usage else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Usage accounting is not available".to_string(),
+        ));
+    };
+    if !state.api_keys.iter().any(|key| key.name == name) {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("No API key named '{name}'"),
+        ));
+    }
+    let until = query.until.unwrap_or_else(Utc::now);
+    let since = match query.since {
+        Some(since) => since,
+        None => until
+            .checked_sub_signed(chrono::Duration::days(30))
+            .ok_or_else(|| {
+                error(
+                    StatusCode::BAD_REQUEST,
+                    "`until` is out of range".to_string(),
+                )
+            })?,
+    };
+    if since > until {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "`since` must not be after `until`".to_string(),
+        ));
+    }
+
+    match usage.summary(&name, since, until).await {
+        Ok(summary) => Ok(Json(KeyUsageResponse {
+            key: name,
+            since,
+            until,
+            usage: summary,
+        })),
+        Err(e) => {
+            error!("Failed to summarize usage of API key '{}': {}", name, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to summarize usage: {e}"),
+            ))
+        }
+    }
+}
+
+/// What all requests used over a span of days: requests, tokens, tool calls and cost, in
+/// total, by day and by session. Admin only, as it covers every key's sessions.
+pub async fn usage_report(
+    State(state): State<AppState>,
+    Query(query): Query<UsageReportQuery>,
+    headers: HeaderMap,
+) -> Result<Json<UsageReportResponse>, ErrorReply> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_admin(&state, api_key).await?;
+
+    let Some(usage) = &state.usage else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Usage accounting is not available".to_string(),
+        ));
+    };
+    let out_of_range = || {
+        error(
+            StatusCode::BAD_REQUEST,
+            "`from` or `to` is out of range".to_string(),
+        )
+    };
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = match query.from {
+        Some(from) => from,
+        None => to
+            .checked_sub_signed(chrono::Duration::days(29))
+            .ok_or_else(out_of_range)?,
+    };
+    if from > to {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "`from` must not be after `to`".to_string(),
+        ));
+    }
+    let since = from.and_time(chrono::NaiveTime::MIN).and_utc();
+    let until = to
+        .checked_add_signed(chrono::Duration::days(1))
+        .ok_or_else(out_of_range)?
+        .and_time(chrono::NaiveTime::MIN)
+        .and_utc();
+
+    match usage.report(since, until).await {
+        Ok(report) => Ok(Json(UsageReportResponse { from, to, report })),
+        Err(e) => {
+            error!("Failed to report usage from {} to {}: {}", from, to, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to report usage: {e}"),
+            ))
+        }
+    }
+}
+
+/// What a session's requests used since it started: tokens and estimated cost, in total
+/// and by model
+pub async fn session_usage(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<SessionUsageResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+
+    let Some(usage) = &state.usage else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Usage accounting is not available".to_string(),
+        ));
+    };
+    match usage.session_summary(&session_id).await {
+        Ok(usage) => Ok(Json(SessionUsageResponse { session_id, usage })),
+        Err(e) => {
+            error!("Failed to summarize usage of session {}: {}", session_id, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to summarize usage: {e}"),
+            ))
+        }
+    }
+}
+
+/// Everything recorded of a run: its state changes, LLM calls and tool calls, in order
+pub async fn run_timeline(
+    State(state): State<AppState>,
+    Path(run_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<RunTimelineResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+
+    let Some(timeline) = &state.timeline else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Run timelines are not available".to_string(),
+        ));
+    };
+    let events = match timeline.timeline(&run_id).await {
+        Ok(events) => events,
+        Err(e) => {
+            error!("Failed to load timeline of run {}: {}", run_id, e);
+            return Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load timeline: {e}"),
+            ));
+        }
+    };
+    let (Some(first), Some(breakdown)) = (events.first(), LatencyBreakdown::from_events(&events))
+    else {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("No run with id '{run_id}'"),
+        ));
+    };
+    authorize_owned_session(&state, api_key, &first.session_id).await?;
+    Ok(Json(RunTimelineResponse {
+        session_id: first.session_id.clone(),
+        run_id,
+        breakdown,
+        events,
+    }))
+}
+
+/// The payload sent to the provider for the `n`th LLM call of a run, counting from 1
+pub async fn run_llm_call(
+    State(state): State<AppState>,
+    Path((run_id, n)): Path<(String, usize)>,
+    headers: HeaderMap,
+) -> Result<Json<LlmCallPayload>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+
+    let Some(timeline) = &state.timeline else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Run timelines are not available".to_string(),
+        ));
+    };
+    let call = match timeline.llm_call(&run_id, n).await {
+        Ok(Some(call)) => call,
+        Ok(None) => {
+            return Err(error(
+                StatusCode::NOT_FOUND,
+                format!("No LLM call {n} recorded for run '{run_id}'"),
+            ));
+        }
+        Err(e) => {
+            error!("Failed to load LLM call {} of run {}: {}", n, run_id, e);
+            return Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load LLM call: {e}"),
+            ));
+        }
+    };
+    authorize_owned_session(&state, api_key, &call.session_id).await?;
+    Ok(Json(call))
+}
+
+/// Conversations of an integration and their sessions
+pub async fn integration_sessions(
+    State(state): State<AppState>,
+    Path(integration): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<IntegrationSession>>, ErrorReply> {
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_admin(&state, api_key).await?;
+    let Some(sessions) = &state.sessions else {
+        return Err(sessions_unavailable());
+    };
+    sessions
+        .conversations(&integration)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!("Failed to list the sessions of {}: {}", integration, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to list sessions: {e}"),
+                }),
+            )
+        })
+}
+
+/// The session of one conversation of an integration, once it was used
+pub async fn integration_session(
+    State(state): State<AppState>,
+    Path((integration, conversation_id)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> Result<Json<IntegrationSession>, ErrorReply> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    let Some(session_id) = integration_session_id(&integration, &conversation_id) else {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            "No such conversation".to_string(),
+        ));
+    };
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    let Some(sessions) = &state.sessions else {
+        return Err(sessions_unavailable());
+    };
+    match sessions.conversation(&integration, &conversation_id).await {
+        Ok(Some(conversation)) => Ok(Json(conversation)),
+        Ok(None) => Err(error(
+            StatusCode::NOT_FOUND,
+            "No such conversation".to_string(),
+        )),
+        Err(e) => {
+            error!("Failed to load the session of {}: {}", session_id, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load the session: {e}"),
+            ))
+        }
+    }
+}
+
+/// Conversations of integrations held by a session, which its replies are relayed to
+pub async fn session_channels(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<IntegrationSession>>, ErrorReply> {
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    let Some(sessions) = &state.sessions else {
+        return Err(sessions_unavailable());
+    };
+    sessions
+        .session_conversations(&session_id)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            error!(
+                "Failed to list the channels of session {}: {}",
+                session_id, e
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: format!("Failed to list channels: {e}"),
+                }),
+            )
+        })
+}
+
+/// Hands a conversation of an integration off to a session: its requests continue there,
+/// sharing the history, and the session's other replies are relayed to `callback_url`.
+/// The key must be allowed the session, the conversation's own one and the one holding it
+/// now, and only admin keys may set a `callback_url`, as the server posts to it.
+pub async fn hand_off_conversation(
+    State(state): State<AppState>,
+    Path((session_id, integration, conversation_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+    Json(request): Json<HandOffRequest>,
+) -> Result<Json<IntegrationSession>, ErrorReply> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    let Some(own_session) = integration_session_id(&integration, &conversation_id) else {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            "integration may only hold letters, digits, '-', '_' and '.', and conversation_id may not be empty".to_string(),
+        ));
+    };
+    authorize_owned_session(&state, api_key, &own_session).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    if request.callback_url.is_some() {
+        authorize_admin(&state, api_key).await?;
+    }
+    let Some(sessions) = &state.sessions else {
+        return Err(sessions_unavailable());
+    };
+    // Taking the conversation away from the session holding it needs that session too
+    let current = sessions
+        .conversation(&integration, &conversation_id)
+        .await
+        .map_err(|e| {
+            error!("Failed to look up conversation {}: {}", own_session, e);
+            error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to hand off the conversation: {e}"),
+            )
+        })?;
+    if let Some(current) =
+        current.filter(|c| c.session_id != own_session && c.session_id != session_id)
+    {
+        authorize_owned_session(&state, api_key, &current.session_id).await?;
+    }
+    match sessions
+        .hand_off(
+            &integration,
+            &conversation_id,
+            &session_id,
+            request.callback_url.as_deref(),
+        )
+        .await
+    {
+        Ok(Some(conversation)) => {
+            info!(
+                "Handed {}:{} off to session {}",
+                integration, conversation_id, session_id
+            );
+            Ok(Json(conversation))
+        }
+        Ok(None) => Err(error(
+            StatusCode::NOT_FOUND,
+            "No such conversation".to_string(),
+        )),
+        Err(e) => {
+            error!(
+                "Failed to hand {} off to {}: {}",
+                own_session, session_id, e
+            );
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to hand off the conversation: {e}"),
+            ))
+        }
+    }
+}
+
+/// Takes a conversation back from a session; its next request continues in its own
+pub async fn unlink_conversation(
+    State(state): State<AppState>,
+    Path((session_id, integration, conversation_id)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<StatusCode, ErrorReply> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+    let Some(sessions) = &state.sessions else {
+        return Err(sessions_unavailable());
+    };
+    match sessions
+        .unlink_conversation(&integration, &conversation_id, &session_id)
+        .await
+    {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(error(
+            StatusCode::NOT_FOUND,
+            "The conversation is not linked to this session".to_string(),
+        )),
+        Err(e) => {
+            error!(
+                "Failed to unlink {}:{} from session {}: {}",
+                integration, conversation_id, session_id, e
+            );
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to unlink the conversation: {e}"),
+            ))
+        }
+    }
+}
+
+fn sessions_unavailable() -> ErrorReply {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Json(ErrorResponse {
+            error: "Sessions are not available".to_string(),
+        }),
+    )
+}
+
+/// Metadata of a session, with its revision as the `ETag`
+pub async fn session_metadata(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+
+    let Some(sessions) = &state.sessions else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Session metadata is not available".to_string(),
+        ));
+    };
+    match sessions.get(&session_id).await {
+        Ok(metadata) => {
+            let etag = format!("\"{}\"", metadata.revision);
+            if not_modified(&headers, &etag) {
+                return Ok(not_modified_response(&etag));
+            }
+            Ok(metadata_response(StatusCode::OK, metadata))
+        }
+        Err(e) => {
+            error!("Failed to load metadata of session {}: {}", session_id, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to load session metadata: {e}"),
+            ))
+        }
+    }
+}
+
+/// Edits the metadata of a session. With `If-Match: "<revision>"`, the edit only applies
+/// if nobody else edited the session since that revision, failing with 412 otherwise.
+pub async fn update_session_metadata(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    headers: HeaderMap,
+    Json(update): Json<SessionMetadataUpdate>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_owned_session(&state, api_key, &session_id).await?;
+
+    let Some(sessions) = &state.sessions else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Session metadata is not available".to_string(),
+        ));
+    };
+    let expected_revision = match headers.get(header::IF_MATCH) {
+        None => None,
+        Some(value) => match value.to_str().map(str::trim) {
+            Ok("*") => None,
+            Ok(value) => Some(
+                value
+                    .trim_start_matches("W/")
+                    .trim_matches('"')
+                    .parse::<i64>()
+                    .map_err(|_| {
+                        error(
+                            StatusCode::BAD_REQUEST,
+                            format!("Invalid If-Match revision '{value}'"),
+                        )
+                    })?,
+            ),
+            Err(_) => {
+                return Err(error(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid If-Match header".to_string(),
+                ));
+            }
+        },
+    };
+
+    match sessions
+        .update(&session_id, update, expected_revision)
+        .await
+    {
+        Ok(UpdateOutcome::Updated(metadata)) => Ok(metadata_response(StatusCode::OK, metadata)),
+        Ok(UpdateOutcome::Conflict(current)) => {
+            info!(
+                "Rejected edit of session {} at revision {:?}, now at {}",
+                session_id, expected_revision, current.revision
+            );
+            Ok(metadata_response(StatusCode::PRECONDITION_FAILED, current))
+        }
+        Err(e) => {
+            error!("Failed to update metadata of session {}: {}", session_id, e);
+            Err(error(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update session metadata: {e}"),
+            ))
+        }
+    }
+}
+
+fn metadata_response(status: StatusCode, metadata: SessionMetadata) -> Response {
+    (
+        status,
+        [
+            (header::ETAG, format!("\"{}\"", metadata.revision)),
+            (header::CACHE_CONTROL, "no-cache".to_string()),
+        ],
+        Json(metadata),
+    )
+        .into_response()
+}
+
+/// Adds a document to the knowledge base, replacing earlier chunks of its source. Admin
+/// only, as what it ingests is injected into prompts.
+#[cfg(feature = "knowledge")]
+pub async fn ingest_document(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<IngestDocumentRequest>,
+) -> Result<Json<IngestDocumentResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let error = |status: StatusCode, error: String| (status, Json(ErrorResponse { error }));
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_admin(&state, api_key).await?;
+
+    let Some(knowledge) = &state.knowledge else {
+        return Err(error(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "The knowledge base is not enabled".to_string(),
+        ));
+    };
+    let format = match &request.format {
+        Some(name) => crate::knowledge::DocumentFormat::from_name(name),
+        None => crate::knowledge::DocumentFormat::from_path(std::path::Path::new(&request.source)),
+    }
+    .ok_or_else(|| {
+        error(
+            StatusCode::BAD_REQUEST,
+            "Unknown document format, expected pdf, html, markdown or text".to_string(),
+        )
+    })?;
+    let bytes = match (request.content, request.content_base64) {
+        (Some(content), None) => content.into_bytes(),
+        (None, Some(encoded)) => BASE64.decode(encoded.trim()).map_err(|e| {
+            error(
+                StatusCode::BAD_REQUEST,
+                format!("Invalid content_base64: {e}"),
+            )
+        })?,
+        _ => {
+            return Err(error(
+                StatusCode::BAD_REQUEST,
+                "Provide exactly one of content or content_base64".to_string(),
+            ));
+        }
+    };
+
+    match knowledge
+        .ingest_bytes(&request.source, format, &bytes, &request.metadata)
+        .await
+    {
+        Ok(chunks) => Ok(Json(IngestDocumentResponse {
+            source: request.source,
+            chunks,
+        })),
+        Err(e) => {
+            error!("Failed to ingest document {}: {}", request.source, e);
+            Err(error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Failed to ingest document: {e}"),
+            ))
+        }
+    }
+}
+
+/// Counts of refused requests by rule and API key, in the Prometheus text format
+pub async fn metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    // The counts name the keys, so only admins see them
+    let api_key = match authenticate(&state, &headers).await {
+        Ok(api_key) => api_key,
+        Err(reply) => return reply.into_response(),
+    };
+    if let Err(reply) = authorize_admin(&state, api_key).await {
+        return reply.into_response();
+    }
+    let counts = match &state.security {
+        Some(security) => match security.counts(None).await {
+            Ok(counts) => counts,
+            Err(e) => {
+                error!("Failed to count security events: {}", e);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(ErrorResponse {
+                        error: format!("Failed to count security events: {e}"),
+                    }),
+                )
+                    .into_response();
+            }
+        },
+        None => Vec::new(),
+    };
+    let mut metrics = render_metrics(&counts);
+    if let Some(run_metrics) = &state.run_metrics {
+        metrics.push_str(&run_metrics.render());
+    }
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics,
+    )
+        .into_response()
+}
+
+/// The system prompt store, authorizing the caller as an admin
+async fn system_prompt_store<'a>(
+    state: &'a AppState,
+    headers: &HeaderMap,
+) -> Result<(&'a SystemPromptStore, Option<&'a ApiKeyConfig>), ErrorReply> {
+    let api_key = authenticate(state, headers).await?;
+    authorize_admin(state, api_key).await?;
+    match &state.system_prompts {
+        Some(store) => Ok((store, api_key)),
+        None => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "System prompt changes are not available".to_string(),
+            }),
+        )),
+    }
+}
+
+fn system_prompt_error(e: crate::Error) -> ErrorReply {
+    error!("Failed to access system prompts: {}", e);
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(ErrorResponse {
+            error: format!("Failed to access system prompts: {e}"),
+        }),
+    )
+}
+
+/// The base system prompt of new runs, with every revision set
+pub async fn get_system_prompt(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<SystemPromptResponse>, ErrorReply> {
+    let (store, _) = system_prompt_store(&state, &headers).await?;
+    let history = store.history().await.map_err(system_prompt_error)?;
+    let prompt = state.agent.lock().await.system_prompt().to_string();
+    Ok(Json(SystemPromptResponse {
+        prompt,
+        revision: history.first().map(|revision| revision.revision),
+        history,
+    }))
+}
+
+/// Replaces the base system prompt of new runs, keeping it across restarts
+pub async fn set_system_prompt(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<SystemPromptRequest>,
+) -> Result<Json<PromptRevision>, ErrorReply> {
+    let (store, api_key) = system_prompt_store(&state, &headers).await?;
+    if request.prompt.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "The system prompt may not be empty".to_string(),
+            }),
+        ));
+    }
+    // Holding the agent keeps runs from starting between storing and applying the prompt
+    let mut agent = state.agent.lock().await;
+    let revision = store
+        .set(&request.prompt, api_key.map(|key| key.name.as_str()))
+        .await
+        .map_err(system_prompt_error)?;
+    agent.set_system_prompt(revision.prompt.clone());
+    info!("System prompt set to revision {}", revision.revision);
+    Ok(Json(revision))
+}
+
+/// Restores an earlier system prompt, by default the one before the current
+pub async fn revert_system_prompt(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    request: Option<Json<RevertSystemPromptRequest>>,
+) -> Result<Json<PromptRevision>, ErrorReply> {
+    let (store, api_key) = system_prompt_store(&state, &headers).await?;
+    let Json(request) = request.unwrap_or_default();
+    let mut agent = state.agent.lock().await;
+    let revision = store
+        .revert(request.revision, api_key.map(|key| key.name.as_str()))
+        .await
+        .map_err(system_prompt_error)?;
+    let Some(revision) = revision else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: match request.revision {
+                    Some(n) => format!("No system prompt revision {n}"),
+                    None => "No earlier system prompt to revert to".to_string(),
+                },
+            }),
+        ));
+    };
+    agent.set_system_prompt(revision.prompt.clone());
+    info!(
+        "System prompt reverted to revision {} as revision {}",
+        revision.reverted_from.unwrap_or_default(),
+        revision.revision
+    );
+    Ok(Json(revision))
+}
+
+/// Which subsystems the server runs
+pub async fn get_features(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<Features>, ErrorReply> {
+    let api_key = authenticate(&state, &headers).await?;
+    authorize_admin(&state, api_key).await?;
+    Ok(Json(*state.features))
+}
@@ -1,31 +1,353 @@
 pub mod handlers;
 mod types;
 
-use crate::{Result, agent::Agent, config::Config, history::HistoryStorage};
-use axum::{Router, routing::post};
-use std::{net::SocketAddr, sync::Arc};
+use crate::{
+    Result,
+    agent::{Agent, PausedRuns},
+    canary::{Canary, CanaryStore},
+    chaos::Chaos,
+    config::Config,
+    events::{RunEventHook, RunEventStore, RunMetrics, SessionEventHook, SessionEvents},
+    history::HistoryStorage,
+    llm::{ModelCatalog, create_llm_client},
+    notifications::RunFailureNotifier,
+    profiles::{ProfileHook, ProfileStore},
+    prompts::SystemPromptStore,
+    scheduler::{FollowUpStore, ScheduledJob, Scheduler, parse_interval},
+    security::SecurityEventStore,
+    sessions::{ChannelRelay, SessionPromptHook, SessionStore},
+    tasks::TaskStore,
+    tools::{
+        datetime::DateTimeSettings,
+        followup::ScheduleFollowUpTool,
+        profile::{ForgetPreferenceTool, RememberPreferenceTool},
+        tasks::{CompleteTaskTool, CreateTaskTool, ListTasksTool},
+    },
+    usage::{UsageHook, UsageStore},
+};
+use axum::{
+    Router,
+    routing::{get, post, put},
+};
+use std::{future::Future, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
 
+/// Runs the server until Ctrl+C or a termination signal
 pub async fn run(config: Config) -> Result<()> {
+    run_until(config, shutdown_signal()).await
+}
+
+/// Runs the server until `shutdown` completes, letting in-flight requests finish
+pub async fn run_until(
+    config: Config,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> Result<()> {
     // Initialize history storage
-    let db_path =
-        std::env::var("HISTORY_DB_PATH").unwrap_or_else(|_| config.server.database_path.clone());
-    let history = HistoryStorage::new(&db_path).await?;
+    let db_path = config.server.resolved_database_path();
+    let database = &config.server.database;
+    let mut history = HistoryStorage::open(&db_path, database).await?;
+    if let Some(chaos) = Chaos::from_config(&config.chaos) {
+        history = history.with_chaos(chaos);
+    }
 
     // Initialize agent
-    let agent = Agent::new(config.llm.clone(), config.mcp_servers.clone()).await?;
+    let mut agent = Agent::from_config(&config).await?;
+    let features = config.features();
+
+    // A system prompt set through the admin endpoints replaces the configured one
+    let system_prompts = Arc::new(SystemPromptStore::open(&db_path, database).await?);
+    if let Some(current) = system_prompts.current().await? {
+        info!("Using system prompt revision {}", current.revision);
+        agent.set_system_prompt(current.prompt);
+    }
+
+    // Start feed monitoring
+    #[cfg(feature = "feeds")]
+    if features.feeds {
+        let store = Arc::new(crate::feeds::FeedStore::open(&db_path, database).await?);
+        agent.register_native_tool(Arc::new(crate::tools::feeds::RecentFeedItemsTool::new(
+            store.clone(),
+        )));
+        crate::feeds::spawn_monitor(config.feeds.clone(), store);
+    }
+    #[cfg(not(feature = "feeds"))]
+    if !config.feeds.is_empty() {
+        warn!("Feeds disabled: jarvis was built without the `feeds` feature");
+    }
+
+    // Let the agent schedule follow-ups for its sessions
+    let datetime_settings =
+        DateTimeSettings::from_timezone_name(config.tools.datetime.timezone.as_deref())?;
+    let followups = if features.scheduler {
+        let followups = Arc::new(FollowUpStore::open(&db_path, database).await?);
+        agent.register_native_tool(Arc::new(ScheduleFollowUpTool::new(
+            followups.clone(),
+            datetime_settings,
+        )));
+        Some(followups)
+    } else {
+        None
+    };
+
+    // Persistent task list
+    let tasks = Arc::new(TaskStore::open(&db_path, database).await?);
+    agent.register_native_tool(Arc::new(CreateTaskTool::new(
+        tasks.clone(),
+        datetime_settings,
+    )));
+    agent.register_native_tool(Arc::new(ListTasksTool::new(
+        tasks.clone(),
+        datetime_settings,
+    )));
+    agent.register_native_tool(Arc::new(CompleteTaskTool::new(
+        tasks.clone(),
+        datetime_settings,
+    )));
+
+    // Session titles, tags and system prompts; the prompt goes in before preferences
+    let sessions = Arc::new(SessionStore::open(&db_path, database).await?);
+    agent.add_hook(Arc::new(SessionPromptHook::new(sessions.clone())));
+
+    // User preferences, injected into the system prompt of each user's sessions
+    let profiles = Arc::new(ProfileStore::open(&db_path, database).await?);
+    agent.register_native_tool(Arc::new(RememberPreferenceTool::new(profiles.clone())));
+    agent.register_native_tool(Arc::new(ForgetPreferenceTool::new(profiles.clone())));
+    agent.add_hook(Arc::new(ProfileHook::new(profiles.clone())));
+
+    // Tokens and cost of each request, by API key
+    let usage = Arc::new(UsageStore::open(&db_path, database, config.usage.prices.clone()).await?);
+    agent.add_hook(Arc::new(UsageHook::new(usage.clone())));
+
+    // Models of the configured providers, listed once up front so requests with models
+    // that have no configured price are priced as their provider lists them
+    let models = Arc::new(ModelCatalog::from_config(&config.llm)?);
+    let warmup = models.clone();
+    tokio::spawn(async move {
+        warmup.list().await;
+    });
+
+    // Document knowledge base
+    #[cfg(feature = "knowledge")]
+    let knowledge = if features.rag {
+        let knowledge =
+            Arc::new(crate::knowledge::KnowledgeBase::from_config(&config, &db_path).await?);
+        agent.register_native_tool(Arc::new(crate::tools::knowledge::KnowledgeSearchTool::new(
+            knowledge.clone(),
+            config.knowledge.result_count(),
+        )));
+        Some(knowledge)
+    } else {
+        None
+    };
+    #[cfg(not(feature = "knowledge"))]
+    if config.knowledge.enabled {
+        warn!("Knowledge base disabled: jarvis was built without the `knowledge` feature");
+    }
+
+    // Timeline of each run's state changes, LLM calls and tool calls, with the payload
+    // of each LLM call as the provider got it
+    let timeline = Arc::new(RunEventStore::open(&db_path, database).await?);
+    let llm_keys = std::iter::once(&config.llm.api_key)
+        .chain(
+            config
+                .llm
+                .fallbacks
+                .iter()
+                .map(|fallback| &fallback.api_key),
+        )
+        .cloned()
+        .collect();
+    agent.add_hook(Arc::new(
+        RunEventHook::new(timeline.clone())
+            .with_tool_servers(agent.get_tool_to_client_map().clone())
+            .with_payloads(Arc::from(create_llm_client(config.llm.clone())?), llm_keys),
+    ));
+
+    // Live session events, registered last so they reflect what other hooks allowed
+    let events = Arc::new(SessionEvents::new(256));
+    agent.add_hook(Arc::new(SessionEventHook::new(events.clone())));
+
+    let history = Arc::new(history);
+    // Retry a database that failed to open or write, flushing what was buffered meanwhile
+    history.clone().spawn_recovery(Duration::from_secs(30));
+    // Remove deleted messages for good once they are past recovery
+    history.clone().spawn_purge(
+        Duration::from_secs(config.history.purge_after_days * 24 * 60 * 60),
+        Duration::from_secs(60 * 60),
+    );
+    let agent = Arc::new(Mutex::new(agent));
+    #[cfg(feature = "notifications")]
+    let notifier = if features.notifications {
+        crate::notifications::create_notification_sink(&config.notifications)?
+    } else {
+        None
+    };
+    #[cfg(not(feature = "notifications"))]
+    let notifier: Option<Arc<dyn crate::notifications::NotificationSink>> = {
+        if config.notifications.provider != crate::config::NotificationProvider::None {
+            warn!("Notifications disabled: jarvis was built without the `notifications` feature");
+        }
+        None
+    };
+
+    // Subsystems following the runs through the session events
+    let run_metrics = Arc::new(RunMetrics::new());
+    events.spawn_subscriber("metrics", run_metrics.clone());
+    if config.notifications.run_failures
+        && let Some(notifier) = &notifier
+    {
+        events.spawn_subscriber(
+            "run_failures",
+            Arc::new(RunFailureNotifier::new(notifier.clone())),
+        );
+    }
+
+    // Replies relayed to the conversations handed off to each session
+    events.spawn_subscriber("channels", Arc::new(ChannelRelay::new(sessions.clone())?));
+
+    crate::version::spawn_update_check(&config.updates);
+
+    // Probes catching silent changes of the model behind the LLM endpoint
+    if config.canary.enabled && !config.canary.probes.is_empty() {
+        let store = Arc::new(CanaryStore::open(&db_path, database).await?);
+        let llm = Arc::from(create_llm_client(config.llm.clone())?);
+        let mut canary = Canary::new(llm, config.llm.model.clone(), store, &config.canary);
+        #[cfg(feature = "knowledge")]
+        if config.canary.embeddings {
+            canary = canary.with_embedder(Arc::new(crate::knowledge::OpenAiEmbedder::from_config(
+                &config,
+            )));
+        }
+        #[cfg(not(feature = "knowledge"))]
+        if config.canary.embeddings {
+            warn!("Canary embeddings disabled: jarvis was built without the `knowledge` feature");
+        }
+        if let Some(notifier) = &notifier {
+            canary = canary.with_notifier(notifier.clone());
+        }
+        Arc::new(canary).spawn(parse_interval(&config.canary.every)?);
+    }
+
+    if let Some(followups) = &followups {
+        let mut scheduler =
+            Scheduler::new(agent.clone(), history.clone(), datetime_settings.timezone());
+        if let Some(notifier) = &notifier {
+            scheduler = scheduler.with_notifier(notifier.clone());
+        }
+        let scheduler = Arc::new(scheduler);
+        scheduler
+            .clone()
+            .spawn_followups(followups.clone(), Duration::from_secs(30));
+
+        // Start scheduled jobs
+        if !config.schedules.is_empty() {
+            let jobs = config
+                .schedules
+                .iter()
+                .map(ScheduledJob::from_config)
+                .collect::<Result<Vec<_>>>()?;
+            scheduler.spawn(jobs);
+        }
+    }
 
     // Create application state
     let app_state = handlers::AppState {
-        history: Arc::new(history),
-        agent: Arc::new(Mutex::new(agent)),
+        history,
+        agent,
+        notifier,
+        followups,
+        tasks: Some(tasks),
+        profiles: Some(profiles),
+        #[cfg(feature = "knowledge")]
+        knowledge,
+        // Subscribers still follow the runs without the streams
+        events: features.streaming.then_some(events),
+        runs: Arc::new(PausedRuns::new()),
+        api_keys: Arc::new(config.server.api_keys.clone()),
+        usage: Some(usage),
+        timeline: Some(timeline),
+        sessions: Some(sessions),
+        examples: Arc::new(config.examples.clone()),
+        commands: Arc::new(if features.commands {
+            config.commands.clone()
+        } else {
+            Default::default()
+        }),
+        formatting: Arc::new(config.formatting.clone()),
+        progress: Arc::new(config.progress.clone()),
+        security: Some(Arc::new(
+            SecurityEventStore::open(&db_path, database).await?,
+        )),
+        system_prompts: Some(system_prompts),
+        models: Some(models.clone()),
+        run_metrics: Some(run_metrics),
+        features: Arc::new(features),
     };
 
     // Create router
-    let app = Router::new()
+    let router = Router::new()
         .route("/", post(handlers::inference))
-        .with_state(app_state);
+        .route("/health", get(handlers::health))
+        .route("/version", get(handlers::version))
+        .route("/metrics", get(handlers::metrics))
+        .route("/models", get(handlers::list_models))
+        .route(
+            "/runs/:run_id/tool_results",
+            post(handlers::submit_tool_results),
+        )
+        .route("/runs/:run_id/timeline", get(handlers::run_timeline))
+        .route("/runs/:run_id/llm_calls/:n", get(handlers::run_llm_call))
+        .route("/tasks", get(handlers::list_tasks))
+        .route("/examples", get(handlers::list_examples))
+        .route("/usage", get(handlers::usage_report))
+        .route("/keys/:name/usage", get(handlers::key_usage))
+        .route(
+            "/sessions/:session_id/transcript",
+            get(handlers::session_transcript),
+        )
+        .route(
+            "/sessions/:session_id/events",
+            get(handlers::session_events),
+        )
+        .route(
+            "/sessions/:session_id/progress",
+            get(handlers::session_progress),
+        )
+        .route("/sessions/:session_id/usage", get(handlers::session_usage))
+        .route(
+            "/sessions/:session_id/metadata",
+            get(handlers::session_metadata).put(handlers::update_session_metadata),
+        )
+        .route(
+            "/sessions/:session_id/channels",
+            get(handlers::session_channels),
+        )
+        .route(
+            "/sessions/:session_id/channels/:integration/:conversation_id",
+            put(handlers::hand_off_conversation).delete(handlers::unlink_conversation),
+        )
+        .route(
+            "/integrations/:integration/sessions",
+            get(handlers::integration_sessions),
+        )
+        .route(
+            "/integrations/:integration/sessions/:conversation_id",
+            get(handlers::integration_session),
+        )
+        .route("/debug/prompt-preview", post(handlers::prompt_preview))
+        .route(
+            "/admin/system_prompt",
+            get(handlers::get_system_prompt).put(handlers::set_system_prompt),
+        )
+        .route(
+            "/admin/system_prompt/revert",
+            post(handlers::revert_system_prompt),
+        )
+        .route("/admin/features", get(handlers::get_features));
+    #[cfg(feature = "knowledge")]
+    let router = router.route("/knowledge/documents", post(handlers::ingest_document));
+    let app = router.with_state(app_state);
 
     // Start server
     let addr = SocketAddr::new(config.server.host.parse()?, config.server.port);
@@ -33,7 +355,61 @@ pub async fn run(config: Config) -> Result<()> {
     info!("Starting server on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown)
+        .await?;
 
+    info!("Server stopped");
     Ok(())
 }
+
+/// Completes on Ctrl+C, SIGTERM on Unix, or the console closing or the system shutting
+/// down on Windows
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(windows)]
+    let terminate = async {
+        use tokio::signal::windows::{ctrl_close, ctrl_shutdown};
+        match (ctrl_close(), ctrl_shutdown()) {
+            (Ok(mut close), Ok(mut shutdown)) => {
+                tokio::select! {
+                    _ = close.recv() => {}
+                    _ = shutdown.recv() => {}
+                }
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("Failed to listen for console shutdown: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(any(unix, windows)))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    info!("Shutdown signal received, stopping server");
+}
@@ -1,3 +1,16 @@
+use crate::{
+    agent::{Citation, ToolMode},
+    config::{OutputFormat, SamplingConfig},
+    events::{LatencyBreakdown, RunEvent},
+    history::StorageStatus,
+    llm::{
+        ChatMessage, ContentPart, ContextFallback, ModelInfo, ProviderError, ResponseFormat, Tool,
+        ToolCall, ToolChoice,
+    },
+    prompts::PromptRevision,
+    usage::{SessionUsage, UsageReport, UsageSummary},
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize)]
@@ -5,12 +18,322 @@ pub struct InferenceRequest {
     #[serde(default)]
     pub session_id: Option<String>,
     pub input: String,
+    /// Also push the output to the configured notification sink
+    #[serde(default)]
+    pub notify: bool,
+    /// Webhook receiving follow-ups the agent later sends in this session
+    #[serde(default)]
+    pub callback_url: Option<String>,
+    /// User the session belongs to, selecting whose stored preferences apply
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// Language to reply in, overriding `agent.response_language`
+    #[serde(default)]
+    pub response_language: Option<String>,
+    /// `auto` (default) runs the agent's tools; `manual` hands every tool call back
+    #[serde(default)]
+    pub tool_mode: ToolMode,
+    /// Tools the caller runs itself, in OpenAI's function format. Calls to them are
+    /// handed back in either mode.
+    #[serde(default)]
+    pub tools: Vec<Tool>,
+    /// Model to answer with, instead of `llm.model` or the API key's default. Limited
+    /// to the models the API key allows.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// `temperature`, `max_tokens`, `top_p`, `stop`, penalties, `seed` and `logit_bias`
+    /// of the reply, instead of those of `llm`
+    #[serde(flatten)]
+    pub sampling: SamplingConfig,
+    /// `{"type": "json_object"}` or `{"type": "json_schema", "json_schema": {"name": ...,
+    /// "schema": ...}}` to have the output be JSON, checked before it is returned
+    #[serde(default)]
+    pub response_format: Option<ResponseFormat>,
+    /// `"auto"`, `"none"`, `"required"` or `{"type": "function", "function": {"name":
+    /// ...}}` to decide whether and which tools the first LLM call of the run calls
+    #[serde(default)]
+    pub tool_choice: Option<ToolChoice>,
+    /// Chat platform or client sending the request, such as `telegram`, selecting
+    /// whether slash-commands in the input are run
+    #[serde(default)]
+    pub integration: Option<String>,
+    /// The integration's own ID of the conversation, such as a Telegram chat, Slack
+    /// thread or webhook name. Without a `session_id`, the request goes to the session
+    /// `integration:conversation_id`.
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    /// Format of the output, instead of the integration's or Markdown
+    #[serde(default)]
+    pub format: Option<OutputFormat>,
+    /// Also return the output split into `chunks` of at most this many characters,
+    /// instead of the integration's limit
+    #[serde(default)]
+    pub max_chunk_size: Option<usize>,
+    /// Images for the agent to look at along with the input, as `{"type": "image_url",
+    /// "url": ...}` or `{"type": "image_base64", "mime_type": ..., "data": ...}`
+    #[serde(default)]
+    pub images: Vec<ContentPart>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct InferenceResponse {
     pub session_id: String,
     pub output: String,
+    /// The output split into messages within the requested or integration's size limit
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub chunks: Vec<String>,
+    /// Sources of the retrieved and tool-derived content the output is based on
+    pub citations: Vec<Citation>,
+    /// How likely the output is to be right, from 0 to 1, when replies are scored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+    /// Identifies the run, for its timeline. A run paused for `tool_calls` is resumed
+    /// by posting their results to it.
+    pub run_id: String,
+    /// Tool calls for the caller to run, in OpenAI's format
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ClientToolCall>,
+    /// How the last LLM call was answered after exceeding the model's context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_fallback: Option<ContextFallback>,
+    /// Present as `degraded` when the exchange is only held in memory
+    #[serde(skip_serializing_if = "StorageStatus::is_ok")]
+    pub storage: StorageStatus,
+}
+
+/// A tool call handed to the caller
+#[derive(Debug, Serialize)]
+pub struct ClientToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub call_type: String,
+    pub function: ClientFunctionCall,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClientFunctionCall {
+    pub name: String,
+    /// JSON-encoded arguments
+    pub arguments: String,
+}
+
+impl From<ToolCall> for ClientToolCall {
+    fn from(call: ToolCall) -> Self {
+        Self {
+            id: call.id,
+            call_type: call.call_type,
+            function: ClientFunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToolResultsRequest {
+    /// One result for each pending tool call of the run
+    pub tool_results: Vec<ClientToolResult>,
+    /// Chat platform or client sending the request, selecting the output's format
+    #[serde(default)]
+    pub integration: Option<String>,
+    /// Format of the output, instead of the integration's or Markdown
+    #[serde(default)]
+    pub format: Option<OutputFormat>,
+    /// Also return the output split into `chunks` of at most this many characters,
+    /// instead of the integration's limit
+    #[serde(default)]
+    pub max_chunk_size: Option<usize>,
+}
+
+/// Output of a tool call the caller ran
+#[derive(Debug, Deserialize)]
+pub struct ClientToolResult {
+    pub tool_call_id: String,
+    pub content: String,
+    /// Whether the call failed, with `content` describing the failure
+    #[serde(default)]
+    pub is_error: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub storage: StorageStatus,
+    /// Messages waiting in memory for the database to come back
+    pub buffered_messages: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromptPreviewRequest {
+    /// Session whose history is replayed; omit for a new session
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub input: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PromptPreviewResponse {
+    pub session_id: Option<String>,
+    pub messages: Vec<ChatMessage>,
+    pub tools: Vec<Tool>,
+    /// Prompt size counted with `tokenizer`; approximate when it is `heuristic`
+    pub estimated_tokens: usize,
+    /// Encoding of the configured model, or `heuristic` (about four characters per token)
+    pub tokenizer: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscriptQuery {
+    /// `markdown` (default) or `html`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TasksQuery {
+    /// `open` (default), `done` or `all`
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProgressQuery {
+    /// Chat platform the progress is shown on, selecting how often typing is renewed
+    #[serde(default)]
+    pub integration: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ExamplesQuery {
+    /// Only examples of this tool
+    #[serde(default)]
+    pub tool: Option<String>,
+    /// Only examples for this profile and those for every profile
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// At most this many examples, e.g. the number of buttons a client shows
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HandOffRequest {
+    /// Webhook receiving the session's replies to its other channels
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Start of the period; 30 days before `until` when omitted
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// End of the period, exclusive; now when omitted
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UsageReportQuery {
+    /// First UTC day of the report; 29 days before `to` when omitted
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    /// Last UTC day of the report, included; today when omitted
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReportResponse {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    #[serde(flatten)]
+    pub report: UsageReport,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeyUsageResponse {
+    pub key: String,
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionUsageResponse {
+    pub session_id: String,
+    #[serde(flatten)]
+    pub usage: SessionUsage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunTimelineResponse {
+    pub run_id: String,
+    pub session_id: String,
+    /// Every recorded event of the run, oldest first
+    pub events: Vec<RunEvent>,
+    /// Where the run's time went, turn by turn
+    pub breakdown: LatencyBreakdown,
+}
+
+#[cfg(feature = "knowledge")]
+#[derive(Debug, Deserialize)]
+pub struct IngestDocumentRequest {
+    /// Identifies the document; ingesting the same source again replaces it
+    pub source: String,
+    /// `pdf`, `html`, `markdown` or `text`; inferred from the source's extension when omitted
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Document text, for text formats
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Base64-encoded document, e.g. for PDFs
+    #[serde(default)]
+    pub content_base64: Option<String>,
+    /// Tags stored with every chunk
+    #[serde(default)]
+    pub metadata: std::collections::BTreeMap<String, String>,
+}
+
+#[cfg(feature = "knowledge")]
+#[derive(Debug, Serialize)]
+pub struct IngestDocumentResponse {
+    pub source: String,
+    pub chunks: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SystemPromptRequest {
+    pub prompt: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RevertSystemPromptRequest {
+    /// Revision to restore; the one before the current when unset
+    #[serde(default)]
+    pub revision: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SystemPromptResponse {
+    /// Base system prompt of new runs
+    pub prompt: String,
+    /// Revision of the prompt; `None` while the configured one is used
+    pub revision: Option<i64>,
+    /// Every revision set, newest first
+    pub history: Vec<PromptRevision>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModelsResponse {
+    /// Model of requests that don't pick one
+    pub default_model: String,
+    pub models: Vec<ModelInfo>,
+    /// Providers that couldn't be listed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<ProviderError>,
 }
 
 #[derive(Debug, Serialize)]
@@ -0,0 +1,168 @@
+//! Running the server as a Windows service: registration with the service control
+//! manager and the entry point it calls.
+
+use crate::{Error, Result, config::Config, server};
+use std::{
+    ffi::OsString,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::sync::Notify;
+use tracing::{error, info};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+pub const SERVICE_NAME: &str = "jarvis";
+
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+
+/// Configuration handed from `run` to the service thread the dispatcher starts
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+fn service_error(e: windows_service::Error) -> Error {
+    Error::internal(format!("Windows service error: {e}"))
+}
+
+/// Registers the current executable as an automatically started service running
+/// `jarvis service run`
+pub fn install() -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(service_error)?;
+    let info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from("J.A.R.V.I.S. agent server"),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: vec![OsString::from("service"), OsString::from("run")],
+        dependencies: vec![],
+        // Runs as LocalSystem
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+        .map_err(service_error)?;
+    service
+        .set_description("Smart-home agent server")
+        .map_err(service_error)?;
+    info!("Installed service '{}'", SERVICE_NAME);
+    Ok(())
+}
+
+/// Stops the service if it is running and removes it
+pub fn uninstall() -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(service_error)?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(service_error)?;
+    if service.query_status().map_err(service_error)?.current_state != ServiceState::Stopped {
+        service.stop().map_err(service_error)?;
+    }
+    service.delete().map_err(service_error)?;
+    info!("Removed service '{}'", SERVICE_NAME);
+    Ok(())
+}
+
+/// Services start in the system directory; switching to the executable's directory
+/// keeps `config.yaml` and a relative `server.data_dir` next to the binary
+pub fn use_executable_dir() -> Result<()> {
+    let executable = std::env::current_exe()?;
+    if let Some(dir) = executable.parent() {
+        std::env::set_current_dir(dir)?;
+    }
+    Ok(())
+}
+
+/// Hands the process to the service control manager, blocking until the service stops.
+/// Only works when started by the service control manager.
+pub fn run(config: Config) -> Result<()> {
+    CONFIG
+        .set(config)
+        .map_err(|_| Error::internal("The service is already running"))?;
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main).map_err(service_error)
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("Service failed: {}", e);
+    }
+}
+
+fn set_state(
+    status: &ServiceStatusHandle,
+    state: ServiceState,
+    controls_accepted: ServiceControlAccept,
+    exit_code: ServiceExitCode,
+) -> Result<()> {
+    status
+        .set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state: state,
+            controls_accepted,
+            exit_code,
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+        .map_err(service_error)
+}
+
+fn run_service() -> Result<()> {
+    let config = CONFIG
+        .get()
+        .cloned()
+        .ok_or_else(|| Error::internal("Service started without a configuration"))?;
+
+    // Stop and system shutdown both end the server gracefully
+    let stop = Arc::new(Notify::new());
+    let handler_stop = stop.clone();
+    let status = service_control_handler::register(SERVICE_NAME, move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            handler_stop.notify_one();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })
+    .map_err(service_error)?;
+    set_state(
+        &status,
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ServiceExitCode::NO_ERROR,
+    )?;
+
+    let result = tokio::runtime::Runtime::new()?.block_on(server::run_until(config, async move {
+        stop.notified().await
+    }));
+    let exit_code = match &result {
+        Ok(()) => ServiceExitCode::NO_ERROR,
+        Err(_) => ServiceExitCode::ServiceSpecific(1),
+    };
+    set_state(
+        &status,
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        exit_code,
+    )?;
+    result
+}
@@ -0,0 +1,525 @@
+//! Editable metadata of a session (title, tags, and a system prompt, model and tool
+//! choice of its own), with a
+//! revision number bumped by every edit so concurrent editors detect each other's
+//! changes instead of overwriting them. Also records who owns each session, and which
+//! session holds each conversation of an integration, so a conversation can be handed
+//! off to another session and get its replies relayed.
+
+mod relay;
+
+pub use relay::ChannelRelay;
+
+use crate::{
+    Error, Result,
+    agent::{AgentHook, HookContext},
+    config::DatabaseConfig,
+    db,
+    llm::{ChatCompletionRequest, ChatMessage},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionMetadata {
+    pub session_id: String,
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    /// Replaces the configured system prompt in this session
+    pub system_prompt: Option<String>,
+    /// Model to answer with, instead of `llm.model` or the API key's default
+    pub model: Option<String>,
+    /// Whether the agent's tools are offered to the LLM
+    pub tools_enabled: bool,
+    /// 0 until first edited, then bumped by every edit
+    pub revision: i64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Default for SessionMetadata {
+    fn default() -> Self {
+        Self {
+            session_id: String::new(),
+            title: None,
+            tags: Vec::new(),
+            system_prompt: None,
+            model: None,
+            tools_enabled: true,
+            revision: 0,
+            updated_at: None,
+        }
+    }
+}
+
+/// Fields to change; unset ones are kept, and `null` clears `title`, `system_prompt` or
+/// `model`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SessionMetadataUpdate {
+    #[serde(default, deserialize_with = "present")]
+    pub title: Option<Option<String>>,
+    pub tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub system_prompt: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    pub model: Option<Option<String>>,
+    pub tools_enabled: Option<bool>,
+}
+
+/// Tells a field set to `null` (`Some(None)`) from a missing one (`None`)
+fn present<'de, D>(deserializer: D) -> std::result::Result<Option<Option<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(Some)
+}
+
+/// The API key and user a session was started by
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionOwner {
+    /// Name of the key; `None` for sessions started while no keys were configured
+    pub api_key: Option<String>,
+    pub user_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A conversation of an integration, such as a Telegram chat, and the session holding it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntegrationSession {
+    pub integration: String,
+    /// The integration's own ID of the conversation
+    pub conversation_id: String,
+    pub session_id: String,
+    /// Webhook the session's replies to other channels are relayed to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+const INTEGRATION_SESSION_COLUMNS: &str =
+    "integration, conversation_id, session_id, created_at, callback_url";
+
+/// Session ID of a conversation of `integration`, as `integration:conversation_id`.
+/// Integration names are limited to letters, digits, `-`, `_` and `.` so the first `:`
+/// ends the namespace and IDs of different integrations never collide. `None` for an
+/// invalid name or an empty conversation ID.
+pub fn integration_session_id(integration: &str, conversation_id: &str) -> Option<String> {
+    let valid_name = !integration.is_empty()
+        && integration
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    (valid_name && !conversation_id.is_empty()).then(|| format!("{integration}:{conversation_id}"))
+}
+
+/// Result of an edit made against an expected revision
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateOutcome {
+    Updated(SessionMetadata),
+    /// Someone else edited first; holds the metadata as they left it
+    Conflict(SessionMetadata),
+}
+
+pub struct SessionStore {
+    conn: Connection,
+}
+
+impl SessionStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_metadata (
+                session_id TEXT PRIMARY KEY,
+                title TEXT,
+                tags TEXT NOT NULL,
+                system_prompt TEXT,
+                model TEXT,
+                tools_enabled INTEGER NOT NULL DEFAULT 1,
+                revision INTEGER NOT NULL,
+                updated_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS session_owners (
+                session_id TEXT PRIMARY KEY,
+                api_key TEXT,
+                user_id TEXT,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS integration_sessions (
+                integration TEXT NOT NULL,
+                conversation_id TEXT NOT NULL,
+                session_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                callback_url TEXT,
+                PRIMARY KEY (integration, conversation_id)
+            )
+            "#,
+            (),
+        )
+        .await?;
+        // Tables created before conversations could be handed off lack the callback
+        let mut columns = conn
+            .query("PRAGMA table_info(integration_sessions)", ())
+            .await?;
+        let mut has_callback_url = false;
+        while let Some(row) = columns.next().await? {
+            has_callback_url |= row.get::<String>(1)? == "callback_url";
+        }
+        if !has_callback_url {
+            conn.execute(
+                "ALTER TABLE integration_sessions ADD COLUMN callback_url TEXT",
+                (),
+            )
+            .await?;
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_integration_sessions_session ON integration_sessions(session_id)",
+            (),
+        )
+        .await?;
+        info!("Session store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    /// Metadata of a session, empty at revision 0 if never edited
+    pub async fn get(&self, session_id: &str) -> Result<SessionMetadata> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT title, tags, system_prompt, model, tools_enabled, revision, updated_at FROM session_metadata WHERE session_id = ?",
+                [session_id],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(SessionMetadata {
+                session_id: session_id.to_string(),
+                ..Default::default()
+            });
+        };
+        let updated_at = DateTime::parse_from_rfc3339(&row.get::<String>(6)?)
+            .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))?
+            .with_timezone(&Utc);
+        Ok(SessionMetadata {
+            session_id: session_id.to_string(),
+            title: row.get(0)?,
+            tags: serde_json::from_str(&row.get::<String>(1)?)?,
+            system_prompt: row.get(2)?,
+            model: row.get(3)?,
+            tools_enabled: row.get::<i64>(4)? != 0,
+            revision: row.get(5)?,
+            updated_at: Some(updated_at),
+        })
+    }
+
+    /// Applies `update` if the session is still at `expected_revision`, or unconditionally
+    /// when that is `None`
+    pub async fn update(
+        &self,
+        session_id: &str,
+        update: SessionMetadataUpdate,
+        expected_revision: Option<i64>,
+    ) -> Result<UpdateOutcome> {
+        loop {
+            let current = self.get(session_id).await?;
+            if expected_revision.is_some_and(|expected| expected != current.revision) {
+                return Ok(UpdateOutcome::Conflict(current));
+            }
+
+            let updated = SessionMetadata {
+                session_id: session_id.to_string(),
+                title: update.title.clone().unwrap_or(current.title.clone()),
+                tags: update.tags.clone().unwrap_or(current.tags.clone()),
+                system_prompt: update
+                    .system_prompt
+                    .clone()
+                    .unwrap_or(current.system_prompt.clone()),
+                model: update.model.clone().unwrap_or(current.model.clone()),
+                tools_enabled: update.tools_enabled.unwrap_or(current.tools_enabled),
+                revision: current.revision + 1,
+                updated_at: Some(Utc::now()),
+            };
+            if self.write(&updated, current.revision).await? {
+                return Ok(UpdateOutcome::Updated(updated));
+            }
+            // Edited between the read and the write
+            if expected_revision.is_some() {
+                return Ok(UpdateOutcome::Conflict(self.get(session_id).await?));
+            }
+        }
+    }
+
+    /// Owner of a session, `None` until someone uses it
+    pub async fn owner(&self, session_id: &str) -> Result<Option<SessionOwner>> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT api_key, user_id, created_at FROM session_owners WHERE session_id = ?",
+                [session_id],
+            )
+            .await?;
+        let Some(row) = rows.next().await? else {
+            return Ok(None);
+        };
+        let created_at = DateTime::parse_from_rfc3339(&row.get::<String>(2)?)
+            .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))?
+            .with_timezone(&Utc);
+        Ok(Some(SessionOwner {
+            api_key: row.get(0)?,
+            user_id: row.get(1)?,
+            created_at,
+        }))
+    }
+
+    /// Makes `api_key` and `user_id` the owners of a session nobody owns yet, returning
+    /// whoever owns it afterwards
+    pub async fn claim(
+        &self,
+        session_id: &str,
+        api_key: Option<&str>,
+        user_id: Option<&str>,
+    ) -> Result<SessionOwner> {
+        self.conn
+            .execute(
+                "INSERT INTO session_owners (session_id, api_key, user_id, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(session_id) DO NOTHING",
+                libsql::params![session_id, api_key, user_id, Utc::now().to_rfc3339()],
+            )
+            .await?;
+        self.owner(session_id)
+            .await?
+            .ok_or_else(|| Error::internal(format!("Session {session_id} has no owner")))
+    }
+
+    /// The session of a conversation of `integration`, recorded on first use. `None` when
+    /// [`integration_session_id`] rejects the names.
+    pub async fn link_conversation(
+        &self,
+        integration: &str,
+        conversation_id: &str,
+    ) -> Result<Option<IntegrationSession>> {
+        let Some(session_id) = integration_session_id(integration, conversation_id) else {
+            return Ok(None);
+        };
+        self.conn
+            .execute(
+                "INSERT INTO integration_sessions (integration, conversation_id, session_id, created_at) VALUES (?, ?, ?, ?) ON CONFLICT(integration, conversation_id) DO NOTHING",
+                libsql::params![integration, conversation_id, session_id, Utc::now().to_rfc3339()],
+            )
+            .await?;
+        self.conversation(integration, conversation_id).await
+    }
+
+    /// Moves a conversation of `integration` to `session_id`, where its requests continue
+    /// with that session's history. Replies to the session's other channels are relayed
+    /// to `callback_url`. `None` when [`integration_session_id`] rejects the names.
+    pub async fn hand_off(
+        &self,
+        integration: &str,
+        conversation_id: &str,
+        session_id: &str,
+        callback_url: Option<&str>,
+    ) -> Result<Option<IntegrationSession>> {
+        if integration_session_id(integration, conversation_id).is_none() {
+            return Ok(None);
+        }
+        self.conn
+            .execute(
+                "INSERT INTO integration_sessions (integration, conversation_id, session_id, created_at, callback_url) VALUES (?, ?, ?, ?, ?) \
+                 ON CONFLICT(integration, conversation_id) DO UPDATE SET session_id = excluded.session_id, callback_url = excluded.callback_url",
+                libsql::params![
+                    integration,
+                    conversation_id,
+                    session_id,
+                    Utc::now().to_rfc3339(),
+                    callback_url
+                ],
+            )
+            .await?;
+        self.conversation(integration, conversation_id).await
+    }
+
+    /// Detaches a conversation from `session_id`; its next request starts over in its own
+    /// session. Whether it was linked to that session.
+    pub async fn unlink_conversation(
+        &self,
+        integration: &str,
+        conversation_id: &str,
+        session_id: &str,
+    ) -> Result<bool> {
+        let deleted = self
+            .conn
+            .execute(
+                "DELETE FROM integration_sessions WHERE integration = ? AND conversation_id = ? AND session_id = ?",
+                [integration, conversation_id, session_id],
+            )
+            .await?;
+        Ok(deleted > 0)
+    }
+
+    /// Conversations held by `session_id`, oldest first
+    pub async fn session_conversations(&self, session_id: &str) -> Result<Vec<IntegrationSession>> {
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT {INTEGRATION_SESSION_COLUMNS} FROM integration_sessions WHERE session_id = ? ORDER BY created_at, integration, conversation_id"
+                ),
+                [session_id],
+            )
+            .await?;
+        let mut conversations = Vec::new();
+        while let Some(row) = rows.next().await? {
+            conversations.push(integration_session(&row)?);
+        }
+        Ok(conversations)
+    }
+
+    /// The session of a conversation of `integration`, `None` until it is first used
+    pub async fn conversation(
+        &self,
+        integration: &str,
+        conversation_id: &str,
+    ) -> Result<Option<IntegrationSession>> {
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT {INTEGRATION_SESSION_COLUMNS} FROM integration_sessions WHERE integration = ? AND conversation_id = ?"
+                ),
+                [integration, conversation_id],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(Some(integration_session(&row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Conversations of `integration`, oldest first
+    pub async fn conversations(&self, integration: &str) -> Result<Vec<IntegrationSession>> {
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT {INTEGRATION_SESSION_COLUMNS} FROM integration_sessions WHERE integration = ? ORDER BY created_at, conversation_id"
+                ),
+                [integration],
+            )
+            .await?;
+        let mut conversations = Vec::new();
+        while let Some(row) = rows.next().await? {
+            conversations.push(integration_session(&row)?);
+        }
+        Ok(conversations)
+    }
+
+    /// Stores `metadata` if the row is still at `previous` revision
+    async fn write(&self, metadata: &SessionMetadata, previous: i64) -> Result<bool> {
+        let tags = serde_json::to_string(&metadata.tags)?;
+        let updated_at = metadata.updated_at.unwrap_or_else(Utc::now).to_rfc3339();
+        let written = if previous == 0 {
+            self.conn
+                .execute(
+                    "INSERT INTO session_metadata (session_id, title, tags, system_prompt, model, tools_enabled, revision, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?) ON CONFLICT(session_id) DO NOTHING",
+                    libsql::params![
+                        metadata.session_id.as_str(),
+                        metadata.title.as_deref(),
+                        tags,
+                        metadata.system_prompt.as_deref(),
+                        metadata.model.as_deref(),
+                        metadata.tools_enabled,
+                        metadata.revision,
+                        updated_at,
+                    ],
+                )
+                .await?
+        } else {
+            self.conn
+                .execute(
+                    "UPDATE session_metadata SET title = ?, tags = ?, system_prompt = ?, model = ?, tools_enabled = ?, revision = ?, updated_at = ? WHERE session_id = ? AND revision = ?",
+                    libsql::params![
+                        metadata.title.as_deref(),
+                        tags,
+                        metadata.system_prompt.as_deref(),
+                        metadata.model.as_deref(),
+                        metadata.tools_enabled,
+                        metadata.revision,
+                        updated_at,
+                        metadata.session_id.as_str(),
+                        previous,
+                    ],
+                )
+                .await?
+        };
+        Ok(written > 0)
+    }
+}
+
+fn integration_session(row: &libsql::Row) -> Result<IntegrationSession> {
+    let created_at = DateTime::parse_from_rfc3339(&row.get::<String>(3)?)
+        .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))?
+        .with_timezone(&Utc);
+    Ok(IntegrationSession {
+        integration: row.get(0)?,
+        conversation_id: row.get(1)?,
+        session_id: row.get(2)?,
+        callback_url: row.get(4)?,
+        created_at,
+    })
+}
+
+/// Puts a session's own system prompt in place of the configured one
+pub struct SessionPromptHook {
+    store: Arc<SessionStore>,
+}
+
+impl SessionPromptHook {
+    pub fn new(store: Arc<SessionStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AgentHook for SessionPromptHook {
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        let Some(prompt) = self.store.get(&ctx.session_id).await?.system_prompt else {
+            return Ok(());
+        };
+        match request.messages.first_mut() {
+            Some(system) if system.role == "system" => system.content = prompt.into(),
+            _ => request.messages.insert(
+                0,
+                ChatMessage {
+                    role: "system".to_string(),
+                    content: prompt.into(),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    name: None,
+                },
+            ),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use super::SessionStore;
+use crate::{
+    Error, Result,
+    events::{EventSubscriber, SessionEvent, SessionEventKind},
+};
+use async_trait::async_trait;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tracing::warn;
+
+/// How long a callback gets to accept a connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a callback gets to accept a relayed reply
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A conversation of an integration, as `(integration, conversation_id)`
+type Channel = (String, String);
+
+/// Relays each reply of a session to the `callback_url` of every conversation linked to
+/// it, except the one whose request it answers. Subscribed to the session events; replies
+/// are posted in the background so a slow callback holds up no other events.
+pub struct ChannelRelay {
+    store: Arc<SessionStore>,
+    client: reqwest::Client,
+    /// Conversation each session's next request comes from, announced before the run
+    arriving: Mutex<HashMap<String, Channel>>,
+    /// Conversation each session's current run answers, `None` for direct requests
+    answering: Mutex<HashMap<String, Channel>>,
+}
+
+impl ChannelRelay {
+    pub fn new(store: Arc<SessionStore>) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| Error::internal(format!("Failed to create HTTP client: {e}")))?;
+        Ok(Self {
+            store,
+            client,
+            arriving: Mutex::new(HashMap::new()),
+            answering: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+/// Posts `output` to the other channels of `session_id`, returning how many got it
+async fn relay(
+    store: &SessionStore,
+    client: &reqwest::Client,
+    session_id: &str,
+    output: &str,
+    origin: Option<&Channel>,
+) -> Result<usize> {
+    let mut delivered = 0;
+    for channel in store.session_conversations(session_id).await? {
+        let Some(url) = &channel.callback_url else {
+            continue;
+        };
+        if origin.is_some_and(|(integration, conversation_id)| {
+            *integration == channel.integration && *conversation_id == channel.conversation_id
+        }) {
+            continue;
+        }
+        let sent = client
+            .post(url)
+            .json(&json!({
+                "session_id": session_id,
+                "integration": channel.integration,
+                "conversation_id": channel.conversation_id,
+                "output": output,
+            }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match sent {
+            Ok(_) => delivered += 1,
+            Err(e) => warn!(
+                "Failed to relay a reply of session {} to {}:{}: {}",
+                session_id, channel.integration, channel.conversation_id, e
+            ),
+        }
+    }
+    Ok(delivered)
+}
+
+#[async_trait]
+impl EventSubscriber for ChannelRelay {
+    async fn on_event(&self, event: &SessionEvent) {
+        let session_id = &event.session_id;
+        match &event.kind {
+            SessionEventKind::Channel {
+                integration,
+                conversation_id,
+            } => {
+                self.arriving.lock().unwrap().insert(
+                    session_id.clone(),
+                    (integration.clone(), conversation_id.clone()),
+                );
+            }
+            SessionEventKind::Message { role, .. } if role == "user" => {
+                let origin = self.arriving.lock().unwrap().remove(session_id);
+                let mut answering = self.answering.lock().unwrap();
+                match origin {
+                    Some(origin) => answering.insert(session_id.clone(), origin),
+                    None => answering.remove(session_id),
+                };
+            }
+            SessionEventKind::Message { role, content } if role == "assistant" => {
+                let origin = self.answering.lock().unwrap().get(session_id).cloned();
+                let store = self.store.clone();
+                let client = self.client.clone();
+                let session_id = session_id.clone();
+                let output = content.clone();
+                tokio::spawn(async move {
+                    if let Err(e) =
+                        relay(&store, &client, &session_id, &output, origin.as_ref()).await
+                    {
+                        warn!("Failed to relay a reply of session {}: {}", session_id, e);
+                    }
+                });
+            }
+            // Also forgets announcements of runs that failed before their request was seen
+            SessionEventKind::RunCompleted | SessionEventKind::RunFailed { .. } => {
+                self.arriving.lock().unwrap().remove(session_id);
+                self.answering.lock().unwrap().remove(session_id);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Persistent task/TODO list the agent manages on the user's behalf.
+
+use crate::{Error, Result, config::DatabaseConfig, db};
+use chrono::{DateTime, Utc};
+use libsql::Connection;
+use serde::{Deserialize, Serialize};
+use std::hash::{DefaultHasher, Hasher};
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Open,
+    Done,
+}
+
+impl TaskStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Open => "open",
+            TaskStatus::Done => "done",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "open" => Ok(TaskStatus::Open),
+            "done" => Ok(TaskStatus::Done),
+            other => Err(Error::internal(format!("Unknown task status '{other}'"))),
+        }
+    }
+
+    /// Parses a list filter: `open`, `done` or `all` (`None`)
+    pub fn parse_filter(value: &str) -> Result<Option<Self>> {
+        match value {
+            "all" => Ok(None),
+            "open" | "done" => Self::parse(value).map(Some),
+            other => Err(Error::tool(format!(
+                "Unknown status '{other}', expected open, done or all"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    pub id: i64,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<DateTime<Utc>>,
+    pub status: TaskStatus,
+    /// Session the task was created in
+    pub session_id: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewTask {
+    pub title: String,
+    pub notes: Option<String>,
+    pub due: Option<DateTime<Utc>>,
+    pub session_id: String,
+}
+
+fn parse_time(value: String) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| Error::internal(format!("Failed to parse timestamp: {e}")))
+}
+
+const TASK_COLUMNS: &str = "id, title, notes, due_at, status, session_id, created_at, completed_at";
+
+/// Tasks with the status `?1` (all when NULL); open tasks by due date, then creation
+fn list_query() -> String {
+    format!(
+        "SELECT {TASK_COLUMNS} FROM tasks WHERE (?1 IS NULL OR status = ?1) \
+         ORDER BY status = 'done', due_at IS NULL, due_at, id"
+    )
+}
+
+pub struct TaskStore {
+    conn: Connection,
+}
+
+impl TaskStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default()).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(db_path: &str, database: &DatabaseConfig) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS tasks (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                title TEXT NOT NULL,
+                notes TEXT,
+                due_at DATETIME,
+                status TEXT NOT NULL,
+                session_id TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                completed_at DATETIME
+            )
+            "#,
+            (),
+        )
+        .await?;
+        info!("Task store initialized: {}", db_path);
+        Ok(Self { conn })
+    }
+
+    pub async fn create(&self, task: NewTask) -> Result<Task> {
+        let created_at = Utc::now();
+        self.conn
+            .execute(
+                "INSERT INTO tasks (title, notes, due_at, status, session_id, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    task.title.as_str(),
+                    task.notes.clone(),
+                    task.due.map(|d| d.to_rfc3339()),
+                    TaskStatus::Open.as_str(),
+                    task.session_id.as_str(),
+                    created_at.to_rfc3339(),
+                ],
+            )
+            .await?;
+        Ok(Task {
+            id: self.conn.last_insert_rowid(),
+            title: task.title,
+            notes: task.notes,
+            due: task.due,
+            status: TaskStatus::Open,
+            session_id: task.session_id,
+            created_at,
+            completed_at: None,
+        })
+    }
+
+    pub async fn get(&self, id: i64) -> Result<Option<Task>> {
+        let mut tasks = self
+            .query(
+                &format!("SELECT {TASK_COLUMNS} FROM tasks WHERE id = ?"),
+                libsql::params![id],
+            )
+            .await?;
+        Ok(tasks.pop())
+    }
+
+    /// Tasks with the given status (all when `None`); open tasks by due date, then creation
+    pub async fn list(&self, status: Option<TaskStatus>) -> Result<Vec<Task>> {
+        self.query(&list_query(), libsql::params![status.map(|s| s.as_str())])
+            .await
+    }
+
+    /// A hash of what `list(status)` returns, changing whenever a task it lists does
+    pub async fn fingerprint(&self, status: Option<TaskStatus>) -> Result<String> {
+        let rows = self
+            .conn
+            .query(&list_query(), libsql::params![status.map(|s| s.as_str())])
+            .await?;
+        let mut hasher = DefaultHasher::new();
+        db::hash_rows(rows, &mut hasher).await?;
+        Ok(format!("{:016x}", hasher.finish()))
+    }
+
+    /// Marks a task done, returning it, or `None` when there is no such task
+    pub async fn complete(&self, id: i64) -> Result<Option<Task>> {
+        self.conn
+            .execute(
+                "UPDATE tasks SET status = ?, completed_at = ? WHERE id = ? AND status = ?",
+                libsql::params![
+                    TaskStatus::Done.as_str(),
+                    Utc::now().to_rfc3339(),
+                    id,
+                    TaskStatus::Open.as_str(),
+                ],
+            )
+            .await?;
+        self.get(id).await
+    }
+
+    async fn query(&self, sql: &str, params: impl libsql::params::IntoParams) -> Result<Vec<Task>> {
+        let mut rows = self.conn.query(sql, params).await?;
+        let mut tasks = Vec::new();
+        while let Some(row) = rows.next().await? {
+            let due: Option<String> = row.get(3)?;
+            let status: String = row.get(4)?;
+            let completed_at: Option<String> = row.get(7)?;
+            tasks.push(Task {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                notes: row.get(2)?,
+                due: due.map(parse_time).transpose()?,
+                status: TaskStatus::parse(&status)?,
+                session_id: row.get(5)?,
+                created_at: parse_time(row.get(6)?)?,
+                completed_at: completed_at.map(parse_time).transpose()?,
+            });
+        }
+        Ok(tasks)
+    }
+}
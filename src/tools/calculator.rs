@@ -0,0 +1,112 @@
+use super::{NativeTool, ToolContext, required_str, text_result};
+use crate::{Error, Result, mcp::McpTool, mcp::McpToolCallResponse};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+/// Longest expression accepted, to bound parse time
+const MAX_EXPRESSION_LEN: usize = 1000;
+
+/// Evaluates a mathematical expression with optional named variables.
+///
+/// Besides fasteval's builtins (`abs`, `round`, `log`, `min`, `max`, trigonometry, `pi()`,
+/// `e()`...), `sqrt`, `cbrt`, `ln` and `exp` are available.
+pub fn evaluate(expression: &str, variables: &HashMap<String, f64>) -> Result<f64> {
+    if expression.len() > MAX_EXPRESSION_LEN {
+        return Err(Error::tool(format!(
+            "Expression is longer than {MAX_EXPRESSION_LEN} characters"
+        )));
+    }
+    // fasteval's print() writes to stderr; it has no place in a tool call
+    if expression.contains("print") {
+        return Err(Error::tool("print() is not supported"));
+    }
+
+    let mut namespace = |name: &str, args: Vec<f64>| -> Option<f64> {
+        match (name, args.as_slice()) {
+            ("sqrt", [x]) => Some(x.sqrt()),
+            ("cbrt", [x]) => Some(x.cbrt()),
+            ("ln", [x]) => Some(x.ln()),
+            ("exp", [x]) => Some(x.exp()),
+            (_, []) => variables.get(name).copied(),
+            _ => None,
+        }
+    };
+
+    let result = fasteval::ez_eval(expression, &mut namespace)
+        .map_err(|e| Error::tool(format!("Invalid expression '{expression}': {e:?}")))?;
+
+    if !result.is_finite() {
+        return Err(Error::tool(format!(
+            "Expression '{expression}' does not have a finite result"
+        )));
+    }
+    Ok(result)
+}
+
+/// Formats a result without floating point noise such as `0.30000000000000004`
+fn format_result(value: f64) -> String {
+    let rounded = format!("{value:.10}");
+    let trimmed = rounded.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Native `calculate` tool
+pub struct CalculatorTool;
+
+#[async_trait]
+impl NativeTool for CalculatorTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "calculate".to_string(),
+            description: "Evaluate a mathematical expression exactly. Supports + - * / % ^, \
+                parentheses, comparisons, abs, round(modulus, x), floor, ceil, min, max, \
+                log(base, x), ln, exp, sqrt, cbrt, trigonometric functions, pi() and e(). \
+                Always use this for arithmetic instead of computing in your head."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "expression": {"type": "string", "description": "Expression to evaluate, e.g. (12.5 * 4) / 3"},
+                    "variables": {
+                        "type": "object",
+                        "description": "Optional named values referenced in the expression",
+                        "additionalProperties": {"type": "number"}
+                    }
+                },
+                "required": ["expression"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let expression = required_str(&arguments, "expression")?;
+        let variables = match arguments.get("variables") {
+            Some(Value::Object(map)) => {
+                map.iter()
+                    .map(|(name, value)| {
+                        value.as_f64().map(|v| (name.clone(), v)).ok_or_else(|| {
+                            Error::tool(format!("Variable '{name}' must be a number"))
+                        })
+                    })
+                    .collect::<Result<HashMap<_, _>>>()?
+            }
+            _ => HashMap::new(),
+        };
+
+        let result = evaluate(expression, &variables)?;
+        Ok(text_result(format!(
+            "{expression} = {}",
+            format_result(result)
+        )))
+    }
+}
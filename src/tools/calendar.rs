@@ -0,0 +1,712 @@
+use super::{
+    NativeTool, ToolContext,
+    datetime::{DateTimeSettings, parse_datetime, parse_timezone},
+    optional_f64, optional_str, required_str, text_result,
+};
+use crate::{
+    Error, Result,
+    config::{CalendarProvider, CalendarToolConfig},
+    mcp::McpTool,
+    mcp::McpToolCallResponse,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Window listed when the model does not give an end
+const DEFAULT_LIST_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub all_day: bool,
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewEvent {
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub location: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Calendar service behind the `list_events` and `create_event` tools
+#[async_trait]
+pub trait CalendarBackend: Send + Sync {
+    /// Events overlapping `[start, end)`, ordered by start time
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>>;
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent>;
+}
+
+pub fn create_calendar_backend(
+    config: &CalendarToolConfig,
+) -> Result<Option<Arc<dyn CalendarBackend>>> {
+    let backend: Arc<dyn CalendarBackend> = match config.provider {
+        CalendarProvider::None => return Ok(None),
+        CalendarProvider::Caldav => {
+            let url = config
+                .url
+                .clone()
+                .ok_or_else(|| Error::config("tools.calendar.url is required for CalDAV"))?;
+            Arc::new(CalDavBackend::new(
+                url,
+                config.username.clone(),
+                config.password.clone(),
+            ))
+        }
+        CalendarProvider::Google => {
+            let required = |value: &Option<String>, name: &str| {
+                value.clone().ok_or_else(|| {
+                    Error::config(format!(
+                        "tools.calendar.{name} is required for Google Calendar"
+                    ))
+                })
+            };
+            Arc::new(GoogleCalendarBackend::new(
+                GoogleCredentials {
+                    client_id: required(&config.client_id, "client_id")?,
+                    client_secret: required(&config.client_secret, "client_secret")?,
+                    refresh_token: required(&config.refresh_token, "refresh_token")?,
+                },
+                config.calendar_id.clone(),
+                config.url.clone(),
+            ))
+        }
+    };
+    Ok(Some(backend))
+}
+
+/// CalDAV calendar collection (Nextcloud, Radicale, iCloud, Fastmail...)
+pub struct CalDavBackend {
+    client: reqwest::Client,
+    calendar_url: String,
+    username: Option<String>,
+    password: Option<String>,
+}
+
+impl CalDavBackend {
+    pub fn new(calendar_url: String, username: Option<String>, password: Option<String>) -> Self {
+        let calendar_url = format!("{}/", calendar_url.trim_end_matches('/'));
+        Self {
+            client: reqwest::Client::new(),
+            calendar_url,
+            username,
+            password,
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, url);
+        match &self.username {
+            Some(username) => builder.basic_auth(username, self.password.as_ref()),
+            None => builder,
+        }
+    }
+}
+
+fn ical_utc(dt: &DateTime<Utc>) -> String {
+    dt.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+#[async_trait]
+impl CalendarBackend for CalDavBackend {
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>> {
+        let body = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:prop><c:calendar-data/></d:prop>
+  <c:filter>
+    <c:comp-filter name="VCALENDAR">
+      <c:comp-filter name="VEVENT">
+        <c:time-range start="{}" end="{}"/>
+      </c:comp-filter>
+    </c:comp-filter>
+  </c:filter>
+</c:calendar-query>"#,
+            ical_utc(&start),
+            ical_utc(&end)
+        );
+        let method = reqwest::Method::from_bytes(b"REPORT").expect("valid HTTP method");
+        let response = self
+            .request(method, &self.calendar_url)
+            .header("Depth", "1")
+            .header("Content-Type", "application/xml; charset=utf-8")
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        let mut events: Vec<CalendarEvent> = extract_calendar_data(&response)
+            .iter()
+            .flat_map(|ics| parse_ics_events(ics))
+            .filter(|event| event.start < end && event.end > start)
+            .collect();
+        events.sort_by_key(|event| event.start);
+        Ok(events)
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+        let uid = format!("{}@jarvis", uuid::Uuid::new_v4());
+        let url = format!("{}{}.ics", self.calendar_url, uid.replace('@', "-"));
+        self.request(reqwest::Method::PUT, &url)
+            .header("Content-Type", "text/calendar; charset=utf-8")
+            .header("If-None-Match", "*")
+            .body(build_ics(&uid, event, Utc::now()))
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(CalendarEvent {
+            uid,
+            title: event.title.clone(),
+            start: event.start,
+            end: event.end,
+            all_day: false,
+            location: event.location.clone(),
+        })
+    }
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#13;", "\r")
+        .replace("&#xD;", "\r")
+        .replace("&amp;", "&")
+}
+
+/// Pulls the iCalendar payloads out of a CalDAV multistatus response, whatever namespace
+/// prefix the server uses
+pub fn extract_calendar_data(xml: &str) -> Vec<String> {
+    let mut payloads = Vec::new();
+    let mut rest = xml;
+    while let Some(name_pos) = rest.find("calendar-data") {
+        let tag_start = rest[..name_pos].rfind('<').unwrap_or(0);
+        let after_name = &rest[name_pos + "calendar-data".len()..];
+        let Some(open_end) = after_name.find('>') else {
+            break;
+        };
+        // Skip closing and self-closing tags
+        let closing = rest[tag_start..].starts_with("</");
+        let self_closing = after_name[..open_end].ends_with('/');
+        let content = &after_name[open_end + 1..];
+        if closing || self_closing {
+            rest = content;
+            continue;
+        }
+        let Some(close) = content.find("</") else {
+            break;
+        };
+        let payload = content[..close].trim();
+        let payload = payload
+            .strip_prefix("<![CDATA[")
+            .and_then(|p| p.strip_suffix("]]>"))
+            .map_or_else(|| unescape_xml(payload), String::from);
+        if !payload.is_empty() {
+            payloads.push(payload);
+        }
+        rest = &content[close..];
+    }
+    payloads
+}
+
+/// Unfolds continuation lines (RFC 5545 §3.1)
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape_text(value: &str) -> String {
+    value
+        .replace("\\n", "\n")
+        .replace("\\N", "\n")
+        .replace("\\,", ",")
+        .replace("\\;", ";")
+        .replace("\\\\", "\\")
+}
+
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Parses a DTSTART/DTEND value, returning the instant and whether it is an all-day date
+fn parse_ical_datetime(params: &str, value: &str) -> Option<(DateTime<Utc>, bool)> {
+    if params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some((Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)), true));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some((Utc.from_utc_datetime(&naive), false));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let tz: Tz = params
+        .split(';')
+        .find_map(|p| p.strip_prefix("TZID="))
+        .and_then(|tzid| tzid.trim_matches('"').parse().ok())
+        .unwrap_or(Tz::UTC);
+    let local = tz.from_local_datetime(&naive).earliest()?;
+    Some((local.with_timezone(&Utc), false))
+}
+
+/// Parses the VEVENTs of an iCalendar document. Recurrence rules are not expanded.
+pub fn parse_ics_events(ics: &str) -> Vec<CalendarEvent> {
+    let mut events = Vec::new();
+    let mut current: Option<HashMap<String, (String, String)>> = None;
+
+    for line in unfold(ics) {
+        match line.as_str() {
+            "BEGIN:VEVENT" => current = Some(HashMap::new()),
+            "END:VEVENT" => {
+                let Some(props) = current.take() else {
+                    continue;
+                };
+                let Some((start, all_day)) = props
+                    .get("DTSTART")
+                    .and_then(|(params, value)| parse_ical_datetime(params, value))
+                else {
+                    continue;
+                };
+                let end = match props
+                    .get("DTEND")
+                    .and_then(|(params, value)| parse_ical_datetime(params, value))
+                {
+                    Some((end, _)) => end,
+                    None if all_day => {
+                        // Skipped like a bad DTSTART when the day after can't be represented
+                        let Some(end) = start.checked_add_signed(Duration::days(1)) else {
+                            continue;
+                        };
+                        end
+                    }
+                    None => start,
+                };
+                let text = |name: &str| props.get(name).map(|(_, v)| unescape_text(v));
+                events.push(CalendarEvent {
+                    uid: text("UID").unwrap_or_default(),
+                    title: text("SUMMARY").unwrap_or_else(|| "(untitled)".to_string()),
+                    start,
+                    end,
+                    all_day,
+                    location: text("LOCATION").filter(|l| !l.is_empty()),
+                });
+            }
+            _ => {
+                let Some(props) = current.as_mut() else {
+                    continue;
+                };
+                let Some((name_params, value)) = line.split_once(':') else {
+                    continue;
+                };
+                let (name, params) = name_params.split_once(';').unwrap_or((name_params, ""));
+                props
+                    .entry(name.to_uppercase())
+                    .or_insert_with(|| (params.to_string(), value.to_string()));
+            }
+        }
+    }
+    events
+}
+
+/// Serializes a new event as an iCalendar document
+pub fn build_ics(uid: &str, event: &NewEvent, now: DateTime<Utc>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//jarvis-rust//EN".to_string(),
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{uid}"),
+        format!("DTSTAMP:{}", ical_utc(&now)),
+        format!("DTSTART:{}", ical_utc(&event.start)),
+        format!("DTEND:{}", ical_utc(&event.end)),
+        format!("SUMMARY:{}", escape_text(&event.title)),
+    ];
+    if let Some(location) = &event.location {
+        lines.push(format!("LOCATION:{}", escape_text(location)));
+    }
+    if let Some(description) = &event.description {
+        lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines.push("END:VCALENDAR".to_string());
+    lines.join("\r\n") + "\r\n"
+}
+
+/// OAuth client and refresh token for the Google Calendar API
+#[derive(Debug, Clone)]
+pub struct GoogleCredentials {
+    pub client_id: String,
+    pub client_secret: String,
+    pub refresh_token: String,
+}
+
+/// Google Calendar API v3. Access tokens are obtained from the refresh token on every call.
+pub struct GoogleCalendarBackend {
+    client: reqwest::Client,
+    credentials: GoogleCredentials,
+    calendar_id: String,
+    api_url: String,
+    token_url: String,
+}
+
+#[derive(Deserialize)]
+struct GoogleToken {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct GoogleEvents {
+    #[serde(default)]
+    items: Vec<GoogleEvent>,
+}
+
+#[derive(Deserialize)]
+struct GoogleEvent {
+    id: String,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+    start: GoogleTime,
+    end: GoogleTime,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleTime {
+    #[serde(default)]
+    date_time: Option<DateTime<chrono::FixedOffset>>,
+    #[serde(default)]
+    date: Option<NaiveDate>,
+}
+
+impl GoogleTime {
+    fn resolve(&self) -> Option<(DateTime<Utc>, bool)> {
+        match (self.date_time, self.date) {
+            (Some(dt), _) => Some((dt.with_timezone(&Utc), false)),
+            (None, Some(date)) => {
+                Some((Utc.from_utc_datetime(&date.and_time(NaiveTime::MIN)), true))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl GoogleEvent {
+    fn into_event(self) -> Option<CalendarEvent> {
+        let (start, all_day) = self.start.resolve()?;
+        let (end, _) = self.end.resolve()?;
+        Some(CalendarEvent {
+            uid: self.id,
+            title: self.summary.unwrap_or_else(|| "(untitled)".to_string()),
+            start,
+            end,
+            all_day,
+            location: self.location,
+        })
+    }
+}
+
+impl GoogleCalendarBackend {
+    /// `base_url` overrides both the API and token endpoints (used in tests)
+    pub fn new(
+        credentials: GoogleCredentials,
+        calendar_id: String,
+        base_url: Option<String>,
+    ) -> Self {
+        let (api_url, token_url) = match base_url {
+            Some(base) => {
+                let base = base.trim_end_matches('/').to_string();
+                (format!("{base}/calendar/v3"), format!("{base}/token"))
+            }
+            None => (
+                "https://www.googleapis.com/calendar/v3".to_string(),
+                "https://oauth2.googleapis.com/token".to_string(),
+            ),
+        };
+        Self {
+            client: reqwest::Client::new(),
+            credentials,
+            calendar_id,
+            api_url,
+            token_url,
+        }
+    }
+
+    async fn access_token(&self) -> Result<String> {
+        let token: GoogleToken = self
+            .client
+            .post(&self.token_url)
+            .form(&[
+                ("client_id", self.credentials.client_id.as_str()),
+                ("client_secret", self.credentials.client_secret.as_str()),
+                ("refresh_token", self.credentials.refresh_token.as_str()),
+                ("grant_type", "refresh_token"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(token.access_token)
+    }
+
+    fn events_url(&self) -> String {
+        format!("{}/calendars/{}/events", self.api_url, self.calendar_id)
+    }
+}
+
+#[async_trait]
+impl CalendarBackend for GoogleCalendarBackend {
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>> {
+        let events: GoogleEvents = self
+            .client
+            .get(self.events_url())
+            .bearer_auth(self.access_token().await?)
+            .query(&[
+                ("timeMin", start.to_rfc3339()),
+                ("timeMax", end.to_rfc3339()),
+                ("singleEvents", "true".to_string()),
+                ("orderBy", "startTime".to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(events
+            .items
+            .into_iter()
+            .filter_map(GoogleEvent::into_event)
+            .collect())
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+        let created: GoogleEvent = self
+            .client
+            .post(self.events_url())
+            .bearer_auth(self.access_token().await?)
+            .json(&json!({
+                "summary": event.title,
+                "location": event.location,
+                "description": event.description,
+                "start": {"dateTime": event.start.to_rfc3339()},
+                "end": {"dateTime": event.end.to_rfc3339()},
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        created
+            .into_event()
+            .ok_or_else(|| Error::tool("Google Calendar returned an event without times"))
+    }
+}
+
+/// Formats an event in `tz`, e.g. `Fri 2026-10-16 09:00–10:00 Dentist @ Main St`
+pub fn describe_event(event: &CalendarEvent, tz: Tz) -> String {
+    let start = event.start.with_timezone(&tz);
+    let end = event.end.with_timezone(&tz);
+    let when = if event.all_day {
+        format!("{} (all day)", event.start.format("%a %Y-%m-%d"))
+    } else if start.date_naive() == end.date_naive() {
+        format!(
+            "{}–{}",
+            start.format("%a %Y-%m-%d %H:%M"),
+            end.format("%H:%M")
+        )
+    } else {
+        format!(
+            "{} – {}",
+            start.format("%a %Y-%m-%d %H:%M"),
+            end.format("%a %Y-%m-%d %H:%M")
+        )
+    };
+    match &event.location {
+        Some(location) => format!("{when} {} @ {location}", event.title),
+        None => format!("{when} {}", event.title),
+    }
+}
+
+fn timezone_arg(args: &HashMap<String, Value>, default: Tz) -> Result<Tz> {
+    optional_str(args, "timezone").map_or(Ok(default), parse_timezone)
+}
+
+/// Native `list_events` tool
+pub struct ListEventsTool {
+    backend: Arc<dyn CalendarBackend>,
+    settings: DateTimeSettings,
+}
+
+impl ListEventsTool {
+    pub fn new(backend: Arc<dyn CalendarBackend>, settings: DateTimeSettings) -> Self {
+        Self { backend, settings }
+    }
+}
+
+#[async_trait]
+impl NativeTool for ListEventsTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "list_events".to_string(),
+            description: "List calendar events between two dates/times.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "start": {"type": "string", "description": "Start as RFC 3339, 'YYYY-MM-DD HH:MM' or 'YYYY-MM-DD'. Defaults to now."},
+                    "end": {"type": "string", "description": "End of the range. Defaults to 7 days after start."},
+                    "timezone": {"type": "string", "description": "IANA timezone for input and output. Defaults to the configured timezone."}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let tz = timezone_arg(&arguments, self.settings.timezone())?;
+        let start = match optional_str(&arguments, "start") {
+            Some(start) => parse_datetime(start, tz)?.with_timezone(&Utc),
+            None => self.settings.now_utc(),
+        };
+        let end = match optional_str(&arguments, "end") {
+            Some(end) => parse_datetime(end, tz)?.with_timezone(&Utc),
+            None => Duration::try_days(DEFAULT_LIST_DAYS)
+                .and_then(|window| start.checked_add_signed(window))
+                .ok_or_else(|| Error::tool("'start' is out of range"))?,
+        };
+        if end <= start {
+            return Err(Error::tool("'end' must be after 'start'"));
+        }
+
+        let events = self.backend.list_events(start, end).await?;
+        if events.is_empty() {
+            return Ok(text_result("No events in this period"));
+        }
+        Ok(text_result(
+            events
+                .iter()
+                .map(|event| describe_event(event, tz))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+}
+
+/// Native `create_event` tool. Requires approval unless overridden in `tools.settings`.
+pub struct CreateEventTool {
+    backend: Arc<dyn CalendarBackend>,
+    settings: DateTimeSettings,
+}
+
+impl CreateEventTool {
+    pub fn new(backend: Arc<dyn CalendarBackend>, settings: DateTimeSettings) -> Self {
+        Self { backend, settings }
+    }
+}
+
+#[async_trait]
+impl NativeTool for CreateEventTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "create_event".to_string(),
+            description: "Create a calendar event.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "start": {"type": "string", "description": "Start as RFC 3339 or 'YYYY-MM-DD HH:MM'"},
+                    "end": {"type": "string", "description": "End; alternatively use duration_minutes"},
+                    "duration_minutes": {"type": "number", "description": "Length of the event. Defaults to 60."},
+                    "location": {"type": "string"},
+                    "description": {"type": "string"},
+                    "timezone": {"type": "string", "description": "IANA timezone of start/end. Defaults to the configured timezone."}
+                },
+                "required": ["title", "start"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    fn requires_approval(&self) -> bool {
+        true
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let tz = timezone_arg(&arguments, self.settings.timezone())?;
+        let start = parse_datetime(required_str(&arguments, "start")?, tz)?;
+        let end = match optional_str(&arguments, "end") {
+            Some(end) => parse_datetime(end, tz)?,
+            None => {
+                // The length comes from the LLM, so it may reach past what dates can hold
+                let minutes = optional_f64(&arguments, "duration_minutes").unwrap_or(60.0);
+                Duration::try_minutes(minutes.round() as i64)
+                    .and_then(|length| start.checked_add_signed(length))
+                    .ok_or_else(|| Error::tool("duration_minutes out of range"))?
+            }
+        };
+        if end <= start {
+            return Err(Error::tool("The event must end after it starts"));
+        }
+
+        let event = NewEvent {
+            title: required_str(&arguments, "title")?.to_string(),
+            start: start.with_timezone(&Utc),
+            end: end.with_timezone(&Utc),
+            location: optional_str(&arguments, "location").map(String::from),
+            description: optional_str(&arguments, "description").map(String::from),
+        };
+        let created = self.backend.create_event(&event).await?;
+        Ok(text_result(format!(
+            "Created event: {}",
+            describe_event(&created, tz)
+        )))
+    }
+}
@@ -0,0 +1,110 @@
+use crate::{
+    Error, Result,
+    config::{CurrencyConfig, CurrencyProvider},
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use std::{collections::HashMap, sync::Arc};
+
+/// Source of currency exchange rates used by `convert_units`
+#[async_trait]
+pub trait RateSource: Send + Sync {
+    /// Returns how many units of `to` one unit of `from` buys
+    async fn rate(&self, from: &str, to: &str) -> Result<f64>;
+}
+
+pub fn create_rate_source(config: &CurrencyConfig) -> Option<Arc<dyn RateSource>> {
+    match config.provider {
+        CurrencyProvider::None => None,
+        CurrencyProvider::Static => Some(Arc::new(StaticRateSource::new(
+            config.base.clone(),
+            config.rates.clone(),
+        ))),
+        CurrencyProvider::Frankfurter => Some(Arc::new(FrankfurterRateSource::new(
+            config.base_url.clone(),
+        ))),
+    }
+}
+
+/// Fixed rates from configuration, expressed as units of each currency per unit of `base`
+pub struct StaticRateSource {
+    base: String,
+    rates: HashMap<String, f64>,
+}
+
+impl StaticRateSource {
+    pub fn new(base: String, rates: HashMap<String, f64>) -> Self {
+        let rates = rates
+            .into_iter()
+            .map(|(code, rate)| (code.to_uppercase(), rate))
+            .collect();
+        Self {
+            base: base.to_uppercase(),
+            rates,
+        }
+    }
+
+    fn per_base(&self, code: &str) -> Result<f64> {
+        if code == self.base {
+            return Ok(1.0);
+        }
+        self.rates
+            .get(code)
+            .copied()
+            .ok_or_else(|| Error::tool(format!("No exchange rate configured for {code}")))
+    }
+}
+
+#[async_trait]
+impl RateSource for StaticRateSource {
+    async fn rate(&self, from: &str, to: &str) -> Result<f64> {
+        Ok(self.per_base(to)? / self.per_base(from)?)
+    }
+}
+
+/// Live ECB reference rates from a Frankfurter API instance (https://frankfurter.dev)
+pub struct FrankfurterRateSource {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct FrankfurterResponse {
+    rates: HashMap<String, f64>,
+}
+
+impl FrankfurterRateSource {
+    pub fn new(base_url: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url
+                .unwrap_or_else(|| "https://api.frankfurter.app".to_string())
+                .trim_end_matches('/')
+                .to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl RateSource for FrankfurterRateSource {
+    async fn rate(&self, from: &str, to: &str) -> Result<f64> {
+        if from == to {
+            return Ok(1.0);
+        }
+        let response: FrankfurterResponse = self
+            .client
+            .get(format!("{}/latest", self.base_url))
+            .query(&[("from", from), ("to", to)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        response
+            .rates
+            .get(to)
+            .copied()
+            .ok_or_else(|| Error::tool(format!("No exchange rate available for {from} -> {to}")))
+    }
+}
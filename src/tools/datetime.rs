@@ -0,0 +1,353 @@
+use super::{NativeTool, ToolContext, optional_str, required_f64, required_str, text_result};
+use crate::{Error, Result, mcp::McpTool, mcp::McpToolCallResponse};
+use async_trait::async_trait;
+use chrono::{
+    DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc,
+    Weekday,
+};
+use chrono_tz::Tz;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+/// Source of the current instant, replaceable for deterministic tests
+pub type Clock = fn() -> DateTime<Utc>;
+
+/// Shared state of the datetime tools: default timezone and clock
+#[derive(Clone, Copy)]
+pub struct DateTimeSettings {
+    timezone: Tz,
+    clock: Clock,
+}
+
+impl DateTimeSettings {
+    pub fn new(timezone: Tz) -> Self {
+        Self {
+            timezone,
+            clock: Utc::now,
+        }
+    }
+
+    /// Builds settings from a configured IANA timezone name, using UTC when unset
+    pub fn from_timezone_name(name: Option<&str>) -> Result<Self> {
+        match name {
+            Some(name) => Ok(Self::new(parse_timezone(name)?)),
+            None => Ok(Self::new(Tz::UTC)),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Timezone used when a call does not specify one
+    pub fn timezone(&self) -> Tz {
+        self.timezone
+    }
+
+    pub fn now_utc(&self) -> DateTime<Utc> {
+        (self.clock)()
+    }
+
+    fn now(&self, tz: Tz) -> DateTime<Tz> {
+        (self.clock)().with_timezone(&tz)
+    }
+
+    fn timezone_arg(&self, args: &HashMap<String, Value>) -> Result<Tz> {
+        optional_str(args, "timezone").map_or(Ok(self.timezone), parse_timezone)
+    }
+
+    /// Reads a datetime argument, defaulting to now
+    fn datetime_arg(
+        &self,
+        args: &HashMap<String, Value>,
+        key: &str,
+        tz: Tz,
+    ) -> Result<DateTime<Tz>> {
+        match optional_str(args, key) {
+            Some(value) => parse_datetime(value, tz),
+            None => Ok(self.now(tz)),
+        }
+    }
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.trim().parse().map_err(|_| {
+        Error::tool(format!(
+            "Unknown timezone '{name}', expected an IANA name such as Europe/Berlin"
+        ))
+    })
+}
+
+/// Parses RFC 3339, `YYYY-MM-DD HH:MM[:SS]` or `YYYY-MM-DD` (midnight), the latter two in `tz`
+pub fn parse_datetime(value: &str, tz: Tz) -> Result<DateTime<Tz>> {
+    let value = value.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&tz));
+    }
+    let naive = [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(NaiveTime::MIN))
+    })
+    .ok_or_else(|| Error::tool(format!("Unrecognized datetime '{value}'")))?;
+    resolve_local(naive, tz)
+}
+
+/// Maps a wall-clock time to an instant. Ambiguous times take the earlier offset and times
+/// skipped by a DST transition move forward by the gap.
+fn resolve_local(naive: NaiveDateTime, tz: Tz) -> Result<DateTime<Tz>> {
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .ok_or_else(|| Error::tool(format!("{naive} does not exist in {tz}")))
+}
+
+/// Formats as RFC 3339 followed by the weekday and timezone, e.g.
+/// `2026-10-16T09:00:00+02:00 (Friday, Europe/Berlin)`
+pub fn describe(dt: &DateTime<Tz>) -> String {
+    format!(
+        "{} ({}, {})",
+        dt.to_rfc3339_opts(chrono::SecondsFormat::Secs, false),
+        dt.format("%A"),
+        dt.timezone()
+    )
+}
+
+/// Adds `amount` of `unit` to `start`. Minutes and hours are exact durations; days and
+/// larger units follow the calendar, keeping the wall-clock time across DST changes.
+pub fn add_duration(start: DateTime<Tz>, amount: f64, unit: &str) -> Result<DateTime<Tz>> {
+    let unit = unit.trim().to_lowercase();
+    let unit = unit.strip_suffix('s').unwrap_or(&unit);
+    let exact = |seconds: f64| -> Result<DateTime<Tz>> {
+        Duration::try_milliseconds((amount * seconds * 1000.0).round() as i64)
+            .and_then(|d| start.checked_add_signed(d))
+            .ok_or_else(|| Error::tool("Resulting datetime is out of range"))
+    };
+    match unit {
+        "second" | "sec" => exact(1.0),
+        "minute" | "min" => exact(60.0),
+        "hour" | "hr" => exact(3600.0),
+        "day" | "week" | "month" | "year" => {
+            if amount.fract() != 0.0 {
+                return Err(Error::tool(format!(
+                    "Amount for {unit}s must be a whole number"
+                )));
+            }
+            // The amount comes from the LLM, so any of it may be out of range
+            let out_of_range = || Error::tool("amount out of range");
+            let amount = amount as i64;
+            let local = start.naive_local();
+            let shifted = match unit {
+                "day" | "week" => {
+                    let delta = if unit == "day" {
+                        Duration::try_days(amount)
+                    } else {
+                        Duration::try_weeks(amount)
+                    };
+                    local.checked_add_signed(delta.ok_or_else(out_of_range)?)
+                }
+                _ => {
+                    let months = if unit == "year" {
+                        amount.checked_mul(12).ok_or_else(out_of_range)?
+                    } else {
+                        amount
+                    };
+                    let delta = Months::new(
+                        u32::try_from(months.unsigned_abs()).map_err(|_| out_of_range())?,
+                    );
+                    if months >= 0 {
+                        local.checked_add_months(delta)
+                    } else {
+                        local.checked_sub_months(delta)
+                    }
+                }
+            }
+            .ok_or_else(|| Error::tool("Resulting datetime is out of range"))?;
+            resolve_local(shifted, start.timezone())
+        }
+        other => Err(Error::tool(format!(
+            "Unsupported unit '{other}', expected seconds, minutes, hours, days, weeks, months or years"
+        ))),
+    }
+}
+
+/// What `next_occurrence` looks for
+#[derive(Debug, Clone, Copy)]
+pub enum Recurrence {
+    Daily,
+    Weekday(Weekday),
+    DayOfMonth(u32),
+}
+
+/// Finds the first occurrence of `recurrence` at `time` strictly after `after`
+pub fn next_occurrence(
+    after: DateTime<Tz>,
+    recurrence: Recurrence,
+    time: NaiveTime,
+) -> Result<DateTime<Tz>> {
+    let tz = after.timezone();
+    let start = after.date_naive();
+    // Two years covers every day of month, including the 29th of February
+    for offset in 0..=800 {
+        let date = start + Duration::days(offset);
+        let matches = match recurrence {
+            Recurrence::Daily => true,
+            Recurrence::Weekday(weekday) => date.weekday() == weekday,
+            Recurrence::DayOfMonth(day) => date.day() == day,
+        };
+        if matches {
+            let candidate = resolve_local(date.and_time(time), tz)?;
+            if candidate > after {
+                return Ok(candidate);
+            }
+        }
+    }
+    Err(Error::tool("No matching date found"))
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    ["%H:%M", "%H:%M:%S", "%I:%M %p", "%I %p"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(value.trim(), format).ok())
+        .ok_or_else(|| Error::tool(format!("Unrecognized time '{value}', expected HH:MM")))
+}
+
+/// Native `current_datetime` tool
+pub struct CurrentDateTimeTool(pub DateTimeSettings);
+
+#[async_trait]
+impl NativeTool for CurrentDateTimeTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "current_datetime".to_string(),
+            description:
+                "Get the current date, time and weekday, optionally in a specific timezone."
+                    .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "timezone": {"type": "string", "description": "IANA timezone, e.g. America/New_York. Defaults to the configured timezone."}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let tz = self.0.timezone_arg(&arguments)?;
+        Ok(text_result(describe(&self.0.now(tz))))
+    }
+}
+
+/// Native `add_duration` tool
+pub struct AddDurationTool(pub DateTimeSettings);
+
+#[async_trait]
+impl NativeTool for AddDurationTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "add_duration".to_string(),
+            description: "Add (or subtract, with a negative amount) a duration to a date/time. \
+                Use it for questions like 'what date is 3 weeks from Friday?'."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "start": {"type": "string", "description": "Start as RFC 3339, 'YYYY-MM-DD HH:MM' or 'YYYY-MM-DD'. Defaults to now."},
+                    "amount": {"type": "number", "description": "Amount to add; negative to subtract"},
+                    "unit": {"type": "string", "enum": ["seconds", "minutes", "hours", "days", "weeks", "months", "years"]},
+                    "timezone": {"type": "string", "description": "IANA timezone. Defaults to the configured timezone."}
+                },
+                "required": ["amount", "unit"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let tz = self.0.timezone_arg(&arguments)?;
+        let start = self.0.datetime_arg(&arguments, "start", tz)?;
+        let amount = required_f64(&arguments, "amount")?;
+        let unit = required_str(&arguments, "unit")?;
+        let result = add_duration(start, amount, unit)?;
+        Ok(text_result(describe(&result)))
+    }
+}
+
+/// Native `next_occurrence` tool
+pub struct NextOccurrenceTool(pub DateTimeSettings);
+
+#[async_trait]
+impl NativeTool for NextOccurrenceTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "next_occurrence".to_string(),
+            description: "Find the next date falling on a weekday (e.g. 'next Friday') or a day \
+                of the month (e.g. 'the next 15th'), optionally at a given time."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "weekday": {"type": "string", "description": "Weekday name, e.g. friday"},
+                    "day_of_month": {"type": "integer", "minimum": 1, "maximum": 31},
+                    "time": {"type": "string", "description": "Time of day as HH:MM. Defaults to 00:00."},
+                    "after": {"type": "string", "description": "Search strictly after this datetime. Defaults to now."},
+                    "timezone": {"type": "string", "description": "IANA timezone. Defaults to the configured timezone."}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let tz = self.0.timezone_arg(&arguments)?;
+        let after = self.0.datetime_arg(&arguments, "after", tz)?;
+        let recurrence = match (
+            optional_str(&arguments, "weekday"),
+            arguments.get("day_of_month").and_then(Value::as_u64),
+        ) {
+            (Some(weekday), None) => Recurrence::Weekday(
+                weekday
+                    .trim()
+                    .parse()
+                    .map_err(|_| Error::tool(format!("Unknown weekday '{weekday}'")))?,
+            ),
+            (None, Some(day @ 1..=31)) => Recurrence::DayOfMonth(day as u32),
+            (None, Some(day)) => {
+                return Err(Error::tool(format!("Invalid day of month {day}")));
+            }
+            _ => {
+                return Err(Error::tool(
+                    "Provide exactly one of 'weekday' or 'day_of_month'",
+                ));
+            }
+        };
+        let time = optional_str(&arguments, "time").map_or(Ok(NaiveTime::MIN), parse_time)?;
+        let result = next_occurrence(after, recurrence, time)?;
+        Ok(text_result(describe(&result)))
+    }
+}
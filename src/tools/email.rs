@@ -0,0 +1,453 @@
+use super::{NativeTool, ToolContext, optional_str, required_str, text_result};
+use crate::{
+    Error, Result,
+    config::{EmailToolConfig, ImapConfig, SmtpConfig, SmtpSecurity},
+    mcp::McpTool,
+    mcp::McpToolCallResponse,
+};
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor, message::Mailbox,
+    transport::smtp::authentication::Credentials,
+};
+use mailparse::MailHeaderMap;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Upper bound on messages a single `search_inbox` call may return
+const MAX_SEARCH_RESULTS: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingEmail {
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InboxQuery {
+    pub text: Option<String>,
+    pub from: Option<String>,
+    pub since: Option<NaiveDate>,
+    pub unread_only: bool,
+    pub max_results: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailSummary {
+    pub from: String,
+    pub subject: String,
+    pub date: String,
+    pub unread: bool,
+}
+
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()>;
+}
+
+#[async_trait]
+pub trait MailboxReader: Send + Sync {
+    /// Returns matching messages, newest first
+    async fn search(&self, query: &InboxQuery) -> Result<Vec<EmailSummary>>;
+}
+
+/// Builds the email tools enabled in configuration
+pub fn email_tools(config: &EmailToolConfig) -> Result<Vec<Arc<dyn NativeTool>>> {
+    let mut tools: Vec<Arc<dyn NativeTool>> = Vec::new();
+    if !config.enabled {
+        return Ok(tools);
+    }
+    if let Some(smtp) = &config.smtp {
+        let from = config
+            .from
+            .as_deref()
+            .ok_or_else(|| Error::config("tools.email.from is required to send email"))?;
+        let sender = SmtpSender::new(smtp, from)?;
+        tools.push(Arc::new(SendEmailTool::new(
+            Arc::new(sender),
+            RecipientAllowlist::new(config.allowed_recipients.clone()),
+        )));
+    }
+    if let Some(imap) = &config.imap {
+        tools.push(Arc::new(SearchInboxTool::new(Arc::new(ImapReader::new(
+            imap.clone(),
+        )))));
+    }
+    Ok(tools)
+}
+
+/// Recipients `send_email` may write to. Entries are full addresses or `@domain`;
+/// an empty allowlist permits every recipient.
+#[derive(Debug, Clone, Default)]
+pub struct RecipientAllowlist {
+    entries: Vec<String>,
+}
+
+impl RecipientAllowlist {
+    pub fn new(entries: Vec<String>) -> Self {
+        Self {
+            entries: entries.iter().map(|e| e.trim().to_lowercase()).collect(),
+        }
+    }
+
+    pub fn allows(&self, address: &str) -> bool {
+        if self.entries.is_empty() {
+            return true;
+        }
+        let address = address.trim().to_lowercase();
+        self.entries.iter().any(|entry| {
+            if entry.starts_with('@') {
+                address.ends_with(entry.as_str())
+            } else {
+                address == *entry
+            }
+        })
+    }
+}
+
+/// Sends mail through an SMTP relay
+pub struct SmtpSender {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpSender {
+    pub fn new(config: &SmtpConfig, from: &str) -> Result<Self> {
+        let builder = match config.security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+            }
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host),
+            SmtpSecurity::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &config.host,
+            )),
+        }
+        .map_err(|e| Error::config(format!("Invalid SMTP configuration: {e}")))?
+        .port(config.port);
+
+        let builder = match (&config.username, &config.password) {
+            (Some(username), Some(password)) => {
+                builder.credentials(Credentials::new(username.clone(), password.clone()))
+            }
+            _ => builder,
+        };
+
+        Ok(Self {
+            transport: builder.build(),
+            from: parse_mailbox(from)?,
+        })
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .trim()
+        .parse()
+        .map_err(|e| Error::tool(format!("Invalid email address '{address}': {e}")))
+}
+
+#[async_trait]
+impl MailSender for SmtpSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(email.subject.clone());
+        for to in &email.to {
+            builder = builder.to(parse_mailbox(to)?);
+        }
+        for cc in &email.cc {
+            builder = builder.cc(parse_mailbox(cc)?);
+        }
+        let message = builder
+            .body(email.body.clone())
+            .map_err(|e| Error::tool(format!("Failed to build email: {e}")))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| Error::tool(format!("Failed to send email: {e}")))?;
+        Ok(())
+    }
+}
+
+/// Reads a mailbox over IMAPS. The `imap` crate is blocking, so sessions run on the
+/// blocking thread pool.
+pub struct ImapReader {
+    config: ImapConfig,
+}
+
+impl ImapReader {
+    pub fn new(config: ImapConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl MailboxReader for ImapReader {
+    async fn search(&self, query: &InboxQuery) -> Result<Vec<EmailSummary>> {
+        let config = self.config.clone();
+        let query = query.clone();
+        tokio::task::spawn_blocking(move || imap_search(&config, &query))
+            .await
+            .map_err(|e| Error::internal(format!("IMAP task failed: {e}")))?
+    }
+}
+
+fn imap_search(config: &ImapConfig, query: &InboxQuery) -> Result<Vec<EmailSummary>> {
+    let imap_error = |e: imap::Error| Error::tool(format!("IMAP error: {e}"));
+
+    let tls = native_tls::TlsConnector::new()
+        .map_err(|e| Error::tool(format!("TLS setup failed: {e}")))?;
+    let client = imap::connect((config.host.as_str(), config.port), &config.host, &tls)
+        .map_err(imap_error)?;
+    let mut session = client
+        .login(&config.username, &config.password)
+        .map_err(|(e, _)| imap_error(e))?;
+    session.examine(&config.mailbox).map_err(imap_error)?;
+
+    let mut uids: Vec<u32> = session
+        .uid_search(build_search_criteria(query))
+        .map_err(imap_error)?
+        .into_iter()
+        .collect();
+    uids.sort_unstable_by(|a, b| b.cmp(a));
+    uids.truncate(query.max_results);
+
+    let mut summaries = Vec::new();
+    if !uids.is_empty() {
+        let uid_set = uids
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+        let fetches = session
+            .uid_fetch(uid_set, "(UID FLAGS BODY.PEEK[HEADER])")
+            .map_err(imap_error)?;
+        let mut by_uid: Vec<_> = fetches
+            .iter()
+            .filter_map(|fetch| {
+                let (headers, _) = mailparse::parse_headers(fetch.header()?).ok()?;
+                let header = |name: &str| headers.get_first_value(name).unwrap_or_default();
+                let unread = !fetch.flags().contains(&imap::types::Flag::Seen);
+                Some((
+                    fetch.uid.unwrap_or_default(),
+                    EmailSummary {
+                        from: header("From"),
+                        subject: header("Subject"),
+                        date: header("Date"),
+                        unread,
+                    },
+                ))
+            })
+            .collect();
+        by_uid.sort_by_key(|(uid, _)| std::cmp::Reverse(*uid));
+        summaries = by_uid.into_iter().map(|(_, summary)| summary).collect();
+    }
+
+    let _ = session.logout();
+    Ok(summaries)
+}
+
+/// Quotes a string for use in an IMAP SEARCH command
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Translates a query into IMAP SEARCH criteria
+pub fn build_search_criteria(query: &InboxQuery) -> String {
+    let mut criteria = Vec::new();
+    if let Some(text) = &query.text {
+        criteria.push(format!("TEXT {}", quote(text)));
+    }
+    if let Some(from) = &query.from {
+        criteria.push(format!("FROM {}", quote(from)));
+    }
+    if let Some(since) = query.since {
+        criteria.push(format!("SINCE {}", since.format("%-d-%b-%Y")));
+    }
+    if query.unread_only {
+        criteria.push("UNSEEN".to_string());
+    }
+    if criteria.is_empty() {
+        "ALL".to_string()
+    } else {
+        criteria.join(" ")
+    }
+}
+
+/// Reads a string or array of strings as a list of addresses
+fn address_list(args: &HashMap<String, Value>, key: &str) -> Vec<String> {
+    match args.get(key) {
+        Some(Value::String(s)) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|s| s.trim().to_string())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Native `send_email` tool. Requires approval unless overridden in `tools.settings`.
+pub struct SendEmailTool {
+    sender: Arc<dyn MailSender>,
+    allowlist: RecipientAllowlist,
+}
+
+impl SendEmailTool {
+    pub fn new(sender: Arc<dyn MailSender>, allowlist: RecipientAllowlist) -> Self {
+        Self { sender, allowlist }
+    }
+}
+
+#[async_trait]
+impl NativeTool for SendEmailTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "send_email".to_string(),
+            description: "Send a plain-text email on the user's behalf.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "to": {"type": "array", "items": {"type": "string"}, "description": "Recipient addresses"},
+                    "cc": {"type": "array", "items": {"type": "string"}},
+                    "subject": {"type": "string"},
+                    "body": {"type": "string", "description": "Plain-text body"}
+                },
+                "required": ["to", "subject", "body"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    fn requires_approval(&self) -> bool {
+        true
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let email = OutgoingEmail {
+            to: address_list(&arguments, "to"),
+            cc: address_list(&arguments, "cc"),
+            subject: required_str(&arguments, "subject")?.to_string(),
+            body: required_str(&arguments, "body")?.to_string(),
+        };
+        if email.to.is_empty() {
+            return Err(Error::tool("At least one recipient is required"));
+        }
+        let blocked: Vec<&str> = email
+            .to
+            .iter()
+            .chain(&email.cc)
+            .filter(|address| !self.allowlist.allows(address))
+            .map(String::as_str)
+            .collect();
+        if !blocked.is_empty() {
+            return Err(Error::tool(format!(
+                "Recipients not in the allowlist: {}",
+                blocked.join(", ")
+            )));
+        }
+
+        self.sender.send(&email).await?;
+        Ok(text_result(format!(
+            "Email '{}' sent to {}",
+            email.subject,
+            email.to.join(", ")
+        )))
+    }
+}
+
+/// Native `search_inbox` tool
+pub struct SearchInboxTool {
+    reader: Arc<dyn MailboxReader>,
+}
+
+impl SearchInboxTool {
+    pub fn new(reader: Arc<dyn MailboxReader>) -> Self {
+        Self { reader }
+    }
+}
+
+#[async_trait]
+impl NativeTool for SearchInboxTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "search_inbox".to_string(),
+            description: "Search the user's inbox and list matching messages (sender, subject, \
+                date), newest first."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Text to look for in the message"},
+                    "from": {"type": "string", "description": "Sender address or name"},
+                    "since": {"type": "string", "description": "Only messages on or after this date (YYYY-MM-DD)"},
+                    "unread_only": {"type": "boolean"},
+                    "max_results": {"type": "integer", "minimum": 1, "maximum": MAX_SEARCH_RESULTS}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let since = optional_str(&arguments, "since")
+            .map(|s| {
+                NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                    .map_err(|_| Error::tool(format!("Invalid date '{s}', expected YYYY-MM-DD")))
+            })
+            .transpose()?;
+        let query = InboxQuery {
+            text: optional_str(&arguments, "query").map(String::from),
+            from: optional_str(&arguments, "from").map(String::from),
+            since,
+            unread_only: arguments
+                .get("unread_only")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+            max_results: arguments
+                .get("max_results")
+                .and_then(Value::as_u64)
+                .map_or(10, |n| n as usize)
+                .clamp(1, MAX_SEARCH_RESULTS),
+        };
+
+        let messages = self.reader.search(&query).await?;
+        if messages.is_empty() {
+            return Ok(text_result("No matching messages"));
+        }
+        Ok(text_result(
+            messages
+                .iter()
+                .enumerate()
+                .map(|(i, m)| {
+                    format!(
+                        "{}. {}{} — from {} ({})",
+                        i + 1,
+                        if m.unread { "[unread] " } else { "" },
+                        m.subject,
+                        m.from,
+                        m.date
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+}
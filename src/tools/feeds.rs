@@ -0,0 +1,90 @@
+use super::{NativeTool, ToolContext, optional_f64, optional_str, text_result};
+use crate::{
+    Error, Result,
+    feeds::FeedStore,
+    mcp::{McpTool, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Upper bound on items a single call may return
+const MAX_ITEMS: usize = 100;
+
+/// Native `get_recent_feed_items` tool
+pub struct RecentFeedItemsTool {
+    store: Arc<FeedStore>,
+}
+
+impl RecentFeedItemsTool {
+    pub fn new(store: Arc<FeedStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl NativeTool for RecentFeedItemsTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "get_recent_feed_items".to_string(),
+            description: "List news/RSS items that arrived recently in the monitored feeds, \
+                newest first. Use it to summarize what's new."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "hours": {"type": "number", "description": "How far back to look. Defaults to 24."},
+                    "feed": {"type": "string", "description": "Only items from this feed"},
+                    "limit": {"type": "integer", "minimum": 1, "maximum": MAX_ITEMS}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let hours = optional_f64(&arguments, "hours").unwrap_or(24.0).max(0.0);
+        // The window comes from the LLM, so it may reach past what dates can hold
+        let since = Duration::try_minutes((hours * 60.0).round() as i64)
+            .and_then(|window| Utc::now().checked_sub_signed(window))
+            .ok_or_else(|| Error::tool("hours out of range"))?;
+        let limit = arguments
+            .get("limit")
+            .and_then(Value::as_u64)
+            .map_or(20, |n| n as usize)
+            .clamp(1, MAX_ITEMS);
+
+        let items = self
+            .store
+            .recent(since, optional_str(&arguments, "feed"), limit)
+            .await?;
+        if items.is_empty() {
+            return Ok(text_result("No new feed items"));
+        }
+
+        Ok(text_result(
+            items
+                .iter()
+                .map(|item| {
+                    let mut entry = format!("[{}] {}", item.feed, item.title);
+                    if let Some(published) = item.published {
+                        entry.push_str(&format!(" ({})", published.format("%Y-%m-%d %H:%M UTC")));
+                    }
+                    if let Some(link) = &item.link {
+                        entry.push_str(&format!("\n  {link}"));
+                    }
+                    if let Some(summary) = &item.summary {
+                        entry.push_str(&format!("\n  {summary}"));
+                    }
+                    entry
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+}
@@ -0,0 +1,89 @@
+use super::{
+    NativeTool, ToolContext,
+    datetime::{DateTimeSettings, describe, parse_datetime},
+    optional_str, required_str, text_result,
+};
+use crate::{
+    Error, Result,
+    mcp::{McpTool, McpToolCallResponse},
+    scheduler::{FollowUpStore, parse_interval},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Native `schedule_followup` tool letting the agent message the user later
+pub struct ScheduleFollowUpTool {
+    store: Arc<FollowUpStore>,
+    settings: DateTimeSettings,
+}
+
+impl ScheduleFollowUpTool {
+    pub fn new(store: Arc<FollowUpStore>, settings: DateTimeSettings) -> Self {
+        Self { store, settings }
+    }
+
+    fn due_at(&self, arguments: &HashMap<String, Value>) -> Result<DateTime<Utc>> {
+        let now = self.settings.now_utc();
+        let due_at = match (
+            optional_str(arguments, "delay"),
+            optional_str(arguments, "at"),
+        ) {
+            (Some(delay), None) => {
+                now + chrono::Duration::from_std(parse_interval(delay)?)
+                    .map_err(|_| Error::tool("delay is out of range"))?
+            }
+            (None, Some(at)) => parse_datetime(at, self.settings.timezone())?.with_timezone(&Utc),
+            _ => return Err(Error::tool("Provide exactly one of 'delay' or 'at'")),
+        };
+        if due_at <= now {
+            return Err(Error::tool("The follow-up time must be in the future"));
+        }
+        Ok(due_at)
+    }
+}
+
+#[async_trait]
+impl NativeTool for ScheduleFollowUpTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "schedule_followup".to_string(),
+            description: "Schedule a message to the user later in this conversation, e.g. \
+                a reminder ('remind me in 2 hours') or checking back on something. \
+                At that time you will be prompted with the instruction and your reply \
+                is delivered to the user."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "instruction": {"type": "string", "description": "What to do or say when the follow-up runs"},
+                    "delay": {"type": "string", "description": "How long from now, e.g. 90s, 15m, 2h, 1d"},
+                    "at": {"type": "string", "description": "Local date and time, e.g. 2026-10-16 09:00"}
+                },
+                "required": ["instruction"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let instruction = required_str(&arguments, "instruction")?;
+        if ctx.session_id.is_empty() {
+            return Err(Error::tool("Follow-ups need a conversation session"));
+        }
+        let due_at = self.due_at(&arguments)?;
+        let id = self
+            .store
+            .create(ctx.session_id, instruction, due_at)
+            .await?;
+        Ok(text_result(format!(
+            "Follow-up {id} scheduled for {}",
+            describe(&due_at.with_timezone(&self.settings.timezone()))
+        )))
+    }
+}
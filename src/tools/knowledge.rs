@@ -0,0 +1,94 @@
+use super::{NativeTool, ToolContext, required_str, text_result};
+use crate::{
+    Result,
+    knowledge::{KnowledgeBase, SearchHit},
+    mcp::{McpContent, McpResourceContent, McpTool, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Upper bound on chunks a single call may request
+const MAX_RESULTS: usize = 20;
+
+/// Returns each hit as a numbered passage in a resource item whose URI
+/// (`<source>#chunk=<index>`) the agent records as a citation
+pub fn hits_result(query: &str, hits: &[SearchHit]) -> McpToolCallResponse {
+    if hits.is_empty() {
+        return text_result(format!("No documents found for '{query}'"));
+    }
+    let content = hits
+        .iter()
+        .enumerate()
+        .map(|(i, hit)| McpContent::Resource {
+            resource: McpResourceContent {
+                uri: format!("{}#chunk={}", hit.source, hit.chunk_index),
+                text: Some(format!(
+                    "{}. {} (chunk {}, score {:.2})\n{}",
+                    i + 1,
+                    hit.source,
+                    hit.chunk_index,
+                    hit.score,
+                    hit.content
+                )),
+                blob: None,
+            },
+        })
+        .collect();
+    McpToolCallResponse {
+        content,
+        is_error: false,
+    }
+}
+
+/// Native `knowledge_search` tool
+pub struct KnowledgeSearchTool {
+    knowledge: Arc<KnowledgeBase>,
+    default_results: usize,
+}
+
+impl KnowledgeSearchTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>, default_results: usize) -> Self {
+        Self {
+            knowledge,
+            default_results: default_results.clamp(1, MAX_RESULTS),
+        }
+    }
+}
+
+#[async_trait]
+impl NativeTool for KnowledgeSearchTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "knowledge_search".to_string(),
+            description: "Search the user's document knowledge base (manuals, notes, \
+                saved pages) and return the most relevant passages. Base answers on the \
+                returned passages and mention their source."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "What to look for, in natural language"},
+                    "top_k": {"type": "integer", "minimum": 1, "maximum": MAX_RESULTS}
+                },
+                "required": ["query"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let query = required_str(&arguments, "query")?;
+        let top_k = arguments
+            .get("top_k")
+            .and_then(Value::as_u64)
+            .map_or(self.default_results, |n| n as usize)
+            .clamp(1, MAX_RESULTS);
+        let hits = self.knowledge.search(query, top_k).await?;
+        Ok(hits_result(query, &hits))
+    }
+}
@@ -0,0 +1,195 @@
+//! Native tools executed in-process instead of through an MCP server.
+
+pub mod calculator;
+#[cfg(feature = "calendar")]
+pub mod calendar;
+pub mod currency;
+pub mod datetime;
+#[cfg(feature = "email")]
+pub mod email;
+#[cfg(feature = "feeds")]
+pub mod feeds;
+pub mod followup;
+#[cfg(feature = "knowledge")]
+pub mod knowledge;
+pub mod profile;
+mod registry;
+pub mod tasks;
+pub mod units;
+#[cfg(feature = "web-search")]
+pub mod web_search;
+
+pub use registry::NativeToolRegistry;
+
+use crate::{
+    Error, Result,
+    config::ToolsConfig,
+    history::HistoryStorage,
+    mcp::{McpContent, McpProgress, McpTool, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::warn;
+
+/// Per-call information available to native tools
+pub struct ToolContext<'a> {
+    pub session_id: &'a str,
+    pub history: Option<&'a HistoryStorage>,
+    /// Receives the call's progress while its heartbeats are watched
+    pub progress: Option<UnboundedSender<McpProgress>>,
+}
+
+impl<'a> ToolContext<'a> {
+    pub fn new(session_id: &'a str, history: Option<&'a HistoryStorage>) -> Self {
+        Self {
+            session_id,
+            history,
+            progress: None,
+        }
+    }
+
+    pub fn with_progress(mut self, progress: UnboundedSender<McpProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Reports progress of a long-running call, keeping it from being cancelled as stalled
+    pub fn report_progress(&self, progress: u32, total: Option<u32>, message: Option<String>) {
+        if let Some(sender) = &self.progress {
+            let _ = sender.send(McpProgress {
+                progress,
+                total,
+                message,
+            });
+        }
+    }
+}
+
+#[async_trait]
+pub trait NativeTool: Send + Sync {
+    /// Tool definition advertised to the LLM
+    fn definition(&self) -> McpTool;
+
+    /// Whether calls must be approved before running, unless overridden in `tools.settings`
+    fn requires_approval(&self) -> bool {
+        false
+    }
+
+    /// Executes the tool. Errors are reported back to the LLM as failed tool results.
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse>;
+}
+
+/// Builds the native tools enabled in configuration
+pub fn builtin_tools(config: &ToolsConfig) -> Vec<Arc<dyn NativeTool>> {
+    let mut tools: Vec<Arc<dyn NativeTool>> = Vec::new();
+
+    if config.units.enabled {
+        tools.push(Arc::new(units::ConvertUnitsTool::new(
+            currency::create_rate_source(&config.units.currency),
+        )));
+        tools.push(Arc::new(units::FormatNumberTool));
+    }
+
+    if config.calculator.enabled {
+        tools.push(Arc::new(calculator::CalculatorTool));
+    }
+
+    let settings =
+        datetime::DateTimeSettings::from_timezone_name(config.datetime.timezone.as_deref())
+            .unwrap_or_else(|e| {
+                warn!("{}, falling back to UTC for datetime tools", e);
+                datetime::DateTimeSettings::new(chrono_tz::Tz::UTC)
+            });
+    if config.datetime.enabled {
+        tools.push(Arc::new(datetime::CurrentDateTimeTool(settings)));
+        tools.push(Arc::new(datetime::AddDurationTool(settings)));
+        tools.push(Arc::new(datetime::NextOccurrenceTool(settings)));
+    }
+
+    #[cfg(feature = "web-search")]
+    match web_search::create_search_provider(&config.web_search) {
+        Ok(Some(provider)) => tools.push(Arc::new(web_search::WebSearchTool::new(
+            provider,
+            config.web_search.max_results,
+        ))),
+        Ok(None) => {}
+        Err(e) => warn!("web_search tool disabled: {}", e),
+    }
+    #[cfg(not(feature = "web-search"))]
+    if config.web_search.provider != crate::config::WebSearchProvider::None {
+        warn!("web_search tool disabled: jarvis was built without the `web-search` feature");
+    }
+
+    #[cfg(feature = "email")]
+    match email::email_tools(&config.email) {
+        Ok(email_tools) => tools.extend(email_tools),
+        Err(e) => warn!("Email tools disabled: {}", e),
+    }
+    #[cfg(not(feature = "email"))]
+    if config.email.smtp.is_some() || config.email.imap.is_some() {
+        warn!("Email tools disabled: jarvis was built without the `email` feature");
+    }
+
+    #[cfg(feature = "calendar")]
+    match calendar::create_calendar_backend(&config.calendar) {
+        Ok(Some(backend)) => {
+            tools.push(Arc::new(calendar::ListEventsTool::new(
+                backend.clone(),
+                settings,
+            )));
+            tools.push(Arc::new(calendar::CreateEventTool::new(backend, settings)));
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Calendar tools disabled: {}", e),
+    }
+    #[cfg(not(feature = "calendar"))]
+    if config.calendar.provider != crate::config::CalendarProvider::None {
+        warn!("Calendar tools disabled: jarvis was built without the `calendar` feature");
+    }
+
+    tools
+}
+
+pub fn text_result(text: impl Into<String>) -> McpToolCallResponse {
+    McpToolCallResponse {
+        content: vec![McpContent::Text { text: text.into() }],
+        is_error: false,
+    }
+}
+
+pub fn error_result(text: impl Into<String>) -> McpToolCallResponse {
+    McpToolCallResponse {
+        content: vec![McpContent::Text { text: text.into() }],
+        is_error: true,
+    }
+}
+
+pub(crate) fn required_str<'a>(args: &'a HashMap<String, Value>, key: &str) -> Result<&'a str> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| Error::tool(format!("Missing required string argument '{key}'")))
+}
+
+pub(crate) fn optional_str<'a>(args: &'a HashMap<String, Value>, key: &str) -> Option<&'a str> {
+    args.get(key).and_then(Value::as_str)
+}
+
+/// Reads a number, accepting numeric strings since models frequently quote numbers
+pub(crate) fn required_f64(args: &HashMap<String, Value>, key: &str) -> Result<f64> {
+    optional_f64(args, key)
+        .ok_or_else(|| Error::tool(format!("Missing required numeric argument '{key}'")))
+}
+
+pub(crate) fn optional_f64(args: &HashMap<String, Value>, key: &str) -> Option<f64> {
+    match args.get(key)? {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
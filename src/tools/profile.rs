@@ -0,0 +1,100 @@
+use super::{NativeTool, ToolContext, required_str, text_result};
+use crate::{
+    Error, Result,
+    mcp::{McpTool, McpToolCallResponse},
+    profiles::{ProfileStore, normalize_key},
+};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Native `remember_preference` tool
+pub struct RememberPreferenceTool {
+    store: Arc<ProfileStore>,
+}
+
+impl RememberPreferenceTool {
+    pub fn new(store: Arc<ProfileStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl NativeTool for RememberPreferenceTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "remember_preference".to_string(),
+            description: "Store a durable preference or fact about the user (e.g. nickname, \
+                dietary restrictions, preferred units). Stored preferences are included in \
+                your instructions in every future conversation with this user."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "key": {"type": "string", "description": "Short name, e.g. nickname, diet, units"},
+                    "value": {"type": "string"}
+                },
+                "required": ["key", "value"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let key = normalize_key(required_str(&arguments, "key")?);
+        let value = required_str(&arguments, "value")?.trim();
+        if key.is_empty() || value.is_empty() {
+            return Err(Error::tool("key and value must not be empty"));
+        }
+        let user_id = self.store.user_for_session(ctx.session_id).await?;
+        self.store.set(&user_id, &key, value).await?;
+        Ok(text_result(format!("Remembered {key}: {value}")))
+    }
+}
+
+/// Native `forget_preference` tool
+pub struct ForgetPreferenceTool {
+    store: Arc<ProfileStore>,
+}
+
+impl ForgetPreferenceTool {
+    pub fn new(store: Arc<ProfileStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl NativeTool for ForgetPreferenceTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "forget_preference".to_string(),
+            description: "Remove a stored user preference by key.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "key": {"type": "string"}
+                },
+                "required": ["key"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let key = normalize_key(required_str(&arguments, "key")?);
+        let user_id = self.store.user_for_session(ctx.session_id).await?;
+        if self.store.remove(&user_id, &key).await? {
+            Ok(text_result(format!("Forgot {key}")))
+        } else {
+            Err(Error::tool(format!("No preference named '{key}'")))
+        }
+    }
+}
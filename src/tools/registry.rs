@@ -0,0 +1,42 @@
+use super::NativeTool;
+use std::{collections::HashMap, sync::Arc};
+use tracing::warn;
+
+/// Native tools available to the agent, keyed by tool name
+#[derive(Default, Clone)]
+pub struct NativeToolRegistry {
+    tools: HashMap<String, Arc<dyn NativeTool>>,
+}
+
+impl NativeToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a tool, replacing any previously registered tool with the same name
+    pub fn register(&mut self, tool: Arc<dyn NativeTool>) {
+        let name = tool.definition().name;
+        if self.tools.insert(name.clone(), tool).is_some() {
+            warn!(
+                "Native tool '{}' registered twice, keeping the latest",
+                name
+            );
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn NativeTool>> {
+        self.tools.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+}
@@ -0,0 +1,190 @@
+use super::{
+    NativeTool, ToolContext,
+    datetime::{DateTimeSettings, parse_datetime},
+    optional_str, required_f64, required_str, text_result,
+};
+use crate::{
+    Error, Result,
+    mcp::{McpTool, McpToolCallResponse},
+    tasks::{NewTask, Task, TaskStatus, TaskStore},
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// One line per task, e.g. `#3 [open] Buy milk (due Fri 2026-10-16 09:00)`
+pub fn describe_task(task: &Task, settings: &DateTimeSettings) -> String {
+    let mut line = format!("#{} [{}] {}", task.id, task.status.as_str(), task.title);
+    if let Some(due) = task.due {
+        let due = due.with_timezone(&settings.timezone());
+        line.push_str(&format!(" (due {})", due.format("%a %Y-%m-%d %H:%M")));
+    }
+    if let Some(notes) = &task.notes {
+        line.push_str(&format!(" — {notes}"));
+    }
+    line
+}
+
+/// Native `create_task` tool
+pub struct CreateTaskTool {
+    store: Arc<TaskStore>,
+    settings: DateTimeSettings,
+}
+
+impl CreateTaskTool {
+    pub fn new(store: Arc<TaskStore>, settings: DateTimeSettings) -> Self {
+        Self { store, settings }
+    }
+}
+
+#[async_trait]
+impl NativeTool for CreateTaskTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "create_task".to_string(),
+            description: "Add a task to the user's persistent TODO list. Use it whenever \
+                the user asks you to remember to do something or you commit to a follow-up."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "notes": {"type": "string"},
+                    "due": {"type": "string", "description": "Local due date/time, e.g. 2026-10-16 or 2026-10-16 09:00"}
+                },
+                "required": ["title"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let title = required_str(&arguments, "title")?.trim();
+        if title.is_empty() {
+            return Err(Error::tool("title must not be empty"));
+        }
+        let due = optional_str(&arguments, "due")
+            .map(|due| parse_datetime(due, self.settings.timezone()))
+            .transpose()?
+            .map(|due| due.with_timezone(&Utc));
+
+        let task = self
+            .store
+            .create(NewTask {
+                title: title.to_string(),
+                notes: optional_str(&arguments, "notes").map(String::from),
+                due,
+                session_id: ctx.session_id.to_string(),
+            })
+            .await?;
+        Ok(text_result(format!(
+            "Created {}",
+            describe_task(&task, &self.settings)
+        )))
+    }
+}
+
+/// Native `list_tasks` tool
+pub struct ListTasksTool {
+    store: Arc<TaskStore>,
+    settings: DateTimeSettings,
+}
+
+impl ListTasksTool {
+    pub fn new(store: Arc<TaskStore>, settings: DateTimeSettings) -> Self {
+        Self { store, settings }
+    }
+}
+
+#[async_trait]
+impl NativeTool for ListTasksTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "list_tasks".to_string(),
+            description: "List the user's tasks, open ones first by due date.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "status": {"type": "string", "enum": ["open", "done", "all"], "description": "Defaults to open"}
+                }
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let status =
+            TaskStatus::parse_filter(optional_str(&arguments, "status").unwrap_or("open"))?;
+
+        let tasks = self.store.list(status).await?;
+        if tasks.is_empty() {
+            return Ok(text_result("No tasks"));
+        }
+        Ok(text_result(
+            tasks
+                .iter()
+                .map(|task| describe_task(task, &self.settings))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ))
+    }
+}
+
+/// Native `complete_task` tool
+pub struct CompleteTaskTool {
+    store: Arc<TaskStore>,
+    settings: DateTimeSettings,
+}
+
+impl CompleteTaskTool {
+    pub fn new(store: Arc<TaskStore>, settings: DateTimeSettings) -> Self {
+        Self { store, settings }
+    }
+}
+
+#[async_trait]
+impl NativeTool for CompleteTaskTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "complete_task".to_string(),
+            description: "Mark a task as done by its id (see list_tasks).".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"}
+                },
+                "required": ["id"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let id = required_f64(&arguments, "id")?;
+        if id.fract() != 0.0 {
+            return Err(Error::tool("id must be a whole number"));
+        }
+        let task = self
+            .store
+            .complete(id as i64)
+            .await?
+            .ok_or_else(|| Error::tool(format!("No task with id {id}")))?;
+        Ok(text_result(format!(
+            "Completed {}",
+            describe_task(&task, &self.settings)
+        )))
+    }
+}
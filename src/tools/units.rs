@@ -0,0 +1,439 @@
+use super::{
+    NativeTool, ToolContext, currency::RateSource, optional_f64, optional_str, required_f64,
+    required_str, text_result,
+};
+use crate::{Error, Result, mcp::McpTool, mcp::McpToolCallResponse};
+use async_trait::async_trait;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Length,
+    Area,
+    Mass,
+    Volume,
+    Temperature,
+    Duration,
+    Speed,
+    Data,
+}
+
+struct Unit {
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    dimension: Dimension,
+    /// Multiplier converting one of this unit into the dimension's base unit
+    factor: f64,
+}
+
+const fn unit(
+    symbol: &'static str,
+    aliases: &'static [&'static str],
+    dimension: Dimension,
+    factor: f64,
+) -> Unit {
+    Unit {
+        symbol,
+        aliases,
+        dimension,
+        factor,
+    }
+}
+
+use Dimension::*;
+
+/// Supported units. Base units: metre, square metre, kilogram, litre, kelvin, second,
+/// metre per second and byte.
+const UNITS: &[Unit] = &[
+    unit("mm", &["millimeter", "millimetre"], Length, 0.001),
+    unit("cm", &["centimeter", "centimetre"], Length, 0.01),
+    unit("m", &["meter", "metre"], Length, 1.0),
+    unit("km", &["kilometer", "kilometre"], Length, 1000.0),
+    unit("in", &["inch", "inches", "\""], Length, 0.0254),
+    unit("ft", &["foot", "feet", "'"], Length, 0.3048),
+    unit("yd", &["yard"], Length, 0.9144),
+    unit("mi", &["mile"], Length, 1609.344),
+    unit("nmi", &["nautical mile"], Length, 1852.0),
+    unit(
+        "m2",
+        &["m²", "sqm", "square meter", "square metre"],
+        Area,
+        1.0,
+    ),
+    unit(
+        "km2",
+        &["km²", "square kilometer", "square kilometre"],
+        Area,
+        1e6,
+    ),
+    unit(
+        "ft2",
+        &["ft²", "sqft", "square foot", "square feet"],
+        Area,
+        0.092_903_04,
+    ),
+    unit("ha", &["hectare"], Area, 10_000.0),
+    unit("acre", &["ac"], Area, 4_046.856_422_4),
+    unit("mg", &["milligram"], Mass, 1e-6),
+    unit("g", &["gram", "gramme"], Mass, 0.001),
+    unit("kg", &["kilogram", "kilo"], Mass, 1.0),
+    unit("t", &["tonne", "metric ton"], Mass, 1000.0),
+    unit("oz", &["ounce"], Mass, 0.028_349_523_125),
+    unit("lb", &["lbs", "pound"], Mass, 0.453_592_37),
+    unit("st", &["stone"], Mass, 6.350_293_18),
+    unit("ml", &["milliliter", "millilitre"], Volume, 0.001),
+    unit("cl", &["centiliter", "centilitre"], Volume, 0.01),
+    unit("l", &["liter", "litre"], Volume, 1.0),
+    unit("tsp", &["teaspoon"], Volume, 0.004_928_921_593_75),
+    unit("tbsp", &["tablespoon"], Volume, 0.014_786_764_781_25),
+    unit(
+        "fl_oz",
+        &["fl oz", "floz", "fluid ounce"],
+        Volume,
+        0.029_573_529_562_5,
+    ),
+    unit("cup", &["cups"], Volume, 0.236_588_236_5),
+    unit("pt", &["pint"], Volume, 0.473_176_473),
+    unit("qt", &["quart"], Volume, 0.946_352_946),
+    unit("gal", &["gallon"], Volume, 3.785_411_784),
+    unit("c", &["°c", "celsius", "degc"], Temperature, 1.0),
+    unit("f", &["°f", "fahrenheit", "degf"], Temperature, 1.0),
+    unit("k", &["kelvin"], Temperature, 1.0),
+    unit("ms", &["millisecond"], Duration, 0.001),
+    unit("s", &["sec", "second"], Duration, 1.0),
+    unit("min", &["minute"], Duration, 60.0),
+    unit("h", &["hr", "hour"], Duration, 3600.0),
+    unit("d", &["day"], Duration, 86_400.0),
+    unit("wk", &["week"], Duration, 604_800.0),
+    unit("m/s", &["mps", "meters per second"], Speed, 1.0),
+    unit(
+        "km/h",
+        &["kph", "kmh", "kilometers per hour"],
+        Speed,
+        1.0 / 3.6,
+    ),
+    unit("mph", &["mi/h", "miles per hour"], Speed, 0.447_04),
+    unit("kn", &["knot", "kt"], Speed, 0.514_444),
+    unit("b", &["byte"], Data, 1.0),
+    unit("kb", &["kilobyte"], Data, 1e3),
+    unit("mb", &["megabyte"], Data, 1e6),
+    unit("gb", &["gigabyte"], Data, 1e9),
+    unit("tb", &["terabyte"], Data, 1e12),
+    unit("kib", &["kibibyte"], Data, 1024.0),
+    unit("mib", &["mebibyte"], Data, 1_048_576.0),
+    unit("gib", &["gibibyte"], Data, 1_073_741_824.0),
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    let normalized = name.trim().to_lowercase();
+    let lookup = |candidate: &str| {
+        UNITS
+            .iter()
+            .find(|u| u.symbol == candidate || u.aliases.contains(&candidate))
+    };
+    lookup(&normalized).or_else(|| {
+        // Accept plurals such as "meters" or "hours"
+        normalized.strip_suffix('s').and_then(lookup)
+    })
+}
+
+fn to_kelvin(value: f64, symbol: &str) -> f64 {
+    match symbol {
+        "c" => value + 273.15,
+        "f" => (value - 32.0) * 5.0 / 9.0 + 273.15,
+        _ => value,
+    }
+}
+
+fn from_kelvin(value: f64, symbol: &str) -> f64 {
+    match symbol {
+        "c" => value - 273.15,
+        "f" => (value - 273.15) * 9.0 / 5.0 + 32.0,
+        _ => value,
+    }
+}
+
+/// Converts `value` between two physical units
+pub fn convert(value: f64, from: &str, to: &str) -> Result<f64> {
+    let from_unit = find_unit(from).ok_or_else(|| Error::tool(format!("Unknown unit '{from}'")))?;
+    let to_unit = find_unit(to).ok_or_else(|| Error::tool(format!("Unknown unit '{to}'")))?;
+
+    if from_unit.dimension != to_unit.dimension {
+        return Err(Error::tool(format!(
+            "Cannot convert {:?} ({from}) to {:?} ({to})",
+            from_unit.dimension, to_unit.dimension
+        )));
+    }
+
+    if from_unit.dimension == Temperature {
+        return Ok(from_kelvin(
+            to_kelvin(value, from_unit.symbol),
+            to_unit.symbol,
+        ));
+    }
+
+    Ok(value * from_unit.factor / to_unit.factor)
+}
+
+fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+/// Formats a value with at most six significant digits, without trailing zeros
+fn format_significant(value: f64) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return value.to_string();
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let decimals = (5 - magnitude).clamp(0, 12) as usize;
+    let formatted = format!("{value:.decimals$}");
+    if formatted.contains('.') {
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    } else {
+        formatted
+    }
+}
+
+/// Native `convert_units` tool covering physical units and currencies
+pub struct ConvertUnitsTool {
+    rates: Option<Arc<dyn RateSource>>,
+}
+
+impl ConvertUnitsTool {
+    pub fn new(rates: Option<Arc<dyn RateSource>>) -> Self {
+        Self { rates }
+    }
+
+    async fn convert_currency(&self, value: f64, from: &str, to: &str) -> Result<f64> {
+        let rates = self
+            .rates
+            .as_ref()
+            .ok_or_else(|| Error::tool("Currency conversion is not configured"))?;
+        Ok(value * rates.rate(from, to).await?)
+    }
+}
+
+#[async_trait]
+impl NativeTool for ConvertUnitsTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "convert_units".to_string(),
+            description: "Convert a value between units of length, area, mass, volume, \
+                temperature, duration, speed, data size, or between currencies (ISO 4217 codes \
+                such as USD or EUR). Always use this instead of converting in your head."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "value": {"type": "number", "description": "The amount to convert"},
+                    "from": {"type": "string", "description": "Source unit, e.g. km, °F, lb, min, USD"},
+                    "to": {"type": "string", "description": "Target unit, e.g. mi, °C, kg, h, EUR"}
+                },
+                "required": ["value", "from", "to"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let value = required_f64(&arguments, "value")?;
+        let from = required_str(&arguments, "from")?;
+        let to = required_str(&arguments, "to")?;
+
+        let result = match convert(value, from, to) {
+            Ok(result) => result,
+            Err(_) if is_currency_code(from) && is_currency_code(to) => {
+                self.convert_currency(value, &from.to_uppercase(), &to.to_uppercase())
+                    .await?
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(text_result(format!(
+            "{} {from} = {} {to}",
+            format_significant(value),
+            format_significant(result)
+        )))
+    }
+}
+
+/// Locale-specific separators and currency placement
+struct LocaleFormat {
+    group: &'static str,
+    decimal: &'static str,
+    currency_prefix: bool,
+    currency_space: bool,
+    percent_space: bool,
+}
+
+fn locale_format(locale: &str) -> LocaleFormat {
+    let locale = locale.replace('_', "-").to_lowercase();
+    let language = locale.split('-').next().unwrap_or("en");
+
+    match (language, locale.as_str()) {
+        (_, "de-ch" | "fr-ch" | "it-ch") => LocaleFormat {
+            group: "'",
+            decimal: ".",
+            currency_prefix: true,
+            currency_space: true,
+            percent_space: false,
+        },
+        (_, "pt-br") => LocaleFormat {
+            group: ".",
+            decimal: ",",
+            currency_prefix: true,
+            currency_space: true,
+            percent_space: false,
+        },
+        ("nl", _) => LocaleFormat {
+            group: ".",
+            decimal: ",",
+            currency_prefix: true,
+            currency_space: true,
+            percent_space: false,
+        },
+        ("de" | "es" | "it" | "pt" | "id" | "da" | "tr" | "el", _) => LocaleFormat {
+            group: ".",
+            decimal: ",",
+            currency_prefix: false,
+            currency_space: true,
+            percent_space: true,
+        },
+        ("fr" | "ru" | "pl" | "cs" | "sk" | "sv" | "fi" | "nb" | "no" | "uk" | "hu", _) => {
+            LocaleFormat {
+                group: "\u{a0}",
+                decimal: ",",
+                currency_prefix: false,
+                currency_space: true,
+                percent_space: true,
+            }
+        }
+        _ => LocaleFormat {
+            group: ",",
+            decimal: ".",
+            currency_prefix: true,
+            currency_space: false,
+            percent_space: false,
+        },
+    }
+}
+
+fn currency_symbol(code: &str) -> &str {
+    match code {
+        "USD" => "$",
+        "EUR" => "€",
+        "GBP" => "£",
+        "JPY" | "CNY" => "¥",
+        "BRL" => "R$",
+        "INR" => "₹",
+        "KRW" => "₩",
+        "RUB" => "₽",
+        "AUD" => "A$",
+        "CAD" => "CA$",
+        _ => code,
+    }
+}
+
+/// Formats a number using the grouping and decimal conventions of `locale`
+pub fn format_number(value: f64, locale: &str, decimals: usize) -> String {
+    let format = locale_format(locale);
+    let fixed = format!("{:.decimals$}", value.abs());
+    let (integer, fraction) = match fixed.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (fixed.as_str(), None),
+    };
+
+    let digits: Vec<char> = integer.chars().collect();
+    let mut grouped = String::new();
+    for (i, digit) in digits.iter().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push_str(format.group);
+        }
+        grouped.push(*digit);
+    }
+
+    let mut result = String::new();
+    if value < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        result.push('-');
+    }
+    result.push_str(&grouped);
+    if let Some(fraction) = fraction {
+        result.push_str(format.decimal);
+        result.push_str(fraction);
+    }
+    result
+}
+
+/// Native `format_number` tool for locale-aware number, percentage and currency formatting
+pub struct FormatNumberTool;
+
+#[async_trait]
+impl NativeTool for FormatNumberTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "format_number".to_string(),
+            description: "Format a number for display using a locale's conventions \
+                (digit grouping, decimal separator), optionally as a percentage or currency amount."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "value": {"type": "number"},
+                    "locale": {"type": "string", "description": "BCP 47 locale such as en-US, de-DE or pt-BR (default en-US)"},
+                    "style": {"type": "string", "enum": ["decimal", "percent", "currency"]},
+                    "currency": {"type": "string", "description": "ISO 4217 code, required for the currency style"},
+                    "decimals": {"type": "integer", "minimum": 0, "maximum": 12}
+                },
+                "required": ["value"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let value = required_f64(&arguments, "value")?;
+        let locale = optional_str(&arguments, "locale").unwrap_or("en-US");
+        let style = optional_str(&arguments, "style").unwrap_or("decimal");
+        let decimals = optional_f64(&arguments, "decimals").map(|d| d.clamp(0.0, 12.0) as usize);
+        let format = locale_format(locale);
+
+        let formatted = match style {
+            "decimal" => format_number(value, locale, decimals.unwrap_or(2)),
+            "percent" => {
+                let number = format_number(value * 100.0, locale, decimals.unwrap_or(0));
+                let space = if format.percent_space { "\u{a0}" } else { "" };
+                format!("{number}{space}%")
+            }
+            "currency" => {
+                let code = required_str(&arguments, "currency")?.to_uppercase();
+                let default_decimals = if code == "JPY" || code == "KRW" { 0 } else { 2 };
+                let number = format_number(value, locale, decimals.unwrap_or(default_decimals));
+                let symbol = currency_symbol(&code);
+                let space = if format.currency_space { "\u{a0}" } else { "" };
+                if format.currency_prefix {
+                    match number.strip_prefix('-') {
+                        Some(abs) => format!("-{symbol}{space}{abs}"),
+                        None => format!("{symbol}{space}{number}"),
+                    }
+                } else {
+                    format!("{number}{space}{symbol}")
+                }
+            }
+            other => return Err(Error::tool(format!("Unknown style '{other}'"))),
+        };
+
+        Ok(text_result(formatted))
+    }
+}
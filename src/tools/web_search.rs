@@ -0,0 +1,314 @@
+use super::{NativeTool, ToolContext, required_str, text_result};
+use crate::{
+    Error, Result,
+    config::{WebSearchProvider, WebSearchToolConfig},
+    mcp::McpTool,
+    mcp::McpToolCallResponse,
+};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+/// Upper bound on results a single call may request
+const MAX_RESULTS_LIMIT: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub title: String,
+    pub url: String,
+    pub snippet: String,
+}
+
+/// Web search backend used by the `web_search` tool
+#[async_trait]
+pub trait SearchProvider: Send + Sync {
+    async fn search(&self, query: &str, max_results: usize) -> Result<Vec<SearchResult>>;
+}
+
+pub fn create_search_provider(
+    config: &WebSearchToolConfig,
+) -> Result<Option<Arc<dyn SearchProvider>>> {
+    let api_key = || {
+        config
+            .api_key
+            .clone()
+            .ok_or_else(|| Error::config("tools.web_search.api_key is required for this provider"))
+    };
+    let provider: Arc<dyn SearchProvider> = match config.provider {
+        WebSearchProvider::None => return Ok(None),
+        WebSearchProvider::Searxng => {
+            Arc::new(SearxngProvider::new(config.base_url.clone().ok_or_else(
+                || Error::config("tools.web_search.base_url is required for SearxNG"),
+            )?))
+        }
+        WebSearchProvider::Brave => {
+            Arc::new(BraveProvider::new(api_key()?, config.base_url.clone()))
+        }
+        WebSearchProvider::Tavily => {
+            Arc::new(TavilyProvider::new(api_key()?, config.base_url.clone()))
+        }
+    };
+    Ok(Some(provider))
+}
+
+fn trim_url(url: String) -> String {
+    url.trim_end_matches('/').to_string()
+}
+
+/// Self-hosted SearxNG instance with the JSON output format enabled
+pub struct SearxngProvider {
+    client: reqwest::Client,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct SearxngResponse {
+    #[serde(default)]
+    results: Vec<SearxngResult>,
+}
+
+#[derive(Deserialize)]
+struct SearxngResult {
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl SearxngProvider {
+    pub fn new(base_url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: trim_url(base_url),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for SearxngProvider {
+    async fn search(&self, query: &str, max_results: usize) -> Result<Vec<SearchResult>> {
+        let response: SearxngResponse = self
+            .client
+            .get(format!("{}/search", self.base_url))
+            .query(&[("q", query), ("format", "json")])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response
+            .results
+            .into_iter()
+            .take(max_results)
+            .map(|r| SearchResult {
+                title: r.title,
+                url: r.url,
+                snippet: r.content,
+            })
+            .collect())
+    }
+}
+
+/// Brave Search API (https://brave.com/search/api/)
+pub struct BraveProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct BraveResponse {
+    web: Option<BraveWeb>,
+}
+
+#[derive(Deserialize)]
+struct BraveWeb {
+    #[serde(default)]
+    results: Vec<BraveResult>,
+}
+
+#[derive(Deserialize)]
+struct BraveResult {
+    title: String,
+    url: String,
+    #[serde(default)]
+    description: String,
+}
+
+impl BraveProvider {
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+            base_url: trim_url(
+                base_url.unwrap_or_else(|| "https://api.search.brave.com".to_string()),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for BraveProvider {
+    async fn search(&self, query: &str, max_results: usize) -> Result<Vec<SearchResult>> {
+        let response: BraveResponse = self
+            .client
+            .get(format!("{}/res/v1/web/search", self.base_url))
+            .header("X-Subscription-Token", &self.api_key)
+            .header("Accept", "application/json")
+            .query(&[("q", query), ("count", &max_results.to_string())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response
+            .web
+            .map(|web| web.results)
+            .unwrap_or_default()
+            .into_iter()
+            .take(max_results)
+            .map(|r| SearchResult {
+                title: r.title,
+                url: r.url,
+                snippet: r.description,
+            })
+            .collect())
+    }
+}
+
+/// Tavily search API (https://tavily.com)
+pub struct TavilyProvider {
+    client: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(Deserialize)]
+struct TavilyResponse {
+    #[serde(default)]
+    results: Vec<TavilyResult>,
+}
+
+#[derive(Deserialize)]
+struct TavilyResult {
+    title: String,
+    url: String,
+    #[serde(default)]
+    content: String,
+}
+
+impl TavilyProvider {
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            api_key,
+            base_url: trim_url(base_url.unwrap_or_else(|| "https://api.tavily.com".to_string())),
+        }
+    }
+}
+
+#[async_trait]
+impl SearchProvider for TavilyProvider {
+    async fn search(&self, query: &str, max_results: usize) -> Result<Vec<SearchResult>> {
+        let response: TavilyResponse = self
+            .client
+            .post(format!("{}/search", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&json!({"query": query, "max_results": max_results}))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response
+            .results
+            .into_iter()
+            .take(max_results)
+            .map(|r| SearchResult {
+                title: r.title,
+                url: r.url,
+                snippet: r.content,
+            })
+            .collect())
+    }
+}
+
+/// Renders results as a numbered list the model can cite from
+pub fn format_results(query: &str, results: &[SearchResult]) -> String {
+    if results.is_empty() {
+        return format!("No results found for '{query}'");
+    }
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let mut entry = format!("{}. {}\n   {}", i + 1, r.title.trim(), r.url);
+            let snippet = r.snippet.trim();
+            if !snippet.is_empty() {
+                entry.push_str("\n   ");
+                entry.push_str(snippet);
+            }
+            entry
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Native `web_search` tool
+pub struct WebSearchTool {
+    provider: Arc<dyn SearchProvider>,
+    default_results: usize,
+}
+
+impl WebSearchTool {
+    pub fn new(provider: Arc<dyn SearchProvider>, default_results: usize) -> Self {
+        Self {
+            provider,
+            default_results,
+        }
+    }
+}
+
+#[async_trait]
+impl NativeTool for WebSearchTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "web_search".to_string(),
+            description: "Search the web and return result titles, URLs and snippets. Use it for \
+                current events or facts you are unsure about."
+                .to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": {"type": "string", "description": "Search query"},
+                    "max_results": {"type": "integer", "minimum": 1, "maximum": MAX_RESULTS_LIMIT}
+                },
+                "required": ["query"]
+            }),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        let query = required_str(&arguments, "query")?.trim();
+        if query.is_empty() {
+            return Err(Error::tool("Search query must not be empty"));
+        }
+        let max_results = arguments
+            .get("max_results")
+            .and_then(Value::as_u64)
+            .map_or(self.default_results, |n| n as usize)
+            .clamp(1, MAX_RESULTS_LIMIT);
+
+        let results = self.provider.search(query, max_results).await?;
+        Ok(text_result(format_results(query, &results)))
+    }
+}
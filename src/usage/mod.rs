@@ -0,0 +1,478 @@
+//! Usage accounting: the tokens, tool calls and cost of each request, by API key, by
+//! session and by day, and the requests refused for exceeding an API key's rate limit.
+
+use crate::{
+    Error, Result,
+    agent::{AgentHook, HookContext},
+    config::{DatabaseConfig, ModelPrice},
+    db,
+    llm::ChatCompletionResponse,
+    mcp::{McpToolCallRequest, McpToolCallResponse},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use libsql::Connection;
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tracing::info;
+
+/// Tokens used by the LLM calls of a request
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Tally {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Part of `completion_tokens` the model reasoned with
+    pub reasoning_tokens: u64,
+    /// Tools the model called, failed calls included
+    pub tool_calls: u64,
+}
+
+/// What an API key used over a period
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageSummary {
+    /// Requests served, not counting refused ones
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Part of `completion_tokens` reasoning models reasoned with
+    pub reasoning_tokens: u64,
+    pub total_tokens: u64,
+    pub tool_calls: u64,
+    pub cost: f64,
+    /// Requests refused for exceeding the key's rate limit
+    pub rate_limit_hits: u64,
+}
+
+/// What the requests of one UTC day used
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayUsage {
+    pub day: NaiveDate,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+/// What the requests of one session used over a period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SessionTotal {
+    pub session_id: String,
+    #[serde(flatten)]
+    pub usage: UsageSummary,
+}
+
+/// What all requests used over a period, in total, by day and by session
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UsageReport {
+    pub total: UsageSummary,
+    /// Days with recorded requests, oldest first
+    pub days: Vec<DayUsage>,
+    /// Sessions with recorded requests, costliest first
+    pub sessions: Vec<SessionTotal>,
+}
+
+/// Columns adding up rows of the usage table, read back by `summary_from`
+const TOTALS: &str = "COUNT(*) - COALESCE(SUM(rate_limited), 0), \
+    COALESCE(SUM(prompt_tokens), 0), COALESCE(SUM(completion_tokens), 0), \
+    COALESCE(SUM(reasoning_tokens), 0), COALESCE(SUM(tool_calls), 0), \
+    COALESCE(SUM(cost), 0.0), COALESCE(SUM(rate_limited), 0)";
+
+/// The `TOTALS` columns of `row`, starting at column `first`
+fn summary_from(row: &libsql::Row, first: i32) -> Result<UsageSummary> {
+    let prompt_tokens = row.get::<i64>(first + 1)? as u64;
+    let completion_tokens = row.get::<i64>(first + 2)? as u64;
+    Ok(UsageSummary {
+        requests: row.get::<i64>(first)? as u64,
+        prompt_tokens,
+        completion_tokens,
+        reasoning_tokens: row.get::<i64>(first + 3)? as u64,
+        total_tokens: prompt_tokens + completion_tokens,
+        tool_calls: row.get::<i64>(first + 4)? as u64,
+        cost: row.get::<f64>(first + 5)?,
+        rate_limit_hits: row.get::<i64>(first + 6)? as u64,
+    })
+}
+
+/// What a session's requests used with one model
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub reasoning_tokens: u64,
+    pub total_tokens: u64,
+    pub cost: f64,
+}
+
+/// What a session used since it started, in total and by model
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionUsage {
+    #[serde(flatten)]
+    pub total: UsageSummary,
+    /// By model, in name order
+    pub models: Vec<ModelUsage>,
+}
+
+/// Cost of `tally` at `price`
+pub fn cost_of(tally: Tally, price: &ModelPrice) -> f64 {
+    (tally.prompt_tokens as f64 * price.input_per_million
+        + tally.completion_tokens as f64 * price.output_per_million)
+        / 1_000_000.0
+}
+
+pub struct UsageStore {
+    conn: Connection,
+    prices: HashMap<String, ModelPrice>,
+    /// Tallies of the requests being metered, by session
+    metered: Mutex<HashMap<String, Tally>>,
+}
+
+impl UsageStore {
+    /// Opens the store at `db_path` with the default database tuning
+    pub async fn new(db_path: &str, prices: HashMap<String, ModelPrice>) -> Result<Self> {
+        Self::open(db_path, &DatabaseConfig::default(), prices).await
+    }
+
+    /// Opens the store at `db_path`, tuning its connection with `database`
+    pub async fn open(
+        db_path: &str,
+        database: &DatabaseConfig,
+        prices: HashMap<String, ModelPrice>,
+    ) -> Result<Self> {
+        let conn = db::connect(db_path, database).await?;
+        conn.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS usage (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                api_key TEXT,
+                session_id TEXT NOT NULL,
+                model TEXT NOT NULL,
+                prompt_tokens INTEGER NOT NULL,
+                completion_tokens INTEGER NOT NULL,
+                reasoning_tokens INTEGER NOT NULL DEFAULT 0,
+                tool_calls INTEGER NOT NULL DEFAULT 0,
+                cost REAL NOT NULL,
+                rate_limited INTEGER NOT NULL,
+                created_at DATETIME NOT NULL
+            )
+            "#,
+            (),
+        )
+        .await?;
+        // Databases created before reasoning tokens and tool calls were counted lack
+        // their columns
+        let mut columns = conn.query("PRAGMA table_info(usage)", ()).await?;
+        let mut existing = Vec::new();
+        while let Some(row) = columns.next().await? {
+            existing.push(row.get::<String>(1)?);
+        }
+        for column in ["reasoning_tokens", "tool_calls"] {
+            if !existing.iter().any(|name| name == column) {
+                conn.execute(
+                    &format!("ALTER TABLE usage ADD COLUMN {column} INTEGER NOT NULL DEFAULT 0"),
+                    (),
+                )
+                .await?;
+            }
+        }
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_api_key ON usage(api_key, created_at)",
+            (),
+        )
+        .await?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_session ON usage(session_id)",
+            (),
+        )
+        .await?;
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_usage_created_at ON usage(created_at)",
+            (),
+        )
+        .await?;
+        info!("Usage store initialized: {}", db_path);
+        Ok(Self {
+            conn,
+            prices,
+            metered: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Starts tallying the LLM calls made for `session_id`
+    pub fn begin(&self, session_id: &str) {
+        self.metered
+            .lock()
+            .unwrap()
+            .insert(session_id.to_string(), Tally::default());
+    }
+
+    /// Stops tallying `session_id`, returning what its LLM calls used
+    pub fn take(&self, session_id: &str) -> Tally {
+        self.metered
+            .lock()
+            .unwrap()
+            .remove(session_id)
+            .unwrap_or_default()
+    }
+
+    /// Adds the tokens of an LLM reply to its session's tally, if it is being metered
+    fn add(&self, session_id: &str, response: &ChatCompletionResponse) {
+        let Some(usage) = &response.usage else {
+            return;
+        };
+        if let Some(tally) = self.metered.lock().unwrap().get_mut(session_id) {
+            tally.prompt_tokens += u64::from(usage.prompt_tokens);
+            tally.completion_tokens += u64::from(usage.completion_tokens);
+            tally.reasoning_tokens += u64::from(usage.reasoning_tokens.unwrap_or(0));
+        }
+    }
+
+    /// Counts a tool call in its session's tally, if it is being metered
+    fn add_tool_call(&self, session_id: &str) {
+        if let Some(tally) = self.metered.lock().unwrap().get_mut(session_id) {
+            tally.tool_calls += 1;
+        }
+    }
+
+    /// Records a request served with `model`, priced from the configured prices
+    pub async fn record_request(
+        &self,
+        api_key: Option<&str>,
+        session_id: &str,
+        model: &str,
+        tally: Tally,
+    ) -> Result<()> {
+        self.record_request_at(api_key, session_id, model, tally, None)
+            .await
+    }
+
+    /// Whether a price is configured for `model`
+    pub fn has_price(&self, model: &str) -> bool {
+        self.prices.contains_key(model)
+    }
+
+    /// Like `record_request`, priced at `listed` when no price is configured for `model`
+    pub async fn record_request_at(
+        &self,
+        api_key: Option<&str>,
+        session_id: &str,
+        model: &str,
+        tally: Tally,
+        listed: Option<ModelPrice>,
+    ) -> Result<()> {
+        let cost = self
+            .prices
+            .get(model)
+            .or(listed.as_ref())
+            .map_or(0.0, |price| cost_of(tally, price));
+        self.insert(api_key, session_id, model, tally, cost, false)
+            .await
+    }
+
+    /// Records a request refused for exceeding `api_key`'s rate limit
+    pub async fn record_rate_limited(&self, api_key: &str, session_id: &str) -> Result<()> {
+        self.insert(Some(api_key), session_id, "", Tally::default(), 0.0, true)
+            .await
+    }
+
+    async fn insert(
+        &self,
+        api_key: Option<&str>,
+        session_id: &str,
+        model: &str,
+        tally: Tally,
+        cost: f64,
+        rate_limited: bool,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT INTO usage (api_key, session_id, model, prompt_tokens, completion_tokens, \
+                 reasoning_tokens, tool_calls, cost, rate_limited, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                libsql::params![
+                    api_key,
+                    session_id,
+                    model,
+                    tally.prompt_tokens as i64,
+                    tally.completion_tokens as i64,
+                    tally.reasoning_tokens as i64,
+                    tally.tool_calls as i64,
+                    cost,
+                    rate_limited as i64,
+                    Utc::now().to_rfc3339(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Requests served for `api_key` since `since`
+    pub async fn requests_since(&self, api_key: &str, since: DateTime<Utc>) -> Result<u64> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT COUNT(*) FROM usage WHERE api_key = ? AND rate_limited = 0 AND created_at >= ?",
+                libsql::params![api_key, since.to_rfc3339()],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => Ok(row.get::<i64>(0)? as u64),
+            None => Ok(0),
+        }
+    }
+
+    /// What `api_key` used from `since` until `until`
+    pub async fn summary(
+        &self,
+        api_key: &str,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> Result<UsageSummary> {
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT {TOTALS} FROM usage \
+                     WHERE api_key = ? AND created_at >= ? AND created_at < ?"
+                ),
+                libsql::params![api_key, since.to_rfc3339(), until.to_rfc3339()],
+            )
+            .await?;
+        match rows.next().await? {
+            Some(row) => summary_from(&row, 0),
+            None => Ok(UsageSummary::default()),
+        }
+    }
+
+    /// What all requests used from `since` until `until`, whatever their API key
+    pub async fn report(&self, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<UsageReport> {
+        let period = || libsql::params![since.to_rfc3339(), until.to_rfc3339()];
+        let mut report = UsageReport::default();
+
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT substr(created_at, 1, 10) AS day, {TOTALS} FROM usage \
+                     WHERE created_at >= ? AND created_at < ? GROUP BY day ORDER BY day"
+                ),
+                period(),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            let day = row.get::<String>(0)?;
+            let usage = summary_from(&row, 1)?;
+            add_to(&mut report.total, &usage);
+            report.days.push(DayUsage {
+                day: NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                    .map_err(|e| Error::internal(format!("Failed to parse day: {e}")))?,
+                usage,
+            });
+        }
+
+        let mut rows = self
+            .conn
+            .query(
+                &format!(
+                    "SELECT session_id, {TOTALS} FROM usage \
+                     WHERE created_at >= ? AND created_at < ? GROUP BY session_id \
+                     ORDER BY SUM(cost) DESC, session_id"
+                ),
+                period(),
+            )
+            .await?;
+        while let Some(row) = rows.next().await? {
+            report.sessions.push(SessionTotal {
+                session_id: row.get(0)?,
+                usage: summary_from(&row, 1)?,
+            });
+        }
+        Ok(report)
+    }
+
+    /// What `session_id` used over all its requests
+    pub async fn session_summary(&self, session_id: &str) -> Result<SessionUsage> {
+        let mut rows = self
+            .conn
+            .query(
+                "SELECT model, COUNT(*) - SUM(rate_limited), SUM(prompt_tokens), \
+                 SUM(completion_tokens), SUM(cost), SUM(rate_limited), SUM(reasoning_tokens) \
+                 FROM usage WHERE session_id = ? GROUP BY model ORDER BY model",
+                libsql::params![session_id],
+            )
+            .await?;
+        let mut usage = SessionUsage::default();
+        while let Some(row) = rows.next().await? {
+            let prompt_tokens = row.get::<i64>(2)? as u64;
+            let completion_tokens = row.get::<i64>(3)? as u64;
+            let model = ModelUsage {
+                model: row.get(0)?,
+                requests: row.get::<i64>(1)? as u64,
+                prompt_tokens,
+                completion_tokens,
+                reasoning_tokens: row.get::<i64>(6)? as u64,
+                total_tokens: prompt_tokens + completion_tokens,
+                cost: row.get(4)?,
+            };
+            usage.total.rate_limit_hits += row.get::<i64>(5)? as u64;
+            // Refused requests are recorded without a model
+            if model.requests == 0 {
+                continue;
+            }
+            usage.total.requests += model.requests;
+            usage.total.prompt_tokens += model.prompt_tokens;
+            usage.total.completion_tokens += model.completion_tokens;
+            usage.total.reasoning_tokens += model.reasoning_tokens;
+            usage.total.total_tokens += model.total_tokens;
+            usage.total.cost += model.cost;
+            usage.models.push(model);
+        }
+        Ok(usage)
+    }
+}
+
+fn add_to(total: &mut UsageSummary, usage: &UsageSummary) {
+    total.requests += usage.requests;
+    total.prompt_tokens += usage.prompt_tokens;
+    total.completion_tokens += usage.completion_tokens;
+    total.reasoning_tokens += usage.reasoning_tokens;
+    total.total_tokens += usage.total_tokens;
+    total.tool_calls += usage.tool_calls;
+    total.cost += usage.cost;
+    total.rate_limit_hits += usage.rate_limit_hits;
+}
+
+/// Tallies the tokens of every LLM call made for the sessions being metered
+pub struct UsageHook {
+    store: Arc<UsageStore>,
+}
+
+impl UsageHook {
+    pub fn new(store: Arc<UsageStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl AgentHook for UsageHook {
+    async fn after_llm_call(
+        &self,
+        ctx: &HookContext,
+        response: &ChatCompletionResponse,
+    ) -> Result<()> {
+        self.store.add(&ctx.session_id, response);
+        Ok(())
+    }
+
+    async fn after_tool(
+        &self,
+        ctx: &HookContext,
+        _call: &McpToolCallRequest,
+        _response: &mut McpToolCallResponse,
+    ) -> Result<()> {
+        self.store.add_tool_call(&ctx.session_id);
+        Ok(())
+    }
+}
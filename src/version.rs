@@ -0,0 +1,96 @@
+use crate::{Result, config::UpdatesConfig};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// Where release information is fetched from
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// What this binary was built from, embedded by the build script
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Short commit hash, or `unknown` when built outside a git checkout
+    pub git_sha: &'static str,
+    /// UTC date of the build, as `YYYY-MM-DD`
+    pub build_date: &'static str,
+}
+
+pub const BUILD: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_sha: env!("JARVIS_GIT_SHA"),
+    build_date: env!("JARVIS_BUILD_DATE"),
+};
+
+/// A published release
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+/// The latest release of `repository` (`owner/name`) on the GitHub API at `api_url`
+pub async fn latest_release(
+    client: &reqwest::Client,
+    api_url: &str,
+    repository: &str,
+) -> Result<Release> {
+    let url = format!(
+        "{}/repos/{repository}/releases/latest",
+        api_url.trim_end_matches('/')
+    );
+    Ok(client
+        .get(url)
+        // GitHub rejects requests without one
+        .header(
+            reqwest::header::USER_AGENT,
+            format!("jarvis/{}", BUILD.version),
+        )
+        .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Whether version `latest` (such as `v1.2.0`) comes after `current`. Pre-release
+/// suffixes are ignored; unparseable versions never count as newer.
+pub fn is_newer(latest: &str, current: &str) -> bool {
+    fn parts(version: &str) -> Option<Vec<u64>> {
+        let version = version.trim().trim_start_matches('v');
+        let release = version.split(['-', '+']).next()?;
+        let mut parts: Vec<u64> = release
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        // 1.2 is 1.2.0
+        while parts.last() == Some(&0) {
+            parts.pop();
+        }
+        Some(parts)
+    }
+    match (parts(latest), parts(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+/// Logs once when `updates.repository` has a release newer than this build
+pub fn spawn_update_check(config: &UpdatesConfig) -> Option<JoinHandle<()>> {
+    if !config.check {
+        return None;
+    }
+    let repository = config.repository.clone();
+    Some(tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        match latest_release(&client, GITHUB_API_URL, &repository).await {
+            Ok(release) if is_newer(&release.tag_name, BUILD.version) => info!(
+                "jarvis {} is available (running {}): {}",
+                release.tag_name, BUILD.version, release.html_url
+            ),
+            Ok(release) => debug!("jarvis is up to date (latest {})", release.tag_name),
+            Err(e) => warn!("Failed to check for a newer jarvis release: {}", e),
+        }
+    }))
+}
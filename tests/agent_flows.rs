@@ -1,16 +1,29 @@
 use jarvis_rust::{
-    config::LlmConfig,
+    agent::{Agent, ProcessOptions},
+    config::{EmptyResponseConfig, LlmConfig, LlmPacingConfig},
     history::HistoryStorage,
-    llm::ChatMessage,
+    llm::{ChatMessage, Function, PacingPolicy, Tool},
     mcp::{McpContent, McpToolCallRequest, McpToolCallResponse},
 };
 use pretty_assertions::assert_eq;
-use std::collections::HashMap;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 use tempfile::TempDir;
-use tokio;
 
 mod common;
-use common::{MockLlmClient, create_mock_chat_response};
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+fn truncated_response(content: &str) -> jarvis_rust::llm::ChatCompletionResponse {
+    let mut response = create_mock_chat_response(content);
+    response.choices[0].finish_reason = Some("Length".to_string());
+    response
+}
 
 /// Test the agent processing a simple request without tool calls
 #[tokio::test]
@@ -22,6 +35,25 @@ async fn test_agent_direct_llm_response() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: Some("You are helpful".to_string()),
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let _mcp_configs: Vec<jarvis_rust::config::McpServerConfig> = vec![]; // No MCP servers
@@ -74,14 +106,14 @@ async fn test_agent_fsm_with_tool_calls() {
     let messages = vec![
         ChatMessage {
             role: "system".to_string(),
-            content: "You are a helpful assistant with access to tools.".to_string(),
+            content: "You are a helpful assistant with access to tools.".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
         },
         ChatMessage {
             role: "user".to_string(),
-            content: "What's the weather in London?".to_string(),
+            content: "What's the weather in London?".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -168,7 +200,7 @@ async fn test_agent_fsm_error_handling() {
 
     let messages = vec![ChatMessage {
         role: "user".to_string(),
-        content: "Test error handling".to_string(),
+        content: "Test error handling".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -187,7 +219,7 @@ async fn test_agent_fsm_error_handling() {
     let mut fsm = AgentStateMachine::new(
         vec![ChatMessage {
             role: "user".to_string(),
-            content: "Test".to_string(),
+            content: "Test".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -343,6 +375,121 @@ async fn test_max_turns_prevention() {
     assert!(context.is_max_turns_reached());
 }
 
+fn tool(name: &str) -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: name.to_string(),
+            description: format!("The {name} tool"),
+            parameters: json!({"type": "object"}),
+        },
+    }
+}
+
+fn weather_agent(mock_llm: MockLlmClient) -> Agent {
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "get_weather".to_string(),
+        create_mock_tool_response("Sunny, 24°C"),
+    );
+    let mut mcp_clients: HashMap<String, Box<dyn jarvis_rust::mcp::McpClient>> = HashMap::new();
+    mcp_clients.insert("weather".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("get_weather".to_string(), "weather".to_string());
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![tool("get_weather")],
+    )
+}
+
+/// Calls after the first wait for the pacing delay
+#[tokio::test]
+async fn test_llm_calls_are_paced() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "get_weather",
+        r#"{"location": "Paris"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("Sunny."));
+    let mut agent = weather_agent(mock_llm).with_pacing(
+        PacingPolicy::new(&LlmPacingConfig {
+            initial_delay_ms: 100,
+            max_delay_ms: 1_000,
+            jitter: false,
+        })
+        .unwrap(),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let started = Instant::now();
+    let reply = agent
+        .process_with_citations("s1", "Weather in Paris?", &history)
+        .await
+        .unwrap();
+
+    assert_eq!(reply.output, "Sunny.");
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+/// The last allowed turn may still answer
+#[tokio::test]
+async fn test_reply_on_the_last_turn_is_kept() {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..4 {
+        mock_llm.add_response(create_mock_tool_call_response(
+            "get_weather",
+            r#"{"location": "Paris"}"#,
+        ));
+    }
+    mock_llm.add_response(create_mock_chat_response("Sunny all week."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = weather_agent(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process("s1", "Weather this week?", &history)
+        .await
+        .unwrap();
+
+    assert_eq!(reply, "Sunny all week.");
+    assert_eq!(requests.lock().unwrap().len(), 5);
+}
+
+/// Runs stop with `MaxTurnsExceeded` once they made `max_turns` LLM calls
+#[tokio::test]
+async fn test_max_turns_is_enforced() {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..3 {
+        mock_llm.add_response(create_mock_tool_call_response(
+            "get_weather",
+            r#"{"location": "Paris"}"#,
+        ));
+    }
+    let requests = mock_llm.requests.clone();
+    let mut agent = weather_agent(mock_llm).with_max_turns(2);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let error = agent
+        .process("s1", "Weather this week?", &history)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        error,
+        jarvis_rust::Error::MaxTurnsExceeded { max_turns: 2 }
+    ));
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_max_turns_config() {
+    let config: jarvis_rust::config::AgentConfig = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(config.max_turns, 5);
+    let config: jarvis_rust::config::AgentConfig = serde_yaml::from_str("max_turns: 12").unwrap();
+    assert_eq!(config.max_turns, 12);
+}
+
 /// Test multiple concurrent sessions
 #[tokio::test]
 async fn test_concurrent_sessions() {
@@ -452,3 +599,359 @@ async fn test_tool_to_client_mapping_logic() {
 
     println!("Tool-to-client mapping logic test passed!");
 }
+
+/// Replies cut off by the token limit are continued and stitched together
+#[tokio::test]
+async fn test_truncated_reply_is_continued() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(truncated_response("The boiler should be kept "));
+    mock_llm.add_response(truncated_response("at 1.5 bar "));
+    mock_llm.add_response(create_mock_chat_response("when cold."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let output = agent
+        .process("s1", "What pressure?", &history)
+        .await
+        .unwrap();
+    assert_eq!(output, "The boiler should be kept at 1.5 bar when cold.");
+
+    let messages = history.list("s1").await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1].content, output);
+
+    // Each continuation replays the reply so far and asks the model to go on
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 3);
+    let continued = &requests[2].messages;
+    let partial = &continued[continued.len() - 2];
+    assert_eq!(partial.role, "assistant");
+    assert_eq!(partial.content, "The boiler should be kept at 1.5 bar ");
+    assert_eq!(continued[continued.len() - 1].role, "user");
+    assert!(requests[2].tools.is_empty());
+}
+
+/// Continuations stop at the configured maximum, returning what was produced
+#[tokio::test]
+async fn test_truncated_reply_continuations_are_capped() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(truncated_response("one, "));
+    mock_llm.add_response(truncated_response("two, "));
+    mock_llm.add_response(create_mock_chat_response("three."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_max_continuations(1);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let output = agent.process("s1", "Count", &history).await.unwrap();
+    assert_eq!(output, "one, two, ");
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+/// Empty replies are retried once with a nudge by default
+#[tokio::test]
+async fn test_empty_reply_is_retried_with_nudge() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("  "));
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let output = agent.process("s1", "Hi", &history).await.unwrap();
+    assert_eq!(output, "Hello!");
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let nudge = requests[1].messages.last().unwrap();
+    assert_eq!(nudge.role, "system");
+    assert_eq!(nudge.content, EmptyResponseConfig::default().nudge);
+}
+
+/// Once retries are used up the request fails instead of returning nothing
+#[tokio::test]
+async fn test_empty_reply_fails_after_retries() {
+    let mock_llm = MockLlmClient::new();
+    let mut no_choices = create_mock_chat_response("");
+    no_choices.choices.clear();
+    mock_llm.add_response(no_choices);
+    mock_llm.add_response(create_mock_chat_response(""));
+    mock_llm.add_response(create_mock_chat_response(""));
+    mock_llm.add_response(create_mock_chat_response("Too late"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_empty_response(EmptyResponseConfig {
+        retries: 2,
+        nudge: "Say something.".to_string(),
+    });
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let error = agent.process("s1", "Hi", &history).await.unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("empty response after 3 attempts")
+    );
+    assert_eq!(requests.lock().unwrap().len(), 3);
+}
+
+/// With no retries configured an empty reply fails right away
+#[tokio::test]
+async fn test_empty_reply_without_retries_fails_immediately() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response(""));
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_empty_response(EmptyResponseConfig {
+        retries: 0,
+        ..EmptyResponseConfig::default()
+    });
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    assert!(agent.process("s1", "Hi", &history).await.is_err());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+/// An empty-reply retry offers the tools the run offered, not the agent's
+#[tokio::test]
+async fn test_empty_reply_retry_keeps_the_run_tools() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response(""));
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = weather_agent(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process_with_options(
+            "s1",
+            "Hi",
+            &history,
+            ProcessOptions {
+                without_tools: true,
+                tools: vec![tool("get_location")],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    for request in requests.iter() {
+        let names: Vec<&str> = request
+            .tools
+            .iter()
+            .map(|tool| tool.function.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["get_location"]);
+    }
+}
+
+/// Calls to tools the run doesn't offer are answered with an error instead of running
+#[tokio::test]
+async fn test_calls_to_tools_the_run_does_not_offer_are_refused() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("get_weather", "{}"));
+    mock_llm.add_response(create_mock_chat_response("I can't check the weather."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = weather_agent(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process_with_options(
+            "s1",
+            "Weather?",
+            &history,
+            ProcessOptions {
+                without_tools: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    let result = requests[1].messages.last().unwrap();
+    assert_eq!(result.role, "tool");
+    assert_eq!(result.content, "Error: Tool 'get_weather' is not available");
+}
+
+/// Tool calls and results of earlier turns are replayed exactly in later requests
+#[tokio::test]
+async fn test_tool_turns_are_replayed_from_history() {
+    let mock_llm = MockLlmClient::new();
+    let mut mixed = create_mock_tool_call_response("get_weather", r#"{"location": "Paris"}"#);
+    mixed.choices[0].message.content = "Checking.".into();
+    mock_llm.add_response(mixed);
+    mock_llm.add_response(create_mock_chat_response("It's sunny in Paris."));
+    mock_llm.add_response(create_mock_chat_response("You're welcome."));
+    let requests = mock_llm.requests.clone();
+
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "get_weather".to_string(),
+        create_mock_tool_response("Sunny, 24°C"),
+    );
+    let mut mcp_clients: HashMap<String, Box<dyn jarvis_rust::mcp::McpClient>> = HashMap::new();
+    mcp_clients.insert("weather".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("get_weather".to_string(), "weather".to_string());
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        Vec::new(),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "Weather in Paris?", &history)
+        .await
+        .unwrap();
+    agent.process("s1", "Thanks", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let turn_of = |messages: &[ChatMessage]| -> Vec<(String, String, Option<String>)> {
+        messages
+            .iter()
+            .filter(|m| m.role != "system")
+            .map(|m| {
+                let call_id = m
+                    .tool_call_id
+                    .clone()
+                    .or_else(|| m.tool_calls.as_ref().map(|calls| calls[0].id.clone()));
+                (m.role.clone(), m.content.to_string(), call_id)
+            })
+            .collect()
+    };
+    let expected_first_turn = turn_of(&requests[1].messages);
+    let replayed = turn_of(&requests[2].messages);
+    assert_eq!(replayed[..3], expected_first_turn[..]);
+    assert_eq!(
+        replayed[3..],
+        [
+            (
+                "assistant".to_string(),
+                "It's sunny in Paris.".to_string(),
+                None
+            ),
+            ("user".to_string(), "Thanks".to_string(), None),
+        ]
+    );
+    assert_eq!(
+        replayed[1],
+        (
+            "assistant".to_string(),
+            "Checking.".to_string(),
+            Some("call_get_weather".to_string())
+        )
+    );
+    assert_eq!(replayed[2].0, "tool");
+}
+
+fn prompt_test_agent(mock_llm: MockLlmClient) -> Agent {
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+}
+
+/// Stored system messages are never replayed next to the current system prompt
+#[tokio::test]
+async fn test_stored_system_messages_are_not_replayed() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hi"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = prompt_test_agent(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    history
+        .save(
+            jarvis_rust::history::Message::system("s1".to_string(), "Old prompt".to_string())
+                .with_metadata(serde_json::json!({"system_prompt": true})),
+        )
+        .await
+        .unwrap();
+
+    agent.process("s1", "Hello", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let system: Vec<&ChatMessage> = requests[0]
+        .messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .collect();
+    assert_eq!(system.len(), 1);
+    assert_ne!(system[0].content, "Old prompt");
+}
+
+/// Without retroactive prompts a session keeps the system prompt it started with
+#[tokio::test]
+async fn test_sessions_keep_their_pinned_system_prompt() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hi"));
+    mock_llm.add_response(create_mock_chat_response("Hi again"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = prompt_test_agent(mock_llm).with_retroactive_system_prompt(false);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    // A new session pins the current prompt
+    agent.process("new", "Hello", &history).await.unwrap();
+    let stored = history.list("new").await.unwrap();
+    assert_eq!(stored[0].role, "system");
+    assert_eq!(
+        stored[0].metadata,
+        Some(serde_json::json!({"system_prompt": true}))
+    );
+    assert_eq!(
+        requests.lock().unwrap()[0].messages[0].content,
+        stored[0].content
+    );
+
+    // An existing session keeps using its pinned prompt
+    history
+        .save(
+            jarvis_rust::history::Message::system("old".to_string(), "Pinned prompt".to_string())
+                .with_metadata(serde_json::json!({"system_prompt": true})),
+        )
+        .await
+        .unwrap();
+    agent.process("old", "Hello", &history).await.unwrap();
+    assert_eq!(history.list("old").await.unwrap().len(), 3);
+    let requests = requests.lock().unwrap();
+    let system: Vec<String> = requests[1]
+        .messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| m.content.to_string())
+        .collect();
+    assert_eq!(system, vec!["Pinned prompt"]);
+}
@@ -9,7 +9,7 @@ use std::collections::HashMap;
 fn create_test_fsm() -> AgentStateMachine {
     let messages = vec![ChatMessage {
         role: "user".to_string(),
-        content: "Test message".to_string(),
+        content: "Test message".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -182,7 +182,7 @@ fn test_agent_context_methods() {
     // Test message addition
     let message = ChatMessage {
         role: "user".to_string(),
-        content: "Test".to_string(),
+        content: "Test".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -286,7 +286,7 @@ fn test_final_content_retrieval() {
     // Add more messages
     fsm.context.messages.push(ChatMessage {
         role: "assistant".to_string(),
-        content: "Assistant response".to_string(),
+        content: "Assistant response".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -315,19 +315,57 @@ fn test_error_handling() {
     assert!(fsm.get_last_error().is_none());
 }
 
+#[tokio::test]
+async fn test_diagnostics_describe_where_the_run_is() {
+    let mut fsm = create_test_fsm();
+    for _ in 0..5 {
+        for event in [
+            AgentEvent::ProcessInput,
+            AgentEvent::LlmRequestedTools,
+            AgentEvent::ToolsExecutionCompleted,
+        ] {
+            fsm.process_event(event, None).await.unwrap();
+        }
+    }
+    fsm.context.set_pending_tool_calls(vec![McpToolCallRequest {
+        name: "test_tool".to_string(),
+        arguments: HashMap::new(),
+    }]);
+
+    let diagnostics = fsm.diagnostics("exceeded 24 loop iterations", 25);
+
+    assert_eq!(diagnostics.state, "ReadyToCallLlm");
+    assert_eq!(
+        diagnostics.last_event.as_deref(),
+        Some("ToolsExecutionCompleted")
+    );
+    assert_eq!(diagnostics.iterations, 25);
+    assert_eq!(
+        diagnostics.pending_tool_calls,
+        vec!["test_tool".to_string()]
+    );
+    // Only the latest transitions are kept
+    assert_eq!(diagnostics.transitions.len(), 8);
+    assert_eq!(
+        diagnostics.transitions.last().unwrap(),
+        "ExecutingTools --ToolsExecutionCompleted--> ReadyToCallLlm"
+    );
+    assert!(!diagnostics.has_llm_response);
+}
+
 #[test]
 fn test_fsm_creation_with_parameters() {
     let messages = vec![
         ChatMessage {
             role: "system".to_string(),
-            content: "System prompt".to_string(),
+            content: "System prompt".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
         },
         ChatMessage {
             role: "user".to_string(),
-            content: "User input".to_string(),
+            content: "User input".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
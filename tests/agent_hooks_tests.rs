@@ -0,0 +1,347 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Error, Result,
+    agent::{Agent, AgentHook, HookContext, ProcessOptions},
+    history::HistoryStorage,
+    llm::{ChatCompletionRequest, ChatCompletionResponse, Function, Tool, ToolChoice},
+    mcp::{McpClient, McpContent, McpToolCallRequest, McpToolCallResponse},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+/// Hook that records every callback it receives
+#[derive(Default)]
+struct RecordingHook {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl AgentHook for RecordingHook {
+    async fn on_request(&self, ctx: &HookContext, input: &str) -> Result<()> {
+        self.record(format!("on_request:{}:{input}", ctx.session_id));
+        Ok(())
+    }
+
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        _request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        self.record(format!("before_llm_call:{}", ctx.turn));
+        Ok(())
+    }
+
+    async fn after_llm_call(
+        &self,
+        ctx: &HookContext,
+        _response: &ChatCompletionResponse,
+    ) -> Result<()> {
+        self.record(format!("after_llm_call:{}", ctx.turn));
+        Ok(())
+    }
+
+    async fn on_commentary(&self, _ctx: &HookContext, content: &str) {
+        self.record(format!("on_commentary:{content}"));
+    }
+
+    async fn before_tool(&self, _ctx: &HookContext, call: &mut McpToolCallRequest) -> Result<()> {
+        self.record(format!("before_tool:{}", call.name));
+        Ok(())
+    }
+
+    async fn after_tool(
+        &self,
+        _ctx: &HookContext,
+        call: &McpToolCallRequest,
+        _response: &mut McpToolCallResponse,
+    ) -> Result<()> {
+        self.record(format!("after_tool:{}", call.name));
+        Ok(())
+    }
+
+    async fn on_complete(&self, _ctx: &HookContext, result: &Result<String>) {
+        self.record(format!("on_complete:{}", result.is_ok()));
+    }
+}
+
+impl RecordingHook {
+    fn record(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+/// Hook that refuses every tool call and rewrites arguments of allowed ones
+struct DenyToolsHook;
+
+#[async_trait]
+impl AgentHook for DenyToolsHook {
+    async fn before_tool(&self, _ctx: &HookContext, call: &mut McpToolCallRequest) -> Result<()> {
+        Err(Error::internal(format!("'{}' is not allowed", call.name)))
+    }
+}
+
+/// Hook that aborts requests containing a forbidden word
+struct BlockInputHook;
+
+#[async_trait]
+impl AgentHook for BlockInputHook {
+    async fn on_request(&self, _ctx: &HookContext, input: &str) -> Result<()> {
+        if input.contains("forbidden") {
+            return Err(Error::internal("input blocked"));
+        }
+        Ok(())
+    }
+}
+
+fn weather_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_weather".to_string(),
+            description: "Gets weather".to_string(),
+            parameters: json!({"type": "object", "properties": {"location": {"type": "string"}}}),
+        },
+    }
+}
+
+fn create_agent_with_weather_tool(mock_llm: MockLlmClient) -> Agent {
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "get_weather".to_string(),
+        create_mock_tool_response("Sunny"),
+    );
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("weather".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("get_weather".to_string(), "weather".to_string());
+
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![weather_tool()],
+    )
+}
+
+#[tokio::test]
+async fn test_hooks_invoked_in_lifecycle_order() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "get_weather",
+        r#"{"location": "London"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("It's sunny"));
+
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    let hook = Arc::new(RecordingHook::default());
+    let events = hook.events.clone();
+    agent.add_hook(hook);
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let result = agent
+        .process("hook-session", "Weather?", &history)
+        .await
+        .unwrap();
+    assert_eq!(result, "It's sunny");
+
+    let events = events.lock().unwrap().clone();
+    assert_eq!(
+        events,
+        vec![
+            "on_request:hook-session:Weather?",
+            "before_llm_call:0",
+            "after_llm_call:0",
+            "before_tool:get_weather",
+            "after_tool:get_weather",
+            "before_llm_call:1",
+            "after_llm_call:1",
+            "on_complete:true",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_before_tool_hook_can_reject_tool() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("get_weather", "{}"));
+    mock_llm.add_response(create_mock_chat_response("I can't check the weather"));
+    let requests = mock_llm.requests.clone();
+
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    agent.add_hook(Arc::new(DenyToolsHook));
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let result = agent.process("deny", "Weather?", &history).await.unwrap();
+    assert_eq!(result, "I can't check the weather");
+
+    // The rejection is reported back to the LLM as the tool result
+    let requests = requests.lock().unwrap();
+    let tool_message = requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .expect("tool result message");
+    assert!(
+        tool_message
+            .content
+            .text()
+            .contains("'get_weather' is not allowed")
+    );
+}
+
+#[tokio::test]
+async fn test_on_request_hook_aborts_run() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    let recorder = RecordingHook::default();
+    let events = recorder.events.clone();
+    agent.add_hook(Arc::new(BlockInputHook));
+    agent.add_hook(Arc::new(recorder));
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let result = agent
+        .process("blocked", "something forbidden", &history)
+        .await;
+
+    assert!(result.is_err());
+    assert!(requests.lock().unwrap().is_empty());
+    // Aborted requests are not persisted
+    assert!(history.list("blocked").await.unwrap().is_empty());
+    // But their run still ends for every hook
+    assert_eq!(*events.lock().unwrap(), vec!["on_complete:false"]);
+}
+
+#[tokio::test]
+async fn test_unknown_forced_tool_is_refused_before_the_run_starts() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    agent.set_system_prompt("You are Jarvis.".to_string());
+    let recorder = RecordingHook::default();
+    let events = recorder.events.clone();
+    agent.add_hook(Arc::new(recorder));
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let result = agent
+        .process_with_options(
+            "forced",
+            "Book a flight",
+            &history,
+            ProcessOptions {
+                tool_choice: Some(ToolChoice::function("book_flight")),
+                ..Default::default()
+            },
+        )
+        .await;
+
+    assert!(matches!(result, Err(Error::ToolNotFound { .. })));
+    assert!(requests.lock().unwrap().is_empty());
+    assert!(history.list("forced").await.unwrap().is_empty());
+    assert!(events.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_after_tool_hook_can_rewrite_response() {
+    struct RedactHook;
+
+    #[async_trait]
+    impl AgentHook for RedactHook {
+        async fn after_tool(
+            &self,
+            _ctx: &HookContext,
+            _call: &McpToolCallRequest,
+            response: &mut McpToolCallResponse,
+        ) -> Result<()> {
+            response.content = vec![McpContent::Text {
+                text: "[redacted]".to_string(),
+            }];
+            Ok(())
+        }
+    }
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("get_weather", "{}"));
+    mock_llm.add_response(create_mock_chat_response("done"));
+    let requests = mock_llm.requests.clone();
+
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    agent.add_hook(Arc::new(RedactHook));
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("redact", "Weather?", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let tool_message = requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .unwrap();
+    assert_eq!(tool_message.content, "[redacted]");
+}
+
+#[tokio::test]
+async fn test_commentary_next_to_tool_calls_is_kept() {
+    let mock_llm = MockLlmClient::new();
+    let mut mixed = create_mock_tool_call_response("get_weather", r#"{"location": "London"}"#);
+    mixed.choices[0].message.content = "Let me check the forecast.".into();
+    mock_llm.add_response(mixed);
+    mock_llm.add_response(create_mock_chat_response("It's sunny"));
+    let requests = mock_llm.requests.clone();
+
+    let mut agent = create_agent_with_weather_tool(mock_llm);
+    let hook = Arc::new(RecordingHook::default());
+    let events = hook.events.clone();
+    agent.add_hook(hook);
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let result = agent.process("mixed", "Weather?", &history).await.unwrap();
+    assert_eq!(result, "It's sunny");
+
+    let events = events.lock().unwrap().clone();
+    assert_eq!(
+        events[2..5],
+        [
+            "after_llm_call:0",
+            "on_commentary:Let me check the forecast.",
+            "before_tool:get_weather",
+        ]
+    );
+
+    // The LLM sees its own commentary next to the tool calls it made
+    {
+        let requests = requests.lock().unwrap();
+        let assistant = requests[1]
+            .messages
+            .iter()
+            .find(|m| m.role == "assistant")
+            .unwrap();
+        assert_eq!(assistant.content, "Let me check the forecast.");
+        assert!(assistant.tool_calls.is_some());
+    }
+
+    let messages = history.list("mixed").await.unwrap();
+    let saved: Vec<(&str, &str)> = messages
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_str()))
+        .collect();
+    assert_eq!(
+        saved,
+        vec![
+            ("user", "Weather?"),
+            ("assistant", "Let me check the forecast."),
+            ("assistant", "It's sunny"),
+        ]
+    );
+    assert_eq!(
+        messages[1].metadata.as_ref().unwrap()["commentary"],
+        json!(true)
+    );
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use jarvis_rust::{
     Error, Result,
     agent::Agent,
-    config::LlmConfig,
+    config::{EmptyResponseConfig, LlmConfig},
     history::HistoryStorage,
     llm::{
         ChatCompletionRequest, ChatCompletionResponse, ChatMessage, Choice,
@@ -69,7 +69,7 @@ fn create_direct_response(content: &str) -> ChatCompletionResponse {
         choices: vec![Choice {
             message: ChatMessage {
                 role: "assistant".to_string(),
-                content: content.to_string(),
+                content: content.into(),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
@@ -78,6 +78,8 @@ fn create_direct_response(content: &str) -> ChatCompletionResponse {
             index: 0,
         }],
         usage: None,
+        provider: None,
+        context_fallback: None,
     }
 }
 
@@ -90,7 +92,7 @@ fn create_tool_request_response(tool_name: &str, tool_args: &str) -> ChatComplet
         choices: vec![Choice {
             message: ChatMessage {
                 role: "assistant".to_string(),
-                content: "".to_string(),
+                content: "".into(),
                 tool_calls: Some(vec![ToolCall {
                     id: "call_123".to_string(),
                     call_type: "function".to_string(),
@@ -106,6 +108,8 @@ fn create_tool_request_response(tool_name: &str, tool_args: &str) -> ChatComplet
             index: 0,
         }],
         usage: None,
+        provider: None,
+        context_fallback: None,
     }
 }
 
@@ -120,6 +124,25 @@ async fn test_agent_process_llm_responds_directly() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let mock_llm = MockLlmClient::new();
@@ -167,6 +190,25 @@ async fn test_agent_process_llm_requests_mcp_tool_success() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let mock_llm = MockLlmClient::new();
@@ -241,6 +283,25 @@ async fn test_agent_process_llm_requests_mcp_tool_mcp_client_fails() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let mock_llm = MockLlmClient::new();
@@ -313,6 +374,25 @@ async fn test_agent_process_sequential_tool_calls() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let mock_llm = MockLlmClient::new();
@@ -409,6 +489,25 @@ async fn test_agent_process_max_turns_exceeded() {
         api_key: "test-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     };
 
     let mock_llm = MockLlmClient::new();
@@ -460,13 +559,10 @@ async fn test_agent_process_max_turns_exceeded() {
     // Process request - should fail with max turns exceeded
     let result = agent.process("test-session", "Start loop", &history).await;
 
-    assert!(result.is_err());
-    let error = result.unwrap_err();
-    assert!(
-        error
-            .to_string()
-            .contains("exceeded maximum interaction turns")
-    );
+    assert!(matches!(
+        result,
+        Err(jarvis_rust::Error::MaxTurnsExceeded { max_turns: 5 })
+    ));
 }
 
 // Mock MCP client for testing
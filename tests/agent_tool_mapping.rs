@@ -5,7 +5,6 @@ use jarvis_rust::{
 };
 use pretty_assertions::assert_eq;
 use std::collections::HashMap;
-use tokio;
 
 mod common;
 use common::{MockLlmClient, MockMcpClient, create_mock_mcp_tool};
@@ -0,0 +1,223 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{ApiKeyConfig, ServerConfig},
+    history::HistoryStorage,
+    llm::ChatCompletionRequest,
+    server::handlers::{AppState, inference},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn keys() -> Vec<ApiKeyConfig> {
+    vec![
+        ApiKeyConfig {
+            name: "cheap".to_string(),
+            key: "cheap-key".to_string(),
+            models: vec!["gpt-4o-mini".to_string()],
+            default_model: Some("gpt-4o-mini".to_string()),
+            requests_per_minute: None,
+            admin: false,
+        },
+        ApiKeyConfig {
+            name: "admin".to_string(),
+            key: "admin-key".to_string(),
+            models: Vec::new(),
+            default_model: None,
+            requests_per_minute: None,
+            admin: false,
+        },
+    ]
+}
+
+type Requests = Arc<std::sync::Mutex<Vec<ChatCompletionRequest>>>;
+
+async fn app(api_keys: Vec<ApiKeyConfig>) -> (Router, Requests) {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    mock_llm.add_response(create_mock_chat_response("Hello again!"));
+    let requests = mock_llm.requests.clone();
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_model("gpt-4o");
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(api_keys),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+    (app, requests)
+}
+
+async fn post(app: Router, key: Option<&str>, body: Value) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(key) = key {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+    }
+    let response = app
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn requested_models(requests: &Requests) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.model.clone())
+        .collect()
+}
+
+#[test]
+fn test_api_keys_config_parse() {
+    let server: ServerConfig = serde_yaml::from_str(
+        r#"
+api_keys:
+  - name: "cheap"
+    key: "cheap-key"
+    models: ["gpt-4o-mini"]
+    default_model: "gpt-4o-mini"
+    requests_per_minute: 10
+  - name: "admin"
+    key: "admin-key"
+"#,
+    )
+    .unwrap();
+    assert_eq!(server.api_keys.len(), 2);
+    assert!(server.api_keys[0].allows("gpt-4o-mini"));
+    assert!(!server.api_keys[0].allows("gpt-4o"));
+    assert!(server.api_keys[1].allows("gpt-4o"));
+    assert_eq!(server.api_keys[0].requests_per_minute, Some(10));
+    assert_eq!(server.api_keys[1].default_model, None);
+    assert_eq!(server.api_keys[1].requests_per_minute, None);
+
+    let server: ServerConfig = serde_yaml::from_str("port: 8080").unwrap();
+    assert!(server.api_keys.is_empty());
+}
+
+#[tokio::test]
+async fn test_requests_without_known_key_are_rejected() {
+    let (app, requests) = app(keys()).await;
+
+    let (status, body) = post(app.clone(), None, json!({"input": "Hi"})).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["error"], "Missing or unknown API key");
+
+    let (status, _) = post(app, Some("stolen-key"), json!({"input": "Hi"})).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_key_default_model_is_used() {
+    let (app, requests) = app(keys()).await;
+
+    let (status, body) = post(app, Some("cheap-key"), json!({"input": "Hi"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["output"], "Hello!");
+    assert_eq!(requested_models(&requests), vec!["gpt-4o-mini"]);
+}
+
+#[tokio::test]
+async fn test_disallowed_model_is_forbidden() {
+    let (app, requests) = app(keys()).await;
+
+    let (status, body) = post(
+        app,
+        Some("cheap-key"),
+        json!({"input": "Hi", "model": "gpt-4o"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(body["error"], "This API key may not use model 'gpt-4o'");
+    assert!(requested_models(&requests).is_empty());
+}
+
+#[tokio::test]
+async fn test_configured_model_is_checked_without_override() {
+    let keys = vec![ApiKeyConfig {
+        name: "cheap".to_string(),
+        key: "cheap-key".to_string(),
+        models: vec!["gpt-4o-mini".to_string()],
+        default_model: None,
+        requests_per_minute: None,
+        admin: false,
+    }];
+    let (app, requests) = app(keys).await;
+
+    // Falling back to the configured gpt-4o isn't allowed either
+    let (status, _) = post(app, Some("cheap-key"), json!({"input": "Hi"})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(requested_models(&requests).is_empty());
+}
+
+#[tokio::test]
+async fn test_unrestricted_key_may_pick_any_model() {
+    let (app, requests) = app(keys()).await;
+
+    let (status, _) = post(
+        app,
+        Some("admin-key"),
+        json!({"input": "Hi", "model": "gpt-4.1"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(requested_models(&requests), vec!["gpt-4.1"]);
+}
+
+#[tokio::test]
+async fn test_without_keys_requests_pick_models_freely() {
+    let (app, requests) = app(Vec::new()).await;
+
+    let (status, _) = post(app.clone(), None, json!({"input": "Hi"})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = post(app, None, json!({"input": "Hi", "model": "gpt-4.1"})).await;
+    assert_eq!(status, StatusCode::OK);
+    // An empty model leaves the choice to the LLM client
+    assert_eq!(requested_models(&requests), vec!["", "gpt-4.1"]);
+}
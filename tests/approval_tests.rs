@@ -0,0 +1,189 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::{Agent, ApprovalHandler, ApprovalRequest, approval::WebhookApprovalHandler},
+    config::{ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{Function, Tool},
+    mcp::{McpClient, McpToolCallRequest},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+};
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+};
+
+struct FixedApproval {
+    approved: bool,
+    requests: Mutex<Vec<ApprovalRequest>>,
+}
+
+#[async_trait]
+impl ApprovalHandler for FixedApproval {
+    async fn approve(&self, request: &ApprovalRequest) -> Result<bool> {
+        self.requests.lock().unwrap().push(request.clone());
+        Ok(self.approved)
+    }
+}
+
+fn approval(approved: bool) -> Arc<FixedApproval> {
+    Arc::new(FixedApproval {
+        approved,
+        requests: Mutex::new(Vec::new()),
+    })
+}
+
+/// Agent whose MCP tool `test_tool` requires approval through configuration
+fn agent_requiring_approval(
+    mock_llm: MockLlmClient,
+) -> (Agent, Arc<Mutex<Vec<McpToolCallRequest>>>) {
+    let mock_mcp = MockMcpClient::new();
+    let calls = mock_mcp.calls.clone();
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("server".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("test_tool".to_string(), "server".to_string());
+
+    let mut tools_config = ToolsConfig::default();
+    tools_config.settings.insert(
+        "test_tool".to_string(),
+        ToolSettings {
+            require_approval: Some(true),
+            ..Default::default()
+        },
+    );
+
+    let tool = Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "test_tool".to_string(),
+            description: "Test tool".to_string(),
+            parameters: json!({"type": "object"}),
+        },
+    };
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![tool],
+    )
+    .with_tools_config(tools_config);
+    (agent, calls)
+}
+
+fn llm_calling_tool() -> MockLlmClient {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "test_tool",
+        r#"{"message": "hi"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("done"));
+    mock_llm
+}
+
+fn tool_message(
+    mock_requests: &Arc<Mutex<Vec<jarvis_rust::llm::ChatCompletionRequest>>>,
+) -> String {
+    let requests = mock_requests.lock().unwrap();
+    requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .unwrap()
+        .content
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_approved_tool_runs() {
+    let mock_llm = llm_calling_tool();
+    let requests = mock_llm.requests.clone();
+    let (agent, calls) = agent_requiring_approval(mock_llm);
+    let handler = approval(true);
+    let mut agent = agent.with_approval_handler(handler.clone());
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("s1", "go", &history).await.unwrap();
+
+    assert_eq!(calls.lock().unwrap().len(), 1);
+    let approvals = handler.requests.lock().unwrap();
+    assert_eq!(approvals[0].session_id, "s1");
+    assert_eq!(approvals[0].tool_name, "test_tool");
+    assert_eq!(approvals[0].arguments["message"], json!("hi"));
+    assert!(!tool_message(&requests).contains("not approved"));
+}
+
+#[tokio::test]
+async fn test_denied_tool_is_reported_to_llm() {
+    let mock_llm = llm_calling_tool();
+    let requests = mock_llm.requests.clone();
+    let (agent, calls) = agent_requiring_approval(mock_llm);
+    let mut agent = agent.with_approval_handler(approval(false));
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("s1", "go", &history).await.unwrap();
+
+    assert!(calls.lock().unwrap().is_empty());
+    assert_eq!(
+        tool_message(&requests),
+        "Error: Tool call not approved: denied by the user"
+    );
+}
+
+#[tokio::test]
+async fn test_approval_without_handler_denies() {
+    let mock_llm = llm_calling_tool();
+    let requests = mock_llm.requests.clone();
+    let (mut agent, calls) = agent_requiring_approval(mock_llm);
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("s1", "go", &history).await.unwrap();
+
+    assert!(calls.lock().unwrap().is_empty());
+    assert!(tool_message(&requests).contains("no approval handler is configured"));
+}
+
+#[tokio::test]
+async fn test_webhook_approval_handler() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/approve"))
+        .and(body_partial_json(json!({"tool_name": "send_email"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"approved": true})))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/approve"))
+        .and(body_partial_json(json!({"tool_name": "delete_everything"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"approved": false})))
+        .mount(&server)
+        .await;
+
+    let handler =
+        WebhookApprovalHandler::new(format!("{}/approve", server.uri()), Duration::from_secs(5));
+    let request = |tool: &str| ApprovalRequest {
+        session_id: "s1".to_string(),
+        tool_name: tool.to_string(),
+        arguments: HashMap::new(),
+    };
+    assert!(handler.approve(&request("send_email")).await.unwrap());
+    assert!(
+        !handler
+            .approve(&request("delete_everything"))
+            .await
+            .unwrap()
+    );
+
+    let unreachable =
+        WebhookApprovalHandler::new(format!("{}/missing", server.uri()), Duration::from_secs(5));
+    assert!(unreachable.approve(&request("send_email")).await.is_err());
+}
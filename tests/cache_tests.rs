@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+#[cfg(feature = "knowledge")]
+use jarvis_rust::knowledge::{CachedEmbedder, Embedder};
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    cache::{Cache, DiskCache, MemoryCache, cache_key, create_cache},
+    config::{CacheBackend, CacheConfig, DatabaseConfig},
+    history::HistoryStorage,
+    llm::{CachedLlmClient, ChatCompletionRequest, LlmClient},
+    mcp::{McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tempfile::TempDir;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+#[tokio::test]
+async fn test_memory_cache_evicts_least_recently_used() {
+    let cache = MemoryCache::new(2);
+    cache.set("a", b"1".to_vec(), None).await.unwrap();
+    cache.set("b", b"2".to_vec(), None).await.unwrap();
+    // Reading `a` makes `b` the least recently used
+    assert_eq!(cache.get("a").await.unwrap(), Some(b"1".to_vec()));
+    cache.set("c", b"3".to_vec(), None).await.unwrap();
+
+    assert_eq!(cache.len(), 2);
+    assert_eq!(cache.get("b").await.unwrap(), None);
+    assert_eq!(cache.get("a").await.unwrap(), Some(b"1".to_vec()));
+    assert_eq!(cache.get("c").await.unwrap(), Some(b"3".to_vec()));
+
+    cache.remove("a").await.unwrap();
+    assert_eq!(cache.get("a").await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_memory_cache_expires_entries() {
+    let cache = MemoryCache::new(10);
+    cache
+        .set("gone", b"x".to_vec(), Some(Duration::ZERO))
+        .await
+        .unwrap();
+    cache
+        .set("kept", b"y".to_vec(), Some(Duration::from_secs(60)))
+        .await
+        .unwrap();
+    assert_eq!(cache.get("gone").await.unwrap(), None);
+    assert_eq!(cache.get("kept").await.unwrap(), Some(b"y".to_vec()));
+    assert_eq!(cache.len(), 1);
+}
+
+#[tokio::test]
+async fn test_disk_cache_persists_and_expires() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("cache.db").to_string_lossy().to_string();
+    {
+        let cache = DiskCache::new(&path).await.unwrap();
+        cache.set("kept", b"1".to_vec(), None).await.unwrap();
+        cache.set("kept", b"2".to_vec(), None).await.unwrap();
+        cache
+            .set("gone", b"x".to_vec(), Some(Duration::ZERO))
+            .await
+            .unwrap();
+        cache
+            .set("other", b"y".to_vec(), Some(Duration::ZERO))
+            .await
+            .unwrap();
+    }
+
+    let cache = DiskCache::new(&path).await.unwrap();
+    assert_eq!(cache.get("kept").await.unwrap(), Some(b"2".to_vec()));
+    assert_eq!(cache.get("gone").await.unwrap(), None);
+    assert_eq!(cache.purge_expired().await.unwrap(), 1);
+    cache.remove("kept").await.unwrap();
+    assert_eq!(cache.get("kept").await.unwrap(), None);
+}
+
+#[test]
+fn test_cache_keys_are_stable_and_distinct() {
+    let key = cache_key("tool", &("clock", json!({"zone": "UTC"}))).unwrap();
+    assert_eq!(
+        key,
+        cache_key("tool", &("clock", json!({"zone": "UTC"}))).unwrap()
+    );
+    assert!(key.starts_with("tool:"));
+    // A SHA-256 of the JSON form
+    assert_eq!(key.len(), "tool:".len() + 64);
+    assert_ne!(
+        key,
+        cache_key("tool", &("clock", json!({"zone": "CET"}))).unwrap()
+    );
+    assert_ne!(
+        key,
+        cache_key("llm", &("clock", json!({"zone": "UTC"}))).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_create_cache_follows_config() {
+    let disabled = CacheConfig {
+        backend: CacheBackend::None,
+        ..CacheConfig::default()
+    };
+    assert!(
+        create_cache(&disabled, ":memory:", &DatabaseConfig::default())
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let disk = CacheConfig {
+        backend: CacheBackend::Disk,
+        ..CacheConfig::default()
+    };
+    let cache = create_cache(&disk, ":memory:", &DatabaseConfig::default())
+        .await
+        .unwrap()
+        .unwrap();
+    cache.set("k", b"v".to_vec(), None).await.unwrap();
+    assert_eq!(cache.get("k").await.unwrap(), Some(b"v".to_vec()));
+}
+
+/// Embeds each text as its length, remembering what it was asked for
+#[cfg(feature = "knowledge")]
+#[derive(Default)]
+struct LengthEmbedder {
+    requested: std::sync::Mutex<Vec<Vec<String>>>,
+}
+
+#[cfg(feature = "knowledge")]
+#[async_trait]
+impl Embedder for LengthEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.requested.lock().unwrap().push(texts.to_vec());
+        Ok(texts.iter().map(|t| vec![t.len() as f32]).collect())
+    }
+}
+
+#[cfg(feature = "knowledge")]
+#[tokio::test]
+async fn test_cached_embedder_only_embeds_new_texts() {
+    let inner = Arc::new(LengthEmbedder::default());
+    let embedder = CachedEmbedder::new(
+        inner.clone(),
+        Arc::new(MemoryCache::new(100)),
+        "test-model".to_string(),
+        None,
+    );
+    let texts = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    let first = embedder.embed(&texts(&["a", "bb"])).await.unwrap();
+    assert_eq!(first, vec![vec![1.0], vec![2.0]]);
+    let second = embedder.embed(&texts(&["bb", "ccc", "a"])).await.unwrap();
+    assert_eq!(second, vec![vec![2.0], vec![3.0], vec![1.0]]);
+
+    assert_eq!(
+        *inner.requested.lock().unwrap(),
+        vec![texts(&["a", "bb"]), texts(&["ccc"])]
+    );
+}
+
+fn request(content: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![jarvis_rust::llm::ChatMessage {
+            role: "user".to_string(),
+            content: content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_cached_llm_client_reuses_identical_requests() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    mock_llm.add_response(create_mock_chat_response("Bye!"));
+    let requests = mock_llm.requests.clone();
+    let client = CachedLlmClient::new(
+        Box::new(mock_llm),
+        Arc::new(MemoryCache::new(10)),
+        "gpt-4".to_string(),
+        None,
+    );
+
+    let first = client.create_chat_completion(request("Hi")).await.unwrap();
+    let again = client.create_chat_completion(request("Hi")).await.unwrap();
+    let other = client.create_chat_completion(request("Bye")).await.unwrap();
+
+    assert_eq!(first.choices[0].message.content, "Hello!");
+    assert_eq!(again.choices[0].message.content, "Hello!");
+    assert_eq!(other.choices[0].message.content, "Bye!");
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+/// Counts how often it really ran
+struct ClockTool {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(text_result("08:00"))
+    }
+}
+
+#[tokio::test]
+async fn test_agent_reuses_cached_tool_results() {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..2 {
+        mock_llm.add_response(create_mock_tool_call_response(
+            "clock",
+            r#"{"zone": "UTC"}"#,
+        ));
+        mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    }
+    let requests = mock_llm.requests.clone();
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_tool_cache(
+        Arc::new(MemoryCache::new(10)),
+        vec!["clock".to_string()],
+        None,
+    );
+    agent.register_native_tool(Arc::new(ClockTool {
+        calls: calls.clone(),
+    }));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent.process("s1", "Time?", &history).await.unwrap();
+    agent.process("s2", "Time?", &history).await.unwrap();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    let requests = requests.lock().unwrap();
+    let tool_message = requests[3]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .unwrap();
+    assert_eq!(tool_message.content, "08:00");
+}
@@ -0,0 +1,76 @@
+use jarvis_rust::{
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        calculator::{CalculatorTool, evaluate},
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[test]
+fn test_evaluate_arithmetic_and_functions() {
+    let none = HashMap::new();
+    assert_eq!(evaluate("(12.5 * 4) / 2", &none).unwrap(), 25.0);
+    assert_eq!(evaluate("2 ^ 10", &none).unwrap(), 1024.0);
+    assert_eq!(evaluate("sqrt(144) + abs(-3)", &none).unwrap(), 15.0);
+    assert_eq!(evaluate("max(1, 7, 3)", &none).unwrap(), 7.0);
+    assert!((evaluate("ln(e())", &none).unwrap() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_evaluate_variables() {
+    let vars = HashMap::from([("price".to_string(), 80.0), ("tip".to_string(), 0.15)]);
+    assert_eq!(evaluate("price * (1 + tip)", &vars).unwrap(), 92.0);
+    assert!(evaluate("price * tax", &vars).is_err());
+}
+
+#[test]
+fn test_evaluate_rejects_invalid_input() {
+    let none = HashMap::new();
+    assert!(evaluate("2 +", &none).is_err());
+    assert!(evaluate("1 / 0", &none).is_err());
+    assert!(evaluate("print(1)", &none).is_err());
+    assert!(evaluate(&"1+".repeat(600), &none).is_err());
+}
+
+#[tokio::test]
+async fn test_calculate_tool_output() {
+    let ctx = ToolContext::new("s1", None);
+    let response = CalculatorTool
+        .call(args(json!({"expression": "0.1 + 0.2"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "0.1 + 0.2 = 0.3");
+
+    let response = CalculatorTool
+        .call(
+            args(json!({"expression": "hours * rate", "variables": {"hours": 7.5, "rate": 40}})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "hours * rate = 300");
+
+    assert!(
+        CalculatorTool
+            .call(
+                args(json!({"expression": "x", "variables": {"x": "ten"}})),
+                &ctx
+            )
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,309 @@
+#![cfg(feature = "calendar")]
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use jarvis_rust::{
+    Result,
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        calendar::{
+            CalDavBackend, CalendarBackend, CalendarEvent, CreateEventTool, GoogleCalendarBackend,
+            GoogleCredentials, ListEventsTool, NewEvent, build_ics, extract_calendar_data,
+            parse_ics_events,
+        },
+        datetime::DateTimeSettings,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_string_contains, header, method, path, query_param},
+};
+
+const ICS: &str = "BEGIN:VCALENDAR\r\n\
+VERSION:2.0\r\n\
+BEGIN:VEVENT\r\n\
+UID:dentist-1\r\n\
+SUMMARY:Dentist\\, check-up\r\n\
+DTSTART;TZID=Europe/Berlin:20261016T090000\r\n\
+DTEND;TZID=Europe/Berlin:20261016T100000\r\n\
+LOCATION:Main\r\n\x20\x20Street 1\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+UID:holiday\r\n\
+SUMMARY:Holiday\r\n\
+DTSTART;VALUE=DATE:20261019\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+
+fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+fn settings() -> DateTimeSettings {
+    // Thursday 2026-10-15 12:00 UTC
+    DateTimeSettings::new(chrono_tz::Europe::Berlin).with_clock(|| utc(2026, 10, 15, 12, 0))
+}
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_ics_events() {
+    let events = parse_ics_events(ICS);
+    assert_eq!(
+        events,
+        vec![
+            CalendarEvent {
+                uid: "dentist-1".to_string(),
+                title: "Dentist, check-up".to_string(),
+                start: utc(2026, 10, 16, 7, 0),
+                end: utc(2026, 10, 16, 8, 0),
+                all_day: false,
+                location: Some("Main Street 1".to_string()),
+            },
+            CalendarEvent {
+                uid: "holiday".to_string(),
+                title: "Holiday".to_string(),
+                start: utc(2026, 10, 19, 0, 0),
+                end: utc(2026, 10, 20, 0, 0),
+                all_day: true,
+                location: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_extract_calendar_data_handles_prefixes_and_escaping() {
+    let xml = r#"<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+<d:response><d:propstat><d:prop><cal:calendar-data>BEGIN:VCALENDAR&#13;
+SUMMARY:Tom &amp; Jerry&#13;
+END:VCALENDAR</cal:calendar-data></d:prop></d:propstat></d:response>
+<d:response><d:propstat><d:prop><C:calendar-data xmlns:C="urn:ietf:params:xml:ns:caldav"><![CDATA[BEGIN:VCALENDAR]]></C:calendar-data></d:prop></d:propstat></d:response>
+</d:multistatus>"#;
+    assert_eq!(
+        extract_calendar_data(xml),
+        vec![
+            "BEGIN:VCALENDAR\r\nSUMMARY:Tom & Jerry\r\nEND:VCALENDAR".to_string(),
+            "BEGIN:VCALENDAR".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_build_ics_round_trips() {
+    let event = NewEvent {
+        title: "Lunch; with Ana".to_string(),
+        start: utc(2026, 10, 20, 11, 0),
+        end: utc(2026, 10, 20, 12, 0),
+        location: Some("Café".to_string()),
+        description: None,
+    };
+    let ics = build_ics("uid-1", &event, utc(2026, 10, 15, 12, 0));
+    assert!(ics.contains("DTSTART:20261020T110000Z\r\n"));
+    let parsed = parse_ics_events(&ics);
+    assert_eq!(parsed[0].title, "Lunch; with Ana");
+    assert_eq!(parsed[0].start, event.start);
+    assert_eq!(parsed[0].location.as_deref(), Some("Café"));
+}
+
+#[tokio::test]
+async fn test_caldav_backend() {
+    let server = MockServer::start().await;
+    let escaped = ICS.replace('&', "&amp;").replace('<', "&lt;");
+    Mock::given(method("REPORT"))
+        .and(path("/cal/personal/"))
+        .and(header("Depth", "1"))
+        .and(body_string_contains(
+            r#"<c:time-range start="20261015T000000Z" end="20261022T000000Z"/>"#,
+        ))
+        .respond_with(ResponseTemplate::new(207).set_body_string(format!(
+            r#"<d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:response><d:propstat><d:prop><c:calendar-data>{escaped}</c:calendar-data></d:prop></d:propstat></d:response></d:multistatus>"#
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(header("If-None-Match", "*"))
+        .and(body_string_contains("SUMMARY:Gym"))
+        .respond_with(ResponseTemplate::new(201))
+        .mount(&server)
+        .await;
+
+    let backend = CalDavBackend::new(
+        format!("{}/cal/personal", server.uri()),
+        Some("me".to_string()),
+        Some("secret".to_string()),
+    );
+    let events = backend
+        .list_events(utc(2026, 10, 15, 0, 0), utc(2026, 10, 22, 0, 0))
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].title, "Dentist, check-up");
+
+    let created = backend
+        .create_event(&NewEvent {
+            title: "Gym".to_string(),
+            start: utc(2026, 10, 16, 17, 0),
+            end: utc(2026, 10, 16, 18, 0),
+            location: None,
+            description: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(created.title, "Gym");
+    assert!(created.uid.ends_with("@jarvis"));
+}
+
+#[tokio::test]
+async fn test_google_backend() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/token"))
+        .and(body_string_contains("grant_type=refresh_token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"access_token": "tok"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/calendar/v3/calendars/primary/events"))
+        .and(header("Authorization", "Bearer tok"))
+        .and(query_param("singleEvents", "true"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": [
+            {"id": "a", "summary": "Standup",
+             "start": {"dateTime": "2026-10-16T09:00:00+02:00"},
+             "end": {"dateTime": "2026-10-16T09:15:00+02:00"}},
+            {"id": "b", "summary": "Trip",
+             "start": {"date": "2026-10-17"}, "end": {"date": "2026-10-18"}}
+        ]})))
+        .mount(&server)
+        .await;
+
+    let backend = GoogleCalendarBackend::new(
+        GoogleCredentials {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            refresh_token: "refresh".to_string(),
+        },
+        "primary".to_string(),
+        Some(server.uri()),
+    );
+    let events = backend
+        .list_events(utc(2026, 10, 15, 0, 0), utc(2026, 10, 22, 0, 0))
+        .await
+        .unwrap();
+    assert_eq!(events[0].start, utc(2026, 10, 16, 7, 0));
+    assert!(events[1].all_day);
+}
+
+#[derive(Default)]
+struct MemoryCalendar {
+    ranges: Mutex<Vec<(DateTime<Utc>, DateTime<Utc>)>>,
+    created: Mutex<Vec<NewEvent>>,
+}
+
+#[async_trait]
+impl CalendarBackend for MemoryCalendar {
+    async fn list_events(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<CalendarEvent>> {
+        self.ranges.lock().unwrap().push((start, end));
+        Ok(parse_ics_events(ICS))
+    }
+
+    async fn create_event(&self, event: &NewEvent) -> Result<CalendarEvent> {
+        self.created.lock().unwrap().push(event.clone());
+        Ok(CalendarEvent {
+            uid: "new".to_string(),
+            title: event.title.clone(),
+            start: event.start,
+            end: event.end,
+            all_day: false,
+            location: event.location.clone(),
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_list_events_tool_uses_configured_timezone() {
+    let calendar = Arc::new(MemoryCalendar::default());
+    let tool = ListEventsTool::new(calendar.clone(), settings());
+    let ctx = ToolContext::new("s1", None);
+
+    let response = tool.call(HashMap::new(), &ctx).await.unwrap();
+    assert_eq!(
+        text(&response),
+        "Fri 2026-10-16 09:00–10:00 Dentist, check-up @ Main Street 1\n\
+         Mon 2026-10-19 (all day) Holiday"
+    );
+    assert_eq!(
+        calendar.ranges.lock().unwrap()[0],
+        (utc(2026, 10, 15, 12, 0), utc(2026, 10, 22, 12, 0))
+    );
+
+    let new_york = tool
+        .call(args(json!({"timezone": "America/New_York"})), &ctx)
+        .await
+        .unwrap();
+    assert!(text(&new_york).starts_with("Fri 2026-10-16 03:00–04:00"));
+}
+
+#[tokio::test]
+async fn test_create_event_tool() {
+    let calendar = Arc::new(MemoryCalendar::default());
+    let tool = CreateEventTool::new(calendar.clone(), settings());
+    assert!(tool.requires_approval());
+    let ctx = ToolContext::new("s1", None);
+
+    let response = tool
+        .call(
+            args(json!({"title": "Gym", "start": "2026-10-16 18:00", "duration_minutes": 90})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&response),
+        "Created event: Fri 2026-10-16 18:00–19:30 Gym"
+    );
+    assert_eq!(
+        calendar.created.lock().unwrap()[0].start,
+        utc(2026, 10, 16, 16, 0)
+    );
+
+    assert!(
+        tool.call(
+            args(json!({"title": "Bad", "start": "2026-10-16 18:00", "end": "2026-10-16 17:00"})),
+            &ctx
+        )
+        .await
+        .is_err()
+    );
+
+    for minutes in [1e12, -1e300] {
+        let error = tool
+            .call(
+                args(json!({"title": "Forever", "start": "2026-10-16 18:00", "duration_minutes": minutes})),
+                &ctx,
+            )
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("duration_minutes out of range"), "{error}");
+    }
+    assert_eq!(calendar.created.lock().unwrap().len(), 1);
+}
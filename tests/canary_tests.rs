@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+#[cfg(feature = "knowledge")]
+use jarvis_rust::knowledge::Embedder;
+use jarvis_rust::{
+    Result,
+    canary::{Canary, CanaryStore, word_similarity},
+    config::{CanaryConfig, CanaryProbeConfig},
+    notifications::{Notification, NotificationSink},
+};
+use pretty_assertions::assert_eq;
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+#[derive(Default)]
+struct RecordingSink {
+    sent: Mutex<Vec<Notification>>,
+}
+
+#[async_trait]
+impl NotificationSink for RecordingSink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        self.sent.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+/// Embeds text by whether it mentions Paris, so answers about it are alike
+#[cfg(feature = "knowledge")]
+struct ParisEmbedder;
+
+#[cfg(feature = "knowledge")]
+#[async_trait]
+impl Embedder for ParisEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| match text.contains("Paris") {
+                true => vec![1.0, 0.0],
+                false => vec![0.0, 1.0],
+            })
+            .collect())
+    }
+}
+
+fn config() -> CanaryConfig {
+    CanaryConfig {
+        enabled: true,
+        probes: vec![CanaryProbeConfig {
+            name: "capital".to_string(),
+            prompt: "What is the capital of France?".to_string(),
+        }],
+        ..Default::default()
+    }
+}
+
+fn canary(replies: &[&str], store: Arc<CanaryStore>) -> (Canary, Arc<RecordingSink>) {
+    let mock_llm = MockLlmClient::new();
+    for reply in replies {
+        mock_llm.add_response(create_mock_chat_response(reply));
+    }
+    let sink = Arc::new(RecordingSink::default());
+    let canary = Canary::new(Arc::new(mock_llm), "gpt-4o".to_string(), store, &config())
+        .with_notifier(sink.clone());
+    (canary, sink)
+}
+
+#[test]
+fn test_word_similarity() {
+    assert_eq!(
+        word_similarity("The capital is Paris.", "the capital is paris"),
+        1.0
+    );
+    assert_eq!(word_similarity("Paris", "Lyon"), 0.0);
+    assert_eq!(word_similarity("It is Paris", "It is Lyon"), 0.5);
+}
+
+#[tokio::test]
+async fn test_first_answer_becomes_the_baseline() {
+    let store = Arc::new(CanaryStore::new(":memory:").await.unwrap());
+    let (canary, sink) = canary(&["The capital of France is Paris."], store.clone());
+
+    let report = canary.run().await.unwrap();
+    assert_eq!(report.drifted().count(), 0);
+    assert_eq!(report.probes[0].similarity, None);
+    let baseline = store.baseline("capital", "gpt-4o").await.unwrap().unwrap();
+    assert_eq!(baseline.answer, "The capital of France is Paris.");
+    // Baselines are kept per model
+    assert!(
+        store
+            .baseline("capital", "gpt-4.1")
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert!(sink.sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_drifted_answers_are_notified() {
+    let store = Arc::new(CanaryStore::new(":memory:").await.unwrap());
+    let (canary, sink) = canary(
+        &[
+            "The capital of France is Paris.",
+            "The capital of France is Paris.",
+            "I cannot help with geography questions.",
+        ],
+        store,
+    );
+
+    canary.run().await.unwrap();
+    let report = canary.run().await.unwrap();
+    assert_eq!(report.probes[0].similarity, Some(1.0));
+    assert!(sink.sent.lock().unwrap().is_empty());
+
+    let report = canary.run().await.unwrap();
+    let drifted: Vec<&str> = report.drifted().map(|probe| probe.name.as_str()).collect();
+    assert_eq!(drifted, vec!["capital"]);
+    assert!(report.probes[0].drift[0].starts_with("answer similarity 0.00 is below 0.80"));
+    let sent = sink.sent.lock().unwrap();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(sent[0].title, "Model drift detected on gpt-4o");
+    assert!(sent[0].message.starts_with("capital: answer similarity"));
+}
+
+#[cfg(feature = "knowledge")]
+#[tokio::test]
+async fn test_embeddings_tolerate_rewording() {
+    let store = Arc::new(CanaryStore::new(":memory:").await.unwrap());
+    let (canary, sink) = canary(&["Paris.", "It's Paris, of course!"], store);
+    let canary = canary.with_embedder(Arc::new(ParisEmbedder));
+
+    canary.run().await.unwrap();
+    let report = canary.run().await.unwrap();
+    assert_eq!(report.probes[0].similarity, Some(1.0));
+    assert_eq!(report.drifted().count(), 0);
+    assert!(sink.sent.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_probes_count_as_drifted() {
+    let store = Arc::new(CanaryStore::new(":memory:").await.unwrap());
+    let (canary, sink) = canary(&[], store);
+
+    let report = canary.run().await.unwrap();
+    assert_eq!(
+        report.probes[0].drift,
+        vec!["request failed: LLM error: No more mock responses available"]
+    );
+    assert_eq!(sink.sent.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,199 @@
+use jarvis_rust::{
+    agent::Agent,
+    chaos::{Chaos, ChaosLlmClient},
+    config::ChaosConfig,
+    history::{HistoryStorage, Message, StorageStatus},
+    llm::{ChatCompletionRequest, Function, LlmClient, Tool},
+    mcp::McpClient,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+fn chaos(config: ChaosConfig) -> Arc<Chaos> {
+    Arc::new(Chaos::new(ChaosConfig {
+        enabled: true,
+        seed: Some(42),
+        ..config
+    }))
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: Vec::new(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_chaos_config_parse() {
+    let config: ChaosConfig = serde_yaml::from_str(
+        r#"
+enabled: true
+seed: 7
+llm_error_rate: 0.2
+llm_latency_ms: 500
+mcp_timeout_rate: 0.1
+db_error_rate: 0.05
+"#,
+    )
+    .unwrap();
+    assert!(config.enabled);
+    assert_eq!(config.seed, Some(7));
+    assert_eq!(config.llm_error_rate, 0.2);
+    assert_eq!(config.llm_latency_ms, 500);
+    assert_eq!(config.mcp_timeout_rate, 0.1);
+    assert_eq!(config.mcp_latency_ms, 0);
+    assert_eq!(config.db_error_rate, 0.05);
+
+    assert!(Chaos::from_config(&ChaosConfig::default()).is_none());
+}
+
+#[tokio::test]
+async fn test_seeded_chaos_fails_the_same_calls_at_the_rate() {
+    let config = ChaosConfig {
+        llm_error_rate: 0.5,
+        ..Default::default()
+    };
+    let (first, second) = (chaos(config.clone()), chaos(config));
+
+    let mut outcomes = Vec::new();
+    for _ in 0..200 {
+        let outcome = first.llm_call().await.is_ok();
+        assert_eq!(second.llm_call().await.is_ok(), outcome);
+        outcomes.push(outcome);
+    }
+    let failures = outcomes.iter().filter(|ok| !**ok).count();
+    assert!((60..=140).contains(&failures), "{failures} failures");
+}
+
+#[tokio::test]
+async fn test_llm_faults_and_latency() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let client = ChaosLlmClient::new(
+        Box::new(mock_llm),
+        chaos(ChaosConfig {
+            llm_latency_ms: 50,
+            ..Default::default()
+        }),
+    );
+    let started = Instant::now();
+    assert!(client.create_chat_completion(request()).await.is_ok());
+    assert!(started.elapsed() >= Duration::from_millis(50));
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let client = ChaosLlmClient::new(
+        Box::new(mock_llm),
+        chaos(ChaosConfig {
+            llm_error_rate: 1.0,
+            ..Default::default()
+        }),
+    );
+    let error = client.create_chat_completion(request()).await.unwrap_err();
+    assert!(error.to_string().contains("500 Internal Server Error"));
+    // The failure happens before the request is sent
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_failing_llm_fails_the_run() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_chaos(chaos(ChaosConfig {
+        llm_error_rate: 1.0,
+        ..Default::default()
+    }));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    assert!(agent.process("s1", "Hi", &history).await.is_err());
+}
+
+#[tokio::test]
+async fn test_timed_out_tool_is_reported_to_the_llm() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("get_weather", "{}"));
+    mock_llm.add_response(create_mock_chat_response("The weather service is down."));
+    let requests = mock_llm.requests.clone();
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "get_weather".to_string(),
+        create_mock_tool_response("Sunny"),
+    );
+    let calls = mock_mcp.calls.clone();
+    let mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::from([(
+        "weather".to_string(),
+        Box::new(mock_mcp) as Box<dyn McpClient>,
+    )]);
+    let tool = Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_weather".to_string(),
+            description: "Gets weather".to_string(),
+            parameters: json!({"type": "object"}),
+        },
+    };
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        HashMap::from([("get_weather".to_string(), "weather".to_string())]),
+        vec![tool],
+    )
+    .with_chaos(chaos(ChaosConfig {
+        mcp_timeout_rate: 1.0,
+        ..Default::default()
+    }));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent.process("s1", "Weather?", &history).await.unwrap();
+    assert_eq!(reply, "The weather service is down.");
+    assert!(calls.lock().unwrap().is_empty());
+    let tool_result = requests.lock().unwrap()[1].messages.last().unwrap().clone();
+    assert_eq!(tool_result.role, "tool");
+    assert!(
+        tool_result.content.text().contains("timed out"),
+        "{}",
+        tool_result.content
+    );
+}
+
+#[tokio::test]
+async fn test_failing_database_writes_fall_back_to_memory() {
+    let history = HistoryStorage::new(":memory:")
+        .await
+        .unwrap()
+        .with_chaos(chaos(ChaosConfig {
+            db_error_rate: 1.0,
+            ..Default::default()
+        }));
+
+    history
+        .save(Message::user("s1".to_string(), "Hi".to_string()))
+        .await
+        .unwrap();
+
+    assert_eq!(history.status().await, StorageStatus::Degraded);
+    assert_eq!(history.buffered(), 1);
+    assert_eq!(history.list("s1").await.unwrap()[0].content, "Hi");
+    // Recovery keeps failing while chaos does
+    assert!(history.recover().await.is_err());
+    assert_eq!(history.buffered(), 1);
+}
@@ -0,0 +1,300 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+};
+use jarvis_rust::{
+    Result,
+    agent::{Agent, Citation, citations_from_tool_result},
+    history::{HistoryStorage, Message},
+    mcp::{McpContent, McpResourceContent, McpTool, McpToolCallRequest, McpToolCallResponse},
+    server::handlers::{AppState, inference},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{
+    MockLlmClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_error_response,
+};
+
+fn resource(uri: &str, text: &str) -> McpContent {
+    McpContent::Resource {
+        resource: McpResourceContent {
+            uri: uri.to_string(),
+            text: Some(text.to_string()),
+            blob: None,
+        },
+    }
+}
+
+fn call(name: &str) -> McpToolCallRequest {
+    McpToolCallRequest {
+        name: name.to_string(),
+        arguments: HashMap::new(),
+    }
+}
+
+fn citation(source_id: &str, span: Option<&str>, tool: &str) -> Citation {
+    Citation {
+        source_id: source_id.to_string(),
+        span: span.map(str::to_string),
+        tool: tool.to_string(),
+    }
+}
+
+/// Returns two manual passages as resources
+struct ManualTool;
+
+#[async_trait]
+impl NativeTool for ManualTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "manual".to_string(),
+            description: "Looks up the boiler manual".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(McpToolCallResponse {
+            content: vec![
+                resource("docs/boiler.md#chunk=0", "Keep the pressure at 1.5 bar."),
+                resource("docs/boiler.md#chunk=2", "Bleed radiators yearly."),
+            ],
+            is_error: false,
+        })
+    }
+}
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn agent_with_tools(mock_llm: MockLlmClient) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ManualTool));
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent
+}
+
+#[test]
+fn test_citations_from_tool_result() {
+    let response = McpToolCallResponse {
+        content: vec![
+            McpContent::Text {
+                text: "Found:".to_string(),
+            },
+            resource("docs/a.md#chunk=1", "a"),
+            resource("https://example.com/page", "b"),
+        ],
+        is_error: false,
+    };
+    assert_eq!(
+        citations_from_tool_result(&call("knowledge_search"), &response),
+        vec![
+            citation("docs/a.md", Some("chunk=1"), "knowledge_search"),
+            citation("https://example.com/page", None, "knowledge_search"),
+        ]
+    );
+
+    assert_eq!(
+        citations_from_tool_result(&call("clock"), &text_result("08:00")),
+        vec![citation("tool:clock", None, "clock")]
+    );
+    assert!(
+        citations_from_tool_result(&call("clock"), &create_mock_tool_error_response("boom"))
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_agent_returns_and_persists_citations() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("manual", "{}"));
+    mock_llm.add_response(create_mock_tool_call_response("manual", "{}"));
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("Keep it at 1.5 bar."));
+    mock_llm.add_response(create_mock_chat_response("You're welcome."));
+    let mut agent = agent_with_tools(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process_with_citations("s1", "What pressure should my boiler have?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.output, "Keep it at 1.5 bar.");
+    // Repeated calls cite each source once
+    let expected = vec![
+        citation("docs/boiler.md", Some("chunk=0"), "manual"),
+        citation("docs/boiler.md", Some("chunk=2"), "manual"),
+        citation("tool:clock", None, "clock"),
+    ];
+    assert_eq!(reply.citations, expected);
+
+    let reply = agent
+        .process_with_citations("s1", "Thanks", &history)
+        .await
+        .unwrap();
+    assert!(reply.citations.is_empty());
+
+    let messages = history.list("s1").await.unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0].metadata, None);
+    assert_eq!(
+        messages[1].metadata.as_ref().unwrap()["citations"],
+        json!([
+            {"source_id": "docs/boiler.md", "span": "chunk=0", "tool": "manual"},
+            {"source_id": "docs/boiler.md", "span": "chunk=2", "tool": "manual"},
+            {"source_id": "tool:clock", "tool": "clock"}
+        ])
+    );
+    assert_eq!(messages[3].metadata, None);
+}
+
+#[tokio::test]
+async fn test_history_adds_metadata_column_to_existing_database() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("history.db").to_string_lossy().to_string();
+    {
+        let db = libsql::Builder::new_local(&db_path).build().await.unwrap();
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "CREATE TABLE messages (id INTEGER PRIMARY KEY AUTOINCREMENT, session_id TEXT NOT NULL, \
+             role TEXT NOT NULL, content TEXT NOT NULL, created_at DATETIME NOT NULL)",
+            (),
+        )
+        .await
+        .unwrap();
+        conn.execute(
+            "INSERT INTO messages (session_id, role, content, created_at) VALUES ('s1', 'user', 'Hi', ?)",
+            [chrono::Utc::now().to_rfc3339()],
+        )
+        .await
+        .unwrap();
+    }
+
+    let history = HistoryStorage::new(&db_path).await.unwrap();
+    history
+        .save(
+            Message::assistant("s1".to_string(), "Hello".to_string())
+                .with_metadata(json!({"citations": []})),
+        )
+        .await
+        .unwrap();
+
+    let messages = history.list("s1").await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].metadata, None);
+    assert_eq!(messages[1].metadata, Some(json!({"citations": []})));
+
+    // Reopening a migrated database leaves it alone
+    let reopened = HistoryStorage::new(&db_path).await.unwrap();
+    assert_eq!(reopened.list("s1").await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_inference_response_includes_citations() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent_with_tools(mock_llm))),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new().route("/", post(inference)).with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"session_id": "s1", "input": "What time is it?"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut body: Value = serde_json::from_slice(&body).unwrap();
+    // Every reply names its run
+    assert!(
+        body.as_object_mut()
+            .unwrap()
+            .remove("run_id")
+            .unwrap()
+            .is_string()
+    );
+    assert_eq!(
+        body,
+        json!({
+            "session_id": "s1",
+            "output": "It is 8 o'clock.",
+            "citations": [{"source_id": "tool:clock", "tool": "clock"}]
+        })
+    );
+}
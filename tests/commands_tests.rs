@@ -0,0 +1,292 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, header},
+};
+use jarvis_rust::{
+    agent::Agent,
+    commands::{self, Command, ParsedInput},
+    config::{ApiKeyConfig, CommandsConfig, parse},
+    history::HistoryStorage,
+    llm::{ChatCompletionRequest, Function, Tool},
+    server::handlers::{AppState, inference},
+    sessions::SessionStore,
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn parsed(commands: Vec<Command>, input: &str) -> ParsedInput {
+    ParsedInput {
+        commands,
+        input: input.to_string(),
+    }
+}
+
+#[test]
+fn test_commands_are_split_off_the_input() {
+    assert_eq!(
+        commands::parse("/reset").unwrap(),
+        parsed(vec![Command::Reset], "")
+    );
+    assert_eq!(
+        commands::parse("/model openai/gpt-4o /tools off What's new?").unwrap(),
+        parsed(
+            vec![
+                Command::Model(Some("openai/gpt-4o".to_string())),
+                Command::Tools(false),
+            ],
+            "What's new?"
+        )
+    );
+    assert_eq!(
+        commands::parse("/model default\n/tools on\nHi").unwrap(),
+        parsed(vec![Command::Model(None), Command::Tools(true)], "Hi")
+    );
+    assert_eq!(
+        commands::parse("/help").unwrap(),
+        parsed(vec![Command::Help], "")
+    );
+}
+
+#[test]
+fn test_other_input_is_left_alone() {
+    assert_eq!(
+        commands::parse("What does /reset do?").unwrap(),
+        parsed(Vec::new(), "What does /reset do?")
+    );
+    assert_eq!(
+        commands::parse("/shrug fine").unwrap(),
+        parsed(Vec::new(), "/shrug fine")
+    );
+    assert_eq!(
+        commands::parse("/reset /shrug").unwrap(),
+        parsed(vec![Command::Reset], "/shrug")
+    );
+    assert_eq!(
+        commands::parse("/resetting the router").unwrap(),
+        parsed(Vec::new(), "/resetting the router")
+    );
+}
+
+#[test]
+fn test_misused_commands_fail_with_usage() {
+    assert_eq!(
+        commands::parse("/model").unwrap_err(),
+        "Usage: /model <name>, or /model default"
+    );
+    assert_eq!(
+        commands::parse("/model /reset").unwrap_err(),
+        "Usage: /model <name>, or /model default"
+    );
+    assert_eq!(
+        commands::parse("/tools maybe").unwrap_err(),
+        "Usage: /tools on|off"
+    );
+}
+
+#[test]
+fn test_commands_config() {
+    let config = parse(
+        r#"
+strict: true
+llm:
+  base_url: "https://api.openai.com"
+  api_key: "test-key"
+  model: "gpt-4"
+server: {}
+commands:
+  integrations:
+    telegram: true
+"#,
+    )
+    .unwrap();
+    assert!(!config.commands.enabled);
+    assert!(config.commands.enabled_for(Some("telegram")));
+    assert!(!config.commands.enabled_for(Some("slack")));
+    assert!(!config.commands.enabled_for(None));
+
+    let config = CommandsConfig {
+        enabled: true,
+        integrations: HashMap::from([("slack".to_string(), false)]),
+    };
+    assert!(config.enabled_for(None));
+    assert!(config.enabled_for(Some("telegram")));
+    assert!(!config.enabled_for(Some("slack")));
+}
+
+type Requests = Arc<std::sync::Mutex<Vec<ChatCompletionRequest>>>;
+
+struct App {
+    router: Router,
+    requests: Requests,
+    history: Arc<HistoryStorage>,
+}
+
+async fn app(api_keys: Vec<ApiKeyConfig>) -> App {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..3 {
+        mock_llm.add_response(create_mock_chat_response("Hello!"));
+    }
+    let requests = mock_llm.requests.clone();
+    let weather = Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_weather".to_string(),
+            description: "Gets weather".to_string(),
+            parameters: json!({"type": "object", "properties": {}}),
+        },
+    };
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        vec![weather],
+    )
+    .with_model("gpt-4o");
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(api_keys),
+        usage: None,
+        timeline: None,
+        sessions: Some(Arc::new(SessionStore::new(":memory:").await.unwrap())),
+        examples: Default::default(),
+        commands: Arc::new(CommandsConfig {
+            enabled: false,
+            integrations: HashMap::from([("telegram".to_string(), true)]),
+        }),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let router = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+    App {
+        router,
+        requests,
+        history,
+    }
+}
+
+async fn post(app: &App, key: Option<&str>, body: Value) -> Value {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(key) = key {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+    }
+    let response = app
+        .router
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn telegram(input: &str) -> Value {
+    json!({"session_id": "chat", "input": input, "integration": "telegram"})
+}
+
+#[tokio::test]
+async fn test_commands_change_the_session() {
+    let app = app(Vec::new()).await;
+
+    let body = post(&app, None, telegram("/model gpt-4o-mini")).await;
+    assert_eq!(body["output"], "Now answering with gpt-4o-mini.");
+    let body = post(&app, None, telegram("/tools off")).await;
+    assert_eq!(body["output"], "Tools are off.");
+    // Commands alone don't reach the LLM
+    assert!(app.requests.lock().unwrap().is_empty());
+
+    let body = post(&app, None, telegram("Hi")).await;
+    assert_eq!(body["output"], "Hello!");
+    {
+        let requests = app.requests.lock().unwrap();
+        assert_eq!(requests[0].model, "gpt-4o-mini");
+        assert!(requests[0].tools.is_empty());
+    }
+
+    // Commands before a message apply to it
+    post(&app, None, telegram("/model default /tools on What now?")).await;
+    let requests = app.requests.lock().unwrap();
+    // Left for the LLM client to fill in with the configured model
+    assert_eq!(requests[1].model, "");
+    assert_eq!(requests[1].tools.len(), 1);
+    assert_eq!(requests[1].messages.last().unwrap().content, "What now?");
+}
+
+#[tokio::test]
+async fn test_reset_starts_a_new_conversation() {
+    let app = app(Vec::new()).await;
+    post(&app, None, telegram("Hi")).await;
+    assert_eq!(app.history.list("chat").await.unwrap().len(), 2);
+
+    let body = post(&app, None, telegram("/reset")).await;
+    assert_eq!(body["output"], "Started a new conversation.");
+    assert!(app.history.list("chat").await.unwrap().is_empty());
+    // Deleted, so still recoverable
+    assert_eq!(app.history.list_deleted("chat").await.unwrap().len(), 2);
+
+    let body = post(&app, None, telegram("/help /tools")).await;
+    assert_eq!(body["output"], "Usage: /tools on|off");
+    let body = post(&app, None, telegram("/help")).await;
+    assert_eq!(body["output"], commands::HELP);
+}
+
+#[tokio::test]
+async fn test_commands_only_run_for_enabled_integrations() {
+    let app = app(Vec::new()).await;
+    let body = post(
+        &app,
+        None,
+        json!({"session_id": "chat", "input": "/reset", "integration": "slack"}),
+    )
+    .await;
+    assert_eq!(body["output"], "Hello!");
+    let requests = app.requests.lock().unwrap();
+    assert_eq!(requests[0].messages.last().unwrap().content, "/reset");
+}
+
+#[tokio::test]
+async fn test_models_are_limited_to_the_api_key() {
+    let app = app(vec![ApiKeyConfig {
+        name: "cheap".to_string(),
+        key: "cheap-key".to_string(),
+        models: vec!["gpt-4o-mini".to_string()],
+        default_model: Some("gpt-4o-mini".to_string()),
+        requests_per_minute: None,
+        admin: false,
+    }])
+    .await;
+    let body = post(&app, Some("cheap-key"), telegram("/model gpt-4o")).await;
+    assert_eq!(body["output"], "This API key may not use model 'gpt-4o'");
+
+    post(&app, Some("cheap-key"), telegram("Hi")).await;
+    assert_eq!(app.requests.lock().unwrap()[0].model, "gpt-4o-mini");
+}
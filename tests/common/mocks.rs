@@ -1,7 +1,10 @@
 use async_trait::async_trait;
 use jarvis_rust::{
     Error, Result,
-    llm::{ChatCompletionRequest, ChatCompletionResponse, ChatMessage, Choice, LlmClient},
+    llm::{
+        ChatCompletionRequest, ChatCompletionResponse, ChatMessage, Choice, FunctionCall,
+        LlmClient, ToolCall,
+    },
     mcp::{
         McpClient, McpContent, McpGetPromptRequest, McpGetPromptResponse, McpInitializeRequest,
         McpInitializeResponse, McpPrompt, McpPromptMessage, McpPromptsCapability,
@@ -69,6 +72,7 @@ pub struct MockMcpClient {
     pub tool_responses: Arc<Mutex<HashMap<String, McpToolCallResponse>>>,
     pub tool_errors: Arc<Mutex<HashMap<String, String>>>,
     pub initialize_error: Option<String>,
+    pub calls: Arc<Mutex<Vec<McpToolCallRequest>>>,
 }
 
 impl MockMcpClient {
@@ -79,6 +83,7 @@ impl MockMcpClient {
             tool_responses: Arc::new(Mutex::new(HashMap::new())),
             tool_errors: Arc::new(Mutex::new(HashMap::new())),
             initialize_error: None,
+            calls: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -149,6 +154,8 @@ impl McpClient for MockMcpClient {
     }
 
     async fn call_tool(&self, request: McpToolCallRequest) -> Result<McpToolCallResponse> {
+        self.calls.lock().unwrap().push(request.clone());
+
         let tool_errors = self.tool_errors.lock().unwrap();
         if let Some(error) = tool_errors.get(&request.name) {
             return Err(Error::mcp(error.clone()));
@@ -209,7 +216,7 @@ pub fn create_mock_chat_response(content: &str) -> ChatCompletionResponse {
             index: 0,
             message: ChatMessage {
                 role: "assistant".to_string(),
-                content: content.to_string(),
+                content: content.into(),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
@@ -217,6 +224,38 @@ pub fn create_mock_chat_response(content: &str) -> ChatCompletionResponse {
             finish_reason: Some("stop".to_string()),
         }],
         usage: None,
+        provider: None,
+        context_fallback: None,
+    }
+}
+
+pub fn create_mock_tool_call_response(tool_name: &str, arguments: &str) -> ChatCompletionResponse {
+    ChatCompletionResponse {
+        id: "test-id".to_string(),
+        object: "chat.completion".to_string(),
+        created: 0,
+        model: "test-model".to_string(),
+        choices: vec![Choice {
+            index: 0,
+            message: ChatMessage {
+                role: "assistant".to_string(),
+                content: String::new().into(),
+                tool_calls: Some(vec![ToolCall {
+                    id: format!("call_{tool_name}"),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: tool_name.to_string(),
+                        arguments: arguments.to_string(),
+                    },
+                }]),
+                tool_call_id: None,
+                name: None,
+            },
+            finish_reason: Some("tool_calls".to_string()),
+        }],
+        usage: None,
+        provider: None,
+        context_fallback: None,
     }
 }
 
@@ -232,6 +271,7 @@ pub fn create_mock_mcp_tool(name: &str, description: &str) -> McpTool {
                 }
             }
         }),
+        output_schema: None,
     }
 }
 
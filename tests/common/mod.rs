@@ -1,3 +1,5 @@
+#![allow(dead_code, unused_imports)]
+
 pub mod mocks;
 pub mod test_utils;
 
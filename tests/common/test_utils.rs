@@ -1,4 +1,4 @@
-use jarvis_rust::config::{Config, LlmConfig, LogsConfig, ServerConfig};
+use jarvis_rust::config::{Config, EmptyResponseConfig, LlmConfig, LogsConfig, ServerConfig};
 
 /// Create a test configuration with sensible defaults
 pub fn create_test_config() -> Config {
@@ -7,6 +7,9 @@ pub fn create_test_config() -> Config {
             host: "127.0.0.1".parse().unwrap(),
             port: 8080,
             database_path: ":memory:".to_string(),
+            data_dir: "data".to_string(),
+            api_keys: Vec::new(),
+            database: Default::default(),
             logs: LogsConfig {
                 level: "debug".to_string(),
             },
@@ -17,7 +20,47 @@ pub fn create_test_config() -> Config {
             api_key: "test-api-key".to_string(),
             model: "gpt-4".to_string(),
             system_prompt: Some("You are a helpful assistant.".to_string()),
+            max_continuations: 2,
+            max_context_tokens: None,
+            retry: Default::default(),
+            pacing: Default::default(),
+            request_timeout: 300,
+            connect_timeout: 10,
+            proxy: None,
+            context_fallback: Default::default(),
+            sampling: Default::default(),
+            empty_response: EmptyResponseConfig::default(),
+            structured_output: Default::default(),
+            routing: Default::default(),
+            retroactive_system_prompt: true,
+            ollama: Default::default(),
+            azure: Default::default(),
+            openrouter: Default::default(),
+            reasoning: Default::default(),
+            replay: Default::default(),
+            fallbacks: Vec::new(),
         },
         mcp_servers: vec![],
+        tools: Default::default(),
+        feeds: Vec::new(),
+        schedules: Vec::new(),
+        examples: Vec::new(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        notifications: Default::default(),
+        knowledge: Default::default(),
+        cache: Default::default(),
+        agent: Default::default(),
+        moderation: Default::default(),
+        usage: Default::default(),
+        history: Default::default(),
+        canary: Default::default(),
+        chaos: Default::default(),
+        features: Default::default(),
+        updates: Default::default(),
+        strict: false,
+        include: Vec::new(),
+        auth_presets: Default::default(),
     }
 }
@@ -0,0 +1,195 @@
+use jarvis_rust::{
+    agent::{Agent, JUDGE_PROMPT, parse_confidence},
+    config::{AgentConfig, ConfidenceConfig},
+    history::HistoryStorage,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+#[test]
+fn test_parse_confidence() {
+    assert_eq!(parse_confidence("0.82"), Some(0.82));
+    assert_eq!(parse_confidence("Confidence: 85%"), Some(0.85));
+    assert_eq!(parse_confidence("1"), Some(1.0));
+    assert_eq!(parse_confidence("0."), Some(0.0));
+    assert_eq!(parse_confidence("90"), Some(0.9));
+    assert_eq!(parse_confidence("I can't tell."), None);
+}
+
+#[test]
+fn test_confidence_config_parse() {
+    let config: AgentConfig = serde_yaml::from_str(
+        r#"
+confidence:
+  enabled: true
+  fallback_reply: "I don't know."
+  escalation_webhook: "http://localhost:9000/escalate"
+"#,
+    )
+    .unwrap();
+    assert!(config.confidence.enabled);
+    assert_eq!(config.confidence.threshold, 0.5);
+    assert_eq!(
+        config.confidence.fallback_reply.as_deref(),
+        Some("I don't know.")
+    );
+
+    assert!(!AgentConfig::default().confidence.enabled);
+}
+
+fn scoring_agent(mock_llm: MockLlmClient, confidence: ConfidenceConfig) -> Agent {
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_confidence(&confidence)
+}
+
+#[tokio::test]
+async fn test_confident_reply_is_kept_with_its_score() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response("Paris."));
+    mock_llm.add_response(create_mock_chat_response("0.95"));
+    let mut agent = scoring_agent(
+        mock_llm,
+        ConfidenceConfig {
+            enabled: true,
+            fallback_reply: Some("I don't know.".to_string()),
+            ..Default::default()
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process_with_citations("session", "Capital of France?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.output, "Paris.");
+    assert_eq!(reply.confidence, Some(0.95));
+
+    // The judge sees the reply it scores
+    let judge = requests.lock().unwrap()[1].messages.clone();
+    assert_eq!(judge[judge.len() - 2].content, "Paris.");
+    assert_eq!(judge.last().unwrap().content, JUDGE_PROMPT);
+
+    let messages = history.list("session").await.unwrap();
+    let saved = messages.last().unwrap();
+    assert_eq!(saved.content, "Paris.");
+    assert_eq!(saved.metadata.as_ref().unwrap()["confidence"], json!(0.95));
+}
+
+#[tokio::test]
+async fn test_low_confidence_reply_is_escalated_and_replaced() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/escalate"))
+        .and(body_partial_json(json!({
+            "session_id": "session",
+            "question": "When was the boiler last serviced?",
+            "answer": "Probably in March.",
+            "confidence": 0.2,
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Probably in March."));
+    mock_llm.add_response(create_mock_chat_response("0.2"));
+    let mut agent = scoring_agent(
+        mock_llm,
+        ConfidenceConfig {
+            enabled: true,
+            threshold: 0.6,
+            fallback_reply: Some("I don't know, I've asked someone to check.".to_string()),
+            escalation_webhook: Some(format!("{}/escalate", server.uri())),
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process_with_citations("session", "When was the boiler last serviced?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.output, "I don't know, I've asked someone to check.");
+    assert_eq!(reply.confidence, Some(0.2));
+
+    let messages = history.list("session").await.unwrap();
+    assert_eq!(
+        messages.last().unwrap().content,
+        "I don't know, I've asked someone to check."
+    );
+}
+
+#[tokio::test]
+async fn test_low_confidence_without_fallback_keeps_reply() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Probably in March."));
+    mock_llm.add_response(create_mock_chat_response("0.1"));
+    // An unreachable webhook doesn't fail the request
+    let mut agent = scoring_agent(
+        mock_llm,
+        ConfidenceConfig {
+            enabled: true,
+            escalation_webhook: Some("http://127.0.0.1:9/escalate".to_string()),
+            ..Default::default()
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process_with_citations("session", "When was the boiler last serviced?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.output, "Probably in March.");
+    assert_eq!(reply.confidence, Some(0.1));
+}
+
+#[tokio::test]
+async fn test_unscored_replies() {
+    // A judge without a score leaves the reply unscored
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Paris."));
+    mock_llm.add_response(create_mock_chat_response("Hard to say."));
+    let mut agent = scoring_agent(
+        mock_llm,
+        ConfidenceConfig {
+            enabled: true,
+            fallback_reply: Some("I don't know.".to_string()),
+            ..Default::default()
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process_with_citations("session", "Capital of France?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.output, "Paris.");
+    assert_eq!(reply.confidence, None);
+
+    // Without scoring there is no judge call
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response("Paris."));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let reply = agent
+        .process_with_citations("other", "Capital of France?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply.confidence, None);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,136 @@
+use jarvis_rust::config::{ConfigKey, load_file_with_key};
+use pretty_assertions::assert_eq;
+use std::fs;
+use tempfile::TempDir;
+
+fn config_dir(key: &ConfigKey) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.yaml"),
+        format!(
+            r#"
+include: [mcp.yaml]
+llm:
+  base_url: "https://api.openai.com"
+  api_key: !encrypted "{}"
+  model: "gpt-4"
+server: {{}}
+"#,
+            key.encrypt("sk-secret").unwrap()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("mcp.yaml"),
+        format!(
+            r#"
+mcp_servers:
+  - name: "home"
+    type: "sse"
+    url: "http://localhost:3000"
+    headers:
+      Authorization: !encrypted "{}"
+"#,
+            key.encrypt("Bearer token").unwrap()
+        ),
+    )
+    .unwrap();
+    dir
+}
+
+#[test]
+fn test_encrypt_round_trip() {
+    let key = ConfigKey::generate().unwrap();
+    let encrypted = key.encrypt("sk-secret").unwrap();
+    assert!(encrypted.starts_with("v1:"));
+    assert!(!encrypted.contains("sk-secret"));
+    // A fresh nonce every time
+    assert_ne!(encrypted, key.encrypt("sk-secret").unwrap());
+    assert_eq!(key.decrypt(&encrypted).unwrap(), "sk-secret");
+
+    let restored = ConfigKey::from_base64(&key.to_base64()).unwrap();
+    assert_eq!(restored.decrypt(&encrypted).unwrap(), "sk-secret");
+    assert_eq!(format!("{key:?}"), "ConfigKey(***)");
+}
+
+#[test]
+fn test_decrypt_rejects_wrong_keys_and_garbage() {
+    let key = ConfigKey::generate().unwrap();
+    let encrypted = key.encrypt("sk-secret").unwrap();
+
+    let other = ConfigKey::generate().unwrap();
+    assert!(
+        other
+            .decrypt(&encrypted)
+            .unwrap_err()
+            .to_string()
+            .contains("wrong key or corrupted ciphertext")
+    );
+    for garbage in ["sk-secret", "v1:!!!", "v1:AAAA"] {
+        assert!(
+            key.decrypt(garbage)
+                .unwrap_err()
+                .to_string()
+                .contains("Malformed encrypted value")
+        );
+    }
+    assert!(ConfigKey::from_base64("c2hvcnQ=").is_err());
+}
+
+#[test]
+fn test_key_file_round_trip() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("keys/config.key");
+    let key = ConfigKey::generate().unwrap();
+    key.write_file(&path).unwrap();
+    assert_eq!(ConfigKey::from_file(&path).unwrap(), key);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    // Never overwrites an existing key
+    assert!(ConfigKey::generate().unwrap().write_file(&path).is_err());
+}
+
+#[test]
+fn test_encrypted_values_are_decrypted_at_load() {
+    let key = ConfigKey::generate().unwrap();
+    let dir = config_dir(&key);
+
+    let config = load_file_with_key(&dir.path().join("config.yaml"), key).unwrap();
+    assert_eq!(config.llm.api_key, "sk-secret");
+    assert_eq!(
+        config.mcp_servers[0].headers["Authorization"],
+        "Bearer token"
+    );
+}
+
+#[test]
+fn test_decryption_errors_name_the_value() {
+    let dir = config_dir(&ConfigKey::generate().unwrap());
+    let error = load_file_with_key(
+        &dir.path().join("config.yaml"),
+        ConfigKey::generate().unwrap(),
+    )
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("config.yaml: 'llm.api_key'"), "{error}");
+    assert!(error.contains("wrong key"), "{error}");
+
+    let key = ConfigKey::generate().unwrap();
+    let dir = config_dir(&key);
+    fs::write(
+        dir.path().join("mcp.yaml"),
+        "mcp_servers:\n  - name: !encrypted [1]\n",
+    )
+    .unwrap();
+    let error = load_file_with_key(&dir.path().join("config.yaml"), key)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("mcp.yaml: 'mcp_servers[0].name': encrypted values must be strings"),
+        "{error}"
+    );
+}
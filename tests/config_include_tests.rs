@@ -0,0 +1,170 @@
+use jarvis_rust::config::{McpClientType, load_file};
+use pretty_assertions::assert_eq;
+use std::{fs, path::Path};
+use tempfile::TempDir;
+
+const ROOT_CONFIG: &str = r#"
+include: ["mcp/*.yaml", "agent.yaml"]
+llm:
+  base_url: "https://api.openai.com"
+  api_key: "test-key"
+  model: "gpt-4"
+server:
+  port: 9000
+mcp_servers:
+  - name: "home"
+    type: "sse"
+    url: "http://localhost:3000"
+"#;
+
+fn write(dir: &Path, name: &str, contents: &str) {
+    let path = dir.join(name);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+fn config_dir() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    write(dir.path(), "config.yaml", ROOT_CONFIG);
+    write(
+        dir.path(),
+        "mcp/weather.yaml",
+        r#"
+mcp_servers:
+  - name: "weather"
+    type: "stdio"
+    command: "./weather-server"
+"#,
+    );
+    write(
+        dir.path(),
+        "mcp/files.yaml",
+        r#"
+mcp_servers:
+  - name: "files"
+    type: "fixture"
+    fixture: "files.yaml"
+"#,
+    );
+    write(
+        dir.path(),
+        "agent.yaml",
+        r#"
+llm:
+  system_prompt: "You are Jarvis"
+server:
+  port: 9000
+"#,
+    );
+    dir
+}
+
+fn load_error(dir: &TempDir) -> String {
+    load_file(&dir.path().join("config.yaml"))
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn test_includes_are_merged_in_order() {
+    let dir = config_dir();
+    let config = load_file(&dir.path().join("config.yaml")).unwrap();
+
+    assert_eq!(
+        config
+            .mcp_servers
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["home", "files", "weather"]
+    );
+    assert!(matches!(
+        config.mcp_servers[1].client_type,
+        McpClientType::Fixture
+    ));
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("You are Jarvis"));
+    assert_eq!(config.llm.model, "gpt-4");
+    assert_eq!(config.server.port, 9000);
+    assert_eq!(config.include, vec!["mcp/*.yaml", "agent.yaml"]);
+}
+
+#[test]
+fn test_nested_includes_are_relative_to_their_file() {
+    let dir = config_dir();
+    write(dir.path(), "agent.yaml", "include: [prompts/prompt.yaml]\n");
+    write(
+        dir.path(),
+        "prompts/prompt.yaml",
+        "llm:\n  system_prompt: \"Nested\"\n",
+    );
+
+    let config = load_file(&dir.path().join("config.yaml")).unwrap();
+    assert_eq!(config.llm.system_prompt.as_deref(), Some("Nested"));
+}
+
+#[test]
+fn test_glob_matching_nothing_is_allowed() {
+    let dir = config_dir();
+    fs::remove_file(dir.path().join("mcp/weather.yaml")).unwrap();
+    fs::remove_file(dir.path().join("mcp/files.yaml")).unwrap();
+
+    let config = load_file(&dir.path().join("config.yaml")).unwrap();
+    assert_eq!(config.mcp_servers.len(), 1);
+}
+
+#[test]
+fn test_errors_name_the_file() {
+    let dir = config_dir();
+    fs::remove_file(dir.path().join("agent.yaml")).unwrap();
+    let error = load_error(&dir);
+    assert!(error.contains("In "), "{error}");
+    assert!(error.contains("agent.yaml does not exist"), "{error}");
+
+    let dir = config_dir();
+    write(dir.path(), "mcp/broken.yaml", "mcp_servers: [name: \"x\"\n");
+    let error = load_error(&dir);
+    assert!(error.contains("broken.yaml:"), "{error}");
+
+    let dir = config_dir();
+    write(
+        dir.path(),
+        "mcp/bad.yaml",
+        "mcp_servers:\n  - type: \"stdio\"\n    command: \"./server\"\n",
+    );
+    let error = load_error(&dir);
+    assert!(error.contains("bad.yaml: invalid mcp_servers"), "{error}");
+    assert!(error.contains("missing field `name`"), "{error}");
+}
+
+#[test]
+fn test_duplicate_servers_and_conflicting_values_are_rejected() {
+    let dir = config_dir();
+    write(
+        dir.path(),
+        "mcp/other.yaml",
+        "mcp_servers:\n  - name: \"weather\"\n    type: \"sse\"\n    url: \"http://localhost:4000\"\n",
+    );
+    let error = load_error(&dir);
+    assert!(
+        error.contains("MCP server 'weather' is already defined in"),
+        "{error}"
+    );
+    assert!(error.contains("other.yaml"), "{error}");
+
+    let dir = config_dir();
+    write(dir.path(), "agent.yaml", "server:\n  port: 9001\n");
+    let error = load_error(&dir);
+    assert!(error.contains("agent.yaml"), "{error}");
+    assert!(
+        error.contains("'server.port' is already set to a different value"),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_include_cycles_are_rejected() {
+    let dir = config_dir();
+    write(dir.path(), "agent.yaml", "include: [config.yaml]\n");
+    let error = load_error(&dir);
+    assert!(error.contains("config.yaml: includes itself"), "{error}");
+}
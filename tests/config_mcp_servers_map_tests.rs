@@ -0,0 +1,142 @@
+use jarvis_rust::config::{McpClientType, load_file, parse};
+use pretty_assertions::assert_eq;
+use std::fs;
+use tempfile::TempDir;
+
+const BASE: &str = r#"
+llm:
+  base_url: "https://api.openai.com"
+  api_key: "test-key"
+  model: "gpt-4"
+server: {}
+"#;
+
+#[test]
+fn test_mcp_servers_map_is_converted() {
+    let config = parse(&format!(
+        r#"{BASE}
+strict: true
+mcpServers:
+  filesystem:
+    command: "npx"
+    args: ["-y", "@modelcontextprotocol/server-filesystem", "/tmp"]
+    env:
+      DEBUG: "1"
+    autoApprove: ["read_file"]
+  search:
+    url: "https://example.com/mcp"
+    headers:
+      Authorization: "Bearer token"
+  events:
+    type: "sse"
+    url: "https://example.com/sse"
+  old:
+    command: "./old-server"
+    disabled: true
+"#
+    ))
+    .unwrap();
+
+    let servers = &config.mcp_servers;
+    assert_eq!(
+        servers.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+        vec!["filesystem", "search", "events"]
+    );
+    assert!(matches!(servers[0].client_type, McpClientType::Stdio));
+    assert_eq!(servers[0].command.as_deref(), Some("npx"));
+    assert_eq!(servers[0].args.len(), 3);
+    assert_eq!(servers[0].env["DEBUG"], "1");
+    assert!(matches!(
+        servers[1].client_type,
+        McpClientType::StreamableHttp
+    ));
+    assert_eq!(servers[1].url.as_deref(), Some("https://example.com/mcp"));
+    assert_eq!(servers[1].headers["Authorization"], "Bearer token");
+    assert!(matches!(servers[2].client_type, McpClientType::Sse));
+}
+
+#[test]
+fn test_json_snippet_is_accepted_under_the_key() {
+    let config = parse(&format!(
+        r#"{BASE}
+mcpServers: {{"memory": {{"command": "npx", "args": ["-y", "@modelcontextprotocol/server-memory"]}}}}
+"#
+    ))
+    .unwrap();
+    assert_eq!(config.mcp_servers[0].name, "memory");
+    assert!(matches!(
+        config.mcp_servers[0].client_type,
+        McpClientType::Stdio
+    ));
+}
+
+#[test]
+fn test_map_servers_follow_listed_servers() {
+    let config = parse(&format!(
+        r#"{BASE}
+mcp_servers:
+  - name: "home"
+    type: "http"
+    url: "http://localhost:3000"
+mcpServers:
+  weather:
+    type: "streamable-http"
+    url: "http://localhost:4000"
+"#
+    ))
+    .unwrap();
+    assert_eq!(
+        config
+            .mcp_servers
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect::<Vec<_>>(),
+        vec!["home", "weather"]
+    );
+    assert!(matches!(
+        config.mcp_servers[1].client_type,
+        McpClientType::StreamableHttp
+    ));
+}
+
+#[test]
+fn test_invalid_map_servers_are_named() {
+    let error = parse(&format!("{BASE}\nmcpServers:\n  broken:\n    args: []\n"))
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("invalid mcpServers: server 'broken' needs a 'command' or a 'url'"),
+        "{error}"
+    );
+
+    let error = parse(&format!(
+        "{BASE}\nmcpServers:\n  ws:\n    type: \"websocket\"\n    url: \"ws://x\"\n"
+    ))
+    .unwrap_err()
+    .to_string();
+    assert!(error.contains("unsupported type 'websocket'"), "{error}");
+}
+
+#[test]
+fn test_included_map_servers_are_checked_for_duplicates() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("config.yaml"),
+        format!("{BASE}\ninclude: [desktop.json]\nmcp_servers:\n  - name: \"memory\"\n    type: \"stdio\"\n    command: \"./memory\"\n"),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("desktop.json"),
+        r#"{"mcpServers": {"memory": {"command": "npx"}}}"#,
+    )
+    .unwrap();
+
+    let error = load_file(&dir.path().join("config.yaml"))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("desktop.json"), "{error}");
+    assert!(
+        error.contains("MCP server 'memory' is already defined"),
+        "{error}"
+    );
+}
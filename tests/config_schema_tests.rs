@@ -0,0 +1,95 @@
+use jarvis_rust::config::{parse, schema, unknown_fields};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+
+const CONFIG_WITH_TYPOS: &str = r#"
+strict: true
+llm:
+  base_url: "https://api.openai.com"
+  api_key: "test-key"
+  model: "gpt-4"
+  systme_prompt: "You are a helpful assistant"
+server:
+  prot: 9000
+mcp_servers:
+  - name: "weather"
+    type: "stdio"
+    comand: "./weather-server"
+    env:
+      ANY_NAME: "allowed"
+"#;
+
+#[test]
+fn test_schema_describes_config() {
+    let schema = schema();
+    assert_eq!(schema["title"], "Config");
+    assert!(schema["properties"]["strict"].is_object());
+    assert!(
+        schema["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("llm"))
+    );
+    assert!(
+        schema["definitions"]["LlmConfig"]["properties"]["system_prompt"].is_object(),
+        "{}",
+        schema["definitions"]["LlmConfig"]
+    );
+}
+
+#[test]
+fn test_unknown_fields_are_named_by_path() {
+    let raw: serde_json::Value = serde_yaml::from_str(CONFIG_WITH_TYPOS).unwrap();
+    assert_eq!(
+        unknown_fields(&raw),
+        vec!["llm.systme_prompt", "mcp_servers[0].comand", "server.prot"]
+    );
+}
+
+#[test]
+fn test_strict_config_fails_on_typos() {
+    let error = parse(CONFIG_WITH_TYPOS).unwrap_err().to_string();
+    assert!(
+        error.contains(
+            "Unknown config fields: llm.systme_prompt, mcp_servers[0].comand, server.prot"
+        ),
+        "{error}"
+    );
+}
+
+#[test]
+fn test_lenient_config_ignores_typos() {
+    let config = parse(&CONFIG_WITH_TYPOS.replace("strict: true", "strict: false")).unwrap();
+    assert!(!config.strict);
+    assert_eq!(config.llm.system_prompt, None);
+    assert_eq!(config.server.port, 8080);
+}
+
+#[test]
+fn test_free_form_fields_are_not_unknown() {
+    let raw = json!({
+        "llm": {"base_url": "", "api_key": "", "model": ""},
+        "server": {},
+        "tools": {
+            "settings": {
+                "get_weather": {
+                    "arguments": {"units": "metric"},
+                    "output_schema": {"type": "object", "anything": true}
+                }
+            }
+        }
+    });
+    assert_eq!(unknown_fields(&raw), Vec::<String>::new());
+}
+
+#[test]
+fn test_readme_example_config_is_strict_clean() {
+    let readme = std::fs::read_to_string("README.md").unwrap();
+    let example = readme
+        .split("```yaml\n")
+        .nth(1)
+        .and_then(|block| block.split("```").next())
+        .unwrap();
+    let raw: serde_json::Value = serde_yaml::from_str(example).unwrap();
+    assert_eq!(unknown_fields(&raw), Vec::<String>::new());
+}
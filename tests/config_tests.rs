@@ -1,6 +1,8 @@
 use jarvis_rust::config::{
-    Config, LlmConfig, LogsConfig, McpClientType, McpServerConfig, ServerConfig,
-    default_database_path, default_host, default_log_level, default_port, default_provider, load,
+    AgentConfig, CacheBackend, CacheConfig, Config, EmptyResponseConfig, KnowledgeConfig,
+    LlmConfig, LogsConfig, McpClientType, McpServerConfig, RerankProvider, ServerConfig,
+    create_data_dir, default_data_dir, default_database_path, default_host, default_log_level,
+    default_port, default_provider, load,
 };
 use pretty_assertions::assert_eq;
 use std::env;
@@ -118,7 +120,11 @@ async fn test_load_minimal_config_with_defaults() {
     assert_eq!(config.server.port, 8080); // default
     assert_eq!(config.server.logs.level, "info"); // default
     assert_eq!(config.server.database_path, "history.db"); // default
+    assert_eq!(config.server.data_dir, "data"); // default
     assert_eq!(config.llm.system_prompt, None); // default
+    assert_eq!(config.llm.max_continuations, 2); // default
+    assert_eq!(config.llm.empty_response.retries, 1); // default
+    assert!(config.llm.retroactive_system_prompt); // default
     assert!(config.mcp_servers.is_empty()); // default
 
     unsafe {
@@ -189,11 +195,33 @@ async fn test_config_serialization() {
             api_key: "test-key".to_string(),
             model: "gpt-4".to_string(),
             system_prompt: Some("Test prompt".to_string()),
+            max_continuations: 2,
+            max_context_tokens: None,
+            retry: Default::default(),
+            pacing: Default::default(),
+            request_timeout: 300,
+            connect_timeout: 10,
+            proxy: None,
+            context_fallback: Default::default(),
+            sampling: Default::default(),
+            empty_response: EmptyResponseConfig::default(),
+            structured_output: Default::default(),
+            routing: Default::default(),
+            retroactive_system_prompt: true,
+            ollama: Default::default(),
+            azure: Default::default(),
+            openrouter: Default::default(),
+            reasoning: Default::default(),
+            replay: Default::default(),
+            fallbacks: Vec::new(),
         },
         server: ServerConfig {
             host: "127.0.0.1".to_string(),
             port: 8080,
             database_path: "test.db".to_string(),
+            data_dir: "data".to_string(),
+            api_keys: Vec::new(),
+            database: Default::default(),
             logs: LogsConfig {
                 level: "debug".to_string(),
             },
@@ -204,9 +232,32 @@ async fn test_config_serialization() {
             client_type: McpClientType::Sse,
             headers: std::collections::HashMap::new(),
             command: None,
+            fixture: None,
+            auth: None,
             args: vec![],
             env: std::collections::HashMap::new(),
         }],
+        tools: Default::default(),
+        feeds: Vec::new(),
+        schedules: Vec::new(),
+        examples: Vec::new(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        notifications: Default::default(),
+        knowledge: Default::default(),
+        cache: Default::default(),
+        agent: Default::default(),
+        moderation: Default::default(),
+        usage: Default::default(),
+        history: Default::default(),
+        canary: Default::default(),
+        chaos: Default::default(),
+        features: Default::default(),
+        updates: Default::default(),
+        strict: false,
+        include: Vec::new(),
+        auth_presets: Default::default(),
     };
 
     // Test serialization
@@ -265,4 +316,92 @@ fn test_default_values() {
     assert_eq!(default_port(), 8080);
     assert_eq!(default_log_level(), "info");
     assert_eq!(default_database_path(), "history.db");
+    assert_eq!(default_data_dir(), "data");
+}
+
+#[test]
+fn test_paths_resolve_under_data_dir() {
+    let server: ServerConfig = serde_yaml::from_str("data_dir: /var/lib/jarvis").unwrap();
+    assert_eq!(server.data_path("history.db"), "/var/lib/jarvis/history.db");
+    assert_eq!(server.data_path("/tmp/other.db"), "/tmp/other.db");
+    assert_eq!(server.data_path(":memory:"), ":memory:");
+    // Files kept in the working directory before data_dir existed are still found there
+    assert!(server.is_legacy_path("Cargo.toml"));
+    assert_eq!(server.data_path("Cargo.toml"), "Cargo.toml");
+
+    let mut config: Config = serde_yaml::from_str(
+        "llm:\n  base_url: http://localhost\n  api_key: key\n  model: gpt-4\n\
+         server:\n  data_dir: /var/lib/jarvis\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.cache_database_path(),
+        config.server.resolved_database_path()
+    );
+    config.cache.path = Some("cache.db".to_string());
+    assert_eq!(config.cache_database_path(), "/var/lib/jarvis/cache.db");
+}
+
+#[test]
+fn test_create_data_dir() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("nested").join("data");
+    let server: ServerConfig =
+        serde_yaml::from_str(&format!("data_dir: {}", data_dir.display())).unwrap();
+
+    create_data_dir(&server).unwrap();
+    assert!(data_dir.is_dir());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&data_dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+
+    // An existing directory is left as it is
+    create_data_dir(&server).unwrap();
+}
+
+#[test]
+fn test_knowledge_rerank_config() {
+    let knowledge: KnowledgeConfig = serde_yaml::from_str("enabled: true").unwrap();
+    assert_eq!(knowledge.rerank.provider, RerankProvider::None);
+    assert_eq!(knowledge.result_count(), 4);
+
+    let knowledge: KnowledgeConfig = serde_yaml::from_str(
+        "enabled: true\ntop_k: 6\nrerank:\n  provider: cross_encoder\n  url: http://localhost:8787\n  top_k_out: 3",
+    )
+    .unwrap();
+    assert_eq!(knowledge.rerank.provider, RerankProvider::CrossEncoder);
+    assert_eq!(knowledge.rerank.top_k_in, 20);
+    assert_eq!(knowledge.result_count(), 3);
+}
+
+#[test]
+fn test_cache_config() {
+    let cache: CacheConfig = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(cache.backend, CacheBackend::Memory);
+    assert_eq!(cache.capacity, 1000);
+    assert!(cache.embeddings);
+    assert!(!cache.llm);
+    assert_eq!(cache.ttl(), None);
+
+    let cache: CacheConfig = serde_yaml::from_str(
+        "backend: disk\npath: cache.db\nttl_seconds: 600\nllm: true\ntools: [web_search]",
+    )
+    .unwrap();
+    assert_eq!(cache.backend, CacheBackend::Disk);
+    assert_eq!(cache.path.as_deref(), Some("cache.db"));
+    assert_eq!(cache.ttl(), Some(std::time::Duration::from_secs(600)));
+    assert!(cache.llm);
+    assert_eq!(cache.tools, vec!["web_search"]);
+}
+
+#[test]
+fn test_agent_config() {
+    let agent: AgentConfig = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(agent.response_language, None);
+
+    let agent: AgentConfig = serde_yaml::from_str("response_language: pt-BR").unwrap();
+    assert_eq!(agent.response_language.as_deref(), Some("pt-BR"));
 }
@@ -0,0 +1,296 @@
+mod common;
+
+use async_openai::error::{ApiError, OpenAIError};
+use async_trait::async_trait;
+use common::create_mock_chat_response;
+use jarvis_rust::{
+    Error, Result,
+    agent::Agent,
+    config::{ContextFallbackConfig, EmptyResponseConfig, LlmConfig},
+    history::HistoryStorage,
+    llm::{
+        ChatCompletionRequest, ChatCompletionResponse, ChatMessage, ContextFallback,
+        ContextFallbackLlmClient, LlmClient, create_llm_client,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, method, path},
+};
+
+/// A model holding `limit` messages, rejecting longer requests like OpenAI does
+struct LimitedLlmClient {
+    limits: HashMap<String, usize>,
+    requests: Arc<Mutex<Vec<ChatCompletionRequest>>>,
+}
+
+impl LimitedLlmClient {
+    fn new(limits: &[(&str, usize)]) -> Self {
+        Self {
+            limits: limits
+                .iter()
+                .map(|(model, limit)| (model.to_string(), *limit))
+                .collect(),
+            requests: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for LimitedLlmClient {
+    async fn create_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        self.requests.lock().unwrap().push(request.clone());
+        if request.messages.len() > self.limits[&request.model] {
+            return Err(OpenAIError::ApiError(ApiError {
+                message: "This model's maximum context length is 8192 tokens".to_string(),
+                r#type: Some("invalid_request_error".to_string()),
+                param: Some("messages".to_string()),
+                code: Some("context_length_exceeded".to_string()),
+            })
+            .into());
+        }
+        let mut response = create_mock_chat_response(&format!("Answered by {}", request.model));
+        response.model = request.model;
+        Ok(response)
+    }
+}
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+/// A system prompt, `exchanges` earlier questions and answers and the current question
+fn request(model: &str, exchanges: usize) -> ChatCompletionRequest {
+    let mut messages = vec![message("system", "Be brief.")];
+    for i in 0..exchanges {
+        messages.push(message(
+            "user",
+            &format!("question {i} {}", "word ".repeat(50)),
+        ));
+        messages.push(message(
+            "assistant",
+            &format!("answer {i} {}", "word ".repeat(50)),
+        ));
+    }
+    messages.push(message("user", "And now?"));
+    ChatCompletionRequest {
+        model: model.to_string(),
+        messages,
+        ..Default::default()
+    }
+}
+
+fn fallback_config(model: Option<&str>, truncate: bool) -> ContextFallbackConfig {
+    ContextFallbackConfig {
+        model: model.map(str::to_string),
+        truncate,
+    }
+}
+
+#[tokio::test]
+async fn test_larger_model_answers_requests_too_long_for_the_configured_one() {
+    // Requests without a model are answered by the small one
+    let inner = LimitedLlmClient::new(&[("", 3), ("large", 100)]);
+    let requests = inner.requests.clone();
+    let client = ContextFallbackLlmClient::new(
+        Box::new(inner),
+        "small".to_string(),
+        fallback_config(Some("large"), true),
+    );
+
+    let response = client.create_chat_completion(request("", 4)).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Answered by large");
+    assert_eq!(
+        response.context_fallback,
+        Some(ContextFallback {
+            from_model: "small".to_string(),
+            to_model: Some("large".to_string()),
+            dropped_messages: 0,
+        })
+    );
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].messages.len(), 10);
+}
+
+#[tokio::test]
+async fn test_request_is_truncated_when_no_model_holds_it() {
+    let inner = LimitedLlmClient::new(&[("small", 3), ("large", 6)]);
+    let requests = inner.requests.clone();
+    let client = ContextFallbackLlmClient::new(
+        Box::new(inner),
+        "small".to_string(),
+        fallback_config(Some("large"), true),
+    );
+
+    let response = client
+        .create_chat_completion(request("small", 4))
+        .await
+        .unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Answered by large");
+    let fallback = response.context_fallback.unwrap();
+    assert_eq!(fallback.to_model.as_deref(), Some("large"));
+    assert!(fallback.dropped_messages > 0);
+
+    let requests = requests.lock().unwrap();
+    let sent = &requests.last().unwrap().messages;
+    assert_eq!(sent.len(), 10 - fallback.dropped_messages);
+    assert_eq!(sent[0].content, "Be brief.");
+    assert_eq!(sent.last().unwrap().content, "And now?");
+}
+
+#[tokio::test]
+async fn test_without_fallbacks_the_error_is_returned() {
+    let inner = LimitedLlmClient::new(&[("small", 3)]);
+    let requests = inner.requests.clone();
+    let client = ContextFallbackLlmClient::new(
+        Box::new(inner),
+        "small".to_string(),
+        fallback_config(None, false),
+    );
+
+    let error = client
+        .create_chat_completion(request("small", 4))
+        .await
+        .unwrap_err();
+
+    assert!(error.is_context_length_exceeded());
+    assert_eq!(requests.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_requests_within_context_are_untouched() {
+    let client = ContextFallbackLlmClient::new(
+        Box::new(LimitedLlmClient::new(&[("small", 100)])),
+        "small".to_string(),
+        fallback_config(Some("large"), true),
+    );
+
+    let response = client
+        .create_chat_completion(request("small", 2))
+        .await
+        .unwrap();
+
+    assert_eq!(response.context_fallback, None);
+}
+
+#[test]
+fn test_context_length_errors_are_recognized() {
+    let openai: Error = OpenAIError::ApiError(ApiError {
+        message: "Too long".to_string(),
+        r#type: None,
+        param: None,
+        code: Some("context_length_exceeded".to_string()),
+    })
+    .into();
+    assert!(openai.is_context_length_exceeded());
+    let gemini = Error::llm_status(
+        400,
+        "The input token count (1200000) exceeds the maximum number of tokens allowed (1048576).",
+    );
+    assert!(gemini.is_context_length_exceeded());
+    let anthropic_like = Error::llm_status(400, "prompt is too long: 210000 tokens > 200000");
+    assert!(anthropic_like.is_context_length_exceeded());
+
+    assert!(!Error::llm_status(400, "Invalid API key").is_context_length_exceeded());
+    assert!(!Error::llm_status(503, "context window overloaded").is_context_length_exceeded());
+}
+
+#[tokio::test]
+async fn test_agent_reports_the_fallback_in_its_reply() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4o"})))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": {
+                "message": "This model's maximum context length is 128000 tokens.",
+                "type": "invalid_request_error",
+                "param": "messages",
+                "code": "context_length_exceeded"
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4.1"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4.1",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Long answer."},
+                "finish_reason": "stop"
+            }]
+        })))
+        .mount(&server)
+        .await;
+    let client = create_llm_client(LlmConfig {
+        provider: "openai".to_string(),
+        base_url: server.uri(),
+        api_key: "test-api-key".to_string(),
+        model: "gpt-4o".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: fallback_config(Some("gpt-4.1"), true),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    })
+    .unwrap();
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = Agent::new_for_testing(client, HashMap::new(), HashMap::new(), Vec::new())
+        .with_model("gpt-4o");
+
+    let reply = agent
+        .process_with_citations("s1", "Summarize everything", &history)
+        .await
+        .unwrap();
+
+    assert_eq!(reply.output, "Long answer.");
+    let fallback = ContextFallback {
+        from_model: "gpt-4o".to_string(),
+        to_model: Some("gpt-4.1".to_string()),
+        dropped_messages: 0,
+    };
+    assert_eq!(reply.context_fallback, Some(fallback.clone()));
+    let saved = history.list("s1").await.unwrap();
+    assert_eq!(
+        saved.last().unwrap().metadata.as_ref().unwrap()["context_fallback"],
+        serde_json::to_value(&fallback).unwrap()
+    );
+}
@@ -0,0 +1,281 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::{Agent, AgentHook, HookContext, trim_to_budget},
+    history::{HistoryStorage, Message},
+    llm::{
+        ChatCompletionRequest, ChatMessage, FunctionCall, HeuristicTokenizer, ToolCall,
+        count_tokens,
+    },
+    mcp::{McpContent, McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+fn tool_call_reply(id: &str) -> ChatMessage {
+    ChatMessage {
+        tool_calls: Some(vec![ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "clock".to_string(),
+                arguments: "{}".to_string(),
+            },
+        }]),
+        ..message("assistant", "")
+    }
+}
+
+fn tool_result(id: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        tool_call_id: Some(id.to_string()),
+        ..message("tool", content)
+    }
+}
+
+fn contents(messages: &[ChatMessage]) -> Vec<String> {
+    messages.iter().map(|m| m.content.to_string()).collect()
+}
+
+#[test]
+fn test_messages_within_budget_are_kept() {
+    let mut messages = vec![message("system", "Be brief."), message("user", "Hi")];
+    let dropped = trim_to_budget(&HeuristicTokenizer, &mut messages, &[], 1000, 1);
+    assert_eq!(dropped, 0);
+    assert_eq!(messages.len(), 2);
+}
+
+#[test]
+fn test_oldest_messages_are_dropped_first() {
+    let long = "word ".repeat(40);
+    let mut messages = vec![
+        message("system", "Be brief."),
+        message("user", &format!("first {long}")),
+        message("assistant", &format!("second {long}")),
+        message("user", &format!("third {long}")),
+        message("assistant", &format!("fourth {long}")),
+        message("user", "What now?"),
+    ];
+    let keep = vec![
+        message("system", "Be brief."),
+        messages[3].clone(),
+        messages[4].clone(),
+        message("user", "What now?"),
+    ];
+    let budget = count_tokens(&HeuristicTokenizer, &keep, &[]);
+
+    let dropped = trim_to_budget(&HeuristicTokenizer, &mut messages, &[], budget, 5);
+
+    assert_eq!(dropped, 2);
+    assert_eq!(contents(&messages), contents(&keep));
+}
+
+#[test]
+fn test_tool_results_go_with_their_call() {
+    let mut messages = vec![
+        message("user", "What time is it?"),
+        tool_call_reply("call_1"),
+        tool_result("call_1", &"08:00 ".repeat(50)),
+        message("assistant", "It is 8."),
+        message("user", "Thanks"),
+    ];
+
+    // Only room for the last exchange: the call and its result leave together
+    let budget = count_tokens(&HeuristicTokenizer, &messages[3..], &[]);
+    let dropped = trim_to_budget(&HeuristicTokenizer, &mut messages, &[], budget, 4);
+
+    assert_eq!(dropped, 3);
+    assert_eq!(contents(&messages), vec!["It is 8.", "Thanks"]);
+}
+
+#[test]
+fn test_system_messages_and_current_turn_are_never_dropped() {
+    let mut messages = vec![
+        message("system", &"rules ".repeat(100)),
+        message("user", "old question"),
+        message("user", &"current ".repeat(100)),
+        tool_call_reply("call_1"),
+        tool_result("call_1", "result"),
+    ];
+
+    let dropped = trim_to_budget(&HeuristicTokenizer, &mut messages, &[], 10, 2);
+
+    assert_eq!(dropped, 1);
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0].role, "system");
+    assert_eq!(messages[1].content, "current ".repeat(100));
+}
+
+/// Ten long exchanges in session `s1`
+async fn long_history() -> HistoryStorage {
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    for i in 0..10 {
+        let filler = "lorem ipsum ".repeat(50);
+        history
+            .save(Message::user(
+                "s1".to_string(),
+                format!("question {i} {filler}"),
+            ))
+            .await
+            .unwrap();
+        history
+            .save(Message::assistant(
+                "s1".to_string(),
+                format!("answer {i} {filler}"),
+            ))
+            .await
+            .unwrap();
+    }
+    history
+}
+
+#[tokio::test]
+async fn test_agent_trims_long_sessions_before_calling_the_llm() {
+    let history = long_history().await;
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Sure."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_max_context_tokens(Some(1000));
+
+    agent.process("s1", "And now?", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let sent = &requests[0].messages;
+    assert!(count_tokens(&HeuristicTokenizer, sent, &requests[0].tools) <= 1000);
+    assert_eq!(sent[0].role, "system");
+    assert_eq!(sent.last().unwrap().content, "And now?");
+    // The newest history survives, the oldest is gone
+    assert!(
+        sent.iter()
+            .any(|m| m.content.text().starts_with("answer 9 "))
+    );
+    assert!(
+        !sent
+            .iter()
+            .any(|m| m.content.text().starts_with("question 0 "))
+    );
+}
+
+/// Takes a screenshot, returning it as an image
+struct ScreenshotTool;
+
+#[async_trait]
+impl NativeTool for ScreenshotTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "screenshot".to_string(),
+            description: "Captures the screen".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(McpToolCallResponse {
+            content: vec![McpContent::Image {
+                data: "iVBORw0K".to_string(),
+                mime_type: "image/png".to_string(),
+            }],
+            is_error: false,
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_trimming_keeps_the_turn_with_tool_images() {
+    let history = long_history().await;
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("screenshot", "{}"));
+    mock_llm.add_response(create_mock_chat_response("A terminal."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_max_context_tokens(Some(10));
+    agent.register_native_tool(Arc::new(ScreenshotTool));
+
+    agent
+        .process("s1", "What's on my screen?", &history)
+        .await
+        .unwrap();
+
+    // The turn is kept whole, the image along with its tool result
+    let requests = requests.lock().unwrap();
+    let sent = &requests[1].messages;
+    let roles: Vec<&str> = sent[sent.len() - 3..]
+        .iter()
+        .map(|m| m.role.as_str())
+        .collect();
+    assert_eq!(roles, vec!["user", "assistant", "tool"]);
+    assert_eq!(sent[sent.len() - 3].content, "What's on my screen?");
+    assert_eq!(sent.last().unwrap().content.images().count(), 1);
+}
+
+/// Adds a long profile to the system prompt, as profile hooks do
+struct LongProfileHook;
+
+#[async_trait]
+impl AgentHook for LongProfileHook {
+    async fn before_llm_call(
+        &self,
+        _ctx: &HookContext,
+        request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        request.messages[0]
+            .content
+            .push_str(&" likes gardening".repeat(100));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_what_hooks_add_counts_against_the_budget() {
+    let history = long_history().await;
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Sure."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_max_context_tokens(Some(1000));
+    agent.add_hook(Arc::new(LongProfileHook));
+
+    agent.process("s1", "And now?", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let sent = &requests[0].messages;
+    assert!(sent[0].content.text().contains("likes gardening"));
+    assert!(count_tokens(&HeuristicTokenizer, sent, &requests[0].tools) <= 1000);
+    assert_eq!(sent.last().unwrap().content, "And now?");
+}
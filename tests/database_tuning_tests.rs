@@ -0,0 +1,151 @@
+use jarvis_rust::{
+    config::{DatabaseConfig, JournalMode, ServerConfig, Synchronous},
+    db,
+    history::{HistoryStorage, Message, StorageStatus},
+    tasks::{NewTask, TaskStore},
+};
+use libsql::{Builder, Connection};
+use pretty_assertions::assert_eq;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+async fn pragma(conn: &Connection, name: &str) -> String {
+    let mut rows = conn.query(&format!("PRAGMA {name}"), ()).await.unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    match row.get_value(0).unwrap() {
+        libsql::Value::Integer(value) => value.to_string(),
+        libsql::Value::Text(value) => value,
+        other => panic!("unexpected pragma value {other:?}"),
+    }
+}
+
+#[test]
+fn test_database_config_parse() {
+    let server: ServerConfig = serde_yaml::from_str(
+        r#"
+database:
+  journal_mode: truncate
+  busy_timeout_ms: 250
+  synchronous: full
+"#,
+    )
+    .unwrap();
+    assert_eq!(server.database.journal_mode, JournalMode::Truncate);
+    assert_eq!(server.database.busy_timeout_ms, 250);
+    assert_eq!(server.database.synchronous, Synchronous::Full);
+
+    let server: ServerConfig = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(server.database.journal_mode, JournalMode::Wal);
+    assert_eq!(server.database.busy_timeout_ms, 5000);
+    assert_eq!(server.database.synchronous, Synchronous::Normal);
+}
+
+#[tokio::test]
+async fn test_connections_get_the_default_tuning() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history.db");
+    let conn = db::connect(path.to_str().unwrap(), &DatabaseConfig::default())
+        .await
+        .unwrap();
+
+    assert_eq!(pragma(&conn, "journal_mode").await, "wal");
+    assert_eq!(pragma(&conn, "busy_timeout").await, "5000");
+    // NORMAL
+    assert_eq!(pragma(&conn, "synchronous").await, "1");
+}
+
+#[tokio::test]
+async fn test_tune_applies_the_config() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history.db");
+    let conn = Builder::new_local(path.to_str().unwrap())
+        .build()
+        .await
+        .unwrap()
+        .connect()
+        .unwrap();
+    let config = DatabaseConfig {
+        journal_mode: JournalMode::Truncate,
+        busy_timeout_ms: 250,
+        synchronous: Synchronous::Full,
+    };
+    db::tune(&conn, &config, false).await.unwrap();
+
+    assert_eq!(pragma(&conn, "journal_mode").await, "truncate");
+    assert_eq!(pragma(&conn, "busy_timeout").await, "250");
+    // FULL
+    assert_eq!(pragma(&conn, "synchronous").await, "2");
+}
+
+#[tokio::test]
+async fn test_in_memory_databases_keep_their_journal_mode() {
+    let conn = db::connect(":memory:", &DatabaseConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(pragma(&conn, "journal_mode").await, "memory");
+    assert_eq!(pragma(&conn, "busy_timeout").await, "5000");
+}
+
+#[tokio::test]
+async fn test_stores_opened_with_different_tuning_keep_their_own() {
+    let dir = TempDir::new().unwrap();
+    let rollback = dir.path().join("rollback.db");
+    let rollback = rollback.to_str().unwrap();
+    let wal = dir.path().join("wal.db");
+    let wal = wal.to_str().unwrap();
+    let config = DatabaseConfig {
+        journal_mode: JournalMode::Delete,
+        ..DatabaseConfig::default()
+    };
+    HistoryStorage::open(rollback, &config).await.unwrap();
+    TaskStore::new(wal).await.unwrap();
+
+    // WAL is a property of the file, so an untuned connection sees what each store set
+    for (path, mode) in [(rollback, "delete"), (wal, "wal")] {
+        let conn = Builder::new_local(path)
+            .build()
+            .await
+            .unwrap()
+            .connect()
+            .unwrap();
+        assert_eq!(pragma(&conn, "journal_mode").await, mode);
+    }
+}
+
+#[tokio::test]
+async fn test_stores_sharing_a_database_write_concurrently() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("history.db");
+    let path = path.to_str().unwrap();
+    let history = Arc::new(HistoryStorage::new(path).await.unwrap());
+    let tasks = Arc::new(TaskStore::new(path).await.unwrap());
+
+    let mut writers = Vec::new();
+    for i in 0..20 {
+        let history = history.clone();
+        let tasks = tasks.clone();
+        writers.push(tokio::spawn(async move {
+            history
+                .save(Message::user(format!("s{i}"), "Hi".to_string()))
+                .await
+                .unwrap();
+            tasks
+                .create(NewTask {
+                    title: format!("task {i}"),
+                    notes: None,
+                    due: None,
+                    session_id: format!("s{i}"),
+                })
+                .await
+                .unwrap();
+        }));
+    }
+    for writer in writers {
+        writer.await.unwrap();
+    }
+
+    assert_eq!(history.status().await, StorageStatus::Ok);
+    assert_eq!(history.buffered(), 0);
+    assert_eq!(history.list("s7").await.unwrap().len(), 1);
+    assert_eq!(tasks.list(None).await.unwrap().len(), 20);
+}
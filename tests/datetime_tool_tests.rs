@@ -0,0 +1,189 @@
+use chrono::{DateTime, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use jarvis_rust::{
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        datetime::{
+            AddDurationTool, CurrentDateTimeTool, DateTimeSettings, NextOccurrenceTool, Recurrence,
+            add_duration, next_occurrence, parse_datetime,
+        },
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const BERLIN: Tz = chrono_tz::Europe::Berlin;
+
+/// Thursday 2026-10-15 12:00 UTC
+fn fixed_clock() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap()
+}
+
+fn settings() -> DateTimeSettings {
+    DateTimeSettings::new(BERLIN).with_clock(fixed_clock)
+}
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_datetime_formats() {
+    let expected = BERLIN.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap();
+    assert_eq!(
+        parse_datetime("2026-10-16 09:30", BERLIN).unwrap(),
+        expected
+    );
+    assert_eq!(
+        parse_datetime("2026-10-16T07:30:00Z", BERLIN).unwrap(),
+        expected
+    );
+    assert_eq!(
+        parse_datetime("2026-10-16", BERLIN).unwrap(),
+        BERLIN.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap()
+    );
+    assert!(parse_datetime("next friday", BERLIN).is_err());
+}
+
+#[test]
+fn test_add_duration_calendar_units_keep_wall_clock_across_dst() {
+    let start = BERLIN.with_ymd_and_hms(2026, 10, 20, 9, 0, 0).unwrap();
+    // Berlin leaves summer time on 2026-10-25
+    let week_later = add_duration(start, 1.0, "weeks").unwrap();
+    assert_eq!(
+        week_later,
+        BERLIN.with_ymd_and_hms(2026, 10, 27, 9, 0, 0).unwrap()
+    );
+
+    let hours_later = add_duration(start, 168.0, "hours").unwrap();
+    assert_eq!(
+        hours_later,
+        BERLIN.with_ymd_and_hms(2026, 10, 27, 8, 0, 0).unwrap()
+    );
+
+    let jan31 = BERLIN.with_ymd_and_hms(2027, 1, 31, 0, 0, 0).unwrap();
+    assert_eq!(
+        add_duration(jan31, 1.0, "month").unwrap(),
+        BERLIN.with_ymd_and_hms(2027, 2, 28, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        add_duration(jan31, -2.0, "years").unwrap(),
+        BERLIN.with_ymd_and_hms(2025, 1, 31, 0, 0, 0).unwrap()
+    );
+    assert!(add_duration(jan31, 1.5, "days").is_err());
+    assert!(add_duration(jan31, 1.0, "fortnights").is_err());
+}
+
+#[test]
+fn test_add_duration_rejects_amounts_out_of_range() {
+    let start = BERLIN.with_ymd_and_hms(2026, 10, 20, 9, 0, 0).unwrap();
+    for (amount, unit) in [
+        (1e18, "days"),
+        (-1e18, "weeks"),
+        (1e18, "years"),
+        (5e9, "months"),
+        (1e30, "hours"),
+    ] {
+        assert!(
+            add_duration(start, amount, unit).is_err(),
+            "{amount} {unit}"
+        );
+    }
+    let error = add_duration(start, 1e18, "days").unwrap_err().to_string();
+    assert!(error.contains("amount out of range"), "{error}");
+}
+
+#[test]
+fn test_next_occurrence() {
+    let thursday = BERLIN.with_ymd_and_hms(2026, 10, 15, 14, 0, 0).unwrap();
+    assert_eq!(
+        next_occurrence(thursday, Recurrence::Weekday(Weekday::Fri), NaiveTime::MIN).unwrap(),
+        BERLIN.with_ymd_and_hms(2026, 10, 16, 0, 0, 0).unwrap()
+    );
+    // Same weekday later today still counts, earlier today rolls to next week
+    let evening = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+    assert_eq!(
+        next_occurrence(thursday, Recurrence::Weekday(Weekday::Thu), evening).unwrap(),
+        BERLIN.with_ymd_and_hms(2026, 10, 15, 18, 0, 0).unwrap()
+    );
+    assert_eq!(
+        next_occurrence(thursday, Recurrence::Weekday(Weekday::Thu), NaiveTime::MIN).unwrap(),
+        BERLIN.with_ymd_and_hms(2026, 10, 22, 0, 0, 0).unwrap()
+    );
+    assert_eq!(
+        next_occurrence(thursday, Recurrence::DayOfMonth(31), NaiveTime::MIN).unwrap(),
+        BERLIN.with_ymd_and_hms(2026, 10, 31, 0, 0, 0).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_tools_use_configured_timezone_and_clock() {
+    let ctx = ToolContext::new("s1", None);
+
+    let now = CurrentDateTimeTool(settings())
+        .call(HashMap::new(), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&now),
+        "2026-10-15T14:00:00+02:00 (Thursday, Europe/Berlin)"
+    );
+
+    let tokyo = CurrentDateTimeTool(settings())
+        .call(args(json!({"timezone": "Asia/Tokyo"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&tokyo),
+        "2026-10-15T21:00:00+09:00 (Thursday, Asia/Tokyo)"
+    );
+
+    assert!(
+        CurrentDateTimeTool(settings())
+            .call(args(json!({"timezone": "Mars/Olympus"})), &ctx)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_three_weeks_from_friday() {
+    let ctx = ToolContext::new("s1", None);
+
+    let friday = NextOccurrenceTool(settings())
+        .call(args(json!({"weekday": "friday", "time": "09:00"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&friday),
+        "2026-10-16T09:00:00+02:00 (Friday, Europe/Berlin)"
+    );
+
+    let later = AddDurationTool(settings())
+        .call(
+            args(json!({"start": "2026-10-16 09:00", "amount": 3, "unit": "weeks"})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&later),
+        "2026-11-06T09:00:00+01:00 (Friday, Europe/Berlin)"
+    );
+
+    assert!(
+        NextOccurrenceTool(settings())
+            .call(args(json!({"weekday": "friday", "day_of_month": 3})), &ctx)
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,198 @@
+#![cfg(feature = "email")]
+
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use jarvis_rust::{
+    Result,
+    config::{EmailToolConfig, SmtpConfig, SmtpSecurity},
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        email::{
+            EmailSummary, InboxQuery, MailSender, MailboxReader, OutgoingEmail, RecipientAllowlist,
+            SearchInboxTool, SendEmailTool, build_search_criteria, email_tools,
+        },
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RecordingSender {
+    sent: Mutex<Vec<OutgoingEmail>>,
+}
+
+#[async_trait]
+impl MailSender for RecordingSender {
+    async fn send(&self, email: &OutgoingEmail) -> Result<()> {
+        self.sent.lock().unwrap().push(email.clone());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct FixedInbox {
+    queries: Mutex<Vec<InboxQuery>>,
+}
+
+#[async_trait]
+impl MailboxReader for FixedInbox {
+    async fn search(&self, query: &InboxQuery) -> Result<Vec<EmailSummary>> {
+        self.queries.lock().unwrap().push(query.clone());
+        Ok(vec![
+            EmailSummary {
+                from: "Alice <alice@example.com>".to_string(),
+                subject: "Dinner".to_string(),
+                date: "Thu, 15 Oct 2026 18:00:00 +0000".to_string(),
+                unread: true,
+            },
+            EmailSummary {
+                from: "bob@example.com".to_string(),
+                subject: "Invoice".to_string(),
+                date: "Wed, 14 Oct 2026 09:00:00 +0000".to_string(),
+                unread: false,
+            },
+        ])
+    }
+}
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[test]
+fn test_recipient_allowlist() {
+    let allowlist = RecipientAllowlist::new(vec![
+        "Me@Example.com".to_string(),
+        "@family.example.com".to_string(),
+    ]);
+    assert!(allowlist.allows("me@example.com"));
+    assert!(allowlist.allows("mum@family.example.com"));
+    assert!(!allowlist.allows("someone@example.com"));
+    assert!(!allowlist.allows("x@evilfamily.example.com.attacker.io"));
+
+    assert!(RecipientAllowlist::default().allows("anyone@example.com"));
+}
+
+#[test]
+fn test_build_search_criteria() {
+    assert_eq!(build_search_criteria(&InboxQuery::default()), "ALL");
+    let query = InboxQuery {
+        text: Some("say \"hi\"".to_string()),
+        from: Some("alice".to_string()),
+        since: NaiveDate::from_ymd_opt(2026, 10, 1),
+        unread_only: true,
+        max_results: 10,
+    };
+    assert_eq!(
+        build_search_criteria(&query),
+        r#"TEXT "say \"hi\"" FROM "alice" SINCE 1-Oct-2026 UNSEEN"#
+    );
+}
+
+#[tokio::test]
+async fn test_send_email_enforces_allowlist() {
+    let sender = Arc::new(RecordingSender::default());
+    let tool = SendEmailTool::new(
+        sender.clone(),
+        RecipientAllowlist::new(vec!["@example.com".to_string()]),
+    );
+    assert!(tool.requires_approval());
+    let ctx = ToolContext::new("s1", None);
+
+    let response = tool
+        .call(
+            args(json!({"to": ["a@example.com"], "subject": "Hi", "body": "Hello"})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "Email 'Hi' sent to a@example.com");
+
+    let blocked = tool
+        .call(
+            args(json!({"to": "a@example.com", "cc": ["x@other.org"], "subject": "Hi", "body": "Hello"})),
+            &ctx,
+        )
+        .await;
+    assert!(blocked.unwrap_err().to_string().contains("x@other.org"));
+
+    let sent = sender.sent.lock().unwrap();
+    assert_eq!(
+        *sent,
+        vec![OutgoingEmail {
+            to: vec!["a@example.com".to_string()],
+            cc: Vec::new(),
+            subject: "Hi".to_string(),
+            body: "Hello".to_string(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn test_search_inbox_formats_results() {
+    let inbox = Arc::new(FixedInbox::default());
+    let tool = SearchInboxTool::new(inbox.clone());
+    assert!(!tool.requires_approval());
+    let ctx = ToolContext::new("s1", None);
+
+    let response = tool
+        .call(
+            args(json!({"from": "alice", "since": "2026-10-01", "unread_only": true})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&response),
+        "1. [unread] Dinner — from Alice <alice@example.com> (Thu, 15 Oct 2026 18:00:00 +0000)\n\
+         2. Invoice — from bob@example.com (Wed, 14 Oct 2026 09:00:00 +0000)"
+    );
+    {
+        let queries = inbox.queries.lock().unwrap();
+        assert_eq!(queries[0].from.as_deref(), Some("alice"));
+        assert_eq!(queries[0].max_results, 10);
+        assert!(queries[0].unread_only);
+    }
+
+    assert!(
+        tool.call(args(json!({"since": "last week"})), &ctx)
+            .await
+            .is_err()
+    );
+}
+
+#[test]
+fn test_email_tools_from_config() {
+    let mut config = EmailToolConfig {
+        smtp: Some(SmtpConfig {
+            host: "localhost".to_string(),
+            port: 2525,
+            security: SmtpSecurity::None,
+            username: None,
+            password: None,
+        }),
+        ..Default::default()
+    };
+    assert!(email_tools(&config).unwrap().is_empty());
+
+    config.enabled = true;
+    assert!(email_tools(&config).is_err());
+
+    config.from = Some("jarvis@example.com".to_string());
+    let names: Vec<String> = email_tools(&config)
+        .unwrap()
+        .iter()
+        .map(|t| t.definition().name)
+        .collect();
+    assert_eq!(names, vec!["send_email".to_string()]);
+}
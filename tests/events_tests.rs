@@ -0,0 +1,376 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    events::{
+        ProgressUpdate, RunMetrics, SessionEventHook, SessionEventKind, SessionEvents,
+        progress_stream,
+    },
+    history::HistoryStorage,
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, session_events},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_stream::StreamExt;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn agent_with_events(mock_llm: MockLlmClient, events: Arc<SessionEvents>) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent.add_hook(Arc::new(SessionEventHook::new(events)));
+    agent
+}
+
+#[tokio::test]
+async fn test_hook_publishes_run_events() {
+    let events = Arc::new(SessionEvents::new(16));
+    let mut receiver = events.subscribe();
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "clock",
+        r#"{"zone": "UTC"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = agent_with_events(mock_llm, events);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        assert_eq!(event.session_id, "s1");
+        kinds.push(event.kind);
+    }
+    assert_eq!(
+        kinds,
+        vec![
+            SessionEventKind::Message {
+                role: "user".to_string(),
+                content: "What time is it?".to_string(),
+            },
+            SessionEventKind::LlmCall { turn: 0 },
+            SessionEventKind::ToolCall {
+                name: "clock".to_string(),
+                arguments: json!({"zone": "UTC"}),
+            },
+            SessionEventKind::ToolResult {
+                name: "clock".to_string(),
+                is_error: false,
+            },
+            SessionEventKind::LlmCall { turn: 1 },
+            SessionEventKind::Message {
+                role: "assistant".to_string(),
+                content: "It is 8 o'clock.".to_string(),
+            },
+            SessionEventKind::RunCompleted,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_hook_publishes_commentary() {
+    let events = Arc::new(SessionEvents::new(16));
+    let mut receiver = events.subscribe();
+    let mock_llm = MockLlmClient::new();
+    let mut mixed = create_mock_tool_call_response("clock", "{}");
+    mixed.choices[0].message.content = "Checking the clock.".into();
+    mock_llm.add_response(mixed);
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = agent_with_events(mock_llm, events);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        kinds.push(event.kind);
+    }
+    assert_eq!(
+        kinds[2],
+        SessionEventKind::Commentary {
+            content: "Checking the clock.".to_string(),
+        }
+    );
+    assert_eq!(kinds[3].name(), "tool_call");
+}
+
+#[tokio::test]
+async fn test_hook_publishes_failed_runs() {
+    let events = Arc::new(SessionEvents::new(16));
+    let mut receiver = events.subscribe();
+    // No scripted responses, so the LLM call fails
+    let mut agent = agent_with_events(MockLlmClient::new(), events);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    assert!(agent.process("s1", "Hello", &history).await.is_err());
+
+    let mut last = None;
+    while let Ok(event) = receiver.try_recv() {
+        last = Some(event.kind);
+    }
+    assert!(matches!(last, Some(SessionEventKind::RunFailed { .. })));
+}
+
+async fn events_app(events: Option<Arc<SessionEvents>>) -> Router {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/sessions/:session_id/events", get(session_events))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn test_events_endpoint_streams_session_events() {
+    let events = Arc::new(SessionEvents::new(16));
+    let response = events_app(Some(events.clone()))
+        .await
+        .oneshot(
+            Request::builder()
+                .uri("/sessions/s1/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "text/event-stream"
+    );
+
+    events.publish("s2", SessionEventKind::RunCompleted);
+    events.publish(
+        "s1",
+        SessionEventKind::Message {
+            role: "user".to_string(),
+            content: "Hi".to_string(),
+        },
+    );
+    events.publish("s1", SessionEventKind::RunCompleted);
+
+    let mut body = response.into_body().into_data_stream();
+    let mut received = String::new();
+    while !received.contains("event: run_completed") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), body.next())
+            .await
+            .expect("timed out waiting for events")
+            .unwrap()
+            .unwrap();
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+
+    let frames: Vec<(&str, Value)> = received
+        .split("\n\n")
+        .filter(|frame| !frame.is_empty())
+        .map(|frame| {
+            let mut lines = frame.lines();
+            let name = lines.next().unwrap().strip_prefix("event: ").unwrap();
+            let data = lines.next().unwrap().strip_prefix("data: ").unwrap();
+            (name, serde_json::from_str(data).unwrap())
+        })
+        .collect();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].0, "message");
+    assert_eq!(frames[0].1["session_id"], "s1");
+    assert_eq!(frames[0].1["type"], "message");
+    assert_eq!(frames[0].1["content"], "Hi");
+    assert_eq!(frames[1].0, "run_completed");
+}
+
+#[tokio::test]
+async fn test_events_endpoint_unavailable_without_bus() {
+    let response = events_app(None)
+        .await
+        .oneshot(
+            Request::builder()
+                .uri("/sessions/s1/events")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+}
+
+fn placeholder(text: &str) -> ProgressUpdate {
+    ProgressUpdate::Placeholder {
+        text: text.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_progress_follows_the_run() {
+    let events = SessionEvents::new(16);
+    let progress = progress_stream(&events, "s1".to_string(), Duration::from_secs(1), true);
+    tokio::pin!(progress);
+
+    events.publish(
+        "s1",
+        SessionEventKind::Message {
+            role: "user".to_string(),
+            content: "What time is it?".to_string(),
+        },
+    );
+    events.publish("s2", SessionEventKind::LlmCall { turn: 0 });
+    events.publish("s1", SessionEventKind::LlmCall { turn: 0 });
+    events.publish(
+        "s1",
+        SessionEventKind::ToolCall {
+            name: "clock".to_string(),
+            arguments: json!({}),
+        },
+    );
+    events.publish("s1", SessionEventKind::LlmCall { turn: 1 });
+    events.publish("s1", SessionEventKind::RunCompleted);
+
+    let mut updates = Vec::new();
+    for _ in 0..5 {
+        let update = tokio::time::timeout(Duration::from_secs(5), progress.next())
+            .await
+            .expect("timed out waiting for progress")
+            .unwrap();
+        updates.push(update);
+    }
+    assert_eq!(
+        updates,
+        vec![
+            ProgressUpdate::Typing,
+            placeholder("Thinking…"),
+            placeholder("Running clock…"),
+            placeholder("Reading the results…"),
+            ProgressUpdate::Done { failed: false },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_typing_is_renewed_while_running() {
+    let events = SessionEvents::new(16);
+    let progress = progress_stream(&events, "s1".to_string(), Duration::from_secs(1), false);
+    tokio::pin!(progress);
+
+    events.publish("s1", SessionEventKind::LlmCall { turn: 0 });
+    for _ in 0..2 {
+        let update = tokio::time::timeout(Duration::from_secs(5), progress.next())
+            .await
+            .expect("timed out waiting for progress")
+            .unwrap();
+        assert_eq!(update, ProgressUpdate::Typing);
+    }
+    events.publish(
+        "s1",
+        SessionEventKind::RunFailed {
+            error: "boom".to_string(),
+        },
+    );
+    let update = tokio::time::timeout(Duration::from_secs(5), progress.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(update, ProgressUpdate::Done { failed: true });
+}
+
+#[tokio::test]
+async fn test_subscribers_follow_runs() {
+    let events = Arc::new(SessionEvents::new(16));
+    let metrics = Arc::new(RunMetrics::new());
+    let subscriber = events.spawn_subscriber("metrics", metrics.clone());
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = agent_with_events(mock_llm, events.clone());
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+    // The subscriber stops once it has seen every event and nobody can publish more
+    drop(agent);
+    drop(events);
+    subscriber.await.unwrap();
+
+    let rendered = metrics.render();
+    assert!(rendered.contains("jarvis_runs_total{outcome=\"completed\"} 1\n"));
+    assert!(rendered.contains("jarvis_runs_total{outcome=\"failed\"} 0\n"));
+    assert!(rendered.contains("jarvis_llm_calls_total 2\n"));
+    assert!(rendered.contains("jarvis_tool_calls_total{tool=\"clock\",outcome=\"ok\"} 1\n"));
+}
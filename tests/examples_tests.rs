@@ -0,0 +1,175 @@
+use axum::{Router, body::Body, http::Request, routing::get};
+use jarvis_rust::{
+    agent::Agent,
+    config::{ExampleConfig, parse},
+    examples::{self, Example, ExampleFilter, MAX_LABEL_CHARS},
+    history::HistoryStorage,
+    server::handlers::{AppState, list_examples},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::MockLlmClient;
+
+const CONFIG: &str = r#"
+strict: true
+llm:
+  base_url: "https://api.openai.com"
+  api_key: "test-key"
+  model: "gpt-4"
+server: {}
+examples:
+  - prompt: "What is 15% of 240?"
+    tool: "calculator"
+  - prompt: "Convert 5 miles to kilometers"
+    label: "Miles to km"
+    tool: "units"
+  - prompt: "What's on my calendar tomorrow?"
+    tool: "calendar"
+    profile: "family"
+  - prompt: "Add milk to my shopping list"
+    tool: "create_task"
+    profile: "demo"
+"#;
+
+fn configured() -> Vec<ExampleConfig> {
+    parse(CONFIG).unwrap().examples
+}
+
+fn prompts(examples: &[Example]) -> Vec<&str> {
+    examples
+        .iter()
+        .map(|example| example.prompt.as_str())
+        .collect()
+}
+
+#[test]
+fn test_examples_are_filtered_by_tool_and_profile() {
+    let examples = configured();
+
+    let all = examples::list(&examples, &ExampleFilter::default());
+    assert_eq!(all.len(), 4);
+    assert_eq!(all[0].label, "What is 15% of 240?");
+    assert_eq!(all[1].label, "Miles to km");
+
+    let units = examples::list(
+        &examples,
+        &ExampleFilter {
+            tool: Some("units".to_string()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(prompts(&units), vec!["Convert 5 miles to kilometers"]);
+
+    // Examples without a profile are offered to every profile
+    let family = examples::list(
+        &examples,
+        &ExampleFilter {
+            profile: Some("family".to_string()),
+            ..Default::default()
+        },
+    );
+    assert_eq!(
+        prompts(&family),
+        vec![
+            "What is 15% of 240?",
+            "Convert 5 miles to kilometers",
+            "What's on my calendar tomorrow?",
+        ]
+    );
+
+    let limited = examples::list(
+        &examples,
+        &ExampleFilter {
+            limit: Some(1),
+            ..Default::default()
+        },
+    );
+    assert_eq!(prompts(&limited), vec!["What is 15% of 240?"]);
+}
+
+#[test]
+fn test_long_prompts_get_shortened_labels() {
+    let example = ExampleConfig {
+        prompt: "Find a vegetarian restaurant near the office that is open after ten".to_string(),
+        label: None,
+        tool: None,
+        profile: None,
+    };
+    let label = Example::from(&example).label;
+    assert_eq!(label, "Find a vegetarian restaurant near the…");
+    assert!(label.chars().count() <= MAX_LABEL_CHARS);
+
+    let example = ExampleConfig {
+        prompt: "x".repeat(60),
+        ..example
+    };
+    let label = Example::from(&example).label;
+    assert_eq!(label.chars().count(), MAX_LABEL_CHARS);
+    assert!(label.ends_with('…'));
+}
+
+#[tokio::test]
+async fn test_examples_endpoint() {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Arc::new(configured()),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/examples", get(list_examples))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/examples?profile=demo&limit=3")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!([
+            {"label": "What is 15% of 240?", "prompt": "What is 15% of 240?", "tool": "calculator", "profile": null},
+            {"label": "Miles to km", "prompt": "Convert 5 miles to kilometers", "tool": "units", "profile": null},
+            {"label": "Add milk to my shopping list", "prompt": "Add milk to my shopping list", "tool": "create_task", "profile": "demo"},
+        ])
+    );
+}
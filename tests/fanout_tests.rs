@@ -0,0 +1,206 @@
+use jarvis_rust::{
+    agent::{Agent, parse_subquestions},
+    config::{AgentConfig, AgentMode, FanoutConfig},
+    history::HistoryStorage,
+    llm::{Function, Tool},
+    mcp::McpClient,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+#[test]
+fn test_parse_subquestions() {
+    assert_eq!(
+        parse_subquestions(r#"["Weather in Lisbon?", "Time in Tokyo?"]"#, 4),
+        vec!["Weather in Lisbon?", "Time in Tokyo?"]
+    );
+    // Fenced replies, blank entries and the width limit
+    assert_eq!(
+        parse_subquestions("```json\n[\"a?\", \" \", \"b?\", \"c?\"]\n```", 2),
+        vec!["a?", "b?"]
+    );
+    assert!(parse_subquestions("[]", 4).is_empty());
+    assert!(parse_subquestions("It is a single question.", 4).is_empty());
+    assert!(parse_subquestions("] not [ json", 4).is_empty());
+}
+
+#[test]
+fn test_fanout_config_parse() {
+    let config: AgentConfig = serde_yaml::from_str(
+        r#"
+mode: fanout
+fanout:
+  max_subquestions: 3
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.mode, AgentMode::Fanout);
+    assert_eq!(config.fanout.max_subquestions, 3);
+    assert_eq!(config.fanout.max_concurrency, 2);
+
+    let config = AgentConfig::default();
+    assert_eq!(config.mode, AgentMode::Single);
+    assert_eq!(config.fanout.max_subquestions, 4);
+}
+
+fn weather_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "weather".to_string(),
+            description: "Forecast for a city".to_string(),
+            parameters: json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+        },
+    }
+}
+
+fn fanout_agent(mock_llm: MockLlmClient, fanout: FanoutConfig) -> Agent {
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "weather".to_string(),
+        create_mock_tool_response("Sunny, 24°C"),
+    );
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("weather".to_string(), Box::new(mock_mcp));
+    let tool_to_client_map = HashMap::from([("weather".to_string(), "weather".to_string())]);
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![weather_tool()],
+    )
+    .with_fanout(fanout)
+}
+
+#[tokio::test]
+async fn test_fanout_answers_subquestions_and_combines_them() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(
+        r#"["Weather in Lisbon?", "Time in Tokyo?"]"#,
+    ));
+    // One sub-run at a time, so the responses are used in order
+    mock_llm.add_response(create_mock_tool_call_response(
+        "weather",
+        r#"{"city": "Lisbon"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("Lisbon is sunny, 24°C."));
+    mock_llm.add_response(create_mock_chat_response("It is 9am in Tokyo."));
+    mock_llm.add_response(create_mock_chat_response(
+        "Lisbon is sunny at 24°C, and it is 9am in Tokyo.",
+    ));
+    let mut agent = fanout_agent(
+        mock_llm,
+        FanoutConfig {
+            max_subquestions: 4,
+            max_concurrency: 1,
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process_with_citations("session", "Weather in Lisbon and time in Tokyo?", &history)
+        .await
+        .unwrap();
+    assert_eq!(
+        reply.output,
+        "Lisbon is sunny at 24°C, and it is 9am in Tokyo."
+    );
+    assert!(
+        reply
+            .citations
+            .iter()
+            .any(|c| c.source_id == "tool:weather")
+    );
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 5);
+    // Each sub-run gets its own question as the last message
+    assert_eq!(
+        requests[1].messages.last().unwrap().content,
+        "Weather in Lisbon?"
+    );
+    assert_eq!(
+        requests[3].messages.last().unwrap().content,
+        "Time in Tokyo?"
+    );
+    assert!(!requests[3].tools.is_empty());
+
+    // The combining request carries the answers and no tools
+    let combine = &requests[4];
+    assert!(combine.tools.is_empty());
+    let findings = &combine.messages.last().unwrap().content;
+    assert!(
+        findings
+            .text()
+            .contains("1. Weather in Lisbon?\nLisbon is sunny, 24°C.")
+    );
+    assert!(
+        findings
+            .text()
+            .contains("2. Time in Tokyo?\nIt is 9am in Tokyo.")
+    );
+
+    // The sub-runs' tool calls are recorded with the reply
+    let messages = history.list("session").await.unwrap();
+    let metadata = messages.last().unwrap().metadata.clone().unwrap();
+    assert_eq!(metadata["tool_calls"][0]["name"], json!("weather"));
+}
+
+#[tokio::test]
+async fn test_fanout_limits_width() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(r#"["a?", "b?", "c?"]"#));
+    mock_llm.add_response(create_mock_chat_response("A."));
+    mock_llm.add_response(create_mock_chat_response("B."));
+    mock_llm.add_response(create_mock_chat_response("A and B."));
+    let mut agent = fanout_agent(
+        mock_llm,
+        FanoutConfig {
+            max_subquestions: 2,
+            max_concurrency: 2,
+        },
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process("session", "a, b and c?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "A and B.");
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 4);
+    assert!(
+        !requests[3]
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .text()
+            .contains("c?")
+    );
+}
+
+#[tokio::test]
+async fn test_single_question_runs_once() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response("[]"));
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let mut agent = fanout_agent(mock_llm, FanoutConfig::default());
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent.process("session", "Hi", &history).await.unwrap();
+    assert_eq!(reply, "Hello!");
+
+    // Answered normally, with the tools and the user's message last
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[1].messages.last().unwrap().content, "Hi");
+    assert_eq!(requests[1].tools.len(), 1);
+}
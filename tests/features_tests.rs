@@ -0,0 +1,129 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{ApiKeyConfig, Features, NotificationProvider},
+    history::HistoryStorage,
+    server::handlers::{AppState, get_features},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, test_utils::create_test_config};
+
+fn key(name: &str, admin: bool) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: Vec::new(),
+        default_model: None,
+        requests_per_minute: None,
+        admin,
+    }
+}
+
+#[test]
+fn test_features_need_their_flag_and_configuration() {
+    let mut config = create_test_config();
+    assert_eq!(
+        config.features(),
+        Features {
+            streaming: true,
+            approvals: false,
+            rag: false,
+            scheduler: true,
+            feeds: false,
+            notifications: false,
+            commands: false,
+        }
+    );
+
+    config.knowledge.enabled = true;
+    config.notifications.provider = NotificationProvider::Ntfy;
+    config
+        .commands
+        .integrations
+        .insert("telegram".to_string(), true);
+    config.features = serde_yaml::from_str("rag: false\nscheduler: false").unwrap();
+    let features = config.features();
+    assert!(!features.rag);
+    assert!(!features.scheduler);
+    assert_eq!(features.notifications, cfg!(feature = "notifications"));
+    assert!(features.commands);
+    assert!(features.streaming);
+}
+
+#[tokio::test]
+async fn test_admin_keys_list_features() {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(vec![key("ops", true), key("app", false)]),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Arc::new(create_test_config().features()),
+    };
+    let app = Router::new()
+        .route("/admin/features", get(get_features))
+        .with_state(state);
+    let get = |name: &str| {
+        Request::builder()
+            .uri("/admin/features")
+            .header(header::AUTHORIZATION, format!("Bearer {name}-key"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(get("ops")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let features: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        features,
+        json!({
+            "streaming": true,
+            "approvals": false,
+            "rag": false,
+            "scheduler": true,
+            "feeds": false,
+            "notifications": false,
+            "commands": false
+        })
+    );
+
+    let response = app.oneshot(get("app")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
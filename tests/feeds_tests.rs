@@ -0,0 +1,174 @@
+#![cfg(feature = "feeds")]
+
+use chrono::{Duration, TimeZone, Utc};
+use jarvis_rust::{
+    config::FeedConfig,
+    feeds::{FeedItem, FeedStore, parse_feed, poll_feed},
+    mcp::McpContent,
+    tools::{NativeTool, ToolContext, feeds::RecentFeedItemsTool},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>News</title>
+  <item>
+    <guid>item-1</guid>
+    <title>Rust 2.0 released</title>
+    <link>https://example.com/rust</link>
+    <description>&lt;p&gt;Big &amp;amp; bold &lt;b&gt;news&lt;/b&gt;&lt;/p&gt;</description>
+    <pubDate>Thu, 15 Oct 2026 08:00:00 GMT</pubDate>
+  </item>
+  <item>
+    <guid>item-2</guid>
+    <title>Second story</title>
+    <link>https://example.com/second</link>
+  </item>
+</channel></rss>"#;
+
+const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Blog</title>
+  <id>urn:blog</id>
+  <updated>2026-10-14T10:00:00Z</updated>
+  <entry>
+    <id>urn:post:1</id>
+    <title>Hello Atom</title>
+    <link href="https://blog.example.com/1"/>
+    <updated>2026-10-14T10:00:00Z</updated>
+    <summary>First post</summary>
+  </entry>
+</feed>"#;
+
+fn feed(name: &str, url: String) -> FeedConfig {
+    FeedConfig {
+        name: name.to_string(),
+        url,
+        interval_minutes: 30,
+    }
+}
+
+#[test]
+fn test_parse_rss_and_atom() {
+    let fetched_at = Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap();
+    let items = parse_feed("news", RSS.as_bytes(), fetched_at).unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(
+        items[0],
+        FeedItem {
+            feed: "news".to_string(),
+            guid: "item-1".to_string(),
+            title: "Rust 2.0 released".to_string(),
+            link: Some("https://example.com/rust".to_string()),
+            summary: Some("Big & bold news".to_string()),
+            published: Some(Utc.with_ymd_and_hms(2026, 10, 15, 8, 0, 0).unwrap()),
+            fetched_at,
+        }
+    );
+    assert_eq!(items[1].summary, None);
+
+    let atom = parse_feed("blog", ATOM.as_bytes(), fetched_at).unwrap();
+    assert_eq!(atom[0].guid, "urn:post:1");
+    assert_eq!(atom[0].link.as_deref(), Some("https://blog.example.com/1"));
+    assert_eq!(atom[0].summary.as_deref(), Some("First post"));
+
+    assert!(parse_feed("bad", b"not a feed", fetched_at).is_err());
+}
+
+#[tokio::test]
+async fn test_store_deduplicates_and_filters() {
+    let store = FeedStore::new(":memory:").await.unwrap();
+    let now = Utc::now();
+    let items = parse_feed("news", RSS.as_bytes(), now).unwrap();
+
+    assert_eq!(store.insert_new(&items).await.unwrap(), 2);
+    assert_eq!(store.insert_new(&items).await.unwrap(), 0);
+
+    let old = parse_feed("blog", ATOM.as_bytes(), now - Duration::days(3)).unwrap();
+    assert_eq!(store.insert_new(&old).await.unwrap(), 1);
+
+    let recent = store
+        .recent(now - Duration::hours(1), None, 10)
+        .await
+        .unwrap();
+    assert_eq!(
+        recent.iter().map(|i| i.guid.as_str()).collect::<Vec<_>>(),
+        vec!["item-2", "item-1"]
+    );
+
+    let blog = store
+        .recent(now - Duration::days(7), Some("blog"), 10)
+        .await
+        .unwrap();
+    assert_eq!(blog.len(), 1);
+    assert_eq!(
+        store
+            .recent(now - Duration::days(7), None, 1)
+            .await
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_poll_feed_stores_new_items() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/rss"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(RSS))
+        .mount(&server)
+        .await;
+
+    let store = FeedStore::new(":memory:").await.unwrap();
+    let client = reqwest::Client::new();
+    let config = feed("news", format!("{}/rss", server.uri()));
+    assert_eq!(poll_feed(&client, &store, &config).await.unwrap(), 2);
+    assert_eq!(poll_feed(&client, &store, &config).await.unwrap(), 0);
+
+    let missing = feed("missing", format!("{}/missing", server.uri()));
+    assert!(poll_feed(&client, &store, &missing).await.is_err());
+}
+
+#[tokio::test]
+async fn test_recent_feed_items_tool() {
+    let store = Arc::new(FeedStore::new(":memory:").await.unwrap());
+    let tool = RecentFeedItemsTool::new(store.clone());
+    let ctx = ToolContext::new("s1", None);
+
+    let empty = tool.call(HashMap::new(), &ctx).await.unwrap();
+    assert!(matches!(&empty.content[0], McpContent::Text { text } if text == "No new feed items"));
+
+    store
+        .insert_new(&parse_feed("news", RSS.as_bytes(), Utc::now()).unwrap())
+        .await
+        .unwrap();
+    let arguments: HashMap<_, _> =
+        serde_json::from_value(json!({"feed": "news", "limit": 1})).unwrap();
+    let response = tool.call(arguments, &ctx).await.unwrap();
+    match &response.content[0] {
+        McpContent::Text { text } => {
+            assert_eq!(text, "[news] Second story\n  https://example.com/second")
+        }
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_recent_feed_items_tool_rejects_a_huge_window() {
+    let store = Arc::new(FeedStore::new(":memory:").await.unwrap());
+    let tool = RecentFeedItemsTool::new(store);
+    let ctx = ToolContext::new("s1", None);
+
+    for hours in [1e12, 1e300] {
+        let arguments: HashMap<_, _> = serde_json::from_value(json!({"hours": hours})).unwrap();
+        let error = tool.call(arguments, &ctx).await.unwrap_err().to_string();
+        assert!(error.contains("hours out of range"), "{error}");
+    }
+}
@@ -0,0 +1,220 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    history::HistoryStorage,
+    mcp::{McpContent, McpToolCallResponse},
+    notifications::{Notification, NotificationSink},
+    scheduler::{FollowUpStore, Scheduler},
+    tools::{NativeTool, ToolContext, datetime::DateTimeSettings, followup::ScheduleFollowUpTool},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn fixed_clock() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap()
+}
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_store_due_and_complete() {
+    let store = FollowUpStore::new(":memory:").await.unwrap();
+    let now = fixed_clock();
+    let first = store
+        .create("s1", "Check the oven", now - Duration::minutes(5))
+        .await
+        .unwrap();
+    store
+        .create("s2", "Ask about the trip", now + Duration::hours(1))
+        .await
+        .unwrap();
+
+    let due = store.due(now).await.unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0].id, first);
+    assert_eq!(due[0].session_id, "s1");
+    assert_eq!(due[0].prompt, "Check the oven");
+    assert_eq!(due[0].due_at, now - Duration::minutes(5));
+
+    store.complete(first).await.unwrap();
+    assert!(store.due(now).await.unwrap().is_empty());
+    assert_eq!(store.due(now + Duration::hours(2)).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_store_callbacks() {
+    let store = FollowUpStore::new(":memory:").await.unwrap();
+    assert_eq!(store.callback("s1").await.unwrap(), None);
+
+    store.set_callback("s1", "http://a/hook").await.unwrap();
+    store.set_callback("s1", "http://b/hook").await.unwrap();
+    assert_eq!(
+        store.callback("s1").await.unwrap().as_deref(),
+        Some("http://b/hook")
+    );
+}
+
+#[tokio::test]
+async fn test_schedule_followup_tool() {
+    let store = Arc::new(FollowUpStore::new(":memory:").await.unwrap());
+    let settings = DateTimeSettings::new(chrono_tz::Europe::Berlin).with_clock(fixed_clock);
+    let tool = ScheduleFollowUpTool::new(store.clone(), settings);
+    let ctx = ToolContext::new("s1", None);
+
+    let response = tool
+        .call(
+            args(json!({"instruction": "Remind them to call mum", "delay": "2h"})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&response),
+        "Follow-up 1 scheduled for 2026-10-15T16:00:00+02:00 (Thursday, Europe/Berlin)"
+    );
+
+    tool.call(
+        args(json!({"instruction": "Morning check-in", "at": "2026-10-16 09:00"})),
+        &ctx,
+    )
+    .await
+    .unwrap();
+
+    let due = store.due(fixed_clock() + Duration::days(1)).await.unwrap();
+    assert_eq!(due.len(), 2);
+    assert_eq!(due[0].prompt, "Remind them to call mum");
+    assert_eq!(due[0].due_at, fixed_clock() + Duration::hours(2));
+    assert_eq!(
+        due[1].due_at,
+        Utc.with_ymd_and_hms(2026, 10, 16, 7, 0, 0).unwrap()
+    );
+
+    for invalid in [
+        json!({"instruction": "x"}),
+        json!({"instruction": "x", "delay": "1h", "at": "2026-10-16 09:00"}),
+        json!({"instruction": "x", "at": "2026-10-14 09:00"}),
+        json!({"instruction": "x", "delay": "soon"}),
+    ] {
+        assert!(tool.call(args(invalid), &ctx).await.is_err());
+    }
+    assert!(
+        tool.call(
+            args(json!({"instruction": "x", "delay": "1h"})),
+            &ToolContext::new("", None)
+        )
+        .await
+        .is_err()
+    );
+}
+
+#[derive(Default)]
+struct RecordingSink {
+    sent: StdMutex<Vec<Notification>>,
+}
+
+#[async_trait]
+impl NotificationSink for RecordingSink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        self.sent.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+fn scheduler(replies: &[&str], history: Arc<HistoryStorage>) -> Scheduler {
+    let mock_llm = MockLlmClient::new();
+    for reply in replies {
+        mock_llm.add_response(create_mock_chat_response(reply));
+    }
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    Scheduler::new(Arc::new(Mutex::new(agent)), history, chrono_tz::UTC)
+}
+
+#[tokio::test]
+async fn test_due_followups_are_delivered_to_session_callback() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(body_json(json!({
+            "session_id": "s1",
+            "output": "Time to call mum!",
+            "followup_id": 1
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let store = FollowUpStore::new(":memory:").await.unwrap();
+    store
+        .create(
+            "s1",
+            "Remind them to call mum",
+            Utc::now() - Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+    store
+        .set_callback("s1", &format!("{}/hook", server.uri()))
+        .await
+        .unwrap();
+
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let scheduler = scheduler(&["Time to call mum!"], history.clone());
+
+    assert_eq!(scheduler.run_due_followups(&store).await.unwrap(), 1);
+    // Already completed, so nothing runs twice
+    assert_eq!(scheduler.run_due_followups(&store).await.unwrap(), 0);
+
+    let messages = history.list("s1").await.unwrap();
+    assert!(messages[0].content.contains("Remind them to call mum"));
+    assert_eq!(messages.last().unwrap().content, "Time to call mum!");
+}
+
+#[tokio::test]
+async fn test_due_followups_fall_back_to_notifications() {
+    let store = FollowUpStore::new(":memory:").await.unwrap();
+    store
+        .create(
+            "s2",
+            "Check on the laundry",
+            Utc::now() - Duration::minutes(1),
+        )
+        .await
+        .unwrap();
+
+    let sink = Arc::new(RecordingSink::default());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let scheduler = scheduler(&["Laundry should be done."], history).with_notifier(sink.clone());
+
+    scheduler.run_due_followups(&store).await.unwrap();
+    assert_eq!(
+        *sink.sent.lock().unwrap(),
+        vec![Notification::new("Follow-up", "Laundry should be done.")]
+    );
+}
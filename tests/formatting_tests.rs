@@ -0,0 +1,275 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, header},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{FormattingConfig, OutputFormat},
+    formatting::{chunk, render, render_chunks},
+    history::HistoryStorage,
+    server::handlers::{AppState, inference},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+const REPLY: &str = "## Results\n\
+    Found **2** items for `a<b` in [the docs](https://example.com/?a=1&b=2):\n\
+    - *first* one\n\
+    - ~~second~~ one\n\
+    ```rust\n\
+    let x = a < b;\n\
+    ```";
+
+#[test]
+fn test_markdown_is_left_alone() {
+    assert_eq!(render(REPLY, OutputFormat::Markdown, 80), REPLY);
+}
+
+#[test]
+fn test_telegram_html() {
+    assert_eq!(
+        render(REPLY, OutputFormat::TelegramHtml, 80),
+        "<b>Results</b>\n\
+         Found <b>2</b> items for <code>a&lt;b</code> in \
+         <a href=\"https://example.com/?a=1&amp;b=2\">the docs</a>:\n\
+         • <i>first</i> one\n\
+         • <s>second</s> one\n\
+         <pre><code class=\"language-rust\">let x = a &lt; b;</code></pre>"
+    );
+}
+
+#[test]
+fn test_slack_mrkdwn() {
+    assert_eq!(
+        render(REPLY, OutputFormat::SlackMrkdwn, 80),
+        "*Results*\n\
+         Found *2* items for `a&lt;b` in <https://example.com/?a=1&amp;b=2|the docs>:\n\
+         • _first_ one\n\
+         • ~second~ one\n\
+         ```\nlet x = a &lt; b;\n```"
+    );
+}
+
+#[test]
+fn test_plain_text() {
+    assert_eq!(
+        render(REPLY, OutputFormat::Plain, 0),
+        "Results\n\
+         Found 2 items for a<b in the docs (https://example.com/?a=1&b=2):\n\
+         - first one\n\
+         - second one\n\
+         let x = a < b;"
+    );
+}
+
+#[test]
+fn test_plain_text_is_wrapped() {
+    assert_eq!(
+        render(
+            "- the quick brown fox jumps over the lazy dog",
+            OutputFormat::Plain,
+            20
+        ),
+        "- the quick brown\n  fox jumps over the\n  lazy dog"
+    );
+    // Code keeps its lines
+    assert_eq!(
+        render(
+            "```\nthe quick brown fox jumps over the lazy dog\n```",
+            OutputFormat::Plain,
+            20
+        ),
+        "the quick brown fox jumps over the lazy dog"
+    );
+}
+
+#[test]
+fn test_underscores_inside_words_are_not_emphasis() {
+    assert_eq!(
+        render(
+            "Set snake_case_name to 2 * 3 * 4",
+            OutputFormat::TelegramHtml,
+            80
+        ),
+        "Set snake_case_name to 2 * 3 * 4"
+    );
+}
+
+#[test]
+fn test_short_text_is_one_chunk() {
+    assert_eq!(chunk("Hello", 10), vec!["Hello"]);
+    assert_eq!(chunk(&"a".repeat(50), 0), vec!["a".repeat(50)]);
+}
+
+#[test]
+fn test_chunks_break_between_paragraphs() {
+    let text = "First paragraph.\n\nSecond paragraph.\n\nThird.";
+    assert_eq!(
+        chunk(text, 30),
+        vec!["First paragraph.", "Second paragraph.\n\nThird."]
+    );
+}
+
+#[test]
+fn test_long_paragraphs_break_between_lines_then_words() {
+    assert_eq!(
+        chunk("one two\nthree four", 12),
+        vec!["one two", "three four"]
+    );
+    assert_eq!(
+        chunk("the quick brown fox jumps", 10),
+        vec!["the quick", "brown fox", "jumps"]
+    );
+    assert_eq!(chunk("abcdefghij", 4), vec!["abcd", "efgh", "ij"]);
+}
+
+#[test]
+fn test_code_blocks_are_kept_whole_when_they_fit() {
+    let text = "Intro\n\n```rust\nfn a() {}\n\nfn b() {}\n```\n\nOutro";
+    assert_eq!(
+        chunk(text, 40),
+        vec!["Intro\n\n```rust\nfn a() {}\n\nfn b() {}\n```", "Outro"]
+    );
+}
+
+#[test]
+fn test_long_code_blocks_are_closed_and_reopened() {
+    let text = "```py\nline_1()\nline_2()\nline_3()\n```";
+    for chunk in chunk(text, 28) {
+        assert!(chunk.chars().count() <= 28);
+        assert!(chunk.starts_with("```py\n") && chunk.ends_with("\n```"));
+    }
+    assert_eq!(
+        chunk(text, 28),
+        vec!["```py\nline_1()\nline_2()\n```", "```py\nline_3()\n```"]
+    );
+}
+
+#[test]
+fn test_telegram_chunks_are_rendered_separately() {
+    let text = format!("**{}**\n\n*{}*", "a".repeat(10), "b".repeat(10));
+    assert_eq!(
+        render_chunks(&text, OutputFormat::TelegramHtml, 80, 16),
+        vec![
+            format!("<b>{}</b>", "a".repeat(10)),
+            format!("<i>{}</i>", "b".repeat(10))
+        ]
+    );
+}
+
+async fn app(formatting: FormattingConfig) -> Router {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..3 {
+        mock_llm.add_response(create_mock_chat_response("Here it is: **bold**"));
+    }
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Arc::new(formatting),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state)
+}
+
+async fn post(app: &Router, body: Value) -> Value {
+    let request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+async fn output(app: &Router, body: Value) -> String {
+    post(app, body).await["output"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_replies_are_formatted_for_the_integration() {
+    let app = app(FormattingConfig {
+        integrations: HashMap::from([("telegram".to_string(), OutputFormat::TelegramHtml)]),
+        plain_width: 80,
+        max_chunk_sizes: HashMap::new(),
+    })
+    .await;
+
+    let telegram = json!({"session_id": "a", "input": "Hi", "integration": "telegram"});
+    assert_eq!(output(&app, telegram).await, "Here it is: <b>bold</b>");
+    let slack = json!({"session_id": "b", "input": "Hi", "integration": "slack"});
+    assert_eq!(output(&app, slack).await, "Here it is: **bold**");
+    // An explicit format wins over the integration's
+    let plain = json!({
+        "session_id": "c",
+        "input": "Hi",
+        "integration": "telegram",
+        "format": "plain"
+    });
+    assert_eq!(output(&app, plain).await, "Here it is: bold");
+}
+
+#[tokio::test]
+async fn test_replies_are_chunked_for_the_integration() {
+    let app = app(FormattingConfig {
+        integrations: HashMap::new(),
+        plain_width: 80,
+        max_chunk_sizes: HashMap::from([("discord".to_string(), 12)]),
+    })
+    .await;
+
+    let discord = json!({"session_id": "a", "input": "Hi", "integration": "discord"});
+    let body = post(&app, discord).await;
+    assert_eq!(body["output"], "Here it is: **bold**");
+    assert_eq!(body["chunks"], json!(["Here it is:", "**bold**"]));
+    // Without a limit, only the output is returned
+    let body = post(&app, json!({"session_id": "b", "input": "Hi"})).await;
+    assert!(body.get("chunks").is_none());
+    let body = post(
+        &app,
+        json!({"session_id": "c", "input": "Hi", "max_chunk_size": 100}),
+    )
+    .await;
+    assert_eq!(body["chunks"], json!(["Here it is: **bold**"]));
+}
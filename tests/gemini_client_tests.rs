@@ -0,0 +1,225 @@
+use jarvis_rust::{
+    config::{EmptyResponseConfig, LlmConfig},
+    llm::{
+        ChatCompletionRequest, ChatMessage, Function, FunctionCall, GeminiClient, LlmClient, Tool,
+        ToolCall, create_llm_client,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, header, method, path},
+};
+
+fn config(base_url: String) -> LlmConfig {
+    LlmConfig {
+        provider: "gemini".to_string(),
+        base_url,
+        api_key: "gemini-key".to_string(),
+        model: "gemini-2.0-flash".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    }
+}
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+fn weather_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_weather".to_string(),
+            description: "Gets weather".to_string(),
+            parameters: json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "type": "object",
+                "properties": {"city": {"type": "string"}},
+                "required": ["city"],
+                "additionalProperties": false
+            }),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_gemini_translates_messages_and_tools() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-2.0-flash:generateContent"))
+        .and(header("x-goog-api-key", "gemini-key"))
+        .and(body_json(json!({
+            "systemInstruction": {"parts": [{"text": "Be brief."}]},
+            "contents": [
+                {"role": "user", "parts": [{"text": "Weather in Paris and Rome?"}]},
+                {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Paris"}}},
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Rome"}}}
+                ]},
+                {"role": "user", "parts": [
+                    {"functionResponse": {"name": "get_weather", "response": {"temp": 21}}},
+                    {"functionResponse": {"name": "get_weather", "response": {"result": "Sunny"}}}
+                ]}
+            ],
+            "tools": [{"functionDeclarations": [{
+                "name": "get_weather",
+                "description": "Gets weather",
+                "parameters": {
+                    "type": "object",
+                    "properties": {"city": {"type": "string"}},
+                    "required": ["city"]
+                }
+            }]}],
+            "generationConfig": {"temperature": 0.2, "maxOutputTokens": 100}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [{"text": "Paris 21°C, Rome sunny."}]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 40,
+                "candidatesTokenCount": 8,
+                "totalTokenCount": 48
+            },
+            "modelVersion": "gemini-2.0-flash-001",
+            "responseId": "resp-1"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let call = |id: &str, city: &str| ToolCall {
+        id: id.to_string(),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: json!({"city": city}).to_string(),
+        },
+    };
+    let mut assistant = message("assistant", "");
+    assistant.tool_calls = Some(vec![call("call_1", "Paris"), call("call_2", "Rome")]);
+    let mut paris = message("tool", r#"{"temp": 21}"#);
+    paris.tool_call_id = Some("call_1".to_string());
+    let mut rome = message("tool", "Sunny");
+    rome.tool_call_id = Some("call_2".to_string());
+
+    let client = GeminiClient::new(config(server.uri()));
+    let response = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: String::new(),
+            messages: vec![
+                message("system", "Be brief."),
+                message("user", "Weather in Paris and Rome?"),
+                assistant,
+                paris,
+                rome,
+            ],
+            tools: vec![weather_tool()],
+            max_tokens: Some(100),
+            temperature: Some(0.2),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(response.id, "resp-1");
+    assert_eq!(response.model, "gemini-2.0-flash-001");
+    assert_eq!(
+        response.choices[0].message.content,
+        "Paris 21°C, Rome sunny."
+    );
+    assert_eq!(response.choices[0].finish_reason.as_deref(), Some("Stop"));
+    assert_eq!(response.usage.unwrap().total_tokens, 48);
+}
+
+#[tokio::test]
+async fn test_gemini_function_calls_become_tool_calls() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1beta/models/gemini-1.5-pro:generateContent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "get_weather", "args": {"city": "Oslo"}}}
+                ]},
+                "finishReason": "STOP"
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = GeminiClient::new(config(server.uri()));
+    let response = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: "gemini-1.5-pro".to_string(),
+            messages: vec![message("user", "Weather in Oslo?")],
+            tools: vec![weather_tool()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let choice = &response.choices[0];
+    assert_eq!(choice.finish_reason.as_deref(), Some("ToolCalls"));
+    let calls = choice.message.tool_calls.as_ref().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert!(!calls[0].id.is_empty());
+    assert_eq!(calls[0].function.name, "get_weather");
+    assert_eq!(calls[0].function.arguments, r#"{"city":"Oslo"}"#);
+}
+
+#[tokio::test]
+async fn test_gemini_errors_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_string("API key not valid"))
+        .mount(&server)
+        .await;
+
+    let client = GeminiClient::new(config(server.uri()));
+    let error = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: String::new(),
+            messages: vec![message("user", "Hi")],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("API key not valid"));
+}
+
+#[test]
+fn test_provider_selects_the_client() {
+    assert!(create_llm_client(config(String::new())).is_ok());
+    let mut unknown = config(String::new());
+    unknown.provider = "claude".to_string();
+    assert!(create_llm_client(unknown).is_err());
+}
@@ -1,5 +1,5 @@
 use chrono::Utc;
-use jarvis_rust::history::{HistoryStorage, Message};
+use jarvis_rust::history::{DEFAULT_COMPRESSION_THRESHOLD, HistoryStorage, Message, StorageStatus};
 use pretty_assertions::assert_eq;
 use std::sync::Arc;
 use tempfile::TempDir;
@@ -259,3 +259,344 @@ async fn test_large_content() {
     assert_eq!(messages[0].content, large_content);
     assert_eq!(messages[0].content.len(), 10000);
 }
+
+#[tokio::test]
+async fn test_large_rows_are_stored_compressed() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("history.db")
+        .to_string_lossy()
+        .to_string();
+    let storage = HistoryStorage::new(&db_path).await.unwrap();
+    let session_id = "compression-test";
+
+    let tool_output = "sensor reading: 21.5C\n".repeat(1000);
+    let metadata = serde_json::json!({
+        "tool_calls": [{"name": "sensors", "arguments": {}, "result": tool_output, "is_error": false}]
+    });
+    storage
+        .save(Message::user(
+            session_id.to_string(),
+            "Check sensors".to_string(),
+        ))
+        .await
+        .unwrap();
+    storage
+        .save(
+            Message::assistant(session_id.to_string(), "All normal".to_string())
+                .with_metadata(metadata.clone()),
+        )
+        .await
+        .unwrap();
+
+    let messages = storage.list(session_id).await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].content, "Check sensors");
+    assert_eq!(messages[1].content, "All normal");
+    assert_eq!(messages[1].metadata, Some(metadata));
+
+    let db = libsql::Builder::new_local(&db_path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    let mut rows = conn
+        .query(
+            "SELECT compressed, typeof(metadata), length(metadata) FROM messages ORDER BY id",
+            (),
+        )
+        .await
+        .unwrap();
+    let row = rows.next().await.unwrap().unwrap();
+    assert!(!row.get::<bool>(0).unwrap());
+    let row = rows.next().await.unwrap().unwrap();
+    assert!(row.get::<bool>(0).unwrap());
+    assert_eq!(row.get::<String>(1).unwrap(), "blob");
+    assert!(row.get::<i64>(2).unwrap() < 1000);
+}
+
+#[tokio::test]
+async fn test_compression_threshold_is_configurable() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("history.db")
+        .to_string_lossy()
+        .to_string();
+    let session_id = "threshold-test";
+
+    let compressing = HistoryStorage::new(&db_path)
+        .await
+        .unwrap()
+        .with_compression_threshold(10);
+    compressing
+        .save(Message::user(
+            session_id.to_string(),
+            "Turn on the lights".to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let plain = HistoryStorage::new(&db_path)
+        .await
+        .unwrap()
+        .with_compression_threshold(usize::MAX);
+    let large_content = "x".repeat(DEFAULT_COMPRESSION_THRESHOLD * 2);
+    plain
+        .save(Message::assistant(
+            session_id.to_string(),
+            large_content.clone(),
+        ))
+        .await
+        .unwrap();
+
+    // Either storage reads both kinds of rows
+    let messages = plain.list(session_id).await.unwrap();
+    assert_eq!(messages[0].content, "Turn on the lights");
+    assert_eq!(messages[1].content, large_content);
+
+    let db = libsql::Builder::new_local(&db_path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    let mut rows = conn
+        .query("SELECT compressed FROM messages ORDER BY id", ())
+        .await
+        .unwrap();
+    let mut flags = Vec::new();
+    while let Some(row) = rows.next().await.unwrap() {
+        flags.push(row.get::<bool>(0).unwrap());
+    }
+    assert_eq!(flags, vec![true, false]);
+}
+
+#[tokio::test]
+async fn test_fallback_is_bounded() {
+    let storage = HistoryStorage::new("/invalid/path/to/database.db")
+        .await
+        .unwrap()
+        .with_fallback_capacity(3);
+    let session_id = "bounded-fallback";
+    for i in 0..5 {
+        storage
+            .save(Message::user(
+                session_id.to_string(),
+                format!("Message {i}"),
+            ))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(storage.status().await, StorageStatus::Degraded);
+    assert_eq!(storage.buffered(), 3);
+    let contents: Vec<String> = storage
+        .list(session_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(contents, vec!["Message 2", "Message 3", "Message 4"]);
+}
+
+#[tokio::test]
+async fn test_recover_flushes_buffered_messages() {
+    let temp_dir = TempDir::new().unwrap();
+    let data_dir = temp_dir.path().join("data");
+    let db_path = data_dir.join("history.db").to_string_lossy().to_string();
+    let session_id = "recovery-test";
+
+    // The directory doesn't exist yet, so the database can't be opened
+    let storage = HistoryStorage::new(&db_path).await.unwrap();
+    assert_eq!(storage.status().await, StorageStatus::Degraded);
+    assert!(storage.recover().await.is_err());
+    storage
+        .save(Message::user(session_id.to_string(), "Hello".to_string()))
+        .await
+        .unwrap();
+    storage
+        .save(Message::assistant(
+            session_id.to_string(),
+            "Hi!".to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(storage.buffered(), 2);
+
+    std::fs::create_dir_all(&data_dir).unwrap();
+    assert_eq!(storage.recover().await.unwrap(), 2);
+    assert_eq!(storage.status().await, StorageStatus::Ok);
+    assert_eq!(storage.buffered(), 0);
+    storage
+        .save(Message::user(
+            session_id.to_string(),
+            "Still there?".to_string(),
+        ))
+        .await
+        .unwrap();
+
+    // Everything reached the database in order
+    let reopened = HistoryStorage::new(&db_path).await.unwrap();
+    let messages = reopened.list(session_id).await.unwrap();
+    let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(contents, vec!["Hello", "Hi!", "Still there?"]);
+    assert!(messages.iter().all(|m| m.id.is_some()));
+}
+
+#[tokio::test]
+async fn test_healthy_storage_status() {
+    let storage = HistoryStorage::new(":memory:").await.unwrap();
+    assert_eq!(storage.status().await, StorageStatus::Ok);
+    assert_eq!(storage.recover().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_update_and_delete_rows() {
+    let storage = HistoryStorage::new(":memory:")
+        .await
+        .unwrap()
+        .with_compression_threshold(64);
+    let session_id = "rows";
+    let id = storage
+        .insert(Message::assistant(
+            session_id.to_string(),
+            "The answer".to_string(),
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    storage
+        .save(Message::user(session_id.to_string(), "Thanks".to_string()))
+        .await
+        .unwrap();
+
+    // Updates keep the row's place, and may cross the compression threshold
+    let long = "The answer is 42. ".repeat(10);
+    storage
+        .update(
+            id,
+            &Message::assistant(session_id.to_string(), long.clone())
+                .with_metadata(serde_json::json!({"citations": []})),
+        )
+        .await
+        .unwrap();
+    let messages = storage.list(session_id).await.unwrap();
+    assert_eq!(messages[0].id, Some(id));
+    assert_eq!(messages[0].content, long);
+    assert_eq!(
+        messages[0].metadata,
+        Some(serde_json::json!({"citations": []}))
+    );
+    assert_eq!(messages[1].content, "Thanks");
+
+    storage.delete(id).await.unwrap();
+    let messages = storage.list(session_id).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(
+        storage
+            .update(id, &Message::assistant(session_id.to_string(), "x".into()))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_deleted_messages_are_hidden_and_restorable() {
+    let storage = HistoryStorage::new(":memory:").await.unwrap();
+    let session_id = "soft-delete";
+    let id = storage
+        .insert(Message::user(session_id.to_string(), "Oops".to_string()))
+        .await
+        .unwrap()
+        .unwrap();
+    storage
+        .save(Message::user(session_id.to_string(), "Hello".to_string()))
+        .await
+        .unwrap();
+
+    storage.delete(id).await.unwrap();
+    let messages = storage.list(session_id).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].content, "Hello");
+    let deleted = storage.list_deleted(session_id).await.unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].id, Some(id));
+    assert_eq!(deleted[0].content, "Oops");
+
+    assert!(storage.restore(id).await.unwrap());
+    assert!(!storage.restore(id).await.unwrap());
+    let messages = storage.list(session_id).await.unwrap();
+    assert_eq!(
+        messages
+            .iter()
+            .map(|m| m.content.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Oops", "Hello"]
+    );
+    assert!(storage.list_deleted(session_id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_purge_removes_only_old_tombstones() {
+    let storage = HistoryStorage::new(":memory:").await.unwrap();
+    let session_id = "purge";
+    let mut ids = Vec::new();
+    for content in ["first", "second", "kept"] {
+        ids.push(
+            storage
+                .insert(Message::user(session_id.to_string(), content.to_string()))
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+    }
+    storage.delete(ids[0]).await.unwrap();
+    storage.delete(ids[1]).await.unwrap();
+
+    // Nothing was deleted before an hour ago
+    assert_eq!(
+        storage
+            .purge(Utc::now() - chrono::Duration::hours(1))
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(storage.list_deleted(session_id).await.unwrap().len(), 2);
+
+    assert_eq!(
+        storage
+            .purge(Utc::now() + chrono::Duration::seconds(1))
+            .await
+            .unwrap(),
+        2
+    );
+    assert!(storage.list_deleted(session_id).await.unwrap().is_empty());
+    assert!(!storage.restore(ids[0]).await.unwrap());
+    assert_eq!(storage.list(session_id).await.unwrap()[0].content, "kept");
+}
+
+#[tokio::test]
+async fn test_databases_without_deleted_at_are_migrated() {
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("history.db")
+        .to_string_lossy()
+        .to_string();
+    let db = libsql::Builder::new_local(&db_path).build().await.unwrap();
+    let conn = db.connect().unwrap();
+    conn.execute(
+        "CREATE TABLE messages (id INTEGER PRIMARY KEY AUTOINCREMENT, session_id TEXT NOT NULL, role TEXT NOT NULL, content TEXT NOT NULL, created_at DATETIME NOT NULL, metadata TEXT, compressed INTEGER NOT NULL DEFAULT 0)",
+        (),
+    )
+    .await
+    .unwrap();
+    conn.execute(
+        "INSERT INTO messages (session_id, role, content, created_at) VALUES ('old', 'user', 'Hi', ?)",
+        [Utc::now().to_rfc3339()],
+    )
+    .await
+    .unwrap();
+
+    let storage = HistoryStorage::new(&db_path).await.unwrap();
+    let messages = storage.list("old").await.unwrap();
+    assert_eq!(messages.len(), 1);
+    storage.delete(messages[0].id.unwrap()).await.unwrap();
+    assert!(storage.list("old").await.unwrap().is_empty());
+}
@@ -0,0 +1,495 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    routing::{get, post, put},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::ApiKeyConfig,
+    events::{SessionEventHook, SessionEvents},
+    history::HistoryStorage,
+    server::handlers::{
+        AppState, hand_off_conversation, inference, integration_session, integration_sessions,
+        session_channels, unlink_conversation,
+    },
+    sessions::{ChannelRelay, SessionStore, integration_session_id},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn key(name: &str, admin: bool) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: Vec::new(),
+        default_model: None,
+        requests_per_minute: None,
+        admin,
+    }
+}
+
+async fn app() -> (Router, Arc<HistoryStorage>) {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..5 {
+        mock_llm.add_response(create_mock_chat_response("Hello!"));
+    }
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let events = Arc::new(SessionEvents::new(64));
+    agent.add_hook(Arc::new(SessionEventHook::new(events.clone())));
+    let sessions = Arc::new(SessionStore::new(":memory:").await.unwrap());
+    events.spawn_subscriber(
+        "channels",
+        Arc::new(ChannelRelay::new(sessions.clone()).unwrap()),
+    );
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: Some(events),
+        runs: Default::default(),
+        api_keys: Arc::new(vec![
+            key("bridge", false),
+            key("ops", true),
+            key("other", false),
+        ]),
+        usage: None,
+        timeline: None,
+        sessions: Some(sessions),
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let router = Router::new()
+        .route("/", post(inference))
+        .route(
+            "/integrations/:integration/sessions",
+            get(integration_sessions),
+        )
+        .route(
+            "/integrations/:integration/sessions/:conversation_id",
+            get(integration_session),
+        )
+        .route("/sessions/:session_id/channels", get(session_channels))
+        .route(
+            "/sessions/:session_id/channels/:integration/:conversation_id",
+            put(hand_off_conversation).delete(unlink_conversation),
+        )
+        .with_state(state);
+    (router, history)
+}
+
+async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    key: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {key}-key"))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    let response = app
+        .clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn ask(integration: &str, conversation_id: &str) -> Value {
+    json!({"integration": integration, "conversation_id": conversation_id, "input": "Hi"})
+}
+
+#[test]
+fn test_session_ids_are_namespaced() {
+    assert_eq!(
+        integration_session_id("telegram", "42").as_deref(),
+        Some("telegram:42")
+    );
+    assert_eq!(
+        integration_session_id("slack", "C1:1700000000.1").as_deref(),
+        Some("slack:C1:1700000000.1")
+    );
+    // A name holding ':' could make "a:b" + "c" collide with "a" + "b:c"
+    assert_eq!(integration_session_id("tele:gram", "42"), None);
+    assert_eq!(integration_session_id("", "42"), None);
+    assert_eq!(integration_session_id("telegram", ""), None);
+}
+
+#[tokio::test]
+async fn test_conversations_get_their_own_sessions() {
+    let (app, history) = app().await;
+    let (status, body) = send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["session_id"], json!("telegram:42"));
+    // The same chat number elsewhere is another conversation
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("discord", "42")),
+    )
+    .await;
+    assert_eq!(body["session_id"], json!("discord:42"));
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    assert_eq!(body["session_id"], json!("telegram:42"));
+
+    assert_eq!(history.list("telegram:42").await.unwrap().len(), 4);
+    assert_eq!(history.list("discord:42").await.unwrap().len(), 2);
+
+    let (status, body) = send(
+        &app,
+        Method::GET,
+        "/integrations/telegram/sessions/42",
+        "bridge",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["session_id"], json!("telegram:42"));
+    assert_eq!(body["conversation_id"], json!("42"));
+
+    let (status, _) = send(
+        &app,
+        Method::GET,
+        "/integrations/telegram/sessions/7",
+        "bridge",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_refused_requests_record_no_conversation() {
+    let (app, _) = app().await;
+    // Someone else already uses the session the conversation would get
+    let direct = json!({"session_id": "telegram:42", "input": "Hi"});
+    let (status, _) = send(&app, Method::POST, "/", "bridge", Some(direct)).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &app,
+        Method::POST,
+        "/",
+        "other",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &app,
+        Method::GET,
+        "/integrations/telegram/sessions/42",
+        "ops",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_listing_conversations_is_admin_only() {
+    let (app, _) = app().await;
+    for conversation_id in ["42", "43"] {
+        send(
+            &app,
+            Method::POST,
+            "/",
+            "bridge",
+            Some(ask("telegram", conversation_id)),
+        )
+        .await;
+    }
+
+    let (status, _) = send(
+        &app,
+        Method::GET,
+        "/integrations/telegram/sessions",
+        "bridge",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(
+        &app,
+        Method::GET,
+        "/integrations/telegram/sessions",
+        "ops",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let sessions: Vec<&Value> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|conversation| &conversation["session_id"])
+        .collect();
+    assert_eq!(sessions, vec![&json!("telegram:42"), &json!("telegram:43")]);
+}
+
+#[tokio::test]
+async fn test_conversation_id_needs_a_valid_integration() {
+    let (app, _) = app().await;
+
+    let body = json!({"conversation_id": "42", "input": "Hi"});
+    let (status, _) = send(&app, Method::POST, "/", "bridge", Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let body = json!({"integration": "my bot", "conversation_id": "42", "input": "Hi"});
+    let (status, _) = send(&app, Method::POST, "/", "bridge", Some(body)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // An explicit session_id wins
+    let body = json!({"session_id": "mine", "integration": "telegram", "conversation_id": "42", "input": "Hi"});
+    let (status, body) = send(&app, Method::POST, "/", "bridge", Some(body)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["session_id"], json!("mine"));
+}
+
+fn ask_in(session_id: &str) -> Value {
+    json!({"session_id": session_id, "input": "Hi"})
+}
+
+#[tokio::test]
+async fn test_handed_off_conversations_share_the_session() {
+    let (app, history) = app().await;
+    send(&app, Method::POST, "/", "bridge", Some(ask_in("web-1"))).await;
+
+    let (status, body) = send(
+        &app,
+        Method::PUT,
+        "/sessions/web-1/channels/telegram/42",
+        "bridge",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["session_id"], json!("web-1"));
+
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    assert_eq!(body["session_id"], json!("web-1"));
+    assert_eq!(history.list("web-1").await.unwrap().len(), 4);
+
+    let (status, body) = send(
+        &app,
+        Method::GET,
+        "/sessions/web-1/channels",
+        "bridge",
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["integration"], json!("telegram"));
+    assert_eq!(body[0]["conversation_id"], json!("42"));
+
+    let uri = "/sessions/web-1/channels/telegram/42";
+    let (status, _) = send(&app, Method::DELETE, uri, "bridge", None).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _) = send(&app, Method::DELETE, uri, "bridge", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    // Back in its own session
+    let (_, body) = send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    assert_eq!(body["session_id"], json!("telegram:42"));
+}
+
+#[tokio::test]
+async fn test_replies_are_relayed_to_the_other_channels() {
+    let (app, _) = app().await;
+    let server = MockServer::start().await;
+    for channel in ["telegram", "discord"] {
+        Mock::given(method("POST"))
+            .and(path(format!("/{channel}")))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+    }
+    for (integration, conversation_id) in [("telegram", "42"), ("discord", "7")] {
+        let uri = format!("/sessions/web-1/channels/{integration}/{conversation_id}");
+        let callback = json!({"callback_url": format!("{}/{integration}", server.uri())});
+        // The server posts to callbacks, so only admins may set them
+        let (status, _) = send(&app, Method::PUT, &uri, "bridge", Some(callback.clone())).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(&app, Method::PUT, &uri, "ops", Some(callback)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Telegram gets its reply in the response; the web UI's goes to both
+    send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    send(&app, Method::POST, "/", "bridge", Some(ask_in("web-1"))).await;
+
+    let mut received = Vec::new();
+    for _ in 0..100 {
+        received = server.received_requests().await.unwrap();
+        if received.len() >= 3 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let relayed: Vec<(String, Value)> = received
+        .iter()
+        .map(|request| {
+            (
+                request.url.path().to_string(),
+                serde_json::from_slice(&request.body).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(relayed.len(), 3);
+    assert_eq!(
+        relayed
+            .iter()
+            .filter(|(path, _)| path == "/telegram")
+            .count(),
+        1
+    );
+    assert!(relayed.contains(&(
+        "/discord".to_string(),
+        json!({
+            "session_id": "web-1",
+            "integration": "discord",
+            "conversation_id": "7",
+            "output": "Hello!"
+        })
+    )));
+}
+
+#[tokio::test]
+async fn test_hand_off_needs_every_session_involved() {
+    let (app, _) = app().await;
+    send(
+        &app,
+        Method::POST,
+        "/",
+        "bridge",
+        Some(ask("telegram", "42")),
+    )
+    .await;
+    send(&app, Method::POST, "/", "other", Some(ask_in("mine"))).await;
+
+    // Another key's conversation can't be taken over
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/sessions/mine/channels/telegram/42",
+        "other",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    // Nor handed to another key's session
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/sessions/mine/channels/telegram/42",
+        "bridge",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/sessions/mine/channels/my%20bot/42",
+        "other",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Nor taken from the session that holds it now
+    send(&app, Method::POST, "/", "bridge", Some(ask_in("web-1"))).await;
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/sessions/web-1/channels/telegram/7",
+        "bridge",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(
+        &app,
+        Method::PUT,
+        "/sessions/mine/channels/telegram/7",
+        "other",
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,165 @@
+use jarvis_rust::{
+    agent::{Agent, ProcessOptions, ResponseLanguage, RunOutcome},
+    history::HistoryStorage,
+};
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+const ENGLISH: &str = "The living room lights are now turned off and the door is locked.";
+const PORTUGUESE: &str = "As luzes da sala estão desligadas agora e a porta está trancada.";
+
+fn agent(mock_llm: MockLlmClient) -> Agent {
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+}
+
+#[test]
+fn test_parse_response_language() {
+    assert_eq!(ResponseLanguage::parse("Portuguese").name(), "Portuguese");
+    assert_eq!(ResponseLanguage::parse("portuguese").name(), "Portuguese");
+    assert_eq!(ResponseLanguage::parse("por").name(), "Portuguese");
+    assert_eq!(ResponseLanguage::parse("pt-BR").name(), "Portuguese");
+    assert_eq!(ResponseLanguage::parse("de_AT").name(), "German");
+    // Unknown languages are still passed to the model as given
+    assert_eq!(ResponseLanguage::parse(" Klingon ").name(), "Klingon");
+}
+
+#[test]
+fn test_drift_detection() {
+    let portuguese = ResponseLanguage::parse("pt-BR");
+    assert!(portuguese.drifted(ENGLISH));
+    assert!(!portuguese.drifted(PORTUGUESE));
+    // Too short to tell
+    assert!(!portuguese.drifted("OK, done."));
+    // Unknown languages are never checked
+    assert!(!ResponseLanguage::parse("Klingon").drifted(ENGLISH));
+}
+
+#[tokio::test]
+async fn test_directive_added_to_system_prompt() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(PORTUGUESE));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let mut agent = agent(mock_llm).with_response_language("pt-BR");
+    let output = agent
+        .process("session", "Turn off the lights", &history)
+        .await
+        .unwrap();
+    assert_eq!(output, PORTUGUESE);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let system = &requests[0].messages[0];
+    assert_eq!(system.role, "system");
+    assert!(
+        system.content.text().ends_with(
+            "Always reply in Portuguese, whatever language the user or tool results use."
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_drifted_reply_is_rewritten() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(ENGLISH));
+    mock_llm.add_response(create_mock_chat_response(PORTUGUESE));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let mut agent = agent(mock_llm).with_response_language("Portuguese");
+    let output = agent
+        .process("session", "Turn off the lights", &history)
+        .await
+        .unwrap();
+    assert_eq!(output, PORTUGUESE);
+
+    let correction = {
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        requests[1].clone()
+    };
+    assert!(correction.tools.is_empty());
+    let last = correction.messages.len() - 1;
+    assert_eq!(correction.messages[last - 1].role, "assistant");
+    assert_eq!(correction.messages[last - 1].content, ENGLISH);
+    assert!(
+        correction.messages[last]
+            .content
+            .text()
+            .starts_with("Your last reply was not in Portuguese.")
+    );
+
+    // Only the corrected reply is kept
+    let messages = history.list("session").await.unwrap();
+    assert_eq!(messages.last().unwrap().content, PORTUGUESE);
+}
+
+#[tokio::test]
+async fn test_request_language_overrides_default() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(PORTUGUESE));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let mut agent = agent(mock_llm).with_response_language("English");
+    let reply = agent
+        .process_with_options(
+            "session",
+            "Turn off the lights",
+            &history,
+            ProcessOptions {
+                response_language: Some("pt".to_string()),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    let RunOutcome::Reply(reply) = reply else {
+        panic!("run paused without caller tools");
+    };
+    // Matches the requested language, so no re-ask
+    assert_eq!(reply.output, PORTUGUESE);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(
+        requests[0].messages[0]
+            .content
+            .text()
+            .contains("Always reply in Portuguese")
+    );
+    assert!(!requests[0].messages[0].content.text().contains("English"));
+}
+
+#[tokio::test]
+async fn test_no_language_leaves_reply_alone() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response(ENGLISH));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let mut agent = agent(mock_llm);
+    let output = agent
+        .process("session", "Turn off the lights", &history)
+        .await
+        .unwrap();
+    assert_eq!(output, ENGLISH);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(
+        requests[0]
+            .messages
+            .iter()
+            .all(|message| !message.content.text().contains("Always reply in"))
+    );
+}
@@ -1,13 +1,18 @@
 use async_openai::types::ChatCompletionRequestMessage;
 use jarvis_rust::{
-    config::LlmConfig,
+    config::{AzureOpenAiConfig, EmptyResponseConfig, LlmConfig},
     llm::{
         ChatCompletionChoice, ChatCompletionRequest, ChatCompletionResponse, ChatMessage, Function,
-        FunctionCall, OpenAiClient, Tool, ToolCall, Usage,
+        FunctionCall, LlmClient, OpenAiClient, Tool, ToolCall, Usage,
     },
 };
 use pretty_assertions::assert_eq;
 use serde_json::json;
+use std::collections::HashMap;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path, query_param},
+};
 
 fn create_test_config() -> LlmConfig {
     LlmConfig {
@@ -16,6 +21,25 @@ fn create_test_config() -> LlmConfig {
         api_key: "test-api-key".to_string(),
         model: "gpt-4".to_string(),
         system_prompt: Some("Test prompt".to_string()),
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
     }
 }
 
@@ -39,7 +63,7 @@ fn test_openai_client_with_custom_base_url() {
 fn test_chat_message_to_openai_system() {
     let msg = ChatMessage {
         role: "system".to_string(),
-        content: "You are a helpful assistant".to_string(),
+        content: "You are a helpful assistant".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -58,7 +82,7 @@ fn test_chat_message_to_openai_system() {
 fn test_chat_message_to_openai_user() {
     let msg = ChatMessage {
         role: "user".to_string(),
-        content: "Hello, how are you?".to_string(),
+        content: "Hello, how are you?".into(),
         tool_calls: None,
         tool_call_id: None,
         name: Some("test_user".to_string()),
@@ -72,7 +96,7 @@ fn test_chat_message_to_openai_user() {
 fn test_chat_message_to_openai_assistant() {
     let msg = ChatMessage {
         role: "assistant".to_string(),
-        content: "I'm doing well, thank you!".to_string(),
+        content: "I'm doing well, thank you!".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -98,7 +122,7 @@ fn test_chat_message_to_openai_assistant_with_tool_calls() {
 
     let msg = ChatMessage {
         role: "assistant".to_string(),
-        content: "".to_string(),
+        content: "".into(),
         tool_calls: Some(tool_calls),
         tool_call_id: None,
         name: None,
@@ -115,7 +139,7 @@ fn test_chat_message_to_openai_assistant_with_tool_calls() {
 fn test_chat_message_to_openai_tool() {
     let msg = ChatMessage {
         role: "tool".to_string(),
-        content: "The weather in London is sunny".to_string(),
+        content: "The weather in London is sunny".into(),
         tool_calls: None,
         tool_call_id: Some("call_123".to_string()),
         name: None,
@@ -129,7 +153,7 @@ fn test_chat_message_to_openai_tool() {
 fn test_chat_message_invalid_role() {
     let msg = ChatMessage {
         role: "invalid_role".to_string(),
-        content: "This should fail".to_string(),
+        content: "This should fail".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -179,14 +203,14 @@ fn test_chat_completion_request_creation() {
     let messages = vec![
         ChatMessage {
             role: "system".to_string(),
-            content: "You are helpful".to_string(),
+            content: "You are helpful".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
         },
         ChatMessage {
             role: "user".to_string(),
-            content: "Hello".to_string(),
+            content: "Hello".into(),
             tool_calls: None,
             tool_call_id: None,
             name: None,
@@ -208,6 +232,7 @@ fn test_chat_completion_request_creation() {
         tools,
         max_tokens: Some(150),
         temperature: Some(0.7),
+        ..Default::default()
     };
 
     assert_eq!(request.model, "gpt-4");
@@ -249,6 +274,7 @@ fn test_usage_serialization() {
         prompt_tokens: 50,
         completion_tokens: 25,
         total_tokens: 75,
+        reasoning_tokens: None,
     };
 
     let serialized = serde_json::to_string(&usage).unwrap();
@@ -306,7 +332,7 @@ fn test_tool_serialization() {
 fn test_chat_message_cloning() {
     let original = ChatMessage {
         role: "user".to_string(),
-        content: "Test message".to_string(),
+        content: "Test message".into(),
         tool_calls: Some(vec![ToolCall {
             id: "test_id".to_string(),
             call_type: "function".to_string(),
@@ -331,7 +357,7 @@ fn test_chat_message_cloning() {
 fn test_choice_creation() {
     let message = ChatMessage {
         role: "assistant".to_string(),
-        content: "Test response".to_string(),
+        content: "Test response".into(),
         tool_calls: None,
         tool_call_id: None,
         name: None,
@@ -359,7 +385,7 @@ fn test_chat_completion_response_creation() {
             index: 0,
             message: ChatMessage {
                 role: "assistant".to_string(),
-                content: "Hello!".to_string(),
+                content: "Hello!".into(),
                 tool_calls: None,
                 tool_call_id: None,
                 name: None,
@@ -370,7 +396,10 @@ fn test_chat_completion_response_creation() {
             prompt_tokens: 10,
             completion_tokens: 5,
             total_tokens: 15,
+            reasoning_tokens: None,
         }),
+        provider: None,
+        context_fallback: None,
     };
 
     assert_eq!(response.id, "chatcmpl-123");
@@ -379,3 +408,208 @@ fn test_chat_completion_response_creation() {
     assert!(response.usage.is_some());
     assert_eq!(response.usage.unwrap().total_tokens, 15);
 }
+
+fn sse_body(chunks: &[serde_json::Value]) -> String {
+    let mut body = String::new();
+    for chunk in chunks {
+        body.push_str(&format!("data: {chunk}\n\n"));
+    }
+    body.push_str("data: [DONE]\n\n");
+    body
+}
+
+fn stream_chunk(delta: serde_json::Value, finish_reason: Option<&str>) -> serde_json::Value {
+    json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion.chunk",
+        "created": 1700000000,
+        "model": "gpt-4",
+        "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}]
+    })
+}
+
+async fn streaming_client(chunks: &[serde_json::Value]) -> (MockServer, OpenAiClient) {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_raw(sse_body(chunks), "text/event-stream"),
+        )
+        .mount(&server)
+        .await;
+    let mut config = create_test_config();
+    config.base_url = server.uri();
+    (server, OpenAiClient::new(config))
+}
+
+fn streaming_request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "gpt-4".to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "What is the answer?".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_streaming_sends_content_deltas() {
+    let (_server, client) = streaming_client(&[
+        stream_chunk(json!({"role": "assistant", "content": ""}), None),
+        stream_chunk(json!({"content": "The answer"}), None),
+        stream_chunk(json!({"content": " is 42"}), None),
+        stream_chunk(json!({}), Some("stop")),
+    ])
+    .await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let response = client
+        .create_chat_completion_streaming(streaming_request(), tx)
+        .await
+        .unwrap();
+
+    let mut deltas = Vec::new();
+    while let Ok(delta) = rx.try_recv() {
+        deltas.push(delta);
+    }
+    assert_eq!(deltas, vec!["The answer", " is 42"]);
+    assert_eq!(response.id, "chatcmpl-1");
+    assert_eq!(response.choices.len(), 1);
+    assert_eq!(response.choices[0].message.role, "assistant");
+    assert_eq!(response.choices[0].message.content, "The answer is 42");
+    assert!(response.choices[0].message.tool_calls.is_none());
+    assert_eq!(response.choices[0].finish_reason.as_deref(), Some("Stop"));
+}
+
+#[tokio::test]
+async fn test_streaming_assembles_tool_calls() {
+    let (_server, client) = streaming_client(&[
+        stream_chunk(
+            json!({"role": "assistant", "tool_calls": [{
+                "index": 0, "id": "call_1", "type": "function",
+                "function": {"name": "get_weather", "arguments": ""}
+            }]}),
+            None,
+        ),
+        stream_chunk(
+            json!({"tool_calls": [{"index": 0, "function": {"arguments": "{\"city\":"}}]}),
+            None,
+        ),
+        stream_chunk(
+            json!({"tool_calls": [{"index": 0, "function": {"arguments": "\"Paris\"}"}}]}),
+            None,
+        ),
+        stream_chunk(json!({}), Some("tool_calls")),
+    ])
+    .await;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let response = client
+        .create_chat_completion_streaming(streaming_request(), tx)
+        .await
+        .unwrap();
+
+    assert!(rx.try_recv().is_err());
+    let tool_calls = response.choices[0].message.tool_calls.clone().unwrap();
+    assert_eq!(tool_calls.len(), 1);
+    assert_eq!(tool_calls[0].id, "call_1");
+    assert_eq!(tool_calls[0].call_type, "function");
+    assert_eq!(tool_calls[0].function.name, "get_weather");
+    assert_eq!(tool_calls[0].function.arguments, r#"{"city":"Paris"}"#);
+}
+
+#[tokio::test]
+async fn test_request_model_overrides_configured_model() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_partial_json(json!({"model": "gpt-4o-mini"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-2",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi!"},
+                "finish_reason": "stop"
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let mut config = create_test_config();
+    config.base_url = server.uri();
+    let client = OpenAiClient::new(config);
+
+    let mut request = streaming_request();
+    request.model = "gpt-4o-mini".to_string();
+    let response = client.create_chat_completion(request).await.unwrap();
+    assert_eq!(response.model, "gpt-4o-mini");
+    assert_eq!(response.choices[0].message.content, "Hi!");
+}
+
+async fn azure_server(deployment: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path(format!(
+            "/openai/deployments/{deployment}/chat/completions"
+        )))
+        .and(query_param("api-version", "2024-06-01"))
+        .and(header("api-key", "azure-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-azure",
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hi from Azure!"},
+                "finish_reason": "stop"
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+fn azure_config(base_url: String) -> LlmConfig {
+    let mut config = create_test_config();
+    config.provider = "azure_openai".to_string();
+    config.base_url = base_url;
+    config.api_key = "azure-key".to_string();
+    config.model = "gpt-4o".to_string();
+    config.azure = AzureOpenAiConfig {
+        api_version: "2024-06-01".to_string(),
+        deployments: HashMap::from([("gpt-4o".to_string(), "gpt4o-prod".to_string())]),
+    };
+    config
+}
+
+#[tokio::test]
+async fn test_azure_requests_go_to_the_model_deployment() {
+    let server = azure_server("gpt4o-prod").await;
+    let client = OpenAiClient::new(azure_config(server.uri()));
+
+    // No model in the request falls back to the configured gpt-4o
+    let mut request = streaming_request();
+    request.model = String::new();
+    let response = client.create_chat_completion(request).await.unwrap();
+    assert_eq!(response.choices[0].message.content, "Hi from Azure!");
+}
+
+#[tokio::test]
+async fn test_azure_unlisted_models_use_the_deployment_of_their_name() {
+    let server = azure_server("gpt-4o-mini").await;
+    let client = OpenAiClient::new(azure_config(server.uri()));
+
+    let mut request = streaming_request();
+    request.model = "gpt-4o-mini".to_string();
+    let response = client.create_chat_completion(request).await.unwrap();
+    assert_eq!(response.choices[0].message.content, "Hi from Azure!");
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use async_trait::async_trait;
+use common::{MockLlmClient, create_mock_chat_response};
+use jarvis_rust::{
+    Error, Result,
+    config::{EmptyResponseConfig, LlmConfig, LlmFallbackConfig},
+    llm::{
+        ChatCompletionRequest, ChatCompletionResponse, ChatMessage, FallbackLlmClient,
+        FallbackProvider, LlmClient, create_llm_client,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+/// Fails every call with an HTTP status
+struct FailingLlmClient {
+    status: u16,
+    calls: Arc<Mutex<usize>>,
+}
+
+#[async_trait]
+impl LlmClient for FailingLlmClient {
+    async fn create_chat_completion(
+        &self,
+        _request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        *self.calls.lock().unwrap() += 1;
+        Err(Error::llm_status(self.status, "provider unavailable"))
+    }
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: "gpt-4o".to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hello".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+fn fallback(name: &str, model: &str, client: MockLlmClient) -> FallbackProvider {
+    FallbackProvider {
+        name: name.to_string(),
+        model: model.to_string(),
+        client: Box::new(client),
+    }
+}
+
+#[tokio::test]
+async fn test_fallback_answers_when_primary_fails_with_server_error() {
+    let calls = Arc::new(Mutex::new(0));
+    let primary = FailingLlmClient {
+        status: 503,
+        calls: calls.clone(),
+    };
+    let backup = MockLlmClient::new();
+    backup.add_response(create_mock_chat_response("Hi from Ollama"));
+    let requests = backup.requests.clone();
+
+    let client = FallbackLlmClient::new(
+        Box::new(primary),
+        vec![fallback("ollama:llama3.1", "llama3.1", backup)],
+    );
+    let response = client.create_chat_completion(request()).await.unwrap();
+
+    assert_eq!(*calls.lock().unwrap(), 1);
+    assert_eq!(response.choices[0].message.content, "Hi from Ollama");
+    assert_eq!(response.provider.as_deref(), Some("ollama:llama3.1"));
+    assert_eq!(requests.lock().unwrap()[0].model, "llama3.1");
+}
+
+#[tokio::test]
+async fn test_primary_answer_has_no_provider() {
+    let primary = MockLlmClient::new();
+    primary.add_response(create_mock_chat_response("Hi"));
+    let backup = MockLlmClient::new();
+    let requests = backup.requests.clone();
+
+    let client = FallbackLlmClient::new(
+        Box::new(primary),
+        vec![fallback("ollama:llama3.1", "llama3.1", backup)],
+    );
+    let response = client.create_chat_completion(request()).await.unwrap();
+
+    assert_eq!(response.provider, None);
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_client_errors_do_not_fall_back() {
+    let primary = FailingLlmClient {
+        status: 400,
+        calls: Arc::new(Mutex::new(0)),
+    };
+    let backup = MockLlmClient::new();
+    backup.add_response(create_mock_chat_response("Hi"));
+    let requests = backup.requests.clone();
+
+    let client = FallbackLlmClient::new(
+        Box::new(primary),
+        vec![fallback("ollama:llama3.1", "llama3.1", backup)],
+    );
+    let error = client.create_chat_completion(request()).await.unwrap_err();
+
+    assert_eq!(error.to_string(), "LLM error: provider unavailable");
+    assert!(requests.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_fallbacks_are_tried_in_order() {
+    let first = FailingLlmClient {
+        status: 429,
+        calls: Arc::new(Mutex::new(0)),
+    };
+    let second = FailingLlmClient {
+        status: 500,
+        calls: Arc::new(Mutex::new(0)),
+    };
+    let third = MockLlmClient::new();
+    third.add_response(create_mock_chat_response("Third time lucky"));
+
+    let client = FallbackLlmClient::new(
+        Box::new(first),
+        vec![
+            FallbackProvider {
+                name: "gemini:gemini-2.0-flash".to_string(),
+                model: "gemini-2.0-flash".to_string(),
+                client: Box::new(second),
+            },
+            fallback("ollama:llama3.1", "llama3.1", third),
+        ],
+    );
+    let response = client.create_chat_completion(request()).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Third time lucky");
+    assert_eq!(response.provider.as_deref(), Some("ollama:llama3.1"));
+}
+
+#[tokio::test]
+async fn test_create_llm_client_falls_back_to_configured_provider() {
+    let gemini = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .mount(&gemini)
+        .await;
+    let ollama = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3.1",
+            "message": {"role": "assistant", "content": "Hi from Ollama"},
+            "done": true,
+            "done_reason": "stop"
+        })))
+        .mount(&ollama)
+        .await;
+
+    let config = LlmConfig {
+        provider: "gemini".to_string(),
+        base_url: gemini.uri(),
+        api_key: "gemini-key".to_string(),
+        model: "gemini-2.0-flash".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: vec![LlmFallbackConfig {
+            provider: "ollama".to_string(),
+            base_url: ollama.uri(),
+            api_key: String::new(),
+            model: "llama3.1".to_string(),
+            ollama: Default::default(),
+            azure: Default::default(),
+            openrouter: Default::default(),
+        }],
+    };
+    let client = create_llm_client(config).unwrap();
+    let response = client.create_chat_completion(request()).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Hi from Ollama");
+    assert_eq!(response.provider.as_deref(), Some("ollama:llama3.1"));
+}
+
+#[test]
+fn test_unknown_fallback_provider_is_rejected() {
+    let config: LlmConfig = serde_yaml::from_str(
+        r#"
+provider: openai
+base_url: ""
+api_key: key
+model: gpt-4o
+fallbacks:
+  - provider: bard
+    model: bard-1
+"#,
+    )
+    .unwrap();
+    let error = create_llm_client(config).err().unwrap();
+    assert!(error.to_string().contains("Unknown llm.provider 'bard'"));
+}
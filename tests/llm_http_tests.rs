@@ -0,0 +1,113 @@
+use jarvis_rust::{
+    config::LlmConfig,
+    llm::{ChatCompletionRequest, ChatMessage, LlmClient, OpenAiClient, create_llm_client},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn config(yaml: &str) -> LlmConfig {
+    serde_yaml::from_str(&format!(
+        r#"
+provider: "openai"
+api_key: "key"
+model: "gpt-4o"
+retry:
+  max_attempts: 1
+{yaml}
+"#
+    ))
+    .unwrap()
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hi".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+fn completion() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello!"},
+            "finish_reason": "stop"
+        }]
+    }))
+}
+
+#[test]
+fn test_http_settings_have_defaults() {
+    let config = config("base_url: \"http://localhost:1\"");
+    assert_eq!(config.request_timeout, 300);
+    assert_eq!(config.connect_timeout, 10);
+    assert_eq!(config.proxy, None);
+}
+
+#[tokio::test]
+async fn test_hung_provider_times_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(completion().set_delay(Duration::from_secs(30)))
+        .mount(&server)
+        .await;
+    let client = OpenAiClient::new(config(&format!(
+        "base_url: \"{}\"\nrequest_timeout: 1",
+        server.uri()
+    )));
+
+    let started = Instant::now();
+    let result = client.create_chat_completion(request()).await;
+
+    assert!(result.is_err());
+    assert!(started.elapsed() < Duration::from_secs(10));
+}
+
+#[tokio::test]
+async fn test_requests_go_through_the_proxy() {
+    let proxy = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(completion())
+        .expect(1)
+        .mount(&proxy)
+        .await;
+    let client = create_llm_client(config(&format!(
+        "base_url: \"http://llm.invalid/v1\"\nproxy: \"{}\"",
+        proxy.uri()
+    )))
+    .unwrap();
+
+    let response = client.create_chat_completion(request()).await.unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Hello!");
+}
+
+#[test]
+fn test_invalid_proxy_is_a_config_error() {
+    let error = create_llm_client(config(
+        "base_url: \"http://localhost:1\"\nproxy: \"http://user:secret@[bad\"",
+    ))
+    .err()
+    .unwrap();
+
+    let message = error.to_string();
+    assert!(message.contains("llm.proxy"), "{message}");
+    assert!(!message.contains("secret"), "{message}");
+}
@@ -0,0 +1,176 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    config::LlmConfig,
+    history::HistoryStorage,
+    llm::{ChatCompletionRequest, ChatMessage, LlmClient, create_llm_client},
+    mcp::{McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, path::Path, sync::Arc};
+use tempfile::TempDir;
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn config(dir: &Path, base_url: &str, record: bool) -> LlmConfig {
+    let mut config: LlmConfig = serde_yaml::from_str(&format!(
+        r#"
+provider: "replay"
+base_url: "{base_url}"
+api_key: "key"
+model: "gpt-4o"
+retry:
+  max_attempts: 1
+"#
+    ))
+    .unwrap();
+    config.replay.dir = dir.display().to_string();
+    config.replay.record = record.then(|| "openai".to_string());
+    config
+}
+
+fn request(content: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: content.into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+fn completion(message: Value) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o",
+        "choices": [{"index": 0, "message": message, "finish_reason": "stop"}]
+    }))
+}
+
+fn recordings(dir: &Path) -> usize {
+    std::fs::read_dir(dir).map_or(0, |entries| entries.count())
+}
+
+#[tokio::test]
+async fn test_completions_are_recorded_then_replayed() {
+    let dir = TempDir::new().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(completion(
+            json!({"role": "assistant", "content": "Hello!"}),
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = create_llm_client(config(dir.path(), &server.uri(), true)).unwrap();
+
+    let recorded = client.create_chat_completion(request("Hi")).await.unwrap();
+    // Whitespace around message text doesn't make another request
+    let replayed = client
+        .create_chat_completion(request("  Hi\n"))
+        .await
+        .unwrap();
+
+    assert_eq!(recorded.choices[0].message.content, "Hello!");
+    assert_eq!(replayed.choices[0].message.content, "Hello!");
+    assert_eq!(recordings(dir.path()), 1);
+}
+
+#[tokio::test]
+async fn test_missing_recording_fails_without_recorder() {
+    let dir = TempDir::new().unwrap();
+    let client = create_llm_client(config(dir.path(), "http://localhost:1", false)).unwrap();
+
+    let error = client
+        .create_chat_completion(request("Hi"))
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("No recording"), "{error}");
+    assert_eq!(recordings(dir.path()), 0);
+}
+
+#[tokio::test]
+async fn test_agent_flow_replays_without_provider() {
+    let dir = TempDir::new().unwrap();
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(completion(json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "clock", "arguments": "{}"}
+            }]
+        })))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(completion(
+            json!({"role": "assistant", "content": "It is 8 o'clock."}),
+        ))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+
+    let run = |client: Box<dyn LlmClient>| async move {
+        let mut agent = Agent::new_for_testing(client, HashMap::new(), HashMap::new(), Vec::new());
+        agent.register_native_tool(Arc::new(ClockTool));
+        let history = HistoryStorage::new(":memory:").await.unwrap();
+        agent.process("s1", "What time is it?", &history).await
+    };
+
+    let recorded = run(create_llm_client(config(dir.path(), &server.uri(), true)).unwrap())
+        .await
+        .unwrap();
+    drop(server);
+    let replayed = run(create_llm_client(config(dir.path(), "http://localhost:1", false)).unwrap())
+        .await
+        .unwrap();
+
+    assert_eq!(recorded, "It is 8 o'clock.");
+    assert_eq!(replayed, recorded);
+    assert_eq!(recordings(dir.path()), 2);
+}
+
+#[test]
+fn test_replay_cannot_record_itself() {
+    let dir = TempDir::new().unwrap();
+    let mut config = config(dir.path(), "http://localhost:1", true);
+    config.replay.record = Some("replay".to_string());
+
+    assert!(create_llm_client(config).is_err());
+}
@@ -0,0 +1,246 @@
+use jarvis_rust::{
+    Error,
+    config::{EmptyResponseConfig, LlmConfig, LlmPacingConfig, LlmRetryConfig},
+    llm::{
+        ChatCompletionRequest, ChatMessage, LlmClient, OpenAiClient, PacingPolicy, RetryPolicy,
+        retry_after,
+    },
+};
+use pretty_assertions::assert_eq;
+use reqwest::header::{HeaderMap, HeaderValue};
+use serde_json::json;
+use std::time::{Duration, Instant};
+use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+fn retry(max_attempts: u32) -> LlmRetryConfig {
+    LlmRetryConfig {
+        max_attempts,
+        initial_backoff_ms: 10,
+        max_backoff_ms: 1_000,
+        jitter: false,
+    }
+}
+
+fn client(server: &MockServer, retry: LlmRetryConfig) -> OpenAiClient {
+    OpenAiClient::new(LlmConfig {
+        provider: "openai".to_string(),
+        base_url: server.uri(),
+        api_key: "test-api-key".to_string(),
+        model: "gpt-4o".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry,
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    })
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hi".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+fn completion() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hello!"},
+            "finish_reason": "stop"
+        }]
+    }))
+}
+
+fn api_error(status: u16, kind: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_json(json!({
+        "error": {"message": format!("{kind} happened"), "type": kind, "param": null, "code": null}
+    }))
+}
+
+#[tokio::test]
+async fn test_rate_limit_is_retried_after_the_requested_wait() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(api_error(429, "rate_limit_exceeded").insert_header("retry-after-ms", "200"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(completion())
+        .mount(&server)
+        .await;
+
+    let started = Instant::now();
+    let response = client(&server, retry(3))
+        .create_chat_completion(request())
+        .await
+        .unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Hello!");
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert_eq!(server.received_requests().await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_server_errors_fail_once_attempts_run_out() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503).set_body_string("overloaded"))
+        .mount(&server)
+        .await;
+
+    let error = client(&server, retry(3))
+        .create_chat_completion(request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, Error::LlmStatus { status: 503, .. }));
+    assert_eq!(server.received_requests().await.unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_rejected_requests_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(api_error(400, "invalid_request_error"))
+        .mount(&server)
+        .await;
+
+    let error = client(&server, retry(3))
+        .create_chat_completion(request())
+        .await
+        .unwrap_err();
+
+    assert!(!error.is_retryable());
+    assert!(error.to_string().contains("invalid_request_error happened"));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_retry_after_beyond_the_longest_backoff_fails_right_away() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(api_error(429, "rate_limit_exceeded").insert_header("retry-after", "3600"))
+        .mount(&server)
+        .await;
+
+    let error = client(&server, retry(3))
+        .create_chat_completion(request())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.retry_after(), Some(Duration::from_secs(3600)));
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}
+
+#[test]
+fn test_backoff_doubles_up_to_the_longest() {
+    let policy = RetryPolicy::new(&LlmRetryConfig {
+        max_attempts: 6,
+        initial_backoff_ms: 300,
+        max_backoff_ms: 1_000,
+        jitter: false,
+    });
+    let delays: Vec<Option<Duration>> = (1..=6).map(|n| policy.delay(n, None)).collect();
+    assert_eq!(
+        delays,
+        vec![
+            Some(Duration::from_millis(300)),
+            Some(Duration::from_millis(600)),
+            Some(Duration::from_millis(1_000)),
+            Some(Duration::from_millis(1_000)),
+            Some(Duration::from_millis(1_000)),
+            None,
+        ]
+    );
+    assert_eq!(
+        policy.delay(1, Some(Duration::from_millis(50))),
+        Some(Duration::from_millis(50))
+    );
+}
+
+#[test]
+fn test_jitter_waits_between_half_and_all_of_the_backoff() {
+    let policy = RetryPolicy::new(&LlmRetryConfig {
+        jitter: true,
+        ..retry(2)
+    });
+    for _ in 0..100 {
+        let delay = policy.delay(1, None).unwrap();
+        assert!(delay >= Duration::from_millis(5) && delay <= Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn test_pacing_doubles_between_calls() {
+    assert!(PacingPolicy::new(&LlmPacingConfig::default()).is_none());
+    let policy = PacingPolicy::new(&LlmPacingConfig {
+        initial_delay_ms: 300,
+        max_delay_ms: 1_000,
+        jitter: false,
+    })
+    .unwrap();
+    let delays: Vec<Duration> = (0..=4).map(|calls| policy.delay(calls)).collect();
+    assert_eq!(
+        delays,
+        vec![
+            Duration::ZERO,
+            Duration::from_millis(300),
+            Duration::from_millis(600),
+            Duration::from_millis(1_000),
+            Duration::from_millis(1_000),
+        ]
+    );
+}
+
+#[test]
+fn test_retry_after_header_formats() {
+    let headers = |name: &'static str, value: &str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    };
+    assert_eq!(
+        retry_after(&headers("retry-after", "7")),
+        Some(Duration::from_secs(7))
+    );
+    assert_eq!(
+        retry_after(&headers("retry-after-ms", "1500")),
+        Some(Duration::from_millis(1500))
+    );
+    assert_eq!(
+        retry_after(&headers("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")),
+        Some(Duration::ZERO)
+    );
+    let later = (chrono::Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+    let wait = retry_after(&headers("retry-after", &later)).unwrap();
+    assert!(wait > Duration::from_secs(100) && wait <= Duration::from_secs(120));
+    assert_eq!(retry_after(&HeaderMap::new()), None);
+}
@@ -0,0 +1,307 @@
+use jarvis_rust::{
+    agent::Agent,
+    config::{EmptyResponseConfig, LlmConfig, McpClientType, McpServerConfig},
+    history::HistoryStorage,
+    mcp::{
+        FixtureMcpClient, McpClient, McpClientCapabilities, McpContent, McpFixture,
+        McpInitializeRequest, McpToolCallRequest, McpToolCallResponse, create_mcp_client,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, io::Write};
+use tempfile::NamedTempFile;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_string_contains, method, path},
+};
+
+const WEATHER_FIXTURE: &str = r#"
+tools:
+  - name: get_weather
+    description: Gets the weather
+    input_schema:
+      type: object
+      properties:
+        location: {type: string}
+    calls:
+      - arguments: {location: London}
+        text: Rainy
+      - arguments: {location: Atlantis}
+        text: Unknown location
+        is_error: true
+      - text: Sunny
+  - name: list_alerts
+    description: Weather alerts
+prompts:
+  - name: forecast
+    description: Summarize the forecast
+    messages:
+      - role: user
+        content: {type: text, text: "Summarize the forecast"}
+"#;
+
+fn fixture_file(contents: &str, suffix: &str) -> NamedTempFile {
+    let mut file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+}
+
+fn fixture_server(file: &NamedTempFile) -> McpServerConfig {
+    McpServerConfig {
+        name: "weather".to_string(),
+        url: None,
+        client_type: McpClientType::Fixture,
+        headers: HashMap::new(),
+        command: None,
+        args: Vec::new(),
+        env: HashMap::new(),
+        fixture: Some(file.path().to_string_lossy().into_owned()),
+        auth: None,
+    }
+}
+
+fn call(name: &str, arguments: Value) -> McpToolCallRequest {
+    McpToolCallRequest {
+        name: name.to_string(),
+        arguments: serde_json::from_value(arguments).unwrap(),
+    }
+}
+
+fn text_of(response: &McpToolCallResponse) -> String {
+    match &response.content[..] {
+        [McpContent::Text { text }] => text.clone(),
+        other => panic!("expected a single text result, got {other:?}"),
+    }
+}
+
+async fn initialized(file: &NamedTempFile) -> Box<dyn McpClient> {
+    let mut client = create_mcp_client(fixture_server(file)).await.unwrap();
+    client
+        .initialize(McpInitializeRequest {
+            capabilities: McpClientCapabilities {
+                roots: None,
+                sampling: None,
+            },
+        })
+        .await
+        .unwrap();
+    client
+}
+
+#[test]
+fn test_fixture_server_config_parse() {
+    let config: McpServerConfig = serde_yaml::from_str(
+        r#"
+name: "weather"
+type: "fixture"
+fixture: "tests/weather.yaml"
+"#,
+    )
+    .unwrap();
+    assert!(matches!(config.client_type, McpClientType::Fixture));
+    assert_eq!(config.fixture.as_deref(), Some("tests/weather.yaml"));
+}
+
+#[test]
+fn test_example_fixture_loads() {
+    let fixture = McpFixture::from_file("tests/fixtures/weather.yaml").unwrap();
+    assert_eq!(fixture.tools.len(), 1);
+    assert_eq!(fixture.tools[0].tool.name, "get_weather");
+    assert_eq!(fixture.tools[0].calls.len(), 3);
+}
+
+#[tokio::test]
+async fn test_fixture_lists_tools_and_prompts() {
+    let file = fixture_file(WEATHER_FIXTURE, ".yaml");
+    let client = initialized(&file).await;
+
+    let tools = client.list_tools().await.unwrap();
+    assert_eq!(
+        tools.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(),
+        vec!["get_weather", "list_alerts"]
+    );
+    assert_eq!(
+        tools[0].input_schema["properties"]["location"]["type"],
+        "string"
+    );
+
+    let prompts = client.list_prompts().await.unwrap();
+    assert_eq!(prompts[0].name, "forecast");
+}
+
+#[tokio::test]
+async fn test_fixture_answers_with_first_matching_result() {
+    let file = fixture_file(WEATHER_FIXTURE, ".yaml");
+    let client = initialized(&file).await;
+
+    let london = client
+        .call_tool(call(
+            "get_weather",
+            json!({"location": "London", "units": "metric"}),
+        ))
+        .await
+        .unwrap();
+    assert_eq!(text_of(&london), "Rainy");
+    assert!(!london.is_error);
+
+    let atlantis = client
+        .call_tool(call("get_weather", json!({"location": "Atlantis"})))
+        .await
+        .unwrap();
+    assert_eq!(text_of(&atlantis), "Unknown location");
+    assert!(atlantis.is_error);
+
+    let paris = client
+        .call_tool(call("get_weather", json!({"location": "Paris"})))
+        .await
+        .unwrap();
+    assert_eq!(text_of(&paris), "Sunny");
+}
+
+#[tokio::test]
+async fn test_fixture_rejects_unknown_tools_and_unmatched_calls() {
+    let file = fixture_file(WEATHER_FIXTURE, ".yaml");
+    let client = initialized(&file).await;
+
+    let error = client
+        .call_tool(call("get_time", json!({})))
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("has no tool 'get_time'"));
+
+    let error = client
+        .call_tool(call("list_alerts", json!({"region": "EU"})))
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains(r#"has no result for tool 'list_alerts' with arguments {"region":"EU"}"#)
+    );
+}
+
+#[tokio::test]
+async fn test_fixture_loads_json_files() {
+    let fixture = json!({
+        "tools": [{
+            "name": "get_weather",
+            "description": "Gets the weather",
+            "calls": [{"content": [{"type": "text", "text": "Sunny"}]}]
+        }]
+    });
+    let file = fixture_file(&fixture.to_string(), ".json");
+    let client = initialized(&file).await;
+
+    let response = client
+        .call_tool(call("get_weather", json!({})))
+        .await
+        .unwrap();
+    assert_eq!(text_of(&response), "Sunny");
+}
+
+#[test]
+fn test_fixture_errors_name_the_problem() {
+    let mut server = fixture_server(&fixture_file(WEATHER_FIXTURE, ".yaml"));
+    server.fixture = None;
+    assert!(
+        FixtureMcpClient::from_config(&server)
+            .err()
+            .unwrap()
+            .to_string()
+            .contains("requires 'fixture' field")
+    );
+
+    assert!(McpFixture::from_file("does/not/exist.yaml").is_err());
+    let file = fixture_file("tools: 42", ".yaml");
+    assert!(
+        McpFixture::from_file(file.path())
+            .unwrap_err()
+            .to_string()
+            .contains("Invalid fixture")
+    );
+}
+
+fn completion(message: Value, finish_reason: &str) -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({
+        "id": "chatcmpl-1",
+        "object": "chat.completion",
+        "created": 1700000000,
+        "model": "gpt-4o-mini",
+        "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}]
+    }))
+}
+
+#[tokio::test]
+async fn test_end_to_end_run_against_fixture_server() {
+    let llm = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(completion(
+            json!({
+                "role": "assistant",
+                "content": null,
+                "tool_calls": [{
+                    "id": "call_1",
+                    "type": "function",
+                    "function": {"name": "get_weather", "arguments": "{\"location\": \"London\"}"}
+                }]
+            }),
+            "tool_calls",
+        ))
+        .up_to_n_times(1)
+        .mount(&llm)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(body_string_contains("Rainy"))
+        .respond_with(completion(
+            json!({"role": "assistant", "content": "Bring an umbrella."}),
+            "stop",
+        ))
+        .mount(&llm)
+        .await;
+
+    let file = fixture_file(WEATHER_FIXTURE, ".yaml");
+    let llm_config = LlmConfig {
+        provider: "openai".to_string(),
+        base_url: llm.uri(),
+        api_key: "test-api-key".to_string(),
+        model: "gpt-4o-mini".to_string(),
+        system_prompt: None,
+        max_continuations: 0,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: false,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    };
+    let mut agent = Agent::new(llm_config, vec![fixture_server(&file)])
+        .await
+        .unwrap();
+    assert_eq!(
+        agent.get_tool_to_client_map().get("get_weather"),
+        Some(&"weather".to_string())
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process("s1", "Do I need an umbrella in London?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "Bring an umbrella.");
+    assert_eq!(llm.received_requests().await.unwrap().len(), 2);
+}
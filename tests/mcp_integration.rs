@@ -8,7 +8,6 @@ use jarvis_rust::{
 use pretty_assertions::assert_eq;
 use serde_json::Value;
 use std::collections::HashMap;
-use tokio;
 
 mod common;
 use common::{
@@ -373,6 +372,8 @@ async fn test_mcp_server_config_types() {
         client_type: McpClientType::Sse,
         url: Some("http://localhost:3000/sse".to_string()),
         command: None,
+        fixture: None,
+        auth: None,
         args: vec![],
         env: HashMap::new(),
         headers: HashMap::new(),
@@ -389,6 +390,8 @@ async fn test_mcp_server_config_types() {
         client_type: McpClientType::Stdio,
         url: None,
         command: Some("./server".to_string()),
+        fixture: None,
+        auth: None,
         args: vec!["--verbose".to_string()],
         env: {
             let mut env = HashMap::new();
@@ -411,6 +414,8 @@ async fn test_mcp_server_config_types() {
         client_type: McpClientType::StreamableHttp,
         url: Some("http://api.example.com".to_string()),
         command: None,
+        fixture: None,
+        auth: None,
         args: vec![],
         env: HashMap::new(),
         headers: {
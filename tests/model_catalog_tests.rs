@@ -0,0 +1,317 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{ApiKeyConfig, AzureOpenAiConfig, EmptyResponseConfig, LlmConfig, LlmFallbackConfig},
+    history::HistoryStorage,
+    llm::{ModelCapabilities, ModelCatalog},
+    server::handlers::{AppState, list_models},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, header as header_matcher, method, path},
+};
+
+mod common;
+use common::MockLlmClient;
+
+fn config(provider: &str, base_url: String, model: &str) -> LlmConfig {
+    LlmConfig {
+        provider: provider.to_string(),
+        base_url,
+        api_key: "key".to_string(),
+        model: model.to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    }
+}
+
+async fn openai_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "object": "list",
+            "data": [
+                {"id": "gpt-4o", "object": "model", "created": 1, "owned_by": "openai"},
+                {"id": "text-embedding-3-small", "object": "model", "created": 1, "owned_by": "openai"},
+                {"id": "gpt-3.5-turbo", "object": "model", "created": 1, "owned_by": "openai"}
+            ]
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+async fn ollama_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/tags"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [{"name": "llama3.1:latest"}, {"name": "llava:7b"}]
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/show"))
+        .and(body_json(json!({"model": "llama3.1:latest"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "capabilities": ["completion", "tools"],
+            "model_info": {"llama.context_length": 131072}
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/show"))
+        .and(body_json(json!({"model": "llava:7b"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "capabilities": ["completion", "vision"]
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+async fn test_catalog_lists_primary_and_fallback_models() {
+    let openai = openai_server().await;
+    let ollama = ollama_server().await;
+    let mut config = config("openai", openai.uri(), "gpt-4o");
+    config.fallbacks.push(LlmFallbackConfig {
+        provider: "ollama".to_string(),
+        base_url: ollama.uri(),
+        api_key: String::new(),
+        model: "llama3.1:latest".to_string(),
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+    });
+
+    let listing = ModelCatalog::from_config(&config).unwrap().list().await;
+
+    assert!(listing.errors.is_empty());
+    let ids: Vec<(&str, &str)> = listing
+        .models
+        .iter()
+        .map(|m| (m.provider.as_str(), m.id.as_str()))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            ("openai", "gpt-3.5-turbo"),
+            ("openai", "gpt-4o"),
+            ("openai", "text-embedding-3-small"),
+            ("ollama", "llama3.1:latest"),
+            ("ollama", "llava:7b"),
+        ]
+    );
+    let llama = &listing.models[3];
+    assert_eq!(
+        llama.capabilities,
+        ModelCapabilities {
+            chat: true,
+            tools: true,
+            vision: false,
+            embeddings: false,
+        }
+    );
+    assert_eq!(llama.context_window, Some(131072));
+    assert!(listing.models[4].capabilities.vision);
+    assert!(listing.models[2].capabilities.embeddings);
+    assert!(!listing.models[2].capabilities.chat);
+}
+
+#[tokio::test]
+async fn test_gemini_models_carry_limits_and_methods() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1beta/models"))
+        .and(header_matcher("x-goog-api-key", "key"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "models": [
+                {
+                    "name": "models/gemini-2.0-flash",
+                    "inputTokenLimit": 1048576,
+                    "supportedGenerationMethods": ["generateContent", "countTokens"]
+                },
+                {
+                    "name": "models/text-embedding-004",
+                    "inputTokenLimit": 2048,
+                    "supportedGenerationMethods": ["embedContent"]
+                }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let listing = ModelCatalog::from_config(&config("gemini", server.uri(), "gemini-2.0-flash"))
+        .unwrap()
+        .list()
+        .await;
+
+    assert_eq!(listing.models[0].id, "gemini-2.0-flash");
+    assert_eq!(listing.models[0].context_window, Some(1048576));
+    assert!(listing.models[0].capabilities.tools);
+    assert_eq!(listing.models[1].id, "text-embedding-004");
+    assert!(listing.models[1].capabilities.embeddings);
+    assert!(!listing.models[1].capabilities.chat);
+}
+
+#[tokio::test]
+async fn test_azure_lists_configured_deployments() {
+    let mut config = config(
+        "azure_openai",
+        "https://example.invalid".to_string(),
+        "gpt-4o",
+    );
+    config.azure = AzureOpenAiConfig {
+        deployments: HashMap::from([("gpt-4o-mini".to_string(), "mini-prod".to_string())]),
+        ..Default::default()
+    };
+
+    let listing = ModelCatalog::from_config(&config).unwrap().list().await;
+
+    let ids: Vec<&str> = listing.models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(ids, vec!["gpt-4o", "gpt-4o-mini"]);
+    assert!(listing.models.iter().all(|m| m.provider == "azure_openai"));
+}
+
+#[tokio::test]
+async fn test_failing_provider_is_reported_next_to_the_others() {
+    let openai = openai_server().await;
+    let ollama = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500).set_body_string("boom"))
+        .mount(&ollama)
+        .await;
+    let mut config = config("openai", openai.uri(), "gpt-4o");
+    config.fallbacks.push(LlmFallbackConfig {
+        provider: "ollama".to_string(),
+        base_url: ollama.uri(),
+        api_key: String::new(),
+        model: "llama3.1".to_string(),
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+    });
+
+    let listing = ModelCatalog::from_config(&config).unwrap().list().await;
+
+    assert_eq!(listing.models.len(), 3);
+    assert_eq!(listing.errors.len(), 1);
+    assert_eq!(listing.errors[0].provider, "ollama");
+    assert!(listing.errors[0].error.contains("500"));
+}
+
+#[test]
+fn test_openai_capabilities_by_name() {
+    let gpt4o = ModelCapabilities::of_openai_model("openai/gpt-4o-mini");
+    assert!(gpt4o.chat && gpt4o.tools && gpt4o.vision);
+    let gpt35 = ModelCapabilities::of_openai_model("gpt-3.5-turbo");
+    assert!(gpt35.chat && gpt35.tools && !gpt35.vision);
+    let whisper = ModelCapabilities::of_openai_model("whisper-1");
+    assert_eq!(whisper, ModelCapabilities::default());
+    let tts = ModelCapabilities::of_openai_model("gpt-4o-mini-tts");
+    assert!(!tts.chat);
+}
+
+#[tokio::test]
+async fn test_models_endpoint_shows_what_the_key_may_use() {
+    let openai = openai_server().await;
+    let catalog = ModelCatalog::from_config(&config("openai", openai.uri(), "gpt-4o")).unwrap();
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(vec![ApiKeyConfig {
+            name: "cheap".to_string(),
+            key: "cheap-key".to_string(),
+            models: vec!["gpt-3.5-turbo".to_string()],
+            default_model: Some("gpt-3.5-turbo".to_string()),
+            requests_per_minute: None,
+            admin: false,
+        }]),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: Some(Arc::new(catalog)),
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/models", get(list_models))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/models")
+                .header(header::AUTHORIZATION, "Bearer cheap-key")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body,
+        json!({
+            "default_model": "gpt-3.5-turbo",
+            "models": [{
+                "id": "gpt-3.5-turbo",
+                "provider": "openai",
+                "capabilities": {"chat": true, "tools": true, "vision": false, "embeddings": false}
+            }]
+        })
+    );
+}
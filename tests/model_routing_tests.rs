@@ -0,0 +1,177 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::{Agent, ProcessOptions, RunOutcome},
+    config::{LlmConfig, ModelRoutingConfig},
+    history::HistoryStorage,
+    mcp::{McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn routing() -> ModelRoutingConfig {
+    ModelRoutingConfig {
+        tool_model: Some("gpt-4o-mini".to_string()),
+        answer_model: Some("gpt-4o".to_string()),
+    }
+}
+
+fn routed_agent(mock_llm: MockLlmClient, with_tools: bool) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_model_routing(routing());
+    if with_tools {
+        agent.register_native_tool(Arc::new(ClockTool));
+    }
+    agent
+}
+
+async fn reply(agent: &mut Agent, options: ProcessOptions) -> String {
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    match agent
+        .process_with_options("s1", "What time is it?", &history, options)
+        .await
+        .unwrap()
+    {
+        RunOutcome::Reply(reply) => reply.output,
+        RunOutcome::Paused(_) => panic!("run paused"),
+    }
+}
+
+#[test]
+fn test_routing_config() {
+    let config: LlmConfig = serde_yaml::from_str(
+        r#"
+base_url: "http://localhost:1"
+api_key: "key"
+model: "gpt-4o"
+routing:
+  tool_model: "gpt-4o-mini"
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.routing,
+        ModelRoutingConfig {
+            tool_model: Some("gpt-4o-mini".to_string()),
+            answer_model: None,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_tool_turns_use_the_tool_model() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("it's 8"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = routed_agent(mock_llm, true);
+
+    let output = reply(&mut agent, ProcessOptions::default()).await;
+
+    assert_eq!(output, "It is 8 o'clock.");
+    let requests = requests.lock().unwrap();
+    let models: Vec<&str> = requests.iter().map(|r| r.model.as_str()).collect();
+    assert_eq!(models, vec!["gpt-4o-mini", "gpt-4o-mini", "gpt-4o"]);
+    // The answer model sees the tool results, not the dropped reply
+    assert_eq!(requests[2].messages.len(), requests[1].messages.len());
+    assert_eq!(requests[2].messages.last().unwrap().role, "tool");
+    assert_eq!(requests[2].tools.len(), 1);
+}
+
+#[tokio::test]
+async fn test_answer_model_may_still_call_tools() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("No idea."));
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("it's 8"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = routed_agent(mock_llm, true);
+
+    let output = reply(&mut agent, ProcessOptions::default()).await;
+
+    assert_eq!(output, "It is 8 o'clock.");
+    let models: Vec<String> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.model.clone())
+        .collect();
+    assert_eq!(
+        models,
+        vec!["gpt-4o-mini", "gpt-4o", "gpt-4o-mini", "gpt-4o"]
+    );
+}
+
+#[tokio::test]
+async fn test_runs_without_tools_go_straight_to_the_answer_model() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = routed_agent(mock_llm, false);
+
+    let output = reply(&mut agent, ProcessOptions::default()).await;
+
+    assert_eq!(output, "Hello!");
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].model, "gpt-4o");
+}
+
+#[tokio::test]
+async fn test_requested_model_overrides_routing() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = routed_agent(mock_llm, true);
+
+    let options = ProcessOptions {
+        model: Some("o3".to_string()),
+        ..Default::default()
+    };
+    let output = reply(&mut agent, options).await;
+
+    assert_eq!(output, "It is 8 o'clock.");
+    let models: Vec<String> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|r| r.model.clone())
+        .collect();
+    assert_eq!(models, vec!["o3", "o3"]);
+}
@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Error, Result,
+    agent::Agent,
+    config::{ModerationAction, ModerationConfig, ModerationProviderKind},
+    history::HistoryStorage,
+    moderation::{
+        KeywordModeration, ModerationProvider, ModerationVerdict, Moderator, OpenAiModeration,
+        create_moderation_provider,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path},
+};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn keywords() -> HashMap<String, Vec<String>> {
+    HashMap::from([
+        ("violence".to_string(), vec!["kill".to_string()]),
+        ("weapons".to_string(), vec!["pipe bomb".to_string()]),
+    ])
+}
+
+fn keyword_config() -> ModerationConfig {
+    ModerationConfig {
+        provider: ModerationProviderKind::Keywords,
+        keywords: keywords(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_keyword_classifier() {
+    let classifier = KeywordModeration::new(keywords());
+    assert_eq!(
+        classifier
+            .classify("How do I KILL a process?")
+            .await
+            .unwrap(),
+        vec!["violence"]
+    );
+    assert_eq!(
+        classifier
+            .classify("Building a pipe bomb to kill")
+            .await
+            .unwrap(),
+        vec!["violence", "weapons"]
+    );
+    // Only whole words match
+    assert!(
+        classifier
+            .classify("A skilled pipe fitter")
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_create_moderation_provider() {
+    assert!(
+        create_moderation_provider(&ModerationConfig::default())
+            .unwrap()
+            .is_none()
+    );
+    assert!(
+        create_moderation_provider(&keyword_config())
+            .unwrap()
+            .is_some()
+    );
+
+    let openai = ModerationConfig {
+        provider: ModerationProviderKind::Openai,
+        ..Default::default()
+    };
+    assert!(matches!(
+        create_moderation_provider(&openai),
+        Err(Error::Config(_))
+    ));
+    let keywords = ModerationConfig {
+        provider: ModerationProviderKind::Keywords,
+        ..Default::default()
+    };
+    assert!(matches!(
+        create_moderation_provider(&keywords),
+        Err(Error::Config(_))
+    ));
+}
+
+#[test]
+fn test_moderation_config_parse() {
+    let config: ModerationConfig = serde_yaml::from_str(
+        r#"
+provider: openai
+api_key: "sk-test"
+actions:
+  harassment: warn
+  violence: allow
+check_output: false
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.provider, ModerationProviderKind::Openai);
+    assert_eq!(config.actions["harassment"], ModerationAction::Warn);
+    assert_eq!(config.default_action, ModerationAction::Block);
+    assert!(config.check_input);
+    assert!(!config.check_output);
+    assert_eq!(config.model, "omni-moderation-latest");
+    assert_eq!(config.blocked_reply, "Sorry, I can't help with that.");
+}
+
+#[tokio::test]
+async fn test_openai_moderation() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/moderations"))
+        .and(header("authorization", "Bearer sk-test"))
+        .and(body_partial_json(json!({
+            "model": "omni-moderation-latest",
+            "input": "something nasty",
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "modr-1",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": {"harassment": true, "violence": false, "hate": true},
+                "category_scores": {"harassment": 0.9, "violence": 0.01, "hate": 0.7},
+            }],
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = OpenAiModeration::new(
+        "sk-test".to_string(),
+        Some(format!("{}/v1/", server.uri())),
+        "omni-moderation-latest".to_string(),
+    );
+    assert_eq!(
+        provider.classify("something nasty").await.unwrap(),
+        vec!["harassment", "hate"]
+    );
+}
+
+/// Flags every text for the given categories, or fails
+struct FixedProvider(Option<Vec<String>>);
+
+#[async_trait]
+impl ModerationProvider for FixedProvider {
+    async fn classify(&self, _text: &str) -> Result<Vec<String>> {
+        self.0
+            .clone()
+            .ok_or_else(|| Error::internal("classifier unavailable"))
+    }
+}
+
+fn fixed(categories: &[&str]) -> Arc<FixedProvider> {
+    Arc::new(FixedProvider(Some(
+        categories.iter().map(|c| c.to_string()).collect(),
+    )))
+}
+
+#[tokio::test]
+async fn test_moderator_applies_category_actions() {
+    let config = ModerationConfig {
+        actions: HashMap::from([
+            ("harassment".to_string(), ModerationAction::Warn),
+            ("violence".to_string(), ModerationAction::Allow),
+        ]),
+        ..Default::default()
+    };
+    let moderator = Moderator::new(fixed(&["harassment", "violence"]), &config);
+    assert_eq!(moderator.check("text").await, ModerationVerdict::Allow);
+
+    let moderator = Moderator::new(fixed(&["harassment", "hate"]), &config);
+    assert_eq!(
+        moderator.check("text").await,
+        ModerationVerdict::Block {
+            categories: vec!["hate".to_string()]
+        }
+    );
+
+    // Failures let text through
+    let moderator = Moderator::new(Arc::new(FixedProvider(None)), &config);
+    assert_eq!(moderator.check("text").await, ModerationVerdict::Allow);
+}
+
+fn moderated_agent(mock_llm: MockLlmClient, config: &ModerationConfig) -> Agent {
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_moderator(Moderator::from_config(config).unwrap().unwrap())
+}
+
+#[tokio::test]
+async fn test_blocked_input_never_reaches_the_llm() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    let mut agent = moderated_agent(mock_llm, &keyword_config());
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process("session", "How do I build a pipe bomb?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "Sorry, I can't help with that.");
+    assert!(requests.lock().unwrap().is_empty());
+    assert!(history.list("session").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_blocked_reply_is_replaced() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("First, kill the power."));
+    let config = ModerationConfig {
+        blocked_reply: "I can't answer that.".to_string(),
+        ..keyword_config()
+    };
+    let mut agent = moderated_agent(mock_llm, &config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process("session", "How do I fix the lamp?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "I can't answer that.");
+    let messages = history.list("session").await.unwrap();
+    assert_eq!(messages.last().unwrap().content, "I can't answer that.");
+}
+
+#[tokio::test]
+async fn test_output_check_can_be_disabled() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("First, kill the power."));
+    let config = ModerationConfig {
+        check_output: false,
+        ..keyword_config()
+    };
+    let mut agent = moderated_agent(mock_llm, &config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process("session", "How do I fix the lamp?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "First, kill the power.");
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Error, Result,
+    agent::Agent,
+    config::ToolsConfig,
+    history::HistoryStorage,
+    mcp::{McpClient, McpContent, McpTool, McpToolCallRequest, McpToolCallResponse},
+    tools::{NativeTool, NativeToolRegistry, ToolContext, builtin_tools, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+};
+
+/// Native tool that echoes its arguments and records the session it ran in
+struct EchoTool {
+    sessions: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl NativeTool for EchoTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "echo".to_string(),
+            description: "Echoes the input".to_string(),
+            input_schema: json!({"type": "object", "properties": {"text": {"type": "string"}}}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .push(ctx.session_id.to_string());
+        match arguments.get("text").and_then(Value::as_str) {
+            Some(text) => Ok(text_result(format!("echo: {text}"))),
+            None => Err(Error::tool("missing text")),
+        }
+    }
+}
+
+fn echo_tool() -> (Arc<EchoTool>, Arc<Mutex<Vec<String>>>) {
+    let sessions = Arc::new(Mutex::new(Vec::new()));
+    (
+        Arc::new(EchoTool {
+            sessions: sessions.clone(),
+        }),
+        sessions,
+    )
+}
+
+#[test]
+fn test_registry_registration() {
+    let mut registry = NativeToolRegistry::new();
+    assert!(registry.is_empty());
+
+    let (tool, _) = echo_tool();
+    registry.register(tool.clone());
+    registry.register(tool);
+
+    assert_eq!(registry.len(), 1);
+    assert!(registry.contains("echo"));
+    assert!(registry.get("missing").is_none());
+}
+
+#[test]
+fn test_builtin_tools_respect_config() {
+    let names = |config: &ToolsConfig| -> Vec<String> {
+        builtin_tools(config)
+            .iter()
+            .map(|t| t.definition().name)
+            .collect()
+    };
+
+    let defaults = names(&ToolsConfig::default());
+    assert!(defaults.contains(&"convert_units".to_string()));
+    assert!(defaults.contains(&"format_number".to_string()));
+    assert!(defaults.contains(&"calculate".to_string()));
+    assert!(defaults.contains(&"next_occurrence".to_string()));
+
+    let mut disabled = ToolsConfig::default();
+    disabled.units.enabled = false;
+    disabled.calculator.enabled = false;
+    assert!(!names(&disabled).contains(&"convert_units".to_string()));
+    assert!(!names(&disabled).contains(&"calculate".to_string()));
+}
+
+#[tokio::test]
+async fn test_agent_routes_calls_to_native_tools() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("echo", r#"{"text": "hi"}"#));
+    mock_llm.add_response(create_mock_chat_response("done"));
+    let requests = mock_llm.requests.clone();
+
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let (tool, sessions) = echo_tool();
+    agent.register_native_tool(tool);
+    assert_eq!(agent.get_available_tools().len(), 1);
+
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("native", "say hi", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].tools[0].function.name, "echo");
+    let tool_message = requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .unwrap();
+    assert_eq!(tool_message.content, "echo: hi");
+    assert_eq!(*sessions.lock().unwrap(), vec!["native".to_string()]);
+}
+
+#[tokio::test]
+async fn test_native_tool_errors_become_error_results() {
+    let mut agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let (tool, _) = echo_tool();
+    agent.register_native_tool(tool);
+
+    let response = agent
+        .execute_mcp_tool_for_testing(&McpToolCallRequest {
+            name: "echo".to_string(),
+            arguments: HashMap::new(),
+        })
+        .await;
+
+    assert!(response.is_error);
+    match &response.content[0] {
+        McpContent::Text { text } => assert!(text.contains("missing text")),
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_native_tool_shadows_mcp_tool() {
+    let mock_mcp = MockMcpClient::new();
+    let mcp_calls = mock_mcp.calls.clone();
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("server".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("echo".to_string(), "server".to_string());
+
+    let mut agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        mcp_clients,
+        tool_to_client_map,
+        Vec::new(),
+    );
+    let (tool, sessions) = echo_tool();
+    agent.register_native_tool(tool);
+
+    let mut arguments = HashMap::new();
+    arguments.insert("text".to_string(), json!("x"));
+    agent
+        .execute_mcp_tool_for_testing(&McpToolCallRequest {
+            name: "echo".to_string(),
+            arguments,
+        })
+        .await;
+
+    assert_eq!(sessions.lock().unwrap().len(), 1);
+    assert!(mcp_calls.lock().unwrap().is_empty());
+}
@@ -0,0 +1,242 @@
+#![cfg(feature = "notifications")]
+
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    config::{NotificationProvider, NotificationsConfig, ScheduleConfig},
+    events::{SessionEventKind, SessionEvents},
+    history::HistoryStorage,
+    notifications::{
+        GotifySink, Notification, NotificationSink, NtfySink, PushoverSink, RunFailureNotifier,
+        create_notification_sink,
+    },
+    scheduler::{ScheduledJob, Scheduler},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+use tokio::sync::Mutex;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, header, method, path},
+};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn notification() -> Notification {
+    Notification::new("Morning news", "Three new items")
+}
+
+#[tokio::test]
+async fn test_ntfy_sink() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/"))
+        .and(header("authorization", "Bearer tk_secret"))
+        .and(body_json(json!({
+            "topic": "jarvis",
+            "title": "Morning news",
+            "message": "Three new items",
+            "priority": 4
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sink = NtfySink::new(
+        Some(format!("{}/", server.uri())),
+        "jarvis".to_string(),
+        Some("tk_secret".to_string()),
+        Some(4),
+    );
+    sink.send(&notification()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pushover_sink() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/1/messages.json"))
+        .and(body_json(json!({
+            "token": "app-token",
+            "user": "user-key",
+            "title": "Morning news",
+            "message": "Three new items"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"status": 1})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let sink = PushoverSink::new(
+        "app-token".to_string(),
+        "user-key".to_string(),
+        Some(server.uri()),
+        None,
+    );
+    sink.send(&notification()).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_pushover_sink_splits_long_messages() {
+    let server = MockServer::start().await;
+    let first = "a".repeat(1000);
+    let second = "b".repeat(500);
+    for (title, message) in [("Digest (1/2)", &first), ("Digest (2/2)", &second)] {
+        Mock::given(method("POST"))
+            .and(path("/1/messages.json"))
+            .and(body_json(json!({
+                "token": "app-token",
+                "user": "user-key",
+                "title": title,
+                "message": message
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"status": 1})))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let sink = PushoverSink::new(
+        "app-token".to_string(),
+        "user-key".to_string(),
+        Some(server.uri()),
+        None,
+    );
+    let message = format!("{first}\n\n{second}");
+    sink.send(&Notification::new("Digest", message))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_gotify_sink_reports_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/message"))
+        .and(header("X-Gotify-Key", "app-token"))
+        .respond_with(ResponseTemplate::new(401))
+        .mount(&server)
+        .await;
+
+    let sink = GotifySink::new(server.uri(), "app-token".to_string(), Some(5));
+    assert!(sink.send(&notification()).await.is_err());
+}
+
+#[test]
+fn test_create_notification_sink() {
+    assert!(
+        create_notification_sink(&NotificationsConfig::default())
+            .unwrap()
+            .is_none()
+    );
+
+    let ntfy = NotificationsConfig {
+        provider: NotificationProvider::Ntfy,
+        topic: Some("jarvis".to_string()),
+        ..Default::default()
+    };
+    assert!(create_notification_sink(&ntfy).unwrap().is_some());
+
+    let missing_topic = NotificationsConfig {
+        provider: NotificationProvider::Ntfy,
+        ..Default::default()
+    };
+    assert!(create_notification_sink(&missing_topic).is_err());
+
+    let missing_user = NotificationsConfig {
+        provider: NotificationProvider::Pushover,
+        token: Some("app-token".to_string()),
+        ..Default::default()
+    };
+    assert!(create_notification_sink(&missing_user).is_err());
+
+    let missing_url = NotificationsConfig {
+        provider: NotificationProvider::Gotify,
+        token: Some("app-token".to_string()),
+        ..Default::default()
+    };
+    assert!(create_notification_sink(&missing_url).is_err());
+}
+
+#[derive(Default)]
+struct RecordingSink {
+    sent: StdMutex<Vec<Notification>>,
+}
+
+#[async_trait]
+impl NotificationSink for RecordingSink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        self.sent.lock().unwrap().push(notification.clone());
+        Ok(())
+    }
+}
+
+fn job(notify: bool) -> ScheduledJob {
+    ScheduledJob::from_config(&ScheduleConfig {
+        name: "news".to_string(),
+        prompt: "What's new?".to_string(),
+        every: Some("1h".to_string()),
+        at: None,
+        session_id: None,
+        notify,
+    })
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_scheduler_notifies_job_results() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Nothing new today."));
+    mock_llm.add_response(create_mock_chat_response("Still nothing."));
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let sink = Arc::new(RecordingSink::default());
+    let scheduler = Scheduler::new(Arc::new(Mutex::new(agent)), history, chrono_tz::UTC)
+        .with_notifier(sink.clone());
+
+    scheduler.run_and_notify(&job(true)).await.unwrap();
+    scheduler.run_and_notify(&job(false)).await.unwrap();
+
+    assert_eq!(
+        *sink.sent.lock().unwrap(),
+        vec![Notification::new("news", "Nothing new today.")]
+    );
+}
+
+#[tokio::test]
+async fn test_failed_runs_are_notified() {
+    let sink = Arc::new(RecordingSink::default());
+    let events = SessionEvents::new(16);
+    let subscriber = events.spawn_subscriber(
+        "run_failures",
+        Arc::new(RunFailureNotifier::new(sink.clone())),
+    );
+
+    events.publish("s1", SessionEventKind::RunCompleted);
+    events.publish(
+        "s2",
+        SessionEventKind::RunFailed {
+            error: "LLM unavailable".to_string(),
+        },
+    );
+    drop(events);
+    subscriber.await.unwrap();
+
+    assert_eq!(
+        *sink.sent.lock().unwrap(),
+        vec![Notification::new(
+            "Run failed in session s2",
+            "LLM unavailable"
+        )]
+    );
+}
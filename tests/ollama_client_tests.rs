@@ -0,0 +1,215 @@
+use jarvis_rust::{
+    config::{EmptyResponseConfig, LlmConfig, OllamaConfig},
+    llm::{
+        ChatCompletionRequest, ChatMessage, Function, FunctionCall, LlmClient, OllamaClient, Tool,
+        ToolCall, create_llm_client,
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_json, method, path},
+};
+
+fn config(base_url: String, ollama: OllamaConfig) -> LlmConfig {
+    LlmConfig {
+        provider: "ollama".to_string(),
+        base_url,
+        api_key: String::new(),
+        model: "llama3.1".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama,
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    }
+}
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+fn weather_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_weather".to_string(),
+            description: "Gets weather".to_string(),
+            parameters: json!({"type": "object", "properties": {"city": {"type": "string"}}}),
+        },
+    }
+}
+
+#[tokio::test]
+async fn test_ollama_request_carries_tools_and_options() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .and(body_json(json!({
+            "model": "llama3.1",
+            "messages": [
+                {"role": "system", "content": "Be brief."},
+                {"role": "user", "content": "Weather in Paris?"},
+                {"role": "assistant", "content": "", "tool_calls": [
+                    {"function": {"name": "get_weather", "arguments": {"city": "Paris"}}}
+                ]},
+                {"role": "tool", "content": "Sunny", "tool_name": "get_weather"}
+            ],
+            "tools": [{
+                "type": "function",
+                "function": {
+                    "name": "get_weather",
+                    "description": "Gets weather",
+                    "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }],
+            "stream": false,
+            "keep_alive": "30m",
+            "options": {"temperature": 0.5, "num_predict": 64, "num_ctx": 8192}
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "llama3.1",
+            "created_at": "2026-10-15T12:00:00Z",
+            "message": {"role": "assistant", "content": "Sunny in Paris."},
+            "done": true,
+            "done_reason": "stop",
+            "prompt_eval_count": 30,
+            "eval_count": 5
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let mut assistant = message("assistant", "");
+    assistant.tool_calls = Some(vec![ToolCall {
+        id: "call_1".to_string(),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: "get_weather".to_string(),
+            arguments: r#"{"city":"Paris"}"#.to_string(),
+        },
+    }]);
+    let mut result = message("tool", "Sunny");
+    result.tool_call_id = Some("call_1".to_string());
+
+    let client = OllamaClient::new(config(
+        server.uri(),
+        OllamaConfig {
+            keep_alive: Some("30m".to_string()),
+            num_ctx: Some(8192),
+        },
+    ));
+    let response = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: String::new(),
+            messages: vec![
+                message("system", "Be brief."),
+                message("user", "Weather in Paris?"),
+                assistant,
+                result,
+            ],
+            tools: vec![weather_tool()],
+            max_tokens: Some(64),
+            temperature: Some(0.5),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Sunny in Paris.");
+    assert_eq!(response.choices[0].finish_reason.as_deref(), Some("Stop"));
+    let usage = response.usage.unwrap();
+    assert_eq!(usage.prompt_tokens, 30);
+    assert_eq!(usage.total_tokens, 35);
+}
+
+#[tokio::test]
+async fn test_ollama_tool_calls_are_converted() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/chat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "qwen2.5",
+            "message": {
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [
+                    {"function": {"name": "get_weather", "arguments": {"city": "Oslo"}}}
+                ]
+            },
+            "done": true,
+            "done_reason": "stop"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = OllamaClient::new(config(server.uri(), OllamaConfig::default()));
+    let response = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: "qwen2.5".to_string(),
+            messages: vec![message("user", "Weather in Oslo?")],
+            tools: vec![weather_tool()],
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    let choice = &response.choices[0];
+    assert_eq!(response.model, "qwen2.5");
+    assert_eq!(choice.finish_reason.as_deref(), Some("ToolCalls"));
+    let calls = choice.message.tool_calls.as_ref().unwrap();
+    assert!(!calls[0].id.is_empty());
+    assert_eq!(calls[0].function.name, "get_weather");
+    assert_eq!(calls[0].function.arguments, r#"{"city":"Oslo"}"#);
+}
+
+#[tokio::test]
+async fn test_ollama_errors_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(
+            json!({"error": "registry.ollama.ai/library/llama3.1 does not support tools"}),
+        ))
+        .mount(&server)
+        .await;
+
+    let client = OllamaClient::new(config(server.uri(), OllamaConfig::default()));
+    let error = client
+        .create_chat_completion(ChatCompletionRequest {
+            model: String::new(),
+            messages: vec![message("user", "Hi")],
+            tools: vec![weather_tool()],
+            ..Default::default()
+        })
+        .await
+        .unwrap_err();
+    assert!(error.to_string().contains("does not support tools"));
+}
+
+#[test]
+fn test_ollama_provider_is_selectable() {
+    assert!(create_llm_client(config(String::new(), OllamaConfig::default())).is_ok());
+}
@@ -0,0 +1,214 @@
+use chrono::{Duration, Utc};
+use jarvis_rust::{
+    config::{LlmConfig, ModelPrice, OpenRouterConfig},
+    llm::{
+        ChatCompletionRequest, ChatMessage, LlmClient, ModelCapabilities, ModelCatalog,
+        OpenAiClient,
+    },
+    usage::{Tally, UsageStore},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path},
+};
+
+fn config(base_url: String) -> LlmConfig {
+    let mut config: LlmConfig = serde_yaml::from_str(
+        r#"
+provider: "openrouter"
+base_url: ""
+api_key: "or-key"
+model: "anthropic/claude-3.5-sonnet"
+openrouter:
+  referer: "https://jarvis.example.com"
+  title: "Jarvis"
+"#,
+    )
+    .unwrap();
+    config.base_url = base_url;
+    config
+}
+
+fn request(model: &str) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: model.to_string(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hi".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+async fn models_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/models"))
+        .and(header("HTTP-Referer", "https://jarvis.example.com"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": [
+                {
+                    "id": "openai/gpt-4o",
+                    "name": "OpenAI: GPT-4o",
+                    "context_length": 128000,
+                    "pricing": {"prompt": "0.0000025", "completion": "0.00001", "image": "0.003613"},
+                    "architecture": {"input_modalities": ["text", "image"], "output_modalities": ["text"]},
+                    "supported_parameters": ["tools", "temperature"]
+                },
+                {
+                    "id": "anthropic/claude-3.5-sonnet",
+                    "context_length": 200000,
+                    "pricing": {"prompt": "0.000003", "completion": "0.000015"},
+                    "architecture": {"input_modalities": ["text"], "output_modalities": ["text"]},
+                    "supported_parameters": ["temperature"]
+                },
+                {
+                    "id": "black-forest-labs/flux",
+                    "architecture": {"input_modalities": ["text"], "output_modalities": ["image"]}
+                }
+            ]
+        })))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[test]
+fn test_openrouter_config() {
+    let config = config(String::new());
+    assert_eq!(config.provider, "openrouter");
+    assert_eq!(
+        config.openrouter,
+        OpenRouterConfig {
+            referer: Some("https://jarvis.example.com".to_string()),
+            title: Some("Jarvis".to_string()),
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_requests_name_the_app_and_upstream_model() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .and(header("authorization", "Bearer or-key"))
+        .and(header("HTTP-Referer", "https://jarvis.example.com"))
+        .and(header("X-Title", "Jarvis"))
+        .and(body_partial_json(json!({"model": "openai/gpt-4o"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "gen-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "openai/gpt-4o",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "finish_reason": "stop"
+            }]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let client = OpenAiClient::new(config(server.uri()));
+    let response = client
+        .create_chat_completion(request("openai/gpt-4o"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.choices[0].message.content, "Hello!");
+}
+
+#[tokio::test]
+async fn test_listing_carries_capabilities_and_prices() {
+    let server = models_server().await;
+    let client = OpenAiClient::new(config(server.uri()));
+
+    let models = client.list_models().await.unwrap();
+
+    let ids: Vec<&str> = models.iter().map(|m| m.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![
+            "anthropic/claude-3.5-sonnet",
+            "black-forest-labs/flux",
+            "openai/gpt-4o"
+        ]
+    );
+    let gpt = &models[2];
+    assert_eq!(gpt.provider, "openrouter");
+    assert_eq!(gpt.context_window, Some(128000));
+    assert_eq!(
+        gpt.capabilities,
+        ModelCapabilities {
+            chat: true,
+            tools: true,
+            vision: true,
+            embeddings: false,
+        }
+    );
+    let price = gpt.pricing.unwrap();
+    assert!((price.input_per_million - 2.5).abs() < 1e-9);
+    assert!((price.output_per_million - 10.0).abs() < 1e-9);
+    assert!(!models[0].capabilities.tools);
+    // Image generators don't chat, and their price is unknown
+    assert!(!models[1].capabilities.chat);
+    assert_eq!(models[1].pricing, None);
+}
+
+#[tokio::test]
+async fn test_usage_falls_back_to_listed_prices() {
+    let server = models_server().await;
+    let catalog = ModelCatalog::from_config(&config(server.uri())).unwrap();
+    // Prices are only known once the models were listed
+    assert_eq!(catalog.listed_price("openai/gpt-4o").await, None);
+    catalog.list().await;
+    let listed = catalog.listed_price("openai/gpt-4o").await;
+    assert!(listed.is_some());
+
+    let mut prices = HashMap::new();
+    prices.insert(
+        "anthropic/claude-3.5-sonnet".to_string(),
+        ModelPrice {
+            input_per_million: 1.0,
+            output_per_million: 1.0,
+        },
+    );
+    let store = UsageStore::new(":memory:", prices).await.unwrap();
+    assert!(store.has_price("anthropic/claude-3.5-sonnet"));
+    let tally = Tally {
+        prompt_tokens: 1_000_000,
+        completion_tokens: 0,
+        reasoning_tokens: 0,
+        tool_calls: 0,
+    };
+    store
+        .record_request_at(Some("key"), "s1", "openai/gpt-4o", tally, listed)
+        .await
+        .unwrap();
+    // Configured prices win over listed ones
+    let claude = catalog.listed_price("anthropic/claude-3.5-sonnet").await;
+    store
+        .record_request_at(
+            Some("key"),
+            "s2",
+            "anthropic/claude-3.5-sonnet",
+            tally,
+            claude,
+        )
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let summary = store
+        .summary("key", now - Duration::hours(1), now + Duration::hours(1))
+        .await
+        .unwrap();
+    assert!((summary.cost - 3.5).abs() < 1e-9);
+}
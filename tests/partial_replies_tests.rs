@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Error, Result,
+    agent::Agent,
+    config::{AgentConfig, ModerationConfig, ModerationProviderKind},
+    history::{HistoryStorage, Message},
+    llm::{ChatCompletionRequest, ChatCompletionResponse, LlmClient},
+    moderation::Moderator,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::mpsc::UnboundedSender;
+
+mod common;
+use common::create_mock_chat_response;
+
+const SESSION: &str = "partial";
+
+/// Streams "The answer is 42" in two pieces, looking at the history in between
+struct StreamingLlm {
+    history: Arc<HistoryStorage>,
+    mid_stream: Arc<Mutex<Vec<Message>>>,
+    streamed: Arc<AtomicUsize>,
+    fail: bool,
+}
+
+impl StreamingLlm {
+    fn new(history: Arc<HistoryStorage>) -> Self {
+        Self {
+            history,
+            mid_stream: Arc::new(Mutex::new(Vec::new())),
+            streamed: Arc::new(AtomicUsize::new(0)),
+            fail: false,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for StreamingLlm {
+    async fn create_chat_completion(
+        &self,
+        _request: ChatCompletionRequest,
+    ) -> Result<ChatCompletionResponse> {
+        Ok(create_mock_chat_response("The answer is 42"))
+    }
+
+    async fn create_chat_completion_streaming(
+        &self,
+        _request: ChatCompletionRequest,
+        deltas: UnboundedSender<String>,
+    ) -> Result<ChatCompletionResponse> {
+        self.streamed.fetch_add(1, Ordering::SeqCst);
+        deltas.send("The answer".to_string()).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        *self.mid_stream.lock().unwrap() = self.history.list(SESSION).await.unwrap();
+        if self.fail {
+            return Err(Error::llm("connection reset"));
+        }
+        deltas.send(" is 42".to_string()).unwrap();
+        Ok(create_mock_chat_response("The answer is 42"))
+    }
+}
+
+fn agent(llm: StreamingLlm) -> Agent {
+    Agent::new_for_testing(Box::new(llm), HashMap::new(), HashMap::new(), Vec::new())
+        .with_partial_replies(Duration::ZERO)
+}
+
+#[test]
+fn test_partial_replies_config_parse() {
+    let config: AgentConfig = serde_yaml::from_str(
+        r#"
+partial_replies:
+  enabled: true
+"#,
+    )
+    .unwrap();
+    assert!(config.partial_replies.enabled);
+    assert_eq!(config.partial_replies.interval_ms, 1000);
+
+    assert!(!AgentConfig::default().partial_replies.enabled);
+}
+
+#[tokio::test]
+async fn test_reply_is_saved_while_streamed_and_finalized_in_place() {
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let llm = StreamingLlm::new(history.clone());
+    let mid_stream = llm.mid_stream.clone();
+
+    let reply = agent(llm)
+        .process(SESSION, "What is the answer?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "The answer is 42");
+
+    let partial = mid_stream.lock().unwrap().clone();
+    assert_eq!(partial.len(), 2);
+    assert_eq!(partial[1].role, "assistant");
+    assert_eq!(partial[1].content, "The answer");
+    assert_eq!(partial[1].metadata, Some(json!({"partial": true})));
+
+    let messages = history.list(SESSION).await.unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[1].id, partial[1].id);
+    assert_eq!(messages[1].content, "The answer is 42");
+    assert!(
+        messages[1]
+            .metadata
+            .as_ref()
+            .is_none_or(|m| m.get("partial").is_none())
+    );
+}
+
+#[tokio::test]
+async fn test_failed_run_removes_partial_reply() {
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let mut llm = StreamingLlm::new(history.clone());
+    llm.fail = true;
+    let mid_stream = llm.mid_stream.clone();
+
+    assert!(
+        agent(llm)
+            .process(SESSION, "What is the answer?", &history)
+            .await
+            .is_err()
+    );
+
+    assert_eq!(mid_stream.lock().unwrap().len(), 2);
+    let messages = history.list(SESSION).await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].role, "user");
+}
+
+#[tokio::test]
+async fn test_output_moderation_disables_partial_replies() {
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let llm = StreamingLlm::new(history.clone());
+    let streamed = llm.streamed.clone();
+    let moderator = Moderator::from_config(&ModerationConfig {
+        provider: ModerationProviderKind::Keywords,
+        keywords: HashMap::from([("violence".to_string(), vec!["kill".to_string()])]),
+        ..Default::default()
+    })
+    .unwrap()
+    .unwrap();
+
+    let reply = agent(llm)
+        .with_moderator(moderator)
+        .process(SESSION, "What is the answer?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "The answer is 42");
+    assert_eq!(streamed.load(Ordering::SeqCst), 0);
+    assert_eq!(history.list(SESSION).await.unwrap().len(), 2);
+}
@@ -0,0 +1,87 @@
+use jarvis_rust::history::{HistoryStorage, Message, PiiKind, PiiMatch, detect_pii, scan_history};
+use pretty_assertions::assert_eq;
+use std::collections::BTreeMap;
+
+fn found(kind: PiiKind, redacted: &str) -> PiiMatch {
+    PiiMatch {
+        kind,
+        redacted: redacted.to_string(),
+    }
+}
+
+#[test]
+fn test_detects_emails_phones_and_cards() {
+    assert_eq!(
+        detect_pii(
+            "Mail jane.doe@example.co.uk or call +1 (415) 555-0132. \
+             My card is 4242 4242 4242 4242."
+        ),
+        vec![
+            found(PiiKind::Email, "j***@example.co.uk"),
+            found(PiiKind::Phone, "***32"),
+            found(PiiKind::Card, "**** 4242"),
+        ]
+    );
+    assert_eq!(
+        detect_pii("Call 07700900123"),
+        vec![found(PiiKind::Phone, "***23")]
+    );
+}
+
+#[test]
+fn test_ignores_numbers_that_are_not_pii() {
+    // Dates, times, short numbers, and long ones failing the Luhn check as cards or too
+    // long for phones
+    for text in [
+        "Meet on 2026-01-15 at 12:30",
+        "The answer is 42, not 1234567",
+        "Order 4242 4242 4242 4241 shipped",
+        "No contact details here",
+    ] {
+        assert_eq!(detect_pii(text), Vec::new(), "{text}");
+    }
+}
+
+#[tokio::test]
+async fn test_scan_reports_sessions_with_pii() {
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let message = |session: &str, content: &str| Message::user(session.into(), content.into());
+    history
+        .save(message("clean", "What's the weather?"))
+        .await
+        .unwrap();
+    history
+        .save(message("leaky", "I'm bob@example.com"))
+        .await
+        .unwrap();
+    let id = history
+        .insert(message(
+            "leaky",
+            "Charge 5555 5555 5555 4444 and text +44 7700 900123",
+        ))
+        .await
+        .unwrap()
+        .unwrap();
+    // Deleted messages are still stored until purged
+    history.delete(id).await.unwrap();
+
+    let report = scan_history(&history, None).await.unwrap();
+    assert_eq!(report.sessions_scanned, 2);
+    assert_eq!(report.messages_scanned, 3);
+    assert_eq!(report.sessions.len(), 1);
+    let leaky = &report.sessions[0];
+    assert_eq!(leaky.session_id, "leaky");
+    assert_eq!(
+        leaky.counts,
+        BTreeMap::from([(PiiKind::Email, 1), (PiiKind::Phone, 1), (PiiKind::Card, 1)])
+    );
+    let deleted: Vec<bool> = leaky.findings.iter().map(|f| f.deleted).collect();
+    assert_eq!(deleted, vec![false, true, true]);
+    assert_eq!(leaky.findings[1].message_id, Some(id));
+
+    let report = scan_history(&history, Some(vec!["clean".to_string()]))
+        .await
+        .unwrap();
+    assert_eq!(report.sessions_scanned, 1);
+    assert!(report.sessions.is_empty());
+}
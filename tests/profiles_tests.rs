@@ -0,0 +1,144 @@
+use jarvis_rust::{
+    agent::Agent,
+    history::HistoryStorage,
+    mcp::{McpContent, McpToolCallResponse},
+    profiles::{DEFAULT_USER, ProfileHook, ProfileStore, normalize_key, render_preferences},
+    tools::{
+        NativeTool, ToolContext,
+        profile::{ForgetPreferenceTool, RememberPreferenceTool},
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+#[test]
+fn test_normalize_key_and_render() {
+    assert_eq!(normalize_key("  Preferred  Units "), "preferred_units");
+
+    assert_eq!(render_preferences(&BTreeMap::new()), None);
+    let preferences = BTreeMap::from([
+        ("units".to_string(), "metric".to_string()),
+        ("nickname".to_string(), "Cap".to_string()),
+    ]);
+    assert_eq!(
+        render_preferences(&preferences).unwrap(),
+        "Known preferences of the user you are talking to (respect them):\n\
+         - nickname: Cap\n\
+         - units: metric"
+    );
+}
+
+#[tokio::test]
+async fn test_store_preferences_per_user() {
+    let store = ProfileStore::new(":memory:").await.unwrap();
+    assert_eq!(store.user_for_session("s1").await.unwrap(), DEFAULT_USER);
+
+    store.link_session("s1", "alice").await.unwrap();
+    assert_eq!(store.user_for_session("s1").await.unwrap(), "alice");
+
+    store.set("alice", "Diet", "vegetarian").await.unwrap();
+    store.set("alice", "diet", "vegan").await.unwrap();
+    store.set("bob", "units", "imperial").await.unwrap();
+
+    assert_eq!(
+        store.preferences("alice").await.unwrap(),
+        BTreeMap::from([("diet".to_string(), "vegan".to_string())])
+    );
+    assert!(store.remove("alice", "diet").await.unwrap());
+    assert!(!store.remove("alice", "diet").await.unwrap());
+    assert!(store.preferences("alice").await.unwrap().is_empty());
+    assert_eq!(store.preferences("bob").await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_preference_tools_use_session_user() {
+    let store = Arc::new(ProfileStore::new(":memory:").await.unwrap());
+    store.link_session("s1", "alice").await.unwrap();
+    let remember = RememberPreferenceTool::new(store.clone());
+    let forget = ForgetPreferenceTool::new(store.clone());
+    let ctx = ToolContext::new("s1", None);
+
+    let response = remember
+        .call(args(json!({"key": "Nickname", "value": "Al"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "Remembered nickname: Al");
+    assert_eq!(store.preferences("alice").await.unwrap()["nickname"], "Al");
+    assert!(store.preferences(DEFAULT_USER).await.unwrap().is_empty());
+    assert!(
+        remember
+            .call(args(json!({"key": "diet", "value": " "})), &ctx)
+            .await
+            .is_err()
+    );
+
+    let response = forget
+        .call(args(json!({"key": "nickname"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "Forgot nickname");
+    assert!(
+        forget
+            .call(args(json!({"key": "nickname"})), &ctx)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_profile_hook_injects_preferences_into_system_prompt() {
+    let store = Arc::new(ProfileStore::new(":memory:").await.unwrap());
+    store.link_session("s1", "alice").await.unwrap();
+    store.set("alice", "units", "metric").await.unwrap();
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Noted."));
+    mock_llm.add_response(create_mock_chat_response("Hi!"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.add_hook(Arc::new(ProfileHook::new(store)));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "How far is it?", &history)
+        .await
+        .unwrap();
+    agent.process("s2", "Hello", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    let system = &requests[0].messages[0];
+    assert_eq!(system.role, "system");
+    assert!(
+        system
+            .content
+            .text()
+            .starts_with("You are a helpful assistant.")
+    );
+    assert!(system.content.text().ends_with("- units: metric"));
+    // s2 belongs to the default user, who has no preferences
+    assert_eq!(
+        requests[1].messages[0].content,
+        "You are a helpful assistant."
+    );
+}
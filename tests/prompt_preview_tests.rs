@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+    routing::post,
+};
+use jarvis_rust::{
+    Result,
+    agent::{Agent, AgentHook, HookContext},
+    events::{SessionEventHook, SessionEvents},
+    history::{HistoryStorage, Message},
+    llm::{
+        ChatCompletionRequest, ChatMessage, HeuristicTokenizer, count_tokens, estimate_tokens,
+        tokenizer_for,
+    },
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, prompt_preview},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::MockLlmClient;
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+/// Adds a note to the system prompt, like the profile hook does
+struct NoteHook;
+
+#[async_trait]
+impl AgentHook for NoteHook {
+    async fn before_llm_call(
+        &self,
+        ctx: &HookContext,
+        request: &mut ChatCompletionRequest,
+    ) -> Result<()> {
+        assert!(ctx.preview);
+        request.messages[0]
+            .content
+            .push_str("\n\nUser prefers metric units.");
+        Ok(())
+    }
+}
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+#[test]
+fn test_estimate_tokens() {
+    assert_eq!(estimate_tokens(&[], &[]), 0);
+    // 4 overhead + ceil(4 / 4) + ceil(8 / 4)
+    assert_eq!(estimate_tokens(&[message("user", "12345678")], &[]), 7);
+    let longer = estimate_tokens(&[message("user", &"word ".repeat(100))], &[]);
+    assert!(longer > 100);
+}
+
+#[test]
+fn test_tokenizer_for_model() {
+    let gpt4o = tokenizer_for("gpt-4o");
+    assert_eq!(gpt4o.name(), "O200kBase");
+    assert_eq!(gpt4o.count("hello world"), 2);
+    // Router-style names resolve to the underlying model
+    assert_eq!(tokenizer_for("openai/gpt-4").name(), "Cl100kBase");
+    assert_eq!(tokenizer_for("llama3.1:8b").name(), "heuristic");
+    assert_eq!(tokenizer_for("").name(), "heuristic");
+
+    let messages = [message("user", &"The kitchen light is on. ".repeat(20))];
+    let exact = count_tokens(gpt4o.as_ref(), &messages, &[]);
+    assert_eq!(
+        count_tokens(&HeuristicTokenizer, &messages, &[]),
+        estimate_tokens(&messages, &[])
+    );
+    assert!(exact > 100 && exact < estimate_tokens(&messages, &[]));
+}
+
+#[tokio::test]
+async fn test_prompt_preview_returns_request_without_calling_llm() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    let events = Arc::new(SessionEvents::new(16));
+    let mut receiver = events.subscribe();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_model("gpt-4o");
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent.add_hook(Arc::new(NoteHook));
+    agent.add_hook(Arc::new(SessionEventHook::new(events.clone())));
+
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    history
+        .save(Message::user("s1".to_string(), "Hi".to_string()))
+        .await
+        .unwrap();
+    history
+        .save(Message::assistant("s1".to_string(), "Hello!".to_string()))
+        .await
+        .unwrap();
+
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: Some(events),
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/debug/prompt-preview", post(prompt_preview))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/debug/prompt-preview")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"session_id": "s1", "input": "What time is it?"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(body["session_id"], "s1");
+    let messages = body["messages"].as_array().unwrap();
+    assert_eq!(messages.len(), 4);
+    assert_eq!(messages[0]["role"], "system");
+    assert!(
+        messages[0]["content"]
+            .as_str()
+            .unwrap()
+            .ends_with("User prefers metric units.")
+    );
+    assert_eq!(messages[1], json!({"role": "user", "content": "Hi"}));
+    assert_eq!(
+        messages[2],
+        json!({"role": "assistant", "content": "Hello!"})
+    );
+    assert_eq!(
+        messages[3],
+        json!({"role": "user", "content": "What time is it?"})
+    );
+    assert_eq!(body["tools"][0]["function"]["name"], "clock");
+    assert!(body["estimated_tokens"].as_u64().unwrap() > 10);
+    assert_eq!(body["tokenizer"], "O200kBase");
+
+    // Nothing was sent, saved or published
+    assert!(requests.lock().unwrap().is_empty());
+    assert_eq!(history.list("s1").await.unwrap().len(), 2);
+    assert!(receiver.try_recv().is_err());
+}
@@ -0,0 +1,170 @@
+use chrono::{Duration, Utc};
+use jarvis_rust::{
+    config::{LlmConfig, ReasoningConfig},
+    llm::{ChatCompletionRequest, ChatMessage, LlmClient, OpenAiClient},
+    usage::{Tally, UsageStore},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+use tempfile::TempDir;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+fn config(base_url: &str, model: &str) -> LlmConfig {
+    let mut config: LlmConfig = serde_yaml::from_str(&format!(
+        r#"
+provider: "openai"
+base_url: "{base_url}"
+api_key: "key"
+model: "{model}"
+reasoning:
+  models: ["deepseek-r1*"]
+  effort: "high"
+"#
+    ))
+    .unwrap();
+    config.base_url = base_url.to_string();
+    config
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Prove it".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        temperature: Some(0.2),
+        top_p: Some(0.9),
+        stop: vec!["END".to_string()],
+        max_tokens: Some(500),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_reasoning_models_are_recognized() {
+    let reasoning = ReasoningConfig {
+        models: vec!["deepseek-r1*".to_string(), "thinker".to_string()],
+        effort: None,
+    };
+    for model in [
+        "o1",
+        "o3-mini",
+        "o4-mini-2025-04-16",
+        "gpt-5",
+        "openai/o3",
+        "deepseek-r1-distill",
+        "thinker",
+    ] {
+        assert!(reasoning.applies_to(model), "{model}");
+    }
+    for model in ["gpt-4o", "o1x", "gpt-50", "thinker-2", "llama3"] {
+        assert!(!reasoning.applies_to(model), "{model}");
+    }
+}
+
+#[test]
+fn test_reasoning_requests_drop_sampling_parameters() {
+    let payload = OpenAiClient::new(config("http://localhost:1", "o3-mini"))
+        .request_payload(&request())
+        .unwrap();
+    assert_eq!(payload["max_completion_tokens"], json!(500));
+    assert_eq!(payload["reasoning_effort"], json!("high"));
+    for parameter in ["temperature", "top_p", "stop", "max_tokens"] {
+        assert_eq!(payload.get(parameter), None, "{parameter}");
+    }
+
+    let payload = OpenAiClient::new(config("http://localhost:1", "gpt-4o"))
+        .request_payload(&request())
+        .unwrap();
+    assert_eq!(payload["max_tokens"], json!(500));
+    assert_eq!(payload["stop"], json!(["END"]));
+    assert_eq!(payload.get("reasoning_effort"), None);
+    assert_eq!(payload.get("max_completion_tokens"), None);
+}
+
+#[tokio::test]
+async fn test_reasoning_tokens_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "id": "chatcmpl-1",
+            "object": "chat.completion",
+            "created": 1,
+            "model": "o3-mini",
+            "choices": [{
+                "index": 0,
+                "message": {"role": "assistant", "content": "QED"},
+                "finish_reason": "stop"
+            }],
+            "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": 300,
+                "total_tokens": 310,
+                "completion_tokens_details": {"reasoning_tokens": 256}
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let response = OpenAiClient::new(config(&server.uri(), "o3-mini"))
+        .create_chat_completion(request())
+        .await
+        .unwrap();
+
+    let usage = response.usage.unwrap();
+    assert_eq!(usage.completion_tokens, 300);
+    assert_eq!(usage.reasoning_tokens, Some(256));
+}
+
+#[tokio::test]
+async fn test_usage_counts_reasoning_tokens() {
+    let dir = TempDir::new().unwrap();
+    let db_path = dir.path().join("usage.db");
+    let db_path = db_path.to_str().unwrap();
+    // A database from before reasoning tokens were counted
+    {
+        let db = libsql::Builder::new_local(db_path).build().await.unwrap();
+        db.connect()
+            .unwrap()
+            .execute(
+                "CREATE TABLE usage (id INTEGER PRIMARY KEY AUTOINCREMENT, api_key TEXT, \
+                 session_id TEXT NOT NULL, model TEXT NOT NULL, prompt_tokens INTEGER NOT NULL, \
+                 completion_tokens INTEGER NOT NULL, cost REAL NOT NULL, \
+                 rate_limited INTEGER NOT NULL, created_at DATETIME NOT NULL)",
+                (),
+            )
+            .await
+            .unwrap();
+    }
+    let store = UsageStore::new(db_path, HashMap::new()).await.unwrap();
+    let tally = Tally {
+        prompt_tokens: 10,
+        completion_tokens: 300,
+        reasoning_tokens: 256,
+        tool_calls: 0,
+    };
+    store
+        .record_request(Some("key"), "s1", "o3-mini", tally)
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let summary = store
+        .summary("key", now - Duration::hours(1), now + Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(summary.completion_tokens, 300);
+    assert_eq!(summary.reasoning_tokens, 256);
+    let session = store.session_summary("s1").await.unwrap();
+    assert_eq!(session.models[0].reasoning_tokens, 256);
+    assert_eq!(session.total.reasoning_tokens, 256);
+}
@@ -0,0 +1,161 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::{Agent, replay_session},
+    history::HistoryStorage,
+    mcp::{McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Clock returning a fixed time, counting how often it really ran
+struct ClockTool {
+    time: &'static str,
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(text_result(self.time))
+    }
+}
+
+fn agent_with_clock(mock_llm: MockLlmClient, time: &'static str) -> (Agent, Arc<AtomicUsize>) {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool {
+        time,
+        calls: calls.clone(),
+    }));
+    (agent, calls)
+}
+
+async fn recorded_session() -> HistoryStorage {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    mock_llm.add_response(create_mock_chat_response("You're welcome."));
+    let (mut agent, _) = agent_with_clock(mock_llm, "08:00");
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+    agent.process("s1", "Thanks", &history).await.unwrap();
+    history
+}
+
+#[tokio::test]
+async fn test_replay_uses_recorded_tool_results_and_reports_divergence() {
+    let history = recorded_session().await;
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    mock_llm.add_response(create_mock_chat_response("No problem!"));
+    let requests = mock_llm.requests.clone();
+    let (mut agent, live_calls) = agent_with_clock(mock_llm, "21:00");
+
+    let report = replay_session(&mut agent, &history, "s1").await.unwrap();
+
+    assert_eq!(report.turns.len(), 2);
+    let first = &report.turns[0];
+    assert!(!first.diverged());
+    assert_eq!(first.recorded_tools, vec!["clock"]);
+    assert_eq!(first.replayed_tools, vec!["clock"]);
+
+    let second = &report.turns[1];
+    assert!(second.diverged());
+    assert!(second.tools_match);
+    assert_eq!(second.input, "Thanks");
+    assert_eq!(second.recorded_output.as_deref(), Some("You're welcome."));
+    assert_eq!(second.replayed_output.as_deref(), Some("No problem!"));
+    assert_eq!(report.first_divergence().unwrap().turn, 2);
+    assert_eq!(report.diverged_count(), 1);
+
+    // The clock never ran; the LLM saw the recorded time
+    assert_eq!(live_calls.load(Ordering::SeqCst), 0);
+    {
+        let requests = requests.lock().unwrap();
+        let tool_message = requests[1]
+            .messages
+            .iter()
+            .find(|m| m.role == "tool")
+            .unwrap();
+        assert_eq!(tool_message.content, "08:00");
+
+        // The second turn starts from the recorded conversation
+        let contents: Vec<String> = requests[2]
+            .messages
+            .iter()
+            .filter(|m| m.role == "user" || m.role == "assistant")
+            .map(|m| m.content.to_string())
+            .collect();
+        assert!(contents.ends_with(&["It is 8 o'clock.".to_string(), "Thanks".to_string()]));
+    }
+
+    // Replaying leaves the stored session alone
+    assert_eq!(history.list("s1").await.unwrap().len(), 4);
+}
+
+#[tokio::test]
+async fn test_replay_flags_unrecorded_tool_calls() {
+    let history = recorded_session().await;
+
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("You're welcome."));
+    let requests = mock_llm.requests.clone();
+    let (mut agent, live_calls) = agent_with_clock(mock_llm, "21:00");
+
+    let report = replay_session(&mut agent, &history, "s1").await.unwrap();
+
+    let second = &report.turns[1];
+    assert!(second.output_matches);
+    assert!(!second.tools_match);
+    assert!(second.recorded_tools.is_empty());
+    assert_eq!(second.replayed_tools, vec!["clock"]);
+    assert_eq!(live_calls.load(Ordering::SeqCst), 0);
+
+    let requests = requests.lock().unwrap();
+    let tool_message = requests[3]
+        .messages
+        .iter()
+        .rfind(|m| m.role == "tool")
+        .unwrap();
+    assert!(
+        tool_message
+            .content
+            .text()
+            .contains("No recorded result for 'clock'")
+    );
+}
@@ -0,0 +1,583 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+    routing::get,
+};
+use chrono::{DateTime, Duration, Utc};
+use jarvis_rust::{
+    Error, Result,
+    agent::{Agent, AgentHook, HookContext, ProcessOptions, RunOutcome, ToolMode},
+    config::{EmptyResponseConfig, LlmConfig},
+    events::{
+        LatencyBreakdown, RunEvent, RunEventHook, RunEventKind, RunEventStore, Span, SpanKind,
+        redact_keys,
+    },
+    history::HistoryStorage,
+    llm::OllamaClient,
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, run_llm_call, run_timeline},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn agent_with_timeline(mock_llm: MockLlmClient, store: Arc<RunEventStore>) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent.add_hook(Arc::new(RunEventHook::new(store)));
+    agent
+}
+
+fn transition(from: &str, to: &str, event: &str) -> RunEventKind {
+    RunEventKind::Transition {
+        from: from.to_string(),
+        to: to.to_string(),
+        event: event.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_store_keeps_events_in_order_by_run() {
+    let store = RunEventStore::new(":memory:").await.unwrap();
+    store
+        .record("r1", "s1", RunEventKind::LlmCall { turn: 0 })
+        .await
+        .unwrap();
+    store
+        .record("r2", "s2", RunEventKind::RunCompleted)
+        .await
+        .unwrap();
+    store
+        .record("r1", "s1", RunEventKind::RunCompleted)
+        .await
+        .unwrap();
+
+    let events = store.timeline("r1").await.unwrap();
+    assert_eq!(
+        events.iter().map(|e| e.kind.clone()).collect::<Vec<_>>(),
+        vec![
+            RunEventKind::LlmCall { turn: 0 },
+            RunEventKind::RunCompleted
+        ]
+    );
+    assert!(
+        events
+            .iter()
+            .all(|e| e.run_id == "r1" && e.session_id == "s1")
+    );
+    assert!(events[0].at <= events[1].at);
+    assert!(store.timeline("unknown").await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_hook_records_transitions_llm_and_tool_calls() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "clock",
+        r#"{"zone": "UTC"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = agent_with_timeline(mock_llm, store.clone());
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process_with_citations("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+
+    let events = store.timeline(&reply.run_id).await.unwrap();
+    assert!(events.iter().all(|e| e.session_id == "s1"));
+    assert_eq!(
+        events.into_iter().map(|e| e.kind).collect::<Vec<_>>(),
+        vec![
+            RunEventKind::RunStarted {
+                input: "What time is it?".to_string(),
+            },
+            transition("ReadyToCallLlm", "AwaitingLlmResponse", "ProcessInput"),
+            RunEventKind::LlmCall { turn: 0 },
+            RunEventKind::LlmResponse {
+                turn: 0,
+                finish_reason: Some("tool_calls".to_string()),
+                tool_calls: 1,
+                prompt_tokens: None,
+                completion_tokens: None,
+                provider: None,
+                context_fallback: None,
+            },
+            transition("AwaitingLlmResponse", "ExecutingTools", "LlmRequestedTools"),
+            RunEventKind::ToolCall {
+                name: "clock".to_string(),
+                arguments: json!({"zone": "UTC"}),
+                server: None,
+            },
+            RunEventKind::ToolResult {
+                name: "clock".to_string(),
+                is_error: false,
+            },
+            transition(
+                "ExecutingTools",
+                "ReadyToCallLlm",
+                "ToolsExecutionCompleted"
+            ),
+            transition("ReadyToCallLlm", "AwaitingLlmResponse", "ProcessInput"),
+            RunEventKind::LlmCall { turn: 1 },
+            RunEventKind::LlmResponse {
+                turn: 1,
+                finish_reason: Some("stop".to_string()),
+                tool_calls: 0,
+                prompt_tokens: None,
+                completion_tokens: None,
+                provider: None,
+                context_fallback: None,
+            },
+            transition("AwaitingLlmResponse", "Done", "LlmRespondedWithContent"),
+            RunEventKind::RunCompleted,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_paused_run_keeps_its_id() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 09:15."));
+    let mut agent = agent_with_timeline(mock_llm, store.clone());
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let RunOutcome::Paused(run) = agent
+        .process_with_options(
+            "s1",
+            "What time is it?",
+            &history,
+            ProcessOptions {
+                tool_mode: ToolMode::Manual,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap()
+    else {
+        panic!("expected the run to pause for its tool call");
+    };
+    let run_id = run.run_id().to_string();
+    let results = HashMap::from([("call_clock".to_string(), text_result("09:15"))]);
+    let RunOutcome::Reply(reply) = agent.resume_run(*run, results, &history).await.unwrap() else {
+        panic!("expected a reply");
+    };
+    assert_eq!(reply.run_id, run_id);
+
+    // Both halves of the run share one timeline
+    let events = store.timeline(&run_id).await.unwrap();
+    assert_eq!(
+        events.first().unwrap().kind,
+        RunEventKind::RunStarted {
+            input: "What time is it?".to_string(),
+        }
+    );
+    assert_eq!(events.last().unwrap().kind, RunEventKind::RunCompleted);
+}
+
+async fn app(store: Arc<RunEventStore>) -> Router {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: Some(store),
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/runs/:run_id/timeline", get(run_timeline))
+        .route("/runs/:run_id/llm_calls/:n", get(run_llm_call))
+        .with_state(state)
+}
+
+async fn get_timeline(app: Router, run_id: &str) -> (StatusCode, Value) {
+    get_json(app, &format!("/runs/{run_id}/timeline")).await
+}
+
+async fn get_json(app: Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_timeline_endpoint() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    store
+        .record(
+            "r1",
+            "s1",
+            RunEventKind::RunStarted {
+                input: "Hi".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    store
+        .record("r1", "s1", RunEventKind::LlmCall { turn: 0 })
+        .await
+        .unwrap();
+    let app = app(store).await;
+
+    let (status, body) = get_timeline(app.clone(), "r1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["run_id"], "r1");
+    assert_eq!(body["session_id"], "s1");
+    assert_eq!(body["events"][0]["type"], "run_started");
+    assert_eq!(body["events"][0]["input"], "Hi");
+    assert_eq!(body["events"][1]["type"], "llm_call");
+    assert_eq!(body["events"][1]["turn"], 0);
+    assert!(body["events"][1]["at"].is_string());
+    assert_eq!(body["breakdown"]["root"]["kind"], "run");
+    assert_eq!(body["breakdown"]["root"]["children"][0]["name"], "turn 0");
+    assert_eq!(
+        body["breakdown"]["root"]["children"][0]["children"][0]["name"],
+        "llm call 1"
+    );
+
+    let (status, body) = get_timeline(app, "missing").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "No run with id 'missing'");
+}
+
+/// Event of run `r1` happening `ms` milliseconds into it
+fn ollama_payloads() -> Arc<OllamaClient> {
+    Arc::new(OllamaClient::new(LlmConfig {
+        provider: "ollama".to_string(),
+        base_url: String::new(),
+        api_key: String::new(),
+        model: "llama3.1".to_string(),
+        system_prompt: None,
+        max_continuations: 2,
+        max_context_tokens: None,
+        retry: Default::default(),
+        pacing: Default::default(),
+        request_timeout: 300,
+        connect_timeout: 10,
+        proxy: None,
+        context_fallback: Default::default(),
+        sampling: Default::default(),
+        empty_response: EmptyResponseConfig::default(),
+        structured_output: Default::default(),
+        routing: Default::default(),
+        retroactive_system_prompt: true,
+        ollama: Default::default(),
+        azure: Default::default(),
+        openrouter: Default::default(),
+        reasoning: Default::default(),
+        replay: Default::default(),
+        fallbacks: Vec::new(),
+    }))
+}
+
+#[tokio::test]
+async fn test_hook_records_provider_payload_of_each_llm_call() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent.add_hook(Arc::new(
+        RunEventHook::new(store.clone())
+            .with_payloads(ollama_payloads(), vec!["sk-secret".to_string()]),
+    ));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process_with_citations("s1", "My key is sk-secret, what time is it?", &history)
+        .await
+        .unwrap();
+
+    let first = store.llm_call(&reply.run_id, 1).await.unwrap().unwrap();
+    assert_eq!(first.session_id, "s1");
+    assert_eq!(first.turn, 0);
+    assert_eq!(first.payload["model"], "llama3.1");
+    assert_eq!(first.payload["stream"], false);
+    let messages = first.payload["messages"].as_array().unwrap();
+    assert_eq!(
+        messages.last().unwrap()["content"],
+        "My key is [redacted], what time is it?"
+    );
+
+    let second = store.llm_call(&reply.run_id, 2).await.unwrap().unwrap();
+    assert_eq!(second.turn, 1);
+    let messages = second.payload["messages"].as_array().unwrap();
+    assert_eq!(messages.last().unwrap()["role"], "tool");
+    assert_eq!(messages.last().unwrap()["tool_name"], "clock");
+    assert!(store.llm_call(&reply.run_id, 3).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_run_failed_keeps_fsm_diagnostics() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    let hook = RunEventHook::new(store.clone());
+    let diagnostics = jarvis_rust::agent::FsmDiagnostics {
+        reason: "exceeded 24 loop iterations".to_string(),
+        state: "AwaitingLlmResponse".to_string(),
+        last_event: Some("ProcessInput".to_string()),
+        iterations: 25,
+        turn: 3,
+        max_turns: 5,
+        transitions: vec!["ReadyToCallLlm --ProcessInput--> AwaitingLlmResponse".to_string()],
+        messages: 7,
+        pending_tool_calls: Vec::new(),
+        has_llm_response: true,
+        last_error: None,
+    };
+    let error = Error::fsm_stuck(diagnostics.clone());
+    assert_eq!(
+        error.to_string(),
+        "FSM error: run stuck in AwaitingLlmResponse after 25 iterations: exceeded 24 loop iterations"
+    );
+
+    hook.on_complete(&HookContext::for_run("s1", "r1", 3), &Err(error))
+        .await;
+
+    let events = store.timeline("r1").await.unwrap();
+    assert_eq!(
+        events.last().unwrap().kind,
+        RunEventKind::RunFailed {
+            error: "FSM error: run stuck in AwaitingLlmResponse after 25 iterations: exceeded 24 loop iterations"
+                .to_string(),
+            fsm: Some(diagnostics),
+        }
+    );
+}
+
+#[test]
+fn test_redact_keys() {
+    let mut payload = json!({
+        "headers": {"Authorization": "Bearer sk-1", "X-Goog-Api-Key": "g-1"},
+        "messages": [{"role": "user", "content": "use sk-1 twice: sk-1"}],
+        "max_tokens": 64
+    });
+    redact_keys(&mut payload, &["sk-1".to_string(), String::new()]);
+    assert_eq!(
+        payload,
+        json!({
+            "headers": {"Authorization": "[redacted]", "X-Goog-Api-Key": "[redacted]"},
+            "messages": [{"role": "user", "content": "use [redacted] twice: [redacted]"}],
+            "max_tokens": 64
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_llm_call_endpoint() {
+    let store = Arc::new(RunEventStore::new(":memory:").await.unwrap());
+    let call = store
+        .record_llm_call("r1", "s1", 0, &json!({"model": "gpt-4o", "messages": []}))
+        .await
+        .unwrap();
+    assert_eq!(call, 1);
+    let app = app(store).await;
+
+    let (status, body) = get_json(app.clone(), "/runs/r1/llm_calls/1").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["run_id"], "r1");
+    assert_eq!(body["session_id"], "s1");
+    assert_eq!(body["call"], 1);
+    assert_eq!(body["turn"], 0);
+    assert_eq!(body["payload"], json!({"model": "gpt-4o", "messages": []}));
+
+    let (status, body) = get_json(app, "/runs/r1/llm_calls/2").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "No LLM call 2 recorded for run 'r1'");
+}
+
+fn event_at(ms: i64, kind: RunEventKind) -> RunEvent {
+    let start: DateTime<Utc> = "2026-01-01T00:00:00Z".parse().unwrap();
+    RunEvent {
+        run_id: "r1".to_string(),
+        session_id: "s1".to_string(),
+        at: start + Duration::milliseconds(ms),
+        kind,
+    }
+}
+
+fn llm_response(turn: usize) -> RunEventKind {
+    RunEventKind::LlmResponse {
+        turn,
+        finish_reason: None,
+        tool_calls: 0,
+        prompt_tokens: None,
+        completion_tokens: None,
+        provider: None,
+        context_fallback: None,
+    }
+}
+
+fn tool_call(name: &str, server: Option<&str>) -> RunEventKind {
+    RunEventKind::ToolCall {
+        name: name.to_string(),
+        arguments: json!({}),
+        server: server.map(str::to_string),
+    }
+}
+
+fn tool_result(name: &str) -> RunEventKind {
+    RunEventKind::ToolResult {
+        name: name.to_string(),
+        is_error: false,
+    }
+}
+
+fn span(name: &str, kind: SpanKind, start_ms: i64, duration_ms: i64) -> Span {
+    Span {
+        name: name.to_string(),
+        kind,
+        server: None,
+        start_ms,
+        duration_ms,
+        children: Vec::new(),
+    }
+}
+
+#[test]
+fn test_latency_breakdown_nests_calls_in_turns() {
+    let events = vec![
+        event_at(
+            0,
+            RunEventKind::RunStarted {
+                input: "Hi".to_string(),
+            },
+        ),
+        event_at(10, RunEventKind::LlmCall { turn: 0 }),
+        event_at(510, llm_response(0)),
+        // Tools of a turn run concurrently
+        event_at(520, tool_call("lights", Some("home"))),
+        event_at(520, tool_call("clock", None)),
+        event_at(530, tool_result("clock")),
+        event_at(1520, tool_result("lights")),
+        event_at(1530, RunEventKind::LlmCall { turn: 1 }),
+        event_at(1830, llm_response(1)),
+        event_at(1840, RunEventKind::RunCompleted),
+    ];
+
+    let breakdown = LatencyBreakdown::from_events(&events).unwrap();
+
+    let mut lights = span("tool lights", SpanKind::ToolCall, 520, 1000);
+    lights.server = Some("home".to_string());
+    let mut turn_0 = span("turn 0", SpanKind::Turn, 10, 1520);
+    turn_0.children = vec![
+        span("llm call 1", SpanKind::LlmCall, 10, 500),
+        span("tool clock", SpanKind::ToolCall, 520, 10),
+        lights,
+    ];
+    let mut turn_1 = span("turn 1", SpanKind::Turn, 1530, 310);
+    turn_1.children = vec![span("llm call 2", SpanKind::LlmCall, 1530, 300)];
+    let mut root = span("run", SpanKind::Run, 0, 1840);
+    root.children = vec![turn_0, turn_1];
+    assert_eq!(breakdown.root, root);
+    assert_eq!(
+        breakdown.by_source.into_iter().collect::<Vec<_>>(),
+        vec![
+            ("llm".to_string(), 800),
+            ("mcp:home".to_string(), 1000),
+            ("native".to_string(), 10),
+        ]
+    );
+}
+
+#[test]
+fn test_latency_breakdown_of_failed_run_ends_open_calls() {
+    let events = vec![
+        event_at(0, RunEventKind::LlmCall { turn: 0 }),
+        event_at(200, RunEventKind::LlmCall { turn: 0 }),
+        event_at(
+            900,
+            RunEventKind::RunFailed {
+                error: "timeout".to_string(),
+                fsm: None,
+            },
+        ),
+    ];
+
+    let breakdown = LatencyBreakdown::from_events(&events).unwrap();
+
+    let turn = &breakdown.root.children[0];
+    assert_eq!(turn.duration_ms, 900);
+    assert_eq!(
+        turn.children,
+        vec![
+            span("llm call 1", SpanKind::LlmCall, 0, 900),
+            span("llm call 2", SpanKind::LlmCall, 200, 700),
+        ]
+    );
+    assert_eq!(breakdown.by_source["llm"], 1600);
+    assert!(LatencyBreakdown::from_events(&[]).is_none());
+}
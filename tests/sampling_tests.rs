@@ -0,0 +1,249 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{LlmConfig, SamplingConfig},
+    history::HistoryStorage,
+    llm::{
+        ChatCompletionRequest, ChatMessage, GeminiClient, LlmClient, OllamaClient, OpenAiClient,
+    },
+    server::handlers::{AppState, inference},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn llm_config(provider: &str) -> LlmConfig {
+    model_config(provider, "some-model")
+}
+
+fn model_config(provider: &str, model: &str) -> LlmConfig {
+    serde_yaml::from_str(&format!(
+        r#"
+provider: "{provider}"
+base_url: "http://localhost:1"
+api_key: "key"
+model: "{model}"
+temperature: 0.3
+max_tokens: 512
+top_p: 0.9
+stop: ["END"]
+frequency_penalty: 0.5
+presence_penalty: 0.25
+seed: 7
+logit_bias: {{"50256": -100}}
+"#
+    ))
+    .unwrap()
+}
+
+fn request(config: &LlmConfig) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Hi".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+    .with_sampling(&config.sampling)
+}
+
+#[test]
+fn test_sampling_params_are_read_from_llm() {
+    let config = llm_config("openai");
+    assert_eq!(
+        config.sampling,
+        SamplingConfig {
+            temperature: Some(0.3),
+            max_tokens: Some(512),
+            top_p: Some(0.9),
+            stop: vec!["END".to_string()],
+            frequency_penalty: Some(0.5),
+            presence_penalty: Some(0.25),
+            seed: Some(7),
+            logit_bias: BTreeMap::from([("50256".to_string(), -100)]),
+        }
+    );
+}
+
+#[test]
+fn test_request_params_take_precedence() {
+    let configured = llm_config("openai").sampling;
+    let requested = SamplingConfig {
+        temperature: Some(1.2),
+        stop: vec!["STOP".to_string()],
+        seed: Some(42),
+        ..Default::default()
+    };
+    assert_eq!(
+        requested.or(&configured),
+        SamplingConfig {
+            temperature: Some(1.2),
+            max_tokens: Some(512),
+            top_p: Some(0.9),
+            stop: vec!["STOP".to_string()],
+            frequency_penalty: Some(0.5),
+            presence_penalty: Some(0.25),
+            seed: Some(42),
+            logit_bias: BTreeMap::from([("50256".to_string(), -100)]),
+        }
+    );
+}
+
+#[test]
+fn test_providers_send_sampling_params() {
+    let config = llm_config("openai");
+    let openai = OpenAiClient::new(config.clone())
+        .request_payload(&request(&config))
+        .unwrap();
+    assert_eq!(openai["temperature"], json!(0.3_f32));
+    assert_eq!(openai["max_tokens"], 512);
+    assert_eq!(openai["top_p"], json!(0.9_f32));
+    assert_eq!(openai["stop"], json!(["END"]));
+    assert_eq!(openai["frequency_penalty"], json!(0.5));
+    assert_eq!(openai["presence_penalty"], json!(0.25));
+    assert_eq!(openai["seed"], json!(7));
+    assert_eq!(openai["logit_bias"], json!({"50256": -100}));
+
+    let config = llm_config("gemini");
+    let gemini = GeminiClient::new(config.clone())
+        .request_payload(&request(&config))
+        .unwrap();
+    assert_eq!(
+        gemini["generationConfig"],
+        json!({
+            "temperature": 0.3_f32,
+            "maxOutputTokens": 512,
+            "topP": 0.9_f32,
+            "stopSequences": ["END"],
+            "frequencyPenalty": 0.5,
+            "presencePenalty": 0.25,
+            "seed": 7
+        })
+    );
+
+    let config = llm_config("ollama");
+    let ollama = OllamaClient::new(config.clone())
+        .request_payload(&request(&config))
+        .unwrap();
+    assert_eq!(
+        ollama["options"],
+        json!({
+            "temperature": 0.3_f32,
+            "num_predict": 512,
+            "top_p": 0.9_f32,
+            "stop": ["END"],
+            "frequency_penalty": 0.5,
+            "presence_penalty": 0.25,
+            "seed": 7
+        })
+    );
+}
+
+#[test]
+fn test_reasoning_models_only_get_the_seed() {
+    let config = model_config("openai", "o3-mini");
+    let payload = OpenAiClient::new(config.clone())
+        .request_payload(&request(&config))
+        .unwrap();
+    assert_eq!(payload["seed"], json!(7));
+    for parameter in ["frequency_penalty", "presence_penalty", "logit_bias"] {
+        assert_eq!(payload.get(parameter), None, "{parameter}");
+    }
+}
+
+#[tokio::test]
+async fn test_inference_request_overrides_configured_params() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let requests = mock_llm.requests.clone();
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_sampling(SamplingConfig {
+        temperature: Some(0.3),
+        max_tokens: Some(512),
+        ..Default::default()
+    });
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(Vec::new()),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+
+    let body = json!({
+        "input": "Hi",
+        "temperature": 0.0,
+        "top_p": 0.5,
+        "stop": ["\n\n"],
+        "seed": 42,
+        "presence_penalty": 1.0,
+        "logit_bias": {"1734": 10}
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].temperature, Some(0.0));
+    assert_eq!(requests[0].max_tokens, Some(512));
+    assert_eq!(requests[0].top_p, Some(0.5));
+    assert_eq!(requests[0].stop, vec!["\n\n".to_string()]);
+    assert_eq!(requests[0].seed, Some(42));
+    assert_eq!(requests[0].presence_penalty, Some(1.0));
+    assert_eq!(requests[0].frequency_penalty, None);
+    assert_eq!(
+        requests[0].logit_bias,
+        BTreeMap::from([("1734".to_string(), 10)])
+    );
+}
@@ -0,0 +1,100 @@
+use chrono::{NaiveTime, TimeZone, Utc};
+use jarvis_rust::{
+    agent::Agent,
+    config::ScheduleConfig,
+    history::HistoryStorage,
+    scheduler::{Schedule, ScheduledJob, Scheduler, parse_interval},
+};
+use pretty_assertions::assert_eq;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn schedule_config(every: Option<&str>, at: Option<&str>) -> ScheduleConfig {
+    ScheduleConfig {
+        name: "news".to_string(),
+        prompt: "What's new?".to_string(),
+        every: every.map(String::from),
+        at: at.map(String::from),
+        session_id: None,
+        notify: true,
+    }
+}
+
+#[test]
+fn test_parse_interval() {
+    assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_interval("15m").unwrap(), Duration::from_secs(900));
+    assert_eq!(parse_interval("2h").unwrap(), Duration::from_secs(7200));
+    assert_eq!(parse_interval("1d").unwrap(), Duration::from_secs(86_400));
+    assert!(parse_interval("0m").is_err());
+    assert!(parse_interval("soon").is_err());
+    assert!(parse_interval("5w").is_err());
+}
+
+#[test]
+fn test_job_from_config() {
+    let job = ScheduledJob::from_config(&schedule_config(Some("6h"), None)).unwrap();
+    assert_eq!(job.session_id, "schedule:news");
+    assert_eq!(job.schedule, Schedule::Every(Duration::from_secs(6 * 3600)));
+
+    let daily = ScheduledJob::from_config(&schedule_config(None, Some("07:30"))).unwrap();
+    assert_eq!(
+        daily.schedule,
+        Schedule::DailyAt(NaiveTime::from_hms_opt(7, 30, 0).unwrap())
+    );
+
+    assert!(ScheduledJob::from_config(&schedule_config(None, None)).is_err());
+    assert!(ScheduledJob::from_config(&schedule_config(Some("1h"), Some("07:30"))).is_err());
+    assert!(ScheduledJob::from_config(&schedule_config(None, Some("7.30am"))).is_err());
+}
+
+#[test]
+fn test_next_run() {
+    let berlin = chrono_tz::Europe::Berlin;
+    let daily = Schedule::DailyAt(NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+
+    // 05:00 UTC is 07:00 in Berlin, so today's 07:30 local (05:30 UTC) is still ahead
+    let early = Utc.with_ymd_and_hms(2026, 10, 15, 5, 0, 0).unwrap();
+    assert_eq!(
+        daily.next_run(early, berlin).unwrap(),
+        Utc.with_ymd_and_hms(2026, 10, 15, 5, 30, 0).unwrap()
+    );
+    let late = Utc.with_ymd_and_hms(2026, 10, 15, 12, 0, 0).unwrap();
+    assert_eq!(
+        daily.next_run(late, berlin).unwrap(),
+        Utc.with_ymd_and_hms(2026, 10, 16, 5, 30, 0).unwrap()
+    );
+
+    let every = Schedule::Every(Duration::from_secs(1800));
+    assert_eq!(
+        every.next_run(late, berlin).unwrap(),
+        Utc.with_ymd_and_hms(2026, 10, 15, 12, 30, 0).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_run_job_uses_job_session() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Nothing new today."));
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let scheduler = Scheduler::new(Arc::new(Mutex::new(agent)), history.clone(), chrono_tz::UTC);
+
+    let job = ScheduledJob::from_config(&schedule_config(Some("1h"), None)).unwrap();
+    let output = scheduler.run_job(&job).await.unwrap();
+    assert_eq!(output, "Nothing new today.");
+
+    let messages = history.list("schedule:news").await.unwrap();
+    assert_eq!(messages[0].content, "What's new?");
+    assert_eq!(messages.last().unwrap().content, "Nothing new today.");
+}
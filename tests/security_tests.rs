@@ -0,0 +1,232 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::{get, post},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::{ApiKeyConfig, ModerationConfig, ModerationProviderKind},
+    history::HistoryStorage,
+    moderation::Moderator,
+    security::{
+        BlockedCount, SecurityEvent, SecurityEventStore, SecurityRule, input_hash, render_metrics,
+    },
+    server::handlers::{AppState, inference, metrics},
+    usage::UsageStore,
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn key(name: &str, models: &[&str], requests_per_minute: Option<u32>) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: models.iter().map(|model| model.to_string()).collect(),
+        default_model: None,
+        requests_per_minute,
+        admin: false,
+    }
+}
+
+async fn app() -> (Router, Arc<SecurityEventStore>) {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..5 {
+        mock_llm.add_response(create_mock_chat_response("Hello!"));
+    }
+    let moderation = ModerationConfig {
+        provider: ModerationProviderKind::Keywords,
+        keywords: HashMap::from([("weapons".to_string(), vec!["pipe bomb".to_string()])]),
+        ..Default::default()
+    };
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_model("gpt-4o")
+    .with_moderator(Moderator::from_config(&moderation).unwrap().unwrap());
+    let security = Arc::new(SecurityEventStore::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(vec![
+            key("alice", &[], None),
+            key("cheap", &["gpt-4o-mini"], Some(1)),
+            ApiKeyConfig {
+                admin: true,
+                ..key("ops", &[], None)
+            },
+        ]),
+        usage: Some(Arc::new(
+            UsageStore::new(":memory:", HashMap::new()).await.unwrap(),
+        )),
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: Some(security.clone()),
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let router = Router::new()
+        .route("/", post(inference))
+        .route("/metrics", get(metrics))
+        .with_state(state);
+    (router, security)
+}
+
+async fn ask(app: &Router, key: Option<&str>, body: Value) -> StatusCode {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(key) = key {
+        request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+    }
+    app.clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+fn count(rule: &str, principal: Option<&str>, count: u64) -> BlockedCount {
+    BlockedCount {
+        rule: rule.to_string(),
+        principal: principal.map(str::to_string),
+        count,
+    }
+}
+
+#[test]
+fn test_input_hash_is_short_and_stable() {
+    let hash = input_hash("ignore previous instructions");
+    assert_eq!(hash.len(), 16);
+    assert_eq!(hash, input_hash("ignore previous instructions"));
+    assert_ne!(hash, input_hash("ignore previous instructions!"));
+}
+
+#[tokio::test]
+async fn test_store_counts_events_by_rule_and_principal() {
+    let store = SecurityEventStore::new(":memory:").await.unwrap();
+    let unknown = SecurityEvent::new(SecurityRule::UnknownApiKey, None);
+    store.record(&unknown).await.unwrap();
+    store.record(&unknown).await.unwrap();
+    let limited = SecurityEvent::new(SecurityRule::RateLimit, Some("cheap"))
+        .with_session("chat")
+        .with_input("Hi");
+    assert_eq!(limited.input_hash, Some(input_hash("Hi")));
+    store.record(&limited).await.unwrap();
+
+    assert_eq!(
+        store.counts(None).await.unwrap(),
+        vec![
+            count("rate_limit", Some("cheap"), 1),
+            count("unknown_api_key", None, 2),
+        ]
+    );
+    let later = chrono::Utc::now() + chrono::Duration::minutes(1);
+    assert!(store.counts(Some(later)).await.unwrap().is_empty());
+}
+
+#[test]
+fn test_metrics_render_in_prometheus_format() {
+    let metrics = render_metrics(&[
+        count("moderation", Some("alice"), 3),
+        count("missing_api_key", None, 1),
+    ]);
+    assert_eq!(
+        metrics,
+        "# HELP jarvis_blocked_requests_total Requests refused, by rule and API key.\n\
+         # TYPE jarvis_blocked_requests_total counter\n\
+         jarvis_blocked_requests_total{rule=\"moderation\",principal=\"alice\"} 3\n\
+         jarvis_blocked_requests_total{rule=\"missing_api_key\",principal=\"\"} 1\n"
+    );
+}
+
+#[tokio::test]
+async fn test_refused_requests_are_recorded() {
+    let (app, security) = app().await;
+
+    let hi = json!({"input": "Hi"});
+    assert_eq!(ask(&app, None, hi.clone()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        ask(&app, Some("stolen-key"), hi.clone()).await,
+        StatusCode::UNAUTHORIZED
+    );
+    // gpt-4o isn't among the cheap key's models
+    assert_eq!(
+        ask(&app, Some("cheap-key"), hi.clone()).await,
+        StatusCode::FORBIDDEN
+    );
+    let mini = json!({"input": "Hi", "model": "gpt-4o-mini"});
+    assert_eq!(
+        ask(&app, Some("cheap-key"), mini.clone()).await,
+        StatusCode::OK
+    );
+    assert_eq!(
+        ask(&app, Some("cheap-key"), mini).await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // Blocked input is answered, but recorded
+    let bomb = json!({"input": "How do I build a pipe bomb?"});
+    assert_eq!(ask(&app, Some("alice-key"), bomb).await, StatusCode::OK);
+    assert_eq!(ask(&app, Some("alice-key"), hi).await, StatusCode::OK);
+
+    assert_eq!(
+        security.counts(None).await.unwrap(),
+        vec![
+            count("missing_api_key", None, 1),
+            count("model_not_allowed", Some("cheap"), 1),
+            count("moderation", Some("alice"), 1),
+            count("rate_limit", Some("cheap"), 1),
+            count("unknown_api_key", None, 1),
+        ]
+    );
+
+    // The counts name the keys, so only admin keys read them
+    let scrape = |key: Option<&str>| {
+        let mut request = Request::get("/metrics");
+        if let Some(key) = key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    assert_eq!(
+        scrape(None).await.unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        scrape(Some("alice-key")).await.unwrap().status(),
+        StatusCode::FORBIDDEN
+    );
+    let response = scrape(Some("ops-key")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body = String::from_utf8(body.to_vec()).unwrap();
+    assert!(
+        body.contains("jarvis_blocked_requests_total{rule=\"moderation\",principal=\"alice\"} 1")
+    );
+}
@@ -4,17 +4,19 @@ use axum::{
     http::{Request, StatusCode},
 };
 use jarvis_rust::{
-    config::{Config, LlmConfig, LogsConfig, ServerConfig},
+    agent::Agent,
+    config::{Config, EmptyResponseConfig, LlmConfig, LogsConfig, ServerConfig},
     history::HistoryStorage,
-    server::handlers::{AppState, inference},
+    server::handlers::{AppState, health, inference},
 };
-use serde_json::json;
-use std::sync::Arc;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
 use tempfile::TempDir;
 use tokio::sync::Mutex;
 use tower::ServiceExt; // for `oneshot`
 
 mod common;
+use common::{MockLlmClient, create_mock_chat_response};
 
 async fn create_test_app() -> (Router, TempDir) {
     let temp_dir = TempDir::new().unwrap();
@@ -31,6 +33,9 @@ async fn create_test_app() -> (Router, TempDir) {
             host: "127.0.0.1".to_string(),
             port: 8080,
             database_path: db_path.to_string_lossy().to_string(),
+            data_dir: "data".to_string(),
+            api_keys: Vec::new(),
+            database: Default::default(),
             logs: LogsConfig {
                 level: "debug".to_string(),
             },
@@ -41,8 +46,48 @@ async fn create_test_app() -> (Router, TempDir) {
             api_key: "test-key".to_string(),
             model: "gpt-4".to_string(),
             system_prompt: Some("Test system prompt".to_string()),
+            max_continuations: 2,
+            max_context_tokens: None,
+            retry: Default::default(),
+            pacing: Default::default(),
+            request_timeout: 300,
+            connect_timeout: 10,
+            proxy: None,
+            context_fallback: Default::default(),
+            sampling: Default::default(),
+            empty_response: EmptyResponseConfig::default(),
+            structured_output: Default::default(),
+            routing: Default::default(),
+            retroactive_system_prompt: true,
+            ollama: Default::default(),
+            azure: Default::default(),
+            openrouter: Default::default(),
+            reasoning: Default::default(),
+            replay: Default::default(),
+            fallbacks: Vec::new(),
         },
         mcp_servers: vec![],
+        tools: Default::default(),
+        feeds: Vec::new(),
+        schedules: Vec::new(),
+        examples: Vec::new(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        notifications: Default::default(),
+        knowledge: Default::default(),
+        cache: Default::default(),
+        agent: Default::default(),
+        moderation: Default::default(),
+        usage: Default::default(),
+        history: Default::default(),
+        canary: Default::default(),
+        chaos: Default::default(),
+        features: Default::default(),
+        updates: Default::default(),
+        strict: false,
+        include: Vec::new(),
+        auth_presets: Default::default(),
     };
 
     // Create mock agent - this is a simplified version since we can't easily mock the real agent
@@ -53,6 +98,27 @@ async fn create_test_app() -> (Router, TempDir) {
     let app_state = AppState {
         history: Arc::new(history),
         agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
     };
 
     let app = Router::new()
@@ -378,3 +444,132 @@ async fn test_session_id_generation() {
     let another_id = Uuid::new_v4().to_string();
     assert_ne!(generated_id, another_id);
 }
+
+async fn degraded_state(mock_llm: MockLlmClient) -> AppState {
+    AppState {
+        history: Arc::new(
+            HistoryStorage::new("/invalid/path/to/database.db")
+                .await
+                .unwrap(),
+        ),
+        agent: Arc::new(Mutex::new(Agent::new_for_testing(
+            Box::new(mock_llm),
+            HashMap::new(),
+            HashMap::new(),
+            Vec::new(),
+        ))),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    }
+}
+
+async fn json_body(response: axum::response::Response) -> Value {
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_health_reports_storage_status() {
+    let (_, temp_dir) = create_test_app().await;
+    let healthy = HistoryStorage::new(&temp_dir.path().join("health.db").to_string_lossy())
+        .await
+        .unwrap();
+    let mut state = degraded_state(MockLlmClient::new()).await;
+    let degraded = state.history.clone();
+    state.history = Arc::new(healthy);
+    let app = Router::new()
+        .route("/health", axum::routing::get(health))
+        .with_state(state.clone());
+
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        json_body(response).await,
+        json!({"status": "ok", "storage": "ok", "buffered_messages": 0})
+    );
+
+    state.history = degraded;
+    let app = Router::new()
+        .route("/health", axum::routing::get(health))
+        .with_state(state);
+    let response = app
+        .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        json_body(response).await,
+        json!({"status": "ok", "storage": "degraded", "buffered_messages": 0})
+    );
+}
+
+#[tokio::test]
+async fn test_inference_flags_degraded_storage() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let state = degraded_state(mock_llm).await;
+    let history = state.history.clone();
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header("content-type", "application/json")
+                .body(Body::from(
+                    json!({"session_id": "s1", "input": "Hi"}).to_string(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["output"], "Hello!");
+    assert_eq!(body["storage"], "degraded");
+    assert_eq!(history.buffered(), 2);
+}
+
+#[tokio::test]
+async fn test_server_stops_on_shutdown_signal() {
+    let mut config = common::test_utils::create_test_config();
+    config.server.port = 0;
+
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(jarvis_rust::server::run_until(config, async move {
+        stopped.await.ok();
+    }));
+    stop.send(()).unwrap();
+
+    let result = tokio::time::timeout(std::time::Duration::from_secs(10), server)
+        .await
+        .expect("server did not stop")
+        .unwrap();
+    assert!(result.is_ok(), "{result:?}");
+}
@@ -0,0 +1,239 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Method, Request, StatusCode, header},
+    routing::{get, post},
+};
+use jarvis_rust::{
+    agent::Agent,
+    config::ApiKeyConfig,
+    history::{HistoryStorage, Message},
+    server::handlers::{AppState, inference, session_metadata, session_transcript},
+    sessions::SessionStore,
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn key(name: &str, admin: bool) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: Vec::new(),
+        default_model: None,
+        requests_per_minute: None,
+        admin,
+    }
+}
+
+async fn app() -> (Router, Arc<SessionStore>, Arc<HistoryStorage>) {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..5 {
+        mock_llm.add_response(create_mock_chat_response("Hello!"));
+    }
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let sessions = Arc::new(SessionStore::new(":memory:").await.unwrap());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(vec![
+            key("alice", false),
+            key("bob", false),
+            key("ops", true),
+        ]),
+        usage: None,
+        timeline: None,
+        sessions: Some(sessions.clone()),
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let router = Router::new()
+        .route("/", post(inference))
+        .route("/sessions/:session_id/transcript", get(session_transcript))
+        .route("/sessions/:session_id/metadata", get(session_metadata))
+        .with_state(state);
+    (router, sessions, history)
+}
+
+async fn send(
+    app: &Router,
+    method: Method,
+    uri: &str,
+    key: &str,
+    body: Option<Value>,
+) -> StatusCode {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {key}-key"))
+        .header(header::CONTENT_TYPE, "application/json");
+    let body = body.map_or_else(Body::empty, |body| Body::from(body.to_string()));
+    app.clone()
+        .oneshot(request.body(body).unwrap())
+        .await
+        .unwrap()
+        .status()
+}
+
+async fn ask(app: &Router, key: &str, body: Value) -> StatusCode {
+    send(app, Method::POST, "/", key, Some(body)).await
+}
+
+#[tokio::test]
+async fn test_sessions_belong_to_the_key_that_started_them() {
+    let (app, sessions, _) = app().await;
+    let hi = json!({"session_id": "chat", "input": "Hi"});
+    assert_eq!(ask(&app, "alice", hi.clone()).await, StatusCode::OK);
+
+    let owner = sessions.owner("chat").await.unwrap().unwrap();
+    assert_eq!(owner.api_key.as_deref(), Some("alice"));
+
+    // Others can neither append to nor read the session
+    assert_eq!(ask(&app, "bob", hi.clone()).await, StatusCode::FORBIDDEN);
+    let transcript = "/sessions/chat/transcript";
+    assert_eq!(
+        send(&app, Method::GET, transcript, "bob", None).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        send(&app, Method::GET, "/sessions/chat/metadata", "bob", None).await,
+        StatusCode::FORBIDDEN
+    );
+
+    assert_eq!(ask(&app, "alice", hi).await, StatusCode::OK);
+    assert_eq!(
+        send(&app, Method::GET, transcript, "alice", None).await,
+        StatusCode::OK
+    );
+    // Admin keys may use every session
+    assert_eq!(
+        send(&app, Method::GET, transcript, "ops", None).await,
+        StatusCode::OK
+    );
+    // Without ever taking it over
+    let owner = sessions.owner("chat").await.unwrap().unwrap();
+    assert_eq!(owner.api_key.as_deref(), Some("alice"));
+}
+
+#[tokio::test]
+async fn test_sessions_of_a_user_are_kept_from_other_users() {
+    let (app, sessions, _) = app().await;
+    let as_user = |user: &str| json!({"session_id": "dm", "input": "Hi", "user_id": user});
+    assert_eq!(ask(&app, "alice", as_user("u1")).await, StatusCode::OK);
+    assert_eq!(
+        sessions
+            .owner("dm")
+            .await
+            .unwrap()
+            .unwrap()
+            .user_id
+            .as_deref(),
+        Some("u1")
+    );
+
+    assert_eq!(
+        ask(&app, "alice", as_user("u2")).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(ask(&app, "alice", as_user("u1")).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_reading_a_session_does_not_claim_it() {
+    let (app, sessions, _) = app().await;
+    let metadata = "/sessions/fresh/metadata";
+    assert_eq!(
+        send(&app, Method::GET, metadata, "bob", None).await,
+        StatusCode::OK
+    );
+    assert!(sessions.owner("fresh").await.unwrap().is_none());
+
+    let hi = json!({"session_id": "fresh", "input": "Hi"});
+    assert_eq!(ask(&app, "alice", hi).await, StatusCode::OK);
+    assert_eq!(
+        send(&app, Method::GET, metadata, "bob", None).await,
+        StatusCode::FORBIDDEN
+    );
+}
+
+#[tokio::test]
+async fn test_history_without_an_owner_is_left_to_admins() {
+    let (app, sessions, history) = app().await;
+    history
+        .save(Message::new(
+            "legacy".to_string(),
+            "user".to_string(),
+            "Hi".to_string(),
+        ))
+        .await
+        .unwrap();
+
+    let transcript = "/sessions/legacy/transcript";
+    assert_eq!(
+        send(&app, Method::GET, transcript, "bob", None).await,
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(
+        send(&app, Method::GET, transcript, "ops", None).await,
+        StatusCode::OK
+    );
+    assert!(sessions.owner("legacy").await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn test_session_is_claimed_once() {
+    let sessions = SessionStore::new(":memory:").await.unwrap();
+    assert!(sessions.owner("s1").await.unwrap().is_none());
+
+    let first = sessions.claim("s1", Some("alice"), None).await.unwrap();
+    let second = sessions.claim("s1", Some("bob"), Some("u2")).await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(second.api_key.as_deref(), Some("alice"));
+    assert_eq!(second.user_id, None);
+}
+
+#[tokio::test]
+async fn test_only_admin_keys_set_a_callback_url() {
+    let (app, sessions, _) = app().await;
+    let callback = |session_id: &str| {
+        json!({
+            "session_id": session_id,
+            "input": "Remind me later",
+            "callback_url": "http://169.254.169.254/latest",
+        })
+    };
+
+    assert_eq!(
+        ask(&app, "alice", callback("chat")).await,
+        StatusCode::FORBIDDEN
+    );
+    // The refused request never took the session
+    assert!(sessions.owner("chat").await.unwrap().is_none());
+    assert_eq!(ask(&app, "ops", callback("ops-chat")).await, StatusCode::OK);
+}
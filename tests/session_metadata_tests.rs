@@ -0,0 +1,277 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use jarvis_rust::{
+    agent::Agent,
+    history::HistoryStorage,
+    server::handlers::{AppState, session_metadata, update_session_metadata},
+    sessions::{SessionMetadataUpdate, SessionPromptHook, SessionStore, UpdateOutcome},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn title(title: &str) -> SessionMetadataUpdate {
+    SessionMetadataUpdate {
+        title: Some(Some(title.to_string())),
+        ..Default::default()
+    }
+}
+
+fn updated(outcome: UpdateOutcome) -> i64 {
+    match outcome {
+        UpdateOutcome::Updated(metadata) => metadata.revision,
+        UpdateOutcome::Conflict(current) => panic!("unexpected conflict at {current:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_unedited_sessions_are_at_revision_zero() {
+    let store = SessionStore::new(":memory:").await.unwrap();
+    let metadata = store.get("s1").await.unwrap();
+    assert_eq!(metadata.session_id, "s1");
+    assert_eq!(metadata.revision, 0);
+    assert_eq!(metadata.title, None);
+    assert!(metadata.tags.is_empty());
+    assert_eq!(metadata.model, None);
+    assert!(metadata.tools_enabled);
+    assert_eq!(metadata.updated_at, None);
+}
+
+#[tokio::test]
+async fn test_edits_bump_the_revision_and_keep_other_fields() {
+    let store = SessionStore::new(":memory:").await.unwrap();
+    assert_eq!(
+        updated(
+            store
+                .update("s1", title("Groceries"), Some(0))
+                .await
+                .unwrap()
+        ),
+        1
+    );
+    let update = SessionMetadataUpdate {
+        tags: Some(vec!["home".to_string()]),
+        system_prompt: Some(Some("Be brief".to_string())),
+        ..Default::default()
+    };
+    assert_eq!(
+        updated(store.update("s1", update, Some(1)).await.unwrap()),
+        2
+    );
+
+    let metadata = store.get("s1").await.unwrap();
+    assert_eq!(metadata.title.as_deref(), Some("Groceries"));
+    assert_eq!(metadata.tags, vec!["home"]);
+    assert_eq!(metadata.system_prompt.as_deref(), Some("Be brief"));
+    assert_eq!(metadata.revision, 2);
+    assert!(metadata.updated_at.is_some());
+
+    // `null` clears a field
+    let update: SessionMetadataUpdate = serde_json::from_value(json!({"title": null})).unwrap();
+    assert_eq!(updated(store.update("s1", update, None).await.unwrap()), 3);
+    let metadata = store.get("s1").await.unwrap();
+    assert_eq!(metadata.title, None);
+    assert_eq!(metadata.system_prompt.as_deref(), Some("Be brief"));
+}
+
+#[tokio::test]
+async fn test_stale_revisions_conflict() {
+    let store = SessionStore::new(":memory:").await.unwrap();
+    // The UI and a bot both read revision 0; the bot writes first
+    updated(
+        store
+            .update("s1", title("From bot"), Some(0))
+            .await
+            .unwrap(),
+    );
+
+    match store.update("s1", title("From UI"), Some(0)).await.unwrap() {
+        UpdateOutcome::Conflict(current) => {
+            assert_eq!(current.revision, 1);
+            assert_eq!(current.title.as_deref(), Some("From bot"));
+        }
+        outcome => panic!("expected a conflict, got {outcome:?}"),
+    }
+    assert_eq!(
+        store.get("s1").await.unwrap().title.as_deref(),
+        Some("From bot")
+    );
+
+    // Edits without an expected revision always apply
+    assert_eq!(
+        updated(store.update("s1", title("Forced"), None).await.unwrap()),
+        2
+    );
+}
+
+#[tokio::test]
+async fn test_session_prompt_replaces_the_configured_one() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("Done."));
+    mock_llm.add_response(create_mock_chat_response("Done."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let store = Arc::new(SessionStore::new(":memory:").await.unwrap());
+    let update = SessionMetadataUpdate {
+        system_prompt: Some(Some("You are a pirate".to_string())),
+        ..Default::default()
+    };
+    updated(store.update("pirate", update, None).await.unwrap());
+    agent.add_hook(Arc::new(SessionPromptHook::new(store)));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent.process("pirate", "Hi", &history).await.unwrap();
+    agent.process("plain", "Hi", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests[0].messages[0].role, "system");
+    assert_eq!(requests[0].messages[0].content, "You are a pirate");
+    assert_ne!(requests[1].messages[0].content, "You are a pirate");
+}
+
+async fn app() -> Router {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: Some(Arc::new(SessionStore::new(":memory:").await.unwrap())),
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route(
+            "/sessions/:session_id/metadata",
+            get(session_metadata).put(update_session_metadata),
+        )
+        .with_state(state)
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Option<String>, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let etag = response
+        .headers()
+        .get(header::ETAG)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, etag, serde_json::from_slice(&body).unwrap())
+}
+
+fn put(if_match: Option<&str>, body: Value) -> Request<Body> {
+    let mut request = Request::builder()
+        .method("PUT")
+        .uri("/sessions/s1/metadata")
+        .header(header::CONTENT_TYPE, "application/json");
+    if let Some(if_match) = if_match {
+        request = request.header(header::IF_MATCH, if_match);
+    }
+    request.body(Body::from(body.to_string())).unwrap()
+}
+
+#[tokio::test]
+async fn test_metadata_endpoints_use_if_match() {
+    let app = app().await;
+
+    let get = || {
+        Request::builder()
+            .uri("/sessions/s1/metadata")
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, etag, body) = send(&app, get()).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(etag.as_deref(), Some("\"0\""));
+    assert_eq!(body["revision"], 0);
+
+    let (status, etag, body) = send(
+        &app,
+        put(
+            Some("\"0\""),
+            json!({"title": "Groceries", "tags": ["home"]}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(etag.as_deref(), Some("\"1\""));
+    assert_eq!(body["title"], "Groceries");
+    assert_eq!(body["tags"], json!(["home"]));
+
+    // A second editor still holding revision 0
+    let (status, etag, body) = send(&app, put(Some("\"0\""), json!({"title": "Chores"}))).await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    assert_eq!(etag.as_deref(), Some("\"1\""));
+    assert_eq!(body["title"], "Groceries");
+
+    let (status, _, body) = send(&app, put(Some("*"), json!({"title": "Chores"}))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["revision"], 2);
+
+    let (status, _, body) = send(&app, put(Some("latest"), json!({}))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(body["error"], "Invalid If-Match revision 'latest'");
+
+    let (_, etag, body) = send(&app, get()).await;
+    assert_eq!(etag.as_deref(), Some("\"2\""));
+    assert_eq!(body["title"], "Chores");
+}
+
+#[tokio::test]
+async fn test_metadata_endpoint_honours_if_none_match() {
+    let app = app().await;
+    let get = |if_none_match: &str| {
+        Request::builder()
+            .uri("/sessions/s1/metadata")
+            .header(header::IF_NONE_MATCH, if_none_match)
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(get("\"0\"")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], "\"0\"");
+
+    send(&app, put(None, json!({"title": "Groceries"}))).await;
+    let (status, etag, body) = send(&app, get("\"0\"")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(etag.as_deref(), Some("\"1\""));
+    assert_eq!(body["title"], "Groceries");
+}
@@ -0,0 +1,341 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use jarvis_rust::{
+    agent::{Agent, ProcessOptions, RunOutcome, check_reply, schema_violations},
+    config::{LlmConfig, StructuredOutputConfig},
+    history::HistoryStorage,
+    llm::{
+        ChatCompletionRequest, ChatMessage, GeminiClient, JsonSchemaFormat, LlmClient,
+        OllamaClient, OpenAiClient, ResponseFormat,
+    },
+    server::handlers::{AppState, inference},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn weather_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "city": {"type": "string", "minLength": 1},
+            "celsius": {"type": "number", "minimum": -90, "maximum": 60},
+            "conditions": {"type": "array", "items": {"$ref": "#/$defs/condition"}}
+        },
+        "required": ["city", "celsius"],
+        "additionalProperties": false,
+        "$defs": {
+            "condition": {"enum": ["sunny", "cloudy", "rain"]}
+        }
+    })
+}
+
+fn weather_format() -> ResponseFormat {
+    ResponseFormat::JsonSchema {
+        json_schema: JsonSchemaFormat {
+            name: "weather".to_string(),
+            description: None,
+            schema: weather_schema(),
+            strict: Some(true),
+        },
+    }
+}
+
+#[test]
+fn test_schema_violations_name_their_path() {
+    let schema = weather_schema();
+    assert_eq!(
+        schema_violations(
+            &schema,
+            &json!({"city": "Lisbon", "celsius": 21.5, "conditions": ["sunny"]})
+        ),
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        schema_violations(
+            &schema,
+            &json!({"celsius": "21", "conditions": ["sunny", "snow"], "wind": 3})
+        ),
+        vec![
+            "$: missing required property city".to_string(),
+            "$.celsius: expected number, got string".to_string(),
+            "$.conditions[1]: must be one of [\"sunny\",\"cloudy\",\"rain\"]".to_string(),
+            "$.wind: is not an allowed property".to_string(),
+        ]
+    );
+    assert_eq!(
+        schema_violations(&schema, &json!({"city": "", "celsius": 100})),
+        vec![
+            "$.celsius: must be at most 60".to_string(),
+            "$.city: has 0 characters, fewer than 1".to_string(),
+        ]
+    );
+
+    let either = json!({"anyOf": [{"type": "integer"}, {"type": "null"}]});
+    assert!(schema_violations(&either, &json!(3)).is_empty());
+    assert!(schema_violations(&either, &json!(null)).is_empty());
+    assert_eq!(
+        schema_violations(&either, &json!(3.5)),
+        vec!["$: matches none of the allowed shapes".to_string()]
+    );
+}
+
+#[test]
+fn test_check_reply_unwraps_code_blocks() {
+    let format = weather_format();
+    assert_eq!(
+        check_reply(
+            "```json\n{\"city\": \"Lisbon\", \"celsius\": 21}\n```",
+            &format
+        ),
+        Ok("{\"city\": \"Lisbon\", \"celsius\": 21}")
+    );
+    assert_eq!(
+        check_reply("It is 21 degrees in Lisbon.", &format)
+            .unwrap_err()
+            .len(),
+        1
+    );
+    assert_eq!(
+        check_reply("[1, 2]", &ResponseFormat::JsonObject),
+        Err(vec!["the reply is not a JSON object".to_string()])
+    );
+    assert!(check_reply("{}", &ResponseFormat::JsonObject).is_ok());
+}
+
+fn llm_config(provider: &str) -> LlmConfig {
+    serde_yaml::from_str(&format!(
+        r#"
+provider: "{provider}"
+base_url: "http://localhost:1"
+api_key: "key"
+model: "some-model"
+"#
+    ))
+    .unwrap()
+}
+
+fn request(format: ResponseFormat) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "Weather in Lisbon?".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        response_format: Some(format),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_providers_send_the_response_format() {
+    let openai = OpenAiClient::new(llm_config("openai"))
+        .request_payload(&request(weather_format()))
+        .unwrap();
+    assert_eq!(
+        openai["response_format"],
+        json!({
+            "type": "json_schema",
+            "json_schema": {"name": "weather", "schema": weather_schema(), "strict": true}
+        })
+    );
+
+    let gemini = GeminiClient::new(llm_config("gemini"))
+        .request_payload(&request(weather_format()))
+        .unwrap();
+    assert_eq!(
+        gemini["generationConfig"]["responseMimeType"],
+        "application/json"
+    );
+    // Gemini rejects additionalProperties
+    assert_eq!(
+        gemini["generationConfig"]["responseSchema"]["required"],
+        json!(["city", "celsius"])
+    );
+    assert!(gemini["generationConfig"]["responseSchema"]["additionalProperties"].is_null());
+
+    let ollama = OllamaClient::new(llm_config("ollama"));
+    assert_eq!(
+        ollama.request_payload(&request(weather_format())).unwrap()["format"],
+        weather_schema()
+    );
+    assert_eq!(
+        ollama
+            .request_payload(&request(ResponseFormat::JsonObject))
+            .unwrap()["format"],
+        "json"
+    );
+    assert!(
+        ollama
+            .request_payload(&request(ResponseFormat::Text))
+            .unwrap()
+            .get("format")
+            .is_none()
+    );
+}
+
+fn options(format: ResponseFormat) -> ProcessOptions {
+    ProcessOptions {
+        response_format: Some(format),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_agent_retries_replies_that_break_the_schema() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response(r#"{"city": "Lisbon"}"#));
+    mock_llm.add_response(create_mock_chat_response(
+        "```json\n{\"city\": \"Lisbon\", \"celsius\": 21}\n```",
+    ));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let outcome = agent
+        .process_with_options(
+            "s1",
+            "Weather in Lisbon?",
+            &history,
+            options(weather_format()),
+        )
+        .await
+        .unwrap();
+
+    let RunOutcome::Reply(reply) = outcome else {
+        panic!("run paused");
+    };
+    assert_eq!(reply.output, r#"{"city": "Lisbon", "celsius": 21}"#);
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].response_format, Some(weather_format()));
+    assert_eq!(requests[1].response_format, Some(weather_format()));
+    let correction = &requests[1].messages.last().unwrap().content;
+    assert!(
+        correction
+            .text()
+            .contains("$: missing required property celsius")
+    );
+}
+
+#[tokio::test]
+async fn test_agent_fails_once_retries_run_out() {
+    let mock_llm = MockLlmClient::new();
+    for _ in 0..2 {
+        mock_llm.add_response(create_mock_chat_response("Sunny, 21 degrees."));
+    }
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_structured_output(StructuredOutputConfig { retries: 1 });
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let error = agent
+        .process_with_options(
+            "s1",
+            "Weather in Lisbon?",
+            &history,
+            options(ResponseFormat::JsonObject),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    assert!(
+        error
+            .to_string()
+            .contains("Reply does not match the requested response_format")
+    );
+    assert_eq!(requests.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_inference_returns_json_unformatted() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response(
+        r#"{"city": "Lisbon", "celsius": 21, "conditions": ["sunny"]}"#,
+    ));
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(Vec::new()),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+
+    let body = json!({
+        "input": "Weather in Lisbon?",
+        "format": "plain",
+        "max_chunk_size": 10,
+        "response_format": {
+            "type": "json_schema",
+            "json_schema": {"name": "weather", "schema": weather_schema()}
+        }
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let output: Value = serde_json::from_str(body["output"].as_str().unwrap()).unwrap();
+    assert_eq!(output["conditions"], json!(["sunny"]));
+    assert!(body.get("chunks").is_none());
+}
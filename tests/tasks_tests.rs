@@ -0,0 +1,299 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use chrono::{TimeZone, Utc};
+use jarvis_rust::{
+    agent::Agent,
+    config::ApiKeyConfig,
+    history::HistoryStorage,
+    mcp::{McpContent, McpToolCallResponse},
+    server::handlers::{AppState, list_tasks},
+    tasks::{NewTask, TaskStatus, TaskStore},
+    tools::{
+        NativeTool, ToolContext,
+        datetime::DateTimeSettings,
+        tasks::{CompleteTaskTool, CreateTaskTool, ListTasksTool},
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::MockLlmClient;
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+fn new_task(title: &str, due_day: Option<u32>) -> NewTask {
+    NewTask {
+        title: title.to_string(),
+        notes: None,
+        due: due_day.map(|day| Utc.with_ymd_and_hms(2026, 10, day, 9, 0, 0).unwrap()),
+        session_id: "s1".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_store_lists_open_tasks_by_due_date() {
+    let store = TaskStore::new(":memory:").await.unwrap();
+    let undated = store
+        .create(new_task("Renew passport", None))
+        .await
+        .unwrap();
+    let later = store
+        .create(new_task("Book flights", Some(20)))
+        .await
+        .unwrap();
+    let sooner = store.create(new_task("Pay rent", Some(16))).await.unwrap();
+
+    let titles = |tasks: Vec<jarvis_rust::tasks::Task>| {
+        tasks.into_iter().map(|t| t.title).collect::<Vec<_>>()
+    };
+    assert_eq!(
+        titles(store.list(Some(TaskStatus::Open)).await.unwrap()),
+        vec!["Pay rent", "Book flights", "Renew passport"]
+    );
+
+    let done = store.complete(sooner.id).await.unwrap().unwrap();
+    assert_eq!(done.status, TaskStatus::Done);
+    assert!(done.completed_at.is_some());
+    assert!(store.complete(999).await.unwrap().is_none());
+
+    assert_eq!(
+        titles(store.list(Some(TaskStatus::Open)).await.unwrap()),
+        vec!["Book flights", "Renew passport"]
+    );
+    assert_eq!(
+        titles(store.list(Some(TaskStatus::Done)).await.unwrap()),
+        vec!["Pay rent"]
+    );
+    assert_eq!(store.list(None).await.unwrap().len(), 3);
+    assert_eq!(
+        store.get(undated.id).await.unwrap().unwrap().title,
+        "Renew passport"
+    );
+    assert_eq!(store.get(later.id).await.unwrap().unwrap().session_id, "s1");
+}
+
+#[tokio::test]
+async fn test_task_tools() {
+    let store = Arc::new(TaskStore::new(":memory:").await.unwrap());
+    let settings = DateTimeSettings::new(chrono_tz::Europe::Berlin);
+    let create = CreateTaskTool::new(store.clone(), settings);
+    let list = ListTasksTool::new(store.clone(), settings);
+    let complete = CompleteTaskTool::new(store.clone(), settings);
+    let ctx = ToolContext::new("s1", None);
+
+    let response = create
+        .call(
+            args(json!({"title": "Pay rent", "due": "2026-10-16 09:00", "notes": "Transfer to landlord"})),
+            &ctx,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&response),
+        "Created #1 [open] Pay rent (due Fri 2026-10-16 09:00) — Transfer to landlord"
+    );
+    // Stored in UTC
+    assert_eq!(
+        store.get(1).await.unwrap().unwrap().due,
+        Some(Utc.with_ymd_and_hms(2026, 10, 16, 7, 0, 0).unwrap())
+    );
+    create
+        .call(args(json!({"title": "Call mum"})), &ctx)
+        .await
+        .unwrap();
+    assert!(
+        create
+            .call(args(json!({"title": " "})), &ctx)
+            .await
+            .is_err()
+    );
+
+    let response = complete.call(args(json!({"id": 1})), &ctx).await.unwrap();
+    assert!(text(&response).starts_with("Completed #1 [done] Pay rent"));
+    assert!(complete.call(args(json!({"id": 42})), &ctx).await.is_err());
+
+    let response = list.call(args(json!({})), &ctx).await.unwrap();
+    assert_eq!(text(&response), "#2 [open] Call mum");
+    let response = list
+        .call(args(json!({"status": "all"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response).lines().count(), 2);
+    assert!(
+        list.call(args(json!({"status": "someday"})), &ctx)
+            .await
+            .is_err()
+    );
+}
+
+async fn tasks_app(tasks: Option<Arc<TaskStore>>) -> Router {
+    tasks_app_with_keys(tasks, Vec::new()).await
+}
+
+async fn tasks_app_with_keys(tasks: Option<Arc<TaskStore>>, keys: Vec<ApiKeyConfig>) -> Router {
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks,
+        runs: Default::default(),
+        api_keys: Arc::new(keys),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/tasks", get(list_tasks))
+        .with_state(state)
+}
+
+async fn get_json(app: Router, uri: &str) -> (StatusCode, Value) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+#[tokio::test]
+async fn test_tasks_endpoint() {
+    let store = Arc::new(TaskStore::new(":memory:").await.unwrap());
+    store.create(new_task("Pay rent", Some(16))).await.unwrap();
+    let done = store.create(new_task("Call mum", None)).await.unwrap();
+    store.complete(done.id).await.unwrap();
+    let app = tasks_app(Some(store)).await;
+
+    let (status, body) = get_json(app.clone(), "/tasks").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 1);
+    assert_eq!(body[0]["title"], "Pay rent");
+    assert_eq!(body[0]["status"], "open");
+    assert_eq!(body[0]["due"], "2026-10-16T09:00:00Z");
+
+    let (_, body) = get_json(app.clone(), "/tasks?status=done").await;
+    assert_eq!(body[0]["title"], "Call mum");
+    let (_, body) = get_json(app.clone(), "/tasks?status=all").await;
+    assert_eq!(body.as_array().unwrap().len(), 2);
+
+    let (status, _) = get_json(app, "/tasks?status=someday").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = get_json(tasks_app(None).await, "/tasks").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+}
+
+#[tokio::test]
+async fn test_tasks_endpoint_answers_unchanged_lists_with_304() {
+    let store = Arc::new(TaskStore::new(":memory:").await.unwrap());
+    let task = store.create(new_task("Pay rent", Some(16))).await.unwrap();
+    let app = tasks_app(Some(store.clone())).await;
+    let get_with = |etag: Option<&str>| {
+        let mut request = Request::builder().uri("/tasks");
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    let response = get_with(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        get_with(Some(&etag)).await.unwrap().status(),
+        StatusCode::NOT_MODIFIED
+    );
+    assert_eq!(
+        get_with(Some("*")).await.unwrap().status(),
+        StatusCode::NOT_MODIFIED
+    );
+
+    store.complete(task.id).await.unwrap();
+    let response = get_with(Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    assert_eq!(
+        store.fingerprint(Some(TaskStatus::Open)).await.unwrap(),
+        TaskStore::new(":memory:")
+            .await
+            .unwrap()
+            .fingerprint(Some(TaskStatus::Open))
+            .await
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_tasks_endpoint_is_admin_only() {
+    let key = |name: &str, admin: bool| ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: Vec::new(),
+        default_model: None,
+        requests_per_minute: None,
+        admin,
+    };
+    let store = Arc::new(TaskStore::new(":memory:").await.unwrap());
+    let app = tasks_app_with_keys(Some(store), vec![key("alice", false), key("ops", true)]).await;
+    let get_as = |key: Option<&str>| {
+        let mut request = Request::builder().uri("/tasks");
+        if let Some(key) = key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {key}-key"));
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+
+    assert_eq!(
+        get_as(None).await.unwrap().status(),
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        get_as(Some("alice")).await.unwrap().status(),
+        StatusCode::FORBIDDEN
+    );
+    assert_eq!(get_as(Some("ops")).await.unwrap().status(), StatusCode::OK);
+}
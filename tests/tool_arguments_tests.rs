@@ -0,0 +1,507 @@
+use jarvis_rust::{
+    agent::{
+        Agent, apply_secret_arguments, apply_static_arguments, hide_injected_arguments,
+        redact_secrets, transform_arguments,
+    },
+    config::{ArgumentTransform, ArgumentType, SecretSource, ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{Function, Tool},
+    mcp::{McpClient, McpContent, McpToolCallRequest, McpToolCallResponse},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+fn orders_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "list_orders".to_string(),
+            description: "Recent orders of the account".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "account_id": {"type": "string"},
+                    "region": {"type": "string"},
+                    "limit": {"type": "integer"},
+                },
+                "required": ["account_id", "limit"],
+            }),
+        },
+    }
+}
+
+fn static_settings() -> ToolSettings {
+    ToolSettings {
+        arguments: HashMap::from([
+            ("account_id".to_string(), json!("acct-42")),
+            ("region".to_string(), json!("eu-west-1")),
+        ]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_static_arguments_hidden_from_schema() {
+    let mut tool = orders_tool();
+    hide_injected_arguments(&mut tool, &static_settings());
+    assert_eq!(
+        tool.function.parameters,
+        json!({
+            "type": "object",
+            "properties": {"limit": {"type": "integer"}},
+            "required": ["limit"],
+        })
+    );
+
+    // Tools without static arguments are left alone
+    let mut tool = orders_tool();
+    hide_injected_arguments(&mut tool, &ToolSettings::default());
+    assert_eq!(tool.function.parameters, orders_tool().function.parameters);
+}
+
+#[test]
+fn test_static_arguments_replace_llm_values() {
+    let mut call = McpToolCallRequest {
+        name: "list_orders".to_string(),
+        arguments: HashMap::from([
+            ("account_id".to_string(), json!("someone-else")),
+            ("limit".to_string(), json!(5)),
+        ]),
+    };
+    apply_static_arguments(&mut call, &static_settings());
+    assert_eq!(
+        call.arguments,
+        HashMap::from([
+            ("account_id".to_string(), json!("acct-42")),
+            ("region".to_string(), json!("eu-west-1")),
+            ("limit".to_string(), json!(5)),
+        ])
+    );
+}
+
+#[test]
+fn test_static_arguments_config() {
+    let settings: ToolSettings =
+        serde_yaml::from_str("arguments:\n  account_id: acct-42\n  retries: 3").unwrap();
+    assert_eq!(settings.arguments["account_id"], json!("acct-42"));
+    assert_eq!(settings.arguments["retries"], json!(3));
+    assert!(ToolSettings::default().arguments.is_empty());
+}
+
+#[tokio::test]
+async fn test_agent_injects_static_arguments() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "list_orders",
+        r#"{"limit": 5, "account_id": "guessed"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("You have no recent orders."));
+
+    let mock_mcp = MockMcpClient::new();
+    let calls = mock_mcp.calls.clone();
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("shop".to_string(), Box::new(mock_mcp));
+    let tool_to_client_map = HashMap::from([("list_orders".to_string(), "shop".to_string())]);
+    let mut tools_config = ToolsConfig::default();
+    tools_config
+        .settings
+        .insert("list_orders".to_string(), static_settings());
+
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![orders_tool()],
+    )
+    .with_tools_config(tools_config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Any orders?", &history)
+        .await
+        .unwrap();
+
+    // The LLM never sees the static arguments
+    let offered = requests.lock().unwrap()[0].tools[0]
+        .function
+        .parameters
+        .clone();
+    assert_eq!(offered["properties"], json!({"limit": {"type": "integer"}}));
+
+    let calls = calls.lock().unwrap().clone();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].arguments["account_id"], json!("acct-42"));
+    assert_eq!(calls[0].arguments["region"], json!("eu-west-1"));
+    assert_eq!(calls[0].arguments["limit"], json!(5));
+
+    // The transcript keeps the call as the LLM made it
+    let messages = history.list("session").await.unwrap();
+    let metadata = messages.last().unwrap().metadata.clone().unwrap();
+    let recorded: &Value = &metadata["tool_calls"][0]["arguments"];
+    assert_eq!(recorded, &json!({"limit": 5, "account_id": "guessed"}));
+}
+
+fn weather_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "weather".to_string(),
+            description: "Forecast".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "city": {"type": "string"},
+                    "api_key": {"type": "string"},
+                },
+                "required": ["city", "api_key"],
+            }),
+        },
+    }
+}
+
+fn secret_settings(source: SecretSource) -> ToolSettings {
+    ToolSettings {
+        secrets: HashMap::from([("api_key".to_string(), source)]),
+        ..Default::default()
+    }
+}
+
+/// Agent with the `weather` tool served by `mock_mcp`
+fn weather_agent(
+    mock_llm: MockLlmClient,
+    mock_mcp: MockMcpClient,
+    settings: ToolSettings,
+) -> Agent {
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("weather".to_string(), Box::new(mock_mcp));
+    let tool_to_client_map = HashMap::from([("weather".to_string(), "weather".to_string())]);
+    let mut tools_config = ToolsConfig::default();
+    tools_config
+        .settings
+        .insert("weather".to_string(), settings);
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![weather_tool()],
+    )
+    .with_tools_config(tools_config)
+}
+
+#[test]
+fn test_secret_config() {
+    let settings: ToolSettings = serde_yaml::from_str(
+        "secrets:\n  api_key:\n    env: WEATHER_API_KEY\n  token:\n    value: s3cr3t",
+    )
+    .unwrap();
+    assert_eq!(
+        settings.secrets["api_key"],
+        SecretSource::Env {
+            env: "WEATHER_API_KEY".to_string(),
+        }
+    );
+    assert_eq!(
+        settings.secrets["token"],
+        SecretSource::Value {
+            value: "s3cr3t".to_string(),
+        }
+    );
+    // Values never show up in debug output
+    let debug = format!("{settings:?}");
+    assert!(debug.contains("WEATHER_API_KEY"));
+    assert!(!debug.contains("s3cr3t"));
+}
+
+#[test]
+fn test_secret_sources_resolve() {
+    assert_eq!(
+        SecretSource::Value {
+            value: "abc".to_string(),
+        }
+        .resolve()
+        .unwrap(),
+        "abc"
+    );
+    let name = "JARVIS_TEST_SECRET_ARGUMENT";
+    // SAFETY: no other test reads or writes this variable
+    unsafe { std::env::set_var(name, "from-env") };
+    assert_eq!(
+        SecretSource::Env {
+            env: name.to_string(),
+        }
+        .resolve()
+        .unwrap(),
+        "from-env"
+    );
+    assert!(
+        SecretSource::Env {
+            env: "JARVIS_TEST_SECRET_UNSET".to_string(),
+        }
+        .resolve()
+        .is_err()
+    );
+}
+
+#[test]
+fn test_secret_arguments_hidden_and_redacted() {
+    let settings = secret_settings(SecretSource::Value {
+        value: "k-123".to_string(),
+    });
+    let mut tool = weather_tool();
+    hide_injected_arguments(&mut tool, &settings);
+    assert_eq!(
+        tool.function.parameters,
+        json!({
+            "type": "object",
+            "properties": {"city": {"type": "string"}},
+            "required": ["city"],
+        })
+    );
+
+    let mut call = McpToolCallRequest {
+        name: "weather".to_string(),
+        arguments: HashMap::from([("api_key".to_string(), json!("guessed"))]),
+    };
+    let secrets = apply_secret_arguments(&mut call, &settings).unwrap();
+    assert_eq!(call.arguments["api_key"], json!("k-123"));
+
+    let mut response = McpToolCallResponse {
+        content: vec![McpContent::Text {
+            text: "Used key k-123: sunny".to_string(),
+        }],
+        is_error: false,
+    };
+    redact_secrets(&mut response, &secrets);
+    let McpContent::Text { text } = &response.content[0] else {
+        panic!("expected text content");
+    };
+    assert_eq!(text, "Used key [redacted]: sunny");
+}
+
+#[tokio::test]
+async fn test_agent_injects_secrets_only_into_the_call() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "weather",
+        r#"{"city": "Lisbon"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("Sunny in Lisbon."));
+    let mock_mcp = MockMcpClient::new().with_tool_response(
+        "weather".to_string(),
+        create_mock_tool_response("Sunny (key k-123)"),
+    );
+    let calls = mock_mcp.calls.clone();
+    let mut agent = weather_agent(
+        mock_llm,
+        mock_mcp,
+        secret_settings(SecretSource::Value {
+            value: "k-123".to_string(),
+        }),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Weather in Lisbon?", &history)
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap().clone();
+    assert_eq!(calls[0].arguments["api_key"], json!("k-123"));
+    assert_eq!(calls[0].arguments["city"], json!("Lisbon"));
+
+    // Neither the LLM nor the history sees the secret
+    let requests = requests.lock().unwrap().clone();
+    assert!(!serde_json::to_string(&requests).unwrap().contains("k-123"));
+    let messages = history.list("session").await.unwrap();
+    assert!(!serde_json::to_string(&messages).unwrap().contains("k-123"));
+    assert_eq!(
+        requests[1].messages.last().unwrap().content,
+        "Sunny (key [redacted])"
+    );
+}
+
+#[tokio::test]
+async fn test_unresolved_secret_fails_the_call() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "weather",
+        r#"{"city": "Lisbon"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("The forecast is unavailable."));
+    let mock_mcp = MockMcpClient::new();
+    let calls = mock_mcp.calls.clone();
+    let mut agent = weather_agent(
+        mock_llm,
+        mock_mcp,
+        secret_settings(SecretSource::Env {
+            env: "JARVIS_TEST_SECRET_MISSING".to_string(),
+        }),
+    );
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Weather in Lisbon?", &history)
+        .await
+        .unwrap();
+
+    assert!(calls.lock().unwrap().is_empty());
+    let result = requests.lock().unwrap()[1]
+        .messages
+        .last()
+        .unwrap()
+        .content
+        .clone();
+    assert!(
+        result
+            .text()
+            .contains("JARVIS_TEST_SECRET_MISSING is not set")
+    );
+}
+
+fn transformed(arguments: Value, transform: &ArgumentTransform) -> Value {
+    let mut call = McpToolCallRequest {
+        name: "thermostat".to_string(),
+        arguments: serde_json::from_value(arguments).unwrap(),
+    };
+    transform_arguments(&mut call, transform);
+    serde_json::to_value(call.arguments).unwrap()
+}
+
+fn coerced(value: Value, argument_type: ArgumentType) -> Value {
+    let transform = ArgumentTransform {
+        coerce: HashMap::from([("value".to_string(), argument_type)]),
+        ..Default::default()
+    };
+    transformed(json!({ "value": value }), &transform)["value"].clone()
+}
+
+#[test]
+fn test_transform_config() {
+    let settings: ToolSettings = serde_yaml::from_str(
+        "transform:\n  rename: {temp: temperature}\n  drop: [debug]\n  coerce: {temperature: number}\n  set: {unit: celsius}",
+    )
+    .unwrap();
+    assert_eq!(
+        settings.transform,
+        ArgumentTransform {
+            rename: HashMap::from([("temp".to_string(), "temperature".to_string())]),
+            drop: vec!["debug".to_string()],
+            coerce: HashMap::from([("temperature".to_string(), ArgumentType::Number)]),
+            set: HashMap::from([("unit".to_string(), json!("celsius"))]),
+        }
+    );
+    assert!(ToolSettings::default().transform.is_empty());
+}
+
+#[test]
+fn test_transform_steps_run_in_order() {
+    let transform = ArgumentTransform {
+        rename: HashMap::from([("temp".to_string(), "temperature".to_string())]),
+        drop: vec!["debug".to_string()],
+        // Refers to the renamed argument
+        coerce: HashMap::from([("temperature".to_string(), ArgumentType::Number)]),
+        set: HashMap::from([("unit".to_string(), json!("celsius"))]),
+    };
+    assert_eq!(
+        transformed(
+            json!({"temp": "21.5", "debug": true, "room": "kitchen", "unit": "kelvin"}),
+            &transform
+        ),
+        json!({"temperature": 21.5, "room": "kitchen", "unit": "celsius"})
+    );
+    // Missing arguments are left missing
+    assert_eq!(
+        transformed(json!({"room": "kitchen"}), &transform),
+        json!({"room": "kitchen", "unit": "celsius"})
+    );
+}
+
+#[test]
+fn test_coercions() {
+    assert_eq!(coerced(json!(42), ArgumentType::String), json!("42"));
+    assert_eq!(
+        coerced(json!({"a": 1}), ArgumentType::String),
+        json!(r#"{"a":1}"#)
+    );
+    assert_eq!(coerced(json!(" 7 "), ArgumentType::Integer), json!(7));
+    assert_eq!(coerced(json!(3.0), ArgumentType::Integer), json!(3));
+    assert_eq!(coerced(json!("2.5"), ArgumentType::Number), json!(2.5));
+    assert_eq!(coerced(json!("Yes"), ArgumentType::Boolean), json!(true));
+    assert_eq!(coerced(json!(0), ArgumentType::Boolean), json!(false));
+    assert_eq!(coerced(json!("a"), ArgumentType::Array), json!(["a"]));
+    assert_eq!(coerced(json!("[1, 2]"), ArgumentType::Array), json!([1, 2]));
+    assert_eq!(coerced(json!(1), ArgumentType::Array), json!([1]));
+    assert_eq!(
+        coerced(json!(r#"{"on": true}"#), ArgumentType::Object),
+        json!({"on": true})
+    );
+
+    // Values that can't be converted are passed through
+    assert_eq!(coerced(json!("warm"), ArgumentType::Number), json!("warm"));
+    assert_eq!(coerced(json!(2.5), ArgumentType::Integer), json!(2.5));
+    assert_eq!(
+        coerced(json!("maybe"), ArgumentType::Boolean),
+        json!("maybe")
+    );
+    assert_eq!(coerced(json!("text"), ArgumentType::Object), json!("text"));
+}
+
+#[tokio::test]
+async fn test_agent_transforms_arguments_before_the_call() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "list_orders",
+        r#"{"max": "5", "verbose": true}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("No orders."));
+    let mock_mcp = MockMcpClient::new();
+    let calls = mock_mcp.calls.clone();
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("shop".to_string(), Box::new(mock_mcp));
+    let tool_to_client_map = HashMap::from([("list_orders".to_string(), "shop".to_string())]);
+    let mut tools_config = ToolsConfig::default();
+    tools_config.settings.insert(
+        "list_orders".to_string(),
+        ToolSettings {
+            transform: ArgumentTransform {
+                rename: HashMap::from([("max".to_string(), "limit".to_string())]),
+                drop: vec!["verbose".to_string()],
+                coerce: HashMap::from([("limit".to_string(), ArgumentType::Integer)]),
+                ..Default::default()
+            },
+            ..static_settings()
+        },
+    );
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![orders_tool()],
+    )
+    .with_tools_config(tools_config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Any orders?", &history)
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap().clone();
+    assert_eq!(
+        serde_json::to_value(&calls[0].arguments).unwrap(),
+        json!({"limit": 5, "account_id": "acct-42", "region": "eu-west-1"})
+    );
+    // The transcript keeps the call as the LLM made it
+    let messages = history.list("session").await.unwrap();
+    let metadata = messages.last().unwrap().metadata.clone().unwrap();
+    assert_eq!(
+        metadata["tool_calls"][0]["arguments"],
+        json!({"max": "5", "verbose": true})
+    );
+}
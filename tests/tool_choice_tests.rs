@@ -0,0 +1,235 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use jarvis_rust::{
+    Result,
+    agent::{Agent, ProcessOptions},
+    config::LlmConfig,
+    history::HistoryStorage,
+    llm::{
+        ChatCompletionRequest, ChatMessage, Function, LlmClient, OpenAiClient, Tool, ToolChoice,
+        ToolChoiceMode,
+    },
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, inference},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+fn agent(mock_llm: MockLlmClient) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool));
+    agent
+}
+
+fn request(tools: Vec<Tool>, tool_choice: ToolChoice) -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: "What time is it?".into(),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        tools,
+        tool_choice: Some(tool_choice),
+        ..Default::default()
+    }
+}
+
+fn clock() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            parameters: json!({"type": "object"}),
+        },
+    }
+}
+
+#[test]
+fn test_tool_choice_formats() {
+    let choices: Vec<ToolChoice> = serde_json::from_value(json!([
+        "auto",
+        "none",
+        "required",
+        {"type": "function", "function": {"name": "clock"}}
+    ]))
+    .unwrap();
+    assert_eq!(
+        choices,
+        vec![
+            ToolChoice::Mode(ToolChoiceMode::Auto),
+            ToolChoice::Mode(ToolChoiceMode::None),
+            ToolChoice::Mode(ToolChoiceMode::Required),
+            ToolChoice::function("clock"),
+        ]
+    );
+    assert_eq!(choices[3].function_name(), Some("clock"));
+    assert_eq!(
+        serde_json::to_value(&choices[3]).unwrap(),
+        json!({"type": "function", "function": {"name": "clock"}})
+    );
+    assert!(serde_json::from_value::<ToolChoice>(json!("sometimes")).is_err());
+}
+
+#[test]
+fn test_openai_requests_carry_tool_choice() {
+    let config: LlmConfig = serde_yaml::from_str(
+        r#"
+provider: "openai"
+base_url: "http://localhost:1"
+api_key: "key"
+model: "gpt-4o"
+"#,
+    )
+    .unwrap();
+    let client = OpenAiClient::new(config);
+
+    let payload = client
+        .request_payload(&request(vec![clock()], ToolChoice::function("clock")))
+        .unwrap();
+    assert_eq!(
+        payload["tool_choice"],
+        json!({"type": "function", "function": {"name": "clock"}})
+    );
+    let payload = client
+        .request_payload(&request(
+            vec![clock()],
+            ToolChoice::Mode(ToolChoiceMode::Required),
+        ))
+        .unwrap();
+    assert_eq!(payload["tool_choice"], json!("required"));
+    // OpenAI rejects a tool_choice without tools
+    let payload = client
+        .request_payload(&request(Vec::new(), ToolChoice::Mode(ToolChoiceMode::None)))
+        .unwrap();
+    assert_eq!(payload.get("tool_choice"), None);
+}
+
+#[tokio::test]
+async fn test_tool_is_forced_on_the_first_call_only() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = agent(mock_llm);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process_with_options(
+            "s1",
+            "Good morning",
+            &history,
+            ProcessOptions {
+                tool_choice: Some(ToolChoice::function("clock")),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let choices: Vec<Option<ToolChoice>> = requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.tool_choice.clone())
+        .collect();
+    assert_eq!(choices, vec![Some(ToolChoice::function("clock")), None]);
+}
+
+#[tokio::test]
+async fn test_inference_rejects_unknown_forced_tool() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent(mock_llm))),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(Vec::new()),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+
+    let body = json!({
+        "session_id": "s1",
+        "input": "Book a flight",
+        "tool_choice": {"type": "function", "function": {"name": "book_flight"}}
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(requests.lock().unwrap().is_empty());
+    assert!(history.list("s1").await.unwrap().is_empty());
+}
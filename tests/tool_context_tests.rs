@@ -0,0 +1,152 @@
+use jarvis_rust::{
+    agent::{Agent, CONTEXT_ARGUMENT, build_tool_context},
+    config::{ToolContextMode, ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{ChatMessage, Function, Tool},
+    mcp::McpClient,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+};
+
+fn message(role: &str, content: &str) -> ChatMessage {
+    ChatMessage {
+        role: role.to_string(),
+        content: content.into(),
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    }
+}
+
+fn settings(context: ToolContextMode, max: usize) -> ToolSettings {
+    ToolSettings {
+        context,
+        context_max_messages: max,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_no_context_by_default() {
+    let messages = vec![message("user", "hi")];
+    assert_eq!(
+        build_tool_context(&ToolSettings::default(), &messages),
+        None
+    );
+}
+
+#[test]
+fn test_question_context_uses_latest_user_message() {
+    let messages = vec![
+        message("system", "be helpful"),
+        message("user", "first question"),
+        message("assistant", "first answer"),
+        message("user", "find rust tutorials"),
+    ];
+
+    let context = build_tool_context(&settings(ToolContextMode::Question, 10), &messages);
+    assert_eq!(context, Some(json!({"question": "find rust tutorials"})));
+}
+
+#[test]
+fn test_transcript_context_is_trimmed() {
+    let messages = vec![
+        message("system", "be helpful"),
+        message("user", "one"),
+        message("assistant", "two"),
+        message("tool", "tool output"),
+        message("user", "three"),
+    ];
+
+    let context = build_tool_context(&settings(ToolContextMode::Transcript, 2), &messages).unwrap();
+    assert_eq!(
+        context,
+        json!({
+            "question": "three",
+            "transcript": [
+                {"role": "assistant", "content": "two"},
+                {"role": "user", "content": "three"},
+            ]
+        })
+    );
+}
+
+#[test]
+fn test_tools_config_parses_context_settings() {
+    let yaml = r#"
+settings:
+  web_search:
+    context: transcript
+    context_max_messages: 4
+  summarize:
+    context: question
+"#;
+    let config: ToolsConfig = serde_yaml::from_str(yaml).unwrap();
+    let web = config.settings_for("web_search").unwrap();
+    assert_eq!(web.context, ToolContextMode::Transcript);
+    assert_eq!(web.context_max_messages, 4);
+    let summarize = config.settings_for("summarize").unwrap();
+    assert_eq!(summarize.context, ToolContextMode::Question);
+    assert_eq!(summarize.context_max_messages, 10);
+    assert!(config.settings_for("other").is_none());
+}
+
+#[tokio::test]
+async fn test_agent_injects_context_only_for_opted_in_tools() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "search",
+        r#"{"query": "rust"}"#,
+    ));
+    mock_llm.add_response(create_mock_tool_call_response("lights", "{}"));
+    mock_llm.add_response(create_mock_chat_response("done"));
+
+    let mock_mcp = MockMcpClient::new();
+    let calls = mock_mcp.calls.clone();
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("server".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("search".to_string(), "server".to_string());
+    tool_to_client_map.insert("lights".to_string(), "server".to_string());
+    let tools = ["search", "lights"]
+        .iter()
+        .map(|name| Tool {
+            tool_type: "function".to_string(),
+            function: Function {
+                name: name.to_string(),
+                description: String::new(),
+                parameters: json!({"type": "object"}),
+            },
+        })
+        .collect();
+
+    let mut tools_config = ToolsConfig::default();
+    tools_config.settings.insert(
+        "search".to_string(),
+        settings(ToolContextMode::Question, 10),
+    );
+
+    let mut agent =
+        Agent::new_for_testing(Box::new(mock_llm), mcp_clients, tool_to_client_map, tools)
+            .with_tools_config(tools_config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("ctx", "look up rust", &history)
+        .await
+        .unwrap();
+
+    let calls = calls.lock().unwrap();
+    assert_eq!(calls.len(), 2);
+    assert_eq!(calls[0].arguments["query"], json!("rust"));
+    assert_eq!(
+        calls[0].arguments[CONTEXT_ARGUMENT],
+        json!({"question": "look up rust"})
+    );
+    assert!(!calls[1].arguments.contains_key(CONTEXT_ARGUMENT));
+}
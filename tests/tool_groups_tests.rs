@@ -0,0 +1,265 @@
+use jarvis_rust::{
+    agent::{Agent, merge_group_results},
+    config::{ToolGroupConfig, ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{Function, Tool},
+    mcp::{McpClient, McpContent, McpToolCallResponse},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+fn search_tool(name: &str) -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: name.to_string(),
+            description: format!("Searches {name}"),
+            parameters: json!({
+                "type": "object",
+                "properties": {"query": {"type": "string"}},
+                "required": ["query"],
+            }),
+        },
+    }
+}
+
+fn search_everywhere(input_schema: Option<serde_json::Value>) -> ToolsConfig {
+    ToolsConfig {
+        groups: vec![ToolGroupConfig {
+            name: "search_everywhere".to_string(),
+            description: "Searches the web and the docs at once".to_string(),
+            tools: vec![
+                "web_search".to_string(),
+                "docs_search".to_string(),
+                "wiki_search".to_string(),
+            ],
+            input_schema,
+        }],
+        ..Default::default()
+    }
+}
+
+fn search_agent(llm: MockLlmClient, web: MockMcpClient, docs: MockMcpClient) -> Agent {
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("web".to_string(), Box::new(web));
+    mcp_clients.insert("docs".to_string(), Box::new(docs));
+    let tool_to_client_map = HashMap::from([
+        ("web_search".to_string(), "web".to_string()),
+        ("docs_search".to_string(), "docs".to_string()),
+    ]);
+    Agent::new_for_testing(
+        Box::new(llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![search_tool("web_search"), search_tool("docs_search")],
+    )
+    .with_tools_config(search_everywhere(None))
+}
+
+#[test]
+fn test_merge_group_results() {
+    let merged = merge_group_results(vec![
+        (
+            "web_search".to_string(),
+            create_mock_tool_response("Rust 1.88"),
+        ),
+        (
+            "docs_search".to_string(),
+            McpToolCallResponse {
+                content: vec![McpContent::Text {
+                    text: "timeout".to_string(),
+                }],
+                is_error: true,
+            },
+        ),
+    ]);
+    assert!(!merged.is_error);
+    let texts: Vec<String> = merged
+        .content
+        .iter()
+        .map(|item| match item {
+            McpContent::Text { text } => text.clone(),
+            _ => String::new(),
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            "[web_search] returned:",
+            "Rust 1.88",
+            "[docs_search] failed:",
+            "timeout",
+        ]
+    );
+
+    let failed = merge_group_results(vec![(
+        "docs_search".to_string(),
+        McpToolCallResponse {
+            content: vec![],
+            is_error: true,
+        },
+    )]);
+    assert!(failed.is_error);
+}
+
+#[test]
+fn test_tool_groups_config_parse() {
+    let config: ToolsConfig = serde_yaml::from_str(
+        r#"
+groups:
+  - name: search_everywhere
+    description: Searches everything
+    tools: [web_search, docs_search]
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.groups.len(), 1);
+    assert_eq!(config.groups[0].tools, vec!["web_search", "docs_search"]);
+    assert_eq!(config.groups[0].input_schema, None);
+    assert!(ToolsConfig::default().groups.is_empty());
+}
+
+#[tokio::test]
+async fn test_group_fans_out_to_its_tools() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "search_everywhere",
+        r#"{"query": "rust release"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("Rust 1.88 is out."));
+    let web = MockMcpClient::new().with_tool_response(
+        "web_search".to_string(),
+        create_mock_tool_response("Rust 1.88 released"),
+    );
+    let web_calls = web.calls.clone();
+    let docs = MockMcpClient::new()
+        .with_tool_error("docs_search".to_string(), "index unavailable".to_string());
+    let docs_calls = docs.calls.clone();
+
+    let mut agent = search_agent(mock_llm, web, docs);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let reply = agent
+        .process("session", "Is there a new Rust?", &history)
+        .await
+        .unwrap();
+    assert_eq!(reply, "Rust 1.88 is out.");
+
+    // The group is offered with the schema of its first available tool
+    let requests = requests.lock().unwrap().clone();
+    let group = requests[0]
+        .tools
+        .iter()
+        .find(|tool| tool.function.name == "search_everywhere")
+        .unwrap();
+    assert_eq!(
+        group.function.parameters,
+        search_tool("web_search").function.parameters
+    );
+
+    // Every available tool gets the group's arguments; the unknown one is skipped
+    let web_calls = web_calls.lock().unwrap().clone();
+    let docs_calls = docs_calls.lock().unwrap().clone();
+    assert_eq!(web_calls.len(), 1);
+    assert_eq!(docs_calls.len(), 1);
+    assert_eq!(web_calls[0].arguments["query"], json!("rust release"));
+    assert_eq!(docs_calls[0].arguments["query"], json!("rust release"));
+
+    let result = &requests[1].messages.last().unwrap().content;
+    assert!(
+        result
+            .text()
+            .starts_with("[web_search] returned:\nRust 1.88 released")
+    );
+    assert!(result.text().contains("[docs_search] failed:"));
+    assert!(result.text().contains("index unavailable"));
+    assert!(!result.text().contains("wiki_search"));
+}
+
+#[tokio::test]
+async fn test_group_uses_configured_schema() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let schema = json!({
+        "type": "object",
+        "properties": {"query": {"type": "string"}, "limit": {"type": "integer"}},
+    });
+    let mut agent = search_agent(mock_llm, MockMcpClient::new(), MockMcpClient::new())
+        .with_tools_config(search_everywhere(Some(schema.clone())));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("session", "Hi", &history).await.unwrap();
+
+    let tools = requests.lock().unwrap()[0].tools.clone();
+    let names: Vec<&str> = tools.iter().map(|t| t.function.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["web_search", "docs_search", "search_everywhere"]
+    );
+    assert_eq!(tools[2].function.parameters, schema);
+}
+
+#[tokio::test]
+async fn test_group_without_available_tools_is_not_offered() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_chat_response("Hello!"));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        vec![search_tool("calendar")],
+    )
+    .with_tools_config(search_everywhere(None));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("session", "Hi", &history).await.unwrap();
+
+    let tools = requests.lock().unwrap()[0].tools.clone();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0].function.name, "calendar");
+}
+
+#[tokio::test]
+async fn test_group_needs_approval_when_any_tool_does() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "search_everywhere",
+        r#"{"query": "rust release"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("I couldn't search."));
+    let web = MockMcpClient::new();
+    let web_calls = web.calls.clone();
+    let mut tools_config = search_everywhere(None);
+    tools_config.settings.insert(
+        "docs_search".to_string(),
+        ToolSettings {
+            require_approval: Some(true),
+            ..Default::default()
+        },
+    );
+    let mut agent =
+        search_agent(mock_llm, web, MockMcpClient::new()).with_tools_config(tools_config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Is there a new Rust?", &history)
+        .await
+        .unwrap();
+
+    // Without an approval handler the whole group is denied
+    assert!(web_calls.lock().unwrap().is_empty());
+    let result = requests.lock().unwrap()[1]
+        .messages
+        .last()
+        .unwrap()
+        .content
+        .clone();
+    assert!(result.text().contains("no approval handler is configured"));
+}
@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    config::{ToolHeartbeatConfig, ToolsConfig},
+    events::{SessionEventHook, SessionEventKind, SessionEvents, placeholder},
+    history::HistoryStorage,
+    mcp::{McpProgress, McpTool, McpToolCallResponse},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Takes `steps` steps of `step` each, reporting progress after each one if `reports`
+struct ExportTool {
+    steps: u32,
+    step: Duration,
+    reports: bool,
+}
+
+#[async_trait]
+impl NativeTool for ExportTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "export".to_string(),
+            description: "Exports the photo library".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        for done in 1..=self.steps {
+            tokio::time::sleep(self.step).await;
+            if self.reports {
+                ctx.report_progress(done, Some(self.steps), None);
+            }
+        }
+        Ok(text_result("Exported 120 photos"))
+    }
+}
+
+fn heartbeat(stall_timeout_ms: Option<u64>, timeout_ms: Option<u64>) -> ToolsConfig {
+    ToolsConfig {
+        heartbeat: ToolHeartbeatConfig {
+            after_ms: 40,
+            interval_ms: 40,
+            stall_timeout_ms,
+            timeout_ms,
+        },
+        ..Default::default()
+    }
+}
+
+/// Runs "Export my photos" with `tool`, returning the run's events and the result
+/// the LLM was given
+async fn run_export(
+    tool: ExportTool,
+    tools_config: ToolsConfig,
+) -> (Vec<SessionEventKind>, String) {
+    let events = Arc::new(SessionEvents::new(256));
+    let mut receiver = events.subscribe();
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("export", "{}"));
+    mock_llm.add_response(create_mock_chat_response("Done."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    )
+    .with_tools_config(tools_config);
+    agent.register_native_tool(Arc::new(tool));
+    agent.add_hook(Arc::new(SessionEventHook::new(events)));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    agent
+        .process("s1", "Export my photos", &history)
+        .await
+        .unwrap();
+
+    let mut kinds = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        kinds.push(event.kind);
+    }
+    let tool_result = requests.lock().unwrap()[1]
+        .messages
+        .last()
+        .unwrap()
+        .content
+        .to_string();
+    (kinds, tool_result)
+}
+
+fn heartbeats(events: &[SessionEventKind]) -> Vec<(u64, Option<McpProgress>)> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            SessionEventKind::ToolHeartbeat {
+                elapsed_ms,
+                progress,
+                ..
+            } => Some((*elapsed_ms, progress.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_long_tool_calls_send_heartbeats_with_progress() {
+    let tool = ExportTool {
+        steps: 6,
+        step: Duration::from_millis(25),
+        reports: true,
+    };
+
+    let (events, tool_result) = run_export(tool, heartbeat(Some(100), None)).await;
+
+    assert!(tool_result.contains("Exported 120 photos"));
+    let heartbeats = heartbeats(&events);
+    assert!(heartbeats.len() >= 2, "heartbeats: {heartbeats:?}");
+    assert!(heartbeats.iter().all(|(elapsed_ms, _)| *elapsed_ms >= 40));
+    let (_, progress) = heartbeats.last().unwrap();
+    assert_eq!(progress.as_ref().unwrap().total, Some(6));
+    assert!(matches!(
+        events
+            .iter()
+            .find(|e| matches!(e, SessionEventKind::ToolResult { .. })),
+        Some(SessionEventKind::ToolResult {
+            is_error: false,
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn test_quick_tool_calls_send_no_heartbeats() {
+    let tool = ExportTool {
+        steps: 1,
+        step: Duration::from_millis(1),
+        reports: false,
+    };
+
+    let (events, _) = run_export(tool, heartbeat(Some(100), None)).await;
+
+    assert_eq!(heartbeats(&events), Vec::new());
+}
+
+#[tokio::test]
+async fn test_silent_tool_calls_are_cancelled_as_stalled() {
+    let tool = ExportTool {
+        steps: 1,
+        step: Duration::from_secs(30),
+        reports: false,
+    };
+    let started = std::time::Instant::now();
+
+    let (events, tool_result) = run_export(tool, heartbeat(Some(60), Some(30_000))).await;
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(
+        tool_result.contains("Tool stalled: no progress for"),
+        "{tool_result}"
+    );
+    assert!(events.contains(&SessionEventKind::ToolResult {
+        name: "export".to_string(),
+        is_error: true,
+    }));
+}
+
+#[tokio::test]
+async fn test_tool_calls_time_out_despite_progress() {
+    let tool = ExportTool {
+        steps: 1_000,
+        step: Duration::from_millis(10),
+        reports: true,
+    };
+
+    let (_, tool_result) = run_export(tool, heartbeat(Some(1_000), Some(150))).await;
+
+    assert!(
+        tool_result.contains("Tool timed out after"),
+        "{tool_result}"
+    );
+}
+
+#[tokio::test]
+async fn test_heartbeats_can_be_turned_off() {
+    let tool = ExportTool {
+        steps: 1,
+        step: Duration::from_millis(120),
+        reports: false,
+    };
+    let tools_config = ToolsConfig {
+        heartbeat: ToolHeartbeatConfig {
+            after_ms: 0,
+            stall_timeout_ms: Some(10),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let (events, tool_result) = run_export(tool, tools_config).await;
+
+    assert!(tool_result.contains("Exported 120 photos"));
+    assert_eq!(heartbeats(&events), Vec::new());
+}
+
+#[test]
+fn test_heartbeat_placeholders() {
+    let heartbeat = |progress| SessionEventKind::ToolHeartbeat {
+        name: "export".to_string(),
+        elapsed_ms: 12_000,
+        progress,
+    };
+    assert_eq!(
+        placeholder(&heartbeat(None)).as_deref(),
+        Some("Still running export…")
+    );
+    assert_eq!(
+        placeholder(&heartbeat(Some(McpProgress {
+            progress: 3,
+            total: Some(6),
+            message: None,
+        })))
+        .as_deref(),
+        Some("Running export (3/6)…")
+    );
+    assert_eq!(
+        placeholder(&heartbeat(Some(McpProgress {
+            progress: 3,
+            total: None,
+            message: Some("Uploading album 2".to_string()),
+        })))
+        .as_deref(),
+        Some("Running export: Uploading album 2")
+    );
+}
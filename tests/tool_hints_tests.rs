@@ -0,0 +1,159 @@
+use jarvis_rust::{
+    agent::{Agent, ToolLatencies, describe_with_hints},
+    config::{ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{ChatCompletionRequest, Function, Tool},
+    mcp::McpClient,
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+};
+
+#[test]
+fn test_describe_with_hints() {
+    assert_eq!(describe_with_hints("Searches", None, None), "Searches");
+    assert_eq!(
+        describe_with_hints("Searches", Some("$0.01 per call"), None),
+        "Searches (cost: $0.01 per call)"
+    );
+    assert_eq!(
+        describe_with_hints("Searches", Some("free"), Some(Duration::from_millis(250))),
+        "Searches (cost: free; average latency: 250ms)"
+    );
+    assert_eq!(
+        describe_with_hints("Searches", None, Some(Duration::from_millis(2340))),
+        "Searches (average latency: 2.3s)"
+    );
+}
+
+#[test]
+fn test_tool_latencies_average() {
+    let latencies = ToolLatencies::new();
+    assert_eq!(latencies.average("search"), None);
+    latencies.record("search", Duration::from_millis(100));
+    latencies.record("search", Duration::from_millis(300));
+    latencies.record("clock", Duration::from_millis(5));
+    assert_eq!(
+        latencies.average("search"),
+        Some(Duration::from_millis(200))
+    );
+    assert_eq!(latencies.average("clock"), Some(Duration::from_millis(5)));
+}
+
+#[test]
+fn test_hint_settings_parse() {
+    let config: ToolsConfig = serde_yaml::from_str(
+        r#"
+latency_hints: true
+settings:
+  deep_research:
+    cost: "expensive"
+"#,
+    )
+    .unwrap();
+    assert!(config.latency_hints);
+    assert_eq!(
+        config.settings["deep_research"].cost.as_deref(),
+        Some("expensive")
+    );
+    assert!(!ToolsConfig::default().latency_hints);
+    assert_eq!(ToolSettings::default().cost, None);
+}
+
+fn search_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "search".to_string(),
+            description: "Searches the web".to_string(),
+            parameters: json!({"type": "object", "properties": {}}),
+        },
+    }
+}
+
+fn search_agent(mock_llm: MockLlmClient, latency_hints: bool) -> Agent {
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("web".to_string(), Box::new(MockMcpClient::new()));
+    let tool_to_client_map = HashMap::from([("search".to_string(), "web".to_string())]);
+    let mut tools_config = ToolsConfig {
+        latency_hints,
+        ..Default::default()
+    };
+    tools_config.settings.insert(
+        "search".to_string(),
+        ToolSettings {
+            cost: Some("$0.01 per call".to_string()),
+            ..Default::default()
+        },
+    );
+    Agent::new_for_testing(
+        Box::new(mock_llm),
+        mcp_clients,
+        tool_to_client_map,
+        vec![search_tool()],
+    )
+    .with_tools_config(tools_config)
+}
+
+fn offered_descriptions(requests: &Arc<Mutex<Vec<ChatCompletionRequest>>>) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|request| request.tools[0].function.description.clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_descriptions_carry_cost_and_measured_latency() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("search", "{}"));
+    mock_llm.add_response(create_mock_chat_response("Found it."));
+    mock_llm.add_response(create_mock_chat_response("Anything else?"));
+    let requests = mock_llm.requests.clone();
+    let mut agent = search_agent(mock_llm, true);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Search something", &history)
+        .await
+        .unwrap();
+    agent.process("session", "Thanks", &history).await.unwrap();
+
+    // No latency is known before the first call; runs after it see the average
+    let descriptions = offered_descriptions(&requests);
+    assert_eq!(descriptions[0], "Searches the web (cost: $0.01 per call)");
+    assert!(
+        descriptions[2].starts_with("Searches the web (cost: $0.01 per call; average latency: ")
+    );
+}
+
+#[tokio::test]
+async fn test_latency_hints_are_opt_in() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("search", "{}"));
+    mock_llm.add_response(create_mock_chat_response("Found it."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = search_agent(mock_llm, false);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent
+        .process("session", "Search something", &history)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        offered_descriptions(&requests),
+        vec![
+            "Searches the web (cost: $0.01 per call)",
+            "Searches the web (cost: $0.01 per call)",
+        ]
+    );
+}
@@ -0,0 +1,159 @@
+use jarvis_rust::{
+    agent::{Agent, render_tool_result},
+    config::{ToolOutputFormat, ToolSettings, ToolsConfig},
+    history::HistoryStorage,
+    llm::{Function, Tool},
+    mcp::{McpClient, McpContent, McpResourceContent, McpToolCallResponse},
+};
+use pretty_assertions::assert_eq;
+use serde_json::json;
+use std::collections::HashMap;
+
+mod common;
+use common::{
+    MockLlmClient, MockMcpClient, create_mock_chat_response, create_mock_tool_call_response,
+    create_mock_tool_response,
+};
+
+fn text_response(text: &str) -> McpToolCallResponse {
+    create_mock_tool_response(text)
+}
+
+#[test]
+fn test_text_rendering_joins_all_content_items() {
+    let response = McpToolCallResponse {
+        content: vec![
+            McpContent::Text {
+                text: "first".to_string(),
+            },
+            McpContent::Image {
+                data: "aGVsbG8=".to_string(),
+                mime_type: "image/png".to_string(),
+            },
+            McpContent::Resource {
+                resource: McpResourceContent {
+                    uri: "file:///notes.txt".to_string(),
+                    text: None,
+                    blob: Some("YmxvYg==".to_string()),
+                },
+            },
+        ],
+        is_error: false,
+    };
+
+    assert_eq!(
+        render_tool_result(&response, ToolOutputFormat::Text, None),
+        "first\n[image: image/png]\n[resource: file:///notes.txt]"
+    );
+}
+
+#[test]
+fn test_json_rendering_is_fenced_with_schema() {
+    let response = text_response(r#"{"temp":21}"#);
+    let schema = json!({"type": "object"});
+
+    let rendered = render_tool_result(&response, ToolOutputFormat::Json, Some(&schema));
+    assert_eq!(
+        rendered,
+        "```json\n{\n  \"temp\": 21\n}\n```\n\nOutput schema:\n```json\n{\n  \"type\": \"object\"\n}\n```"
+    );
+}
+
+#[test]
+fn test_json_rendering_falls_back_to_text_for_invalid_json() {
+    let response = text_response("not json at all");
+    assert_eq!(
+        render_tool_result(&response, ToolOutputFormat::Json, None),
+        "not json at all"
+    );
+}
+
+#[test]
+fn test_error_results_are_not_fenced() {
+    let mut response = text_response(r#"{"error": "boom"}"#);
+    response.is_error = true;
+    assert_eq!(
+        render_tool_result(&response, ToolOutputFormat::Json, None),
+        r#"{"error": "boom"}"#
+    );
+}
+
+#[test]
+fn test_output_format_config_parsing() {
+    let yaml = r#"
+settings:
+  sensors:
+    output_format: json
+    output_schema:
+      type: object
+"#;
+    let config: ToolsConfig = serde_yaml::from_str(yaml).unwrap();
+    let sensors = config.settings_for("sensors").unwrap();
+    assert_eq!(sensors.output_format, Some(ToolOutputFormat::Json));
+    assert_eq!(sensors.output_schema, Some(json!({"type": "object"})));
+}
+
+async fn run_single_tool(tool_response: McpToolCallResponse, tools_config: ToolsConfig) -> String {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("sensors", "{}"));
+    mock_llm.add_response(create_mock_chat_response("done"));
+    let requests = mock_llm.requests.clone();
+
+    let mock_mcp = MockMcpClient::new().with_tool_response("sensors".to_string(), tool_response);
+    let mut mcp_clients: HashMap<String, Box<dyn McpClient>> = HashMap::new();
+    mcp_clients.insert("home".to_string(), Box::new(mock_mcp));
+    let mut tool_to_client_map = HashMap::new();
+    tool_to_client_map.insert("sensors".to_string(), "home".to_string());
+    let tools = vec![Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "sensors".to_string(),
+            description: "Reads sensors".to_string(),
+            parameters: json!({"type": "object"}),
+        },
+    }];
+
+    let mut agent =
+        Agent::new_for_testing(Box::new(mock_llm), mcp_clients, tool_to_client_map, tools)
+            .with_tools_config(tools_config);
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    agent.process("output", "read", &history).await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    requests[1]
+        .messages
+        .iter()
+        .find(|m| m.role == "tool")
+        .expect("tool message")
+        .content
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_agent_passes_json_through_for_json_tools() {
+    let mut tools_config = ToolsConfig::default();
+    tools_config.settings.insert(
+        "sensors".to_string(),
+        ToolSettings {
+            output_format: Some(ToolOutputFormat::Json),
+            ..Default::default()
+        },
+    );
+
+    let content = run_single_tool(text_response(r#"{"kitchen":true}"#), tools_config).await;
+    assert_eq!(content, "```json\n{\n  \"kitchen\": true\n}\n```");
+}
+
+#[tokio::test]
+async fn test_agent_reports_non_text_results() {
+    let response = McpToolCallResponse {
+        content: vec![McpContent::Image {
+            data: "aGVsbG8=".to_string(),
+            mime_type: "image/jpeg".to_string(),
+        }],
+        is_error: false,
+    };
+
+    let content = run_single_tool(response, ToolsConfig::default()).await;
+    assert_eq!(content, "[image: image/jpeg]");
+}
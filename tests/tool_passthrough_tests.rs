@@ -0,0 +1,675 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode},
+};
+use jarvis_rust::{
+    Result,
+    agent::{Agent, PAUSED_RUN_TTL, PausedRun, PausedRuns, ProcessOptions, RunOutcome, ToolMode},
+    config::ApiKeyConfig,
+    history::HistoryStorage,
+    llm::{ChatCompletionResponse, Function, FunctionCall, Tool, ToolCall},
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, inference, submit_tool_results},
+    sessions::SessionStore,
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Instant,
+};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Server-side tool counting its calls
+struct ClockTool {
+    calls: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(text_result("08:00"))
+    }
+}
+
+fn location_tool() -> Tool {
+    Tool {
+        tool_type: "function".to_string(),
+        function: Function {
+            name: "get_location".to_string(),
+            description: "Where the user is".to_string(),
+            parameters: json!({"type": "object"}),
+        },
+    }
+}
+
+fn call(name: &str) -> ToolCall {
+    ToolCall {
+        id: format!("call_{name}"),
+        call_type: "function".to_string(),
+        function: FunctionCall {
+            name: name.to_string(),
+            arguments: "{}".to_string(),
+        },
+    }
+}
+
+fn tool_calls_response(names: &[&str]) -> ChatCompletionResponse {
+    let mut response = create_mock_tool_call_response(names[0], "{}");
+    response.choices[0].message.tool_calls = Some(names.iter().map(|name| call(name)).collect());
+    response
+}
+
+fn agent(mock_llm: MockLlmClient, clock_calls: Arc<AtomicUsize>) -> Agent {
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool { calls: clock_calls }));
+    agent
+}
+
+fn paused(outcome: RunOutcome) -> PausedRun {
+    match outcome {
+        RunOutcome::Paused(run) => *run,
+        RunOutcome::Reply(reply) => panic!("expected a paused run, got {reply:?}"),
+    }
+}
+
+fn reply_output(outcome: RunOutcome) -> String {
+    match outcome {
+        RunOutcome::Reply(reply) => reply.output,
+        RunOutcome::Paused(run) => panic!("expected a reply, run paused for {:?}", run.tool_calls),
+    }
+}
+
+fn tool_call_ids(run: &PausedRun) -> Vec<&str> {
+    run.tool_calls.iter().map(|call| call.id.as_str()).collect()
+}
+
+#[tokio::test]
+async fn test_manual_mode_hands_back_every_call() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 09:15."));
+    let clock_calls = Arc::new(AtomicUsize::new(0));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, clock_calls.clone());
+
+    let run = paused(
+        agent
+            .process_with_options(
+                "session",
+                "What time is it?",
+                &history,
+                ProcessOptions {
+                    tool_mode: ToolMode::Manual,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+    );
+    assert_eq!(run.session_id, "session");
+    assert_eq!(tool_call_ids(&run), vec!["call_clock"]);
+    assert_eq!(clock_calls.load(Ordering::SeqCst), 0);
+    assert_eq!(requests.lock().unwrap().len(), 1);
+
+    let results = HashMap::from([("call_clock".to_string(), text_result("09:15"))]);
+    let output = reply_output(agent.resume_run(run, results, &history).await.unwrap());
+    assert_eq!(output, "It is 09:15.");
+    assert_eq!(clock_calls.load(Ordering::SeqCst), 0);
+
+    let resumed = requests.lock().unwrap()[1].clone();
+    let result = resumed.messages.last().unwrap();
+    assert_eq!(result.role, "tool");
+    assert_eq!(result.tool_call_id.as_deref(), Some("call_clock"));
+    assert_eq!(result.content, "09:15");
+
+    let messages = history.list("session").await.unwrap();
+    let reply = messages.last().unwrap();
+    assert_eq!(reply.content, "It is 09:15.");
+    let metadata = reply.metadata.as_ref().unwrap();
+    assert_eq!(metadata["tool_calls"][0]["name"], "clock");
+    assert_eq!(metadata["tool_calls"][0]["result"], "09:15");
+}
+
+#[tokio::test]
+async fn test_auto_mode_runs_own_tools_and_hands_back_client_tools() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(tool_calls_response(&["clock", "get_location"]));
+    mock_llm.add_response(create_mock_chat_response("08:00 in Lisbon."));
+    let clock_calls = Arc::new(AtomicUsize::new(0));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, clock_calls.clone());
+
+    let run = paused(
+        agent
+            .process_with_options(
+                "session",
+                "What time is it here?",
+                &history,
+                ProcessOptions {
+                    tools: vec![location_tool()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+    );
+    // The agent's own tool ran; only the caller's is pending
+    assert_eq!(clock_calls.load(Ordering::SeqCst), 1);
+    assert_eq!(tool_call_ids(&run), vec!["call_get_location"]);
+    let offered: Vec<String> = requests.lock().unwrap()[0]
+        .tools
+        .iter()
+        .map(|tool| tool.function.name.clone())
+        .collect();
+    assert!(offered.contains(&"clock".to_string()));
+    assert!(offered.contains(&"get_location".to_string()));
+
+    let results = HashMap::from([("call_get_location".to_string(), text_result("Lisbon"))]);
+    let output = reply_output(agent.resume_run(run, results, &history).await.unwrap());
+    assert_eq!(output, "08:00 in Lisbon.");
+
+    let resumed = requests.lock().unwrap()[1].clone();
+    let results: Vec<(Option<&str>, String)> = resumed
+        .messages
+        .iter()
+        .filter(|message| message.role == "tool")
+        .map(|message| (message.tool_call_id.as_deref(), message.content.to_string()))
+        .collect();
+    assert_eq!(
+        results,
+        vec![
+            (Some("call_clock"), "08:00".to_string()),
+            (Some("call_get_location"), "Lisbon".to_string()),
+        ]
+    );
+    // The caller's tools stay on offer after resuming
+    assert!(
+        resumed
+            .tools
+            .iter()
+            .any(|tool| tool.function.name == "get_location")
+    );
+}
+
+#[tokio::test]
+async fn test_auto_mode_without_client_tools_never_pauses() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 08:00."));
+    let clock_calls = Arc::new(AtomicUsize::new(0));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, clock_calls.clone());
+
+    let output = reply_output(
+        agent
+            .process_with_options(
+                "session",
+                "What time is it?",
+                &history,
+                ProcessOptions::default(),
+            )
+            .await
+            .unwrap(),
+    );
+    assert_eq!(output, "It is 08:00.");
+    assert_eq!(clock_calls.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_resume_without_result_reports_error_to_llm() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response("get_location", "{}"));
+    mock_llm.add_response(create_mock_chat_response("I couldn't find you."));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, Arc::new(AtomicUsize::new(0)));
+
+    let run = paused(
+        agent
+            .process_with_options(
+                "session",
+                "Where am I?",
+                &history,
+                ProcessOptions {
+                    tools: vec![location_tool()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+    );
+    let output = reply_output(
+        agent
+            .resume_run(run, HashMap::new(), &history)
+            .await
+            .unwrap(),
+    );
+    assert_eq!(output, "I couldn't find you.");
+
+    let resumed = requests.lock().unwrap()[1].clone();
+    let result = resumed.messages.last().unwrap();
+    assert_eq!(result.role, "tool");
+    assert!(result.content.text().contains("No result was provided"));
+}
+
+#[tokio::test]
+async fn test_paused_runs_are_taken_once() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, Arc::new(AtomicUsize::new(0)));
+    let run = paused(
+        agent
+            .process_with_options(
+                "session",
+                "What time is it?",
+                &history,
+                ProcessOptions {
+                    tool_mode: ToolMode::Manual,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+    );
+
+    let runs = PausedRuns::new();
+    let id = runs.insert(run).await;
+    assert_eq!(runs.len().await, 1);
+    assert!(runs.take("unknown").await.is_none());
+    assert!(runs.take(&id).await.is_some());
+    assert!(runs.take(&id).await.is_none());
+    assert!(runs.is_empty().await);
+}
+
+#[tokio::test]
+async fn test_restored_runs_keep_expiring() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+    let mut agent = agent(mock_llm, Arc::new(AtomicUsize::new(0)));
+    let mut run = paused(
+        agent
+            .process_with_options(
+                "session",
+                "What time is it?",
+                &history,
+                ProcessOptions {
+                    tool_mode: ToolMode::Manual,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap(),
+    );
+
+    let runs = PausedRuns::new();
+    let paused_at = run.paused_at;
+    let id = runs.insert(run).await;
+    run = runs.take(&id).await.unwrap();
+    runs.restore(&id, run).await;
+    run = runs.take(&id).await.unwrap();
+    assert_eq!(run.paused_at, paused_at);
+
+    // Putting back a run whose time is up doesn't revive it
+    run.paused_at = Instant::now().checked_sub(PAUSED_RUN_TTL).unwrap();
+    runs.restore(&id, run).await;
+    assert!(runs.take(&id).await.is_none());
+}
+
+fn app(mock_llm: MockLlmClient, runs: Arc<PausedRuns>, history: Arc<HistoryStorage>) -> Router {
+    keyed_app(mock_llm, runs, history, Vec::new(), None)
+}
+
+fn key(name: &str) -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: name.to_string(),
+        key: format!("{name}-key"),
+        models: Vec::new(),
+        default_model: None,
+        requests_per_minute: None,
+        admin: false,
+    }
+}
+
+/// Like `app`, requiring one of `api_keys` and keeping session owners in `sessions`
+fn keyed_app(
+    mock_llm: MockLlmClient,
+    runs: Arc<PausedRuns>,
+    history: Arc<HistoryStorage>,
+    api_keys: Vec<ApiKeyConfig>,
+    sessions: Option<Arc<SessionStore>>,
+) -> Router {
+    let state = AppState {
+        history,
+        agent: Arc::new(Mutex::new(agent(mock_llm, Arc::new(AtomicUsize::new(0))))),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs,
+        api_keys: Arc::new(api_keys),
+        usage: None,
+        timeline: None,
+        sessions,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/", axum::routing::post(inference))
+        .route(
+            "/runs/:run_id/tool_results",
+            axum::routing::post(submit_tool_results),
+        )
+        .with_state(state)
+}
+
+async fn post(app: &Router, uri: &str, body: Value) -> (StatusCode, Value) {
+    post_as(app, uri, None, body).await
+}
+
+/// Posts `body`, authenticated with the key of `key` when given
+async fn post_as(app: &Router, uri: &str, key: Option<&str>, body: Value) -> (StatusCode, Value) {
+    let mut request = Request::post(uri).header("content-type", "application/json");
+    if let Some(key) = key {
+        request = request.header("authorization", format!("Bearer {key}-key"));
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::from(body.to_string())).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+/// Starts a manual-mode run for `clock`, returning its id
+async fn paused_clock_run(app: &Router) -> String {
+    let (status, body) = post(
+        app,
+        "/",
+        json!({"session_id": "session", "input": "What time is it?", "tool_mode": "manual"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    body["run_id"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn test_inference_returns_tool_calls_in_manual_mode() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    let runs = Arc::new(PausedRuns::new());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let app = app(mock_llm, runs.clone(), history);
+
+    let (status, body) = post(
+        &app,
+        "/",
+        json!({"session_id": "session", "input": "What time is it?", "tool_mode": "manual"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["output"], "");
+    assert_eq!(
+        body["tool_calls"],
+        json!([{
+            "id": "call_clock",
+            "type": "function",
+            "function": {"name": "clock", "arguments": "{}"},
+        }])
+    );
+    let run_id = body["run_id"].as_str().unwrap();
+    assert!(runs.take(run_id).await.is_some());
+}
+
+#[tokio::test]
+async fn test_submitted_tool_results_resume_the_run() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 09:15."));
+    let runs = Arc::new(PausedRuns::new());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let app = app(mock_llm, runs.clone(), history.clone());
+    let run_id = paused_clock_run(&app).await;
+
+    let (status, body) = post(
+        &app,
+        &format!("/runs/{run_id}/tool_results"),
+        json!({"tool_results": [{"tool_call_id": "call_clock", "content": "09:15"}]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({
+            "session_id": "session",
+            "output": "It is 09:15.",
+            "run_id": run_id,
+            "citations": [{"source_id": "tool:clock", "tool": "clock"}],
+        })
+    );
+    assert!(runs.is_empty().await);
+
+    let resumed = requests.lock().unwrap()[1].clone();
+    let result = resumed.messages.last().unwrap();
+    assert_eq!(result.tool_call_id.as_deref(), Some("call_clock"));
+    assert_eq!(result.content, "09:15");
+    let messages = history.list("session").await.unwrap();
+    assert_eq!(messages.last().unwrap().content, "It is 09:15.");
+
+    // The run is gone once resumed
+    let (status, _) = post(
+        &app,
+        &format!("/runs/{run_id}/tool_results"),
+        json!({"tool_results": [{"tool_call_id": "call_clock", "content": "09:15"}]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_failed_tool_results_reach_the_llm_as_errors() {
+    let mock_llm = MockLlmClient::new();
+    let requests = mock_llm.requests.clone();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("The clock is unavailable."));
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let app = app(mock_llm, Arc::new(PausedRuns::new()), history.clone());
+    let run_id = paused_clock_run(&app).await;
+
+    let (status, _) = post(
+        &app,
+        &format!("/runs/{run_id}/tool_results"),
+        json!({"tool_results": [
+            {"tool_call_id": "call_clock", "content": "Clock offline", "is_error": true},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        requests.lock().unwrap()[1]
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .text()
+            .contains("Clock offline")
+    );
+    let messages = history.list("session").await.unwrap();
+    let metadata = messages.last().unwrap().metadata.clone().unwrap();
+    assert_eq!(metadata["tool_calls"][0]["is_error"], true);
+}
+
+#[tokio::test]
+async fn test_incomplete_tool_results_are_rejected() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(tool_calls_response(&["clock", "get_location"]));
+    mock_llm.add_response(create_mock_chat_response("08:00 in Lisbon."));
+    let runs = Arc::new(PausedRuns::new());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let app = app(mock_llm, runs.clone(), history);
+    let (status, body) = post(
+        &app,
+        "/",
+        json!({
+            "session_id": "session",
+            "input": "What time is it where I am?",
+            "tool_mode": "manual",
+            "tools": [location_tool()],
+        }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let run_id = body["run_id"].as_str().unwrap();
+    let uri = format!("/runs/{run_id}/tool_results");
+
+    let (status, body) = post(
+        &app,
+        &uri,
+        json!({"tool_results": [{"tool_call_id": "call_clock", "content": "08:00"}]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["error"],
+        "Tool error: Invalid tool results: missing results for call_get_location"
+    );
+
+    let (status, body) = post(
+        &app,
+        &uri,
+        json!({"tool_results": [
+            {"tool_call_id": "call_clock", "content": "08:00"},
+            {"tool_call_id": "call_clock", "content": "08:00"},
+            {"tool_call_id": "call_weather", "content": "Sunny"},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["error"],
+        "Tool error: Invalid tool results: duplicate result for 'call_clock'; no pending tool call 'call_weather'; missing results for call_get_location"
+    );
+
+    // Rejected submissions keep the run waiting
+    assert_eq!(runs.len().await, 1);
+    let (status, body) = post(
+        &app,
+        &uri,
+        json!({"tool_results": [
+            {"tool_call_id": "call_get_location", "content": "Lisbon"},
+            {"tool_call_id": "call_clock", "content": "08:00"},
+        ]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["output"], "08:00 in Lisbon.");
+}
+
+#[tokio::test]
+async fn test_tool_results_for_unknown_run() {
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let app = app(MockLlmClient::new(), Arc::new(PausedRuns::new()), history);
+
+    let (status, body) = post(
+        &app,
+        "/runs/missing/tool_results",
+        json!({"tool_results": []}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        body["error"],
+        "No run 'missing' is waiting for tool results"
+    );
+}
+
+#[tokio::test]
+async fn test_tool_results_from_another_key_are_refused_before_validation() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("clock", "{}"));
+    mock_llm.add_response(create_mock_chat_response("It is 09:15."));
+    let runs = Arc::new(PausedRuns::new());
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let sessions = Arc::new(SessionStore::new(":memory:").await.unwrap());
+    let app = keyed_app(
+        mock_llm,
+        runs.clone(),
+        history,
+        vec![key("alice"), key("bob")],
+        Some(sessions),
+    );
+    let (status, body) = post_as(
+        &app,
+        "/",
+        Some("alice"),
+        json!({"session_id": "session", "input": "What time is it?", "tool_mode": "manual"}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let uri = format!("/runs/{}/tool_results", body["run_id"].as_str().unwrap());
+
+    // An invalid submission from a key without access doesn't learn the pending calls
+    let (status, body) = post_as(&app, &uri, Some("bob"), json!({"tool_results": []})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(!body["error"].as_str().unwrap().contains("call_clock"));
+    assert_eq!(runs.len().await, 1);
+
+    let (status, body) = post_as(
+        &app,
+        &uri,
+        Some("alice"),
+        json!({"tool_results": [{"tool_call_id": "call_clock", "content": "09:15"}]}),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["output"], "It is 09:15.");
+}
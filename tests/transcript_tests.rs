@@ -0,0 +1,354 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+    routing::get,
+};
+use chrono::{TimeZone, Utc};
+use jarvis_rust::{
+    Result,
+    agent::Agent,
+    history::{HistoryStorage, Message, ToolCallRecord, TranscriptFormat, render_transcript},
+    mcp::{McpTool, McpToolCallResponse},
+    server::handlers::{AppState, session_transcript},
+    tools::{NativeTool, ToolContext, text_result},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+fn at(message: Message, seconds: u32) -> Message {
+    Message {
+        created_at: Utc
+            .with_ymd_and_hms(2026, 3, 1, 8, 0, seconds)
+            .single()
+            .unwrap(),
+        ..message
+    }
+}
+
+fn boiler_session() -> Vec<Message> {
+    let tool_calls = vec![ToolCallRecord {
+        name: "knowledge_search".to_string(),
+        arguments: json!({"query": "boiler pressure"}),
+        result: "1. docs/boiler.md (chunk 0, score 0.91)\nKeep it at 1.5 bar.".to_string(),
+        is_error: false,
+        duration_ms: 42,
+    }];
+    vec![
+        at(
+            Message::user("s1".to_string(), "What pressure <exactly>?".to_string()),
+            0,
+        ),
+        at(
+            Message::assistant("s1".to_string(), "1.5 bar.".to_string()).with_metadata(json!({
+                "tool_calls": tool_calls,
+                "citations": [{"source_id": "docs/boiler.md", "span": "chunk=0", "tool": "knowledge_search"}]
+            })),
+            3,
+        ),
+    ]
+}
+
+#[test]
+fn test_render_markdown_transcript() {
+    assert_eq!(
+        render_transcript("s1", &boiler_session(), TranscriptFormat::Markdown),
+        "# Session `s1`\n\
+         \n## User — 2026-03-01 08:00:00 UTC\n\
+         \nWhat pressure <exactly>?\n\
+         \n## Assistant — 2026-03-01 08:00:03 UTC (3.0 s)\n\
+         \n**Tool call:** `knowledge_search` · 42 ms\n\
+         \nArguments:\n```json\n{\n  \"query\": \"boiler pressure\"\n}\n```\n\
+         \nResult:\n```\n1. docs/boiler.md (chunk 0, score 0.91)\nKeep it at 1.5 bar.\n```\n\
+         \n1.5 bar.\n\
+         \n**Sources:** docs/boiler.md (chunk=0)\n"
+    );
+}
+
+#[test]
+fn test_render_html_transcript_escapes_content() {
+    let html = render_transcript("s1", &boiler_session(), TranscriptFormat::Html);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Session s1</title>"));
+    assert!(html.contains("<div class=\"content\">What pressure &lt;exactly&gt;?</div>"));
+    assert!(html.contains("<summary>Tool call <code>knowledge_search</code> · 42 ms</summary>"));
+    assert!(html.contains("<pre>{\n  &quot;query&quot;: &quot;boiler pressure&quot;\n}</pre>"));
+    assert!(html.contains("<span class=\"timing\">(3.0 s)</span>"));
+    assert!(html.contains("<p class=\"sources\">Sources: docs/boiler.md (chunk=0)</p>"));
+    assert!(html.ends_with("</body>\n</html>\n"));
+}
+
+#[test]
+fn test_code_blocks_outlast_backticks_in_results() {
+    let messages = vec![
+        Message::assistant("s1".to_string(), "Done".to_string()).with_metadata(json!({
+            "tool_calls": [{
+                "name": "shell",
+                "arguments": {},
+                "result": "```\nnested\n```",
+                "is_error": true,
+                "duration_ms": 1500
+            }]
+        })),
+    ];
+    let markdown = render_transcript("s1", &messages, TranscriptFormat::Markdown);
+    assert!(markdown.contains("**Tool call:** `shell` · 1.5 s · failed"));
+    assert!(markdown.contains("Result:\n````\n```\nnested\n```\n````"));
+}
+
+#[test]
+fn test_commentary_is_labelled_and_not_timed() {
+    let messages = vec![
+        at(Message::user("s1".to_string(), "Time?".to_string()), 0),
+        at(
+            Message::assistant("s1".to_string(), "Checking.".to_string())
+                .with_metadata(json!({"commentary": true})),
+            1,
+        ),
+        at(Message::assistant("s1".to_string(), "8:00".to_string()), 2),
+    ];
+    let markdown = render_transcript("s1", &messages, TranscriptFormat::Markdown);
+    assert!(markdown.contains("## Assistant (commentary) — 2026-03-01 08:00:01 UTC\n"));
+    assert!(markdown.contains("## Assistant — 2026-03-01 08:00:02 UTC (2.0 s)\n"));
+}
+
+/// Plain-text tool
+struct ClockTool;
+
+#[async_trait]
+impl NativeTool for ClockTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "clock".to_string(),
+            description: "Current time".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(text_result("08:00"))
+    }
+}
+
+async fn get_transcript(app: Router, uri: &str) -> (StatusCode, Option<String>, String) {
+    let response = app
+        .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_string());
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (
+        status,
+        content_type,
+        String::from_utf8(body.to_vec()).unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn test_transcript_endpoint_renders_recorded_tool_calls() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response(
+        "clock",
+        r#"{"zone": "UTC"}"#,
+    ));
+    mock_llm.add_response(create_mock_chat_response("It is 8 o'clock."));
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ClockTool));
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    agent
+        .process("s1", "What time is it?", &history)
+        .await
+        .unwrap();
+
+    let messages = history.list("s1").await.unwrap();
+    let recorded: Vec<ToolCallRecord> =
+        serde_json::from_value(messages[1].metadata.as_ref().unwrap()["tool_calls"].clone())
+            .unwrap();
+    assert_eq!(recorded.len(), 1);
+    assert_eq!(recorded[0].name, "clock");
+    assert_eq!(recorded[0].arguments, json!({"zone": "UTC"}));
+    assert_eq!(recorded[0].result, "08:00");
+    assert!(!recorded[0].is_error);
+
+    let state = AppState {
+        history,
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/sessions/:session_id/transcript", get(session_transcript))
+        .with_state(state);
+
+    let (status, content_type, body) = get_transcript(app.clone(), "/sessions/s1/transcript").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        content_type.as_deref(),
+        Some("text/markdown; charset=utf-8")
+    );
+    assert!(body.starts_with("# Session `s1`"));
+    assert!(body.contains("**Tool call:** `clock`"));
+    assert!(body.contains("**Sources:** tool:clock"));
+
+    let (status, content_type, body) =
+        get_transcript(app.clone(), "/sessions/s1/transcript?format=html").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("text/html; charset=utf-8"));
+    assert!(body.contains("It is 8 o&#39;clock."));
+
+    let (status, _, _) = get_transcript(app.clone(), "/sessions/s1/transcript?format=pdf").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _, _) = get_transcript(app, "/sessions/unknown/transcript").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_transcript_endpoint_answers_unchanged_sessions_with_304() {
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    history
+        .save(Message::user("s1".to_string(), "Time?".to_string()))
+        .await
+        .unwrap();
+    let agent = Agent::new_for_testing(
+        Box::new(MockLlmClient::new()),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        tasks: None,
+        runs: Default::default(),
+        api_keys: Default::default(),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/sessions/:session_id/transcript", get(session_transcript))
+        .with_state(state);
+    let get_with = |uri: &str, etag: Option<&str>| {
+        let mut request = Request::builder().uri(uri);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        app.clone().oneshot(request.body(Body::empty()).unwrap())
+    };
+    let etag_of = |response: &axum::response::Response| {
+        response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string()
+    };
+
+    let response = get_with("/sessions/s1/transcript", None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+    let etag = etag_of(&response);
+
+    let response = get_with("/sessions/s1/transcript", Some(&etag))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(etag_of(&response), etag);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+    let weak = format!("\"other\", W/{etag}");
+    let response = get_with("/sessions/s1/transcript", Some(&weak))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // Each format is its own representation
+    let response = get_with("/sessions/s1/transcript?format=html", Some(&etag))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(etag_of(&response), etag);
+
+    history
+        .save(Message::assistant("s1".to_string(), "8:00".to_string()))
+        .await
+        .unwrap();
+    let response = get_with("/sessions/s1/transcript", Some(&etag))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let after_reply = etag_of(&response);
+    assert_ne!(after_reply, etag);
+
+    let id = history.list("s1").await.unwrap()[1].id.unwrap();
+    history.delete(id).await.unwrap();
+    let response = get_with("/sessions/s1/transcript", Some(&after_reply))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(etag_of(&response), etag);
+
+    // A session that doesn't exist is a 404 even for a wildcard validator
+    let response = get_with("/sessions/missing/transcript", Some("*"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
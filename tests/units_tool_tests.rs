@@ -0,0 +1,167 @@
+use jarvis_rust::{
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        currency::{FrankfurterRateSource, RateSource, StaticRateSource},
+        units::{ConvertUnitsTool, FormatNumberTool, convert, format_number},
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path, query_param},
+};
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-6,
+        "expected {expected}, got {actual}"
+    );
+}
+
+#[test]
+fn test_physical_conversions() {
+    assert_close(convert(5.0, "km", "mi").unwrap(), 3.106_855_961);
+    assert_close(convert(100.0, "°C", "F").unwrap(), 212.0);
+    assert_close(convert(32.0, "fahrenheit", "celsius").unwrap(), 0.0);
+    assert_close(convert(0.0, "C", "K").unwrap(), 273.15);
+    assert_close(convert(90.0, "minutes", "hours").unwrap(), 1.5);
+    assert_close(convert(2.0, "lbs", "kg").unwrap(), 0.907_184_74);
+    assert_close(convert(1.0, "gal", "l").unwrap(), 3.785_411_784);
+    assert_close(convert(100.0, "km/h", "mph").unwrap(), 62.137_119_224);
+    assert_close(convert(1.0, "GiB", "MB").unwrap(), 1_073.741_824);
+}
+
+#[test]
+fn test_incompatible_and_unknown_units() {
+    let err = convert(1.0, "kg", "m").unwrap_err();
+    assert!(err.to_string().contains("Cannot convert"));
+    let err = convert(1.0, "parsec", "m").unwrap_err();
+    assert!(err.to_string().contains("Unknown unit 'parsec'"));
+}
+
+#[test]
+fn test_format_number_locales() {
+    assert_eq!(format_number(1234567.891, "en-US", 2), "1,234,567.89");
+    assert_eq!(format_number(1234567.891, "de-DE", 2), "1.234.567,89");
+    assert_eq!(
+        format_number(1234567.891, "fr-FR", 1),
+        "1\u{a0}234\u{a0}567,9"
+    );
+    assert_eq!(format_number(-1234.6, "de-CH", 0), "-1'235");
+    assert_eq!(format_number(999.0, "en", 0), "999");
+    assert_eq!(format_number(-0.001, "en", 2), "0.00");
+}
+
+#[tokio::test]
+async fn test_convert_units_tool_output() {
+    let tool = ConvertUnitsTool::new(None);
+    let ctx = ToolContext::new("s", None);
+
+    let response = tool
+        .call(args(json!({"value": 5, "from": "km", "to": "mi"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "5 km = 3.10686 mi");
+
+    // Models frequently pass numbers as strings
+    let response = tool
+        .call(args(json!({"value": "72", "from": "F", "to": "C"})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "72 F = 22.2222 C");
+
+    let err = tool
+        .call(args(json!({"value": 1, "from": "USD", "to": "EUR"})), &ctx)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("not configured"));
+}
+
+#[tokio::test]
+async fn test_static_currency_rates() {
+    let mut rates = HashMap::new();
+    rates.insert("eur".to_string(), 0.5);
+    rates.insert("BRL".to_string(), 5.0);
+    let source = StaticRateSource::new("usd".to_string(), rates);
+
+    assert_close(source.rate("USD", "EUR").await.unwrap(), 0.5);
+    assert_close(source.rate("EUR", "BRL").await.unwrap(), 10.0);
+    assert!(source.rate("USD", "GBP").await.is_err());
+
+    let tool = ConvertUnitsTool::new(Some(Arc::new(source)));
+    let response = tool
+        .call(
+            args(json!({"value": 20, "from": "usd", "to": "brl"})),
+            &ToolContext::new("s", None),
+        )
+        .await
+        .unwrap();
+    assert_eq!(text(&response), "20 usd = 100 brl");
+}
+
+#[tokio::test]
+async fn test_frankfurter_rate_source() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/latest"))
+        .and(query_param("from", "USD"))
+        .and(query_param("to", "EUR"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "amount": 1.0,
+            "base": "USD",
+            "date": "2024-01-02",
+            "rates": {"EUR": 0.91}
+        })))
+        .mount(&server)
+        .await;
+
+    let source = FrankfurterRateSource::new(Some(server.uri()));
+    assert_close(source.rate("USD", "EUR").await.unwrap(), 0.91);
+    assert_close(source.rate("EUR", "EUR").await.unwrap(), 1.0);
+}
+
+#[tokio::test]
+async fn test_format_number_tool_styles() {
+    let tool = FormatNumberTool;
+    let ctx = ToolContext::new("s", None);
+    let run = |value: Value| {
+        let tool = &tool;
+        let ctx = &ctx;
+        async move { text(&tool.call(args(value), ctx).await.unwrap()).to_string() }
+    };
+
+    assert_eq!(run(json!({"value": 1234.5})).await, "1,234.50");
+    assert_eq!(
+        run(json!({"value": 0.125, "style": "percent", "decimals": 1})).await,
+        "12.5%"
+    );
+    assert_eq!(
+        run(json!({"value": 1234.5, "style": "currency", "currency": "eur", "locale": "de-DE"}))
+            .await,
+        "1.234,50\u{a0}€"
+    );
+    assert_eq!(
+        run(json!({"value": -5, "style": "currency", "currency": "USD"})).await,
+        "-$5.00"
+    );
+    assert_eq!(
+        run(json!({"value": 1500, "style": "currency", "currency": "JPY", "locale": "ja-JP"}))
+            .await,
+        "¥1,500"
+    );
+}
@@ -0,0 +1,546 @@
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use chrono::{Duration, Utc};
+use jarvis_rust::{
+    agent::{Agent, AgentHook, HookContext},
+    config::{ApiKeyConfig, ModelPrice, UsageConfig},
+    history::HistoryStorage,
+    llm::{ChatCompletionResponse, Usage},
+    mcp::McpToolCallRequest,
+    server::handlers::{AppState, inference, key_usage, session_usage, usage_report},
+    tools::text_result,
+    usage::{ModelUsage, Tally, UsageHook, UsageStore, UsageSummary, cost_of},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response};
+
+fn prices() -> HashMap<String, ModelPrice> {
+    HashMap::from([(
+        "gpt-4o-mini".to_string(),
+        ModelPrice {
+            input_per_million: 0.15,
+            output_per_million: 0.6,
+        },
+    )])
+}
+
+fn reply_using(
+    content: &str,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+) -> ChatCompletionResponse {
+    let mut response = create_mock_chat_response(content);
+    response.usage = Some(Usage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        reasoning_tokens: None,
+    });
+    response
+}
+
+#[test]
+fn test_usage_config_parse() {
+    let config: UsageConfig = serde_yaml::from_str(
+        r#"
+prices:
+  gpt-4o-mini:
+    input_per_million: 0.15
+    output_per_million: 0.6
+"#,
+    )
+    .unwrap();
+    assert_eq!(config.prices, prices());
+    assert!(UsageConfig::default().prices.is_empty());
+}
+
+#[test]
+fn test_cost_of() {
+    let tally = Tally {
+        prompt_tokens: 2_000_000,
+        completion_tokens: 500_000,
+        reasoning_tokens: 0,
+        tool_calls: 0,
+    };
+    let cost = cost_of(tally, &prices()["gpt-4o-mini"]);
+    assert!((cost - 0.6).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn test_hook_tallies_only_metered_sessions() {
+    let store = Arc::new(UsageStore::new(":memory:", prices()).await.unwrap());
+    let hook = UsageHook::new(store.clone());
+
+    store.begin("metered");
+    for session_id in ["metered", "other"] {
+        let ctx = HookContext::new(session_id, 0);
+        hook.after_llm_call(&ctx, &reply_using("Hi", 100, 20))
+            .await
+            .unwrap();
+        hook.after_llm_call(&ctx, &create_mock_chat_response("No usage reported"))
+            .await
+            .unwrap();
+    }
+    hook.after_llm_call(&HookContext::new("metered", 1), &reply_using("Hi", 50, 5))
+        .await
+        .unwrap();
+    let call = McpToolCallRequest {
+        name: "get_weather".to_string(),
+        arguments: HashMap::new(),
+    };
+    for session_id in ["metered", "metered", "other"] {
+        hook.after_tool(
+            &HookContext::new(session_id, 0),
+            &call,
+            &mut text_result("Sunny"),
+        )
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(
+        store.take("metered"),
+        Tally {
+            prompt_tokens: 150,
+            completion_tokens: 25,
+            reasoning_tokens: 0,
+            tool_calls: 2,
+        }
+    );
+    assert_eq!(store.take("metered"), Tally::default());
+    assert_eq!(store.take("other"), Tally::default());
+}
+
+#[tokio::test]
+async fn test_summary_covers_the_period() {
+    let store = UsageStore::new(":memory:", prices()).await.unwrap();
+    let tally = Tally {
+        prompt_tokens: 1000,
+        completion_tokens: 200,
+        reasoning_tokens: 0,
+        tool_calls: 0,
+    };
+    store
+        .record_request(Some("cheap"), "s1", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+    // Models without a price cost nothing
+    store
+        .record_request(Some("cheap"), "s2", "gpt-4o", tally)
+        .await
+        .unwrap();
+    store.record_rate_limited("cheap", "s3").await.unwrap();
+    store
+        .record_request(Some("admin"), "s4", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+    store
+        .record_request(None, "s5", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+
+    let now = Utc::now();
+    let summary = store
+        .summary("cheap", now - Duration::hours(1), now + Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(summary.requests, 2);
+    assert_eq!(summary.prompt_tokens, 2000);
+    assert_eq!(summary.completion_tokens, 400);
+    assert_eq!(summary.total_tokens, 2400);
+    assert!((summary.cost - 0.00027).abs() < 1e-12);
+    assert_eq!(summary.rate_limit_hits, 1);
+    assert_eq!(
+        store
+            .requests_since("cheap", now - Duration::minutes(1))
+            .await
+            .unwrap(),
+        2
+    );
+
+    let earlier = store
+        .summary("cheap", now - Duration::days(2), now - Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(earlier, UsageSummary::default());
+}
+
+#[tokio::test]
+async fn test_session_summary_splits_by_model() {
+    let store = UsageStore::new(":memory:", prices()).await.unwrap();
+    let tally = Tally {
+        prompt_tokens: 1000,
+        completion_tokens: 200,
+        reasoning_tokens: 0,
+        tool_calls: 0,
+    };
+    for model in ["gpt-4o-mini", "gpt-4o", "gpt-4o-mini"] {
+        store
+            .record_request(Some("cheap"), "s1", model, tally)
+            .await
+            .unwrap();
+    }
+    store.record_rate_limited("cheap", "s1").await.unwrap();
+    store
+        .record_request(Some("cheap"), "s2", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+
+    let usage = store.session_summary("s1").await.unwrap();
+    assert_eq!(usage.total.requests, 3);
+    assert_eq!(usage.total.total_tokens, 3600);
+    assert_eq!(usage.total.rate_limit_hits, 1);
+    assert!((usage.total.cost - 0.00054).abs() < 1e-12);
+    assert_eq!(
+        usage.models[0],
+        ModelUsage {
+            model: "gpt-4o".to_string(),
+            requests: 1,
+            prompt_tokens: 1000,
+            completion_tokens: 200,
+            reasoning_tokens: 0,
+            total_tokens: 1200,
+            cost: 0.0,
+        }
+    );
+    assert_eq!(usage.models[1].model, "gpt-4o-mini");
+    assert_eq!(usage.models[1].requests, 2);
+    assert_eq!(usage.models.len(), 2);
+
+    let unknown = store.session_summary("s3").await.unwrap();
+    assert_eq!(unknown.total, UsageSummary::default());
+    assert!(unknown.models.is_empty());
+}
+
+#[tokio::test]
+async fn test_report_groups_by_day_and_session() {
+    let store = UsageStore::new(":memory:", prices()).await.unwrap();
+    let tally = Tally {
+        prompt_tokens: 1000,
+        completion_tokens: 200,
+        reasoning_tokens: 0,
+        tool_calls: 3,
+    };
+    store
+        .record_request(Some("cheap"), "s1", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+    store
+        .record_request(None, "s2", "gpt-4o-mini", tally)
+        .await
+        .unwrap();
+    store
+        .record_request(None, "s2", "gpt-4o", tally)
+        .await
+        .unwrap();
+    store.record_rate_limited("cheap", "s3").await.unwrap();
+
+    let now = Utc::now();
+    let report = store
+        .report(now - Duration::hours(1), now + Duration::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(report.total.requests, 3);
+    assert_eq!(report.total.tool_calls, 9);
+    assert_eq!(report.total.total_tokens, 3600);
+    assert_eq!(report.total.rate_limit_hits, 1);
+    assert!((report.total.cost - 0.00054).abs() < 1e-12);
+
+    assert_eq!(report.days.len(), 1);
+    assert_eq!(report.days[0].day, now.date_naive());
+    assert_eq!(report.days[0].usage, report.total);
+
+    let sessions: Vec<(&str, u64, u64)> = report
+        .sessions
+        .iter()
+        .map(|session| {
+            (
+                session.session_id.as_str(),
+                session.usage.requests,
+                session.usage.tool_calls,
+            )
+        })
+        .collect();
+    // Costliest first; both s1 and s2 paid for one gpt-4o-mini request
+    assert_eq!(sessions, vec![("s1", 1, 3), ("s2", 2, 6), ("s3", 0, 0)]);
+
+    let earlier = store
+        .report(now - Duration::days(2), now - Duration::days(1))
+        .await
+        .unwrap();
+    assert_eq!(earlier.total, UsageSummary::default());
+    assert!(earlier.days.is_empty() && earlier.sessions.is_empty());
+}
+
+fn limited_key() -> ApiKeyConfig {
+    ApiKeyConfig {
+        name: "cheap".to_string(),
+        key: "cheap-key".to_string(),
+        models: vec!["gpt-4o-mini".to_string()],
+        default_model: Some("gpt-4o-mini".to_string()),
+        requests_per_minute: Some(2),
+        admin: false,
+    }
+}
+
+async fn app() -> Router {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(reply_using("Hello!", 1000, 200));
+    mock_llm.add_response(reply_using("Hello again!", 3000, 100));
+    let usage = Arc::new(UsageStore::new(":memory:", prices()).await.unwrap());
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.add_hook(Arc::new(UsageHook::new(usage.clone())));
+    let state = AppState {
+        history: Arc::new(HistoryStorage::new(":memory:").await.unwrap()),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(vec![
+            limited_key(),
+            ApiKeyConfig {
+                name: "ops".to_string(),
+                key: "ops-key".to_string(),
+                models: Vec::new(),
+                default_model: None,
+                requests_per_minute: None,
+                admin: true,
+            },
+        ]),
+        usage: Some(usage),
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    Router::new()
+        .route("/", axum::routing::post(inference))
+        .route("/usage", axum::routing::get(usage_report))
+        .route("/keys/:name/usage", axum::routing::get(key_usage))
+        .route(
+            "/sessions/:session_id/usage",
+            axum::routing::get(session_usage),
+        )
+        .with_state(state)
+}
+
+async fn send(app: &Router, request: Request<Body>) -> (StatusCode, Value) {
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn ask(input: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::AUTHORIZATION, "Bearer cheap-key")
+        .body(Body::from(json!({"input": input}).to_string()))
+        .unwrap()
+}
+
+fn usage_of(name: &str, key: &str) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/keys/{name}/usage"))
+        .header(header::AUTHORIZATION, format!("Bearer {key}-key"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_key_usage_endpoint_and_rate_limit() {
+    let app = app().await;
+
+    assert_eq!(send(&app, ask("Hi")).await.0, StatusCode::OK);
+    assert_eq!(send(&app, ask("Hi again")).await.0, StatusCode::OK);
+    let (status, body) = send(&app, ask("And again")).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        body["error"],
+        "Rate limit of 2 requests per minute exceeded"
+    );
+
+    let (status, body) = send(&app, usage_of("cheap", "cheap")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["key"], "cheap");
+    assert_eq!(body["requests"], 2);
+    assert_eq!(body["prompt_tokens"], 4000);
+    assert_eq!(body["completion_tokens"], 300);
+    assert_eq!(body["total_tokens"], 4300);
+    assert!((body["cost"].as_f64().unwrap() - 0.00078).abs() < 1e-12);
+    assert_eq!(body["rate_limit_hits"], 1);
+    assert!(body["since"].is_string());
+    assert!(body["until"].is_string());
+}
+
+#[tokio::test]
+async fn test_key_usage_endpoint_rejects_unknown_keys_and_bad_periods() {
+    let app = app().await;
+
+    let (status, body) = send(&app, usage_of("stolen", "ops")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(body["error"], "No API key named 'stolen'");
+
+    let request = Request::builder()
+        .uri("/keys/cheap/usage?since=2026-02-01T00:00:00Z&until=2026-01-01T00:00:00Z")
+        .header(header::AUTHORIZATION, "Bearer cheap-key")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::BAD_REQUEST);
+
+    // The default period would start before the earliest date there is
+    let request = Request::builder()
+        .uri("/keys/cheap/usage?until=-262143-01-01T00:00:00Z")
+        .header(header::AUTHORIZATION, "Bearer cheap-key")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, request).await.0, StatusCode::BAD_REQUEST);
+
+    let request = Request::builder()
+        .uri("/keys/cheap/usage?since=2026-01-01T00:00:00Z&until=2026-02-01T00:00:00Z")
+        .header(header::AUTHORIZATION, "Bearer cheap-key")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["since"], "2026-01-01T00:00:00Z");
+    assert_eq!(body["requests"], 0);
+}
+
+#[tokio::test]
+async fn test_key_usage_is_kept_from_other_keys() {
+    let app = app().await;
+    assert_eq!(send(&app, ask("Hi")).await.0, StatusCode::OK);
+
+    let (status, _) = send(&app, usage_of("ops", "cheap")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, usage_of("stolen", "cheap")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = send(&app, usage_of("cheap", "ops")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["requests"], 1);
+
+    let anonymous = Request::builder()
+        .uri("/keys/cheap/usage")
+        .body(Body::empty())
+        .unwrap();
+    assert_eq!(send(&app, anonymous).await.0, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_session_usage_endpoint() {
+    let app = app().await;
+    let ask_in = |session_id: &str| {
+        Request::builder()
+            .method("POST")
+            .uri("/")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, "Bearer cheap-key")
+            .body(Body::from(
+                json!({"input": "Hi", "session_id": session_id}).to_string(),
+            ))
+            .unwrap()
+    };
+    assert_eq!(send(&app, ask_in("s1")).await.0, StatusCode::OK);
+    assert_eq!(send(&app, ask_in("s2")).await.0, StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/sessions/s1/usage")
+        .header(header::AUTHORIZATION, "Bearer cheap-key")
+        .body(Body::empty())
+        .unwrap();
+    let (status, body) = send(&app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["session_id"], "s1");
+    assert_eq!(body["requests"], 1);
+    assert_eq!(body["prompt_tokens"], 1000);
+    assert_eq!(body["completion_tokens"], 200);
+    assert_eq!(body["total_tokens"], 1200);
+    assert!((body["cost"].as_f64().unwrap() - 0.00027).abs() < 1e-12);
+    assert_eq!(body["models"][0]["model"], "gpt-4o-mini");
+    assert_eq!(body["models"][0]["requests"], 1);
+}
+
+#[tokio::test]
+async fn test_usage_report_endpoint() {
+    let app = app().await;
+    assert_eq!(send(&app, ask("Hi")).await.0, StatusCode::OK);
+    let report = |uri: &str, key: &str| {
+        Request::builder()
+            .uri(uri)
+            .header(header::AUTHORIZATION, format!("Bearer {key}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let (status, _) = send(&app, report("/usage", "cheap-key")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(&app, report("/usage", "ops-key")).await;
+    assert_eq!(status, StatusCode::OK);
+    let today = Utc::now().date_naive();
+    assert_eq!(body["to"], json!(today));
+    assert_eq!(body["from"], json!(today - Duration::days(29)));
+    assert_eq!(body["total"]["requests"], 1);
+    assert_eq!(body["total"]["prompt_tokens"], 1000);
+    assert_eq!(body["days"][0]["day"], json!(today));
+    assert_eq!(body["days"][0]["completion_tokens"], 200);
+    assert_eq!(body["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(body["sessions"][0]["requests"], 1);
+    assert_eq!(body["sessions"][0]["tool_calls"], 0);
+
+    let (status, body) = send(
+        &app,
+        report("/usage?from=2026-01-01&to=2026-01-31", "ops-key"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"]["requests"], 0);
+    assert_eq!(body["days"], json!([]));
+
+    let (status, _) = send(
+        &app,
+        report("/usage?from=2026-02-01&to=2026-01-01", "ops-key"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Ranges reaching the ends of the dates there are
+    for uri in [
+        "/usage?to=-262143-01-01",
+        "/usage?from=2026-01-01&to=%2B262142-12-31",
+    ] {
+        let (status, _) = send(&app, report(uri, "ops-key")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}");
+    }
+}
@@ -0,0 +1,83 @@
+use axum::{Router, body::Body, http::Request, routing::get};
+use jarvis_rust::{
+    config::UpdatesConfig,
+    server::handlers::version,
+    version::{BUILD, Release, is_newer, latest_release, spawn_update_check},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{header_exists, method, path},
+};
+
+#[tokio::test]
+async fn test_version_reports_build() {
+    let app = Router::new().route("/version", get(version));
+
+    let response = app
+        .oneshot(Request::get("/version").body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(body["version"], json!(env!("CARGO_PKG_VERSION")));
+    assert_eq!(body["git_sha"], json!(BUILD.git_sha));
+    assert!(!BUILD.git_sha.is_empty());
+    let date = body["build_date"].as_str().unwrap();
+    assert!(
+        chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok(),
+        "{date}"
+    );
+}
+
+#[test]
+fn test_newer_versions_are_recognized() {
+    assert!(is_newer("v0.3.0", "0.2.9"));
+    assert!(is_newer("1.10.0", "1.9.0"));
+    assert!(is_newer("v1.2.1-rc.1", "1.2.0"));
+    assert!(!is_newer("v1.2", "1.2.0"));
+    assert!(!is_newer("1.2.0", "1.2.0"));
+    assert!(!is_newer("1.1.9", "1.2.0"));
+    assert!(!is_newer("nightly", "1.2.0"));
+}
+
+#[tokio::test]
+async fn test_latest_release_is_fetched() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/repos/comigor/jarvis-rs/releases/latest"))
+        .and(header_exists("user-agent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "tag_name": "v9.0.0",
+            "html_url": "https://github.com/comigor/jarvis-rs/releases/tag/v9.0.0",
+            "name": "Jarvis 9"
+        })))
+        .mount(&server)
+        .await;
+
+    let release = latest_release(&reqwest::Client::new(), &server.uri(), "comigor/jarvis-rs")
+        .await
+        .unwrap();
+
+    assert_eq!(
+        release,
+        Release {
+            tag_name: "v9.0.0".to_string(),
+            html_url: "https://github.com/comigor/jarvis-rs/releases/tag/v9.0.0".to_string(),
+        }
+    );
+    assert!(is_newer(&release.tag_name, BUILD.version));
+}
+
+#[test]
+fn test_update_check_is_opt_in() {
+    let config: UpdatesConfig = serde_yaml::from_str("{}").unwrap();
+    assert!(!config.check);
+    assert_eq!(config.repository, "comigor/jarvis-rs");
+    assert!(spawn_update_check(&config).is_none());
+}
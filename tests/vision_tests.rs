@@ -0,0 +1,367 @@
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Body,
+    http::{Request, StatusCode, header},
+};
+use jarvis_rust::{
+    Result,
+    agent::{Agent, tool_images},
+    config::LlmConfig,
+    history::HistoryStorage,
+    llm::{
+        ChatCompletionRequest, ChatMessage, ContentPart, FunctionCall, GeminiClient, LlmClient,
+        MessageContent, OllamaClient, OpenAiClient, ToolCall,
+    },
+    mcp::{McpContent, McpTool, McpToolCallResponse},
+    server::handlers::{AppState, inference},
+    tools::{NativeTool, ToolContext},
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+use tower::ServiceExt;
+
+mod common;
+use common::{MockLlmClient, create_mock_chat_response, create_mock_tool_call_response};
+
+/// Takes a screenshot, returning it as an image
+struct ScreenshotTool;
+
+#[async_trait]
+impl NativeTool for ScreenshotTool {
+    fn definition(&self) -> McpTool {
+        McpTool {
+            name: "screenshot".to_string(),
+            description: "Captures the screen".to_string(),
+            input_schema: json!({"type": "object"}),
+            output_schema: None,
+        }
+    }
+
+    async fn call(
+        &self,
+        _arguments: HashMap<String, Value>,
+        _ctx: &ToolContext<'_>,
+    ) -> Result<McpToolCallResponse> {
+        Ok(McpToolCallResponse {
+            content: vec![
+                McpContent::Text {
+                    text: "Captured".to_string(),
+                },
+                McpContent::Image {
+                    data: "iVBORw0K".to_string(),
+                    mime_type: "image/png".to_string(),
+                },
+            ],
+            is_error: false,
+        })
+    }
+}
+
+fn llm_config(provider: &str) -> LlmConfig {
+    serde_yaml::from_str(&format!(
+        r#"
+provider: "{provider}"
+base_url: "http://localhost:1"
+api_key: "key"
+model: "some-model"
+"#
+    ))
+    .unwrap()
+}
+
+fn request() -> ChatCompletionRequest {
+    ChatCompletionRequest {
+        model: String::new(),
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: MessageContent::Parts(vec![
+                ContentPart::Text {
+                    text: "What's in these?".to_string(),
+                },
+                ContentPart::ImageUrl {
+                    url: "https://example.com/cat.jpg".to_string(),
+                },
+                ContentPart::ImageBase64 {
+                    mime_type: "image/png".to_string(),
+                    data: "iVBORw0K".to_string(),
+                },
+            ]),
+            tool_calls: None,
+            tool_call_id: None,
+            name: None,
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_content_parts_are_typed() {
+    let parts: Vec<ContentPart> = serde_json::from_value(json!([
+        {"type": "text", "text": "Look"},
+        {"type": "image_url", "url": "https://example.com/cat.jpg"},
+        {"type": "image_base64", "mime_type": "image/png", "data": "iVBORw0K"}
+    ]))
+    .unwrap();
+    assert_eq!(parts[0].image_url(), None);
+    assert_eq!(
+        parts[1].image_url().as_deref(),
+        Some("https://example.com/cat.jpg")
+    );
+    assert_eq!(
+        parts[2].image_url().as_deref(),
+        Some("data:image/png;base64,iVBORw0K")
+    );
+    // Data URLs are inline images too
+    let data_url = ContentPart::ImageUrl {
+        url: "data:image/jpeg;base64,/9j/4AAQ".to_string(),
+    };
+    assert_eq!(
+        data_url.inline_image(),
+        Some(("image/jpeg".to_string(), "/9j/4AAQ".to_string()))
+    );
+    assert_eq!(parts[1].inline_image(), None);
+}
+
+#[test]
+fn test_providers_send_images() {
+    let openai = OpenAiClient::new(llm_config("openai"))
+        .request_payload(&request())
+        .unwrap();
+    assert_eq!(
+        openai["messages"][0]["content"],
+        json!([
+            {"type": "text", "text": "What's in these?"},
+            {"type": "image_url", "image_url": {"url": "https://example.com/cat.jpg", "detail": "auto"}},
+            {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K", "detail": "auto"}}
+        ])
+    );
+
+    let gemini = GeminiClient::new(llm_config("gemini"))
+        .request_payload(&request())
+        .unwrap();
+    assert_eq!(
+        gemini["contents"][0]["parts"],
+        json!([
+            {"text": "What's in these?"},
+            {"fileData": {"fileUri": "https://example.com/cat.jpg"}},
+            {"inlineData": {"mimeType": "image/png", "data": "iVBORw0K"}}
+        ])
+    );
+
+    // Ollama can't fetch URLs, so only the inline image is sent
+    let ollama = OllamaClient::new(llm_config("ollama"))
+        .request_payload(&request())
+        .unwrap();
+    assert_eq!(
+        ollama["messages"][0],
+        json!({"role": "user", "content": "What's in these?", "images": ["iVBORw0K"]})
+    );
+}
+
+#[test]
+fn test_text_messages_stay_plain() {
+    let mut request = request();
+    request.messages[0].content = "What's in these?".into();
+    let openai = OpenAiClient::new(llm_config("openai"))
+        .request_payload(&request)
+        .unwrap();
+    assert_eq!(openai["messages"][0]["content"], json!("What's in these?"));
+}
+
+#[tokio::test]
+async fn test_tool_images_reach_the_llm() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_tool_call_response("screenshot", "{}"));
+    mock_llm.add_response(create_mock_chat_response("A terminal window."));
+    let requests = mock_llm.requests.clone();
+    let mut agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    agent.register_native_tool(Arc::new(ScreenshotTool));
+    let history = HistoryStorage::new(":memory:").await.unwrap();
+
+    let reply = agent
+        .process_with_citations("s1", "What's on my screen?", &history)
+        .await
+        .unwrap();
+
+    assert_eq!(reply.output, "A terminal window.");
+    let requests = requests.lock().unwrap();
+    // The image goes along with the tool's result
+    let tool = requests[1].messages.last().unwrap();
+    assert_eq!(tool.role, "tool");
+    assert_eq!(
+        tool.content,
+        MessageContent::Parts(vec![
+            ContentPart::Text {
+                text: "Captured\n[image: image/png]".to_string(),
+            },
+            ContentPart::ImageBase64 {
+                mime_type: "image/png".to_string(),
+                data: "iVBORw0K".to_string(),
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_providers_send_tool_images() {
+    let message = |role: &str, content: MessageContent| ChatMessage {
+        role: role.to_string(),
+        content,
+        tool_calls: None,
+        tool_call_id: None,
+        name: None,
+    };
+    let screenshot = MessageContent::from("Captured").with_images(vec![ContentPart::ImageBase64 {
+        mime_type: "image/png".to_string(),
+        data: "iVBORw0K".to_string(),
+    }]);
+    let request = ChatCompletionRequest {
+        messages: vec![
+            message("user", "What's on my screen?".into()),
+            ChatMessage {
+                tool_calls: Some(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "screenshot".to_string(),
+                        arguments: "{}".to_string(),
+                    },
+                }]),
+                ..message("assistant", "".into())
+            },
+            ChatMessage {
+                tool_call_id: Some("call_1".to_string()),
+                ..message("tool", screenshot)
+            },
+        ],
+        ..Default::default()
+    };
+
+    // OpenAI's tool messages only carry text, so the image follows in a user message
+    let openai = OpenAiClient::new(llm_config("openai"))
+        .request_payload(&request)
+        .unwrap();
+    assert_eq!(openai["messages"][2]["role"], "tool");
+    assert_eq!(openai["messages"][2]["content"], "Captured");
+    assert_eq!(openai["messages"][3]["role"], "user");
+    assert_eq!(
+        openai["messages"][3]["content"][1],
+        json!({"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0K", "detail": "auto"}})
+    );
+
+    let gemini = GeminiClient::new(llm_config("gemini"))
+        .request_payload(&request)
+        .unwrap();
+    assert_eq!(
+        gemini["contents"][2]["parts"],
+        json!([
+            {"functionResponse": {"name": "screenshot", "response": {"result": "Captured"}}},
+            {"inlineData": {"mimeType": "image/png", "data": "iVBORw0K"}}
+        ])
+    );
+
+    let ollama = OllamaClient::new(llm_config("ollama"))
+        .request_payload(&request)
+        .unwrap();
+    assert_eq!(ollama["messages"][2]["role"], "tool");
+    assert_eq!(ollama["messages"][2]["images"], json!(["iVBORw0K"]));
+}
+
+#[test]
+fn test_tool_images_skip_text() {
+    let response = McpToolCallResponse {
+        content: vec![McpContent::Text {
+            text: "No image".to_string(),
+        }],
+        is_error: false,
+    };
+    assert!(tool_images(&response).is_empty());
+}
+
+#[tokio::test]
+async fn test_inference_accepts_images() {
+    let mock_llm = MockLlmClient::new();
+    mock_llm.add_response(create_mock_chat_response("A cat."));
+    let requests = mock_llm.requests.clone();
+    let agent = Agent::new_for_testing(
+        Box::new(mock_llm),
+        HashMap::new(),
+        HashMap::new(),
+        Vec::new(),
+    );
+    let history = Arc::new(HistoryStorage::new(":memory:").await.unwrap());
+    let state = AppState {
+        history: history.clone(),
+        agent: Arc::new(Mutex::new(agent)),
+        notifier: None,
+        followups: None,
+        tasks: None,
+        profiles: None,
+        #[cfg(feature = "knowledge")]
+        knowledge: None,
+        events: None,
+        runs: Default::default(),
+        api_keys: Arc::new(Vec::new()),
+        usage: None,
+        timeline: None,
+        sessions: None,
+        examples: Default::default(),
+        commands: Default::default(),
+        formatting: Default::default(),
+        progress: Default::default(),
+        security: None,
+        system_prompts: None,
+        models: None,
+        run_metrics: None,
+        features: Default::default(),
+    };
+    let app = Router::new()
+        .route("/", axum::routing::post(inference))
+        .with_state(state);
+
+    let body = json!({
+        "session_id": "s1",
+        "input": "What animal is this?",
+        "images": [{"type": "image_url", "url": "https://example.com/cat.jpg"}]
+    });
+    let response = app
+        .oneshot(
+            Request::builder()
+                .method("POST")
+                .uri("/")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let input = requests.lock().unwrap()[0]
+        .messages
+        .last()
+        .cloned()
+        .unwrap();
+    assert_eq!(
+        input.content,
+        MessageContent::Parts(vec![
+            ContentPart::Text {
+                text: "What animal is this?".to_string(),
+            },
+            ContentPart::ImageUrl {
+                url: "https://example.com/cat.jpg".to_string(),
+            },
+        ])
+    );
+    // The history keeps the text
+    let saved = history.list("s1").await.unwrap();
+    assert_eq!(saved[0].content, "What animal is this?");
+}
@@ -0,0 +1,166 @@
+#![cfg(feature = "web-search")]
+
+use jarvis_rust::{
+    config::{WebSearchProvider, WebSearchToolConfig},
+    mcp::{McpContent, McpToolCallResponse},
+    tools::{
+        NativeTool, ToolContext,
+        web_search::{
+            BraveProvider, SearchProvider, SearchResult, SearxngProvider, TavilyProvider,
+            WebSearchTool, create_search_provider, format_results,
+        },
+    },
+};
+use pretty_assertions::assert_eq;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::Arc;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_partial_json, header, method, path, query_param},
+};
+
+fn args(value: Value) -> HashMap<String, Value> {
+    serde_json::from_value(value).unwrap()
+}
+
+fn text(response: &McpToolCallResponse) -> &str {
+    match &response.content[0] {
+        McpContent::Text { text } => text,
+        other => panic!("unexpected content: {other:?}"),
+    }
+}
+
+fn result(n: usize) -> SearchResult {
+    SearchResult {
+        title: format!("Result {n}"),
+        url: format!("https://example.com/{n}"),
+        snippet: format!("Snippet {n}"),
+    }
+}
+
+#[tokio::test]
+async fn test_searxng_provider() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .and(query_param("q", "rust async"))
+        .and(query_param("format", "json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                {"title": "Result 1", "url": "https://example.com/1", "content": "Snippet 1"},
+                {"title": "Result 2", "url": "https://example.com/2", "content": "Snippet 2"},
+                {"title": "Result 3", "url": "https://example.com/3"}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = SearxngProvider::new(format!("{}/", server.uri()));
+    let results = provider.search("rust async", 2).await.unwrap();
+    assert_eq!(results, vec![result(1), result(2)]);
+}
+
+#[tokio::test]
+async fn test_brave_provider() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/res/v1/web/search"))
+        .and(header("X-Subscription-Token", "brave-key"))
+        .and(query_param("count", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "web": {"results": [
+                {"title": "Result 1", "url": "https://example.com/1", "description": "Snippet 1"}
+            ]}
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = BraveProvider::new("brave-key".to_string(), Some(server.uri()));
+    assert_eq!(provider.search("q", 1).await.unwrap(), vec![result(1)]);
+}
+
+#[tokio::test]
+async fn test_tavily_provider() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/search"))
+        .and(header("Authorization", "Bearer tvly-key"))
+        .and(body_partial_json(json!({"query": "q", "max_results": 3})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                {"title": "Result 1", "url": "https://example.com/1", "content": "Snippet 1"}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let provider = TavilyProvider::new("tvly-key".to_string(), Some(server.uri()));
+    assert_eq!(provider.search("q", 3).await.unwrap(), vec![result(1)]);
+}
+
+#[tokio::test]
+async fn test_provider_errors_are_reported() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&server)
+        .await;
+
+    let provider = SearxngProvider::new(server.uri());
+    assert!(provider.search("q", 5).await.is_err());
+}
+
+#[test]
+fn test_create_search_provider_validates_config() {
+    let mut config = WebSearchToolConfig::default();
+    assert!(create_search_provider(&config).unwrap().is_none());
+
+    config.provider = WebSearchProvider::Searxng;
+    assert!(create_search_provider(&config).is_err());
+    config.base_url = Some("http://localhost:8888".to_string());
+    assert!(create_search_provider(&config).unwrap().is_some());
+
+    config.provider = WebSearchProvider::Brave;
+    assert!(create_search_provider(&config).is_err());
+    config.api_key = Some("key".to_string());
+    assert!(create_search_provider(&config).unwrap().is_some());
+}
+
+#[test]
+fn test_format_results() {
+    assert_eq!(
+        format_results("q", &[result(1), result(2)]),
+        "1. Result 1\n   https://example.com/1\n   Snippet 1\n\n\
+         2. Result 2\n   https://example.com/2\n   Snippet 2"
+    );
+    assert_eq!(format_results("q", &[]), "No results found for 'q'");
+}
+
+#[tokio::test]
+async fn test_web_search_tool() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/search"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "results": [
+                {"title": "Result 1", "url": "https://example.com/1", "content": "Snippet 1"},
+                {"title": "Result 2", "url": "https://example.com/2", "content": "Snippet 2"}
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let tool = WebSearchTool::new(Arc::new(SearxngProvider::new(server.uri())), 5);
+    let ctx = ToolContext::new("s1", None);
+    let response = tool
+        .call(args(json!({"query": "weather", "max_results": 1})), &ctx)
+        .await
+        .unwrap();
+    assert_eq!(
+        text(&response),
+        "1. Result 1\n   https://example.com/1\n   Snippet 1"
+    );
+
+    assert!(tool.call(args(json!({"query": "  "})), &ctx).await.is_err());
+}